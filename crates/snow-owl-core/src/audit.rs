@@ -0,0 +1,255 @@
+//! Shared audit trail
+//!
+//! Every service records its security-relevant events (authentication
+//! failures, denied operations, destructive API calls) into one durable
+//! sink so an auditor has a single place to look. Services never write to
+//! the sink directly on the request path: records go through an
+//! [`AuditQueue`], a bounded channel drained by a background writer, so a
+//! slow or unavailable database cannot stall a transfer or an HTTP
+//! request.
+//!
+//! NIST 800-53 Controls:
+//! - AU-2: Audit Events (security events from every service)
+//! - AU-3: Content of Audit Records (who, what, where, outcome)
+//! - AU-9: Protection of Audit Information (durable, central store)
+//! - AU-12: Audit Generation
+
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Default number of records buffered between producers and the writer
+pub const DEFAULT_AUDIT_QUEUE_CAPACITY: usize = 1024;
+
+/// One row of the shared audit trail
+///
+/// `resource_id` holds the UUID of API resources; `resource` holds a free
+/// form identifier (an SFTP path, a username) for resources that have none.
+///
+/// NIST 800-53: AU-3 (Content of Audit Records)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub action: String,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<Uuid>,
+    pub resource: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub username: Option<String>,
+    pub ip_address: Option<IpAddr>,
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditRecord {
+    /// Record of `action` with the given outcome, stamped now
+    pub fn new(action: impl Into<String>, success: bool) -> Self {
+        Self {
+            action: action.into(),
+            resource_type: None,
+            resource_id: None,
+            resource: None,
+            user_id: None,
+            username: None,
+            ip_address: None,
+            success,
+            error_message: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Failed `action` with the reason it failed
+    pub fn failure(action: impl Into<String>, error: impl Into<String>) -> Self {
+        Self::new(action, false).with_error(error)
+    }
+
+    pub fn with_resource_id(mut self, resource_type: impl Into<String>, id: Uuid) -> Self {
+        self.resource_type = Some(resource_type.into());
+        self.resource_id = Some(id);
+        self
+    }
+
+    pub fn with_resource(
+        mut self,
+        resource_type: impl Into<String>,
+        resource: impl Into<String>,
+    ) -> Self {
+        self.resource_type = Some(resource_type.into());
+        self.resource = Some(resource.into());
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn with_ip(mut self, ip: IpAddr) -> Self {
+        self.ip_address = Some(ip);
+        self
+    }
+
+    pub fn with_error(mut self, error: impl Into<String>) -> Self {
+        self.error_message = Some(error.into());
+        self
+    }
+}
+
+/// Stored audit record with its row ID, as read back for review
+///
+/// NIST 800-53: AU-6 (Audit Review, Analysis, and Reporting)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+}
+
+/// Durable destination for audit records
+///
+/// Implemented by the database for production and by in-memory stores in
+/// tests.
+pub trait AuditSink: Send + Sync + 'static {
+    /// Persist one record
+    fn record(&self, record: &AuditRecord) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<S: AuditSink> AuditSink for Arc<S> {
+    fn record(&self, record: &AuditRecord) -> impl Future<Output = Result<()>> + Send {
+        S::record(self, record)
+    }
+}
+
+/// Non-blocking handle for submitting audit records
+///
+/// Cloning is cheap; all clones feed the same writer. When the queue is
+/// full the record is dropped and counted rather than blocking the caller,
+/// and the writer logs every record it fails to persist.
+///
+/// NIST 800-53: AU-5 (Response to Audit Processing Failures)
+#[derive(Debug, Clone)]
+pub struct AuditQueue {
+    tx: mpsc::Sender<AuditRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditQueue {
+    /// Spawn the background writer for `sink` with room for `capacity` records
+    ///
+    /// The writer stops once every handle is dropped and the queue is empty;
+    /// await the returned task to flush on shutdown.
+    pub fn spawn<S: AuditSink>(sink: S, capacity: usize) -> (Self, JoinHandle<()>) {
+        let (tx, mut rx) = mpsc::channel::<AuditRecord>(capacity.max(1));
+        let writer = tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                if let Err(e) = sink.record(&record).await {
+                    warn!(
+                        action = %record.action,
+                        error = %e,
+                        "Failed to persist audit record"
+                    );
+                }
+            }
+        });
+
+        (
+            Self {
+                tx,
+                dropped: Arc::new(AtomicU64::new(0)),
+            },
+            writer,
+        )
+    }
+
+    /// Queue `record` without waiting; returns false if it was dropped
+    pub fn record(&self, record: AuditRecord) -> bool {
+        match self.tx.try_send(record) {
+            Ok(()) => true,
+            Err(e) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                warn!(
+                    action = %e.into_inner().action,
+                    dropped,
+                    "Audit queue unavailable; record dropped"
+                );
+                false
+            }
+        }
+    }
+
+    /// Records dropped because the queue was full or the writer had stopped
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct MemorySink {
+        records: Arc<Mutex<Vec<AuditRecord>>>,
+    }
+
+    impl AuditSink for MemorySink {
+        async fn record(&self, record: &AuditRecord) -> Result<()> {
+            self.records.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    /// Sink that never finishes a write, so the queue fills up
+    struct StalledSink;
+
+    impl AuditSink for StalledSink {
+        async fn record(&self, _record: &AuditRecord) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_delivers_in_order_and_flushes_on_drop() {
+        let sink = MemorySink::default();
+        let (queue, writer) = AuditQueue::spawn(sink.clone(), 8);
+
+        assert!(queue.record(AuditRecord::new("image.delete", true)));
+        assert!(queue.record(AuditRecord::failure("auth.login", "invalid api key")));
+        drop(queue);
+        writer.await.unwrap();
+
+        let records = sink.records.lock().unwrap();
+        let actions: Vec<_> = records.iter().map(|r| r.action.as_str()).collect();
+        assert_eq!(actions, ["image.delete", "auth.login"]);
+        assert_eq!(records[1].error_message.as_deref(), Some("invalid api key"));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_instead_of_blocking() {
+        let (queue, writer) = AuditQueue::spawn(StalledSink, 1);
+
+        // The writer takes the first record and stalls; one more fits the
+        // buffer and everything after that is dropped.
+        assert!(queue.record(AuditRecord::new("a", true)));
+        tokio::task::yield_now().await;
+        assert!(queue.record(AuditRecord::new("b", true)));
+        assert!(!queue.record(AuditRecord::new("c", true)));
+        assert_eq!(queue.dropped(), 1);
+
+        writer.abort();
+    }
+}
@@ -0,0 +1,453 @@
+//! JSON Schema export and validation for configuration files
+//!
+//! Every service's configuration structs derive [`JsonSchema`], so the
+//! schema is generated from the same definitions serde parses and cannot
+//! drift from them. Config-management pipelines check rendered TOML against
+//! the exported schema (or run `config validate`) so a typo fails in CI
+//! instead of at the next service restart.
+//!
+//! The schema is stricter than the parser in one respect: unknown keys are
+//! rejected, because at runtime serde silently ignores a misspelled setting.
+//! Settings marked `deprecated` are still accepted and reported as warnings.
+//!
+//! NIST 800-53 Controls:
+//! - CM-3: Configuration Change Control (checked before deployment)
+//! - CM-6: Configuration Settings (every setting typed and constrained)
+//! - SI-10: Information Input Validation
+
+use crate::{Result, SnowOwlError};
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use schemars::{JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::Path;
+
+/// Generate the JSON Schema document for configuration type `T`
+///
+/// Objects that do not say otherwise get `"additionalProperties": false`.
+/// TOML has no null, so an optional setting is described by its value's
+/// schema alone and is simply left out when unset.
+pub fn config_schema<T: JsonSchema>() -> Value {
+    let generator = SchemaSettings::draft2020_12()
+        .with_transform(RecursiveTransform(drop_null))
+        .with_transform(RecursiveTransform(deny_unknown_keys))
+        .into_generator();
+    generator.into_root_schema_for::<T>().to_value()
+}
+
+/// Turn `Option<T>` schemas into plain `T` schemas
+///
+/// Besides matching what a TOML file can contain, this keeps violations
+/// inside optional tables reported at the offending key instead of as an
+/// opaque `anyOf` failure on the table.
+fn drop_null(schema: &mut Schema) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+
+    if let Some(Value::Array(types)) = object.get_mut("type") {
+        types.retain(|t| t != "null");
+        if types.len() == 1 {
+            let single = types.remove(0);
+            object.insert("type".to_string(), single);
+        }
+    }
+
+    let null = serde_json::json!({ "type": "null" });
+    if let Some(Value::Array(branches)) = object.get("anyOf")
+        && branches.len() == 2
+        && branches.contains(&null)
+    {
+        let value_schema = branches.iter().find(|branch| **branch != null).cloned();
+        object.remove("anyOf");
+        if let Some(Value::Object(value_schema)) = value_schema {
+            for (keyword, value) in value_schema {
+                object.entry(keyword).or_insert(value);
+            }
+        }
+    }
+
+    if object.get("default") == Some(&Value::Null) {
+        object.remove("default");
+    }
+}
+
+fn deny_unknown_keys(schema: &mut Schema) {
+    if let Some(object) = schema.as_object_mut()
+        && object.contains_key("properties")
+        && !object.contains_key("additionalProperties")
+    {
+        object.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+}
+
+/// Outcome of checking one configuration file against a schema
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaReport {
+    /// Schema violations as `key.path: message`
+    pub errors: Vec<String>,
+    /// Deprecated settings that are still accepted
+    pub warnings: Vec<String>,
+}
+
+impl SchemaReport {
+    /// Whether the file satisfied the schema; warnings do not count
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Convert TOML text to the JSON value the schema describes
+///
+/// TOML datetimes become RFC 3339 strings, which is how the config structs
+/// deserialize them.
+pub fn toml_to_json(contents: &str) -> Result<Value> {
+    let table: toml::Table =
+        toml::from_str(contents).map_err(|e| SnowOwlError::Parse(e.to_string()))?;
+    Ok(toml_value_to_json(toml::Value::Table(table)))
+}
+
+fn toml_value_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        // NaN and infinities have no JSON form; null fails the type check
+        toml::Value::Float(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => {
+            Value::Array(items.into_iter().map(toml_value_to_json).collect())
+        }
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Check TOML configuration text against `schema`
+///
+/// Fails only if the text is not TOML or the schema itself is invalid;
+/// schema violations are returned in the report.
+pub fn validate_toml(schema: &Value, contents: &str) -> Result<SchemaReport> {
+    validate_value(schema, &toml_to_json(contents)?)
+}
+
+/// Check a configuration already converted to JSON against `schema`
+///
+/// Object members that are `null` count as unset, as serde writes them for
+/// `None`.
+pub fn validate_value(schema: &Value, config: &Value) -> Result<SchemaReport> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| SnowOwlError::InvalidConfig(format!("Invalid schema: {}", e)))?;
+
+    let mut config = config.clone();
+    remove_nulls(&mut config);
+    let config = &config;
+
+    let errors = validator
+        .iter_errors(config)
+        .map(|error| format!("{}: {}", key_path(error.instance_path.as_str()), error))
+        .collect();
+
+    let mut warnings = Vec::new();
+    collect_deprecated(schema, schema, config, "", &mut warnings);
+
+    Ok(SchemaReport { errors, warnings })
+}
+
+/// Check a configuration file the way `config validate` does
+///
+/// The file is checked against the JSON Schema at `schema` when given, and
+/// otherwise against the schema generated for `T`. A file that passes is
+/// also parsed as `T`, so anything the schema cannot express still fails.
+pub fn check_config_file<T>(path: &Path, schema: Option<&Path>) -> Result<SchemaReport>
+where
+    T: JsonSchema + DeserializeOwned,
+{
+    let contents = std::fs::read_to_string(path)?;
+    let schema = match schema {
+        Some(schema_path) => {
+            let text = std::fs::read_to_string(schema_path)?;
+            serde_json::from_str(&text).map_err(|e| {
+                SnowOwlError::InvalidConfig(format!(
+                    "Schema {} is not JSON: {}",
+                    schema_path.display(),
+                    e
+                ))
+            })?
+        }
+        None => config_schema::<T>(),
+    };
+
+    let mut report = validate_toml(&schema, &contents)?;
+    if report.is_valid()
+        && let Err(e) = toml::from_str::<T>(&contents)
+    {
+        report.errors.push(format!("(root): {}", e.message()));
+    }
+    Ok(report)
+}
+
+/// Dotted key path for a JSON pointer, `(root)` for the document itself
+fn key_path(pointer: &str) -> String {
+    if pointer.is_empty() {
+        return "(root)".to_string();
+    }
+    pointer
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, member| !member.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+/// Follow a local `$ref` (`#/$defs/Name`) to its definition
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| root.pointer(pointer))
+        .unwrap_or(schema)
+}
+
+fn is_deprecated(schema: &Value) -> bool {
+    schema.get("deprecated").and_then(Value::as_bool) == Some(true)
+}
+
+/// Walk `value` alongside `schema`, noting every deprecated key it sets
+fn collect_deprecated(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    warnings: &mut Vec<String>,
+) {
+    let resolved = resolve(root, schema);
+
+    for keyword in ["anyOf", "oneOf", "allOf"] {
+        if let Some(branches) = resolved.get(keyword).and_then(Value::as_array) {
+            for branch in branches {
+                collect_deprecated(root, branch, value, path, warnings);
+            }
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let Some(child_schema) = resolved
+                    .get("properties")
+                    .and_then(|properties| properties.get(key))
+                    .or_else(|| {
+                        resolved
+                            .get("additionalProperties")
+                            .filter(|s| s.is_object())
+                    })
+                else {
+                    continue;
+                };
+
+                if is_deprecated(child_schema) || is_deprecated(resolve(root, child_schema)) {
+                    let warning = match child_schema
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .and_then(|description| description.lines().next())
+                    {
+                        Some(reason) => format!("{} is deprecated: {}", child_path, reason),
+                        None => format!("{} is deprecated", child_path),
+                    };
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+                collect_deprecated(root, child_schema, child, &child_path, warnings);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = resolved.get("items").filter(|s| s.is_object()) {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{}[{}]", path, index);
+                    collect_deprecated(root, item_schema, item, &item_path, warnings);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Properties in `schema` that have no description
+///
+/// Descriptions come from the fields' doc comments. Each service has a test
+/// asserting this is empty, so a new setting cannot ship undocumented.
+pub fn undocumented_properties(schema: &Value) -> Vec<String> {
+    let mut missing = Vec::new();
+    let title = schema
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or("(root)");
+    collect_undocumented(title, schema, &mut missing);
+
+    if let Some(defs) = schema.get("$defs").and_then(Value::as_object) {
+        for (name, def) in defs {
+            collect_undocumented(name, def, &mut missing);
+        }
+    }
+    missing
+}
+
+fn collect_undocumented(owner: &str, schema: &Value, missing: &mut Vec<String>) {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    for (name, property) in properties {
+        let documented = property
+            .get("description")
+            .and_then(Value::as_str)
+            .is_some_and(|description| !description.trim().is_empty());
+        if !documented {
+            missing.push(format!("{}.{}", owner, name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ServerConfig;
+
+    #[test]
+    fn test_server_config_schema_documents_every_field() {
+        let schema = config_schema::<ServerConfig>();
+        assert_eq!(undocumented_properties(&schema), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_default_server_config_satisfies_schema() {
+        let schema = config_schema::<ServerConfig>();
+        let toml = toml::to_string_pretty(&ServerConfig::default()).unwrap();
+        let report = validate_toml(&schema, &toml).unwrap();
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_schema_rejects_typos_and_out_of_range_values() {
+        let schema = config_schema::<ServerConfig>();
+        let mut config = serde_json::to_value(ServerConfig::default()).unwrap();
+        config["http_prot"] = Value::from(8080);
+        config["multicast"]["multicast_port"] = Value::from(70_000);
+
+        let report = validate_value(&schema, &config).unwrap();
+        assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+        assert!(report.errors.iter().any(|e| e.contains("http_prot")));
+        assert!(
+            report
+                .errors
+                .iter()
+                .any(|e| e.starts_with("multicast.multicast_port:"))
+        );
+    }
+
+    #[test]
+    fn test_deprecated_keys_warn() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "limits": { "$ref": "#/$defs/Limits" },
+                "old_limit": { "type": "integer", "deprecated": true,
+                               "description": "Use limits.max instead" }
+            },
+            "$defs": {
+                "Limits": {
+                    "type": "object",
+                    "properties": {
+                        "max": { "type": "integer" },
+                        "legacy": { "type": "boolean", "deprecated": true }
+                    }
+                }
+            }
+        });
+        let toml = "old_limit = 5\n[limits]\nmax = 3\nlegacy = true\n";
+
+        let report = validate_toml(&schema, toml).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(
+            report.warnings,
+            [
+                "limits.legacy is deprecated",
+                "old_limit is deprecated: Use limits.max instead"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_config_file_uses_given_schema() {
+        let dir = std::env::temp_dir().join(format!("snow-owl-schema-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        std::fs::write(
+            &config,
+            toml::to_string_pretty(&ServerConfig::default()).unwrap(),
+        )
+        .unwrap();
+        assert!(
+            check_config_file::<ServerConfig>(&config, None)
+                .unwrap()
+                .is_valid()
+        );
+
+        // A schema from an older release that requires a setting we dropped
+        let schema = dir.join("schema.json");
+        std::fs::write(&schema, r#"{"type": "object", "required": ["legacy"]}"#).unwrap();
+        let report = check_config_file::<ServerConfig>(&config, Some(&schema)).unwrap();
+        assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_toml_datetimes_become_strings() {
+        let json = toml_to_json("expires = 2030-01-01T00:00:00Z\nratio = 0.5").unwrap();
+        assert_eq!(json["expires"], "2030-01-01T00:00:00Z");
+        assert_eq!(json["ratio"], 0.5);
+        assert!(toml_to_json("not toml =").is_err());
+    }
+
+    #[test]
+    fn test_optional_settings_have_no_null_branch() {
+        let schema = config_schema::<ServerConfig>();
+        let properties = &schema["properties"];
+        assert_eq!(properties["https_port"]["type"], "integer");
+        assert!(properties["tls"].get("anyOf").is_none());
+        assert_eq!(properties["tls"]["$ref"], "#/$defs/TlsConfig");
+
+        // A bad key inside an optional table is reported at that key
+        let report = validate_toml(&schema, "[tls]\nenabled = \"yes\"\n").unwrap();
+        assert!(
+            report.errors.iter().any(|e| e.starts_with("tls.enabled:")),
+            "{:?}",
+            report.errors
+        );
+    }
+}
@@ -23,12 +23,24 @@ pub enum SnowOwlError {
     #[error("Image not found: {0}")]
     ImageNotFound(String),
 
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+
+    #[error("Image in use: {0}")]
+    ImageInUse(String),
+
+    #[error("Image name already in use: {0}")]
+    ImageNameTaken(String),
+
     #[error("Machine not found: {0}")]
     MachineNotFound(String),
 
     #[error("Deployment not found: {0}")]
     DeploymentNotFound(String),
 
+    #[error("Driver pack not found: {0}")]
+    DriverPackNotFound(String),
+
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
 
@@ -0,0 +1,617 @@
+//! WIM and VHD/VHDX header inspection
+//!
+//! Registering an image reads its on-disk headers to record what is really
+//! inside it (edition, build, architecture for WIMs; virtual size for virtual
+//! disks) instead of trusting a hand-typed description. Only the header and
+//! metadata regions are read, so inspecting a multi-gigabyte image costs a
+//! few seeks and at most [`MAX_WIM_XML_BYTES`] of reading.
+//!
+//! Parsing never fails on a malformed file: whatever could not be read is
+//! recorded in [`ImageMetadata::parse_error`] and the caller decides, via
+//! [`ImageMetadata::check_declared_type`], whether that blocks registration.
+//!
+//! NIST Controls:
+//! - CM-8: System Component Inventory (accurate image inventory)
+//! - SI-10: Information Input Validation (declared type must match the file)
+
+use crate::{ImageType, Result, SnowOwlError};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest WIM XML blob that will be read (real images are well under 1 MiB)
+pub const MAX_WIM_XML_BYTES: u64 = 16 * 1024 * 1024;
+
+const WIM_MAGIC: &[u8; 8] = b"MSWIM\0\0\0";
+const WIM_HEADER_LEN: usize = 208;
+/// RESHDR flag set when a resource is compressed
+const WIM_RESHDR_COMPRESSED: u8 = 0x04;
+
+const VHDX_MAGIC: &[u8; 8] = b"vhdxfile";
+const VHDX_REGION_TABLE_OFFSET: u64 = 192 * 1024;
+const VHDX_METADATA_TABLE_LEN: usize = 64 * 1024;
+/// Entry limit for VHDX region and metadata tables (MS-VHDX 2.2.3, 2.6.1)
+const VHDX_MAX_TABLE_ENTRIES: usize = 2047;
+const VHDX_METADATA_REGION: [u8; 16] = guid(
+    0x8B7C_A206,
+    0x4790,
+    0x4B9A,
+    [0xB8, 0xFE, 0x57, 0x5F, 0x05, 0x0F, 0x88, 0x6E],
+);
+const VHDX_FILE_PARAMETERS: [u8; 16] = guid(
+    0xCAA1_6737,
+    0xFA36,
+    0x4D43,
+    [0xB3, 0xB6, 0x33, 0xF0, 0xAA, 0x44, 0xE7, 0x6B],
+);
+const VHDX_VIRTUAL_DISK_SIZE: [u8; 16] = guid(
+    0x2FA5_4224,
+    0xCD1B,
+    0x4876,
+    [0xB2, 0x11, 0x5D, 0xBE, 0xD8, 0x3B, 0xF4, 0xB8],
+);
+
+const VHD_COOKIE: &[u8; 8] = b"conectix";
+const VHD_FOOTER_LEN: u64 = 512;
+
+/// Metadata read from an image file's headers
+///
+/// Stored alongside the image record and shown by the API and CLI info views.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageMetadata {
+    /// Format identified from the file's signature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_type: Option<ImageType>,
+    /// Number of images in a WIM, from its header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_count: Option<u32>,
+    /// Per-index details from the WIM XML metadata
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<WimImageInfo>,
+    /// Size of the disk presented to the guest (VHD/VHDX)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_size_bytes: Option<u64>,
+    /// Payload block size (VHDX)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_size_bytes: Option<u32>,
+    /// Why inspection stopped early, when the file is malformed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
+}
+
+/// One image inside a WIM file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WimImageInfo {
+    /// 1-based index used by DISM `/Index:`
+    pub index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Edition ID, e.g. `Professional`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+    /// Processor architecture, e.g. `x64`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+    /// Build number, e.g. 22621 for Windows 11 22H2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<u32>,
+    /// Full version, e.g. `10.0.22621.2428`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl ImageMetadata {
+    /// Check the declared image type against the detected format
+    ///
+    /// A file detected as a different format is always rejected. In strict
+    /// mode, a file whose format could not be detected or whose headers are
+    /// malformed is rejected as well; otherwise it is accepted and the
+    /// problem stays recorded in `parse_error`.
+    pub fn check_declared_type(&self, declared: ImageType, strict: bool) -> Result<()> {
+        match self.detected_type {
+            Some(detected) if detected != declared => Err(SnowOwlError::InvalidImage(format!(
+                "declared as {} but the file is a {} image",
+                declared, detected
+            ))),
+            None if strict => Err(SnowOwlError::InvalidImage(format!(
+                "declared as {} but the file is not a recognized image",
+                declared
+            ))),
+            _ => match &self.parse_error {
+                Some(error) if strict => Err(SnowOwlError::InvalidImage(format!(
+                    "malformed {} image: {}",
+                    declared, error
+                ))),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// Inspect the image file at `path`
+///
+/// Blocking; call from `spawn_blocking` in async code. Only failing to open
+/// the file is an error.
+pub fn read_image_metadata_file(path: &Path) -> Result<ImageMetadata> {
+    let mut file = std::fs::File::open(path)?;
+    Ok(read_image_metadata(&mut file))
+}
+
+/// Inspect an image by seeking to its header regions
+pub fn read_image_metadata<R: Read + Seek>(reader: &mut R) -> ImageMetadata {
+    let mut metadata = ImageMetadata::default();
+
+    let result = detect_image_type(reader).and_then(|detected| {
+        metadata.detected_type = detected;
+        match detected {
+            Some(ImageType::Wim) => read_wim(reader, &mut metadata),
+            Some(ImageType::Vhdx) => read_vhdx(reader, &mut metadata),
+            Some(ImageType::Vhd) => read_vhd(reader, &mut metadata),
+            None => Err(invalid("unrecognized file signature")),
+        }
+    });
+
+    if let Err(e) = result {
+        metadata.parse_error = Some(e.to_string());
+    }
+    metadata
+}
+
+/// Identify the image format from its signature
+fn detect_image_type<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<ImageType>> {
+    let mut magic = [0u8; 8];
+    reader.seek(SeekFrom::Start(0))?;
+    if read_up_to(reader, &mut magic)? == magic.len() {
+        if &magic == WIM_MAGIC {
+            return Ok(Some(ImageType::Wim));
+        }
+        if &magic == VHDX_MAGIC {
+            return Ok(Some(ImageType::Vhdx));
+        }
+        // Dynamic VHDs start with a copy of the footer
+        if &magic == VHD_COOKIE {
+            return Ok(Some(ImageType::Vhd));
+        }
+    }
+
+    let len = reader.seek(SeekFrom::End(0))?;
+    if len >= VHD_FOOTER_LEN {
+        reader.seek(SeekFrom::Start(len - VHD_FOOTER_LEN))?;
+        if read_up_to(reader, &mut magic)? == magic.len() && &magic == VHD_COOKIE {
+            return Ok(Some(ImageType::Vhd));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Read the WIM header and the XML metadata resource
+fn read_wim<R: Read + Seek>(reader: &mut R, metadata: &mut ImageMetadata) -> std::io::Result<()> {
+    let mut header = [0u8; WIM_HEADER_LEN];
+    reader.seek(SeekFrom::Start(0))?;
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| invalid("truncated WIM header"))?;
+
+    metadata.image_count = Some(le_u32(&header, 44));
+
+    // rhXmlData: 7-byte size + flags, offset, original size
+    let xml_size = le_u64(&header, 72) & 0x00FF_FFFF_FFFF_FFFF;
+    let xml_flags = header[79];
+    let xml_offset = le_u64(&header, 80);
+
+    if xml_size == 0 {
+        return Err(invalid("WIM has no XML metadata"));
+    }
+    if xml_flags & WIM_RESHDR_COMPRESSED != 0 {
+        return Err(invalid("compressed WIM XML metadata is not supported"));
+    }
+    if xml_size > MAX_WIM_XML_BYTES {
+        return Err(invalid(format!(
+            "WIM XML metadata is {} bytes (limit {})",
+            xml_size, MAX_WIM_XML_BYTES
+        )));
+    }
+
+    let mut xml = vec![0u8; xml_size as usize];
+    reader.seek(SeekFrom::Start(xml_offset))?;
+    reader
+        .read_exact(&mut xml)
+        .map_err(|_| invalid("WIM XML metadata extends past end of file"))?;
+
+    metadata.images = parse_wim_xml(&decode_utf16le(&xml));
+    Ok(())
+}
+
+/// Read virtual size and block size from the VHDX metadata region
+fn read_vhdx<R: Read + Seek>(reader: &mut R, metadata: &mut ImageMetadata) -> std::io::Result<()> {
+    let mut table_header = [0u8; 16];
+    reader.seek(SeekFrom::Start(VHDX_REGION_TABLE_OFFSET))?;
+    reader
+        .read_exact(&mut table_header)
+        .map_err(|_| invalid("truncated VHDX region table"))?;
+    if &table_header[..4] != b"regi" {
+        return Err(invalid("missing VHDX region table signature"));
+    }
+
+    let entry_count = le_u32(&table_header, 8) as usize;
+    if entry_count > VHDX_MAX_TABLE_ENTRIES {
+        return Err(invalid("too many VHDX region table entries"));
+    }
+
+    let mut region_offset = None;
+    let mut entry = [0u8; 32];
+    for _ in 0..entry_count {
+        reader
+            .read_exact(&mut entry)
+            .map_err(|_| invalid("truncated VHDX region table"))?;
+        if entry[..16] == VHDX_METADATA_REGION {
+            region_offset = Some(le_u64(&entry, 16));
+            break;
+        }
+    }
+    let region_offset = region_offset.ok_or_else(|| invalid("VHDX has no metadata region"))?;
+
+    let mut table = vec![0u8; VHDX_METADATA_TABLE_LEN];
+    reader.seek(SeekFrom::Start(region_offset))?;
+    reader
+        .read_exact(&mut table)
+        .map_err(|_| invalid("truncated VHDX metadata table"))?;
+    if &table[..8] != b"metadata" {
+        return Err(invalid("missing VHDX metadata table signature"));
+    }
+
+    let entry_count = usize::from(le_u16(&table, 10)).min(VHDX_MAX_TABLE_ENTRIES);
+    for i in 0..entry_count {
+        let entry = &table[32 + i * 32..64 + i * 32];
+        let item_offset = region_offset + u64::from(le_u32(entry, 16));
+        let item_length = le_u32(entry, 20);
+
+        if entry[..16] == VHDX_VIRTUAL_DISK_SIZE && item_length >= 8 {
+            let mut value = [0u8; 8];
+            reader.seek(SeekFrom::Start(item_offset))?;
+            reader
+                .read_exact(&mut value)
+                .map_err(|_| invalid("truncated VHDX virtual disk size"))?;
+            metadata.virtual_size_bytes = Some(u64::from_le_bytes(value));
+        } else if entry[..16] == VHDX_FILE_PARAMETERS && item_length >= 4 {
+            let mut value = [0u8; 4];
+            reader.seek(SeekFrom::Start(item_offset))?;
+            reader
+                .read_exact(&mut value)
+                .map_err(|_| invalid("truncated VHDX file parameters"))?;
+            metadata.block_size_bytes = Some(u32::from_le_bytes(value));
+        }
+    }
+
+    if metadata.virtual_size_bytes.is_none() {
+        return Err(invalid("VHDX metadata has no virtual disk size"));
+    }
+    Ok(())
+}
+
+/// Read the virtual size from the VHD footer
+fn read_vhd<R: Read + Seek>(reader: &mut R, metadata: &mut ImageMetadata) -> std::io::Result<()> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut footer = [0u8; VHD_FOOTER_LEN as usize];
+    let footer_offset = if len >= VHD_FOOTER_LEN {
+        reader.seek(SeekFrom::Start(len - VHD_FOOTER_LEN))?;
+        reader.read_exact(&mut footer)?;
+        if &footer[..8] == VHD_COOKIE {
+            len - VHD_FOOTER_LEN
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+
+    if footer_offset == 0 {
+        reader.seek(SeekFrom::Start(0))?;
+        reader
+            .read_exact(&mut footer)
+            .map_err(|_| invalid("truncated VHD footer"))?;
+    }
+
+    // Current Size, big-endian (VHD spec: Hard Disk Footer Format)
+    let mut size = [0u8; 8];
+    size.copy_from_slice(&footer[48..56]);
+    metadata.virtual_size_bytes = Some(u64::from_be_bytes(size));
+    Ok(())
+}
+
+/// Extract per-image details from the `<WIM>` XML document
+fn parse_wim_xml(xml: &str) -> Vec<WimImageInfo> {
+    let mut images = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<IMAGE") {
+        let after = &rest[start + "<IMAGE".len()..];
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let attributes = &after[..tag_end];
+        let body_and_rest = &after[tag_end + 1..];
+        let body_end = body_and_rest
+            .find("</IMAGE>")
+            .unwrap_or(body_and_rest.len());
+        let body = &body_and_rest[..body_end];
+
+        let index = attribute(attributes, "INDEX")
+            .and_then(|index| index.parse().ok())
+            .unwrap_or(images.len() as u32 + 1);
+        let windows = element(body, "WINDOWS").unwrap_or_default();
+        let version = element(windows, "VERSION").unwrap_or_default();
+        let number = |tag| element(version, tag).and_then(|v| v.trim().parse::<u32>().ok());
+        let build = number("BUILD");
+
+        images.push(WimImageInfo {
+            index,
+            name: text(body, "NAME"),
+            description: text(body, "DESCRIPTION"),
+            edition: text(windows, "EDITIONID"),
+            architecture: element(windows, "ARCH").map(|arch| architecture_name(arch.trim())),
+            build,
+            version: match (number("MAJOR"), number("MINOR"), build) {
+                (Some(major), Some(minor), Some(build)) => Some(match number("SPBUILD") {
+                    Some(sp) => format!("{}.{}.{}.{}", major, minor, build, sp),
+                    None => format!("{}.{}.{}", major, minor, build),
+                }),
+                _ => None,
+            },
+        });
+
+        rest = &body_and_rest[body_end..];
+    }
+
+    images
+}
+
+/// Raw contents of the first `<tag>...</tag>` element in `xml`
+fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(&xml[start..end])
+}
+
+/// Unescaped, non-empty text of the first `<tag>` element
+fn text(xml: &str, tag: &str) -> Option<String> {
+    let value = element(xml, tag)?.trim();
+    (!value.is_empty()).then(|| {
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    })
+}
+
+/// Value of `name="..."` in an element's attribute list
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("{}=\"", name);
+    let start = attributes.find(&key)? + key.len();
+    let end = attributes[start..].find('"')? + start;
+    Some(&attributes[start..end])
+}
+
+/// Map a `PROCESSOR_ARCHITECTURE_*` value to its common name
+fn architecture_name(arch: &str) -> String {
+    match arch {
+        "0" => "x86".to_string(),
+        "5" => "arm".to_string(),
+        "6" => "ia64".to_string(),
+        "9" => "x64".to_string(),
+        "12" => "arm64".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Decode UTF-16LE, dropping a leading byte-order mark
+fn decode_utf16le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let units = units.strip_prefix(&[0xFEFF]).unwrap_or(&units);
+    String::from_utf16_lossy(units)
+}
+
+/// Fill as much of `buf` as the reader has, returning the count read
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn invalid(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+fn le_u16(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn le_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn le_u64(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// On-disk (mixed-endian) layout of a GUID
+const fn guid(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> [u8; 16] {
+    let a = data1.to_le_bytes();
+    let b = data2.to_le_bytes();
+    let c = data3.to_le_bytes();
+    [
+        a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1], data4[0], data4[1], data4[2], data4[3],
+        data4[4], data4[5], data4[6], data4[7],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const INSTALL_WIM_XML: &str = "<WIM><TOTALBYTES>4096</TOTALBYTES>\
+        <IMAGE INDEX=\"1\"><WINDOWS><ARCH>9</ARCH><EDITIONID>Professional</EDITIONID>\
+        <VERSION><MAJOR>10</MAJOR><MINOR>0</MINOR><BUILD>22621</BUILD><SPBUILD>2428</SPBUILD></VERSION>\
+        </WINDOWS><NAME>Windows 11 Pro</NAME><DESCRIPTION>Windows 11 Pro &amp; tools</DESCRIPTION></IMAGE>\
+        <IMAGE INDEX=\"2\"><WINDOWS><ARCH>12</ARCH><EDITIONID>Enterprise</EDITIONID>\
+        <VERSION><MAJOR>10</MAJOR><MINOR>0</MINOR><BUILD>22631</BUILD></VERSION>\
+        </WINDOWS><NAME>Windows 11 Enterprise</NAME></IMAGE></WIM>";
+
+    /// Minimal WIM: header followed directly by the UTF-16LE XML resource
+    fn wim_fixture(xml: &str, image_count: u32) -> Vec<u8> {
+        let mut xml_bytes = vec![0xFF, 0xFE];
+        for unit in xml.encode_utf16() {
+            xml_bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let mut wim = vec![0u8; WIM_HEADER_LEN];
+        wim[..8].copy_from_slice(WIM_MAGIC);
+        wim[8..12].copy_from_slice(&(WIM_HEADER_LEN as u32).to_le_bytes());
+        wim[44..48].copy_from_slice(&image_count.to_le_bytes());
+        wim[72..80].copy_from_slice(&(xml_bytes.len() as u64).to_le_bytes());
+        wim[80..88].copy_from_slice(&(WIM_HEADER_LEN as u64).to_le_bytes());
+        wim[88..96].copy_from_slice(&(xml_bytes.len() as u64).to_le_bytes());
+        wim.extend_from_slice(&xml_bytes);
+        wim
+    }
+
+    /// Minimal VHDX: file identifier, region table and metadata region
+    fn vhdx_fixture(virtual_size: u64, block_size: u32) -> Vec<u8> {
+        const METADATA_REGION: usize = 1024 * 1024;
+        let mut vhdx = vec![0u8; METADATA_REGION + VHDX_METADATA_TABLE_LEN + 64];
+        vhdx[..8].copy_from_slice(VHDX_MAGIC);
+
+        let table = VHDX_REGION_TABLE_OFFSET as usize;
+        vhdx[table..table + 4].copy_from_slice(b"regi");
+        vhdx[table + 8..table + 12].copy_from_slice(&1u32.to_le_bytes());
+        vhdx[table + 16..table + 32].copy_from_slice(&VHDX_METADATA_REGION);
+        vhdx[table + 32..table + 40].copy_from_slice(&(METADATA_REGION as u64).to_le_bytes());
+        vhdx[table + 40..table + 44]
+            .copy_from_slice(&((VHDX_METADATA_TABLE_LEN + 64) as u32).to_le_bytes());
+
+        let meta = METADATA_REGION;
+        vhdx[meta..meta + 8].copy_from_slice(b"metadata");
+        vhdx[meta + 10..meta + 12].copy_from_slice(&2u16.to_le_bytes());
+        let items = [(VHDX_FILE_PARAMETERS, 8u32), (VHDX_VIRTUAL_DISK_SIZE, 8u32)];
+        for (i, (id, length)) in items.iter().enumerate() {
+            let entry = meta + 32 + i * 32;
+            let item_offset = (VHDX_METADATA_TABLE_LEN + i * 8) as u32;
+            vhdx[entry..entry + 16].copy_from_slice(id);
+            vhdx[entry + 16..entry + 20].copy_from_slice(&item_offset.to_le_bytes());
+            vhdx[entry + 20..entry + 24].copy_from_slice(&length.to_le_bytes());
+        }
+        let data = meta + VHDX_METADATA_TABLE_LEN;
+        vhdx[data..data + 4].copy_from_slice(&block_size.to_le_bytes());
+        vhdx[data + 8..data + 16].copy_from_slice(&virtual_size.to_le_bytes());
+        vhdx
+    }
+
+    #[test]
+    fn test_wim_xml_fields() {
+        let wim = wim_fixture(INSTALL_WIM_XML, 2);
+
+        let metadata = read_image_metadata(&mut Cursor::new(wim));
+
+        assert_eq!(metadata.detected_type, Some(ImageType::Wim));
+        assert_eq!(metadata.image_count, Some(2));
+        assert_eq!(metadata.parse_error, None);
+        assert_eq!(
+            metadata.images,
+            vec![
+                WimImageInfo {
+                    index: 1,
+                    name: Some("Windows 11 Pro".to_string()),
+                    description: Some("Windows 11 Pro & tools".to_string()),
+                    edition: Some("Professional".to_string()),
+                    architecture: Some("x64".to_string()),
+                    build: Some(22621),
+                    version: Some("10.0.22621.2428".to_string()),
+                },
+                WimImageInfo {
+                    index: 2,
+                    name: Some("Windows 11 Enterprise".to_string()),
+                    description: None,
+                    edition: Some("Enterprise".to_string()),
+                    architecture: Some("arm64".to_string()),
+                    build: Some(22631),
+                    version: Some("10.0.22631".to_string()),
+                },
+            ]
+        );
+        assert!(metadata.check_declared_type(ImageType::Wim, true).is_ok());
+    }
+
+    #[test]
+    fn test_vhdx_virtual_size() {
+        let vhdx = vhdx_fixture(127 * 1024 * 1024 * 1024, 32 * 1024 * 1024);
+
+        let metadata = read_image_metadata(&mut Cursor::new(vhdx));
+
+        assert_eq!(metadata.detected_type, Some(ImageType::Vhdx));
+        assert_eq!(metadata.virtual_size_bytes, Some(127 * 1024 * 1024 * 1024));
+        assert_eq!(metadata.block_size_bytes, Some(32 * 1024 * 1024));
+        assert_eq!(metadata.parse_error, None);
+    }
+
+    #[test]
+    fn test_renamed_text_file_rejected_in_strict_mode() {
+        let text = b"Windows 11 23H2 install notes\n".to_vec();
+
+        let metadata = read_image_metadata(&mut Cursor::new(text));
+
+        assert_eq!(metadata.detected_type, None);
+        assert!(metadata.parse_error.is_some());
+        assert!(metadata.check_declared_type(ImageType::Wim, true).is_err());
+        assert!(metadata.check_declared_type(ImageType::Wim, false).is_ok());
+    }
+
+    #[test]
+    fn test_detected_type_mismatch_always_rejected() {
+        let vhdx = vhdx_fixture(1024 * 1024 * 1024, 1024 * 1024);
+
+        let metadata = read_image_metadata(&mut Cursor::new(vhdx));
+
+        assert!(metadata.check_declared_type(ImageType::Wim, false).is_err());
+        assert!(metadata.check_declared_type(ImageType::Vhdx, true).is_ok());
+    }
+
+    #[test]
+    fn test_truncated_wim_records_parse_error() {
+        let mut wim = wim_fixture(INSTALL_WIM_XML, 2);
+        wim.truncate(WIM_HEADER_LEN + 10);
+
+        let metadata = read_image_metadata(&mut Cursor::new(wim));
+
+        assert_eq!(metadata.detected_type, Some(ImageType::Wim));
+        assert_eq!(metadata.image_count, Some(2));
+        assert!(metadata.images.is_empty());
+        assert!(
+            metadata
+                .parse_error
+                .as_deref()
+                .is_some_and(|e| e.contains("past end of file"))
+        );
+        assert!(metadata.check_declared_type(ImageType::Wim, false).is_ok());
+        assert!(metadata.check_declared_type(ImageType::Wim, true).is_err());
+    }
+}
@@ -1,5 +1,14 @@
+pub mod audit;
+pub mod config_schema;
 pub mod error;
+pub mod image_metadata;
+pub mod pagination;
+pub mod shutdown;
 pub mod types;
 
+pub use audit::*;
 pub use error::*;
+pub use image_metadata::{ImageMetadata, WimImageInfo};
+pub use pagination::*;
+pub use shutdown::*;
 pub use types::*;
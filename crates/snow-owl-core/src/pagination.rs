@@ -0,0 +1,350 @@
+//! Paging, filtering and sorting for list queries
+//!
+//! List endpoints return one page at a time so a large inventory (tens of
+//! thousands of machines) never has to be loaded or serialized in one go.
+//! Sort keys are closed enums mapped to fixed column names, so nothing from
+//! the request is ever spliced into SQL.
+//!
+//! NIST Controls:
+//! - SC-5: Denial of Service Protection (bounded page size)
+//! - SI-10: Information Input Validation (sort keys are not free-form)
+
+use crate::{DeploymentStatus, ImageType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Page size used when the request does not give one
+pub const DEFAULT_PER_PAGE: u32 = 50;
+
+/// Largest page size a request may ask for
+pub const MAX_PER_PAGE: u32 = 500;
+
+/// Which page to fetch, 1-based, with the page size clamped to
+/// `1..=MAX_PER_PAGE`
+///
+/// Requests made with [`PageRequest::at_offset`] start at any row; their
+/// `page` is the one holding that row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub page: u32,
+    pub per_page: u32,
+    skip: u64,
+}
+
+impl PageRequest {
+    pub fn new(page: Option<u32>, per_page: Option<u32>) -> Self {
+        let page = page.unwrap_or(1).max(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        Self {
+            page,
+            per_page,
+            skip: u64::from(page - 1) * u64::from(per_page),
+        }
+    }
+
+    /// `limit` rows starting `offset` rows in, the limit clamped like
+    /// `per_page`
+    pub fn at_offset(offset: u64, limit: Option<u32>) -> Self {
+        let per_page = limit.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        Self {
+            page: u32::try_from((offset / u64::from(per_page)).saturating_add(1))
+                .unwrap_or(u32::MAX),
+            per_page,
+            skip: offset,
+        }
+    }
+
+    /// Page from list query parameters: `?limit=&offset=` when either is
+    /// given, otherwise `?page=&per_page=`
+    pub fn from_query(
+        page: Option<u32>,
+        per_page: Option<u32>,
+        limit: Option<u32>,
+        offset: Option<u64>,
+    ) -> Self {
+        if limit.is_some() || offset.is_some() {
+            Self::at_offset(offset.unwrap_or(0), limit)
+        } else {
+            Self::new(page, per_page)
+        }
+    }
+
+    /// Rows to skip before this page
+    pub fn offset(&self) -> u64 {
+        self.skip
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.per_page
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+/// Deserialize a `?limit=` query parameter, rejecting values outside
+/// `1..=MAX_PER_PAGE` instead of clamping them
+///
+/// For `#[serde(default, deserialize_with = "...")]` on an `Option<u32>`.
+pub fn deserialize_limit<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let limit = Option::<u32>::deserialize(deserializer)?;
+    match limit {
+        Some(limit) if !(1..=MAX_PER_PAGE).contains(&limit) => Err(serde::de::Error::custom(
+            format!("limit must be between 1 and {}", MAX_PER_PAGE),
+        )),
+        _ => Ok(limit),
+    }
+}
+
+/// One page of a list, with the total number of matching rows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+    /// Rows before the first item
+    #[serde(default)]
+    pub offset: u64,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: u64, request: PageRequest) -> Self {
+        Self {
+            items,
+            total,
+            page: request.page,
+            per_page: request.per_page,
+            offset: request.offset(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MachineSort {
+    #[default]
+    LastSeen,
+    CreatedAt,
+    Hostname,
+    MacAddress,
+}
+
+impl MachineSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            MachineSort::LastSeen => "last_seen",
+            MachineSort::CreatedAt => "created_at",
+            MachineSort::Hostname => "hostname",
+            MachineSort::MacAddress => "mac_address",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSort {
+    #[default]
+    CreatedAt,
+    Name,
+    SizeBytes,
+}
+
+impl ImageSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            ImageSort::CreatedAt => "created_at",
+            ImageSort::Name => "name",
+            ImageSort::SizeBytes => "size_bytes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentSort {
+    #[default]
+    StartedAt,
+    CompletedAt,
+    Status,
+}
+
+impl DeploymentSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            DeploymentSort::StartedAt => "started_at",
+            DeploymentSort::CompletedAt => "completed_at",
+            DeploymentSort::Status => "status",
+        }
+    }
+}
+
+/// Machine list filter; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct MachineFilter {
+    /// Case-insensitive hostname substring
+    pub hostname: Option<String>,
+    pub sort: MachineSort,
+    pub order: SortOrder,
+}
+
+/// Image list filter; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct ImageFilter {
+    /// Case-insensitive name substring
+    pub name: Option<String>,
+    pub image_type: Option<ImageType>,
+    pub sort: ImageSort,
+    pub order: SortOrder,
+}
+
+/// Deployment list filter; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct DeploymentFilter {
+    pub status: Option<DeploymentStatus>,
+    pub machine_id: Option<Uuid>,
+    pub image_id: Option<Uuid>,
+    pub sort: DeploymentSort,
+    pub order: SortOrder,
+}
+
+/// Audit log filter; unset fields match everything
+///
+/// Audit queries always return the newest records first.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub user_id: Option<Uuid>,
+    /// Exact username, for records (SFTP, failed logins) that carry no user ID
+    pub username: Option<String>,
+    /// Action prefix, e.g. `image.` for every image event
+    pub action_prefix: Option<String>,
+    pub resource_type: Option<String>,
+    pub success: Option<bool>,
+    /// Records created at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Records created before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Escape `%`, `_` and `\` so a substring matches literally in `LIKE`/`ILIKE`
+pub fn like_pattern(substring: &str) -> String {
+    let mut pattern = String::with_capacity(substring.len() + 2);
+    pattern.push('%');
+    push_like_escaped(&mut pattern, substring);
+    pattern.push('%');
+    pattern
+}
+
+/// `LIKE` pattern matching strings that start with `prefix`, escaped as in
+/// [`like_pattern`]
+pub fn like_prefix_pattern(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    push_like_escaped(&mut pattern, prefix);
+    pattern.push('%');
+    pattern
+}
+
+fn push_like_escaped(pattern: &mut String, text: &str) {
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request_clamping() {
+        let request = PageRequest::new(None, None);
+        assert_eq!((request.page, request.per_page), (1, DEFAULT_PER_PAGE));
+        assert_eq!(request.offset(), 0);
+
+        let request = PageRequest::new(Some(0), Some(0));
+        assert_eq!((request.page, request.per_page), (1, 1));
+
+        let request = PageRequest::new(Some(3), Some(10_000));
+        assert_eq!(request.per_page, MAX_PER_PAGE);
+        assert_eq!(request.offset(), 1000);
+
+        // Offsets past u32 do not overflow
+        let request = PageRequest::new(Some(u32::MAX), Some(MAX_PER_PAGE));
+        assert_eq!(request.offset(), u64::from(u32::MAX - 1) * 500);
+    }
+
+    #[test]
+    fn test_page_request_at_offset() {
+        let request = PageRequest::from_query(None, None, Some(20), Some(45));
+        assert_eq!((request.offset(), request.limit()), (45, 20));
+        assert_eq!(request.page, 3);
+
+        // An offset alone keeps the default page size; limit and offset win
+        // over page and per_page
+        let request = PageRequest::from_query(Some(4), Some(10), None, Some(7));
+        assert_eq!((request.offset(), request.limit()), (7, DEFAULT_PER_PAGE));
+        assert_eq!(
+            PageRequest::from_query(Some(4), Some(10), None, None).offset(),
+            30
+        );
+
+        let request = PageRequest::at_offset(u64::MAX, Some(1));
+        assert_eq!(request.page, u32::MAX);
+        assert_eq!(request.offset(), u64::MAX);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct LimitQuery {
+        #[serde(default, deserialize_with = "deserialize_limit")]
+        limit: Option<u32>,
+    }
+
+    #[test]
+    fn test_limit_bounds_are_validated() {
+        let parse = |json: &str| serde_json::from_str::<LimitQuery>(json).map(|q| q.limit);
+        assert_eq!(parse("{}").unwrap(), None);
+        assert_eq!(parse(r#"{"limit": 1}"#).unwrap(), Some(1));
+        assert_eq!(parse(r#"{"limit": 500}"#).unwrap(), Some(MAX_PER_PAGE));
+        assert!(parse(r#"{"limit": 0}"#).is_err());
+        assert!(
+            parse(r#"{"limit": 501}"#)
+                .unwrap_err()
+                .to_string()
+                .contains("limit must be between 1 and 500")
+        );
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("lab"), "%lab%");
+        assert_eq!(like_pattern("50%_a\\b"), "%50\\%\\_a\\\\b%");
+        assert_eq!(like_prefix_pattern("image."), "image.%");
+        assert_eq!(like_prefix_pattern("sftp_%"), "sftp\\_\\%%");
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tracing::{Level, event, info, warn};
+
+/// Boxed future returned by a shutdown phase
+pub type ShutdownFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type ShutdownAction = Box<dyn FnOnce() -> ShutdownFuture + Send>;
+
+/// A named step of the shutdown sequence
+struct ShutdownPhase {
+    name: String,
+    timeout: Duration,
+    action: ShutdownAction,
+}
+
+/// Coordinates graceful shutdown across services
+///
+/// Services register named phases in the order they must run (for example
+/// stop accepting → drain protocol traffic → flush audit queues → close the
+/// database pool). Each phase gets its own timeout; a phase that overruns is
+/// cut off and the next phase still runs.
+///
+/// NIST 800-53 Controls:
+/// - SC-24: Fail in Known State (ordered, bounded shutdown)
+/// - AU-12: Audit Generation (shutdown report is the final audit event)
+pub struct ShutdownCoordinator {
+    service: String,
+    phases: Vec<ShutdownPhase>,
+    deadline: Option<Duration>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator for the named service
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            phases: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Limit the total time spent across all phases
+    ///
+    /// Each phase is given the smaller of its own timeout and the time left
+    /// before the deadline. Phases that have no time left are skipped.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Register a shutdown phase; phases run in registration order
+    pub fn register<F, Fut>(&mut self, name: impl Into<String>, timeout: Duration, action: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.phases.push(ShutdownPhase {
+            name: name.into(),
+            timeout,
+            action: Box::new(move || Box::pin(action())),
+        });
+    }
+
+    /// Number of registered phases
+    pub fn phase_count(&self) -> usize {
+        self.phases.len()
+    }
+
+    /// Wait for Ctrl+C, then run the shutdown sequence
+    pub async fn run_on_ctrl_c(self) -> ShutdownReport {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for shutdown signal: {}", e);
+        }
+        self.run("signal").await
+    }
+
+    /// Run all phases in order and return the shutdown report
+    ///
+    /// The report is logged as the final audit event before returning.
+    pub async fn run(self, reason: &str) -> ShutdownReport {
+        let started_at = Utc::now();
+        let start = Instant::now();
+
+        info!(
+            service = %self.service,
+            reason,
+            phases = self.phases.len(),
+            "Starting coordinated shutdown"
+        );
+
+        let mut phases = Vec::with_capacity(self.phases.len());
+        for phase in self.phases {
+            let remaining = self
+                .deadline
+                .map(|deadline| deadline.saturating_sub(start.elapsed()));
+            let budget = match remaining {
+                Some(remaining) => phase.timeout.min(remaining),
+                None => phase.timeout,
+            };
+
+            if budget.is_zero() {
+                warn!(phase = %phase.name, "Shutdown deadline reached, skipping phase");
+                phases.push(PhaseReport {
+                    name: phase.name,
+                    timeout_ms: duration_ms(phase.timeout),
+                    duration_ms: 0,
+                    outcome: PhaseOutcome::Skipped,
+                });
+                continue;
+            }
+
+            let phase_start = Instant::now();
+            let outcome = match tokio::time::timeout(budget, (phase.action)()).await {
+                Ok(()) => PhaseOutcome::Completed,
+                Err(_) => PhaseOutcome::TimedOut,
+            };
+            let elapsed = phase_start.elapsed();
+
+            match outcome {
+                PhaseOutcome::Completed => info!(
+                    phase = %phase.name,
+                    duration_ms = duration_ms(elapsed),
+                    "Shutdown phase completed"
+                ),
+                _ => warn!(
+                    phase = %phase.name,
+                    duration_ms = duration_ms(elapsed),
+                    timeout_ms = duration_ms(budget),
+                    "Shutdown phase timed out"
+                ),
+            }
+
+            phases.push(PhaseReport {
+                name: phase.name,
+                timeout_ms: duration_ms(phase.timeout),
+                duration_ms: duration_ms(elapsed),
+                outcome,
+            });
+        }
+
+        let report = ShutdownReport {
+            service: self.service,
+            reason: reason.to_string(),
+            started_at,
+            total_duration_ms: duration_ms(start.elapsed()),
+            phases,
+        };
+        report.log();
+        report
+    }
+}
+
+/// How a shutdown phase ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseOutcome {
+    Completed,
+    TimedOut,
+    Skipped,
+}
+
+/// Result of a single shutdown phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseReport {
+    pub name: String,
+    pub timeout_ms: u64,
+    pub duration_ms: u64,
+    pub outcome: PhaseOutcome,
+}
+
+/// Structured summary of a coordinated shutdown
+///
+/// NIST 800-53 Controls:
+/// - AU-3: Content of Audit Records (per-phase outcome and timing)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    pub service: String,
+    pub reason: String,
+    pub started_at: DateTime<Utc>,
+    pub total_duration_ms: u64,
+    pub phases: Vec<PhaseReport>,
+}
+
+impl ShutdownReport {
+    /// True if every phase completed within its timeout
+    pub fn is_clean(&self) -> bool {
+        self.phases
+            .iter()
+            .all(|phase| phase.outcome == PhaseOutcome::Completed)
+    }
+
+    /// Look up a phase by name
+    pub fn phase(&self, name: &str) -> Option<&PhaseReport> {
+        self.phases.iter().find(|phase| phase.name == name)
+    }
+
+    /// Write the report as a structured audit event
+    pub fn log(&self) {
+        let json = serde_json::json!({
+            "event_type": "shutdown_report",
+            "report": self,
+        });
+
+        if self.is_clean() {
+            event!(Level::INFO, audit_event = %json);
+        } else {
+            event!(Level::WARN, audit_event = %json);
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn record(log: &Arc<Mutex<Vec<&'static str>>>, name: &'static str) {
+        log.lock().unwrap().push(name);
+    }
+
+    #[tokio::test]
+    async fn test_phases_run_in_order_with_timeout() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = ShutdownCoordinator::new("test");
+
+        let l = log.clone();
+        coordinator.register("listener", Duration::from_secs(1), move || async move {
+            record(&l, "listener");
+        });
+
+        let l = log.clone();
+        coordinator.register("drain", Duration::from_millis(50), move || async move {
+            record(&l, "drain-start");
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            record(&l, "drain-end");
+        });
+
+        let l = log.clone();
+        coordinator.register("database", Duration::from_secs(1), move || async move {
+            record(&l, "database");
+        });
+
+        let report = coordinator.run("test").await;
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["listener", "drain-start", "database"]
+        );
+        assert_eq!(report.phases.len(), 3);
+        assert_eq!(report.phases[0].name, "listener");
+        assert_eq!(report.phases[1].name, "drain");
+        assert_eq!(report.phases[2].name, "database");
+        assert_eq!(
+            report.phase("listener").unwrap().outcome,
+            PhaseOutcome::Completed
+        );
+        assert_eq!(report.phase("drain").unwrap().outcome, PhaseOutcome::TimedOut);
+        assert_eq!(
+            report.phase("database").unwrap().outcome,
+            PhaseOutcome::Completed
+        );
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_deadline_skips_remaining_phases() {
+        let mut coordinator =
+            ShutdownCoordinator::new("test").with_deadline(Duration::from_millis(50));
+
+        coordinator.register("slow", Duration::from_secs(5), || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        coordinator.register("after", Duration::from_secs(5), || async {});
+
+        let report = coordinator.run("test").await;
+
+        assert_eq!(report.phase("slow").unwrap().outcome, PhaseOutcome::TimedOut);
+        assert_eq!(report.phase("after").unwrap().outcome, PhaseOutcome::Skipped);
+    }
+
+    #[test]
+    fn test_report_serializes_outcomes() {
+        let report = ShutdownReport {
+            service: "test".to_string(),
+            reason: "signal".to_string(),
+            started_at: Utc::now(),
+            total_duration_ms: 10,
+            phases: vec![PhaseReport {
+                name: "drain".to_string(),
+                timeout_ms: 5,
+                duration_ms: 5,
+                outcome: PhaseOutcome::TimedOut,
+            }],
+        };
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"outcome\":\"timed_out\""));
+    }
+}
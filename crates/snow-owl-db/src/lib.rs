@@ -1,7 +1,13 @@
 use snow_owl_core::*;
 use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::{Connection, Postgres, QueryBuilder};
 use uuid::Uuid;
 
+/// Most images one deployment may stack, base included
+///
+/// NIST SC-5: bounds the parent walk even if the table holds a cycle
+pub const MAX_IMAGE_CHAIN_DEPTH: usize = 16;
+
 /// Database abstraction layer with security controls
 ///
 /// NIST Controls:
@@ -25,6 +31,11 @@ impl Database {
         Ok(db)
     }
 
+    /// Close the connection pool, waiting for checked-out connections to be returned
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
     async fn run_migrations(&self) -> Result<()> {
         sqlx::query(
             r#"
@@ -41,11 +52,15 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query("ALTER TABLE machines ADD COLUMN IF NOT EXISTS kernel_args TEXT")
+            .execute(&self.pool)
+            .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS images (
                 id UUID PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
                 description TEXT,
                 image_type TEXT NOT NULL,
                 file_path TEXT NOT NULL,
@@ -58,6 +73,37 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // NIST CM-8: Header-derived image details (edition, build, virtual size)
+        sqlx::query("ALTER TABLE images ADD COLUMN IF NOT EXISTS metadata JSONB")
+            .execute(&self.pool)
+            .await?;
+
+        // NIST CM-2: Differential images name the base they apply on top of;
+        // the reference keeps a base from being deleted under its deltas
+        sqlx::query(
+            "ALTER TABLE images ADD COLUMN IF NOT EXISTS parent_image_id UUID REFERENCES images(id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // NIST AU-11: Images that deployments reference are soft-deleted so
+        // the deployment history keeps its image record
+        sqlx::query("ALTER TABLE images ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ")
+            .execute(&self.pool)
+            .await?;
+
+        // Names are unique among live images only, so a deleted image's name
+        // can be reused; older installs still carry a table-wide constraint
+        sqlx::query("ALTER TABLE images DROP CONSTRAINT IF EXISTS images_name_key")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_images_live_name ON images(name) WHERE deleted_at IS NULL",
+        )
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS deployments (
@@ -74,6 +120,74 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // NIST CM-2: Driver packs injected by the WinPE agent
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS driver_packs (
+                id UUID PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                location TEXT NOT NULL,
+                hardware_ids TEXT[] NOT NULL DEFAULT '{}',
+                created_at TIMESTAMPTZ NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE deployments ADD COLUMN IF NOT EXISTS driver_pack_ids UUID[] NOT NULL DEFAULT '{}'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // NIST IA-3: Per-deployment secret for WinPE agent registration
+        sqlx::query("ALTER TABLE deployments ADD COLUMN IF NOT EXISTS registration_token TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_deployments_registration_token ON deployments(registration_token)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // NIST SC-24: Timed-out deployments recreated by the reconciler
+        // count their attempts so retries stop at the configured limit
+        sqlx::query(
+            "ALTER TABLE deployments ADD COLUMN IF NOT EXISTS retry_count INTEGER NOT NULL DEFAULT 0",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // NIST CM-8: Machine groups for group-scoped deployments and access
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS machine_groups (
+                id UUID PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                created_at TIMESTAMPTZ NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS machine_group_members (
+                group_id UUID NOT NULL REFERENCES machine_groups(id) ON DELETE CASCADE,
+                machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+                added_at TIMESTAMPTZ NOT NULL,
+                PRIMARY KEY (group_id, machine_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         // NIST AC-2: Account Management - users table
         sqlx::query(
             r#"
@@ -126,6 +240,38 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // NIST AU-3: SFTP paths and usernames that have no UUID
+        sqlx::query("ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS resource TEXT")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS username TEXT")
+            .execute(&self.pool)
+            .await?;
+        // NIST AU-6: Review queries and retention pruning scan by time
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at)")
+            .execute(&self.pool)
+            .await?;
+
+        // NIST SI-10: Idempotency keys for retry-safe API automation
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS idempotency_keys (
+                scope TEXT NOT NULL,
+                key TEXT NOT NULL,
+                endpoint TEXT NOT NULL,
+                request_hash TEXT NOT NULL,
+                resource_id UUID,
+                status_code INTEGER,
+                response_body TEXT,
+                created_at TIMESTAMPTZ NOT NULL,
+                expires_at TIMESTAMPTZ NOT NULL,
+                PRIMARY KEY (scope, key)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
@@ -142,11 +288,12 @@ impl Database {
         // PostgreSQL placeholder syntax ($1, $2, ...) ensures safe parameter binding
         sqlx::query(
             r#"
-            INSERT INTO machines (id, mac_address, hostname, ip_address, last_seen, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO machines (id, mac_address, hostname, ip_address, kernel_args, last_seen, created_at)
+            VALUES ($1, $2, $3, $4::inet, $5, $6, $7)
             ON CONFLICT(mac_address) DO UPDATE SET
                 hostname = EXCLUDED.hostname,
                 ip_address = EXCLUDED.ip_address,
+                kernel_args = EXCLUDED.kernel_args,
                 last_seen = EXCLUDED.last_seen
             "#,
         )
@@ -154,6 +301,7 @@ impl Database {
         .bind(machine.mac_address.to_string())
         .bind(&machine.hostname)
         .bind(machine.ip_address.map(|ip| ip.to_string()))
+        .bind(&machine.kernel_args)
         .bind(machine.last_seen)
         .bind(machine.created_at)
         .execute(&self.pool)
@@ -163,38 +311,111 @@ impl Database {
     }
 
     pub async fn get_machine_by_mac(&self, mac: &MacAddress) -> Result<Option<Machine>> {
-        let row = sqlx::query_as::<_, MachineRow>("SELECT * FROM machines WHERE mac_address = $1")
-            .bind(mac.to_string())
-            .fetch_optional(&self.pool)
-            .await?;
+        let row = sqlx::query_as::<_, MachineRow>(&format!(
+            "SELECT {} FROM machines WHERE mac_address = $1",
+            MACHINE_COLUMNS
+        ))
+        .bind(mac.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|r| r.try_into().ok()))
+    }
+
+    /// Machine last seen at `ip`; the most recently seen one if several share it
+    pub async fn get_machine_by_ip(&self, ip: std::net::IpAddr) -> Result<Option<Machine>> {
+        let row = sqlx::query_as::<_, MachineRow>(&format!(
+            "SELECT {} FROM machines WHERE ip_address = $1::inet ORDER BY last_seen DESC LIMIT 1",
+            MACHINE_COLUMNS
+        ))
+        .bind(ip.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
 
         Ok(row.and_then(|r| r.try_into().ok()))
     }
 
     pub async fn get_machine_by_id(&self, id: Uuid) -> Result<Option<Machine>> {
-        let row = sqlx::query_as::<_, MachineRow>("SELECT * FROM machines WHERE id = $1")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+        let row = sqlx::query_as::<_, MachineRow>(&format!(
+            "SELECT {} FROM machines WHERE id = $1",
+            MACHINE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
 
         Ok(row.and_then(|r| r.try_into().ok()))
     }
 
     pub async fn list_machines(&self) -> Result<Vec<Machine>> {
-        let rows =
-            sqlx::query_as::<_, MachineRow>("SELECT * FROM machines ORDER BY last_seen DESC")
-                .fetch_all(&self.pool)
-                .await?;
+        let rows = sqlx::query_as::<_, MachineRow>(&format!(
+            "SELECT {} FROM machines ORDER BY last_seen DESC",
+            MACHINE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
 
         Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
     }
 
+    /// List one page of machines matching `filter`, with the total match count
+    ///
+    /// NIST Controls:
+    /// - SI-10: Information Input Validation (filters bound as parameters)
+    /// - SC-5: Denial of Service Protection (bounded result size)
+    pub async fn list_machines_paged(
+        &self,
+        offset: u64,
+        limit: u32,
+        filter: &MachineFilter,
+    ) -> Result<(Vec<Machine>, u64)> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM machines");
+        push_machine_filter(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::new(format!("SELECT {} FROM machines", MACHINE_COLUMNS));
+        push_machine_filter(&mut query, filter);
+        push_order_and_page(
+            &mut query,
+            filter.sort.column(),
+            filter.order,
+            offset,
+            limit,
+        );
+        let rows = query
+            .build_query_as::<MachineRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((
+            rows.into_iter().filter_map(|r| r.try_into().ok()).collect(),
+            total as u64,
+        ))
+    }
+
     // Image operations
+    /// Register an image
+    ///
+    /// Returns `ImageNotFound` if the image names a parent that is not
+    /// registered or has been deleted, and `ImageNameTaken` if a live image
+    /// already has its name.
     pub async fn create_image(&self, image: &WindowsImage) -> Result<()> {
+        if let Some(parent_id) = image.parent_image_id
+            && self
+                .get_image_by_id(parent_id)
+                .await?
+                .is_none_or(|parent| parent.deleted_at.is_some())
+        {
+            return Err(SnowOwlError::ImageNotFound(format!(
+                "parent image {}",
+                parent_id
+            )));
+        }
+
         sqlx::query(
             r#"
-            INSERT INTO images (id, name, description, image_type, file_path, size_bytes, created_at, checksum)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO images (id, name, description, image_type, file_path, size_bytes, created_at, checksum, metadata, parent_image_id, deleted_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
         )
         .bind(image.id)
@@ -205,12 +426,18 @@ impl Database {
         .bind(image.size_bytes as i64)
         .bind(image.created_at)
         .bind(&image.checksum)
+        .bind(image.metadata.as_ref().map(sqlx::types::Json))
+        .bind(image.parent_image_id)
+        .bind(image.deleted_at)
         .execute(&self.pool)
-        .await?;
+        .await
+        .map_err(|e| name_taken(e, &image.name))?;
 
         Ok(())
     }
 
+    /// Image with `id`, including a soft-deleted one so deployment history
+    /// can still show what it deployed
     pub async fn get_image_by_id(&self, id: Uuid) -> Result<Option<WindowsImage>> {
         let row = sqlx::query_as::<_, ImageRow>("SELECT * FROM images WHERE id = $1")
             .bind(id)
@@ -221,15 +448,43 @@ impl Database {
     }
 
     pub async fn get_image_by_name(&self, name: &str) -> Result<Option<WindowsImage>> {
-        let row = sqlx::query_as::<_, ImageRow>("SELECT * FROM images WHERE name = $1")
-            .bind(name)
-            .fetch_optional(&self.pool)
-            .await?;
+        let row = sqlx::query_as::<_, ImageRow>(
+            "SELECT * FROM images WHERE name = $1 AND deleted_at IS NULL",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|r| r.try_into().ok()))
+    }
+
+    /// Live image registered with `path` as its file path
+    pub async fn get_image_by_path(&self, path: &std::path::Path) -> Result<Option<WindowsImage>> {
+        let row = sqlx::query_as::<_, ImageRow>(
+            "SELECT * FROM images WHERE file_path = $1 AND deleted_at IS NULL",
+        )
+        .bind(path.to_string_lossy().to_string())
+        .fetch_optional(&self.pool)
+        .await?;
 
         Ok(row.and_then(|r| r.try_into().ok()))
     }
 
     pub async fn list_images(&self) -> Result<Vec<WindowsImage>> {
+        let rows = sqlx::query_as::<_, ImageRow>(
+            "SELECT * FROM images WHERE deleted_at IS NULL ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
+    }
+
+    /// List every image, soft-deleted ones included
+    ///
+    /// NIST Controls:
+    /// - AU-11: Audit Record Retention (archived images stay reviewable)
+    pub async fn list_images_including_deleted(&self) -> Result<Vec<WindowsImage>> {
         let rows = sqlx::query_as::<_, ImageRow>("SELECT * FROM images ORDER BY created_at DESC")
             .fetch_all(&self.pool)
             .await?;
@@ -237,11 +492,200 @@ impl Database {
         Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
     }
 
-    pub async fn delete_image(&self, id: Uuid) -> Result<()> {
-        sqlx::query("DELETE FROM images WHERE id = $1")
+    /// List one page of images matching `filter`, with the total match count
+    ///
+    /// NIST Controls:
+    /// - SI-10: Information Input Validation (filters bound as parameters)
+    /// - SC-5: Denial of Service Protection (bounded result size)
+    pub async fn list_images_paged(
+        &self,
+        offset: u64,
+        limit: u32,
+        filter: &ImageFilter,
+    ) -> Result<(Vec<WindowsImage>, u64)> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM images");
+        push_image_filter(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM images");
+        push_image_filter(&mut query, filter);
+        push_order_and_page(
+            &mut query,
+            filter.sort.column(),
+            filter.order,
+            offset,
+            limit,
+        );
+        let rows = query
+            .build_query_as::<ImageRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((
+            rows.into_iter().filter_map(|r| r.try_into().ok()).collect(),
+            total as u64,
+        ))
+    }
+
+    /// The images a deployment of `id` applies, base first and `id` last
+    ///
+    /// Returns `ImageNotFound` if `id` or a parent along the way is not
+    /// registered or has been deleted, and `InvalidImage` if the chain is
+    /// deeper than [`MAX_IMAGE_CHAIN_DEPTH`].
+    pub async fn get_image_chain(&self, id: Uuid) -> Result<Vec<WindowsImage>> {
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(id) = next {
+            if chain.len() == MAX_IMAGE_CHAIN_DEPTH {
+                return Err(SnowOwlError::InvalidImage(format!(
+                    "image chain deeper than {} images",
+                    MAX_IMAGE_CHAIN_DEPTH
+                )));
+            }
+            let image = self
+                .get_image_by_id(id)
+                .await?
+                .filter(|image| image.deleted_at.is_none())
+                .ok_or_else(|| SnowOwlError::ImageNotFound(id.to_string()))?;
+            next = image.parent_image_id;
+            chain.push(image);
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// Live images registered as deltas on `id`
+    pub async fn list_dependent_images(&self, id: Uuid) -> Result<Vec<WindowsImage>> {
+        let rows = sqlx::query_as::<_, ImageRow>(
+            "SELECT * FROM images WHERE parent_image_id = $1 AND deleted_at IS NULL ORDER BY name",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
+    }
+
+    /// Delete an image
+    ///
+    /// An image that deployments or deleted deltas still reference is
+    /// soft-deleted: it keeps its row with `deleted_at` set and drops out of
+    /// listings and name lookups. Otherwise the row is removed.
+    ///
+    /// Returns `ImageNotFound` for an unknown or already deleted image, and
+    /// `ImageInUse` while live differential images depend on it or
+    /// deployments of it are still in progress.
+    ///
+    /// NIST Controls:
+    /// - AU-11: Audit Record Retention (deployment history keeps its image)
+    /// - CM-3: Configuration Change Control (in-progress deployments protected)
+    pub async fn delete_image(&self, id: Uuid) -> Result<ImageDeletion> {
+        let mut tx = self.pool.begin().await?;
+
+        // Lock the row so a concurrent deployment or delete waits for us
+        let live: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM images WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if live.is_none() {
+            return Err(SnowOwlError::ImageNotFound(id.to_string()));
+        }
+
+        let dependents: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM images WHERE parent_image_id = $1 AND deleted_at IS NULL ORDER BY name",
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if !dependents.is_empty() {
+            return Err(SnowOwlError::ImageInUse(format!(
+                "{} is the base of {}",
+                id,
+                dependents.join(", ")
+            )));
+        }
+
+        let active: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM deployments
+            WHERE image_id = $1 AND status NOT IN ('"completed"', '"failed"')
+            ORDER BY started_at
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if !active.is_empty() {
+            let ids: Vec<String> = active.iter().map(Uuid::to_string).collect();
+            return Err(SnowOwlError::ImageInUse(format!(
+                "{} has {} active deployment(s): {}",
+                id,
+                ids.len(),
+                ids.join(", ")
+            )));
+        }
+
+        let referenced: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM deployments WHERE image_id = $1)
+                OR EXISTS (SELECT 1 FROM images WHERE parent_image_id = $1)
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let deletion = if referenced {
+            sqlx::query("UPDATE images SET deleted_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            ImageDeletion::Archived
+        } else {
+            sqlx::query("DELETE FROM images WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            ImageDeletion::Removed
+        };
+
+        tx.commit().await?;
+        Ok(deletion)
+    }
+
+    /// Bring back a soft-deleted image
+    ///
+    /// Restoring a live image does nothing. Returns `ImageNotFound` if no
+    /// image has `id` or its base is still deleted, and `ImageNameTaken` if a
+    /// live image has taken its name since.
+    pub async fn restore_image(&self, id: Uuid) -> Result<()> {
+        let image = self
+            .get_image_by_id(id)
+            .await?
+            .ok_or_else(|| SnowOwlError::ImageNotFound(id.to_string()))?;
+        if image.deleted_at.is_none() {
+            return Ok(());
+        }
+        if let Some(parent_id) = image.parent_image_id
+            && self
+                .get_image_by_id(parent_id)
+                .await?
+                .is_none_or(|parent| parent.deleted_at.is_some())
+        {
+            return Err(SnowOwlError::ImageNotFound(format!(
+                "parent image {} is deleted; restore it first",
+                parent_id
+            )));
+        }
+
+        sqlx::query("UPDATE images SET deleted_at = NULL WHERE id = $1")
             .bind(id)
             .execute(&self.pool)
-            .await?;
+            .await
+            .map_err(|e| name_taken(e, &image.name))?;
 
         Ok(())
     }
@@ -250,8 +694,8 @@ impl Database {
     pub async fn create_deployment(&self, deployment: &Deployment) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO deployments (id, machine_id, image_id, status, started_at, completed_at, error_message)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO deployments (id, machine_id, image_id, status, started_at, completed_at, error_message, driver_pack_ids, registration_token, retry_count)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             "#,
         )
         .bind(deployment.id)
@@ -261,6 +705,9 @@ impl Database {
         .bind(deployment.started_at)
         .bind(deployment.completed_at)
         .bind(&deployment.error_message)
+        .bind(&deployment.driver_pack_ids)
+        .bind(&deployment.registration_token)
+        .bind(deployment.retry_count as i32)
         .execute(&self.pool)
         .await?;
 
@@ -308,6 +755,25 @@ impl Database {
         Ok(row.and_then(|r| r.try_into().ok()))
     }
 
+    /// Deployment whose WinPE agent registers with `token`, whatever its status
+    ///
+    /// NIST Controls:
+    /// - IA-3: Device Identification and Authentication
+    /// - SI-10: Information Input Validation (token bound as a parameter)
+    pub async fn get_deployment_by_registration_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<Deployment>> {
+        let row = sqlx::query_as::<_, DeploymentRow>(
+            "SELECT * FROM deployments WHERE registration_token = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|r| r.try_into().ok()))
+    }
+
     pub async fn get_active_deployment_for_machine(
         &self,
         machine_id: Uuid,
@@ -327,39 +793,377 @@ impl Database {
         Ok(row.and_then(|r| r.try_into().ok()))
     }
 
-    pub async fn list_deployments(&self) -> Result<Vec<Deployment>> {
+    /// Pending or in-progress deployments started before `started_before`,
+    /// oldest first
+    ///
+    /// NIST SC-24: Fail in Known State (finds deployments that never finished)
+    pub async fn list_stale_deployments(
+        &self,
+        started_before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<Deployment>> {
         let rows = sqlx::query_as::<_, DeploymentRow>(
-            "SELECT * FROM deployments ORDER BY started_at DESC",
+            r#"
+            SELECT * FROM deployments
+            WHERE started_at < $1 AND status NOT IN ('"completed"', '"failed"')
+            ORDER BY started_at
+            "#,
         )
+        .bind(started_before)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
     }
 
-    // User operations
-
-    /// Create a new user account
+    /// Create a pending deployment of `image_id` for each machine in one transaction
+    ///
+    /// Machines that already have an active deployment are skipped and
+    /// reported; duplicate IDs are deployed once. The machine rows are locked
+    /// for the duration so two concurrent batches cannot both deploy the same
+    /// machine, and any failed insert (e.g. an unknown machine) rolls back
+    /// the whole batch.
     ///
     /// NIST Controls:
-    /// - AC-2: Account Management
-    /// - IA-2: Identification and Authentication
-    /// - AU-3: Content of Audit Records
-    pub async fn create_user(&self, user: &User) -> Result<()> {
-        // NIST SI-10: Parameterized query prevents SQL injection
-        sqlx::query(
+    /// - CM-3: Configuration Change Control (all-or-nothing batch)
+    /// - SI-10: Information Input Validation (IDs bound as parameters)
+    pub async fn create_deployments_bulk(
+        &self,
+        machine_ids: &[Uuid],
+        image_id: Uuid,
+        driver_pack_ids: &[Uuid],
+    ) -> Result<BulkDeployment> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT id FROM machines WHERE id = ANY($1) ORDER BY id FOR UPDATE")
+            .bind(machine_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        let active: std::collections::HashMap<Uuid, Uuid> = sqlx::query_as::<_, (Uuid, Uuid)>(
             r#"
-            INSERT INTO users (id, username, role, created_at, last_login)
-            VALUES ($1, $2, $3, $4, $5)
+            SELECT DISTINCT ON (machine_id) machine_id, id FROM deployments
+            WHERE machine_id = ANY($1) AND status NOT IN ('"completed"', '"failed"')
+            ORDER BY machine_id, started_at DESC
             "#,
         )
-        .bind(user.id)
-        .bind(&user.username)
-        .bind(user.role.to_string())
-        .bind(user.created_at)
-        .bind(user.last_login)
-        .execute(&self.pool)
-        .await?;
+        .bind(machine_ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut result = BulkDeployment::default();
+        let mut seen = std::collections::HashSet::new();
+        for &machine_id in machine_ids {
+            if !seen.insert(machine_id) {
+                continue;
+            }
+            if let Some(&active_deployment_id) = active.get(&machine_id) {
+                result.skipped.push(SkippedMachine {
+                    machine_id,
+                    active_deployment_id,
+                });
+                continue;
+            }
+
+            let deployment = Deployment::pending(machine_id, image_id, driver_pack_ids.to_vec());
+            sqlx::query(
+                r#"
+                INSERT INTO deployments (id, machine_id, image_id, status, started_at, completed_at, error_message, driver_pack_ids, registration_token, retry_count)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
+            )
+            .bind(deployment.id)
+            .bind(deployment.machine_id)
+            .bind(deployment.image_id)
+            .bind(serde_json::to_string(&deployment.status).unwrap())
+            .bind(deployment.started_at)
+            .bind(deployment.completed_at)
+            .bind(&deployment.error_message)
+            .bind(&deployment.driver_pack_ids)
+            .bind(&deployment.registration_token)
+            .bind(deployment.retry_count as i32)
+            .execute(&mut *tx)
+            .await?;
+            result.created.push(deployment);
+        }
+
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn list_deployments(&self) -> Result<Vec<Deployment>> {
+        let rows = sqlx::query_as::<_, DeploymentRow>(
+            "SELECT * FROM deployments ORDER BY started_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
+    }
+
+    /// List one page of deployments matching `filter`, with the total match count
+    ///
+    /// NIST Controls:
+    /// - SI-10: Information Input Validation (filters bound as parameters)
+    /// - SC-5: Denial of Service Protection (bounded result size)
+    pub async fn list_deployments_paged(
+        &self,
+        offset: u64,
+        limit: u32,
+        filter: &DeploymentFilter,
+    ) -> Result<(Vec<Deployment>, u64)> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM deployments");
+        push_deployment_filter(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::new("SELECT * FROM deployments");
+        push_deployment_filter(&mut query, filter);
+        push_order_and_page(
+            &mut query,
+            filter.sort.column(),
+            filter.order,
+            offset,
+            limit,
+        );
+        let rows = query
+            .build_query_as::<DeploymentRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((
+            rows.into_iter().filter_map(|r| r.try_into().ok()).collect(),
+            total as u64,
+        ))
+    }
+
+    // Driver pack operations
+
+    /// Register a driver pack
+    ///
+    /// NIST Controls:
+    /// - CM-2: Baseline Configuration
+    /// - SI-10: Information Input Validation (parameterized queries)
+    pub async fn create_driver_pack(&self, pack: &DriverPack) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO driver_packs (id, name, description, location, hardware_ids, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(pack.id)
+        .bind(&pack.name)
+        .bind(&pack.description)
+        .bind(&pack.location)
+        .bind(&pack.hardware_ids)
+        .bind(pack.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_driver_pack_by_id(&self, id: Uuid) -> Result<Option<DriverPack>> {
+        let row = sqlx::query_as::<_, DriverPackRow>("SELECT * FROM driver_packs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn list_driver_packs(&self) -> Result<Vec<DriverPack>> {
+        let rows = sqlx::query_as::<_, DriverPackRow>("SELECT * FROM driver_packs ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Get the driver packs with the given IDs, in the order the IDs are listed
+    ///
+    /// IDs with no matching pack (e.g. deleted since the deployment was
+    /// created) are skipped.
+    pub async fn get_driver_packs(&self, ids: &[Uuid]) -> Result<Vec<DriverPack>> {
+        let rows =
+            sqlx::query_as::<_, DriverPackRow>("SELECT * FROM driver_packs WHERE id = ANY($1)")
+                .bind(ids)
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut packs: Vec<DriverPack> = rows.into_iter().map(Into::into).collect();
+        packs.sort_by_key(|pack| ids.iter().position(|id| *id == pack.id));
+        Ok(packs)
+    }
+
+    pub async fn delete_driver_pack(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM driver_packs WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Machine group operations
+
+    /// Create a machine group
+    ///
+    /// Fails if a group with the same name already exists.
+    ///
+    /// NIST Controls:
+    /// - CM-8: Information System Component Inventory
+    /// - SI-10: Information Input Validation (parameterized queries)
+    pub async fn create_machine_group(&self, group: &MachineGroup) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO machine_groups (id, name, description, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(group.id)
+        .bind(&group.name)
+        .bind(&group.description)
+        .bind(group.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_machine_group_by_id(&self, id: Uuid) -> Result<Option<MachineGroup>> {
+        let row =
+            sqlx::query_as::<_, MachineGroupRow>("SELECT * FROM machine_groups WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn get_machine_group_by_name(&self, name: &str) -> Result<Option<MachineGroup>> {
+        let row =
+            sqlx::query_as::<_, MachineGroupRow>("SELECT * FROM machine_groups WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn list_machine_groups(&self) -> Result<Vec<MachineGroup>> {
+        let rows =
+            sqlx::query_as::<_, MachineGroupRow>("SELECT * FROM machine_groups ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Delete a group and its memberships; the machines are kept
+    pub async fn delete_machine_group(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM machine_groups WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add a machine to a group; returns false if it was already a member
+    ///
+    /// NIST Controls:
+    /// - CM-8: Information System Component Inventory
+    /// - SI-10: Information Input Validation (parameterized queries)
+    pub async fn add_machine_to_group(&self, group_id: Uuid, machine_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO machine_group_members (group_id, machine_id, added_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (group_id, machine_id) DO NOTHING
+            "#,
+        )
+        .bind(group_id)
+        .bind(machine_id)
+        .bind(chrono::Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a machine from a group; returns false if it was not a member
+    pub async fn remove_machine_from_group(
+        &self,
+        group_id: Uuid,
+        machine_id: Uuid,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM machine_group_members WHERE group_id = $1 AND machine_id = $2",
+        )
+        .bind(group_id)
+        .bind(machine_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Machines in a group, by hostname then MAC address
+    pub async fn list_group_machines(&self, group_id: Uuid) -> Result<Vec<Machine>> {
+        let rows = sqlx::query_as::<_, MachineRow>(&format!(
+            "SELECT {} FROM machines WHERE id IN \
+             (SELECT machine_id FROM machine_group_members WHERE group_id = $1) \
+             ORDER BY hostname NULLS LAST, mac_address",
+            MACHINE_COLUMNS
+        ))
+        .bind(group_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().filter_map(|r| r.try_into().ok()).collect())
+    }
+
+    /// Groups a machine belongs to, by name
+    pub async fn list_machine_group_memberships(
+        &self,
+        machine_id: Uuid,
+    ) -> Result<Vec<MachineGroup>> {
+        let rows = sqlx::query_as::<_, MachineGroupRow>(
+            "SELECT * FROM machine_groups WHERE id IN \
+             (SELECT group_id FROM machine_group_members WHERE machine_id = $1) \
+             ORDER BY name",
+        )
+        .bind(machine_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    // User operations
+
+    /// Create a new user account
+    ///
+    /// NIST Controls:
+    /// - AC-2: Account Management
+    /// - IA-2: Identification and Authentication
+    /// - AU-3: Content of Audit Records
+    pub async fn create_user(&self, user: &User) -> Result<()> {
+        // NIST SI-10: Parameterized query prevents SQL injection
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, username, role, created_at, last_login)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(user.id)
+        .bind(&user.username)
+        .bind(user.role.to_string())
+        .bind(user.created_at)
+        .bind(user.last_login)
+        .execute(&self.pool)
+        .await?;
 
         Ok(())
     }
@@ -511,15 +1315,405 @@ impl Database {
 
         Ok(())
     }
+
+    // Audit log operations
+
+    /// Append a record to the shared audit trail
+    ///
+    /// The one insert path for `audit_log`; services normally reach it
+    /// through an `AuditQueue` rather than calling it on the request path.
+    ///
+    /// NIST Controls:
+    /// - AU-3: Content of Audit Records
+    /// - AU-9: Protection of Audit Information (append-only insert)
+    pub async fn append_audit_event(&self, record: &AuditRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (id, user_id, action, resource_type, resource_id, resource, username, ip_address, success, error_message, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8::inet, $9, $10, $11)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(record.user_id)
+        .bind(&record.action)
+        .bind(&record.resource_type)
+        .bind(record.resource_id)
+        .bind(&record.resource)
+        .bind(&record.username)
+        .bind(record.ip_address.map(|ip| ip.to_string()))
+        .bind(record.success)
+        .bind(&record.error_message)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List one page of audit records matching `filter`, newest first, with
+    /// the total match count
+    ///
+    /// NIST Controls:
+    /// - AU-6: Audit Review, Analysis, and Reporting
+    /// - AU-7: Audit Reduction and Report Generation (filtering)
+    /// - SI-10: Information Input Validation (filters bound as parameters)
+    /// - SC-5: Denial of Service Protection (bounded result size)
+    pub async fn query_audit_log(
+        &self,
+        offset: u64,
+        limit: u32,
+        filter: &AuditFilter,
+    ) -> Result<(Vec<AuditEntry>, u64)> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM audit_log");
+        push_audit_filter(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut query = QueryBuilder::new(format!("SELECT {} FROM audit_log", AUDIT_COLUMNS));
+        push_audit_filter(&mut query, filter);
+        push_order_and_page(&mut query, "created_at", SortOrder::Desc, offset, limit);
+        let rows = query
+            .build_query_as::<AuditRow>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((
+            rows.into_iter().filter_map(|r| r.try_into().ok()).collect(),
+            total as u64,
+        ))
+    }
+
+    /// Delete audit records created before `older_than`, returning how many
+    /// were removed
+    ///
+    /// NIST Controls:
+    /// - AU-11: Audit Record Retention
+    pub async fn prune_audit_log(&self, older_than: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM audit_log WHERE created_at < $1")
+            .bind(older_than)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Idempotency key operations
+
+    /// Claim an idempotency key before running the request it guards
+    ///
+    /// An expired record for the same key is discarded first, as is a claim
+    /// that has had no response stored for longer than `in_flight_limit`: its
+    /// request died without releasing it. Returns false if a live record
+    /// already exists, in which case the caller must not run the request.
+    ///
+    /// NIST Controls:
+    /// - SI-10: Information Input Validation (unique insert serializes retries)
+    pub async fn claim_idempotency_key(
+        &self,
+        record: &IdempotencyRecord,
+        in_flight_limit: std::time::Duration,
+    ) -> Result<bool> {
+        let stale_before = chrono::Utc::now()
+            - chrono::Duration::from_std(in_flight_limit).unwrap_or(chrono::Duration::zero());
+
+        sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE scope = $1 AND key = $2
+              AND (expires_at <= NOW() OR (status_code IS NULL AND created_at <= $3))
+            "#,
+        )
+        .bind(&record.scope)
+        .bind(&record.key)
+        .bind(stale_before)
+        .execute(&self.pool)
+        .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (scope, key, endpoint, request_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (scope, key) DO NOTHING
+            "#,
+        )
+        .bind(&record.scope)
+        .bind(&record.key)
+        .bind(&record.endpoint)
+        .bind(&record.request_hash)
+        .bind(record.created_at)
+        .bind(record.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Get an unexpired idempotency record
+    pub async fn get_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+    ) -> Result<Option<IdempotencyRecord>> {
+        let row = sqlx::query_as::<_, IdempotencyRow>(
+            r#"
+            SELECT * FROM idempotency_keys
+            WHERE scope = $1 AND key = $2 AND expires_at > NOW()
+            "#,
+        )
+        .bind(scope)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|r| r.try_into().ok()))
+    }
+
+    /// Store the response for a claimed idempotency key
+    pub async fn complete_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        resource_id: Option<Uuid>,
+        status_code: u16,
+        response_body: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET resource_id = $1, status_code = $2, response_body = $3
+            WHERE scope = $4 AND key = $5
+            "#,
+        )
+        .bind(resource_id)
+        .bind(i32::from(status_code))
+        .bind(response_body.to_string())
+        .bind(scope)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Release a claimed idempotency key so the request can be retried
+    pub async fn release_idempotency_key(&self, scope: &str, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE scope = $1 AND key = $2")
+            .bind(scope)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete idempotency records past their retention window
+    pub async fn purge_expired_idempotency_keys(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Try to take the session-level advisory lock for `name` without waiting
+    ///
+    /// The lock lives on a connection detached from the pool, so it is held
+    /// until [`AdvisoryLock::release`] is called or the connection drops; if
+    /// the process crashes, Postgres releases it when the session ends.
+    ///
+    /// NIST Controls:
+    /// - SC-24: Fail in Known State (lock released with the session)
+    pub async fn try_advisory_lock(&self, name: &str) -> Result<Option<AdvisoryLock>> {
+        let mut conn = self.pool.acquire().await?.detach();
+
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1, hashtext($2))")
+            .bind(ADVISORY_LOCK_NAMESPACE)
+            .bind(name)
+            .fetch_one(&mut conn)
+            .await?;
+
+        if !acquired {
+            // Closing the connection cannot release a lock we do not hold
+            let _ = conn.close().await;
+            return Ok(None);
+        }
+
+        Ok(Some(AdvisoryLock {
+            conn,
+            name: name.to_string(),
+        }))
+    }
+}
+
+impl AuditSink for Database {
+    async fn record(&self, record: &AuditRecord) -> Result<()> {
+        self.append_audit_event(record).await
+    }
+}
+
+// List query building
+//
+// Filters are appended with `push_bind`, and sort columns come from the
+// closed sort enums, so no request text reaches the SQL string.
+
+/// Map a unique violation on an image insert or restore to `ImageNameTaken`
+fn name_taken(error: sqlx::Error, name: &str) -> SnowOwlError {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+            SnowOwlError::ImageNameTaken(name.to_string())
+        }
+        _ => error.into(),
+    }
+}
+
+/// Start the next filter condition with `WHERE` or `AND`
+fn push_condition(query: &mut QueryBuilder<'_, Postgres>, first: &mut bool, condition: &str) {
+    query.push(if *first { " WHERE " } else { " AND " });
+    query.push(condition);
+    *first = false;
+}
+
+fn push_machine_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &MachineFilter) {
+    let mut first = true;
+    if let Some(hostname) = &filter.hostname {
+        push_condition(query, &mut first, "hostname ILIKE ");
+        query.push_bind(like_pattern(hostname));
+    }
+}
+
+fn push_image_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &ImageFilter) {
+    let mut first = true;
+    push_condition(query, &mut first, "deleted_at IS NULL");
+    if let Some(name) = &filter.name {
+        push_condition(query, &mut first, "name ILIKE ");
+        query.push_bind(like_pattern(name));
+    }
+    if let Some(image_type) = filter.image_type {
+        push_condition(query, &mut first, "image_type = ");
+        query.push_bind(serde_json::to_string(&image_type).unwrap());
+    }
+}
+
+fn push_deployment_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &DeploymentFilter) {
+    let mut first = true;
+    if let Some(status) = filter.status {
+        push_condition(query, &mut first, "status = ");
+        query.push_bind(serde_json::to_string(&status).unwrap());
+    }
+    if let Some(machine_id) = filter.machine_id {
+        push_condition(query, &mut first, "machine_id = ");
+        query.push_bind(machine_id);
+    }
+    if let Some(image_id) = filter.image_id {
+        push_condition(query, &mut first, "image_id = ");
+        query.push_bind(image_id);
+    }
+}
+
+fn push_audit_filter(query: &mut QueryBuilder<'_, Postgres>, filter: &AuditFilter) {
+    let mut first = true;
+    if let Some(user_id) = filter.user_id {
+        push_condition(query, &mut first, "user_id = ");
+        query.push_bind(user_id);
+    }
+    if let Some(username) = &filter.username {
+        push_condition(query, &mut first, "username = ");
+        query.push_bind(username.clone());
+    }
+    if let Some(prefix) = &filter.action_prefix {
+        push_condition(query, &mut first, "action LIKE ");
+        query.push_bind(like_prefix_pattern(prefix));
+    }
+    if let Some(resource_type) = &filter.resource_type {
+        push_condition(query, &mut first, "resource_type = ");
+        query.push_bind(resource_type.clone());
+    }
+    if let Some(success) = filter.success {
+        push_condition(query, &mut first, "success = ");
+        query.push_bind(success);
+    }
+    if let Some(from) = filter.from {
+        push_condition(query, &mut first, "created_at >= ");
+        query.push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        push_condition(query, &mut first, "created_at < ");
+        query.push_bind(to);
+    }
+}
+
+/// Append the sort order and page bounds
+///
+/// `id` breaks ties so rows with equal sort keys keep a stable order across
+/// pages.
+fn push_order_and_page(
+    query: &mut QueryBuilder<'_, Postgres>,
+    column: &'static str,
+    order: SortOrder,
+    offset: u64,
+    limit: u32,
+) {
+    query.push(format!(
+        " ORDER BY {} {} NULLS LAST, id {}",
+        column,
+        order.as_sql(),
+        order.as_sql()
+    ));
+    query.push(" LIMIT ");
+    query.push_bind(i64::from(limit));
+    query.push(" OFFSET ");
+    query.push_bind(i64::try_from(offset).unwrap_or(i64::MAX));
+}
+
+/// Namespace for Snow-Owl advisory locks (first key of the two-key form)
+const ADVISORY_LOCK_NAMESPACE: i32 = 0x534f_574c; // "SOWL"
+
+/// A held Postgres advisory lock
+///
+/// Dropping the lock closes its dedicated connection, which releases it.
+pub struct AdvisoryLock {
+    conn: sqlx::PgConnection,
+    name: String,
+}
+
+impl AdvisoryLock {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check that the session holding the lock is still alive
+    pub async fn is_held(&mut self) -> bool {
+        sqlx::query("SELECT 1")
+            .execute(&mut self.conn)
+            .await
+            .is_ok()
+    }
+
+    /// Release the lock and close its connection
+    pub async fn release(mut self) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_unlock($1, hashtext($2))")
+            .bind(ADVISORY_LOCK_NAMESPACE)
+            .bind(&self.name)
+            .execute(&mut self.conn)
+            .await?;
+        self.conn.close().await?;
+
+        Ok(())
+    }
 }
 
 // Row structures for PostgreSQL
+
+/// Machine columns, with the INET address read back as text for `MachineRow`
+const MACHINE_COLUMNS: &str =
+    "id, mac_address, hostname, host(ip_address) AS ip_address, kernel_args, last_seen, created_at";
+
 #[derive(sqlx::FromRow)]
 struct MachineRow {
     id: Uuid,
     mac_address: String,
     hostname: Option<String>,
     ip_address: Option<String>,
+    kernel_args: Option<String>,
     last_seen: chrono::DateTime<chrono::Utc>,
     created_at: chrono::DateTime<chrono::Utc>,
 }
@@ -533,6 +1727,7 @@ impl TryFrom<MachineRow> for Machine {
             mac_address: row.mac_address.parse()?,
             hostname: row.hostname,
             ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+            kernel_args: row.kernel_args,
             last_seen: row.last_seen,
             created_at: row.created_at,
         })
@@ -549,6 +1744,9 @@ struct ImageRow {
     size_bytes: i64,
     created_at: chrono::DateTime<chrono::Utc>,
     checksum: Option<String>,
+    metadata: Option<sqlx::types::Json<ImageMetadata>>,
+    parent_image_id: Option<Uuid>,
+    deleted_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl TryFrom<ImageRow> for WindowsImage {
@@ -564,6 +1762,9 @@ impl TryFrom<ImageRow> for WindowsImage {
             size_bytes: row.size_bytes as u64,
             created_at: row.created_at,
             checksum: row.checksum,
+            metadata: row.metadata.map(|sqlx::types::Json(metadata)| metadata),
+            parent_image_id: row.parent_image_id,
+            deleted_at: row.deleted_at,
         })
     }
 }
@@ -577,6 +1778,9 @@ struct DeploymentRow {
     started_at: chrono::DateTime<chrono::Utc>,
     completed_at: Option<chrono::DateTime<chrono::Utc>>,
     error_message: Option<String>,
+    driver_pack_ids: Vec<Uuid>,
+    registration_token: Option<String>,
+    retry_count: i32,
 }
 
 impl TryFrom<DeploymentRow> for Deployment {
@@ -591,10 +1795,55 @@ impl TryFrom<DeploymentRow> for Deployment {
             started_at: row.started_at,
             completed_at: row.completed_at,
             error_message: row.error_message,
+            driver_pack_ids: row.driver_pack_ids,
+            registration_token: row.registration_token,
+            retry_count: u32::try_from(row.retry_count)?,
         })
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct DriverPackRow {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    location: String,
+    hardware_ids: Vec<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<DriverPackRow> for DriverPack {
+    fn from(row: DriverPackRow) -> Self {
+        DriverPack {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            location: row.location,
+            hardware_ids: row.hardware_ids,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MachineGroupRow {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<MachineGroupRow> for MachineGroup {
+    fn from(row: MachineGroupRow) -> Self {
+        MachineGroup {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            created_at: row.created_at,
+        }
+    }
+}
+
 #[derive(sqlx::FromRow)]
 struct UserRow {
     id: Uuid,
@@ -651,3 +1900,960 @@ impl TryFrom<ApiKeyRow> for ApiKey {
         })
     }
 }
+
+/// Audit columns, with the INET address read back as text for `AuditRow`
+const AUDIT_COLUMNS: &str = "id, user_id, action, resource_type, resource_id, resource, username, \
+     host(ip_address) AS ip_address, success, error_message, created_at";
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    id: Uuid,
+    user_id: Option<Uuid>,
+    action: String,
+    resource_type: Option<String>,
+    resource_id: Option<Uuid>,
+    resource: Option<String>,
+    username: Option<String>,
+    ip_address: Option<String>,
+    success: bool,
+    error_message: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<AuditRow> for AuditEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: AuditRow) -> std::result::Result<Self, Self::Error> {
+        Ok(AuditEntry {
+            id: row.id,
+            record: AuditRecord {
+                action: row.action,
+                resource_type: row.resource_type,
+                resource_id: row.resource_id,
+                resource: row.resource,
+                user_id: row.user_id,
+                username: row.username,
+                ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+                success: row.success,
+                error_message: row.error_message,
+                created_at: row.created_at,
+            },
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct IdempotencyRow {
+    scope: String,
+    key: String,
+    endpoint: String,
+    request_hash: String,
+    resource_id: Option<Uuid>,
+    status_code: Option<i32>,
+    response_body: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<IdempotencyRow> for IdempotencyRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(row: IdempotencyRow) -> std::result::Result<Self, Self::Error> {
+        Ok(IdempotencyRecord {
+            scope: row.scope,
+            key: row.key,
+            endpoint: row.endpoint,
+            request_hash: row.request_hash,
+            resource_id: row.resource_id,
+            status_code: row.status_code.map(u16::try_from).transpose()?,
+            response_body: row
+                .response_body
+                .map(|body| serde_json::from_str(&body))
+                .transpose()?,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, SubsecRound, Utc};
+
+    /// Database for one test, created fresh under the server named by
+    /// `SNOW_OWL_TEST_DATABASE_URL`
+    ///
+    /// Tests using it are ignored by default; run them with
+    /// `cargo test -- --ignored` against a PostgreSQL server.
+    struct TestDb {
+        db: Database,
+        admin_url: String,
+        name: String,
+    }
+
+    impl TestDb {
+        async fn new() -> Self {
+            let admin_url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+                .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+            let name = format!("snow_owl_test_{}", Uuid::new_v4().simple());
+
+            let mut admin = sqlx::PgConnection::connect(&admin_url).await.unwrap();
+            sqlx::query(&format!("CREATE DATABASE {}", name))
+                .execute(&mut admin)
+                .await
+                .unwrap();
+            admin.close().await.unwrap();
+
+            let (server, _) = admin_url.rsplit_once('/').unwrap();
+            let db = Database::new(&format!("{}/{}", server, name))
+                .await
+                .unwrap();
+            Self {
+                db,
+                admin_url,
+                name,
+            }
+        }
+
+        async fn cleanup(self) {
+            self.db.close().await;
+            let mut admin = sqlx::PgConnection::connect(&self.admin_url).await.unwrap();
+            sqlx::query(&format!("DROP DATABASE {} WITH (FORCE)", self.name))
+                .execute(&mut admin)
+                .await
+                .unwrap();
+        }
+    }
+
+    /// 60 machines (`lab-00`..`lab-39`, `dc-00`..`dc-19`), 30 images
+    /// alternating WIM/VHDX, and 45 deployments cycling through
+    /// pending/completed/failed
+    async fn seed(db: &Database) -> Vec<Machine> {
+        let now = Utc::now();
+        let mut machines = Vec::new();
+        for i in 0..60u8 {
+            let hostname = if i < 40 {
+                format!("lab-{:02}", i)
+            } else {
+                format!("dc-{:02}", i - 40)
+            };
+            let machine = Machine {
+                id: Uuid::new_v4(),
+                mac_address: MacAddress::new([0x02, 0, 0, 0, 0, i]),
+                hostname: Some(hostname),
+                ip_address: (i == 0).then(|| "10.0.0.5".parse().unwrap()),
+                kernel_args: None,
+                last_seen: now - Duration::minutes(i64::from(i)),
+                created_at: now,
+            };
+            db.create_or_update_machine(&machine).await.unwrap();
+            machines.push(machine);
+        }
+
+        let mut images = Vec::new();
+        for i in 0..30 {
+            let image = WindowsImage {
+                id: Uuid::new_v4(),
+                name: format!("image-{:02}", i),
+                description: None,
+                image_type: if i % 2 == 0 {
+                    ImageType::Wim
+                } else {
+                    ImageType::Vhdx
+                },
+                file_path: format!("/var/lib/snow-owl/images/{}.img", i).into(),
+                size_bytes: i as u64 * 1024,
+                created_at: now - Duration::minutes(i as i64),
+                checksum: None,
+                metadata: None,
+                parent_image_id: None,
+                deleted_at: None,
+            };
+            db.create_image(&image).await.unwrap();
+            images.push(image);
+        }
+
+        let statuses = [
+            DeploymentStatus::Pending,
+            DeploymentStatus::Completed,
+            DeploymentStatus::Failed,
+        ];
+        for i in 0..45 {
+            db.create_deployment(&Deployment {
+                id: Uuid::new_v4(),
+                machine_id: machines[i % 5].id,
+                image_id: images[i % 30].id,
+                status: statuses[i % 3],
+                started_at: now - Duration::minutes(i as i64),
+                completed_at: None,
+                error_message: None,
+                driver_pack_ids: Vec::new(),
+                registration_token: None,
+                retry_count: 0,
+            })
+            .await
+            .unwrap();
+        }
+
+        machines
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_machine_paging_boundaries() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let filter = MachineFilter::default();
+
+        let mut seen = Vec::new();
+        for (offset, expected) in [(0, 25), (25, 25), (50, 10), (75, 0)] {
+            let (page, total) = test
+                .db
+                .list_machines_paged(offset, 25, &filter)
+                .await
+                .unwrap();
+            assert_eq!(total, 60);
+            assert_eq!(page.len(), expected);
+            seen.extend(page.into_iter().map(|machine| machine.id));
+        }
+
+        // Default order is most recently seen first, and no row repeats
+        let expected: Vec<Uuid> = machines.iter().map(|machine| machine.id).collect();
+        assert_eq!(seen, expected);
+
+        let (page, _) = test.db.list_machines_paged(0, 1, &filter).await.unwrap();
+        assert_eq!(page[0].ip_address, machines[0].ip_address);
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_machine_by_ip() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+
+        let found = test
+            .db
+            .get_machine_by_ip("10.0.0.5".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(found.map(|machine| machine.id), Some(machines[0].id));
+        let missing = test
+            .db
+            .get_machine_by_ip("10.0.0.6".parse().unwrap())
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_stale_deployments_and_retry_count() {
+        let test = TestDb::new().await;
+        seed(&test.db).await;
+
+        // Seeded deployments 33, 36, 39 and 42 are pending and over 30 minutes old
+        let cutoff = Utc::now() - Duration::minutes(30) - Duration::seconds(30);
+        let stale = test.db.list_stale_deployments(cutoff).await.unwrap();
+        assert_eq!(stale.len(), 4);
+        assert!(stale.iter().all(|d| d.status == DeploymentStatus::Pending));
+        assert!(stale.windows(2).all(|w| w[0].started_at <= w[1].started_at));
+
+        test.db
+            .update_deployment_status(
+                stale[0].id,
+                DeploymentStatus::Failed,
+                Some("timeout".into()),
+            )
+            .await
+            .unwrap();
+        let retry = stale[0].retry();
+        test.db.create_deployment(&retry).await.unwrap();
+        let stored = test
+            .db
+            .get_deployment_by_id(retry.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.retry_count, 1);
+        assert_eq!(
+            test.db.list_stale_deployments(cutoff).await.unwrap().len(),
+            3
+        );
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_list_filters_and_sorting() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+
+        // Hostname substring, case-insensitive, sorted by hostname
+        let filter = MachineFilter {
+            hostname: Some("DC-".to_string()),
+            sort: MachineSort::Hostname,
+            order: SortOrder::Asc,
+        };
+        let (page, total) = test.db.list_machines_paged(0, 5, &filter).await.unwrap();
+        assert_eq!(total, 20);
+        let hostnames: Vec<_> = page.iter().filter_map(|m| m.hostname.as_deref()).collect();
+        assert_eq!(hostnames, ["dc-00", "dc-01", "dc-02", "dc-03", "dc-04"]);
+
+        // LIKE wildcards in the search term match literally
+        let filter = MachineFilter {
+            hostname: Some("%".to_string()),
+            ..Default::default()
+        };
+        let (page, total) = test.db.list_machines_paged(0, 500, &filter).await.unwrap();
+        assert_eq!((page.len(), total), (0, 0));
+
+        let filter = ImageFilter {
+            image_type: Some(ImageType::Vhdx),
+            sort: ImageSort::SizeBytes,
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+        let (page, total) = test.db.list_images_paged(0, 3, &filter).await.unwrap();
+        assert_eq!(total, 15);
+        let names: Vec<_> = page.iter().map(|image| image.name.as_str()).collect();
+        assert_eq!(names, ["image-29", "image-27", "image-25"]);
+
+        let filter = DeploymentFilter {
+            status: Some(DeploymentStatus::Failed),
+            ..Default::default()
+        };
+        let (page, total) = test
+            .db
+            .list_deployments_paged(10, 10, &filter)
+            .await
+            .unwrap();
+        assert_eq!((page.len(), total), (5, 15));
+        assert!(
+            page.iter()
+                .all(|deployment| deployment.status == DeploymentStatus::Failed)
+        );
+
+        let filter = DeploymentFilter {
+            status: Some(DeploymentStatus::Pending),
+            machine_id: Some(machines[0].id),
+            ..Default::default()
+        };
+        let (_, total) = test
+            .db
+            .list_deployments_paged(0, 50, &filter)
+            .await
+            .unwrap();
+        // Machine 0 takes deployments 0, 5, ..., 40; every third is pending
+        assert_eq!(total, 3);
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_machine_group_membership() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+
+        let group = MachineGroup::new("lab", Some("Lab benches".to_string()));
+        db.create_machine_group(&group).await.unwrap();
+        assert!(
+            db.create_machine_group(&MachineGroup::new("lab", None))
+                .await
+                .is_err()
+        );
+        let found = db.get_machine_group_by_name("lab").await.unwrap().unwrap();
+        assert_eq!(found.id, group.id);
+
+        for machine in machines.iter().take(3) {
+            assert!(db.add_machine_to_group(group.id, machine.id).await.unwrap());
+        }
+        // Adding twice is a no-op
+        assert!(
+            !db.add_machine_to_group(group.id, machines[0].id)
+                .await
+                .unwrap()
+        );
+
+        let members = db.list_group_machines(group.id).await.unwrap();
+        let hostnames: Vec<_> = members
+            .iter()
+            .filter_map(|m| m.hostname.as_deref())
+            .collect();
+        assert_eq!(hostnames, ["lab-00", "lab-01", "lab-02"]);
+        let memberships = db
+            .list_machine_group_memberships(machines[1].id)
+            .await
+            .unwrap();
+        assert_eq!(memberships.len(), 1);
+        assert_eq!(memberships[0].name, "lab");
+
+        assert!(
+            db.remove_machine_from_group(group.id, machines[1].id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.remove_machine_from_group(group.id, machines[1].id)
+                .await
+                .unwrap()
+        );
+        assert_eq!(db.list_group_machines(group.id).await.unwrap().len(), 2);
+
+        // Deleting the group drops memberships but keeps the machines
+        db.delete_machine_group(group.id).await.unwrap();
+        assert!(
+            db.get_machine_group_by_id(group.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(db.list_group_machines(group.id).await.unwrap().is_empty());
+        assert!(
+            db.get_machine_by_id(machines[0].id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_bulk_deployment_skips_active_machines() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+        let image = db.list_images().await.unwrap().remove(0);
+
+        // Machines 0-4 each have a pending deployment from the seed
+        let ids: Vec<Uuid> = machines[3..8].iter().map(|m| m.id).collect();
+        let mut request = ids.clone();
+        request.push(ids[2]);
+        let result = db
+            .create_deployments_bulk(&request, image.id, &[])
+            .await
+            .unwrap();
+
+        let skipped: Vec<Uuid> = result.skipped.iter().map(|s| s.machine_id).collect();
+        assert_eq!(skipped, ids[..2]);
+        for skip in &result.skipped {
+            let active = db
+                .get_active_deployment_for_machine(skip.machine_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(skip.active_deployment_id, active.id);
+        }
+
+        let created: Vec<Uuid> = result.created.iter().map(|d| d.machine_id).collect();
+        assert_eq!(created, ids[2..]);
+        for deployment in &result.created {
+            let stored = db
+                .get_deployment_by_id(deployment.id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(stored.status, DeploymentStatus::Pending);
+            assert_eq!(stored.image_id, image.id);
+        }
+
+        // Deploying again skips every machine that was just created
+        let again = db
+            .create_deployments_bulk(&ids, image.id, &[])
+            .await
+            .unwrap();
+        assert!(again.created.is_empty());
+        assert_eq!(again.skipped.len(), ids.len());
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_bulk_deployment_rolls_back_on_failure() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+        let image = db.list_images().await.unwrap().remove(0);
+
+        // The unknown machine fails its insert after two have succeeded
+        let request = [machines[10].id, machines[11].id, Uuid::new_v4()];
+        assert!(
+            db.create_deployments_bulk(&request, image.id, &[])
+                .await
+                .is_err()
+        );
+
+        for machine in &machines[10..12] {
+            assert!(
+                db.get_active_deployment_for_machine(machine.id)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        assert_eq!(db.list_deployments().await.unwrap().len(), 45);
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_differential_image_chain() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+
+        let base = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "server-2022-base".to_string(),
+            description: None,
+            image_type: ImageType::Vhdx,
+            file_path: "/var/lib/snow-owl/images/base.vhdx".into(),
+            size_bytes: 1 << 30,
+            created_at: Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        let delta = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "server-2022-iis".to_string(),
+            file_path: "/var/lib/snow-owl/images/iis.vhdx".into(),
+            parent_image_id: Some(base.id),
+            ..base.clone()
+        };
+        db.create_image(&base).await.unwrap();
+        db.create_image(&delta).await.unwrap();
+
+        // A parent must be registered
+        let orphan = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "orphan".to_string(),
+            parent_image_id: Some(Uuid::new_v4()),
+            ..base.clone()
+        };
+        assert!(matches!(
+            db.create_image(&orphan).await,
+            Err(SnowOwlError::ImageNotFound(_))
+        ));
+
+        // A deployment of the delta resolves the base first, then the delta
+        let deployment = Deployment::pending(machines[20].id, delta.id, Vec::new());
+        db.create_deployment(&deployment).await.unwrap();
+        let chain = db.get_image_chain(deployment.image_id).await.unwrap();
+        let ids: Vec<Uuid> = chain.iter().map(|image| image.id).collect();
+        assert_eq!(ids, [base.id, delta.id]);
+        assert_eq!(db.get_image_chain(base.id).await.unwrap().len(), 1);
+
+        // The base cannot go while the delta depends on it
+        assert!(matches!(
+            db.delete_image(base.id).await,
+            Err(SnowOwlError::ImageInUse(message)) if message.contains("server-2022-iis")
+        ));
+        assert!(db.get_image_by_id(base.id).await.unwrap().is_some());
+
+        sqlx::query("DELETE FROM deployments WHERE image_id = $1")
+            .bind(delta.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.delete_image(delta.id).await.unwrap();
+        db.delete_image(base.id).await.unwrap();
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_delete_image_with_history_archives() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "server-2019".to_string(),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/var/lib/snow-owl/images/server2019.wim".into(),
+            size_bytes: 1 << 30,
+            created_at: Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&image).await.unwrap();
+        let deployment = Deployment::pending(machines[0].id, image.id, Vec::new());
+        db.create_deployment(&deployment).await.unwrap();
+
+        // An in-progress deployment blocks the delete and is named in the reason
+        assert!(matches!(
+            db.delete_image(image.id).await,
+            Err(SnowOwlError::ImageInUse(message))
+                if message.contains(&deployment.id.to_string())
+        ));
+
+        // Once it finishes, the image is archived instead of removed
+        db.update_deployment_status(deployment.id, DeploymentStatus::Completed, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.delete_image(image.id).await.unwrap(),
+            ImageDeletion::Archived
+        );
+        assert!(db.get_image_by_name("server-2019").await.unwrap().is_none());
+        assert!(
+            !db.list_images()
+                .await
+                .unwrap()
+                .iter()
+                .any(|i| i.id == image.id)
+        );
+        let archived = db.get_image_by_id(image.id).await.unwrap().unwrap();
+        assert!(archived.deleted_at.is_some());
+        assert!(
+            db.list_images_including_deleted()
+                .await
+                .unwrap()
+                .iter()
+                .any(|i| i.id == image.id && i.deleted_at.is_some())
+        );
+        assert!(
+            db.get_deployment_by_id(deployment.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Archived images cannot be deployed or deleted again
+        assert!(matches!(
+            db.get_image_chain(image.id).await,
+            Err(SnowOwlError::ImageNotFound(_))
+        ));
+        assert!(matches!(
+            db.delete_image(image.id).await,
+            Err(SnowOwlError::ImageNotFound(_))
+        ));
+
+        // Restoring brings it back everywhere
+        db.restore_image(image.id).await.unwrap();
+        let restored = db.get_image_by_name("server-2019").await.unwrap().unwrap();
+        assert_eq!(restored.id, image.id);
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(db.get_image_chain(image.id).await.unwrap().len(), 1);
+
+        // Without history the record is removed outright
+        let unused = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "unused".to_string(),
+            ..image.clone()
+        };
+        db.create_image(&unused).await.unwrap();
+        assert_eq!(
+            db.delete_image(unused.id).await.unwrap(),
+            ImageDeletion::Removed
+        );
+        assert!(db.get_image_by_id(unused.id).await.unwrap().is_none());
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_archived_image_name_reuse() {
+        let test = TestDb::new().await;
+        let machines = seed(&test.db).await;
+        let db = &test.db;
+
+        let original = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "win11-gold".to_string(),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/var/lib/snow-owl/images/win11-v1.wim".into(),
+            size_bytes: 1 << 30,
+            created_at: Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&original).await.unwrap();
+        let deployment = Deployment::pending(machines[0].id, original.id, Vec::new());
+        db.create_deployment(&deployment).await.unwrap();
+        db.update_deployment_status(deployment.id, DeploymentStatus::Failed, None)
+            .await
+            .unwrap();
+
+        // Live names stay unique
+        let duplicate = WindowsImage {
+            id: Uuid::new_v4(),
+            file_path: "/var/lib/snow-owl/images/win11-dup.wim".into(),
+            ..original.clone()
+        };
+        assert!(matches!(
+            db.create_image(&duplicate).await,
+            Err(SnowOwlError::ImageNameTaken(name)) if name == "win11-gold"
+        ));
+
+        // Archiving frees the name for a replacement
+        assert_eq!(
+            db.delete_image(original.id).await.unwrap(),
+            ImageDeletion::Archived
+        );
+        let replacement = WindowsImage {
+            id: Uuid::new_v4(),
+            file_path: "/var/lib/snow-owl/images/win11-v2.wim".into(),
+            ..original.clone()
+        };
+        db.create_image(&replacement).await.unwrap();
+        let by_name = db.get_image_by_name("win11-gold").await.unwrap().unwrap();
+        assert_eq!(by_name.id, replacement.id);
+
+        // The archived image cannot come back while the replacement holds its name
+        assert!(matches!(
+            db.restore_image(original.id).await,
+            Err(SnowOwlError::ImageNameTaken(_))
+        ));
+        assert!(
+            db.get_image_by_id(original.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .deleted_at
+                .is_some()
+        );
+
+        // Nor can a new delta be based on it
+        let delta = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "win11-gold-office".to_string(),
+            parent_image_id: Some(original.id),
+            ..original.clone()
+        };
+        assert!(matches!(
+            db.create_image(&delta).await,
+            Err(SnowOwlError::ImageNotFound(_))
+        ));
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_audit_sink_inserts_rows() {
+        let test = TestDb::new().await;
+        let image_id = Uuid::new_v4();
+        let records = [
+            AuditRecord::new("image.delete", true)
+                .with_resource_id("image", image_id)
+                .with_ip("10.0.0.7".parse().unwrap()),
+            AuditRecord::failure("sftp.path_traversal", "outside root")
+                .with_resource("path", "/../etc/shadow")
+                .with_username("deploy"),
+        ];
+        for record in &records {
+            AuditSink::record(&test.db, record).await.unwrap();
+        }
+
+        type Row = (
+            String,
+            Option<Uuid>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            bool,
+        );
+        let rows: Vec<Row> = sqlx::query_as(
+            "SELECT action, resource_id, resource, username, host(ip_address), success \
+             FROM audit_log ORDER BY created_at",
+        )
+        .fetch_all(&test.db.pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (
+                    "image.delete".into(),
+                    Some(image_id),
+                    None,
+                    None,
+                    Some("10.0.0.7".into()),
+                    true
+                ),
+                (
+                    "sftp.path_traversal".into(),
+                    None,
+                    Some("/../etc/shadow".into()),
+                    Some("deploy".into()),
+                    None,
+                    false
+                ),
+            ]
+        );
+
+        test.cleanup().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_audit_log_query_filters_and_prune() {
+        let test = TestDb::new().await;
+        let db = &test.db;
+        let admin = User {
+            id: Uuid::new_v4(),
+            username: "admin".to_string(),
+            role: UserRole::Admin,
+            created_at: Utc::now(),
+            last_login: None,
+        };
+        db.create_user(&admin).await.unwrap();
+
+        // Oldest first: a 100-day-old login failure, then one event a day;
+        // microseconds, as PostgreSQL stores them, so records compare equal
+        let now = Utc::now().trunc_subsecs(6);
+        let at = |days: i64, record: AuditRecord| AuditRecord {
+            created_at: now - Duration::days(days),
+            ..record
+        };
+        let events = [
+            at(100, AuditRecord::failure("auth.api_key", "missing api key")),
+            at(
+                4,
+                AuditRecord::new("image.create", true)
+                    .with_resource_id("image", Uuid::new_v4())
+                    .with_user_id(admin.id),
+            ),
+            at(
+                3,
+                AuditRecord::new("image.delete", true)
+                    .with_resource_id("image", Uuid::new_v4())
+                    .with_user_id(admin.id),
+            ),
+            at(
+                2,
+                AuditRecord::failure("sftp.path_traversal", "outside root")
+                    .with_resource("path", "/../etc/shadow")
+                    .with_username("deploy"),
+            ),
+            at(
+                1,
+                AuditRecord::failure("image.delete", "image in use")
+                    .with_resource_id("image", Uuid::new_v4())
+                    .with_user_id(admin.id),
+            ),
+        ];
+        for event in &events {
+            db.append_audit_event(event).await.unwrap();
+        }
+
+        let query = |filter: AuditFilter| async move {
+            let (entries, total) = db.query_audit_log(0, 50, &filter).await.unwrap();
+            let actions: Vec<_> = entries.into_iter().map(|e| e.record.action).collect();
+            (actions, total)
+        };
+
+        // Newest first, everything by default
+        let (actions, total) = query(AuditFilter::default()).await;
+        assert_eq!(total, 5);
+        assert_eq!(
+            actions,
+            [
+                "image.delete",
+                "sftp.path_traversal",
+                "image.delete",
+                "image.create",
+                "auth.api_key"
+            ]
+        );
+
+        let (actions, _) = query(AuditFilter {
+            user_id: Some(admin.id),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["image.delete", "image.delete", "image.create"]);
+
+        let (actions, _) = query(AuditFilter {
+            username: Some("deploy".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["sftp.path_traversal"]);
+
+        // Prefixes match literally, not as LIKE wildcards
+        let (actions, _) = query(AuditFilter {
+            action_prefix: Some("image.".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["image.delete", "image.delete", "image.create"]);
+        let (_, total) = query(AuditFilter {
+            action_prefix: Some("%".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(total, 0);
+
+        let (actions, _) = query(AuditFilter {
+            resource_type: Some("path".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["sftp.path_traversal"]);
+
+        let (actions, _) = query(AuditFilter {
+            success: Some(false),
+            action_prefix: Some("image.".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["image.delete"]);
+
+        // `from` is inclusive, `to` exclusive
+        let (actions, _) = query(AuditFilter {
+            from: Some(events[1].created_at),
+            to: Some(events[3].created_at),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(actions, ["image.delete", "image.create"]);
+
+        // Pages cut the newest-first list
+        let (page, total) = db
+            .query_audit_log(2, 2, &AuditFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(total, 5);
+        let page: Vec<_> = page.iter().map(|e| e.record.clone()).collect();
+        assert_eq!(page, [events[2].clone(), events[1].clone()]);
+
+        // Retention removes only records older than the cutoff
+        assert_eq!(
+            db.prune_audit_log(now - Duration::days(90)).await.unwrap(),
+            1
+        );
+        let (actions, total) = query(AuditFilter::default()).await;
+        assert_eq!(total, 4);
+        assert!(!actions.contains(&"auth.api_key".to_string()));
+        assert_eq!(
+            db.prune_audit_log(now - Duration::days(90)).await.unwrap(),
+            0
+        );
+
+        test.cleanup().await;
+    }
+}
@@ -1,13 +1,25 @@
 use axum::{
-    Json,
-    extract::{Path, State},
-    http::StatusCode,
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
-use snow_owl_core::{Deployment, DeploymentStatus, ImageType, Machine, WindowsImage};
+use snow_owl_core::{
+    AuditRecord, BulkDeployment, Deployment, DeploymentFilter, DeploymentSort, DeploymentStatus,
+    ImageFilter, ImageSort, ImageType, MacAddress, Machine, MachineFilter, MachineSort, Page,
+    PageRequest, SnowOwlError, SortOrder, WindowsImage, deserialize_limit,
+};
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::AppState;
+use crate::DryRunBundle;
+use crate::auth::AuthUser;
+use crate::events::DeploymentEvent;
+use crate::idempotency::{IdempotentRequest, StoredResponse, run_idempotent};
 
 // Response types
 #[derive(Serialize)]
@@ -36,18 +48,123 @@ impl<T> ApiResponse<T> {
 }
 
 // Request types
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct CreateImageRequest {
     pub name: String,
     pub description: Option<String>,
     pub image_type: ImageType,
     pub file_path: String,
+    /// Base image this one is a differential image on
+    #[serde(default)]
+    pub parent_image_id: Option<Uuid>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct CreateDeploymentRequest {
     pub machine_id: Uuid,
     pub image_id: Uuid,
+    /// Driver packs to inject, in order
+    #[serde(default)]
+    pub driver_pack_ids: Vec<Uuid>,
+}
+
+/// One image deployed to a machine group or an explicit list of machines
+#[derive(Serialize, Deserialize)]
+pub struct BulkDeploymentRequest {
+    /// Group name or ID; mutually exclusive with `machine_ids`
+    pub group: Option<String>,
+    #[serde(default)]
+    pub machine_ids: Vec<Uuid>,
+    pub image_id: Uuid,
+    /// Driver packs to inject on every machine, in order
+    #[serde(default)]
+    pub driver_pack_ids: Vec<Uuid>,
+}
+
+/// Sent by the WinPE agent once the machine has booted
+#[derive(Serialize, Deserialize)]
+pub struct RegisterMachineRequest {
+    pub mac: String,
+    pub hostname: Option<String>,
+    pub ip: Option<IpAddr>,
+    /// `registration-token` from the machine's iPXE boot parameters
+    pub token: String,
+}
+
+/// Machines a bulk request names
+#[derive(Debug, PartialEq)]
+enum BulkTarget<'a> {
+    Group(&'a str),
+    Machines(&'a [Uuid]),
+}
+
+impl BulkDeploymentRequest {
+    /// The request's target, requiring exactly one of `group` and `machine_ids`
+    fn target(&self) -> Result<BulkTarget<'_>, String> {
+        match (self.group.as_deref(), self.machine_ids.is_empty()) {
+            (Some(group), true) => Ok(BulkTarget::Group(group)),
+            (None, false) => Ok(BulkTarget::Machines(&self.machine_ids)),
+            (Some(_), false) => Err("Specify either group or machine_ids, not both".to_string()),
+            (None, true) => Err("Specify a group or at least one machine ID".to_string()),
+        }
+    }
+}
+
+// List query parameters (`?page=&per_page=` or `?limit=&offset=`, then
+// `?q=&sort=&order=` plus filters)
+#[derive(Debug, Default, Deserialize)]
+pub struct MachineListQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// Page size, 1 to `MAX_PER_PAGE`; with `offset`, replaces `page`/`per_page`
+    #[serde(default, deserialize_with = "deserialize_limit")]
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    /// Hostname substring
+    pub q: Option<String>,
+    #[serde(default)]
+    pub sort: MachineSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImageListQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// Page size, 1 to `MAX_PER_PAGE`; with `offset`, replaces `page`/`per_page`
+    #[serde(default, deserialize_with = "deserialize_limit")]
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    /// Name substring
+    pub q: Option<String>,
+    pub image_type: Option<ImageType>,
+    #[serde(default)]
+    pub sort: ImageSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeploymentListQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// Page size, 1 to `MAX_PER_PAGE`; with `offset`, replaces `page`/`per_page`
+    #[serde(default, deserialize_with = "deserialize_limit")]
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    pub status: Option<DeploymentStatus>,
+    pub machine_id: Option<Uuid>,
+    pub image_id: Option<Uuid>,
+    #[serde(default)]
+    pub sort: DeploymentSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// Treat `?q=` the same as no search term
+fn search_term(q: Option<String>) -> Option<String> {
+    q.filter(|q| !q.is_empty())
 }
 
 #[derive(Deserialize)]
@@ -56,12 +173,58 @@ pub struct UpdateDeploymentStatusRequest {
     pub error_message: Option<String>,
 }
 
+/// Run a resource-creating handler, honouring an `Idempotency-Key` header
+///
+/// Without the header the handler runs as usual. With it, a retry replays
+/// the first response instead of creating the resource again.
+///
+/// NIST Controls:
+/// - SI-10: Information Input Validation (retry-safe resource creation)
+async fn idempotent<T, F, Fut>(
+    state: &AppState,
+    auth: Option<&AuthUser>,
+    headers: &HeaderMap,
+    endpoint: &'static str,
+    body: &impl Serialize,
+    handler: F,
+) -> Response
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Json<ApiResponse<T>>, StatusCode>>,
+{
+    let request = match IdempotentRequest::from_headers(headers, auth, endpoint, body) {
+        Ok(Some(request)) => request,
+        Ok(None) => return handler().await.into_response(),
+        Err(status) => return status.into_response(),
+    };
+
+    let retention = Duration::from_secs(state.config.idempotency_retention_secs);
+    run_idempotent(state.db.clone(), &request, retention, || async {
+        StoredResponse::from_result(handler().await)
+    })
+    .await
+    .into_response()
+}
+
 // Machine handlers
 pub async fn list_machines(
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<Machine>>>, StatusCode> {
-    match state.db.list_machines().await {
-        Ok(machines) => Ok(Json(ApiResponse::ok(machines))),
+    Query(query): Query<MachineListQuery>,
+) -> Result<Json<ApiResponse<Page<Machine>>>, StatusCode> {
+    let request = PageRequest::from_query(query.page, query.per_page, query.limit, query.offset);
+    let filter = MachineFilter {
+        hostname: search_term(query.q),
+        sort: query.sort,
+        order: query.order,
+    };
+
+    match state
+        .db
+        .list_machines_paged(request.offset(), request.limit(), &filter)
+        .await
+    {
+        Ok((machines, total)) => Ok(Json(ApiResponse::ok(Page::new(machines, total, request)))),
         Err(e) => {
             tracing::error!("Failed to list machines: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -83,12 +246,132 @@ pub async fn get_machine(
     }
 }
 
+/// Register a machine booted into WinPE, recording its hostname and IP
+///
+/// The agent presents the registration token of the deployment it boots
+/// for, from a machine with that deployment's MAC address. The first
+/// registration moves a pending deployment to booting; later ones only
+/// refresh the machine record. Omitted fields keep their stored values.
+///
+/// NIST Controls:
+/// - IA-3: Device Identification and Authentication (per-deployment token)
+/// - CM-8: Information System Component Inventory
+/// - AU-2: Audit Events (rejected tokens answered 401 and audited)
+pub async fn register_machine(
+    State(state): State<AppState>,
+    Json(req): Json<RegisterMachineRequest>,
+) -> Result<Json<ApiResponse<Machine>>, StatusCode> {
+    let mac: MacAddress = req.mac.parse().map_err(|e| {
+        tracing::warn!("Invalid MAC address {} in registration: {}", req.mac, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    // NIST SI-10: A DNS name is at most 253 characters
+    if req.hostname.as_ref().is_some_and(|hostname| {
+        hostname.is_empty() || hostname.len() > 253 || hostname.chars().any(char::is_control)
+    }) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let deployment = state
+        .db
+        .get_deployment_by_registration_token(&req.token)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to look up registration token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let machine = match &deployment {
+        Some(deployment) => state
+            .db
+            .get_machine_by_id(deployment.machine_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get machine: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+        None => None,
+    };
+
+    let (deployment, mut machine) = match (deployment, machine) {
+        (Some(deployment), Some(machine))
+            if deployment.is_active() && machine.mac_address == mac =>
+        {
+            (deployment, machine)
+        }
+        (deployment, _) => {
+            let reason = match deployment {
+                None => "unknown registration token",
+                Some(deployment) if !deployment.is_active() => {
+                    "registration token of a finished deployment"
+                }
+                Some(_) => "registration token issued to another machine",
+            };
+            tracing::warn!("Rejected registration of {}: {}", mac, reason);
+            let mut record = AuditRecord::failure("machine.register", reason)
+                .with_resource("machine", mac.to_string());
+            if let Some(ip) = req.ip {
+                record = record.with_ip(ip);
+            }
+            state.audit(record);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    if req.hostname.is_some() {
+        machine.hostname = req.hostname;
+    }
+    if req.ip.is_some() {
+        machine.ip_address = req.ip;
+    }
+    machine.last_seen = chrono::Utc::now();
+    if let Err(e) = state.db.create_or_update_machine(&machine).await {
+        tracing::error!("Failed to update machine: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    if deployment.status == DeploymentStatus::Pending
+        && let Err(e) = state
+            .set_deployment_status(deployment.id, DeploymentStatus::Booting, None)
+            .await
+    {
+        tracing::error!("Failed to update deployment status: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    tracing::info!(
+        "Machine {} registered for deployment {}",
+        mac,
+        deployment.id
+    );
+    let mut record =
+        AuditRecord::new("machine.register", true).with_resource_id("machine", machine.id);
+    if let Some(ip) = machine.ip_address {
+        record = record.with_ip(ip);
+    }
+    state.audit(record);
+
+    Ok(Json(ApiResponse::ok(machine)))
+}
+
 // Image handlers
 pub async fn list_images(
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<WindowsImage>>>, StatusCode> {
-    match state.db.list_images().await {
-        Ok(images) => Ok(Json(ApiResponse::ok(images))),
+    Query(query): Query<ImageListQuery>,
+) -> Result<Json<ApiResponse<Page<WindowsImage>>>, StatusCode> {
+    let request = PageRequest::from_query(query.page, query.per_page, query.limit, query.offset);
+    let filter = ImageFilter {
+        name: search_term(query.q),
+        image_type: query.image_type,
+        sort: query.sort,
+        order: query.order,
+    };
+
+    match state
+        .db
+        .list_images_paged(request.offset(), request.limit(), &filter)
+        .await
+    {
+        Ok((images, total)) => Ok(Json(ApiResponse::ok(Page::new(images, total, request)))),
         Err(e) => {
             tracing::error!("Failed to list images: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -112,7 +395,26 @@ pub async fn get_image(
 
 pub async fn create_image(
     State(state): State<AppState>,
+    auth: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
     Json(req): Json<CreateImageRequest>,
+) -> Response {
+    let auth = auth.map(|Extension(auth)| auth);
+    let body = serde_json::to_value(&req).unwrap_or_default();
+    idempotent(
+        &state,
+        auth.as_ref(),
+        &headers,
+        "POST /api/images",
+        &body,
+        || register_image(&state, req),
+    )
+    .await
+}
+
+async fn register_image(
+    state: &AppState,
+    req: CreateImageRequest,
 ) -> Result<Json<ApiResponse<WindowsImage>>, StatusCode> {
     // Validate file exists
     let file_path = std::path::PathBuf::from(&req.file_path);
@@ -133,6 +435,39 @@ pub async fn create_image(
         }
     };
 
+    // NIST SI-10: Record what the headers say and reject a mismatched type
+    let header_path = file_path.clone();
+    let image_metadata = match tokio::task::spawn_blocking(move || {
+        snow_owl_core::image_metadata::read_image_metadata_file(&header_path)
+    })
+    .await
+    {
+        Ok(Ok(image_metadata)) => image_metadata,
+        Ok(Err(e)) => {
+            return Ok(Json(ApiResponse::error(format!(
+                "Failed to read image headers: {}",
+                e
+            ))));
+        }
+        Err(e) => {
+            tracing::error!("Image header inspection panicked: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    if let Err(e) =
+        image_metadata.check_declared_type(req.image_type, state.config.strict_image_validation)
+    {
+        tracing::warn!("Rejecting image registration for {}: {}", req.file_path, e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(parse_error) = &image_metadata.parse_error {
+        tracing::warn!(
+            "Registering {} without full metadata: {}",
+            req.file_path,
+            parse_error
+        );
+    }
+
     let image = WindowsImage {
         id: Uuid::new_v4(),
         name: req.name,
@@ -142,10 +477,16 @@ pub async fn create_image(
         size_bytes: metadata.len(),
         created_at: chrono::Utc::now(),
         checksum: None, // TODO: Calculate checksum
+        metadata: Some(image_metadata),
+        parent_image_id: req.parent_image_id,
+        deleted_at: None,
     };
 
     match state.db.create_image(&image).await {
         Ok(_) => Ok(Json(ApiResponse::ok(image))),
+        Err(e @ (SnowOwlError::ImageNotFound(_) | SnowOwlError::ImageNameTaken(_))) => {
+            Ok(Json(ApiResponse::error(e.to_string())))
+        }
         Err(e) => {
             tracing::error!("Failed to create image: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -153,15 +494,47 @@ pub async fn create_image(
     }
 }
 
+/// Delete an image
+///
+/// An image that deployment history references is archived rather than
+/// removed. Answers 409 Conflict, with the reason in the body, while
+/// differential images are based on it or deployments of it are in progress.
+///
+/// NIST Controls:
+/// - AU-2: Audit Events (destructive change recorded in the audit trail)
 pub async fn delete_image(
     State(state): State<AppState>,
+    auth: Option<Extension<AuthUser>>,
     Path(id): Path<Uuid>,
-) -> Result<Json<ApiResponse<()>>, StatusCode> {
-    match state.db.delete_image(id).await {
-        Ok(_) => Ok(Json(ApiResponse::ok(()))),
+) -> Response {
+    let result = state.db.delete_image(id).await;
+
+    let mut record = AuditRecord::new("image.delete", result.is_ok()).with_resource_id("image", id);
+    if let Some(Extension(auth)) = auth {
+        record = record
+            .with_user_id(auth.user.id)
+            .with_username(auth.user.username);
+    }
+    if let Err(e) = &result {
+        record = record.with_error(e.to_string());
+    }
+    state.audit(record);
+
+    match result {
+        Ok(deletion) => Json(ApiResponse::ok(deletion)).into_response(),
+        Err(SnowOwlError::ImageNotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        // Dependent images or in-progress deployments
+        Err(e @ SnowOwlError::ImageInUse(_)) => {
+            tracing::warn!("Refusing to delete image: {}", e);
+            (
+                StatusCode::CONFLICT,
+                Json(ApiResponse::<()>::error(e.to_string())),
+            )
+                .into_response()
+        }
         Err(e) => {
             tracing::error!("Failed to delete image: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
@@ -169,9 +542,27 @@ pub async fn delete_image(
 // Deployment handlers
 pub async fn list_deployments(
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<Deployment>>>, StatusCode> {
-    match state.db.list_deployments().await {
-        Ok(deployments) => Ok(Json(ApiResponse::ok(deployments))),
+    Query(query): Query<DeploymentListQuery>,
+) -> Result<Json<ApiResponse<Page<Deployment>>>, StatusCode> {
+    let request = PageRequest::from_query(query.page, query.per_page, query.limit, query.offset);
+    let filter = DeploymentFilter {
+        status: query.status,
+        machine_id: query.machine_id,
+        image_id: query.image_id,
+        sort: query.sort,
+        order: query.order,
+    };
+
+    match state
+        .db
+        .list_deployments_paged(request.offset(), request.limit(), &filter)
+        .await
+    {
+        Ok((deployments, total)) => Ok(Json(ApiResponse::ok(Page::new(
+            deployments,
+            total,
+            request,
+        )))),
         Err(e) => {
             tracing::error!("Failed to list deployments: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -195,7 +586,26 @@ pub async fn get_deployment(
 
 pub async fn create_deployment(
     State(state): State<AppState>,
+    auth: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
     Json(req): Json<CreateDeploymentRequest>,
+) -> Response {
+    let auth = auth.map(|Extension(auth)| auth);
+    let body = serde_json::to_value(&req).unwrap_or_default();
+    idempotent(
+        &state,
+        auth.as_ref(),
+        &headers,
+        "POST /api/deployments",
+        &body,
+        || start_deployment(&state, req),
+    )
+    .await
+}
+
+async fn start_deployment(
+    state: &AppState,
+    req: CreateDeploymentRequest,
 ) -> Result<Json<ApiResponse<Deployment>>, StatusCode> {
     // Validate machine exists
     if state
@@ -211,32 +621,28 @@ pub async fn create_deployment(
         ))));
     }
 
-    // Validate image exists
-    if state
-        .db
-        .get_image_by_id(req.image_id)
-        .await
-        .unwrap()
-        .is_none()
-    {
+    // Validate the image and any base images it is a delta on
+    if let Some(error) = unresolvable_image(state, req.image_id).await? {
+        return Ok(Json(ApiResponse::error(error)));
+    }
+
+    // Validate driver packs exist
+    if let Some(id) = missing_driver_pack(state, &req.driver_pack_ids).await? {
         return Ok(Json(ApiResponse::error(format!(
-            "Image not found: {}",
-            req.image_id
+            "Driver pack not found: {}",
+            id
         ))));
     }
 
-    let deployment = Deployment {
-        id: Uuid::new_v4(),
-        machine_id: req.machine_id,
-        image_id: req.image_id,
-        status: DeploymentStatus::Pending,
-        started_at: chrono::Utc::now(),
-        completed_at: None,
-        error_message: None,
-    };
+    let deployment = Deployment::pending(req.machine_id, req.image_id, req.driver_pack_ids);
 
     match state.db.create_deployment(&deployment).await {
-        Ok(_) => Ok(Json(ApiResponse::ok(deployment))),
+        Ok(_) => {
+            state.events.publish(DeploymentEvent::Created {
+                deployment: deployment.clone(),
+            });
+            Ok(Json(ApiResponse::ok(deployment)))
+        }
         Err(e) => {
             tracing::error!("Failed to create deployment: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -244,14 +650,184 @@ pub async fn create_deployment(
     }
 }
 
+/// Why `image_id` cannot be deployed, if its chain of base images does not resolve
+async fn unresolvable_image(
+    state: &AppState,
+    image_id: Uuid,
+) -> Result<Option<String>, StatusCode> {
+    match state.db.get_image_chain(image_id).await {
+        Ok(_) => Ok(None),
+        Err(e @ (SnowOwlError::ImageNotFound(_) | SnowOwlError::InvalidImage(_))) => {
+            Ok(Some(e.to_string()))
+        }
+        Err(e) => {
+            tracing::error!("Failed to resolve image chain: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// First of `ids` with no driver pack, if any
+async fn missing_driver_pack(state: &AppState, ids: &[Uuid]) -> Result<Option<Uuid>, StatusCode> {
+    for id in ids {
+        match state.db.get_driver_pack_by_id(*id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(Some(*id)),
+            Err(e) => {
+                tracing::error!("Failed to get driver pack: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Deploy one image to every machine in a group or list
+///
+/// All deployments are created in one transaction. Machines that already
+/// have an active deployment are skipped and listed in the response instead
+/// of failing the batch.
+///
+/// NIST Controls:
+/// - CM-3: Configuration Change Control (all-or-nothing batch)
+/// - SI-10: Information Input Validation (every target checked first)
+pub async fn create_bulk_deployment(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+    Json(req): Json<BulkDeploymentRequest>,
+) -> Response {
+    let auth = auth.map(|Extension(auth)| auth);
+    let body = serde_json::to_value(&req).unwrap_or_default();
+    idempotent(
+        &state,
+        auth.as_ref(),
+        &headers,
+        "POST /api/deployments/bulk",
+        &body,
+        || start_bulk_deployment(&state, req),
+    )
+    .await
+}
+
+async fn start_bulk_deployment(
+    state: &AppState,
+    req: BulkDeploymentRequest,
+) -> Result<Json<ApiResponse<BulkDeployment>>, StatusCode> {
+    let machine_ids = match req.target() {
+        Err(e) => return Ok(Json(ApiResponse::error(e))),
+        Ok(BulkTarget::Group(name_or_id)) => {
+            let group = match Uuid::parse_str(name_or_id) {
+                Ok(id) => state.db.get_machine_group_by_id(id).await,
+                Err(_) => state.db.get_machine_group_by_name(name_or_id).await,
+            };
+            let group = match group {
+                Ok(Some(group)) => group,
+                Ok(None) => {
+                    return Ok(Json(ApiResponse::error(format!(
+                        "Machine group not found: {}",
+                        name_or_id
+                    ))));
+                }
+                Err(e) => {
+                    tracing::error!("Failed to get machine group: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            };
+            match state.db.list_group_machines(group.id).await {
+                Ok(machines) => machines.into_iter().map(|machine| machine.id).collect(),
+                Err(e) => {
+                    tracing::error!("Failed to list group machines: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            }
+        }
+        Ok(BulkTarget::Machines(ids)) => {
+            for id in ids {
+                match state.db.get_machine_by_id(*id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        return Ok(Json(ApiResponse::error(format!(
+                            "Machine not found: {}",
+                            id
+                        ))));
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to get machine: {}", e);
+                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    }
+                }
+            }
+            ids.to_vec()
+        }
+    };
+
+    if let Some(error) = unresolvable_image(state, req.image_id).await? {
+        return Ok(Json(ApiResponse::error(error)));
+    }
+
+    if let Some(id) = missing_driver_pack(state, &req.driver_pack_ids).await? {
+        return Ok(Json(ApiResponse::error(format!(
+            "Driver pack not found: {}",
+            id
+        ))));
+    }
+
+    match state
+        .db
+        .create_deployments_bulk(&machine_ids, req.image_id, &req.driver_pack_ids)
+        .await
+    {
+        Ok(result) => {
+            tracing::info!(
+                "Bulk deployment of image {}: {} created, {} skipped",
+                req.image_id,
+                result.created.len(),
+                result.skipped.len()
+            );
+            for deployment in &result.created {
+                state.events.publish(DeploymentEvent::Created {
+                    deployment: deployment.clone(),
+                });
+            }
+            Ok(Json(ApiResponse::ok(result)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create bulk deployment: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Render what a deployment would serve, without creating it
+///
+/// Takes the same payload as `POST /api/deployments`.
+pub async fn dry_run_deployment(
+    State(state): State<AppState>,
+    Json(req): Json<CreateDeploymentRequest>,
+) -> Result<Json<ApiResponse<DryRunBundle>>, StatusCode> {
+    match crate::dry_run(&state.db, &state.config, &req).await {
+        Ok(bundle) => Ok(Json(ApiResponse::ok(bundle))),
+        Err(
+            e @ (SnowOwlError::MachineNotFound(_)
+            | SnowOwlError::ImageNotFound(_)
+            | SnowOwlError::InvalidImage(_)
+            | SnowOwlError::DriverPackNotFound(_)),
+        ) => Ok(Json(ApiResponse::error(e.to_string()))),
+        Err(e) => {
+            tracing::error!("Deployment dry-run failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 pub async fn update_deployment_status(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
     Json(req): Json<UpdateDeploymentStatusRequest>,
 ) -> Result<Json<ApiResponse<()>>, StatusCode> {
     match state
-        .db
-        .update_deployment_status(id, req.status, req.error_message)
+        .set_deployment_status(id, req.status, req.error_message)
         .await
     {
         Ok(_) => Ok(Json(ApiResponse::ok(()))),
@@ -261,3 +837,447 @@ pub async fn update_deployment_status(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Uri;
+    use snow_owl_core::{AuditQueue, AuditSink, ServerConfig, User, UserRole};
+    use snow_owl_db::Database;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct MemorySink {
+        records: Arc<Mutex<Vec<AuditRecord>>>,
+    }
+
+    impl AuditSink for MemorySink {
+        async fn record(&self, record: &AuditRecord) -> snow_owl_core::Result<()> {
+            self.records.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    fn query<T: serde::de::DeserializeOwned>(uri: &str) -> Option<T> {
+        Query::<T>::try_from_uri(&uri.parse::<Uri>().unwrap())
+            .ok()
+            .map(|Query(query)| query)
+    }
+
+    #[test]
+    fn test_list_query_parsing() {
+        let machines: MachineListQuery =
+            query("/api/machines?page=2&per_page=1000&q=lab&sort=hostname&order=asc").unwrap();
+        let request = PageRequest::new(machines.page, machines.per_page);
+        assert_eq!((request.page, request.per_page), (2, 500));
+        assert_eq!(machines.q.as_deref(), Some("lab"));
+        assert_eq!(machines.sort, MachineSort::Hostname);
+        assert_eq!(machines.order, SortOrder::Asc);
+
+        let deployments: DeploymentListQuery = query("/api/deployments?status=failed").unwrap();
+        assert_eq!(deployments.status, Some(DeploymentStatus::Failed));
+        assert_eq!(deployments.sort, DeploymentSort::StartedAt);
+        assert_eq!(deployments.order, SortOrder::Desc);
+
+        let images: ImageListQuery = query("/api/images?image_type=vhdx&q=").unwrap();
+        assert_eq!(images.image_type, Some(ImageType::Vhdx));
+        assert_eq!(search_term(images.q), None);
+
+        // Sort keys are a closed set
+        assert!(
+            query::<MachineListQuery>("/api/machines?sort=id;DROP%20TABLE%20machines").is_none()
+        );
+        assert!(query::<DeploymentListQuery>("/api/deployments?status=bogus").is_none());
+    }
+
+    #[test]
+    fn test_limit_offset_parsing() {
+        let deployments: DeploymentListQuery =
+            query("/api/deployments?status=failed&limit=20&offset=45").unwrap();
+        let request = PageRequest::from_query(
+            deployments.page,
+            deployments.per_page,
+            deployments.limit,
+            deployments.offset,
+        );
+        assert_eq!((request.offset(), request.limit()), (45, 20));
+        assert_eq!(deployments.status, Some(DeploymentStatus::Failed));
+
+        // Unlike per_page, an out-of-range limit is refused rather than clamped
+        assert!(query::<MachineListQuery>("/api/machines?limit=0").is_none());
+        assert!(query::<ImageListQuery>("/api/images?limit=501").is_none());
+        assert!(query::<ImageListQuery>("/api/images?limit=500").is_some());
+        assert!(query::<MachineListQuery>("/api/machines?offset=-1").is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_list_endpoints_page_by_limit_and_offset() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let app = crate::HttpServer::new(db.clone(), ServerConfig::default()).create_router();
+        let get = |uri: String| async {
+            use tower::ServiceExt;
+            let request = axum::http::Request::get(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+            (status, body)
+        };
+        let ids = |body: &serde_json::Value| -> Vec<String> {
+            body["data"]["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // The shared test database holds other rows; this prefix is unique
+        let prefix = format!("page-{}", Uuid::new_v4().simple());
+        let now = chrono::Utc::now();
+        let mut machines = Vec::new();
+        for i in 0..5i64 {
+            let machine = Machine {
+                id: Uuid::new_v4(),
+                mac_address: MacAddress::new(*Uuid::new_v4().as_bytes().first_chunk().unwrap()),
+                hostname: Some(format!("{}-{}", prefix, i)),
+                ip_address: None,
+                kernel_args: None,
+                last_seen: now - chrono::Duration::minutes(i),
+                created_at: now,
+            };
+            db.create_or_update_machine(&machine).await.unwrap();
+            machines.push(machine);
+        }
+        let mut images = Vec::new();
+        for i in 0..4i64 {
+            let image = WindowsImage {
+                id: Uuid::new_v4(),
+                name: format!("{}-{}", prefix, i),
+                description: None,
+                image_type: if i % 2 == 0 {
+                    ImageType::Wim
+                } else {
+                    ImageType::Vhdx
+                },
+                file_path: format!("/srv/images/{}-{}.img", prefix, i).into(),
+                size_bytes: 0,
+                created_at: now - chrono::Duration::minutes(i),
+                checksum: None,
+                metadata: None,
+                parent_image_id: None,
+                deleted_at: None,
+            };
+            db.create_image(&image).await.unwrap();
+            images.push(image);
+        }
+        // Six deployments of one machine, newest first, every other one failed
+        let mut failed = Vec::new();
+        for i in 0..6i64 {
+            let deployment = Deployment {
+                status: if i % 2 == 0 {
+                    DeploymentStatus::Failed
+                } else {
+                    DeploymentStatus::Completed
+                },
+                started_at: now - chrono::Duration::minutes(i),
+                ..Deployment::pending(machines[0].id, images[0].id, Vec::new())
+            };
+            db.create_deployment(&deployment).await.unwrap();
+            if deployment.status == DeploymentStatus::Failed {
+                failed.push(deployment.id.to_string());
+            }
+        }
+
+        let (status, body) = get(format!("/api/machines?q={}&limit=2&offset=3", prefix)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 5);
+        assert_eq!(body["data"]["offset"], 3);
+        assert_eq!(
+            ids(&body),
+            [machines[3].id.to_string(), machines[4].id.to_string()]
+        );
+
+        let (_, body) = get(format!(
+            "/api/images?q={}&image_type=vhdx&limit=1&offset=1",
+            prefix
+        ))
+        .await;
+        assert_eq!(body["data"]["total"], 2);
+        assert_eq!(ids(&body), [images[3].id.to_string()]);
+
+        let (_, body) = get(format!(
+            "/api/deployments?machine_id={}&status=failed&limit=2&offset=1",
+            machines[0].id
+        ))
+        .await;
+        assert_eq!(body["data"]["total"], 3);
+        assert_eq!(ids(&body), failed[1..]);
+
+        // Past the end is an empty page, not an error
+        let (status, body) = get(format!("/api/machines?q={}&offset=10", prefix)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 5);
+        assert!(ids(&body).is_empty());
+
+        let (status, _) = get(format!("/api/machines?q={}&limit=0", prefix)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        db.close().await;
+    }
+
+    #[test]
+    fn test_bulk_request_needs_exactly_one_target() {
+        let image_id = Uuid::new_v4();
+        let machine_id = Uuid::new_v4();
+        let parse = |body: serde_json::Value| -> BulkDeploymentRequest {
+            serde_json::from_value(body).unwrap()
+        };
+
+        let by_group = parse(serde_json::json!({ "group": "lab", "image_id": image_id }));
+        assert_eq!(by_group.target(), Ok(BulkTarget::Group("lab")));
+
+        let by_ids = parse(serde_json::json!({
+            "machine_ids": [machine_id],
+            "image_id": image_id,
+        }));
+        assert_eq!(by_ids.target(), Ok(BulkTarget::Machines(&[machine_id])));
+
+        let both = parse(serde_json::json!({
+            "group": "lab",
+            "machine_ids": [machine_id],
+            "image_id": image_id,
+        }));
+        assert!(both.target().is_err());
+        let neither = parse(serde_json::json!({ "machine_ids": [], "image_id": image_id }));
+        assert!(neither.target().is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_delete_image_emits_one_audit_event() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: format!("audit-test-{}", Uuid::new_v4().simple()),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/srv/images/audit-test.wim".into(),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&image).await.unwrap();
+
+        let sink = MemorySink::default();
+        let (audit, writer) = AuditQueue::spawn(sink.clone(), 8);
+        let state = AppState {
+            db: db.clone(),
+            config: ServerConfig::default(),
+            audit: Some(audit),
+            metrics: Default::default(),
+            events: Default::default(),
+        };
+        let user = User {
+            id: Uuid::new_v4(),
+            username: "operator".to_string(),
+            role: UserRole::Operator,
+            created_at: chrono::Utc::now(),
+            last_login: None,
+        };
+        let auth = AuthUser {
+            user: user.clone(),
+            api_key_id: None,
+        };
+
+        let response = delete_image(State(state), Some(Extension(auth)), Path(image.id)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"], "removed");
+        writer.await.unwrap();
+        db.close().await;
+
+        let records = sink.records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.action, "image.delete");
+        assert_eq!(record.resource_type.as_deref(), Some("image"));
+        assert_eq!(record.resource_id, Some(image.id));
+        assert_eq!(record.user_id, Some(user.id));
+        assert!(record.success);
+    }
+
+    /// Machine, image and pending deployment for registration tests
+    async fn registration_fixture(db: &Database) -> (Machine, Deployment) {
+        let machine = Machine {
+            id: Uuid::new_v4(),
+            mac_address: MacAddress::new(*Uuid::new_v4().as_bytes().first_chunk().unwrap()),
+            hostname: None,
+            ip_address: None,
+            kernel_args: None,
+            last_seen: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        };
+        db.create_or_update_machine(&machine).await.unwrap();
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: format!("register-test-{}", Uuid::new_v4().simple()),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/srv/images/register-test.wim".into(),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&image).await.unwrap();
+        let deployment = Deployment::pending(machine.id, image.id, Vec::new());
+        db.create_deployment(&deployment).await.unwrap();
+        (machine, deployment)
+    }
+
+    fn registration(mac: MacAddress, hostname: &str, token: &str) -> RegisterMachineRequest {
+        RegisterMachineRequest {
+            mac: mac.to_string(),
+            hostname: Some(hostname.to_string()),
+            ip: Some("192.168.100.50".parse().unwrap()),
+            token: token.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_register_machine_then_reregister() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let (machine, deployment) = registration_fixture(&db).await;
+        let token = deployment.registration_token.clone().unwrap();
+        let state = AppState {
+            db: db.clone(),
+            config: ServerConfig::default(),
+            audit: None,
+            metrics: Default::default(),
+            events: Default::default(),
+        };
+
+        // First registration reports the hostname and starts the deployment
+        let Json(response) = register_machine(
+            State(state.clone()),
+            Json(registration(machine.mac_address, "WINPE-01", &token)),
+        )
+        .await
+        .unwrap();
+        let registered = response.data.unwrap();
+        assert_eq!(registered.id, machine.id);
+        assert_eq!(registered.hostname.as_deref(), Some("WINPE-01"));
+        let stored = db
+            .get_deployment_by_id(deployment.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.status, DeploymentStatus::Booting);
+
+        // Registering again updates the hostname and leaves the status alone
+        db.update_deployment_status(deployment.id, DeploymentStatus::Installing, None)
+            .await
+            .unwrap();
+        let Json(response) = register_machine(
+            State(state),
+            Json(registration(machine.mac_address, "lab-pc-01", &token)),
+        )
+        .await
+        .unwrap();
+        assert!(response.success);
+        let stored = db.get_machine_by_id(machine.id).await.unwrap().unwrap();
+        assert_eq!(stored.hostname.as_deref(), Some("lab-pc-01"));
+        assert!(stored.last_seen > machine.last_seen);
+        let stored = db
+            .get_deployment_by_id(deployment.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.status, DeploymentStatus::Installing);
+
+        // The token is never returned by the API
+        let json = serde_json::to_value(&stored).unwrap();
+        assert!(json.get("registration_token").is_none());
+        db.close().await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_register_machine_rejects_bad_token() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let (machine, deployment) = registration_fixture(&db).await;
+        let (other, _) = registration_fixture(&db).await;
+        let token = deployment.registration_token.clone().unwrap();
+
+        let sink = MemorySink::default();
+        let (audit, writer) = AuditQueue::spawn(sink.clone(), 8);
+        let state = AppState {
+            db: db.clone(),
+            config: ServerConfig::default(),
+            audit: Some(audit),
+            metrics: Default::default(),
+            events: Default::default(),
+        };
+        let register = |req| register_machine(State(state.clone()), Json(req));
+
+        // Unknown token, another machine's token, and a finished deployment's
+        let unknown = registration(machine.mac_address, "rogue", "not-a-token");
+        assert_eq!(
+            register(unknown).await.err(),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        let stolen = registration(other.mac_address, "rogue", &token);
+        assert_eq!(register(stolen).await.err(), Some(StatusCode::UNAUTHORIZED));
+        db.update_deployment_status(deployment.id, DeploymentStatus::Completed, None)
+            .await
+            .unwrap();
+        let expired = registration(machine.mac_address, "rogue", &token);
+        assert_eq!(
+            register(expired).await.err(),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+
+        drop(state);
+        writer.await.unwrap();
+        for id in [machine.id, other.id] {
+            let stored = db.get_machine_by_id(id).await.unwrap().unwrap();
+            assert_eq!(stored.hostname, None);
+        }
+        db.close().await;
+
+        let records = sink.records.lock().unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(
+            records
+                .iter()
+                .all(|record| record.action == "machine.register" && !record.success)
+        );
+        assert_eq!(
+            records[1].error_message.as_deref(),
+            Some("registration token issued to another machine")
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Audit trail review
+//!
+//! `GET /api/audit` reads the shared audit trail back, newest first, so a
+//! compliance review does not need direct database access. With
+//! authentication enabled only Admin users may read it; refused attempts are
+//! themselves recorded.
+//!
+//! NIST Controls:
+//! - AU-6: Audit Review, Analysis, and Reporting
+//! - AU-7: Audit Reduction and Report Generation (filtering)
+//! - AU-9(4): Access by Subset of Privileged Users
+
+use axum::{
+    Extension, Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use snow_owl_core::{
+    AuditEntry, AuditFilter, AuditRecord, Page, PageRequest, ServerConfig, UserRole,
+    deserialize_limit,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::api::ApiResponse;
+use crate::auth::{AuthUser, check_role};
+
+/// `?user=&action=&resource_type=&success=&from=&to=` with
+/// `?page=&per_page=` or `?limit=&offset=`
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditListQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_limit")]
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    /// User ID, or username for records that carry no ID
+    pub user: Option<String>,
+    /// Action prefix
+    pub action: Option<String>,
+    pub resource_type: Option<String>,
+    pub success: Option<bool>,
+    /// RFC 3339 time, inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// RFC 3339 time, exclusive
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditListQuery {
+    fn filter(self) -> AuditFilter {
+        let user = self.user.filter(|user| !user.is_empty());
+        let user_id = user.as_deref().and_then(|user| Uuid::parse_str(user).ok());
+        AuditFilter {
+            user_id,
+            username: user.filter(|_| user_id.is_none()),
+            action_prefix: self.action.filter(|action| !action.is_empty()),
+            resource_type: self.resource_type.filter(|kind| !kind.is_empty()),
+            success: self.success,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+/// Whether the caller may read the audit trail
+///
+/// Without authentication configured the API is open and so is the trail;
+/// with it, only Admin users get through.
+///
+/// NIST Controls:
+/// - AC-3: Access Enforcement
+/// - AC-6: Least Privilege
+fn authorize(config: &ServerConfig, auth: Option<&AuthUser>) -> Result<(), StatusCode> {
+    if !config.auth.as_ref().is_some_and(|auth| auth.enabled) {
+        return Ok(());
+    }
+    match auth {
+        Some(auth) if check_role(&auth.user, UserRole::Admin) => Ok(()),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// List audit records matching the query, newest first
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthUser>>,
+    Query(query): Query<AuditListQuery>,
+) -> Result<Json<ApiResponse<Page<AuditEntry>>>, StatusCode> {
+    let auth = auth.map(|Extension(auth)| auth);
+    if let Err(status) = authorize(&state.config, auth.as_ref()) {
+        // NIST AU-9(4): Refused reads of the trail are recorded in it
+        let mut record = AuditRecord::failure("audit.read", "admin role required");
+        if let Some(auth) = auth {
+            record = record
+                .with_user_id(auth.user.id)
+                .with_username(auth.user.username);
+        }
+        state.audit(record);
+        return Err(status);
+    }
+
+    let request = PageRequest::from_query(query.page, query.per_page, query.limit, query.offset);
+    match state
+        .db
+        .query_audit_log(request.offset(), request.limit(), &query.filter())
+        .await
+    {
+        Ok((entries, total)) => Ok(Json(ApiResponse::ok(Page::new(entries, total, request)))),
+        Err(e) => {
+            tracing::error!("Failed to query audit log: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpServer;
+    use crate::auth::{generate_api_key, hash_api_key};
+    use axum::body::Body;
+    use axum::http::Uri;
+    use snow_owl_core::{ApiKey, AuthConfig, User};
+    use snow_owl_db::Database;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn query(uri: &str) -> Option<AuditListQuery> {
+        Query::<AuditListQuery>::try_from_uri(&uri.parse::<Uri>().unwrap())
+            .ok()
+            .map(|Query(query)| query)
+    }
+
+    fn auth_user(role: UserRole) -> AuthUser {
+        AuthUser {
+            user: User {
+                id: Uuid::new_v4(),
+                username: format!("{}-user", role),
+                role,
+                created_at: Utc::now(),
+                last_login: None,
+            },
+            api_key_id: None,
+        }
+    }
+
+    #[test]
+    fn test_audit_query_parsing() {
+        let id = Uuid::new_v4();
+        let filter = query(&format!(
+            "/api/audit?user={}&action=image.&success=false&from=2026-01-01T00:00:00Z",
+            id
+        ))
+        .unwrap()
+        .filter();
+        assert_eq!(filter.user_id, Some(id));
+        assert_eq!(filter.username, None);
+        assert_eq!(filter.action_prefix.as_deref(), Some("image."));
+        assert_eq!(filter.success, Some(false));
+        assert_eq!(
+            filter.from,
+            Some("2026-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap())
+        );
+        assert_eq!(filter.to, None);
+
+        // Anything that is not a UUID is a username; empty values are ignored
+        let filter = query("/api/audit?user=deploy&action=").unwrap().filter();
+        assert_eq!(filter.user_id, None);
+        assert_eq!(filter.username.as_deref(), Some("deploy"));
+        assert_eq!(filter.action_prefix, None);
+
+        assert!(query("/api/audit?from=yesterday").is_none());
+    }
+
+    #[test]
+    fn test_audit_read_requires_admin_when_auth_enabled() {
+        let open = ServerConfig::default();
+        assert_eq!(authorize(&open, None), Ok(()));
+
+        let secured = ServerConfig {
+            auth: Some(AuthConfig {
+                enabled: true,
+                require_auth: false,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            authorize(&secured, Some(&auth_user(UserRole::Admin))),
+            Ok(())
+        );
+        assert_eq!(
+            authorize(&secured, Some(&auth_user(UserRole::Operator))),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            authorize(&secured, Some(&auth_user(UserRole::ReadOnly))),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(authorize(&secured, None), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_audit_endpoint_filters_for_admins() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+
+        // Keys for an admin and an operator
+        let mut keys = Vec::new();
+        for role in [UserRole::Admin, UserRole::Operator] {
+            let user = User {
+                username: format!("audit-{}-{}", role, Uuid::new_v4().simple()),
+                ..auth_user(role).user
+            };
+            db.create_user(&user).await.unwrap();
+            let key = generate_api_key();
+            db.create_api_key(&ApiKey {
+                id: Uuid::new_v4(),
+                user_id: user.id,
+                name: "audit".to_string(),
+                key_hash: hash_api_key(&key),
+                created_at: Utc::now(),
+                expires_at: None,
+                last_used: None,
+            })
+            .await
+            .unwrap();
+            keys.push(key);
+        }
+
+        // The shared test database holds other rows; this username is unique
+        let username = format!("sftp-{}", Uuid::new_v4().simple());
+        db.append_audit_event(
+            &AuditRecord::new("sftp.upload", true)
+                .with_resource("path", "/winpe/boot.wim")
+                .with_username(&username),
+        )
+        .await
+        .unwrap();
+        db.append_audit_event(
+            &AuditRecord::failure("sftp.path_traversal", "outside root").with_username(&username),
+        )
+        .await
+        .unwrap();
+
+        let config = ServerConfig {
+            auth: Some(AuthConfig {
+                enabled: true,
+                require_auth: true,
+            }),
+            ..Default::default()
+        };
+        let app = HttpServer::new(db.clone(), config).create_router();
+        let get = |uri: String, key: &str| {
+            let request = axum::http::Request::get(uri)
+                .header("authorization", format!("Bearer {}", key))
+                .body(Body::empty())
+                .unwrap();
+            app.clone().oneshot(request)
+        };
+
+        let uri = format!("/api/audit?user={}&action=sftp.", username);
+        let response = get(uri.clone(), &keys[0]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["total"], 2);
+        assert_eq!(body["data"]["items"][0]["action"], "sftp.path_traversal");
+        assert_eq!(body["data"]["items"][1]["resource"], "/winpe/boot.wim");
+
+        let response = get(format!("{}&success=true", uri), &keys[0])
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["total"], 1);
+
+        let response = get(uri, &keys[1]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        db.close().await;
+    }
+}
@@ -8,17 +8,18 @@
 /// - AU-2: Audit Events
 /// - AU-3: Content of Audit Records
 use axum::{
-    extract::{Request, State},
-    http::{StatusCode, header::AUTHORIZATION},
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
     middleware::Next,
     response::Response,
 };
 use sha2::{Digest, Sha256};
-use snow_owl_core::{SnowOwlError, User, UserRole};
-use snow_owl_db::Database;
-use std::sync::Arc;
+use snow_owl_core::{AuditRecord, SnowOwlError, User, UserRole};
+use std::net::SocketAddr;
 use tracing::{info, warn};
 
+use crate::AppState;
+
 /// Authentication state passed through request extensions
 ///
 /// NIST Controls:
@@ -27,6 +28,8 @@ use tracing::{info, warn};
 #[derive(Clone, Debug)]
 pub struct AuthUser {
     pub user: User,
+    /// API key the request authenticated with
+    pub api_key_id: Option<uuid::Uuid>,
 }
 
 /// Generate API key
@@ -55,56 +58,70 @@ pub fn hash_api_key(key: &str) -> String {
     hex::encode(result)
 }
 
+/// Header carrying an API key for clients that can't send `Authorization`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// API key from `Authorization: Bearer <key>` or, failing that, `X-API-Key`
+///
+/// NIST IA-2: Identification and Authentication
+fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    bearer.or_else(|| headers.get(API_KEY_HEADER).and_then(|h| h.to_str().ok()))
+}
+
 /// Authentication middleware
 ///
 /// NIST Controls:
 /// - IA-2: Identification and Authentication
 /// - AC-3: Access Enforcement
 /// - AU-3: Content of Audit Records (log auth attempts)
+/// - AU-2: Audit Events (failures recorded in the shared audit trail)
 pub async fn auth_middleware(
-    State(db): State<Arc<Database>>,
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> std::result::Result<Response, StatusCode> {
-    // NIST IA-2: Extract and validate Authorization header
-    let auth_header = request
-        .headers()
-        .get(AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
-
-    if let Some(auth_value) = auth_header {
-        // NIST IA-2: Support Bearer token format
-        if let Some(token) = auth_value.strip_prefix("Bearer ") {
-            // NIST SC-13: Hash the provided key to compare with stored hash
-            let key_hash = hash_api_key(token);
-
-            // NIST IA-2: Validate API key against database
-            match db.validate_api_key(&key_hash).await {
-                Ok(Some((user, api_key))) => {
-                    // NIST AU-3: Log successful authentication
-                    info!(
-                        "Authenticated user: {} (role: {}) via API key: {}",
-                        user.username, user.role, api_key.name
-                    );
-
-                    // NIST AU-3: Update last used timestamp
-                    let _ = db.update_api_key_last_used(api_key.id).await;
-                    let _ = db.update_user_last_login(user.id).await;
-
-                    // NIST AC-3: Store authenticated user in request extensions
-                    request.extensions_mut().insert(AuthUser { user });
-
-                    // Continue to next middleware/handler
-                    return Ok(next.run(request).await);
-                }
-                Ok(None) => {
-                    // NIST AU-3: Log failed authentication attempt
-                    warn!("Invalid or expired API key");
-                }
-                Err(e) => {
-                    // NIST AU-3: Log authentication errors
-                    warn!("Authentication error: {}", e);
-                }
+    // NIST IA-2: Extract the API key from the Authorization or X-API-Key header
+    let db = &state.db;
+    let mut reason = "missing api key";
+    if let Some(token) = api_key_from_headers(request.headers()) {
+        // NIST SC-13: Hash the provided key to compare with stored hash
+        let key_hash = hash_api_key(token);
+
+        // NIST IA-2: Validate API key against database
+        match db.validate_api_key(&key_hash).await {
+            Ok(Some((user, api_key))) => {
+                // NIST AU-3: Log successful authentication
+                info!(
+                    "Authenticated user: {} (role: {}) via API key: {}",
+                    user.username, user.role, api_key.name
+                );
+
+                // NIST AU-3: Update last used timestamp
+                let _ = db.update_api_key_last_used(api_key.id).await;
+                let _ = db.update_user_last_login(user.id).await;
+
+                // NIST AC-3: Store authenticated user in request extensions
+                request.extensions_mut().insert(AuthUser {
+                    user,
+                    api_key_id: Some(api_key.id),
+                });
+
+                // Continue to next middleware/handler
+                return Ok(next.run(request).await);
+            }
+            Ok(None) => {
+                // NIST AU-3: Log failed authentication attempt
+                warn!("Invalid or expired API key");
+                reason = "invalid or expired api key";
+            }
+            Err(e) => {
+                // NIST AU-3: Log authentication errors
+                warn!("Authentication error: {}", e);
+                reason = "api key lookup failed";
             }
         }
     }
@@ -112,6 +129,12 @@ pub async fn auth_middleware(
     // NIST AC-3: Deny access if authentication fails
     // NIST AU-3: Log unauthorized access attempt
     warn!("Unauthorized access attempt");
+    let mut record = AuditRecord::failure("auth.api_key", reason)
+        .with_resource("endpoint", request.uri().path());
+    if let Some(ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        record = record.with_ip(peer.ip());
+    }
+    state.audit(record);
     Err(StatusCode::UNAUTHORIZED)
 }
 
@@ -120,22 +143,18 @@ pub async fn auth_middleware(
 /// Useful for endpoints that have public access but may have enhanced
 /// functionality when authenticated.
 pub async fn optional_auth_middleware(
-    State(db): State<Arc<Database>>,
+    State(state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Response {
-    let auth_header = request
-        .headers()
-        .get(AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
-
-    if let Some(auth_value) = auth_header
-        && let Some(token) = auth_value.strip_prefix("Bearer ")
-    {
+    if let Some(token) = api_key_from_headers(request.headers()) {
         let key_hash = hash_api_key(token);
 
-        if let Ok(Some((user, _))) = db.validate_api_key(&key_hash).await {
-            request.extensions_mut().insert(AuthUser { user });
+        if let Ok(Some((user, api_key))) = state.db.validate_api_key(&key_hash).await {
+            request.extensions_mut().insert(AuthUser {
+                user,
+                api_key_id: Some(api_key.id),
+            });
         }
     }
 
@@ -173,6 +192,126 @@ pub fn get_auth_user(request: &Request) -> std::result::Result<User, SnowOwlErro
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::HttpServer;
+    use axum::body::Body;
+    use chrono::Utc;
+    use snow_owl_core::{ApiKey, AuthConfig, ServerConfig};
+    use snow_owl_db::Database;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn get_status(
+        app: &axum::Router,
+        uri: &str,
+        header: Option<(&str, String)>,
+    ) -> StatusCode {
+        let mut request = axum::http::Request::get(uri);
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        app.clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn test_api_key_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(api_key_from_headers(&headers), None);
+
+        headers.insert(API_KEY_HEADER, "so_header".parse().unwrap());
+        assert_eq!(api_key_from_headers(&headers), Some("so_header"));
+
+        // A bearer token takes precedence
+        headers.insert(AUTHORIZATION, "Bearer so_bearer".parse().unwrap());
+        assert_eq!(api_key_from_headers(&headers), Some("so_bearer"));
+
+        // Other schemes fall back to X-API-Key
+        headers.insert(AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(api_key_from_headers(&headers), Some("so_header"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_api_routes_require_valid_api_key() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let user = User {
+            id: Uuid::new_v4(),
+            username: format!("auth-test-{}", Uuid::new_v4().simple()),
+            role: UserRole::ReadOnly,
+            created_at: Utc::now(),
+            last_login: None,
+        };
+        db.create_user(&user).await.unwrap();
+
+        let valid_key = generate_api_key();
+        let valid = ApiKey {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            name: "valid".to_string(),
+            key_hash: hash_api_key(&valid_key),
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used: None,
+        };
+        db.create_api_key(&valid).await.unwrap();
+        let expired_key = generate_api_key();
+        db.create_api_key(&ApiKey {
+            id: Uuid::new_v4(),
+            name: "expired".to_string(),
+            key_hash: hash_api_key(&expired_key),
+            expires_at: Some(Utc::now() - chrono::Duration::days(1)),
+            ..valid.clone()
+        })
+        .await
+        .unwrap();
+
+        let config = ServerConfig {
+            auth: Some(AuthConfig {
+                enabled: true,
+                require_auth: true,
+            }),
+            ..Default::default()
+        };
+        let app = HttpServer::new(db.clone(), config).create_router();
+
+        let bearer = Some(("authorization", format!("Bearer {}", valid_key)));
+        assert_eq!(
+            get_status(&app, "/api/machines", bearer).await,
+            StatusCode::OK
+        );
+        let header = Some((API_KEY_HEADER, valid_key.clone()));
+        assert_eq!(
+            get_status(&app, "/api/machines", header).await,
+            StatusCode::OK
+        );
+
+        assert_eq!(
+            get_status(&app, "/api/machines", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let expired = Some(("authorization", format!("Bearer {}", expired_key)));
+        assert_eq!(
+            get_status(&app, "/api/machines", expired).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Booting clients are not asked for a key
+        assert_ne!(
+            get_status(&app, "/boot.ipxe", None).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        let keys = db.list_user_api_keys(user.id).await.unwrap();
+        let used = keys.iter().find(|key| key.id == valid.id).unwrap();
+        assert!(used.last_used.is_some());
+        db.close().await;
+    }
 
     #[test]
     fn test_api_key_generation() {
@@ -202,9 +341,6 @@ mod tests {
 
     #[test]
     fn test_role_hierarchy() {
-        use chrono::Utc;
-        use uuid::Uuid;
-
         let admin = User {
             id: Uuid::new_v4(),
             username: "admin".to_string(),
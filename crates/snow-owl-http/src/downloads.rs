@@ -0,0 +1,568 @@
+//! WinPE and image downloads with HTTP range requests
+//!
+//! WinPE clients pull multi-gigabyte WIMs over links that drop. With range
+//! requests (RFC 9110 section 14) a broken download resumes where it stopped
+//! instead of starting over, and `If-Range` makes sure the resumed bytes come
+//! from the same version of the file. Under `/images` only files registered
+//! in the images table are served, with the stored checksum as their ETag;
+//! WinPE files get an ETag from their size and modification time.
+//!
+//! Only single ranges are served. A multi-range request is answered with 416
+//! rather than a multipart/byteranges body, which no deployment client uses.
+//!
+//! NIST Controls:
+//! - AC-3: Access Enforcement (images limited to registered files)
+//! - SI-10: Information Input Validation (paths confined to the directory, range syntax)
+//! - SI-7: Software, Firmware, and Information Integrity (checksum ETags)
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, Method, StatusCode, header, response::Builder},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::io::SeekFrom;
+use std::path::{Component, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf};
+use tokio_util::io::ReaderStream;
+use tracing::{debug, error, warn};
+
+use crate::AppState;
+use crate::metrics::{FileArea, Metrics};
+
+/// GET or HEAD a file under `winpe_dir`
+pub async fn winpe_file(
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Response {
+    let Some(file) = resolve(&state.config.winpe_dir, &path).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    serve_file(
+        &file,
+        None,
+        &method,
+        &headers,
+        &state.metrics,
+        FileArea::Winpe,
+    )
+    .await
+}
+
+/// GET or HEAD a registered image under `images_dir`
+///
+/// NIST AC-3: files that are not registered images are not served, even
+/// when they sit in `images_dir`
+pub async fn image_file(
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+) -> Response {
+    let Some(file) = resolve(&state.config.images_dir, &path).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    // Registered paths are usually canonical, but may have been stored as given
+    let mut image = state.db.get_image_by_path(&file).await;
+    if let Ok(None) = image {
+        image = state
+            .db
+            .get_image_by_path(&state.config.images_dir.join(&path))
+            .await;
+    }
+    let image = match image {
+        Ok(Some(image)) => image,
+        Ok(None) => {
+            warn!("Refusing download of unregistered file {}", file.display());
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        Err(e) => {
+            error!("Failed to look up image {}: {}", file.display(), e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = image.checksum.map(|checksum| format!("\"{}\"", checksum));
+    serve_file(
+        &file,
+        etag,
+        &method,
+        &headers,
+        &state.metrics,
+        FileArea::Images,
+    )
+    .await
+}
+
+/// Canonical path of the regular file `request` names under `root`
+///
+/// NIST SI-10: only plain relative components are accepted, and the result
+/// must still be inside `root` once symlinks are resolved
+async fn resolve(root: &std::path::Path, request: &str) -> Option<PathBuf> {
+    let relative = std::path::Path::new(request);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+
+    let root = tokio::fs::canonicalize(root).await.ok()?;
+    let file = tokio::fs::canonicalize(root.join(relative)).await.ok()?;
+    let is_file = tokio::fs::metadata(&file).await.ok()?.is_file();
+    (is_file && file.starts_with(&root)).then_some(file)
+}
+
+/// Answer a GET or HEAD for `file`, honouring conditional and range headers
+///
+/// `etag` is used when given (with quotes), otherwise one is derived from
+/// the file's size and modification time.
+///
+/// RFC 9110 sections 13 (conditional requests) and 14 (range requests)
+async fn serve_file(
+    file: &std::path::Path,
+    etag: Option<String>,
+    method: &Method,
+    headers: &HeaderMap,
+    metrics: &Arc<Metrics>,
+    area: FileArea,
+) -> Response {
+    let mut handle = match tokio::fs::File::open(file).await {
+        Ok(handle) => handle,
+        Err(e) => {
+            debug!("Failed to open {}: {}", file.display(), e);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    };
+    let metadata = match handle.metadata().await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            error!("Failed to stat {}: {}", file.display(), e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let len = metadata.len();
+    let modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+    let etag = etag.unwrap_or_else(|| {
+        let secs = modified.map_or(0, |m| m.timestamp());
+        format!("\"{:x}-{:x}\"", len, secs)
+    });
+
+    let counters = metrics.downloads(area);
+    counters.record_request();
+
+    let mut response = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag);
+    if let Some(modified) = modified {
+        response = response.header(header::LAST_MODIFIED, http_date(modified));
+    }
+
+    // RFC 9110 section 13.2.2: If-None-Match is evaluated before Range
+    if let Some(tags) = header_str(headers, header::IF_NONE_MATCH)
+        && etag_list_matches(tags, &etag)
+    {
+        counters.record_not_modified();
+        return finish(response.status(StatusCode::NOT_MODIFIED), Body::empty());
+    }
+
+    // A Range whose If-Range validator no longer matches is ignored, so the
+    // client gets the whole new file rather than a splice of two versions
+    let range = match header_str(headers, header::RANGE) {
+        Some(range) if if_range_holds(headers, &etag, modified) => parse_range(range, len),
+        _ => Ok(None),
+    };
+    let (start, count) = match range {
+        Ok(None) => {
+            response = response.status(StatusCode::OK);
+            (0, len)
+        }
+        Ok(Some((start, end))) => {
+            counters.record_partial();
+            response = response.status(StatusCode::PARTIAL_CONTENT).header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len),
+            );
+            (start, end - start + 1)
+        }
+        Err(()) => {
+            debug!("Unsatisfiable range for {}", file.display());
+            return finish(
+                response
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", len)),
+                Body::empty(),
+            );
+        }
+    };
+
+    let response = response
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, count);
+    if method == Method::HEAD {
+        return finish(response, Body::empty());
+    }
+
+    if start > 0
+        && let Err(e) = handle.seek(SeekFrom::Start(start)).await
+    {
+        error!("Failed to seek {} to {}: {}", file.display(), start, e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    let body = CountedReader {
+        inner: handle.take(count),
+        metrics: metrics.clone(),
+        area,
+    };
+    finish(response, Body::from_stream(ReaderStream::new(body)))
+}
+
+fn finish(response: Builder, body: Body) -> Response {
+    response.body(body).unwrap_or_else(|e| {
+        error!("Failed to build download response: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// IMF-fixdate (RFC 9110 section 5.6.7)
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Whether an If-None-Match list names `etag` (weak comparison)
+fn etag_list_matches(tags: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    tags.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Whether a Range may be honoured under the request's If-Range, if any
+///
+/// RFC 9110 section 13.1.5: an entity tag must match strongly, a date must
+/// equal the Last-Modified time exactly.
+fn if_range_holds(headers: &HeaderMap, etag: &str, modified: Option<DateTime<Utc>>) -> bool {
+    let Some(validator) = header_str(headers, header::IF_RANGE) else {
+        return true;
+    };
+    let validator = validator.trim();
+    if validator.starts_with('"') {
+        return validator == etag && !etag.starts_with("W/");
+    }
+    if validator.starts_with("W/") {
+        return false;
+    }
+    match (DateTime::parse_from_rfc2822(validator), modified) {
+        (Ok(date), Some(modified)) => date.timestamp() == modified.timestamp(),
+        _ => false,
+    }
+}
+
+/// The inclusive byte range a `Range` header selects from a `len`-byte file
+///
+/// `Ok(None)` when the header is not a byte range and is ignored; `Err` when
+/// it is malformed, asks for more than one range or is unsatisfiable, all
+/// answered with 416.
+fn parse_range(value: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Err(());
+    }
+    let (first, last) = spec.trim().split_once('-').ok_or(())?;
+    let (first, last) = (first.trim(), last.trim());
+
+    let (start, end) = if first.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = last.parse().map_err(|_| ())?;
+        if suffix == 0 {
+            return Err(());
+        }
+        (len.saturating_sub(suffix), len.checked_sub(1).ok_or(())?)
+    } else {
+        let start: u64 = first.parse().map_err(|_| ())?;
+        let end = if last.is_empty() {
+            len.checked_sub(1).ok_or(())?
+        } else {
+            let end: u64 = last.parse().map_err(|_| ())?;
+            if end < start {
+                return Err(());
+            }
+            end.min(len.saturating_sub(1))
+        };
+        (start, end)
+    };
+
+    if start >= len {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+/// File reader that counts the bytes it hands to the response body
+struct CountedReader<R> {
+    inner: R,
+    metrics: Arc<Metrics>,
+    area: FileArea,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let read = (buf.filled().len() - before) as u64;
+            self.metrics.downloads(self.area).record_bytes(read);
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const FIXTURE_LEN: usize = 10_000;
+
+    fn fixture() -> (PathBuf, Vec<u8>) {
+        let dir = std::env::temp_dir().join(format!("snow_owl_downloads_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = (0..FIXTURE_LEN).map(|i| (i % 251) as u8).collect();
+        let path = dir.join("install.wim");
+        std::fs::write(&path, &data).unwrap();
+        (path, data)
+    }
+
+    async fn get(
+        path: &std::path::Path,
+        method: Method,
+        headers: &[(header::HeaderName, &str)],
+        metrics: &Arc<Metrics>,
+    ) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        let etag = Some("\"abc123\"".to_string());
+        let response = serve_file(path, etag, &method, &map, metrics, FileArea::Images).await;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, headers, body.to_vec())
+    }
+
+    fn header(headers: &HeaderMap, name: header::HeaderName) -> &str {
+        headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_full_and_ranged_requests() {
+        let (path, data) = fixture();
+        let metrics = Arc::new(Metrics::default());
+
+        let (status, headers, body) = get(&path, Method::GET, &[], &metrics).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, data);
+        assert_eq!(header(&headers, header::ACCEPT_RANGES), "bytes");
+        assert_eq!(header(&headers, header::ETAG), "\"abc123\"");
+        assert_eq!(header(&headers, header::CONTENT_LENGTH), "10000");
+
+        let (status, headers, body) = get(
+            &path,
+            Method::GET,
+            &[(header::RANGE, "bytes=100-199")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, &data[100..200]);
+        assert_eq!(
+            header(&headers, header::CONTENT_RANGE),
+            "bytes 100-199/10000"
+        );
+
+        // Open-ended, suffix and past-the-end ranges
+        let (_, _, body) = get(
+            &path,
+            Method::GET,
+            &[(header::RANGE, "bytes=9000-")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(body, &data[9000..]);
+        let (_, headers, body) = get(
+            &path,
+            Method::GET,
+            &[(header::RANGE, "bytes=-500")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(body, &data[9500..]);
+        assert_eq!(
+            header(&headers, header::CONTENT_RANGE),
+            "bytes 9500-9999/10000"
+        );
+        let (_, _, body) = get(
+            &path,
+            Method::GET,
+            &[(header::RANGE, "bytes=9990-20000")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(body, &data[9990..]);
+
+        // HEAD reports the length without a body
+        let (status, headers, body) = get(
+            &path,
+            Method::HEAD,
+            &[(header::RANGE, "bytes=0-99")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&headers, header::CONTENT_LENGTH), "100");
+        assert!(body.is_empty());
+
+        let snapshot = metrics.snapshot(FileArea::Images);
+        assert_eq!(snapshot.requests, 6);
+        assert_eq!(snapshot.partial, 5);
+        assert_eq!(
+            snapshot.bytes_served,
+            (FIXTURE_LEN + 100 + 1000 + 500 + 10) as u64
+        );
+        assert_eq!(metrics.snapshot(FileArea::Winpe), Default::default());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_ranges_are_not_satisfiable() {
+        let (path, _) = fixture();
+        let metrics = Arc::new(Metrics::default());
+
+        for range in [
+            "bytes=10000-",
+            "bytes=0-1,5-9",
+            "bytes=-0",
+            "bytes=50-10",
+            "bytes=abc",
+        ] {
+            let (status, headers, body) =
+                get(&path, Method::GET, &[(header::RANGE, range)], &metrics).await;
+            assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE, "{}", range);
+            assert_eq!(header(&headers, header::CONTENT_RANGE), "bytes */10000");
+            assert!(body.is_empty());
+        }
+
+        // Units other than bytes are ignored
+        let (status, _, body) = get(
+            &path,
+            Method::GET,
+            &[(header::RANGE, "items=0-1")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.len(), FIXTURE_LEN);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_requests() {
+        let (path, data) = fixture();
+        let metrics = Arc::new(Metrics::default());
+
+        let (status, headers, body) = get(
+            &path,
+            Method::GET,
+            &[(header::IF_NONE_MATCH, "\"other\", W/\"abc123\"")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&headers, header::ETAG), "\"abc123\"");
+        assert!(body.is_empty());
+
+        let (status, _, _) = get(
+            &path,
+            Method::GET,
+            &[(header::IF_NONE_MATCH, "\"other\"")],
+            &metrics,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // If-Range with the current ETag resumes; a stale one restarts
+        let resume = [
+            (header::RANGE, "bytes=5000-"),
+            (header::IF_RANGE, "\"abc123\""),
+        ];
+        let (status, _, body) = get(&path, Method::GET, &resume, &metrics).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, &data[5000..]);
+        let stale = [
+            (header::RANGE, "bytes=5000-"),
+            (header::IF_RANGE, "\"old\""),
+        ];
+        let (status, _, body) = get(&path, Method::GET, &stale, &metrics).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, data);
+
+        // If-Range by date must equal Last-Modified
+        let (_, headers, _) = get(&path, Method::HEAD, &[], &metrics).await;
+        let modified = header(&headers, header::LAST_MODIFIED).to_string();
+        let by_date = [
+            (header::RANGE, "bytes=0-9"),
+            (header::IF_RANGE, modified.as_str()),
+        ];
+        let (status, _, _) = get(&path, Method::GET, &by_date, &metrics).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        let old_date = [
+            (header::RANGE, "bytes=0-9"),
+            (header::IF_RANGE, "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ];
+        let (status, _, _) = get(&path, Method::GET, &old_date, &metrics).await;
+        assert_eq!(status, StatusCode::OK);
+
+        assert_eq!(metrics.snapshot(FileArea::Images).not_modified, 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_stays_inside_root() {
+        let (path, _) = fixture();
+        let root = path.parent().unwrap();
+
+        assert_eq!(
+            resolve(root, "install.wim").await,
+            Some(path.canonicalize().unwrap())
+        );
+        assert_eq!(resolve(root, "../install.wim").await, None);
+        assert_eq!(resolve(root, "/etc/passwd").await, None);
+        assert_eq!(resolve(root, "missing.wim").await, None);
+        // Directories are not files
+        assert_eq!(
+            resolve(
+                root.parent().unwrap(),
+                root.file_name().unwrap().to_str().unwrap()
+            )
+            .await,
+            None
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Driver packs and the per-deployment driver manifest
+//!
+//! A driver pack is a set of drivers (typically an INF tree) that the WinPE
+//! agent injects into the applied image, e.g. storage or NIC drivers the
+//! stock image lacks. Deployments list the packs to inject; the agent fetches
+//! `/api/deployments/:id/drivers` once the image is applied and injects each
+//! pack whose hardware IDs match the machine.
+//!
+//! NIST Controls:
+//! - CM-2: Baseline Configuration (drivers recorded per deployment)
+//! - SI-10: Information Input Validation (pack locations confined to `drivers_dir`)
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use snow_owl_core::{Deployment, DriverPack};
+use std::path::Component;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::api::ApiResponse;
+use crate::ipxe::url_host;
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateDriverPackRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Path under `drivers_dir`, or an `http(s)://` URL
+    pub location: String,
+    #[serde(default)]
+    pub hardware_ids: Vec<String>,
+}
+
+/// Drivers the WinPE agent injects for one deployment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DriverManifest {
+    pub deployment_id: Uuid,
+    pub machine_id: Uuid,
+    /// Packs in injection order
+    pub drivers: Vec<DriverManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DriverManifestEntry {
+    pub id: Uuid,
+    pub name: String,
+    /// Where the agent downloads the pack from
+    pub url: String,
+    /// PnP hardware IDs the pack is meant for; empty means inject unconditionally
+    pub hardware_ids: Vec<String>,
+}
+
+/// Check that a pack location is an `http(s)://` URL or a relative path that
+/// stays inside `drivers_dir`
+///
+/// NIST SI-10: Information Input Validation (no absolute paths or `..`)
+fn validate_location(location: &str) -> Result<(), String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return Ok(());
+    }
+
+    let path = std::path::Path::new(location);
+    let confined = path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if confined {
+        Ok(())
+    } else {
+        Err(format!(
+            "Driver pack location must be a path under drivers_dir or an http(s) URL: {}",
+            location
+        ))
+    }
+}
+
+/// URL the agent fetches a pack from
+///
+/// Paths are served from `drivers_dir` under `/drivers`.
+fn pack_url(base_url: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        location.to_string()
+    } else {
+        format!("{}/drivers/{}", base_url, location.trim_start_matches("./"))
+    }
+}
+
+/// Build the manifest for `deployment` from its packs
+///
+/// Entries follow the deployment's `driver_pack_ids` order; packs that no
+/// longer exist are left out.
+pub(crate) fn build_manifest(
+    base_url: &str,
+    deployment: &Deployment,
+    packs: &[DriverPack],
+) -> DriverManifest {
+    let drivers = deployment
+        .driver_pack_ids
+        .iter()
+        .filter_map(|id| packs.iter().find(|pack| pack.id == *id))
+        .map(|pack| DriverManifestEntry {
+            id: pack.id,
+            name: pack.name.clone(),
+            url: pack_url(base_url, &pack.location),
+            hardware_ids: pack.hardware_ids.clone(),
+        })
+        .collect();
+
+    DriverManifest {
+        deployment_id: deployment.id,
+        machine_id: deployment.machine_id,
+        drivers,
+    }
+}
+
+pub async fn list_driver_packs(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<DriverPack>>>, StatusCode> {
+    match state.db.list_driver_packs().await {
+        Ok(packs) => Ok(Json(ApiResponse::ok(packs))),
+        Err(e) => {
+            tracing::error!("Failed to list driver packs: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn get_driver_pack(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<DriverPack>>, StatusCode> {
+    match state.db.get_driver_pack_by_id(id).await {
+        Ok(Some(pack)) => Ok(Json(ApiResponse::ok(pack))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get driver pack: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn create_driver_pack(
+    State(state): State<AppState>,
+    Json(req): Json<CreateDriverPackRequest>,
+) -> Result<Json<ApiResponse<DriverPack>>, StatusCode> {
+    if let Err(e) = validate_location(&req.location) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    let pack = DriverPack {
+        id: Uuid::new_v4(),
+        name: req.name,
+        description: req.description,
+        location: req.location,
+        hardware_ids: req.hardware_ids,
+        created_at: chrono::Utc::now(),
+    };
+
+    match state.db.create_driver_pack(&pack).await {
+        Ok(_) => Ok(Json(ApiResponse::ok(pack))),
+        Err(e) => {
+            tracing::error!("Failed to create driver pack: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn delete_driver_pack(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, StatusCode> {
+    match state.db.delete_driver_pack(id).await {
+        Ok(_) => Ok(Json(ApiResponse::ok(()))),
+        Err(e) => {
+            tracing::error!("Failed to delete driver pack: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Driver manifest fetched by the WinPE agent for its deployment
+pub async fn get_deployment_drivers(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<DriverManifest>>, StatusCode> {
+    let deployment = match state.db.get_deployment_by_id(id).await {
+        Ok(Some(deployment)) => deployment,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get deployment: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let packs = state
+        .db
+        .get_driver_packs(&deployment.driver_pack_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get driver packs: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let base_url = format!(
+        "http://{}:{}",
+        url_host(state.config.network.server_ip),
+        state.config.http_port
+    );
+    Ok(Json(ApiResponse::ok(build_manifest(
+        &base_url,
+        &deployment,
+        &packs,
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::CreateDeploymentRequest;
+    use snow_owl_core::DeploymentStatus;
+
+    fn pack(name: &str, location: &str, hardware_ids: &[&str]) -> DriverPack {
+        DriverPack {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            location: location.to_string(),
+            hardware_ids: hardware_ids.iter().map(|id| id.to_string()).collect(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_for_deployment() {
+        let storage = pack("dell-storage", "dell/storage", &["PCI\\VEN_1028&DEV_0015"]);
+        let nic = pack("intel-nic", "https://mirror.example.com/intel-nic.zip", &[]);
+        let unused = pack("unused", "unused", &[]);
+        let removed = Uuid::new_v4();
+
+        // Requested as a client would, with injection order nic, storage
+        let req: CreateDeploymentRequest = serde_json::from_value(serde_json::json!({
+            "machine_id": Uuid::new_v4(),
+            "image_id": Uuid::new_v4(),
+            "driver_pack_ids": [nic.id, removed, storage.id],
+        }))
+        .unwrap();
+        let deployment = Deployment {
+            id: Uuid::new_v4(),
+            machine_id: req.machine_id,
+            image_id: req.image_id,
+            status: DeploymentStatus::Pending,
+            started_at: chrono::Utc::now(),
+            completed_at: None,
+            error_message: None,
+            driver_pack_ids: req.driver_pack_ids,
+            registration_token: None,
+            retry_count: 0,
+        };
+
+        let manifest = build_manifest(
+            "http://192.168.100.1:8080",
+            &deployment,
+            &[storage.clone(), unused, nic.clone()],
+        );
+
+        // Round-trip through the API response the agent receives
+        let body = serde_json::to_string(&ApiResponse::ok(manifest)).unwrap();
+        let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let manifest: DriverManifest = serde_json::from_value(response["data"].clone()).unwrap();
+
+        assert_eq!(manifest.deployment_id, deployment.id);
+        assert_eq!(manifest.machine_id, deployment.machine_id);
+        let ids: Vec<Uuid> = manifest.drivers.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![nic.id, storage.id]);
+        assert_eq!(
+            manifest.drivers[0].url,
+            "https://mirror.example.com/intel-nic.zip"
+        );
+        assert_eq!(
+            manifest.drivers[1].url,
+            "http://192.168.100.1:8080/drivers/dell/storage"
+        );
+        assert_eq!(
+            manifest.drivers[1].hardware_ids,
+            vec!["PCI\\VEN_1028&DEV_0015".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_deployment_request_without_driver_packs() {
+        let req: CreateDeploymentRequest = serde_json::from_value(serde_json::json!({
+            "machine_id": Uuid::new_v4(),
+            "image_id": Uuid::new_v4(),
+        }))
+        .unwrap();
+        assert!(req.driver_pack_ids.is_empty());
+    }
+
+    #[test]
+    fn test_validate_location() {
+        assert!(validate_location("dell/storage").is_ok());
+        assert!(validate_location("./intel").is_ok());
+        assert!(validate_location("https://mirror.example.com/nic.zip").is_ok());
+        assert!(validate_location("/etc/shadow").is_err());
+        assert!(validate_location("../winpe").is_err());
+        assert!(validate_location("dell/../../winpe").is_err());
+        assert!(validate_location("").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Deployment dry-runs
+//!
+//! A dry-run resolves a deployment request the way a real deployment would
+//! and renders what the machine would then receive, without writing
+//! anything: no deployment row, no machine update. Operators use it to
+//! preview a template or driver change for one machine before it reaches the
+//! fleet, and `snow-owl deploy dry-run` writes the result to a directory so
+//! two template versions can be diffed.
+//!
+//! Rendering goes through [`render_boot_script`] and [`build_manifest`], the
+//! same functions `/boot/:mac` and `/api/deployments/:id/drivers` use.
+//!
+//! NIST Controls:
+//! - CM-3: Configuration Change Control (changes previewed before rollout)
+//! - CM-4: Impact Analyses
+
+use serde::{Deserialize, Serialize};
+use snow_owl_core::{
+    Deployment, DriverPack, Machine, Result, ServerConfig, SnowOwlError, WindowsImage,
+};
+use snow_owl_db::Database;
+use uuid::Uuid;
+
+use crate::api::CreateDeploymentRequest;
+use crate::drivers::{DriverManifest, build_manifest};
+use crate::ipxe::{BootRender, load_template, render_boot_script, url_host};
+
+/// Artifacts a machine would receive for a deployment, and how they were chosen
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DryRunBundle {
+    /// The deployment that would be created; its ID and registration token
+    /// are nil since none is assigned
+    pub deployment: Deployment,
+    /// Script `/boot/:mac` serves once the deployment exists
+    pub boot_script: String,
+    /// Manifest `/api/deployments/:id/drivers` serves to the WinPE agent
+    pub driver_manifest: DriverManifest,
+    /// One line per resolution step
+    pub trace: Vec<String>,
+}
+
+/// Resolve `req` and render its artifacts without creating anything
+///
+/// # Errors
+///
+/// Returns `MachineNotFound`, `ImageNotFound` or `DriverPackNotFound` for
+/// unknown IDs, `InvalidImage` if the image's chain of base images is too
+/// deep, and `InvalidConfig` if the iPXE template cannot be read or rendered.
+pub async fn dry_run(
+    db: &Database,
+    config: &ServerConfig,
+    req: &CreateDeploymentRequest,
+) -> Result<DryRunBundle> {
+    let machine = db
+        .get_machine_by_id(req.machine_id)
+        .await?
+        .ok_or_else(|| SnowOwlError::MachineNotFound(req.machine_id.to_string()))?;
+    // The deployed image comes last, after any base images it is a delta on
+    let mut base_images = db.get_image_chain(req.image_id).await?;
+    let image = base_images
+        .pop()
+        .ok_or_else(|| SnowOwlError::ImageNotFound(req.image_id.to_string()))?;
+
+    let packs = db.get_driver_packs(&req.driver_pack_ids).await?;
+    if let Some(missing) = req
+        .driver_pack_ids
+        .iter()
+        .find(|id| !packs.iter().any(|pack| pack.id == **id))
+    {
+        return Err(SnowOwlError::DriverPackNotFound(missing.to_string()));
+    }
+
+    let mut deployment =
+        Deployment::pending(req.machine_id, req.image_id, req.driver_pack_ids.clone());
+    deployment.id = Uuid::nil();
+    deployment.registration_token = Some(Uuid::nil().simple().to_string());
+
+    let template = load_template(config).await?;
+    render_bundle(
+        config,
+        template.as_deref(),
+        &machine,
+        &image,
+        &base_images,
+        deployment,
+        &packs,
+    )
+}
+
+/// Render the bundle for resolved inputs
+fn render_bundle(
+    config: &ServerConfig,
+    template: Option<&str>,
+    machine: &Machine,
+    image: &WindowsImage,
+    base_images: &[WindowsImage],
+    deployment: Deployment,
+    packs: &[DriverPack],
+) -> Result<DryRunBundle> {
+    let server_ip = config.network.server_ip;
+    let http_port = config.http_port;
+
+    let boot_script = render_boot_script(&BootRender {
+        server_ip,
+        http_port,
+        machine,
+        assignment: Some((&deployment, image)),
+        base_images,
+        template,
+    })
+    .map_err(|e| SnowOwlError::InvalidConfig(format!("Failed to render iPXE template: {}", e)))?;
+
+    let base_url = format!("http://{}:{}", url_host(server_ip), http_port);
+    let driver_manifest = build_manifest(&base_url, &deployment, packs);
+
+    let mut trace = vec![
+        format!(
+            "machine: {} ({}), id {}",
+            machine.mac_address,
+            machine.hostname.as_deref().unwrap_or("no hostname"),
+            machine.id
+        ),
+        format!(
+            "image: {} ({}) at {}",
+            image.name,
+            image.image_type,
+            image.file_path.display()
+        ),
+        if base_images.is_empty() {
+            "image chain: full image, no base".to_string()
+        } else {
+            format!(
+                "image chain: {} applied in order",
+                base_images
+                    .iter()
+                    .chain([image])
+                    .map(|image| format!("{} ({})", image.name, image.id))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            )
+        },
+        match (&config.ipxe_template, template) {
+            (Some(path), Some(_)) => format!("boot script: template {}", path.display()),
+            _ => "boot script: built-in deployment script (ipxe_template not set)".to_string(),
+        },
+        "deployment id: not assigned in a dry-run; nil UUID used in URLs".to_string(),
+    ];
+    if packs.is_empty() {
+        trace.push("driver packs: none requested".to_string());
+    }
+    for (position, entry) in driver_manifest.drivers.iter().enumerate() {
+        let hardware = if entry.hardware_ids.is_empty() {
+            "any hardware".to_string()
+        } else {
+            format!("hardware IDs {}", entry.hardware_ids.join(", "))
+        };
+        trace.push(format!(
+            "driver pack {}: requested at position {}, applies to {}, from {}",
+            entry.name,
+            position + 1,
+            hardware,
+            entry.url
+        ));
+    }
+
+    Ok(DryRunBundle {
+        deployment,
+        boot_script,
+        driver_manifest,
+        trace,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snow_owl_core::{ImageType, MacAddress};
+    use std::path::PathBuf;
+
+    const TEMPLATE: &str = "#!ipxe
+set base-url {{ base_url }}
+set image-id {{ image_id }}
+set drivers {{ driver_manifest_url }}
+echo Deploying {{ image_name }} to {{ hostname }}
+kernel ${base-url}/winpe/wimboot {{ kernel_args }}
+boot
+";
+
+    fn fixture() -> (Machine, WindowsImage, DriverPack) {
+        let machine = Machine {
+            id: Uuid::new_v4(),
+            mac_address: MacAddress::new([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+            hostname: Some("lab-01".to_string()),
+            ip_address: None,
+            kernel_args: Some("console=ttyS0".to_string()),
+            last_seen: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        };
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: "Server 2022".to_string(),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: PathBuf::from("/var/lib/snow-owl/images/server2022.wim"),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        let pack = DriverPack {
+            id: Uuid::new_v4(),
+            name: "dell-storage".to_string(),
+            description: None,
+            location: "dell/storage".to_string(),
+            hardware_ids: vec!["PCI\\VEN_1028&DEV_0015".to_string()],
+            created_at: chrono::Utc::now(),
+        };
+        (machine, image, pack)
+    }
+
+    /// What `/boot/:mac` and the driver manifest endpoint serve for a
+    /// created deployment
+    fn served(
+        config: &ServerConfig,
+        template: Option<&str>,
+        machine: &Machine,
+        image: &WindowsImage,
+        deployment: &Deployment,
+        packs: &[DriverPack],
+    ) -> (String, DriverManifest) {
+        let script = render_boot_script(&BootRender {
+            server_ip: config.network.server_ip,
+            http_port: config.http_port,
+            machine,
+            assignment: Some((deployment, image)),
+            base_images: &[],
+            template,
+        })
+        .unwrap();
+        let manifest = build_manifest("http://192.168.100.1:8080", deployment, packs);
+        (script, manifest)
+    }
+
+    #[test]
+    fn test_dry_run_matches_served_artifacts() {
+        let (machine, image, pack) = fixture();
+        let config = ServerConfig {
+            ipxe_template: Some(PathBuf::from("/etc/snow-owl/boot.ipxe.tmpl")),
+            ..ServerConfig::default()
+        };
+        let packs = [pack.clone()];
+
+        for template in [Some(TEMPLATE), None] {
+            let mut preview = Deployment::pending(machine.id, image.id, vec![pack.id]);
+            preview.id = Uuid::nil();
+            preview.registration_token = Some(Uuid::nil().simple().to_string());
+            let bundle =
+                render_bundle(&config, template, &machine, &image, &[], preview, &packs).unwrap();
+
+            // The real deployment differs only in its assigned ID and token
+            let created = Deployment::pending(machine.id, image.id, vec![pack.id]);
+            let (script, manifest) = served(&config, template, &machine, &image, &created, &packs);
+
+            assert_eq!(
+                bundle
+                    .boot_script
+                    .replace(&Uuid::nil().to_string(), &created.id.to_string())
+                    .replace(
+                        &Uuid::nil().simple().to_string(),
+                        created.registration_token.as_deref().unwrap()
+                    ),
+                script
+            );
+            assert_eq!(
+                serde_json::to_value(&bundle.driver_manifest.drivers).unwrap(),
+                serde_json::to_value(&manifest.drivers).unwrap()
+            );
+            assert_eq!(bundle.driver_manifest.machine_id, manifest.machine_id);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_trace() {
+        let (machine, image, pack) = fixture();
+        let config = ServerConfig::default();
+        let mut preview = Deployment::pending(machine.id, image.id, vec![pack.id]);
+        preview.id = Uuid::nil();
+
+        let bundle = render_bundle(&config, None, &machine, &image, &[], preview, &[pack]).unwrap();
+
+        assert!(
+            bundle
+                .boot_script
+                .contains("echo Deploying image: Server 2022\n")
+        );
+        assert!(bundle.trace.iter().any(|line| line.contains("built-in")));
+        assert!(
+            bundle.trace.contains(
+                &"driver pack dell-storage: requested at position 1, applies to hardware IDs \
+              PCI\\VEN_1028&DEV_0015, from http://192.168.100.1:8080/drivers/dell/storage"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,572 @@
+//! Live deployment status over WebSocket and Server-Sent Events
+//!
+//! `GET /api/deployments/ws` upgrades to a WebSocket that sends the first
+//! page of deployments on connect, then one JSON message whenever a
+//! deployment is created or its status changes, so the web UI no longer has
+//! to poll `GET /api/deployments`. `GET /api/deployments/{id}/events` is an
+//! SSE stream of one deployment's status transitions for operators and
+//! scripts; it ends once the deployment completes or fails.
+//!
+//! Handlers publish to a broadcast channel owned by [`AppState`]. Publishing
+//! never waits for clients: one that falls a full channel behind is
+//! disconnected and can reconnect for a fresh snapshot.
+//!
+//! Events reach the clients of the replica whose handler made the change.
+//!
+//! NIST Controls:
+//! - SI-4: System Monitoring (live deployment status)
+//! - SC-5: Denial of Service Protection (slow clients dropped, not buffered for)
+//! - AC-12: Session Termination (unanswered pings close the socket)
+
+use axum::{
+    extract::{
+        Path, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    http::StatusCode,
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use snow_owl_core::{Deployment, DeploymentFilter, DeploymentStatus, PageRequest};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{Instant, interval_at};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Events a client may fall behind by before it is disconnected
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// How often clients are pinged; a ping still unanswered at the next one
+/// closes the socket
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Message sent to WebSocket clients, tagged by `type`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeploymentEvent {
+    /// First page of deployments, most recent first; sent once on connect
+    Snapshot { deployments: Vec<Deployment> },
+    /// A deployment was created
+    Created { deployment: Deployment },
+    /// A deployment moved to a new status
+    StatusChanged {
+        id: Uuid,
+        status: DeploymentStatus,
+        error_message: Option<String>,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl DeploymentEvent {
+    /// Status change of deployment `id`, stamped now
+    pub fn status_changed(
+        id: Uuid,
+        status: DeploymentStatus,
+        error_message: Option<String>,
+    ) -> Self {
+        Self::StatusChanged {
+            id,
+            status,
+            error_message,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Data of one `status` event on the SSE stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentProgress {
+    pub status: DeploymentStatus,
+    pub timestamp: DateTime<Utc>,
+    pub error_message: Option<String>,
+}
+
+impl DeploymentProgress {
+    /// Whether no further transitions follow
+    fn is_final(&self) -> bool {
+        matches!(
+            self.status,
+            DeploymentStatus::Completed | DeploymentStatus::Failed
+        )
+    }
+
+    fn to_event(&self) -> Result<Event, axum::Error> {
+        Event::default().event("status").json_data(self)
+    }
+}
+
+/// Broadcast channel of [`DeploymentEvent`]s shared by the request handlers
+#[derive(Debug, Clone)]
+pub struct DeploymentEvents {
+    sender: broadcast::Sender<DeploymentEvent>,
+}
+
+impl DeploymentEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Send `event` to every connected client without waiting for any
+    pub fn publish(&self, event: DeploymentEvent) {
+        // Fails only when no client is connected
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<DeploymentEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for DeploymentEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `GET /api/deployments/ws`
+pub async fn deployments_ws(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| stream_events(socket, state))
+}
+
+async fn stream_events(mut socket: WebSocket, state: AppState) {
+    // Subscribe before reading the snapshot so no change falls in between
+    let mut events = state.events.subscribe();
+    let request = PageRequest::default();
+    let deployments = match state
+        .db
+        .list_deployments_paged(
+            request.offset(),
+            request.limit(),
+            &DeploymentFilter::default(),
+        )
+        .await
+    {
+        Ok((deployments, _)) => deployments,
+        Err(e) => {
+            error!("Failed to list deployments for event stream: {}", e);
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    if send(&mut socket, &DeploymentEvent::Snapshot { deployments })
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut ping = interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let mut awaiting_pong = false;
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if send(&mut socket, &event).await.is_err() {
+                        return;
+                    }
+                }
+                // NIST SC-5: A slow client never holds back publishers
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Closing deployment event stream {} events behind", missed);
+                    let close = CloseFrame {
+                        code: close_code::AGAIN,
+                        reason: "Too far behind; reconnect for a fresh snapshot".into(),
+                    };
+                    let _ = socket.send(Message::Close(Some(close))).await;
+                    return;
+                }
+                Err(RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Pong(_))) => awaiting_pong = false,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                // Pings are answered by the WebSocket layer; other messages are ignored
+                Some(Ok(_)) => {}
+            },
+            _ = ping.tick() => {
+                // NIST AC-12: Drop connections whose client has gone away
+                if awaiting_pong {
+                    debug!("Closing deployment event stream after unanswered ping");
+                    return;
+                }
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    return;
+                }
+                awaiting_pong = true;
+            }
+        }
+    }
+}
+
+/// `GET /api/deployments/{id}/events`
+///
+/// Sends the deployment's current status first, then each transition as
+/// a `status` event. The stream ends after `completed` or `failed`, and
+/// also when the client falls a full channel behind; an `EventSource`
+/// then reconnects and starts again from the current status.
+pub async fn deployment_sse(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    // Subscribe before reading the current status so no change falls in between
+    let events = state.events.subscribe();
+    let deployment = match state.db.get_deployment_by_id(id).await {
+        Ok(Some(deployment)) => deployment,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to load deployment for event stream: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    Ok(Sse::new(progress_stream(&deployment, events)).keep_alive(KeepAlive::default()))
+}
+
+/// Current status of `deployment`, then its transitions from `events`
+fn progress_stream(
+    deployment: &Deployment,
+    events: broadcast::Receiver<DeploymentEvent>,
+) -> impl Stream<Item = Result<Event, axum::Error>> + use<> {
+    let current = DeploymentProgress {
+        status: deployment.status,
+        timestamp: deployment.completed_at.unwrap_or_else(Utc::now),
+        error_message: deployment.error_message.clone(),
+    };
+
+    // State: progress to send next, the receiver, and whether the last sent was final
+    let id = deployment.id;
+    stream::unfold(
+        (Some(current), events, false),
+        move |(next, mut events, done)| async move {
+            if done {
+                return None;
+            }
+            let progress = match next {
+                Some(progress) => progress,
+                None => loop {
+                    match events.recv().await {
+                        Ok(DeploymentEvent::StatusChanged {
+                            id: changed,
+                            status,
+                            error_message,
+                            timestamp,
+                        }) if changed == id => {
+                            break DeploymentProgress {
+                                status,
+                                timestamp,
+                                error_message,
+                            };
+                        }
+                        Ok(_) => {}
+                        // NIST SC-5: A slow client never holds back publishers
+                        Err(RecvError::Lagged(missed)) => {
+                            warn!(
+                                "Closing event stream of deployment {} {} events behind",
+                                id, missed
+                            );
+                            return None;
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+            let done = progress.is_final();
+            Some((progress.to_event(), (None, events, done)))
+        },
+    )
+}
+
+async fn send(socket: &mut WebSocket, event: &DeploymentEvent) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(event).map_err(axum::Error::new)?;
+    socket.send(Message::Text(json.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpServer;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use futures_util::StreamExt;
+    use snow_owl_core::{ImageType, MacAddress, Machine, ServerConfig, WindowsImage};
+    use snow_owl_db::Database;
+    use std::sync::Arc;
+    use tokio_tungstenite::tungstenite;
+    use tower::ServiceExt;
+
+    type Client = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    /// Next event from `client`, skipping control frames
+    async fn next_event(client: &mut Client) -> serde_json::Value {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("no event within 5 seconds")
+                .unwrap()
+                .unwrap();
+            if let tungstenite::Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    fn post(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_deployment_events_reach_websocket_clients() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let machine = Machine {
+            id: Uuid::new_v4(),
+            mac_address: MacAddress::new(*Uuid::new_v4().as_bytes().first_chunk().unwrap()),
+            hostname: None,
+            ip_address: None,
+            kernel_args: None,
+            last_seen: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        };
+        db.create_or_update_machine(&machine).await.unwrap();
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: format!("events-test-{}", Uuid::new_v4().simple()),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/srv/images/events-test.wim".into(),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&image).await.unwrap();
+
+        let app = HttpServer::new(db.clone(), ServerConfig::default()).create_router();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(axum::serve(listener, app.clone()).into_future());
+
+        let (mut client, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/api/deployments/ws", addr))
+                .await
+                .unwrap();
+        let snapshot = next_event(&mut client).await;
+        assert_eq!(snapshot["type"], "snapshot");
+        assert!(snapshot["deployments"].is_array());
+
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/api/deployments",
+                serde_json::json!({ "machine_id": machine.id, "image_id": image.id }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = body["data"]["id"].as_str().unwrap().to_string();
+
+        let created = next_event(&mut client).await;
+        assert_eq!(created["type"], "created");
+        assert_eq!(created["deployment"]["id"], id.as_str());
+        assert_eq!(created["deployment"]["status"], "pending");
+        assert_eq!(created["deployment"]["machine_id"], machine.id.to_string());
+
+        let response = app
+            .oneshot(post(
+                &format!("/api/deployments/{}/status", id),
+                serde_json::json!({ "status": "installing" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let changed = next_event(&mut client).await;
+        assert_eq!(changed["type"], "status_changed");
+        assert_eq!(changed["id"], id.as_str());
+        assert_eq!(changed["status"], "installing");
+
+        client.close(None).await.unwrap();
+        server.abort();
+        db.close().await;
+    }
+
+    /// `status` events of an SSE body, parsed
+    fn sse_statuses(body: &str) -> Vec<DeploymentProgress> {
+        body.split("\n\n")
+            .filter(|frame| frame.starts_with("event: status\n"))
+            .map(|frame| serde_json::from_str(frame.split_once("data: ").unwrap().1).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_progress_stream_ends_after_final_status() {
+        let events = DeploymentEvents::new();
+        let deployment = Deployment::pending(Uuid::new_v4(), Uuid::new_v4(), Vec::new());
+        let stream = progress_stream(&deployment, events.subscribe());
+
+        for (id, status, error) in [
+            (Uuid::new_v4(), DeploymentStatus::Completed, None),
+            (deployment.id, DeploymentStatus::Installing, None),
+            (deployment.id, DeploymentStatus::Failed, Some("disk full")),
+            (deployment.id, DeploymentStatus::Completed, None),
+        ] {
+            events.publish(DeploymentEvent::status_changed(
+                id,
+                status,
+                error.map(String::from),
+            ));
+        }
+
+        // The body is complete only because the stream ended at `failed`
+        let response = axum::response::IntoResponse::into_response(Sse::new(stream));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let statuses = sse_statuses(std::str::from_utf8(&body).unwrap());
+        assert_eq!(
+            statuses.iter().map(|p| p.status).collect::<Vec<_>>(),
+            [
+                DeploymentStatus::Pending,
+                DeploymentStatus::Installing,
+                DeploymentStatus::Failed
+            ]
+        );
+        assert_eq!(statuses[2].error_message.as_deref(), Some("disk full"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs SNOW_OWL_TEST_DATABASE_URL"]
+    async fn test_sse_stream_delivers_status_updates() {
+        let url = std::env::var("SNOW_OWL_TEST_DATABASE_URL")
+            .expect("SNOW_OWL_TEST_DATABASE_URL must name a PostgreSQL server");
+        let db = Arc::new(Database::new(&url).await.unwrap());
+        let machine = Machine {
+            id: Uuid::new_v4(),
+            mac_address: MacAddress::new(*Uuid::new_v4().as_bytes().first_chunk().unwrap()),
+            hostname: None,
+            ip_address: None,
+            kernel_args: None,
+            last_seen: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        };
+        db.create_or_update_machine(&machine).await.unwrap();
+        let image = WindowsImage {
+            id: Uuid::new_v4(),
+            name: format!("sse-test-{}", Uuid::new_v4().simple()),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: "/srv/images/sse-test.wim".into(),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        db.create_image(&image).await.unwrap();
+        let deployment = Deployment::pending(machine.id, image.id, Vec::new());
+        db.create_deployment(&deployment).await.unwrap();
+
+        let app = HttpServer::new(db.clone(), ServerConfig::default()).create_router();
+        let missing = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/deployments/{}/events", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(format!("/api/deployments/{}/events", deployment.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut body = response.into_body().into_data_stream();
+        let mut next_status = async || loop {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+                .await
+                .expect("no event within 5 seconds")
+                .unwrap()
+                .unwrap();
+            if let Some(progress) = sse_statuses(std::str::from_utf8(&chunk).unwrap()).pop() {
+                return progress;
+            }
+        };
+
+        assert_eq!(next_status().await.status, DeploymentStatus::Pending);
+
+        let status_update = |status: &str| {
+            app.clone().oneshot(post(
+                &format!("/api/deployments/{}/status", deployment.id),
+                serde_json::json!({ "status": status, "error_message": null }),
+            ))
+        };
+        let before = chrono::Utc::now();
+        assert_eq!(
+            status_update("installing").await.unwrap().status(),
+            StatusCode::OK
+        );
+        let installing = next_status().await;
+        assert_eq!(installing.status, DeploymentStatus::Installing);
+        assert!(installing.timestamp >= before);
+
+        assert_eq!(
+            status_update("completed").await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(next_status().await.status, DeploymentStatus::Completed);
+        // Nothing follows a final status
+        assert!(body.next().await.is_none());
+        db.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber_is_told_how_far_behind() {
+        let events = DeploymentEvents::new();
+        let mut subscriber = events.subscribe();
+        // Publishing never blocks, however far behind a subscriber is
+        for _ in 0..EVENT_CHANNEL_CAPACITY + 10 {
+            events.publish(DeploymentEvent::status_changed(
+                Uuid::nil(),
+                DeploymentStatus::Installing,
+                None,
+            ));
+        }
+        assert!(matches!(
+            subscriber.recv().await,
+            Err(RecvError::Lagged(10))
+        ));
+    }
+}
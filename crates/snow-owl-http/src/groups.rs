@@ -0,0 +1,179 @@
+//! Machine groups
+//!
+//! A group is a named set of machines (a lab, a rack, a department) that
+//! deployments and access rules can target instead of listing machines one
+//! by one. Membership is many-to-many: a machine may sit in several groups,
+//! and deleting a group leaves its machines in the inventory.
+//!
+//! NIST Controls:
+//! - CM-8: Information System Component Inventory (grouping of components)
+//! - SI-10: Information Input Validation (group names)
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use snow_owl_core::{Machine, MachineGroup};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::api::ApiResponse;
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateMachineGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+pub async fn list_machine_groups(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<MachineGroup>>>, StatusCode> {
+    match state.db.list_machine_groups().await {
+        Ok(groups) => Ok(Json(ApiResponse::ok(groups))),
+        Err(e) => {
+            tracing::error!("Failed to list machine groups: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn get_machine_group(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<MachineGroup>>, StatusCode> {
+    match state.db.get_machine_group_by_id(id).await {
+        Ok(Some(group)) => Ok(Json(ApiResponse::ok(group))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get machine group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn create_machine_group(
+    State(state): State<AppState>,
+    Json(req): Json<CreateMachineGroupRequest>,
+) -> Result<Json<ApiResponse<MachineGroup>>, StatusCode> {
+    if let Err(e) = MachineGroup::validate_name(&req.name) {
+        return Ok(Json(ApiResponse::error(e)));
+    }
+
+    match state.db.get_machine_group_by_name(&req.name).await {
+        Ok(Some(_)) => {
+            return Ok(Json(ApiResponse::error(format!(
+                "Machine group already exists: {}",
+                req.name
+            ))));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to look up machine group: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let group = MachineGroup::new(req.name, req.description);
+    match state.db.create_machine_group(&group).await {
+        Ok(_) => Ok(Json(ApiResponse::ok(group))),
+        Err(e) => {
+            tracing::error!("Failed to create machine group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn delete_machine_group(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, StatusCode> {
+    match state.db.delete_machine_group(id).await {
+        Ok(_) => Ok(Json(ApiResponse::ok(()))),
+        Err(e) => {
+            tracing::error!("Failed to delete machine group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Machines in a group
+pub async fn list_group_machines(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<Machine>>>, StatusCode> {
+    require_group(&state, id).await?;
+
+    match state.db.list_group_machines(id).await {
+        Ok(machines) => Ok(Json(ApiResponse::ok(machines))),
+        Err(e) => {
+            tracing::error!("Failed to list group machines: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Add a machine to a group; adding an existing member succeeds
+pub async fn add_group_machine(
+    State(state): State<AppState>,
+    Path((id, machine_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<()>>, StatusCode> {
+    require_group(&state, id).await?;
+    match state.db.get_machine_by_id(machine_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get machine: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match state.db.add_machine_to_group(id, machine_id).await {
+        Ok(_) => Ok(Json(ApiResponse::ok(()))),
+        Err(e) => {
+            tracing::error!("Failed to add machine to group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Remove a machine from a group
+pub async fn remove_group_machine(
+    State(state): State<AppState>,
+    Path((id, machine_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<()>>, StatusCode> {
+    match state.db.remove_machine_from_group(id, machine_id).await {
+        Ok(true) => Ok(Json(ApiResponse::ok(()))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to remove machine from group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Groups a machine belongs to
+pub async fn list_machine_groups_for_machine(
+    State(state): State<AppState>,
+    Path(machine_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<MachineGroup>>>, StatusCode> {
+    match state.db.list_machine_group_memberships(machine_id).await {
+        Ok(groups) => Ok(Json(ApiResponse::ok(groups))),
+        Err(e) => {
+            tracing::error!("Failed to list machine group memberships: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn require_group(state: &AppState, id: Uuid) -> Result<(), StatusCode> {
+    match state.db.get_machine_group_by_id(id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get machine group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,690 @@
+/// Idempotency Keys for Retry-Safe API Automation
+///
+/// Clients send an `Idempotency-Key` header on resource-creating requests.
+/// The first request with a key runs and its response is stored; a retry
+/// with the same key replays the stored response instead of acting again.
+/// Concurrent first-time requests are serialized by claiming the key with a
+/// unique insert before the request runs.
+///
+/// NIST Controls:
+/// - SI-10: Information Input Validation (key format, key reuse with a different request)
+/// - AC-3: Access Enforcement (keys are scoped per API key)
+/// - SC-5: Denial of Service Protection (bounded wait for in-flight requests)
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use snow_owl_core::{IdempotencyRecord, Result};
+use snow_owl_db::Database;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::api::ApiResponse;
+use crate::auth::AuthUser;
+
+/// Request header carrying the idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Maximum accepted key length
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// How often a duplicate polls for the original request to finish
+const IN_FLIGHT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a duplicate waits for the original request before giving up
+const IN_FLIGHT_WAIT: Duration = Duration::from_secs(10);
+
+/// Age at which a claim with no stored response is treated as abandoned
+///
+/// Covers a request that died without releasing its claim, e.g. when the
+/// process exits mid-request. Well above how long the guarded handlers run.
+const IN_FLIGHT_LIMIT: Duration = Duration::from_secs(30);
+
+/// Storage for idempotency records
+///
+/// Implemented by [`Database`]; kept as a trait so the replay logic can be
+/// exercised without PostgreSQL.
+pub trait IdempotencyStore: Send + Sync {
+    /// Insert the record if no live record exists for its key
+    ///
+    /// A record without a stored response that is older than
+    /// `in_flight_limit` does not count as live.
+    fn claim(
+        &self,
+        record: &IdempotencyRecord,
+        in_flight_limit: Duration,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Get the unexpired record for a key
+    fn get(
+        &self,
+        scope: &str,
+        key: &str,
+    ) -> impl Future<Output = Result<Option<IdempotencyRecord>>> + Send;
+
+    /// Store the response for a claimed key
+    fn complete(
+        &self,
+        scope: &str,
+        key: &str,
+        resource_id: Option<Uuid>,
+        status_code: u16,
+        response_body: &serde_json::Value,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Drop a claimed key so the request can be retried
+    fn release(&self, scope: &str, key: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl IdempotencyStore for Database {
+    async fn claim(&self, record: &IdempotencyRecord, in_flight_limit: Duration) -> Result<bool> {
+        self.claim_idempotency_key(record, in_flight_limit).await
+    }
+
+    async fn get(&self, scope: &str, key: &str) -> Result<Option<IdempotencyRecord>> {
+        self.get_idempotency_key(scope, key).await
+    }
+
+    async fn complete(
+        &self,
+        scope: &str,
+        key: &str,
+        resource_id: Option<Uuid>,
+        status_code: u16,
+        response_body: &serde_json::Value,
+    ) -> Result<()> {
+        self.complete_idempotency_key(scope, key, resource_id, status_code, response_body)
+            .await
+    }
+
+    async fn release(&self, scope: &str, key: &str) -> Result<()> {
+        self.release_idempotency_key(scope, key).await
+    }
+}
+
+/// A request guarded by an idempotency key
+#[derive(Debug, Clone)]
+pub struct IdempotentRequest {
+    pub scope: String,
+    pub key: String,
+    pub endpoint: &'static str,
+    pub request_hash: String,
+}
+
+impl IdempotentRequest {
+    /// Build the guard from request headers
+    ///
+    /// Returns `Ok(None)` when no `Idempotency-Key` header was sent and
+    /// `Err(StatusCode::BAD_REQUEST)` for a malformed key.
+    pub fn from_headers<T: Serialize>(
+        headers: &HeaderMap,
+        auth: Option<&AuthUser>,
+        endpoint: &'static str,
+        body: &T,
+    ) -> std::result::Result<Option<Self>, StatusCode> {
+        let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(None);
+        };
+
+        // NIST SI-10: Keys are short printable ASCII tokens
+        let key = value.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        if key.is_empty()
+            || key.len() > MAX_KEY_LENGTH
+            || !key.bytes().all(|b| b.is_ascii_graphic())
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        Ok(Some(Self {
+            scope: scope_for(auth),
+            key: key.to_string(),
+            endpoint,
+            request_hash: request_hash(body),
+        }))
+    }
+}
+
+/// Scope keys to the calling API key so one tenant cannot replay another's responses
+///
+/// NIST AC-3: Access Enforcement
+fn scope_for(auth: Option<&AuthUser>) -> String {
+    match auth {
+        Some(AuthUser {
+            api_key_id: Some(id),
+            ..
+        }) => format!("api_key:{}", id),
+        Some(auth) => format!("user:{}", auth.user.id),
+        None => "anonymous".to_string(),
+    }
+}
+
+/// SHA-256 of the serialized request body
+fn request_hash<T: Serialize>(body: &T) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(body).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+/// Status code and JSON body of an API response, as stored for replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub body: serde_json::Value,
+}
+
+impl StoredResponse {
+    /// Capture the response of an API handler
+    pub fn from_result<T: Serialize>(
+        result: std::result::Result<Json<ApiResponse<T>>, StatusCode>,
+    ) -> Self {
+        match result {
+            Ok(Json(response)) => Self {
+                status: StatusCode::OK,
+                body: serde_json::to_value(response).unwrap_or(serde_json::Value::Null),
+            },
+            Err(status) => Self {
+                status,
+                body: serde_json::Value::Null,
+            },
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::to_value(ApiResponse::<()>::error(message.to_string()))
+                .unwrap_or(serde_json::Value::Null),
+        }
+    }
+
+    /// Id of the created resource, if the response carries one
+    fn resource_id(&self) -> Option<Uuid> {
+        self.body
+            .get("data")
+            .and_then(|data| data.get("id"))
+            .and_then(|id| id.as_str())
+            .and_then(|id| id.parse().ok())
+    }
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> Response {
+        if self.body.is_null() {
+            self.status.into_response()
+        } else {
+            (self.status, Json(self.body)).into_response()
+        }
+    }
+}
+
+/// Run `action` at most once per idempotency key
+///
+/// - First use: the key is claimed, the action runs and its response is stored.
+///   Client errors (4xx) are stored and replayed too; server errors release the
+///   key so a retry can act.
+/// - Retry after completion: the stored response is replayed.
+/// - Retry while the first request is still running: waits for it to finish,
+///   then replays; answers 409 if it does not finish in time.
+/// - Same key with a different endpoint or body: 422.
+/// - The request is cancelled (client disconnect, shutdown): the key is
+///   released so a retry can act.
+pub async fn run_idempotent<S, F, Fut>(
+    store: Arc<S>,
+    request: &IdempotentRequest,
+    retention: Duration,
+    action: F,
+) -> StoredResponse
+where
+    S: IdempotencyStore + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = StoredResponse>,
+{
+    let now = chrono::Utc::now();
+    let record = IdempotencyRecord {
+        scope: request.scope.clone(),
+        key: request.key.clone(),
+        endpoint: request.endpoint.to_string(),
+        request_hash: request.request_hash.clone(),
+        resource_id: None,
+        status_code: None,
+        response_body: None,
+        created_at: now,
+        expires_at: now
+            + chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::days(1)),
+    };
+
+    let deadline = tokio::time::Instant::now() + IN_FLIGHT_WAIT;
+    loop {
+        match store.claim(&record, IN_FLIGHT_LIMIT).await {
+            Ok(true) => return execute(store, request, action).await,
+            Ok(false) => {}
+            Err(e) => {
+                error!("Failed to claim idempotency key: {}", e);
+                return StoredResponse {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: serde_json::Value::Null,
+                };
+            }
+        }
+
+        match store.get(&request.scope, &request.key).await {
+            Ok(Some(existing)) => {
+                // NIST SI-10: A key identifies one request; reuse with other input is a client bug
+                if existing.endpoint != request.endpoint
+                    || existing.request_hash != request.request_hash
+                {
+                    warn!(
+                        "Idempotency key reused with a different request on {}",
+                        request.endpoint
+                    );
+                    return StoredResponse::error(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "Idempotency-Key was already used with a different request",
+                    );
+                }
+
+                if let Some(status) = existing
+                    .status_code
+                    .and_then(|code| StatusCode::from_u16(code).ok())
+                {
+                    return StoredResponse {
+                        status,
+                        body: existing.response_body.unwrap_or(serde_json::Value::Null),
+                    };
+                }
+            }
+            // Released or expired since the claim failed; try to claim again
+            Ok(None) => continue,
+            Err(e) => {
+                error!("Failed to read idempotency key: {}", e);
+                return StoredResponse {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: serde_json::Value::Null,
+                };
+            }
+        }
+
+        // NIST SC-5: Bound the time a duplicate holds its connection open
+        if tokio::time::Instant::now() >= deadline {
+            return StoredResponse::error(
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still in progress",
+            );
+        }
+        tokio::time::sleep(IN_FLIGHT_POLL_INTERVAL).await;
+    }
+}
+
+/// Releases a claimed key if the request is dropped before its outcome is stored
+struct ClaimGuard<S: IdempotencyStore + 'static> {
+    store: Arc<S>,
+    scope: String,
+    key: String,
+    armed: bool,
+}
+
+impl<S: IdempotencyStore + 'static> Drop for ClaimGuard<S> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        // Drop cannot await; hand the release to the runtime. Without one the
+        // claim is left for IN_FLIGHT_LIMIT to expire.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let store = self.store.clone();
+        let scope = std::mem::take(&mut self.scope);
+        let key = std::mem::take(&mut self.key);
+        runtime.spawn(async move {
+            if let Err(e) = store.release(&scope, &key).await {
+                error!("Failed to release abandoned idempotency key: {}", e);
+            }
+        });
+    }
+}
+
+async fn execute<S, F, Fut>(store: Arc<S>, request: &IdempotentRequest, action: F) -> StoredResponse
+where
+    S: IdempotencyStore + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = StoredResponse>,
+{
+    let mut guard = ClaimGuard {
+        store: store.clone(),
+        scope: request.scope.clone(),
+        key: request.key.clone(),
+        armed: true,
+    };
+
+    let response = action().await;
+
+    let stored = if response.status.is_server_error() {
+        store.release(&request.scope, &request.key).await
+    } else {
+        store
+            .complete(
+                &request.scope,
+                &request.key,
+                response.resource_id(),
+                response.status.as_u16(),
+                &response.body,
+            )
+            .await
+    };
+
+    guard.armed = false;
+
+    if let Err(e) = stored {
+        error!(
+            "Failed to record idempotency key for {}: {}",
+            request.endpoint, e
+        );
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        records: Mutex<HashMap<(String, String), IdempotencyRecord>>,
+    }
+
+    impl IdempotencyStore for MemoryStore {
+        async fn claim(
+            &self,
+            record: &IdempotencyRecord,
+            in_flight_limit: Duration,
+        ) -> Result<bool> {
+            let mut records = self.records.lock().await;
+            let key = (record.scope.clone(), record.key.clone());
+            let stale_before =
+                chrono::Utc::now() - chrono::Duration::from_std(in_flight_limit).unwrap();
+            if records
+                .get(&key)
+                .is_some_and(|r| r.status_code.is_some() || r.created_at > stale_before)
+            {
+                return Ok(false);
+            }
+            records.insert(key, record.clone());
+            Ok(true)
+        }
+
+        async fn get(&self, scope: &str, key: &str) -> Result<Option<IdempotencyRecord>> {
+            let records = self.records.lock().await;
+            Ok(records.get(&(scope.to_string(), key.to_string())).cloned())
+        }
+
+        async fn complete(
+            &self,
+            scope: &str,
+            key: &str,
+            resource_id: Option<Uuid>,
+            status_code: u16,
+            response_body: &serde_json::Value,
+        ) -> Result<()> {
+            let mut records = self.records.lock().await;
+            if let Some(record) = records.get_mut(&(scope.to_string(), key.to_string())) {
+                record.resource_id = resource_id;
+                record.status_code = Some(status_code);
+                record.response_body = Some(response_body.clone());
+            }
+            Ok(())
+        }
+
+        async fn release(&self, scope: &str, key: &str) -> Result<()> {
+            let mut records = self.records.lock().await;
+            records.remove(&(scope.to_string(), key.to_string()));
+            Ok(())
+        }
+    }
+
+    /// Stand-in for deployment creation: a new id per execution, or a
+    /// business-logic 409 once `existing` deployments reach `limit`
+    async fn create_deployment(created: &AtomicUsize, limit: usize) -> StoredResponse {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        if created.fetch_add(1, Ordering::SeqCst) >= limit {
+            return StoredResponse::error(StatusCode::CONFLICT, "Deployment already active");
+        }
+        StoredResponse::from_result(Ok(Json(ApiResponse::ok(serde_json::json!({
+            "id": Uuid::new_v4(),
+        })))))
+    }
+
+    fn guard(key: &str) -> IdempotentRequest {
+        IdempotentRequest {
+            scope: "api_key:test".to_string(),
+            key: key.to_string(),
+            endpoint: "POST /api/deployments",
+            request_hash: request_hash(&serde_json::json!({ "machine_id": 1 })),
+        }
+    }
+
+    const RETENTION: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn test_duplicate_request_replays_original_response() {
+        let store = Arc::new(MemoryStore::default());
+        let created = AtomicUsize::new(0);
+
+        let first = run_idempotent(store.clone(), &guard("retry-1"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+        let second = run_idempotent(store.clone(), &guard("retry-1"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+
+        assert_eq!(first.status, StatusCode::OK);
+        assert!(first.resource_id().is_some());
+        assert_eq!(second, first);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicates_execute_once() {
+        let store = Arc::new(MemoryStore::default());
+        let created = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let created = created.clone();
+                tokio::spawn(async move {
+                    run_idempotent(store.clone(), &guard("concurrent"), RETENTION, || {
+                        create_deployment(&created, usize::MAX)
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        let mut ids = Vec::new();
+        for task in tasks {
+            let response = task.await.unwrap();
+            assert_eq!(response.status, StatusCode::OK);
+            ids.push(response.resource_id().unwrap());
+        }
+
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert!(ids.iter().all(|id| *id == ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_different_key_acts_again() {
+        let store = Arc::new(MemoryStore::default());
+        let created = AtomicUsize::new(0);
+
+        let first = run_idempotent(store.clone(), &guard("key-a"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+        let second = run_idempotent(store.clone(), &guard("key-b"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+        assert_ne!(first.resource_id(), second.resource_id());
+
+        // With the machine now busy, a third key reaches the business-logic conflict
+        let third = run_idempotent(store.clone(), &guard("key-c"), RETENTION, || {
+            create_deployment(&created, 2)
+        })
+        .await;
+        assert_eq!(third.status, StatusCode::CONFLICT);
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_replayed_and_server_errors_released() {
+        let store = Arc::new(MemoryStore::default());
+        let runs = AtomicUsize::new(0);
+
+        let rejected = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            StoredResponse::error(StatusCode::NOT_FOUND, "Machine not found")
+        };
+        let first =
+            run_idempotent(store.clone(), &guard("client-error"), RETENTION, rejected).await;
+        let replay =
+            run_idempotent(store.clone(), &guard("client-error"), RETENTION, rejected).await;
+        assert_eq!(replay, first);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let failing = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            StoredResponse {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: serde_json::Value::Null,
+            }
+        };
+        run_idempotent(store.clone(), &guard("server-error"), RETENTION, failing).await;
+        run_idempotent(store.clone(), &guard("server-error"), RETENTION, failing).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_key_reuse_with_different_body_is_rejected() {
+        let store = Arc::new(MemoryStore::default());
+        let created = AtomicUsize::new(0);
+
+        run_idempotent(store.clone(), &guard("reused"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+
+        let mut other = guard("reused");
+        other.request_hash = request_hash(&serde_json::json!({ "machine_id": 2 }));
+        let response = run_idempotent(store.clone(), &other, RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_keys_are_scoped_per_api_key() {
+        let user = snow_owl_core::User {
+            id: Uuid::new_v4(),
+            username: "automation".to_string(),
+            role: snow_owl_core::UserRole::Operator,
+            created_at: chrono::Utc::now(),
+            last_login: None,
+        };
+        let key_a = AuthUser {
+            user: user.clone(),
+            api_key_id: Some(Uuid::new_v4()),
+        };
+        let key_b = AuthUser {
+            user,
+            api_key_id: Some(Uuid::new_v4()),
+        };
+
+        assert_ne!(scope_for(Some(&key_a)), scope_for(Some(&key_b)));
+        assert_eq!(scope_for(None), "anonymous");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_action_releases_claim() {
+        let store = Arc::new(MemoryStore::default());
+        let created = Arc::new(AtomicUsize::new(0));
+
+        // The client disconnects while the handler is still running
+        let pending = tokio::spawn({
+            let store = store.clone();
+            async move {
+                run_idempotent(store, &guard("cancelled"), RETENTION, || {
+                    std::future::pending::<StoredResponse>()
+                })
+                .await
+            }
+        });
+        while store
+            .get("api_key:test", "cancelled")
+            .await
+            .unwrap()
+            .is_none()
+        {
+            tokio::task::yield_now().await;
+        }
+        pending.abort();
+        assert!(pending.await.unwrap_err().is_cancelled());
+
+        // The retry acts instead of waiting out the abandoned claim
+        let retry = run_idempotent(store.clone(), &guard("cancelled"), RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+        assert_eq!(retry.status, StatusCode::OK);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_claim_is_reclaimed_after_in_flight_limit() {
+        let store = Arc::new(MemoryStore::default());
+        let created = AtomicUsize::new(0);
+
+        // A claim left behind by a request that never finished or released it
+        let claimed_at =
+            chrono::Utc::now() - chrono::Duration::from_std(IN_FLIGHT_LIMIT * 2).unwrap();
+        let request = guard("abandoned");
+        store.records.lock().await.insert(
+            (request.scope.clone(), request.key.clone()),
+            IdempotencyRecord {
+                scope: request.scope.clone(),
+                key: request.key.clone(),
+                endpoint: request.endpoint.to_string(),
+                request_hash: request.request_hash.clone(),
+                resource_id: None,
+                status_code: None,
+                response_body: None,
+                created_at: claimed_at,
+                expires_at: claimed_at + chrono::Duration::days(1),
+            },
+        );
+
+        let response = run_idempotent(store.clone(), &request, RETENTION, || {
+            create_deployment(&created, usize::MAX)
+        })
+        .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+}
@@ -3,9 +3,11 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use snow_owl_core::{MacAddress, Machine};
+use snow_owl_core::{Deployment, MacAddress, Machine, ServerConfig, SnowOwlError, WindowsImage};
+use std::net::IpAddr;
 
 use crate::AppState;
+use crate::template::{self, TemplateContext, TemplateError};
 
 /// Generate the main iPXE boot menu
 pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoResponse, StatusCode> {
@@ -14,6 +16,17 @@ pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoRespons
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // Differential images boot with their base images listed first; one
+    // whose chain does not resolve is left out of the menu
+    let mut entries = Vec::with_capacity(images.len());
+    for image in images {
+        match state.db.get_image_chain(image.id).await {
+            Ok(chain) => entries.push((image, chain_ids(&chain))),
+            Err(e) => tracing::warn!("Leaving image {} out of the boot menu: {}", image.name, e),
+        }
+    }
+    let images = entries;
+
     let server_ip = state.config.network.server_ip;
     let http_port = state.config.http_port;
 
@@ -26,7 +39,7 @@ pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoRespons
     if images.is_empty() {
         menu.push_str("item --gap -- No images available\n");
     } else {
-        for (idx, image) in images.iter().enumerate() {
+        for (idx, (image, _)) in images.iter().enumerate() {
             menu.push_str(&format!("item image{} {}\n", idx, image.name));
         }
     }
@@ -38,7 +51,7 @@ pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoRespons
     menu.push_str("goto ${selected}\n\n");
 
     // Generate boot entries for each image
-    for (idx, image) in images.iter().enumerate() {
+    for (idx, (image, chain)) in images.iter().enumerate() {
         menu.push_str(&format!(":image{}\n", idx));
         menu.push_str(&format!(
             "echo Booting {} ({})\n",
@@ -48,6 +61,8 @@ pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoRespons
             server_ip,
             http_port,
             &image.id.to_string(),
+            chain,
+            None,
         ));
         menu.push('\n');
     }
@@ -63,6 +78,8 @@ pub async fn boot_menu(State(state): State<AppState>) -> Result<impl IntoRespons
 
 /// Generate boot script for a specific MAC address
 /// This can be used for machine-specific deployments
+///
+/// See [`render_boot_script`] for what is served.
 pub async fn boot_mac(
     State(state): State<AppState>,
     Path(mac): Path<String>,
@@ -78,7 +95,10 @@ pub async fn boot_mac(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    if let Some(machine) = machine {
+    let server_ip = state.config.network.server_ip;
+    let http_port = state.config.http_port;
+
+    let (machine, assignment) = if let Some(machine) = machine {
         // Update last seen
         let mut updated_machine = machine.clone();
         updated_machine.last_seen = chrono::Utc::now();
@@ -92,36 +112,29 @@ pub async fn boot_mac(
             })?;
 
         // Check for active deployment
-        if let Some(deployment) = state
+        let assignment = match state
             .db
             .get_active_deployment_for_machine(machine.id)
             .await
             .unwrap()
         {
-            let image = state
-                .db
-                .get_image_by_id(deployment.image_id)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Failed to get image: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?
-                .ok_or(StatusCode::NOT_FOUND)?;
-
-            let server_ip = state.config.network.server_ip;
-            let http_port = state.config.http_port;
-
-            let mut script = String::from("#!ipxe\n\n");
-            script.push_str(&format!("# Deployment for {}\n", mac_addr));
-            script.push_str(&format!("echo Deploying image: {}\n", image.name));
-            script.push_str(&generate_winpe_boot(
-                server_ip,
-                http_port,
-                &image.id.to_string(),
-            ));
-
-            return Ok((StatusCode::OK, [("Content-Type", "text/plain")], script));
-        }
+            Some(deployment) => {
+                let mut chain = match state.db.get_image_chain(deployment.image_id).await {
+                    Ok(chain) => chain,
+                    Err(SnowOwlError::ImageNotFound(_)) => return Err(StatusCode::NOT_FOUND),
+                    Err(e) => {
+                        tracing::error!("Failed to resolve image chain: {}", e);
+                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    }
+                };
+                // The deployed image is last, after its base images
+                let image = chain.pop().ok_or(StatusCode::NOT_FOUND)?;
+                Some((deployment, image, chain))
+            }
+            None => None,
+        };
+
+        (updated_machine, assignment)
     } else {
         // Register new machine
         let new_machine = Machine {
@@ -129,6 +142,7 @@ pub async fn boot_mac(
             mac_address: mac_addr,
             hostname: None,
             ip_address: None,
+            kernel_args: None,
             last_seen: chrono::Utc::now(),
             created_at: chrono::Utc::now(),
         };
@@ -141,37 +155,374 @@ pub async fn boot_mac(
                 tracing::error!("Failed to create machine: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+
+        (new_machine, None)
+    };
+
+    let template = load_template(&state.config).await.map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let script = render_boot_script(&BootRender {
+        server_ip,
+        http_port,
+        machine: &machine,
+        assignment: assignment
+            .as_ref()
+            .map(|(deployment, image, _)| (deployment, image)),
+        base_images: assignment
+            .as_ref()
+            .map_or(&[], |(_, _, base_images)| base_images.as_slice()),
+        template: template.as_deref(),
+    })
+    .map_err(|e| {
+        tracing::error!("Failed to render iPXE template for {}: {}", mac_addr, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((StatusCode::OK, [("Content-Type", "text/plain")], script))
+}
+
+/// Read the operator-supplied `ipxe_template`, if one is configured
+///
+/// NIST CM-6: Configuration Settings (template read per request so edits apply immediately)
+pub(crate) async fn load_template(config: &ServerConfig) -> snow_owl_core::Result<Option<String>> {
+    let Some(template_path) = &config.ipxe_template else {
+        return Ok(None);
+    };
+
+    tokio::fs::read_to_string(template_path)
+        .await
+        .map(Some)
+        .map_err(|e| {
+            SnowOwlError::InvalidConfig(format!(
+                "Failed to read iPXE template {}: {}",
+                template_path.display(),
+                e
+            ))
+        })
+}
+
+/// Everything a `/boot/:mac` script is rendered from
+pub(crate) struct BootRender<'a> {
+    pub server_ip: IpAddr,
+    pub http_port: u16,
+    pub machine: &'a Machine,
+    pub assignment: Option<(&'a Deployment, &'a WindowsImage)>,
+    /// Base images the assigned image is a delta on, base first; empty for
+    /// a full image
+    pub base_images: &'a [WindowsImage],
+    /// Contents of `ipxe_template`, when configured
+    pub template: Option<&'a str>,
+}
+
+/// Render the per-machine boot script
+///
+/// This is the only place `/boot/:mac` scripts are produced; deployment
+/// dry-runs call it too, so a preview cannot drift from what machines boot.
+/// With a template, it is rendered whether or not a deployment is assigned;
+/// otherwise the built-in deployment script or a menu redirect is returned.
+pub(crate) fn render_boot_script(render: &BootRender<'_>) -> Result<String, TemplateError> {
+    if let Some(template) = render.template {
+        let context = machine_context(
+            render.server_ip,
+            render.http_port,
+            render.machine,
+            render.assignment,
+            render.base_images,
+        );
+        return template::render(template, &context);
+    }
+
+    if let Some((deployment, image)) = render.assignment {
+        let mut script = String::from("#!ipxe\n\n");
+        script.push_str(&format!(
+            "# Deployment for {}\n",
+            render.machine.mac_address
+        ));
+        script.push_str(&format!("echo Deploying image: {}\n", image.name));
+        script.push_str(&generate_winpe_boot(
+            render.server_ip,
+            render.http_port,
+            &image.id.to_string(),
+            &chain_ids(render.base_images.iter().chain([image])),
+            deployment.registration_token.as_deref(),
+        ));
+        return Ok(script);
     }
 
     // No active deployment, redirect to main menu
-    let redirect_script = format!(
+    Ok(format!(
         "#!ipxe\nchain http://{}:{}/boot.ipxe\n",
-        state.config.network.server_ip, state.config.http_port
+        url_host(render.server_ip),
+        render.http_port
+    ))
+}
+
+/// Build the variables available to a per-machine iPXE template
+///
+/// Deployment variables are empty strings when no deployment is assigned,
+/// so templates can branch with iPXE's `isset ${...}`. `image_chain` lists
+/// the IDs of the images to apply, comma-separated, base first and the
+/// assigned image last. `registration_token` is the secret the WinPE agent
+/// presents to `POST /api/machines/register`.
+fn machine_context(
+    server_ip: IpAddr,
+    http_port: u16,
+    machine: &Machine,
+    assignment: Option<(&Deployment, &WindowsImage)>,
+    base_images: &[WindowsImage],
+) -> TemplateContext {
+    let host = url_host(server_ip);
+    let mut context = TemplateContext::new();
+
+    context.set("server_ip", &host);
+    context.set("http_port", http_port.to_string());
+    context.set("base_url", format!("http://{}:{}", host, http_port));
+
+    context.set("machine_id", machine.id.to_string());
+    context.set("mac", machine.mac_address.to_string_colon());
+    context.set("mac_dash", machine.mac_address.to_string_dash());
+    context.set("hostname", machine.hostname.as_deref().unwrap_or_default());
+    context.set(
+        "ip_address",
+        machine
+            .ip_address
+            .map(|ip| ip.to_string())
+            .unwrap_or_default(),
+    );
+    context.set(
+        "kernel_args",
+        machine.kernel_args.as_deref().unwrap_or_default(),
     );
 
-    Ok((
-        StatusCode::OK,
-        [("Content-Type", "text/plain")],
-        redirect_script,
-    ))
+    let (deployment_id, image_id, image_name, image_type, image_path, driver_manifest_url) =
+        match assignment {
+            Some((deployment, image)) => (
+                deployment.id.to_string(),
+                image.id.to_string(),
+                image.name.clone(),
+                image.image_type.to_string(),
+                image.file_path.display().to_string(),
+                format!(
+                    "http://{}:{}/api/deployments/{}/drivers",
+                    host, http_port, deployment.id
+                ),
+            ),
+            None => Default::default(),
+        };
+    context.set("deployment_id", deployment_id);
+    context.set("image_id", image_id);
+    context.set("image_name", image_name);
+    context.set("image_type", image_type);
+    context.set("image_path", image_path);
+    context.set("driver_manifest_url", driver_manifest_url);
+    context.set(
+        "registration_token",
+        assignment
+            .and_then(|(deployment, _)| deployment.registration_token.as_deref())
+            .unwrap_or_default(),
+    );
+    context.set(
+        "image_chain",
+        assignment.map_or_else(String::new, |(_, image)| {
+            chain_ids(base_images.iter().chain([image]))
+        }),
+    );
+
+    context
+}
+
+/// Format an IP address for use as a URL host (IPv6 in brackets)
+pub(crate) fn url_host(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    }
+}
+
+/// Comma-separated IDs of a chain of images, in order
+fn chain_ids<'a>(chain: impl IntoIterator<Item = &'a WindowsImage>) -> String {
+    chain
+        .into_iter()
+        .map(|image| image.id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
-fn generate_winpe_boot(server_ip: std::net::IpAddr, http_port: u16, image_id: &str) -> String {
+/// WinPE boot stanza; `image_chain` is passed to the agent, which applies
+/// the listed images in order, and `registration_token` (menu boots have
+/// none) lets it register the machine
+fn generate_winpe_boot(
+    server_ip: IpAddr,
+    http_port: u16,
+    image_id: &str,
+    image_chain: &str,
+    registration_token: Option<&str>,
+) -> String {
     // For IPv6 addresses, we need to wrap them in brackets for URL formatting
-    let ip_str = match server_ip {
-        std::net::IpAddr::V4(ip) => ip.to_string(),
-        std::net::IpAddr::V6(ip) => format!("[{}]", ip),
-    };
+    let ip_str = url_host(server_ip);
+    let registration = registration_token
+        .map(|token| format!("set registration-token {}\n", token))
+        .unwrap_or_default();
 
     format!(
         r#"set base-url http://{}:{}
 set image-id {}
-kernel ${{base-url}}/winpe/wimboot
+set image-chain {}
+{}kernel ${{base-url}}/winpe/wimboot
 initrd ${{base-url}}/winpe/boot/bcd         BCD
 initrd ${{base-url}}/winpe/boot/boot.sdi    boot.sdi
 initrd ${{base-url}}/winpe/sources/boot.wim boot.wim
 boot
 "#,
-        ip_str, http_port, image_id
+        ip_str, http_port, image_id, image_chain, registration
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snow_owl_core::{DeploymentStatus, ImageType};
+    use std::net::Ipv4Addr;
+    use std::path::PathBuf;
+
+    const TEMPLATE: &str = "#!ipxe
+set base-url {{ base_url }}
+set hostname {{ hostname }}
+set image-id {{ image_id }}
+isset ${image-id} || chain ${base-url}/boot.ipxe
+echo Deploying {{ image_name }} to ${hostname}
+kernel ${base-url}/winpe/wimboot {{ kernel_args }}
+set drivers {{ driver_manifest_url }}
+boot
+";
+
+    fn machine() -> Machine {
+        Machine {
+            id: uuid::Uuid::new_v4(),
+            mac_address: "00:11:22:33:44:55".parse().unwrap(),
+            hostname: Some("lab-01".to_string()),
+            ip_address: None,
+            kernel_args: Some("console=ttyS0".to_string()),
+            last_seen: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn render_for(machine: &Machine, assignment: Option<(&Deployment, &WindowsImage)>) -> String {
+        let server_ip = IpAddr::V4(Ipv4Addr::new(192, 168, 100, 1));
+        let context = machine_context(server_ip, 8080, machine, assignment, &[]);
+        template::render(TEMPLATE, &context).unwrap()
+    }
+
+    #[test]
+    fn test_template_with_deployment() {
+        let machine = machine();
+        let image = WindowsImage {
+            id: uuid::Uuid::new_v4(),
+            name: "Server 2022".to_string(),
+            description: None,
+            image_type: ImageType::Wim,
+            file_path: PathBuf::from("/var/lib/snow-owl/images/server2022.wim"),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        let deployment = Deployment {
+            id: uuid::Uuid::new_v4(),
+            machine_id: machine.id,
+            image_id: image.id,
+            status: DeploymentStatus::Pending,
+            started_at: chrono::Utc::now(),
+            completed_at: None,
+            error_message: None,
+            driver_pack_ids: Vec::new(),
+            registration_token: None,
+            retry_count: 0,
+        };
+
+        let script = render_for(&machine, Some((&deployment, &image)));
+
+        assert!(script.contains("set base-url http://192.168.100.1:8080\n"));
+        assert!(script.contains("set hostname lab-01\n"));
+        assert!(script.contains(&format!("set image-id {}\n", image.id)));
+        assert!(script.contains("echo Deploying Server 2022 to ${hostname}\n"));
+        assert!(script.contains("kernel ${base-url}/winpe/wimboot console=ttyS0\n"));
+        assert!(script.contains(&format!(
+            "set drivers http://192.168.100.1:8080/api/deployments/{}/drivers\n",
+            deployment.id
+        )));
+        assert!(!script.contains("{{"));
+    }
+
+    #[test]
+    fn test_delta_image_boots_with_base_first() {
+        let machine = machine();
+        let base = WindowsImage {
+            id: uuid::Uuid::new_v4(),
+            name: "Server 2022 base".to_string(),
+            description: None,
+            image_type: ImageType::Vhdx,
+            file_path: PathBuf::from("/var/lib/snow-owl/images/base.vhdx"),
+            size_bytes: 0,
+            created_at: chrono::Utc::now(),
+            checksum: None,
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        };
+        let delta = WindowsImage {
+            id: uuid::Uuid::new_v4(),
+            name: "Server 2022 IIS".to_string(),
+            file_path: PathBuf::from("/var/lib/snow-owl/images/iis.vhdx"),
+            parent_image_id: Some(base.id),
+            ..base.clone()
+        };
+        let deployment = Deployment::pending(machine.id, delta.id, Vec::new());
+        let chain = format!("{},{}", base.id, delta.id);
+
+        let render = |template| {
+            render_boot_script(&BootRender {
+                server_ip: IpAddr::V4(Ipv4Addr::new(192, 168, 100, 1)),
+                http_port: 8080,
+                machine: &machine,
+                assignment: Some((&deployment, &delta)),
+                base_images: std::slice::from_ref(&base),
+                template,
+            })
+            .unwrap()
+        };
+
+        let script = render(None);
+        assert!(script.contains(&format!("set image-id {}\n", delta.id)));
+        assert!(script.contains(&format!(
+            "set registration-token {}\n",
+            deployment.registration_token.as_deref().unwrap()
+        )));
+        assert!(script.contains(&format!("set image-chain {}\n", chain)));
+        assert_eq!(
+            render(Some("set layers {{ image_chain }}\n")),
+            format!("set layers {}\n", chain)
+        );
+    }
+
+    #[test]
+    fn test_template_without_deployment() {
+        let mut machine = machine();
+        machine.hostname = None;
+        machine.kernel_args = None;
+
+        let script = render_for(&machine, None);
+
+        assert!(script.contains("set image-id \n"));
+        assert!(script.contains("set hostname \n"));
+        assert!(script.contains("isset ${image-id} || chain ${base-url}/boot.ipxe\n"));
+        assert!(script.contains("kernel ${base-url}/winpe/wimboot \n"));
+        assert!(script.contains("set drivers \n"));
+        assert!(!script.contains("{{"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Leader election for singleton background tasks
+//!
+//! When several HTTP server replicas share one database, periodic jobs such
+//! as the idempotency-key purge must run in exactly one of them. Each replica
+//! runs a [`LeaderElection`] that keeps trying to take a named database lock;
+//! the replica holding it runs the tasks, and the others take over once the
+//! lock is released on shutdown or dropped with a crashed replica's session.
+//!
+//! NIST Controls:
+//! - SC-24: Fail in Known State (leadership ends with the lock session)
+//! - SI-7: Software, Firmware, and Information Integrity (no double processing)
+
+use snow_owl_core::Result;
+use snow_owl_db::{AdvisoryLock, Database};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How often followers retry the lock and leaders check they still hold it
+pub const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Source of named, exclusive locks shared by all replicas
+pub trait LockBackend: Send + Sync + 'static {
+    type Lock: HeldLock;
+
+    /// Take the lock if no one else holds it; never waits
+    fn try_acquire(&self, name: &str) -> impl Future<Output = Result<Option<Self::Lock>>> + Send;
+}
+
+/// A lock currently held by this replica
+pub trait HeldLock: Send + 'static {
+    /// False once the lock has been lost (e.g. the database session died)
+    fn is_held(&mut self) -> impl Future<Output = bool> + Send;
+
+    /// Give the lock up so another replica can take over
+    fn release(self) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl LockBackend for Database {
+    type Lock = AdvisoryLock;
+
+    async fn try_acquire(&self, name: &str) -> Result<Option<AdvisoryLock>> {
+        self.try_advisory_lock(name).await
+    }
+}
+
+impl HeldLock for AdvisoryLock {
+    async fn is_held(&mut self) -> bool {
+        AdvisoryLock::is_held(self).await
+    }
+
+    async fn release(self) -> Result<()> {
+        AdvisoryLock::release(self).await
+    }
+}
+
+/// Runs background tasks only while holding a named lock
+pub struct LeaderElection<B: LockBackend> {
+    backend: Arc<B>,
+    name: String,
+    retry_interval: Duration,
+}
+
+impl<B: LockBackend> LeaderElection<B> {
+    pub fn new(backend: Arc<B>, name: impl Into<String>) -> Self {
+        Self {
+            backend,
+            name: name.into(),
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+        }
+    }
+
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Start campaigning; `start_tasks` is called each time leadership is won
+    ///
+    /// The returned tasks are aborted when leadership is lost or the
+    /// election is stopped.
+    pub fn spawn<F>(self, start_tasks: F) -> LeaderHandle
+    where
+        F: Fn() -> Vec<JoinHandle<()>> + Send + Sync + 'static,
+    {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let is_leader = Arc::new(AtomicBool::new(false));
+        let task = tokio::spawn(self.run(start_tasks, stop_rx, is_leader.clone()));
+
+        LeaderHandle {
+            stop_tx,
+            is_leader,
+            task,
+        }
+    }
+
+    async fn run<F>(
+        self,
+        start_tasks: F,
+        mut stop_rx: watch::Receiver<bool>,
+        is_leader: Arc<AtomicBool>,
+    ) where
+        F: Fn() -> Vec<JoinHandle<()>> + Send + Sync + 'static,
+    {
+        loop {
+            let lock = match self.backend.try_acquire(&self.name).await {
+                Ok(Some(lock)) => lock,
+                Ok(None) => {
+                    if wait_or_stop(&mut stop_rx, self.retry_interval).await {
+                        return;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to acquire leader lock '{}': {}", self.name, e);
+                    if wait_or_stop(&mut stop_rx, self.retry_interval).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            info!(
+                "Acquired leader lock '{}'; starting background tasks",
+                self.name
+            );
+            is_leader.store(true, Ordering::SeqCst);
+            let tasks = start_tasks();
+
+            let (stopped, lock) = self.hold(lock, &mut stop_rx).await;
+
+            for task in &tasks {
+                task.abort();
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+            is_leader.store(false, Ordering::SeqCst);
+
+            if stopped {
+                if let Some(lock) = lock
+                    && let Err(e) = lock.release().await
+                {
+                    warn!("Failed to release leader lock '{}': {}", self.name, e);
+                }
+                info!("Released leader lock '{}'", self.name);
+                return;
+            }
+        }
+    }
+
+    /// Keep the lock until stopped (returns true) or lost (returns false)
+    async fn hold(
+        &self,
+        mut lock: B::Lock,
+        stop_rx: &mut watch::Receiver<bool>,
+    ) -> (bool, Option<B::Lock>) {
+        loop {
+            if wait_or_stop(stop_rx, self.retry_interval).await {
+                return (true, Some(lock));
+            }
+            if !lock.is_held().await {
+                warn!(
+                    "Lost leader lock '{}'; stopping background tasks",
+                    self.name
+                );
+                return (false, None);
+            }
+        }
+    }
+}
+
+/// Sleep for `interval`; returns true if a stop was requested meanwhile
+async fn wait_or_stop(stop_rx: &mut watch::Receiver<bool>, interval: Duration) -> bool {
+    if *stop_rx.borrow() {
+        return true;
+    }
+    tokio::select! {
+        changed = stop_rx.changed() => changed.is_err() || *stop_rx.borrow(),
+        _ = tokio::time::sleep(interval) => false,
+    }
+}
+
+/// Handle to a running [`LeaderElection`]
+pub struct LeaderHandle {
+    stop_tx: watch::Sender<bool>,
+    is_leader: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl LeaderHandle {
+    /// True while this replica holds the lock and runs the tasks
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    /// Stop the tasks and release the lock so another replica can take over
+    pub async fn stop(self) {
+        let _ = self.stop_tx.send(true);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    /// In-memory stand-in for Postgres advisory locks
+    #[derive(Default)]
+    struct MemoryLocks {
+        held: Arc<Mutex<HashSet<String>>>,
+    }
+
+    struct MemoryLock {
+        held: Arc<Mutex<HashSet<String>>>,
+        name: String,
+    }
+
+    impl LockBackend for MemoryLocks {
+        type Lock = MemoryLock;
+
+        async fn try_acquire(&self, name: &str) -> Result<Option<MemoryLock>> {
+            let acquired = self.held.lock().unwrap().insert(name.to_string());
+            Ok(acquired.then(|| MemoryLock {
+                held: self.held.clone(),
+                name: name.to_string(),
+            }))
+        }
+    }
+
+    impl HeldLock for MemoryLock {
+        async fn is_held(&mut self) -> bool {
+            self.held.lock().unwrap().contains(&self.name)
+        }
+
+        async fn release(self) -> Result<()> {
+            self.held.lock().unwrap().remove(&self.name);
+            Ok(())
+        }
+    }
+
+    fn replica(
+        backend: &Arc<MemoryLocks>,
+        runs: &Arc<Mutex<Vec<&'static str>>>,
+        id: &'static str,
+    ) -> LeaderHandle {
+        let runs = runs.clone();
+        LeaderElection::new(backend.clone(), "scheduler")
+            .with_retry_interval(Duration::from_millis(10))
+            .spawn(move || {
+                runs.lock().unwrap().push(id);
+                vec![tokio::spawn(std::future::pending())]
+            })
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    #[tokio::test]
+    async fn test_only_one_replica_leads() {
+        let backend = Arc::new(MemoryLocks::default());
+        let runs = Arc::new(Mutex::new(Vec::new()));
+
+        let a = replica(&backend, &runs, "a");
+        wait_until(|| a.is_leader()).await;
+        let b = replica(&backend, &runs, "b");
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(a.is_leader());
+        assert!(!b.is_leader());
+        assert_eq!(*runs.lock().unwrap(), vec!["a"]);
+
+        a.stop().await;
+        b.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_over_after_release() {
+        let backend = Arc::new(MemoryLocks::default());
+        let runs = Arc::new(Mutex::new(Vec::new()));
+
+        let a = replica(&backend, &runs, "a");
+        wait_until(|| a.is_leader()).await;
+        let b = replica(&backend, &runs, "b");
+
+        a.stop().await;
+        wait_until(|| b.is_leader()).await;
+
+        assert_eq!(*runs.lock().unwrap(), vec!["a", "b"]);
+        b.stop().await;
+        assert!(backend.held.lock().unwrap().is_empty());
+    }
+}
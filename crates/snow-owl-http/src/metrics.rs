@@ -0,0 +1,140 @@
+//! Counters for files served over HTTP
+//!
+//! NIST Controls:
+//! - SI-4: System Monitoring (download volume per area)
+//! - AU-12: Audit Generation (served byte counts)
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Directory a download was served from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileArea {
+    /// Registered images under `images_dir`
+    Images,
+    /// WinPE boot files under `winpe_dir`
+    Winpe,
+}
+
+/// Metrics registry shared by every request handler
+#[derive(Debug, Default)]
+pub struct Metrics {
+    images: DownloadCounters,
+    winpe: DownloadCounters,
+}
+
+impl Metrics {
+    pub(crate) fn downloads(&self, area: FileArea) -> &DownloadCounters {
+        match area {
+            FileArea::Images => &self.images,
+            FileArea::Winpe => &self.winpe,
+        }
+    }
+
+    pub fn snapshot(&self, area: FileArea) -> DownloadSnapshot {
+        self.downloads(area).read(false)
+    }
+
+    /// Snapshot one area's counters and restart them from zero
+    ///
+    /// Each counter is swapped to zero atomically, so a download recorded
+    /// while the reset runs is counted in this window or the next, never lost.
+    pub fn snapshot_and_reset(&self, area: FileArea) -> DownloadSnapshot {
+        self.downloads(area).read(true)
+    }
+}
+
+/// Download counters for one area
+#[derive(Debug, Default)]
+pub(crate) struct DownloadCounters {
+    requests: AtomicU64,
+    partial: AtomicU64,
+    not_modified: AtomicU64,
+    bytes_served: AtomicU64,
+}
+
+impl DownloadCounters {
+    pub(crate) fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_partial(&self) {
+        self.partial.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_not_modified(&self) {
+        self.not_modified.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count body bytes as they are handed to the connection
+    pub(crate) fn record_bytes(&self, bytes: u64) {
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn read(&self, reset: bool) -> DownloadSnapshot {
+        let take = |counter: &AtomicU64| {
+            if reset {
+                counter.swap(0, Ordering::Relaxed)
+            } else {
+                counter.load(Ordering::Relaxed)
+            }
+        };
+        DownloadSnapshot {
+            requests: take(&self.requests),
+            partial: take(&self.partial),
+            not_modified: take(&self.not_modified),
+            bytes_served: take(&self.bytes_served),
+        }
+    }
+}
+
+/// Point-in-time view of one area's downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DownloadSnapshot {
+    /// GET and HEAD requests for existing files
+    pub requests: u64,
+    /// 206 Partial Content replies
+    pub partial: u64,
+    /// 304 Not Modified replies
+    pub not_modified: u64,
+    /// Body bytes sent, including those of transfers cut short
+    pub bytes_served: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_and_reset() {
+        let metrics = Metrics::default();
+        let images = metrics.downloads(FileArea::Images);
+        images.record_request();
+        images.record_partial();
+        images.record_bytes(1024);
+        metrics.downloads(FileArea::Winpe).record_request();
+
+        let window = metrics.snapshot_and_reset(FileArea::Images);
+        assert_eq!(
+            window,
+            DownloadSnapshot {
+                requests: 1,
+                partial: 1,
+                not_modified: 0,
+                bytes_served: 1024,
+            }
+        );
+        assert_eq!(
+            metrics.snapshot(FileArea::Images),
+            DownloadSnapshot::default()
+        );
+        // Other areas keep their counts
+        assert_eq!(metrics.snapshot(FileArea::Winpe).requests, 1);
+
+        images.record_bytes(10);
+        assert_eq!(
+            metrics.snapshot_and_reset(FileArea::Images).bytes_served,
+            10
+        );
+        assert_eq!(metrics.snapshot(FileArea::Images).bytes_served, 0);
+    }
+}
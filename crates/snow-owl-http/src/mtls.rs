@@ -0,0 +1,364 @@
+//! Client-certificate (mutual TLS) authentication
+//!
+//! When `tls.client_ca_path` is set, the HTTPS listener asks every client
+//! for a certificate and completes the handshake only if it chains to that
+//! CA bundle. The verified certificate's subject and subject alternative
+//! names are attached to each request on the connection, so handlers can
+//! authorize by identity by taking a [`ClientIdentity`] (rejects requests
+//! without one) or an `Option<ClientIdentity>` argument.
+//!
+//! Without `client_ca_path` no certificate is requested, since iPXE cannot
+//! present one.
+//!
+//! NIST Controls:
+//! - IA-3: Device Identification and Authentication
+//! - IA-5(2): PKI-based Authentication
+//! - SC-17: Public Key Infrastructure Certificates
+//! - AC-3: Access Enforcement (identity available to handlers)
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{StatusCode, request::Parts},
+};
+use axum_server::{accept::Accept, tls_rustls::RustlsAcceptor};
+use rustls::RootCertStore;
+use rustls::crypto::CryptoProvider;
+use rustls::server::{WebPkiClientVerifier, danger::ClientCertVerifier};
+use rustls_pemfile::certs;
+use snow_owl_core::{Result, SnowOwlError};
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower_http::add_extension::AddExtension;
+use tracing::debug;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+/// Identity from the verified client certificate of the connection
+///
+/// NIST IA-3: Device Identification and Authentication
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Subject distinguished name, RFC 4514 style (`CN=agent, O=Example`)
+    pub subject: String,
+    /// First common name in the subject
+    pub common_name: Option<String>,
+    /// DNS names from the subject alternative name extension
+    pub dns_names: Vec<String>,
+    /// Email addresses from the subject alternative name extension
+    pub email_addresses: Vec<String>,
+    /// IP addresses from the subject alternative name extension
+    pub ip_addresses: Vec<IpAddr>,
+}
+
+impl ClientIdentity {
+    /// Read the identity from a DER certificate; `None` if it does not parse
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+
+        let mut identity = Self {
+            subject: cert.subject().to_string(),
+            common_name,
+            dns_names: Vec::new(),
+            email_addresses: Vec::new(),
+            ip_addresses: Vec::new(),
+        };
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => identity.dns_names.push(dns.to_string()),
+                    GeneralName::RFC822Name(email) => {
+                        identity.email_addresses.push(email.to_string())
+                    }
+                    GeneralName::IPAddress(bytes) => {
+                        if let Ok(octets) = <[u8; 4]>::try_from(*bytes) {
+                            identity.ip_addresses.push(IpAddr::from(octets));
+                        } else if let Ok(octets) = <[u8; 16]>::try_from(*bytes) {
+                            identity.ip_addresses.push(IpAddr::from(octets));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(identity)
+    }
+
+    /// Whether `name` is the common name or one of the DNS or email SANs
+    pub fn has_name(&self, name: &str) -> bool {
+        self.common_name.as_deref() == Some(name)
+            || self
+                .dns_names
+                .iter()
+                .any(|dns| dns.eq_ignore_ascii_case(name))
+            || self.email_addresses.iter().any(|email| email == name)
+    }
+}
+
+/// Connection-level extension read by the [`ClientIdentity`] extractors;
+/// empty when no certificate was presented
+#[derive(Debug, Clone)]
+pub struct PeerIdentity(Option<ClientIdentity>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIdentity {
+    type Rejection = StatusCode;
+
+    /// NIST AC-3: Requests without a verified certificate are refused
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        <Self as OptionalFromRequestParts<S>>::from_request_parts(parts, state)
+            .await?
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ClientIdentity {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Option<Self>, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<PeerIdentity>()
+            .and_then(|peer| peer.0.clone()))
+    }
+}
+
+/// Verifier requiring client certificates that chain to the CA bundle at `path`
+///
+/// NIST Controls:
+/// - IA-5(2): PKI-based Authentication
+/// - SI-10: Information Input Validation (CA bundle validation)
+pub(crate) fn load_client_verifier(
+    path: &Path,
+    provider: Arc<CryptoProvider>,
+) -> Result<Arc<dyn ClientCertVerifier>> {
+    let file = File::open(path)
+        .map_err(|e| SnowOwlError::Http(format!("Failed to open client CA file: {}", e)))?;
+    let ca_certs: Vec<_> = certs(&mut BufReader::new(file))
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| SnowOwlError::Http(format!("Failed to parse client CA: {}", e)))?;
+    if ca_certs.is_empty() {
+        return Err(SnowOwlError::Http(
+            "No certificates found in client CA file".to_string(),
+        ));
+    }
+
+    let mut roots = RootCertStore::empty();
+    for cert in ca_certs {
+        roots
+            .add(cert)
+            .map_err(|e| SnowOwlError::Http(format!("Invalid client CA certificate: {}", e)))?;
+    }
+    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+        .build()
+        .map_err(|e| SnowOwlError::Http(format!("Failed to build client verifier: {}", e)))
+}
+
+/// TLS acceptor that attaches the client certificate identity to requests
+#[derive(Debug, Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(inner: RustlsAcceptor) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, PeerIdentity>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let handshake = self.inner.accept(stream, service);
+        Box::pin(async move {
+            let (stream, service) = handshake.await?;
+            // The verifier has already checked the chain; only the leaf names the client
+            let identity = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|cert| ClientIdentity::from_der(cert));
+            if let Some(identity) = &identity {
+                debug!("TLS client authenticated as {}", identity.subject);
+            }
+            Ok((stream, AddExtension::new(service, PeerIdentity(identity))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpServer;
+    use axum::{Router, routing::get};
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair,
+    };
+    use rustls::ClientConfig;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+    use snow_owl_core::TlsConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+
+    fn temp_dir(label: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "snow-owl-http-mtls-{}-{}",
+            label,
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn ca() -> CertifiedIssuer<'static, KeyPair> {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Snow Owl Test CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+    }
+
+    /// Certificate and key for `common_name`, signed by `issuer` or self-signed
+    fn leaf(
+        common_name: &str,
+        names: &[&str],
+        issuer: Option<&CertifiedIssuer<'static, KeyPair>>,
+    ) -> (Certificate, KeyPair) {
+        let mut params =
+            CertificateParams::new(names.iter().map(|n| n.to_string()).collect::<Vec<_>>())
+                .unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        let key = KeyPair::generate().unwrap();
+        let cert = match issuer {
+            Some(issuer) => params.signed_by(&key, issuer).unwrap(),
+            None => params.self_signed(&key).unwrap(),
+        };
+        (cert, key)
+    }
+
+    async fn whoami(identity: ClientIdentity) -> String {
+        format!("{}|{}", identity.subject, identity.dns_names.join(","))
+    }
+
+    /// HTTPS server requiring client certificates from `ca`; returns its address
+    async fn start_server(ca: &CertifiedIssuer<'static, KeyPair>) -> std::net::SocketAddr {
+        let dir = temp_dir("server");
+        let (server_cert, server_key) = leaf("localhost", &["localhost"], Some(ca));
+        let tls = TlsConfig {
+            enabled: true,
+            cert_path: dir.join("server.pem"),
+            key_path: dir.join("server-key.pem"),
+            enable_http2: false,
+            client_ca_path: Some(dir.join("client-ca.pem")),
+        };
+        std::fs::write(&tls.cert_path, server_cert.pem()).unwrap();
+        std::fs::write(&tls.key_path, server_key.serialize_pem()).unwrap();
+        std::fs::write(tls.client_ca_path.as_ref().unwrap(), ca.pem()).unwrap();
+
+        let config = HttpServer::load_tls_config(&tls).unwrap();
+        let acceptor = ClientCertAcceptor::new(RustlsAcceptor::new(
+            axum_server::tls_rustls::RustlsConfig::from_config(Arc::new(config)),
+        ));
+        let app = Router::new().route("/whoami", get(whoami));
+        let handle = axum_server::Handle::new();
+        let server = axum_server::bind("127.0.0.1:0".parse().unwrap())
+            .acceptor(acceptor)
+            .handle(handle.clone());
+        tokio::spawn(server.serve(app.into_make_service()));
+        handle.listening().await.unwrap()
+    }
+
+    /// GET /whoami presenting `client`; `Err` if the handshake or request fails
+    async fn get_whoami(
+        addr: std::net::SocketAddr,
+        ca: &CertifiedIssuer<'static, KeyPair>,
+        client: (Certificate, KeyPair),
+    ) -> io::Result<String> {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        let (cert, key) = client;
+        let config = ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::aws_lc_rs::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_client_auth_cert(
+            vec![cert.der().clone()],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+        )
+        .unwrap();
+
+        let tcp = tokio::net::TcpStream::connect(addr).await?;
+        let mut tls = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await?;
+        tls.write_all(b"GET /whoami HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        tls.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_from_ca_is_accepted() {
+        let ca = ca();
+        let addr = start_server(&ca).await;
+
+        let client = leaf("imaging-agent", &["agent.example.test"], Some(&ca));
+        let response = get_whoami(addr, &ca, client).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(
+            response.ends_with("CN=imaging-agent|agent.example.test"),
+            "{response}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_client_certificate_is_rejected() {
+        let ca = ca();
+        let addr = start_server(&ca).await;
+
+        // Self-signed, so it does not chain to the configured CA
+        let client = leaf("imaging-agent", &["agent.example.test"], None);
+        assert!(get_whoami(addr, &ca, client).await.is_err());
+    }
+
+    #[test]
+    fn test_identity_names() {
+        let ca = ca();
+        let (cert, _) = leaf("imaging-agent", &["Agent.Example.Test"], Some(&ca));
+        let identity = ClientIdentity::from_der(cert.der()).unwrap();
+        assert_eq!(identity.common_name.as_deref(), Some("imaging-agent"));
+        assert!(identity.has_name("imaging-agent"));
+        assert!(identity.has_name("agent.example.test"));
+        assert!(!identity.has_name("other.example.test"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Deployment Timeout Reconciler
+//!
+//! A machine powered off mid-apply never reports back, so its deployment
+//! stays active and `get_active_deployment_for_machine` refuses every new
+//! deployment for it. The reconciler periodically marks deployments that
+//! have been active longer than `deployment_timeout_minutes` as failed and,
+//! up to `deployment_retry_limit` times, creates a fresh deployment of the
+//! same image for the machine.
+//!
+//! NIST Controls:
+//! - SC-24: Fail in Known State (stuck deployments end as failed)
+//! - CM-3: Configuration Change Control (bounded automatic retries)
+
+use chrono::{DateTime, Utc};
+use snow_owl_core::{Deployment, DeploymentStatus, Result};
+use snow_owl_db::Database;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::events::{DeploymentEvent, DeploymentEvents};
+
+/// How often active deployments are checked against the timeout
+pub const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Storage for the deployments the reconciler inspects
+///
+/// Implemented by [`Database`]; kept as a trait so the reconciler can be
+/// exercised without PostgreSQL.
+pub trait DeploymentStore: Send + Sync {
+    /// Pending or in-progress deployments started before `started_before`
+    fn stale(
+        &self,
+        started_before: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<Deployment>>> + Send;
+
+    /// Mark a deployment failed with `reason`
+    fn fail(&self, id: Uuid, reason: String) -> impl Future<Output = Result<()>> + Send;
+
+    /// Store a new deployment
+    fn create(&self, deployment: &Deployment) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl DeploymentStore for Database {
+    async fn stale(&self, started_before: DateTime<Utc>) -> Result<Vec<Deployment>> {
+        self.list_stale_deployments(started_before).await
+    }
+
+    async fn fail(&self, id: Uuid, reason: String) -> Result<()> {
+        self.update_deployment_status(id, DeploymentStatus::Failed, Some(reason))
+            .await
+    }
+
+    async fn create(&self, deployment: &Deployment) -> Result<()> {
+        self.create_deployment(deployment).await
+    }
+}
+
+/// [`Database`] that also tells WebSocket and SSE clients about the
+/// deployments the reconciler fails and creates
+struct PublishingStore {
+    db: Arc<Database>,
+    events: DeploymentEvents,
+}
+
+impl DeploymentStore for PublishingStore {
+    async fn stale(&self, started_before: DateTime<Utc>) -> Result<Vec<Deployment>> {
+        self.db.stale(started_before).await
+    }
+
+    async fn fail(&self, id: Uuid, reason: String) -> Result<()> {
+        self.db.fail(id, reason.clone()).await?;
+        self.events.publish(DeploymentEvent::status_changed(
+            id,
+            DeploymentStatus::Failed,
+            Some(reason),
+        ));
+        Ok(())
+    }
+
+    async fn create(&self, deployment: &Deployment) -> Result<()> {
+        self.db.create(deployment).await?;
+        self.events.publish(DeploymentEvent::Created {
+            deployment: deployment.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// What one reconciliation pass did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Reconciled {
+    /// Deployments marked failed for exceeding the timeout
+    pub timed_out: Vec<Uuid>,
+    /// Deployments created to retry them
+    pub retried: Vec<Uuid>,
+}
+
+/// Fail every deployment active for longer than `timeout` and retry those
+/// that have not used up `retry_limit`
+pub async fn reconcile_deployments<S: DeploymentStore>(
+    store: &S,
+    timeout: Duration,
+    retry_limit: u32,
+    now: DateTime<Utc>,
+) -> Result<Reconciled> {
+    let cutoff = now - chrono::Duration::from_std(timeout).unwrap_or(chrono::Duration::MAX);
+    let mut reconciled = Reconciled::default();
+
+    for deployment in store.stale(cutoff).await? {
+        let reason = format!(
+            "Timed out after {} minutes (last status: {:?})",
+            timeout.as_secs() / 60,
+            deployment.status
+        );
+        warn!(
+            "Deployment {} for machine {}: {}",
+            deployment.id, deployment.machine_id, reason
+        );
+        store.fail(deployment.id, reason).await?;
+        reconciled.timed_out.push(deployment.id);
+
+        if deployment.retry_count < retry_limit {
+            let retry = deployment.retry();
+            store.create(&retry).await?;
+            info!(
+                "Retrying deployment {} as {} (retry {} of {})",
+                deployment.id, retry.id, retry.retry_count, retry_limit
+            );
+            reconciled.retried.push(retry.id);
+        }
+    }
+
+    Ok(reconciled)
+}
+
+/// Run [`reconcile_deployments`] every [`RECONCILE_INTERVAL`]
+pub fn spawn_deployment_reconciler(
+    db: Arc<Database>,
+    events: DeploymentEvents,
+    timeout: Duration,
+    retry_limit: u32,
+) -> tokio::task::JoinHandle<()> {
+    let store = PublishingStore { db, events };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = reconcile_deployments(&store, timeout, retry_limit, Utc::now()).await {
+                warn!("Failed to reconcile timed-out deployments: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        deployments: Mutex<Vec<Deployment>>,
+    }
+
+    impl DeploymentStore for MemoryStore {
+        async fn stale(&self, started_before: DateTime<Utc>) -> Result<Vec<Deployment>> {
+            let deployments = self.deployments.lock().await;
+            Ok(deployments
+                .iter()
+                .filter(|d| d.is_active() && d.started_at < started_before)
+                .cloned()
+                .collect())
+        }
+
+        async fn fail(&self, id: Uuid, reason: String) -> Result<()> {
+            let mut deployments = self.deployments.lock().await;
+            if let Some(deployment) = deployments.iter_mut().find(|d| d.id == id) {
+                deployment.status = DeploymentStatus::Failed;
+                deployment.completed_at = Some(Utc::now());
+                deployment.error_message = Some(reason);
+            }
+            Ok(())
+        }
+
+        async fn create(&self, deployment: &Deployment) -> Result<()> {
+            self.deployments.lock().await.push(deployment.clone());
+            Ok(())
+        }
+    }
+
+    impl MemoryStore {
+        async fn get(&self, id: Uuid) -> Deployment {
+            let deployments = self.deployments.lock().await;
+            deployments.iter().find(|d| d.id == id).unwrap().clone()
+        }
+    }
+
+    /// Deployment of a fresh machine and image that has been `status` since `started_at`
+    fn deployment(status: DeploymentStatus, started_at: DateTime<Utc>) -> Deployment {
+        Deployment {
+            status,
+            started_at,
+            ..Deployment::pending(Uuid::new_v4(), Uuid::new_v4(), vec![Uuid::new_v4()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stuck_deployment_times_out_and_retries() {
+        let store = MemoryStore::default();
+        let now = Utc::now();
+        let stuck = deployment(
+            DeploymentStatus::Installing,
+            now - chrono::Duration::minutes(3),
+        );
+        let recent = deployment(DeploymentStatus::Downloading, now);
+        let done = deployment(
+            DeploymentStatus::Completed,
+            now - chrono::Duration::hours(1),
+        );
+        for d in [&stuck, &recent, &done] {
+            store.create(d).await.unwrap();
+        }
+
+        let timeout = Duration::from_secs(120);
+        let reconciled = reconcile_deployments(&store, timeout, 1, now)
+            .await
+            .unwrap();
+        assert_eq!(reconciled.timed_out, vec![stuck.id]);
+        assert_eq!(reconciled.retried.len(), 1);
+
+        let failed = store.get(stuck.id).await;
+        assert_eq!(failed.status, DeploymentStatus::Failed);
+        assert!(failed.completed_at.is_some());
+        assert_eq!(
+            failed.error_message.as_deref(),
+            Some("Timed out after 2 minutes (last status: Installing)")
+        );
+
+        let retry = store.get(reconciled.retried[0]).await;
+        assert_eq!(retry.status, DeploymentStatus::Pending);
+        assert_eq!(retry.retry_count, 1);
+        assert_eq!(
+            (retry.machine_id, retry.image_id, &retry.driver_pack_ids),
+            (stuck.machine_id, stuck.image_id, &stuck.driver_pack_ids)
+        );
+        assert_ne!(retry.registration_token, stuck.registration_token);
+
+        // Untouched: still within the timeout, or already finished
+        assert_eq!(
+            store.get(recent.id).await.status,
+            DeploymentStatus::Downloading
+        );
+        assert_eq!(store.get(done.id).await.status, DeploymentStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_limit() {
+        let store = MemoryStore::default();
+        let timeout = Duration::from_secs(60);
+        let mut now = Utc::now();
+        store
+            .create(&deployment(DeploymentStatus::Booting, now))
+            .await
+            .unwrap();
+
+        // Each pass times out the latest attempt; the limit of 2 allows two retries
+        let mut retries = Vec::new();
+        for _ in 0..3 {
+            now += chrono::Duration::minutes(5);
+            let reconciled = reconcile_deployments(&store, timeout, 2, now)
+                .await
+                .unwrap();
+            assert_eq!(reconciled.timed_out.len(), 1);
+            retries.extend(reconciled.retried);
+        }
+        assert_eq!(retries.len(), 2);
+        assert_eq!(store.get(retries[1]).await.retry_count, 2);
+
+        now += chrono::Duration::minutes(5);
+        let reconciled = reconcile_deployments(&store, timeout, 2, now)
+            .await
+            .unwrap();
+        assert_eq!(reconciled, Reconciled::default());
+        assert!(
+            store
+                .deployments
+                .lock()
+                .await
+                .iter()
+                .all(|d| d.status == DeploymentStatus::Failed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_by_default() {
+        let store = MemoryStore::default();
+        let now = Utc::now();
+        let stuck = deployment(DeploymentStatus::Pending, now - chrono::Duration::hours(5));
+        store.create(&stuck).await.unwrap();
+
+        let timeout = Duration::from_secs(240 * 60);
+        let reconciled = reconcile_deployments(&store, timeout, 0, now)
+            .await
+            .unwrap();
+        assert_eq!(reconciled.timed_out, vec![stuck.id]);
+        assert!(reconciled.retried.is_empty());
+        assert_eq!(store.deployments.lock().await.len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Sandboxed iPXE script templates
+//!
+//! Templates are plain iPXE scripts with `{{ name }}` placeholders. The only
+//! supported operation is substituting a value from a fixed set of variables:
+//! there are no expressions, loops, includes or filesystem access, so a
+//! template cannot do anything the server did not explicitly hand it.
+//! Branching belongs in the iPXE script itself (e.g. `isset ${image-id}`).
+//!
+//! NIST Controls:
+//! - SI-10: Information Input Validation (unknown variables are rejected)
+//! - CM-7: Least Functionality (substitution only)
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Errors raised while rendering a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `{{` without a matching `}}`
+    Unterminated { offset: usize },
+    /// A placeholder that is not a plain variable name
+    InvalidPlaceholder(String),
+    /// A variable the context does not define
+    UnknownVariable(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unterminated { offset } => {
+                write!(f, "unterminated placeholder at byte {}", offset)
+            }
+            Self::InvalidPlaceholder(p) => write!(f, "invalid placeholder '{{{{{}}}}}'", p),
+            Self::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Variables available to a template
+#[derive(Debug, Default, Clone)]
+pub struct TemplateContext {
+    vars: BTreeMap<&'static str, String>,
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define a variable
+    ///
+    /// Control characters are stripped from the value so machine-supplied
+    /// data (e.g. a hostname) cannot inject extra lines into the script.
+    pub fn set(&mut self, name: &'static str, value: impl AsRef<str>) {
+        let value = value.as_ref().chars().filter(|c| !c.is_control()).collect();
+        self.vars.insert(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+}
+
+/// Render a template, substituting every `{{ name }}` from `context`
+pub fn render(template: &str, context: &TemplateContext) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or(TemplateError::Unterminated {
+            offset: offset + start,
+        })?;
+
+        let placeholder = after[..end].trim();
+        if placeholder.is_empty()
+            || !placeholder
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(TemplateError::InvalidPlaceholder(placeholder.to_string()));
+        }
+
+        let value = context
+            .get(placeholder)
+            .ok_or_else(|| TemplateError::UnknownVariable(placeholder.to_string()))?;
+        out.push_str(value);
+
+        let consumed = start + 2 + end + 2;
+        rest = &rest[consumed..];
+        offset += consumed;
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitutes_variables() {
+        let mut context = TemplateContext::new();
+        context.set("hostname", "lab-01");
+
+        let rendered = render("echo {{hostname}} / {{ hostname }}\n", &context).unwrap();
+        assert_eq!(rendered, "echo lab-01 / lab-01\n");
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_invalid_placeholders() {
+        let context = TemplateContext::new();
+
+        assert_eq!(
+            render("{{ missing }}", &context),
+            Err(TemplateError::UnknownVariable("missing".to_string()))
+        );
+        assert_eq!(
+            render("{{ a.b }}", &context),
+            Err(TemplateError::InvalidPlaceholder("a.b".to_string()))
+        );
+        assert_eq!(
+            render("x {{ open", &context),
+            Err(TemplateError::Unterminated { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn test_values_cannot_inject_lines() {
+        let mut context = TemplateContext::new();
+        context.set("hostname", "evil\nshell");
+
+        let rendered = render("set hostname {{ hostname }}", &context).unwrap();
+        assert_eq!(rendered, "set hostname evilshell");
+        // iPXE's own ${...} syntax passes through untouched
+        assert_eq!(render("${base-url}", &context).unwrap(), "${base-url}");
+    }
+}
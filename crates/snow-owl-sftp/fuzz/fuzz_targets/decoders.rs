@@ -0,0 +1,8 @@
+//! Attribute and string decoders on raw bytes
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    snow_owl_sftp::fuzzing::decoders(data);
+});
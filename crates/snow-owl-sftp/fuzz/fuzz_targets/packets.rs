@@ -0,0 +1,8 @@
+//! Whole sessions: framed packets replayed after INIT
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    snow_owl_sftp::fuzzing::packets(data);
+});
@@ -0,0 +1,391 @@
+//! Account expiry and scheduled access windows
+//!
+//! NIST 800-53: AC-2 (Account Management), AC-2(3) (Disable Accounts), AC-12 (Session Termination)
+//! STIG: V-222567 (User Access Control)
+//! Implementation: Evaluates per-user expiry and access windows after key verification,
+//! warns operators about accounts nearing expiry, and ends sessions at window boundaries
+
+use crate::Config;
+use chrono::{DateTime, Duration, Utc};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// How often accounts nearing expiry are reported
+const EXPIRY_WARNING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Source of the current time, injectable for tests
+pub trait Clock: Send + Sync {
+    /// Current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Outcome of an account policy check
+///
+/// NIST 800-53: AU-3 (Content of Audit Records)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessDecision {
+    /// Account may authenticate
+    Allowed,
+    /// Account expired at the given time
+    Expired {
+        /// When the account expired
+        expired_at: DateTime<Utc>,
+    },
+    /// Outside every configured access window
+    OutsideWindow,
+}
+
+impl AccessDecision {
+    /// Audit reason for a denial, None when allowed
+    pub const fn reason(&self) -> Option<&'static str> {
+        match self {
+            Self::Allowed => None,
+            Self::Expired { .. } => Some("account_expired"),
+            Self::OutsideWindow => Some("outside_access_window"),
+        }
+    }
+}
+
+/// Per-user expiry and access-window enforcement
+///
+/// NIST 800-53: AC-2 (Account Management)
+/// Implementation: Applies `expires_at` and `access_schedule` from the user configuration
+pub struct AccountPolicy {
+    config: Arc<Config>,
+    clock: Arc<dyn Clock>,
+}
+
+impl AccountPolicy {
+    /// Create a policy using the system clock
+    pub fn new(config: Arc<Config>) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Create a policy with a custom clock
+    pub fn with_clock(config: Arc<Config>, clock: Arc<dyn Clock>) -> Self {
+        Self { config, clock }
+    }
+
+    /// Check whether `username` may authenticate now
+    ///
+    /// Users without a configuration entry are not restricted.
+    ///
+    /// NIST 800-53: AC-2(3) (Disable Accounts), AC-3 (Access Enforcement)
+    pub fn check(&self, username: &str) -> AccessDecision {
+        let Some(user_config) = self.config.get_user_config(username) else {
+            return AccessDecision::Allowed;
+        };
+        let now = self.clock.now();
+
+        if let Some(expires_at) = user_config.expires_at
+            && now >= expires_at
+        {
+            return AccessDecision::Expired {
+                expired_at: expires_at,
+            };
+        }
+
+        if let Some(ref schedule) = user_config.access_schedule
+            && !schedule.is_open_at(now)
+        {
+            return AccessDecision::OutsideWindow;
+        }
+
+        AccessDecision::Allowed
+    }
+
+    /// When a session for `username` opened now must be terminated
+    ///
+    /// Only set when the user's schedule has `terminate_sessions` enabled and
+    /// an access window is currently open.
+    ///
+    /// NIST 800-53: AC-12 (Session Termination)
+    pub fn session_deadline(&self, username: &str) -> Option<DateTime<Utc>> {
+        let schedule = self
+            .config
+            .get_user_config(username)?
+            .access_schedule
+            .as_ref()
+            .filter(|schedule| schedule.terminate_sessions)?;
+
+        schedule.window_end_at(self.clock.now())
+    }
+
+    /// Run `terminate` when the session deadline for `username` is reached
+    ///
+    /// Returns None when the session has no deadline. Abort the returned task
+    /// if the session ends first.
+    ///
+    /// NIST 800-53: AC-12 (Session Termination)
+    pub fn schedule_termination<F, Fut>(
+        &self,
+        username: &str,
+        terminate: F,
+    ) -> Option<JoinHandle<()>>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let deadline = self.session_deadline(username)?;
+        let delay = (deadline - self.clock.now())
+            .to_std()
+            .unwrap_or(std::time::Duration::ZERO);
+        let username = username.to_string();
+
+        Some(tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            warn!(
+                event = "session_window_closed",
+                username,
+                deadline = %deadline,
+                "Access window ended, terminating session"
+            );
+            terminate().await;
+        }))
+    }
+
+    /// Accounts expiring within the configured warning period, sorted by name
+    ///
+    /// Already-expired accounts are not included.
+    pub fn expiring_accounts(&self) -> Vec<(String, DateTime<Utc>)> {
+        let now = self.clock.now();
+        let horizon = now + Duration::days(i64::from(self.config.account_expiry_warning_days));
+
+        let mut expiring: Vec<_> = self
+            .config
+            .users
+            .iter()
+            .filter_map(|(username, user_config)| {
+                let expires_at = user_config.expires_at?;
+                (expires_at > now && expires_at <= horizon).then(|| (username.clone(), expires_at))
+            })
+            .collect();
+        expiring.sort();
+        expiring
+    }
+
+    /// Log a warning for every account nearing expiry
+    ///
+    /// NIST 800-53: AC-2 (Account Management), AU-12 (Audit Generation)
+    pub fn log_expiry_warnings(&self) {
+        let now = self.clock.now();
+        for (username, expires_at) in self.expiring_accounts() {
+            warn!(
+                event = "account_expiring",
+                username,
+                expires_at = %expires_at,
+                days_remaining = (expires_at - now).num_days(),
+                "Account expires soon; renew it to keep access"
+            );
+        }
+    }
+
+    /// Log expiry warnings now and then once a day
+    pub fn spawn_expiry_warnings(self: &Arc<Self>) -> JoinHandle<()> {
+        let policy = self.clone();
+        info!(
+            "Warning daily about accounts expiring within {} days",
+            policy.config.account_expiry_warning_days
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_WARNING_INTERVAL);
+            loop {
+                interval.tick().await;
+                policy.log_expiry_warnings();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AccessSchedule, AccessWindow, UserConfig};
+    use chrono::NaiveTime;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_default()
+    }
+
+    fn hm(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or_default()
+    }
+
+    /// Vendor allowed Sunday 00:00-06:00 in New York
+    fn vendor_schedule(terminate_sessions: bool) -> AccessSchedule {
+        AccessSchedule {
+            timezone: "America/New_York".to_string(),
+            windows: vec![AccessWindow {
+                days: vec![0],
+                start: hm(0, 0),
+                end: hm(6, 0),
+            }],
+            terminate_sessions,
+            ..AccessSchedule::default()
+        }
+    }
+
+    fn policy(users: Vec<(&str, UserConfig)>, now: &str) -> AccountPolicy {
+        let mut config = Config::default();
+        for (name, user_config) in users {
+            config.users.insert(name.to_string(), user_config);
+        }
+        AccountPolicy::with_clock(Arc::new(config), Arc::new(FixedClock(at(now))))
+    }
+
+    #[test]
+    fn test_expired_account_rejected_with_reason() {
+        let contractor = UserConfig {
+            expires_at: Some(at("2026-03-31T23:59:59Z")),
+            ..UserConfig::default()
+        };
+        let policy = policy(vec![("contractor", contractor)], "2026-04-01T09:00:00Z");
+
+        let decision = policy.check("contractor");
+
+        assert_eq!(
+            decision,
+            AccessDecision::Expired {
+                expired_at: at("2026-03-31T23:59:59Z")
+            }
+        );
+        assert_eq!(decision.reason(), Some("account_expired"));
+        assert_eq!(policy.check("someone-else"), AccessDecision::Allowed);
+    }
+
+    #[test]
+    fn test_inside_window_accepted() {
+        let vendor = UserConfig {
+            access_schedule: Some(vendor_schedule(false)),
+            ..UserConfig::default()
+        };
+        // Sunday 03:00 in New York (EDT, UTC-4)
+        let policy = policy(vec![("vendor", vendor)], "2026-06-07T07:00:00Z");
+
+        assert_eq!(policy.check("vendor"), AccessDecision::Allowed);
+    }
+
+    #[test]
+    fn test_outside_window_rejected() {
+        let vendor = UserConfig {
+            access_schedule: Some(vendor_schedule(false)),
+            ..UserConfig::default()
+        };
+        // Sunday 03:00 UTC is still Saturday 23:00 in New York
+        let policy = policy(vec![("vendor", vendor)], "2026-06-07T03:00:00Z");
+
+        let decision = policy.check("vendor");
+        assert_eq!(decision, AccessDecision::OutsideWindow);
+        assert_eq!(decision.reason(), Some("outside_access_window"));
+    }
+
+    #[test]
+    fn test_window_past_midnight() {
+        let schedule = AccessSchedule {
+            windows: vec![AccessWindow {
+                days: vec![5],
+                start: hm(22, 0),
+                end: hm(2, 0),
+            }],
+            ..AccessSchedule::default()
+        };
+
+        // Friday 23:00 and Saturday 01:00 are inside, Saturday 02:00 is not
+        assert_eq!(
+            schedule.window_end_at(at("2026-06-05T23:00:00Z")),
+            Some(at("2026-06-06T02:00:00Z"))
+        );
+        assert!(schedule.is_open_at(at("2026-06-06T01:00:00Z")));
+        assert!(!schedule.is_open_at(at("2026-06-06T02:00:00Z")));
+        assert!(!schedule.is_open_at(at("2026-06-06T23:00:00Z")));
+    }
+
+    #[test]
+    fn test_expiring_accounts_within_warning_period() {
+        let soon = UserConfig {
+            expires_at: Some(at("2026-06-10T00:00:00Z")),
+            ..UserConfig::default()
+        };
+        let later = UserConfig {
+            expires_at: Some(at("2026-12-31T00:00:00Z")),
+            ..UserConfig::default()
+        };
+        let expired = UserConfig {
+            expires_at: Some(at("2026-05-01T00:00:00Z")),
+            ..UserConfig::default()
+        };
+        let policy = policy(
+            vec![("soon", soon), ("later", later), ("expired", expired)],
+            "2026-06-01T00:00:00Z",
+        );
+
+        assert_eq!(
+            policy.expiring_accounts(),
+            vec![("soon".to_string(), at("2026-06-10T00:00:00Z"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_termination_fires_at_window_end() {
+        let vendor = UserConfig {
+            access_schedule: Some(vendor_schedule(true)),
+            ..UserConfig::default()
+        };
+        // 50ms before the window closes at 06:00 EDT
+        let policy = policy(vec![("vendor", vendor)], "2026-06-07T09:59:59.950Z");
+        let fired = Arc::new(AtomicBool::new(false));
+
+        let flag = fired.clone();
+        let task = policy.schedule_termination("vendor", move || async move {
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        assert_eq!(
+            policy.session_deadline("vendor"),
+            Some(at("2026-06-07T10:00:00Z"))
+        );
+        assert!(task.is_some());
+        if let Some(task) = task {
+            assert!(
+                tokio::time::timeout(std::time::Duration::from_secs(2), task)
+                    .await
+                    .is_ok()
+            );
+        }
+        assert!(fired.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_no_termination_when_disabled() {
+        let vendor = UserConfig {
+            access_schedule: Some(vendor_schedule(false)),
+            ..UserConfig::default()
+        };
+        let policy = policy(vec![("vendor", vendor)], "2026-06-07T09:59:59Z");
+
+        assert_eq!(policy.session_deadline("vendor"), None);
+    }
+}
@@ -6,6 +6,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use snow_owl_core::AuditRecord;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use tracing::{info, warn};
@@ -119,6 +120,67 @@ pub enum AuditEvent {
 }
 
 impl AuditEvent {
+    /// Security event stamped with the current time
+    #[must_use]
+    pub fn security(
+        client_ip: Option<IpAddr>,
+        username: Option<String>,
+        event: impl Into<String>,
+        details: impl Into<String>,
+    ) -> Self {
+        AuditEvent::SecurityEvent {
+            client_ip,
+            username,
+            event: event.into(),
+            details: details.into(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// File or directory operation stamped with the current time; it
+    /// succeeded unless `error` is set
+    #[must_use]
+    pub fn file_operation(
+        client_ip: Option<IpAddr>,
+        username: Option<String>,
+        operation: impl Into<String>,
+        path: impl Into<String>,
+        bytes_transferred: Option<u64>,
+        error: Option<String>,
+    ) -> Self {
+        AuditEvent::FileOperation {
+            client_ip,
+            username,
+            operation: operation.into(),
+            path: path.into(),
+            timestamp: Utc::now(),
+            success: error.is_none(),
+            bytes_transferred,
+            error,
+        }
+    }
+
+    /// Directory operation stamped with the current time; it succeeded
+    /// unless `error` is set
+    #[must_use]
+    pub fn directory_operation(
+        client_ip: Option<IpAddr>,
+        username: Option<String>,
+        operation: impl Into<String>,
+        path: impl Into<String>,
+        error: Option<String>,
+    ) -> Self {
+        AuditEvent::DirectoryOperation {
+            client_ip,
+            username,
+            operation: operation.into(),
+            path: path.into(),
+            timestamp: Utc::now(),
+            success: error.is_none(),
+            error,
+        }
+    }
+
     /// Log the audit event
     ///
     /// NIST 800-53: AU-12 (Audit Generation)
@@ -275,6 +337,75 @@ impl AuditEvent {
         }
     }
 
+    /// Row for the shared audit trail, for security-relevant events
+    ///
+    /// Authentication attempts, policy violations and lockouts are kept;
+    /// routine connection and file activity stays in the tracing log only.
+    ///
+    /// NIST 800-53: AU-2 (Audit Events), AU-3 (Content of Audit Records)
+    #[must_use]
+    pub fn to_record(&self) -> Option<AuditRecord> {
+        let (mut record, client_ip, timestamp) = match self {
+            AuditEvent::AuthAttempt {
+                client_ip,
+                username,
+                timestamp,
+                success,
+                reason,
+            } => {
+                let mut record = AuditRecord::new("sftp.auth", *success).with_username(username);
+                if let Some(reason) = reason {
+                    record = record.with_error(reason);
+                }
+                (record, client_ip, timestamp)
+            }
+            AuditEvent::SecurityEvent {
+                client_ip,
+                username,
+                event,
+                details,
+                timestamp,
+            } => {
+                let mut record = AuditRecord::failure(format!("sftp.{}", event), details);
+                if let Some(username) = username {
+                    record = record.with_username(username);
+                }
+                (record, client_ip, timestamp)
+            }
+            AuditEvent::RateLimitTriggered {
+                client_ip,
+                timestamp,
+                duration_secs,
+            } => {
+                let record = AuditRecord::failure(
+                    "sftp.rate_limit",
+                    format!("locked out for {}s", duration_secs),
+                );
+                (record, client_ip, timestamp)
+            }
+            AuditEvent::ConnectionLimitReached {
+                username,
+                current_connections,
+                max_connections,
+                timestamp,
+            } => {
+                let record = AuditRecord::failure(
+                    "sftp.connection_limit",
+                    format!("{} of {} connections", current_connections, max_connections),
+                )
+                .with_username(username);
+                (record, &None, timestamp)
+            }
+            _ => return None,
+        };
+
+        if let Some(ip) = client_ip {
+            record = record.with_ip(*ip);
+        }
+        record.created_at = *timestamp;
+        Some(record)
+    }
+
     /// Export as JSON
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
@@ -435,14 +566,7 @@ impl AuditLogger {
         event: String,
         details: String,
     ) {
-        let audit_event = AuditEvent::SecurityEvent {
-            client_ip,
-            username,
-            event,
-            details,
-            timestamp: Utc::now(),
-        };
-        audit_event.log();
+        AuditEvent::security(client_ip, username, event, details).log();
     }
 }
 
@@ -454,7 +578,7 @@ mod tests {
     #[test]
     fn test_audit_event_creation() {
         let event = AuditEvent::AuthAttempt {
-            client_ip: Some("127.0.0.1".parse::<IpAddr>().ok()),
+            client_ip: "127.0.0.1".parse::<IpAddr>().ok(),
             username: "testuser".to_string(),
             timestamp: Utc::now(),
             success: true,
@@ -462,14 +586,14 @@ mod tests {
         };
 
         let json = event.to_json().expect("JSON serialization failed");
-        assert!(json.contains("Auth Attempt"));
+        assert!(json.contains("\"event_type\":\"AuthAttempt\""));
     }
 
     #[test]
     fn test_session_info() {
         let mut session = SessionInfo::new(
             "test-session".to_string(),
-            Some("127.0.0.1".parse().ok()),
+            "127.0.0.1".parse().ok(),
         );
 
         assert_eq!(session.session_id, "test-session");
@@ -486,7 +610,7 @@ mod tests {
     fn test_file_operation_audit() {
         let path = PathBuf::from("/test/file.txt");
         AuditLogger::log_file_read(
-            Some("127.0.0.1".parse().ok()),
+            "127.0.0.1".parse().ok(),
             Some("testuser".to_string()),
             &path,
             1024,
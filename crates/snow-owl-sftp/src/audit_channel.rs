@@ -0,0 +1,605 @@
+//! Buffered, batched delivery of audit events
+//!
+//! Writing every audit record from the session that produced it makes sink
+//! latency part of every SFTP operation. An [`AuditChannel`] instead queues
+//! events in a bounded channel and a dedicated writer thread hands them to an
+//! [`AuditSink`] in batches, flushing when a batch is full or has waited
+//! `flush_interval_ms`. Recording never awaits, so it works from the
+//! synchronous validation paths as well as from async handlers.
+//!
+//! When the queue is full, [`AuditOverflow::Block`] makes the recording
+//! operation wait for room and [`AuditOverflow::Drop`] discards the event and
+//! counts it. [`AuditChannel::queued`] reports the backlog still waiting for
+//! the sink, and [`AuditChannel::shutdown`] delivers all of it before it
+//! returns.
+//!
+//! NIST 800-53: AU-4 (Audit Log Storage Capacity), AU-5 (Response to Audit Processing Failures), AU-12 (Audit Generation)
+//! STIG: V-222648 (Audit Records)
+
+use crate::audit::AuditEvent;
+use crate::config::{AuditChannelConfig, AuditOverflow};
+use snow_owl_core::AuditQueue;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+/// Destination for batches of audit events
+///
+/// Called from the channel's writer thread, never from a session.
+pub trait AuditSink: Send + 'static {
+    /// Write one batch, in the order the events were recorded
+    fn write_batch(&mut self, events: &[AuditEvent]);
+}
+
+/// Sink writing each event through `tracing`, the default audit output
+#[derive(Debug, Default)]
+pub struct TracingSink;
+
+impl AuditSink for TracingSink {
+    fn write_batch(&mut self, events: &[AuditEvent]) {
+        for event in events {
+            event.log();
+        }
+    }
+}
+
+/// Sink writing each event through `tracing` and forwarding security
+/// events to the shared audit trail
+///
+/// Forwarding never blocks the writer thread: a full shared queue drops the
+/// record and counts it on the [`AuditQueue`].
+///
+/// NIST 800-53: AU-9 (Protection of Audit Information)
+#[derive(Debug, Clone)]
+pub struct SharedAuditSink {
+    queue: AuditQueue,
+}
+
+impl SharedAuditSink {
+    /// Sink forwarding to `queue`
+    #[must_use]
+    pub fn new(queue: AuditQueue) -> Self {
+        Self { queue }
+    }
+}
+
+impl AuditSink for SharedAuditSink {
+    fn write_batch(&mut self, events: &[AuditEvent]) {
+        for event in events {
+            event.log();
+            if let Some(record) = event.to_record() {
+                self.queue.record(record);
+            }
+        }
+    }
+}
+
+/// Sink appending each event to a file as one JSON object per line before
+/// passing the batch on to `inner`
+///
+/// The file is opened for appending, created owner-only, and flushed after
+/// every batch. A failed write is logged and the batch still reaches `inner`.
+///
+/// NIST 800-53: AU-9 (Protection of Audit Information)
+pub struct JsonFileSink<S> {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    inner: S,
+}
+
+impl<S: AuditSink> JsonFileSink<S> {
+    /// Append to `path`, creating it if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened for appending.
+    pub fn open(path: &Path, inner: S) -> crate::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(path).map_err(|e| {
+            crate::Error::Other(format!("Failed to open audit file {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            inner,
+        })
+    }
+
+    fn append(&mut self, events: &[AuditEvent]) -> std::io::Result<()> {
+        for event in events {
+            serde_json::to_writer(&mut self.writer, event)?;
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<S: AuditSink> AuditSink for JsonFileSink<S> {
+    fn write_batch(&mut self, events: &[AuditEvent]) {
+        if let Err(e) = self.append(events) {
+            error!(
+                event = "audit_file_write_failed",
+                path = %self.path.display(),
+                error = %e,
+                "Failed to append audit events to file"
+            );
+        }
+        self.inner.write_batch(events);
+    }
+}
+
+enum Message {
+    Event(Box<AuditEvent>),
+    Shutdown,
+}
+
+struct Inner {
+    sender: SyncSender<Message>,
+    overflow: AuditOverflow,
+    dropped: AtomicU64,
+    /// Events recorded but not yet written, shared with the writer
+    queued: Arc<AtomicUsize>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Handle for recording audit events; clones share one queue and writer
+///
+/// NIST 800-53: AU-12 (Audit Generation)
+#[derive(Clone)]
+pub struct AuditChannel {
+    inner: Arc<Inner>,
+}
+
+impl AuditChannel {
+    /// Start a writer thread delivering to `sink`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer thread cannot be spawned.
+    pub fn spawn(config: &AuditChannelConfig, sink: impl AuditSink) -> crate::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(config.capacity.max(1));
+        let batch_size = config.batch_size.max(1);
+        let flush_interval = Duration::from_millis(config.flush_interval_ms.max(1));
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        let writer_queued = queued.clone();
+        let writer = std::thread::Builder::new()
+            .name("sftp-audit".to_string())
+            .spawn(move || {
+                run_writer(&receiver, sink, batch_size, flush_interval, &writer_queued);
+            })
+            .map_err(|e| crate::Error::Other(format!("Failed to start audit writer: {}", e)))?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                sender,
+                overflow: config.overflow,
+                dropped: AtomicU64::new(0),
+                queued,
+                writer: Mutex::new(Some(writer)),
+            }),
+        })
+    }
+
+    /// Queue `event` for the sink
+    ///
+    /// With [`AuditOverflow::Block`] this waits while the queue is full.
+    /// Events recorded after [`shutdown`](Self::shutdown) are counted as
+    /// dropped.
+    pub fn record(&self, event: AuditEvent) {
+        let message = Message::Event(Box::new(event));
+        // Counted before sending so the writer never takes it below zero
+        self.inner.queued.fetch_add(1, Ordering::Relaxed);
+        let delivered = match self.inner.overflow {
+            AuditOverflow::Block => self.inner.sender.send(message).is_ok(),
+            AuditOverflow::Drop => match self.inner.sender.try_send(message) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            },
+        };
+        if !delivered {
+            self.inner.queued.fetch_sub(1, Ordering::Relaxed);
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Events recorded but not yet handed to the sink
+    ///
+    /// NIST 800-53: AU-4 (Audit Log Storage Capacity)
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.inner.queued.load(Ordering::Relaxed)
+    }
+
+    /// Events discarded because the queue was full or already shut down
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Deliver every queued event and stop the writer
+    ///
+    /// Waits for the final batch to be written; calling it again does
+    /// nothing.
+    ///
+    /// NIST 800-53: AU-5 (Response to Audit Processing Failures)
+    pub fn shutdown(&self) {
+        let writer = self
+            .inner
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let Some(writer) = writer else {
+            return;
+        };
+
+        // Queued behind every event recorded so far, even if that has to wait
+        let _ = self.inner.sender.send(Message::Shutdown);
+        if writer.join().is_err() {
+            error!(event = "audit_writer_failed", "Audit writer thread panicked");
+        }
+
+        let dropped = self.dropped();
+        if dropped > 0 {
+            warn!(
+                event = "audit_events_dropped",
+                dropped,
+                "Audit events were dropped because the audit queue was full"
+            );
+        }
+    }
+}
+
+/// Writer thread: batch events and deliver them until shut down
+///
+/// Exits on [`Message::Shutdown`] or once every handle is gone; either way
+/// whatever is still queued is delivered first.
+fn run_writer(
+    receiver: &Receiver<Message>,
+    mut sink: impl AuditSink,
+    batch_size: usize,
+    flush_interval: Duration,
+    queued: &AtomicUsize,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let message = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match message {
+            Ok(Message::Event(event)) => {
+                if batch.is_empty() {
+                    deadline = Some(Instant::now() + flush_interval);
+                }
+                batch.push(*event);
+                if batch.len() >= batch_size {
+                    flush(&mut sink, &mut batch, queued);
+                    deadline = None;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                flush(&mut sink, &mut batch, queued);
+                deadline = None;
+            }
+            Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                // Events recorded while shutdown was being queued
+                while let Ok(Message::Event(event)) = receiver.try_recv() {
+                    batch.push(*event);
+                    if batch.len() >= batch_size {
+                        flush(&mut sink, &mut batch, queued);
+                    }
+                }
+                flush(&mut sink, &mut batch, queued);
+                return;
+            }
+        }
+    }
+}
+
+fn flush(sink: &mut impl AuditSink, batch: &mut Vec<AuditEvent>, queued: &AtomicUsize) {
+    if !batch.is_empty() {
+        sink.write_batch(batch);
+        queued.fetch_sub(batch.len(), Ordering::Relaxed);
+        batch.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    /// Sink recording batch sizes and event details, optionally slow
+    #[derive(Clone, Default)]
+    struct CaptureSink {
+        batches: Arc<Mutex<Vec<Vec<String>>>>,
+        delay: Duration,
+    }
+
+    impl AuditSink for CaptureSink {
+        fn write_batch(&mut self, events: &[AuditEvent]) {
+            std::thread::sleep(self.delay);
+            let details = events
+                .iter()
+                .map(|event| match event {
+                    AuditEvent::SecurityEvent { details, .. } => details.clone(),
+                    other => format!("{:?}", other),
+                })
+                .collect();
+            self.batches
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(details);
+        }
+    }
+
+    impl CaptureSink {
+        fn events(&self) -> Vec<String> {
+            self.batches
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .flatten()
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn event(n: usize) -> AuditEvent {
+        AuditEvent::SecurityEvent {
+            client_ip: None,
+            username: Some("loadtest".to_string()),
+            event: "test".to_string(),
+            details: n.to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_shutdown_flushes_every_event() -> crate::Result<()> {
+        let sink = CaptureSink::default();
+        let config = AuditChannelConfig {
+            capacity: 64,
+            batch_size: 50,
+            // Long enough that only size and shutdown trigger writes
+            flush_interval_ms: 60_000,
+            overflow: AuditOverflow::Block,
+        };
+        let channel = AuditChannel::spawn(&config, sink.clone())?;
+
+        let producers: Vec<_> = (0..4)
+            .map(|producer| {
+                let channel = channel.clone();
+                std::thread::spawn(move || {
+                    for n in 0..250 {
+                        channel.record(event(producer * 1000 + n));
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            assert!(producer.join().is_ok());
+        }
+        channel.shutdown();
+
+        let mut events = sink.events();
+        assert_eq!(events.len(), 1000);
+        assert_eq!(channel.dropped(), 0);
+        events.sort();
+        events.dedup();
+        assert_eq!(events.len(), 1000);
+
+        // Batches never exceed batch_size
+        let batches = sink.batches.lock().unwrap_or_else(PoisonError::into_inner);
+        assert!(batches.iter().all(|batch| batch.len() <= 50));
+
+        // Recording after shutdown is counted, not written
+        drop(batches);
+        channel.record(event(0));
+        assert_eq!(channel.dropped(), 1);
+        assert_eq!(sink.events().len(), 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_batch_flushed_after_interval() -> crate::Result<()> {
+        let sink = CaptureSink::default();
+        let config = AuditChannelConfig {
+            flush_interval_ms: 20,
+            ..AuditChannelConfig::default()
+        };
+        let channel = AuditChannel::spawn(&config, sink.clone())?;
+
+        channel.record(event(1));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sink.events().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(sink.events(), vec!["1".to_string()]);
+
+        channel.shutdown();
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_policy_counts_overflow_under_load() -> crate::Result<()> {
+        // A slow sink and a tiny queue: most events cannot be queued
+        let sink = CaptureSink {
+            delay: Duration::from_millis(50),
+            ..CaptureSink::default()
+        };
+        let config = AuditChannelConfig {
+            capacity: 8,
+            batch_size: 4,
+            flush_interval_ms: 10,
+            overflow: AuditOverflow::Drop,
+        };
+        let channel = AuditChannel::spawn(&config, sink.clone())?;
+
+        let started = Instant::now();
+        for n in 0..2000 {
+            channel.record(event(n));
+        }
+        // Producers never waited on the sink
+        assert!(started.elapsed() < Duration::from_secs(1));
+        channel.shutdown();
+
+        let written = sink.events().len() as u64;
+        assert!(channel.dropped() > 0);
+        assert_eq!(channel.queued(), 0);
+        assert_eq!(written + channel.dropped(), 2000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_policy_waits_for_room() -> crate::Result<()> {
+        let sink = CaptureSink {
+            delay: Duration::from_millis(20),
+            ..CaptureSink::default()
+        };
+        let config = AuditChannelConfig {
+            capacity: 2,
+            batch_size: 2,
+            flush_interval_ms: 10,
+            overflow: AuditOverflow::Block,
+        };
+        let channel = AuditChannel::spawn(&config, sink.clone())?;
+
+        for n in 0..40 {
+            channel.record(event(n));
+        }
+        channel.shutdown();
+
+        assert_eq!(channel.dropped(), 0);
+        assert_eq!(channel.queued(), 0);
+        let expected: Vec<String> = (0..40).map(|n| n.to_string()).collect();
+        assert_eq!(sink.events(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_file_sink_appends_lines() -> crate::Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("audit.jsonl");
+        std::fs::write(&path, "{\"earlier\":true}\n")?;
+        let inner = CaptureSink::default();
+        let channel = AuditChannel::spawn(
+            &AuditChannelConfig::default(),
+            JsonFileSink::open(&path, inner.clone())?,
+        )?;
+
+        channel.record(event(1));
+        channel.record(AuditEvent::file_operation(
+            None,
+            Some("deploy".to_string()),
+            "DELETE",
+            "/upload.txt",
+            None,
+            None,
+        ));
+        channel.shutdown();
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "{\"earlier\":true}");
+        let value: serde_json::Value =
+            serde_json::from_str(lines[2]).map_err(|e| crate::Error::Other(e.to_string()))?;
+        assert_eq!(value["event_type"], "FileOperation");
+        assert_eq!(value["operation"], "DELETE");
+        assert_eq!(value["success"], true);
+        // The wrapped sink still sees every event
+        assert_eq!(inner.events().len(), 2);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::remove_file(&path)?;
+            let channel = AuditChannel::spawn(
+                &AuditChannelConfig::default(),
+                JsonFileSink::open(&path, TracingSink)?,
+            )?;
+            channel.shutdown();
+            assert_eq!(std::fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+        Ok(())
+    }
+
+    #[derive(Clone, Default)]
+    struct MemoryStore {
+        records: Arc<Mutex<Vec<snow_owl_core::AuditRecord>>>,
+    }
+
+    impl snow_owl_core::AuditSink for MemoryStore {
+        async fn record(&self, record: &snow_owl_core::AuditRecord) -> snow_owl_core::Result<()> {
+            self.records
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(record.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_sink_forwards_security_events() -> crate::Result<()> {
+        let store = MemoryStore::default();
+        let (queue, writer) = AuditQueue::spawn(store.clone(), 16);
+        let channel =
+            AuditChannel::spawn(&AuditChannelConfig::default(), SharedAuditSink::new(queue))?;
+
+        let client_ip = "192.0.2.10".parse().ok();
+        channel.record(AuditEvent::AuthAttempt {
+            client_ip,
+            username: "deploy".to_string(),
+            timestamp: Utc::now(),
+            success: false,
+            reason: Some("Invalid password".to_string()),
+        });
+        // Routine file activity stays out of the shared trail
+        channel.record(AuditEvent::FileOperation {
+            client_ip,
+            username: Some("deploy".to_string()),
+            operation: "read".to_string(),
+            path: "/boot.wim".to_string(),
+            timestamp: Utc::now(),
+            success: true,
+            bytes_transferred: Some(1024),
+            error: None,
+        });
+        channel.record(AuditEvent::security(
+            client_ip,
+            Some("deploy".to_string()),
+            "path_traversal",
+            "path=../etc/shadow",
+        ));
+        channel.shutdown();
+        // The writer thread dropped the last queue handle on exit
+        assert!(writer.await.is_ok());
+
+        let records = store.records.lock().unwrap_or_else(PoisonError::into_inner);
+        let actions: Vec<_> = records.iter().map(|r| r.action.as_str()).collect();
+        assert_eq!(actions, ["sftp.auth", "sftp.path_traversal"]);
+        assert!(records.iter().all(|r| !r.success && r.ip_address == client_ip));
+        assert_eq!(records[0].username.as_deref(), Some("deploy"));
+        assert_eq!(records[0].error_message.as_deref(), Some("Invalid password"));
+        Ok(())
+    }
+}
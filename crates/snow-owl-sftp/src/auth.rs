@@ -144,15 +144,11 @@ impl AuthorizedKeys {
             ));
         }
 
-        // Extract key type and key data
-        let key_type = parts[0];
+        // Extract key data; the blob names its own key type
         let key_data = parts[1];
 
-        // Combine for parsing
-        let key_string = format!("{} {}", key_type, key_data);
-
-        // Parse using russh::keys
-        russh::keys::parse_public_key_base64(&key_string)
+        // Parse using russh::keys, which expects the base64 blob alone
+        russh::keys::parse_public_key_base64(key_data)
             .map_err(|e| Error::Config(format!("Failed to parse public key: {}", e)))
     }
 
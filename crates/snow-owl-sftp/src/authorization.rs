@@ -0,0 +1,529 @@
+//! Per-operation authorization callbacks
+//!
+//! NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege), SC-24 (Fail in Known State)
+//! STIG: V-222567 (User Access Control)
+//! Implementation: Embedders plug an [`Authorizer`] into the server to decide every file
+//! operation after the built-in path and handle checks. Decisions are bounded by a timeout
+//! with a configurable fail policy and briefly cached per session, operation and path.
+
+use crate::Config;
+use crate::audit::SessionInfo;
+use crate::config::{AuthorizationConfig, FailPolicy, PathAccess};
+use crate::protocol::OpenFlags;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant, timeout};
+use tracing::warn;
+
+/// Most cached decisions kept per session
+const MAX_CACHED_DECISIONS: usize = 1024;
+
+/// Longest deny reason passed on to the client and the audit log
+const MAX_REASON_LENGTH: usize = 256;
+
+/// File operation being authorized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `SSH_FXP_OPEN`
+    Open,
+    /// `SSH_FXP_READ`
+    Read,
+    /// `SSH_FXP_WRITE`
+    Write,
+    /// `SSH_FXP_STAT` and `SSH_FXP_LSTAT`
+    Stat,
+    /// `SSH_FXP_FSTAT`
+    Fstat,
+    /// `SSH_FXP_SETSTAT`
+    Setstat,
+    /// `SSH_FXP_FSETSTAT`
+    Fsetstat,
+    /// `SSH_FXP_OPENDIR`
+    Opendir,
+    /// `SSH_FXP_READDIR`
+    Readdir,
+    /// `SSH_FXP_REMOVE`
+    Remove,
+    /// `SSH_FXP_MKDIR`
+    Mkdir,
+    /// `SSH_FXP_RMDIR`
+    Rmdir,
+    /// `SSH_FXP_RENAME` and `posix-rename@openssh.com`
+    Rename,
+    /// `SSH_FXP_READLINK`
+    Readlink,
+    /// `SSH_FXP_SYMLINK`
+    Symlink,
+    /// `hardlink@openssh.com`
+    Hardlink,
+    /// `fsync@openssh.com`
+    Fsync,
+}
+
+impl Operation {
+    /// Lowercase operation name used in logs and audit records
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Stat => "stat",
+            Self::Fstat => "fstat",
+            Self::Setstat => "setstat",
+            Self::Fsetstat => "fsetstat",
+            Self::Opendir => "opendir",
+            Self::Readdir => "readdir",
+            Self::Remove => "remove",
+            Self::Mkdir => "mkdir",
+            Self::Rmdir => "rmdir",
+            Self::Rename => "rename",
+            Self::Readlink => "readlink",
+            Self::Symlink => "symlink",
+            Self::Hardlink => "hardlink",
+            Self::Fsync => "fsync",
+        }
+    }
+}
+
+/// Everything an authorizer knows about one operation
+///
+/// Paths are as the client sees them, rooted at `/` after resolution
+/// against the server root.
+#[derive(Debug, Clone)]
+pub struct OperationContext {
+    /// Session the request arrived on
+    pub session: SessionInfo,
+    /// Operation being performed
+    pub operation: Operation,
+    /// Path operated on (for handle operations, the path the handle was opened with)
+    pub path: PathBuf,
+    /// Second path for rename, symlink and hardlink
+    pub target_path: Option<PathBuf>,
+    /// `SSH_FXP_OPEN` pflags
+    pub flags: Option<u32>,
+    /// File offset for reads and writes
+    pub offset: Option<u64>,
+    /// Bytes requested by a read or carried by a write
+    pub length: Option<u64>,
+}
+
+impl OperationContext {
+    /// Context for `operation` on `path`
+    pub fn new(session: SessionInfo, operation: Operation, path: impl Into<PathBuf>) -> Self {
+        Self {
+            session,
+            operation,
+            path: path.into(),
+            target_path: None,
+            flags: None,
+            offset: None,
+            length: None,
+        }
+    }
+
+    /// Set the second path of a two-path operation
+    #[must_use]
+    pub fn with_target(mut self, target_path: impl Into<PathBuf>) -> Self {
+        self.target_path = Some(target_path.into());
+        self
+    }
+
+    /// Set the open flags
+    #[must_use]
+    pub const fn with_flags(mut self, flags: u32) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Set the offset and byte count of a read or write
+    #[must_use]
+    pub const fn with_range(mut self, offset: u64, length: u64) -> Self {
+        self.offset = Some(offset);
+        self.length = Some(length);
+        self
+    }
+
+    /// Whether the operation leaves the filesystem unchanged
+    pub fn is_read_only(&self) -> bool {
+        match self.operation {
+            Operation::Open => !self.flags.map(OpenFlags).is_some_and(|flags| {
+                flags.has_write() || flags.has_append() || flags.has_creat() || flags.has_trunc()
+            }),
+            Operation::Read
+            | Operation::Stat
+            | Operation::Fstat
+            | Operation::Opendir
+            | Operation::Readdir
+            | Operation::Readlink => true,
+            _ => false,
+        }
+    }
+
+    /// Path permissions the operation needs under read-only mode and path rules
+    ///
+    /// Rename needs write access to both names; hardlink needs read access to
+    /// the source and write access to the new name. A symlink target is link
+    /// content rather than a path operated on, so only the link is checked.
+    pub fn required_access(&self) -> Vec<(PathAccess, &Path)> {
+        let access = match self.operation {
+            Operation::Remove | Operation::Rmdir => PathAccess::Delete,
+            Operation::Hardlink => PathAccess::Read,
+            _ if self.is_read_only() => PathAccess::Read,
+            _ => PathAccess::Write,
+        };
+
+        let mut required = vec![(access, self.path.as_path())];
+        if matches!(self.operation, Operation::Rename | Operation::Hardlink)
+            && let Some(target) = &self.target_path
+        {
+            required.push((PathAccess::Write, target.as_path()));
+        }
+        required
+    }
+}
+
+/// Authorization decision for one operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Perform the operation
+    Allow,
+    /// Refuse the operation; the reason is shown to the client and audited
+    Deny {
+        /// Why the operation was refused
+        reason: String,
+    },
+    /// Perform the operation only if it does not modify the filesystem
+    AllowReadOnly,
+}
+
+impl Decision {
+    /// Deny with `reason`
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Self::Deny {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Decides whether a file operation may proceed
+///
+/// Called after the server's own path and handle validation, so the paths
+/// in the context are already confined to the server root.
+///
+/// NIST 800-53: AC-3 (Access Enforcement)
+#[async_trait::async_trait]
+pub trait Authorizer: Send + Sync {
+    /// Decide whether the operation described by `ctx` may proceed
+    async fn authorize(&self, ctx: &OperationContext) -> Decision;
+}
+
+/// Authorizer enforcing the per-user policy from the configuration
+///
+/// Applies `read_only`, `allowed_operations` and `denied_operations` through
+/// [`Config::is_operation_allowed`]. Sessions without a username, or users
+/// without a configuration entry, are not restricted.
+///
+/// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+pub struct StaticAuthorizer {
+    config: Arc<Config>,
+}
+
+impl StaticAuthorizer {
+    /// Authorizer for the users in `config`
+    pub const fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+
+    /// Operation name as used in `allowed_operations` and `denied_operations`
+    fn policy_name(ctx: &OperationContext) -> &'static str {
+        match ctx.operation {
+            Operation::Open if ctx.is_read_only() => "read",
+            Operation::Open | Operation::Fsync => "write",
+            Operation::Fstat => "stat",
+            Operation::Fsetstat => "setstat",
+            operation => operation.name(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Authorizer for StaticAuthorizer {
+    async fn authorize(&self, ctx: &OperationContext) -> Decision {
+        let Some(username) = ctx.session.username.as_deref() else {
+            return Decision::Allow;
+        };
+
+        let operation = Self::policy_name(ctx);
+        if self.config.is_operation_allowed(username, operation) {
+            Decision::Allow
+        } else {
+            Decision::deny(format!("{} not permitted for this account", operation))
+        }
+    }
+}
+
+/// Cache key: the session is implied by the per-session gate
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    operation: Operation,
+    read_only: bool,
+    path: PathBuf,
+    target_path: Option<PathBuf>,
+}
+
+/// Per-session authorization with timeout, fail policy and decision cache
+///
+/// NIST 800-53: AC-3 (Access Enforcement), SC-24 (Fail in Known State)
+pub struct AuthorizationGate {
+    authorizer: Arc<dyn Authorizer>,
+    timeout: Duration,
+    fail_policy: FailPolicy,
+    cache_ttl: Duration,
+    cache: HashMap<CacheKey, (Decision, Instant)>,
+}
+
+impl AuthorizationGate {
+    /// Gate consulting `authorizer` with the given settings
+    pub fn new(authorizer: Arc<dyn Authorizer>, config: &AuthorizationConfig) -> Self {
+        Self {
+            authorizer,
+            timeout: Duration::from_millis(config.timeout_ms),
+            fail_policy: config.fail_policy,
+            cache_ttl: Duration::from_millis(config.cache_ttl_ms),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Check an operation, returning the sanitized deny reason if refused
+    ///
+    /// Decisions are reused for the same operation and path until the cache
+    /// TTL expires; offsets and lengths are not part of the cache key.
+    /// Timeouts are not cached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the operation was denied.
+    pub async fn check(&mut self, ctx: &OperationContext) -> std::result::Result<(), String> {
+        let key = CacheKey {
+            operation: ctx.operation,
+            read_only: ctx.is_read_only(),
+            path: ctx.path.clone(),
+            target_path: ctx.target_path.clone(),
+        };
+
+        let now = Instant::now();
+        let cached = self
+            .cache
+            .get(&key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(decision, _)| decision.clone());
+
+        let decision = if let Some(decision) = cached {
+            decision
+        } else {
+            match timeout(self.timeout, self.authorizer.authorize(ctx)).await {
+                Ok(decision) => {
+                    self.remember(key, decision.clone(), now);
+                    decision
+                }
+                Err(_) => {
+                    warn!(
+                        event = "authorization_timeout",
+                        operation = ctx.operation.name(),
+                        path = %ctx.path.display(),
+                        fail_policy = ?self.fail_policy,
+                        "Authorizer did not answer within {} ms",
+                        self.timeout.as_millis()
+                    );
+                    match self.fail_policy {
+                        FailPolicy::Open => Decision::Allow,
+                        FailPolicy::Closed => Decision::deny("authorization timed out"),
+                    }
+                }
+            }
+        };
+
+        match decision {
+            Decision::Allow => Ok(()),
+            Decision::AllowReadOnly if ctx.is_read_only() => Ok(()),
+            Decision::AllowReadOnly => Err("read-only access".to_string()),
+            Decision::Deny { reason } => Err(sanitize_reason(&reason)),
+        }
+    }
+
+    fn remember(&mut self, key: CacheKey, decision: Decision, now: Instant) {
+        if self.cache_ttl.is_zero() {
+            return;
+        }
+        if self.cache.len() >= MAX_CACHED_DECISIONS {
+            self.cache.retain(|_, (_, expires_at)| *expires_at > now);
+            if self.cache.len() >= MAX_CACHED_DECISIONS {
+                self.cache.clear();
+            }
+        }
+        self.cache.insert(key, (decision, now + self.cache_ttl));
+    }
+}
+
+/// Strip control characters and bound the length of a deny reason
+///
+/// NIST 800-53: SI-11 (Error Handling)
+fn sanitize_reason(reason: &str) -> String {
+    let reason: String = reason
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_REASON_LENGTH)
+        .collect();
+    let reason = reason.trim();
+    if reason.is_empty() {
+        "denied by policy".to_string()
+    } else {
+        reason.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts calls and allows everything
+    #[derive(Default)]
+    struct CountingAuthorizer {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Authorizer for CountingAuthorizer {
+        async fn authorize(&self, _ctx: &OperationContext) -> Decision {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Decision::Allow
+        }
+    }
+
+    /// Never answers in time
+    struct SlowAuthorizer;
+
+    #[async_trait::async_trait]
+    impl Authorizer for SlowAuthorizer {
+        async fn authorize(&self, _ctx: &OperationContext) -> Decision {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Decision::Allow
+        }
+    }
+
+    fn session() -> SessionInfo {
+        let mut session = SessionInfo::new("test-session".to_string(), None);
+        session.set_username("alice".to_string());
+        session
+    }
+
+    fn settings(fail_policy: FailPolicy) -> AuthorizationConfig {
+        AuthorizationConfig {
+            timeout_ms: 100,
+            fail_policy,
+            ..AuthorizationConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_reused_for_sequential_reads() {
+        let authorizer = Arc::new(CountingAuthorizer::default());
+        let mut gate = AuthorizationGate::new(authorizer.clone(), &settings(FailPolicy::Closed));
+
+        for block in 0..10 {
+            let ctx = OperationContext::new(session(), Operation::Read, "/images/boot.wim")
+                .with_range(block * 32768, 32768);
+            assert_eq!(gate.check(&ctx).await, Ok(()));
+        }
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 1);
+
+        let other = OperationContext::new(session(), Operation::Read, "/images/other.wim");
+        assert_eq!(gate.check(&other).await, Ok(()));
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cache_expires_after_ttl() {
+        let authorizer = Arc::new(CountingAuthorizer::default());
+        let mut gate = AuthorizationGate::new(authorizer.clone(), &settings(FailPolicy::Closed));
+        let ctx = OperationContext::new(session(), Operation::Stat, "/images");
+
+        assert_eq!(gate.check(&ctx).await, Ok(()));
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(gate.check(&ctx).await, Ok(()));
+
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_follows_fail_policy() {
+        let ctx = OperationContext::new(session(), Operation::Remove, "/images/boot.wim");
+
+        let mut closed =
+            AuthorizationGate::new(Arc::new(SlowAuthorizer), &settings(FailPolicy::Closed));
+        assert_eq!(
+            closed.check(&ctx).await,
+            Err("authorization timed out".to_string())
+        );
+
+        let mut open =
+            AuthorizationGate::new(Arc::new(SlowAuthorizer), &settings(FailPolicy::Open));
+        assert_eq!(open.check(&ctx).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_static_policy_read_only_user() {
+        let mut config = Config::default();
+        config.users.insert(
+            "alice".to_string(),
+            crate::config::UserConfig {
+                read_only: true,
+                ..crate::config::UserConfig::default()
+            },
+        );
+        let mut gate = AuthorizationGate::new(
+            Arc::new(StaticAuthorizer::new(Arc::new(config))),
+            &AuthorizationConfig::default(),
+        );
+
+        let read =
+            OperationContext::new(session(), Operation::Open, "/a.txt").with_flags(OpenFlags::READ);
+        let write = OperationContext::new(session(), Operation::Open, "/a.txt")
+            .with_flags(OpenFlags::WRITE | OpenFlags::CREAT);
+
+        assert_eq!(gate.check(&read).await, Ok(()));
+        assert_eq!(
+            gate.check(&write).await,
+            Err("write not permitted for this account".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reason_sanitized() {
+        assert_eq!(sanitize_reason("no\r\nuploads\u{7}"), "nouploads");
+        assert_eq!(sanitize_reason("\n"), "denied by policy");
+        assert_eq!(sanitize_reason(&"x".repeat(1000)).len(), MAX_REASON_LENGTH);
+    }
+    #[test]
+    fn test_required_access() {
+        let read = OperationContext::new(session(), Operation::Open, "/a").with_flags(OpenFlags::READ);
+        assert_eq!(read.required_access(), vec![(PathAccess::Read, Path::new("/a"))]);
+
+        let remove = OperationContext::new(session(), Operation::Remove, "/a");
+        assert_eq!(remove.required_access(), vec![(PathAccess::Delete, Path::new("/a"))]);
+
+        let rename = OperationContext::new(session(), Operation::Rename, "/a").with_target("/b");
+        assert_eq!(
+            rename.required_access(),
+            vec![
+                (PathAccess::Write, Path::new("/a")),
+                (PathAccess::Write, Path::new("/b"))
+            ]
+        );
+
+        let symlink = OperationContext::new(session(), Operation::Symlink, "/link").with_target("/etc");
+        assert_eq!(symlink.required_access(), vec![(PathAccess::Write, Path::new("/link"))]);
+    }
+}
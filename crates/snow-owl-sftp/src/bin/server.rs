@@ -6,15 +6,27 @@
 //!
 //! Run with: cargo run --bin snow-owl-sftp-server
 
-use clap::Parser;
-use snow_owl_sftp::{Config, LogFormat, Server};
+use clap::{Parser, Subcommand};
+use snow_owl_core::ShutdownCoordinator;
+use snow_owl_core::config_schema::{check_config_file, config_schema};
+use snow_owl_sftp::{Config, HostKeyPaths, LogFormat, Server};
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
+/// How long open sessions may keep running after a shutdown signal
+///
+/// NIST 800-53: SC-24 (Fail in Known State)
+const SESSION_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Configuration file path
     #[arg(short, long)]
     config: Option<String>,
@@ -31,9 +43,9 @@ struct Args {
     #[arg(short, long)]
     root: Option<PathBuf>,
 
-    /// Host key path
+    /// Host key file or directory (repeat to offer several keys)
     #[arg(long)]
-    host_key: Option<PathBuf>,
+    host_key: Vec<PathBuf>,
 
     /// Verbose logging (debug level)
     #[arg(short, long)]
@@ -48,10 +60,84 @@ struct Args {
     log_file: Option<PathBuf>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export or check the configuration file schema
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the JSON Schema for the configuration file
+    Schema,
+    /// Check a configuration file against the schema without starting
+    Validate {
+        /// Configuration file to check (defaults to --config)
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Validate against this exported schema instead of the built-in one
+        #[arg(long, num_args = 0..=1, value_name = "SCHEMA_FILE")]
+        schema: Option<Option<PathBuf>>,
+    },
+}
+
+/// Run the `config` subcommands and return the process exit code
+///
+/// NIST 800-53: CM-3 (Configuration Change Control)
+fn run_config_command(command: ConfigCommand, config_path: Option<&str>) -> i32 {
+    match command {
+        ConfigCommand::Schema => match serde_json::to_string_pretty(&config_schema::<Config>()) {
+            Ok(json) => {
+                println!("{}", json);
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to serialize schema: {}", e);
+                1
+            }
+        },
+        ConfigCommand::Validate { file, schema } => {
+            let Some(file) = file.or_else(|| config_path.map(PathBuf::from)) else {
+                eprintln!("--file or --config is required");
+                return 1;
+            };
+            let report = match check_config_file::<Config>(&file, schema.flatten().as_deref()) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            for warning in &report.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            for error in &report.errors {
+                eprintln!("error: {}", error);
+            }
+            if report.is_valid() {
+                println!("Config OK: {}", file.display());
+                0
+            } else {
+                eprintln!(
+                    "{} failed validation with {} error(s)",
+                    file.display(),
+                    report.errors.len()
+                );
+                1
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
 
+    if let Some(Command::Config(command)) = args.command {
+        std::process::exit(run_config_command(command, args.config.as_deref()));
+    }
+
     // Load or create configuration
     let config = if let Some(config_path) = args.config {
         match Config::from_file(&config_path) {
@@ -71,8 +157,8 @@ async fn main() {
             config.root_dir = root;
         }
 
-        if let Some(host_key) = args.host_key {
-            config.host_key_path = host_key;
+        if !args.host_key.is_empty() {
+            config.host_key_path = HostKeyPaths::Many(args.host_key);
         }
 
         if let Some(log_format) = args.log_format {
@@ -244,15 +330,56 @@ async fn main() {
         "SFTP server is now running and accepting connections"
     );
 
-    if let Err(e) = server.run().await {
-        error!(
-            event = "server_error",
-            error = %e,
-            "Server encountered an error"
-        );
-        std::process::exit(1);
+    let audit = server.audit_channel();
+    let stop = CancellationToken::new();
+    let mut server_task =
+        tokio::spawn(server.run_with_shutdown(stop.clone(), SESSION_DRAIN_TIMEOUT));
+
+    tokio::select! {
+        result = &mut server_task => {
+            let error = match result {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(e) = error {
+                error!(
+                    event = "server_error",
+                    error = %e,
+                    "Server encountered an error"
+                );
+                std::process::exit(1);
+            }
+        }
+        result = tokio::signal::ctrl_c() => {
+            if let Err(e) = result {
+                error!(
+                    event = "signal_error",
+                    error = %e,
+                    "Failed to listen for shutdown signal"
+                );
+            }
+        }
     }
 
+    // NIST 800-53 SC-24: Fail in Known State
+    // The server aborts sessions still open at the drain timeout; the phase
+    // allows a little longer for that to complete
+    let mut shutdown = ShutdownCoordinator::new("snow-owl-sftp");
+    shutdown.register(
+        "sftp",
+        SESSION_DRAIN_TIMEOUT + Duration::from_secs(5),
+        move || async move {
+            stop.cancel();
+            let _ = server_task.await;
+        },
+    );
+    // NIST 800-53 AU-5: Write out queued audit events before exiting
+    shutdown.register("audit", Duration::from_secs(10), move || async move {
+        let _ = tokio::task::spawn_blocking(move || audit.shutdown()).await;
+    });
+    shutdown.run("signal").await;
+
     info!(
         event = "server_shutdown",
         "SFTP server shutdown complete"
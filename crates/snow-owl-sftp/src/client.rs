@@ -4,17 +4,19 @@
 //! STIG: V-222577 (Cryptographic mechanisms), V-222611 (Certificate validation)
 //! Implementation: RFC-compliant SFTP client with SSH authentication
 
+use crate::mirror::{self, LocalKind, RemoteKind, SymlinkHandling, SyncOptions, SyncReport};
 use crate::{cnsa, Error, Result};
 use bytes::{Buf, BufMut, BytesMut};
 use russh::client::{self, Handle, Msg};
 use russh::{Channel, ChannelMsg};
 use russh::keys::{PrivateKey, PrivateKeyWithHashAlg, PublicKey};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::Mutex;
+use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
 
 use crate::protocol::{codec, FileAttrs, MessageType, OpenFlags, StatusCode, SFTP_VERSION};
@@ -29,6 +31,8 @@ pub struct Client {
     channel: Arc<Mutex<Option<Channel<Msg>>>>,
     next_request_id: Arc<Mutex<u32>>,
     _responses: Arc<Mutex<HashMap<u32, Vec<u8>>>>,
+    /// Channel data not yet returned as a whole packet
+    received: Arc<Mutex<BytesMut>>,
 }
 
 impl Client {
@@ -109,23 +113,14 @@ impl Client {
 
         info!("Authentication successful for user: {}", username);
 
-        // Open channel for SFTP subsystem
-        let channel = session
-            .channel_open_session()
-            .await
-            .map_err(|e| Error::Connection(format!("Failed to open channel: {}", e)))?;
-
-        // Request SFTP subsystem
-        channel
-            .request_subsystem(true, "sftp")
-            .await
-            .map_err(|e| Error::Protocol(format!("Failed to start SFTP subsystem: {}", e)))?;
+        let channel = open_sftp_channel(&session).await?;
 
         let client = Self {
             session: Arc::new(Mutex::new(Some(session))),
             channel: Arc::new(Mutex::new(Some(channel))),
             next_request_id: Arc::new(Mutex::new(1)),
             _responses: Arc::new(Mutex::new(HashMap::new())),
+            received: Arc::new(Mutex::new(BytesMut::new())),
         };
 
         // Initialize SFTP protocol
@@ -445,6 +440,249 @@ impl Client {
         self.parse_attrs_response(&response)
     }
 
+    /// Set file attributes
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File or directory path
+    /// * `attrs` - Attributes to apply; unset fields are left alone
+    ///
+    /// # NIST 800-53: AC-3 (Access Enforcement)
+    /// # Implementation: Changes attributes within authorized scope
+    pub async fn setstat(&mut self, path: &str, attrs: &FileAttrs) -> Result<()> {
+        debug!("Setting attributes for: {}", path);
+
+        let request_id = self.next_request_id().await;
+
+        let mut buf = BytesMut::new();
+        buf.put_u8(MessageType::Setstat as u8);
+        buf.put_u32(request_id);
+        codec::put_string(&mut buf, path);
+        buf.extend_from_slice(&attrs.encode());
+
+        self.send_packet(&buf).await?;
+        self.check_status(request_id).await
+    }
+
+    /// Create a symbolic link
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Path the link points to, stored as given
+    /// * `link_path` - Path of the link to create
+    ///
+    /// # NIST 800-53: AC-3 (Access Enforcement)
+    /// # Implementation: Creates link within authorized scope; the server's link policy applies
+    pub async fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        debug!("Creating symlink {} -> {}", link_path, target);
+
+        let request_id = self.next_request_id().await;
+
+        let mut buf = BytesMut::new();
+        buf.put_u8(MessageType::Symlink as u8);
+        buf.put_u32(request_id);
+        codec::put_string(&mut buf, link_path);
+        codec::put_string(&mut buf, target);
+
+        self.send_packet(&buf).await?;
+        self.check_status(request_id).await
+    }
+
+    /// Read the target of a symbolic link
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Link path
+    ///
+    /// # Returns
+    ///
+    /// The target stored in the link
+    ///
+    /// # NIST 800-53: AC-3 (Access Enforcement)
+    /// # Implementation: Reads link within authorized scope
+    pub async fn readlink(&mut self, path: &str) -> Result<String> {
+        debug!("Reading symlink: {}", path);
+
+        let request_id = self.next_request_id().await;
+
+        let mut buf = BytesMut::new();
+        buf.put_u8(MessageType::Readlink as u8);
+        buf.put_u32(request_id);
+        codec::put_string(&mut buf, path);
+
+        self.send_packet(&buf).await?;
+
+        let response = self.receive_response(request_id).await?;
+        self.parse_name_response(&response)?
+            .into_iter()
+            .next()
+            .map(|(target, _)| target)
+            .ok_or_else(|| Error::Protocol("Empty READLINK response".into()))
+    }
+
+    /// Make a remote directory mirror a local one
+    ///
+    /// Walks `local`, creates missing remote directories and uploads files
+    /// whose size or modification time differ from the remote copy. Uploaded
+    /// files get the local modification time, so a second sync of an
+    /// unchanged tree transfers nothing. With `delete_extraneous`, remote
+    /// entries absent locally are removed afterwards, contents first.
+    ///
+    /// Uploads run on up to `concurrency` SFTP channels of this connection.
+    /// A path that fails is recorded in the report and the sync carries on.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - Local directory to mirror
+    /// * `remote` - Remote directory to update; created if missing
+    /// * `opts` - Deletion, concurrency and symbolic link handling
+    ///
+    /// # Returns
+    ///
+    /// What was uploaded, skipped, deleted and what failed
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The local directory cannot be read
+    /// - The remote directory cannot be created or listed
+    ///
+    /// # NIST 800-53: CM-3 (Configuration Change Control), SI-7 (Software, Firmware, and Information Integrity)
+    /// # Implementation: Transfers only changed files over encrypted SSH channels
+    pub async fn sync_dir(
+        &mut self,
+        local: &Path,
+        remote: &str,
+        opts: SyncOptions,
+    ) -> Result<SyncReport> {
+        info!("Syncing {:?} to {}", local, remote);
+
+        let root = local.to_path_buf();
+        let symlinks = opts.symlinks;
+        let (entries, failures) =
+            tokio::task::spawn_blocking(move || mirror::scan_local(&root, symlinks))
+                .await
+                .map_err(|e| Error::Other(format!("Local scan failed: {}", e)))??;
+
+        let mut report = SyncReport {
+            failed: failures,
+            ..SyncReport::default()
+        };
+
+        match self.stat(remote).await {
+            Ok(attrs) if RemoteKind::of(&attrs) != RemoteKind::Dir => {
+                return Err(Error::InvalidPath(format!("{} is not a directory", remote)));
+            }
+            Ok(_) => {}
+            Err(_) => self.mkdir(remote).await?,
+        }
+        let remote_tree = self.list_tree(remote).await?;
+
+        let mut uploads = Vec::new();
+        for entry in &entries {
+            let relative = entry.relative.as_str();
+            let path = mirror::remote_join(remote, relative);
+            let existing = remote_tree.get(relative);
+
+            match &entry.kind {
+                LocalKind::Dir => match existing.map(RemoteKind::of) {
+                    Some(RemoteKind::Dir) => {}
+                    Some(_) => report.fail(relative, "remote path is not a directory"),
+                    None => {
+                        if let Err(e) = self.mkdir(&path).await {
+                            report.fail(relative, e);
+                        }
+                    }
+                },
+                LocalKind::File { size, mtime } => match existing {
+                    Some(attrs) if RemoteKind::of(attrs) == RemoteKind::Dir => {
+                        report.fail(relative, "remote path is a directory");
+                    }
+                    Some(attrs) if !mirror::needs_upload(*size, *mtime, attrs) => {
+                        report.skipped.push(entry.relative.clone());
+                    }
+                    _ => uploads.push(PendingUpload {
+                        relative: entry.relative.clone(),
+                        local: entry.path.clone(),
+                        remote: path,
+                        mtime: *mtime,
+                    }),
+                },
+                LocalKind::Symlink { target } => {
+                    if opts.symlinks != SymlinkHandling::Recreate {
+                        report.skipped.push(entry.relative.clone());
+                        continue;
+                    }
+                    let existing = existing.map(RemoteKind::of);
+                    if existing == Some(RemoteKind::Dir) {
+                        report.fail(relative, "remote path is a directory");
+                    } else if existing == Some(RemoteKind::Symlink)
+                        && self.readlink(&path).await.ok().as_deref() == Some(target.as_str())
+                    {
+                        report.skipped.push(entry.relative.clone());
+                    } else {
+                        match self.replace_symlink(target, &path, existing.is_some()).await {
+                            Ok(()) => report.uploaded.push(entry.relative.clone()),
+                            Err(e) => report.fail(relative, e),
+                        }
+                    }
+                }
+            }
+        }
+
+        for (relative, result) in self.run_uploads(uploads, opts.concurrency).await {
+            match result {
+                Ok(()) => report.uploaded.push(relative),
+                Err(e) => report.fail(&relative, e),
+            }
+        }
+
+        if opts.delete_extraneous {
+            // Anything local is kept, even if it was skipped or failed
+            let keep: HashSet<&str> = entries
+                .iter()
+                .map(|e| e.relative.as_str())
+                .chain(report.failed.iter().map(|f| f.path.as_str()))
+                .collect();
+            let extraneous: Vec<(String, RemoteKind)> = remote_tree
+                .iter()
+                .rev()
+                .filter(|(relative, _)| !keep.contains(relative.as_str()))
+                .map(|(relative, attrs)| (relative.clone(), RemoteKind::of(attrs)))
+                .collect();
+
+            // Reverse name order puts a directory's contents before it
+            for (relative, kind) in extraneous {
+                let path = mirror::remote_join(remote, &relative);
+                let result = if kind == RemoteKind::Dir {
+                    self.rmdir(&path).await
+                } else {
+                    self.remove(&path).await
+                };
+                match result {
+                    Ok(()) => report.deleted.push(relative),
+                    Err(e) => report.fail(&relative, e),
+                }
+            }
+        }
+
+        report.uploaded.sort();
+        report.skipped.sort();
+        report.failed.sort_by(|a, b| a.path.cmp(&b.path));
+
+        info!(
+            "Sync of {:?} to {} finished: {} uploaded, {} skipped, {} deleted, {} failed",
+            local,
+            remote,
+            report.uploaded.len(),
+            report.skipped.len(),
+            report.deleted.len(),
+            report.failed.len()
+        );
+
+        Ok(report)
+    }
+
     /// Disconnect from the server
     ///
     /// # NIST 800-53: AC-12 (Session Termination)
@@ -464,6 +702,133 @@ impl Client {
 
     // ===== Private helper methods =====
 
+    /// Open another SFTP channel on this connection
+    ///
+    /// The returned client shares the SSH session; drop it with
+    /// [`close_channel`](Self::close_channel), never `disconnect`.
+    async fn open_worker(&self) -> Result<Self> {
+        let channel = {
+            let session = self.session.lock().await;
+            let session = session
+                .as_ref()
+                .ok_or_else(|| Error::Connection("Session closed".into()))?;
+            open_sftp_channel(session).await?
+        };
+
+        let worker = Self {
+            session: self.session.clone(),
+            channel: Arc::new(Mutex::new(Some(channel))),
+            next_request_id: Arc::new(Mutex::new(1)),
+            _responses: Arc::new(Mutex::new(HashMap::new())),
+            received: Arc::new(Mutex::new(BytesMut::new())),
+        };
+        worker.init().await?;
+        Ok(worker)
+    }
+
+    async fn close_channel(&self) {
+        let channel = self.channel.lock().await.take();
+        if let Some(channel) = channel {
+            channel.close().await.ok();
+        }
+    }
+
+    /// Upload queued files on up to `concurrency` channels, this one included
+    async fn run_uploads(
+        &mut self,
+        uploads: Vec<PendingUpload>,
+        concurrency: usize,
+    ) -> Vec<(String, Result<()>)> {
+        let mut workers = Vec::new();
+        for _ in 1..concurrency.min(uploads.len()) {
+            match self.open_worker().await {
+                Ok(worker) => workers.push(worker),
+                Err(e) => {
+                    warn!("Uploading on {} channels: {}", workers.len() + 1, e);
+                    break;
+                }
+            }
+        }
+
+        let queue = Arc::new(Mutex::new(VecDeque::from(uploads)));
+        let mut tasks = JoinSet::new();
+        for mut worker in workers {
+            let queue = queue.clone();
+            tasks.spawn(async move {
+                let done = worker.drain_uploads(&queue).await;
+                worker.close_channel().await;
+                done
+            });
+        }
+
+        let mut results = self.drain_uploads(&queue).await;
+        while let Some(done) = tasks.join_next().await {
+            match done {
+                Ok(done) => results.extend(done),
+                Err(e) => warn!("Upload worker failed: {}", e),
+            }
+        }
+        results
+    }
+
+    async fn drain_uploads(
+        &mut self,
+        queue: &Mutex<VecDeque<PendingUpload>>,
+    ) -> Vec<(String, Result<()>)> {
+        let mut done = Vec::new();
+        loop {
+            let Some(upload) = queue.lock().await.pop_front() else {
+                return done;
+            };
+            let result = self.upload_file(&upload).await;
+            done.push((upload.relative, result));
+        }
+    }
+
+    async fn upload_file(&mut self, upload: &PendingUpload) -> Result<()> {
+        self.put(&upload.local, &upload.remote).await?;
+
+        // The local mtime marks the copy current for the next sync
+        let attrs = FileAttrs {
+            atime: Some(upload.mtime),
+            mtime: Some(upload.mtime),
+            ..FileAttrs::default()
+        };
+        self.setstat(&upload.remote, &attrs).await
+    }
+
+    async fn replace_symlink(&mut self, target: &str, path: &str, exists: bool) -> Result<()> {
+        if exists {
+            self.remove(path).await?;
+        }
+        self.symlink(target, path).await
+    }
+
+    /// Everything under `root`, keyed by `/`-separated relative path
+    async fn list_tree(&mut self, root: &str) -> Result<BTreeMap<String, FileAttrs>> {
+        let mut tree = BTreeMap::new();
+        let mut pending = vec![String::new()];
+
+        while let Some(prefix) = pending.pop() {
+            for (name, attrs) in self.list(&mirror::remote_join(root, &prefix)).await? {
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let relative = if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", prefix, name)
+                };
+                if RemoteKind::of(&attrs) == RemoteKind::Dir {
+                    pending.push(relative.clone());
+                }
+                tree.insert(relative, attrs);
+            }
+        }
+
+        Ok(tree)
+    }
+
     async fn open(&mut self, path: &str, flags: OpenFlags) -> Result<Vec<u8>> {
         let request_id = self.next_request_id().await;
 
@@ -559,8 +924,8 @@ impl Client {
             MessageType::Status => {
                 // EOF is indicated by STATUS with EOF code
                 let mut buf = &response[1..];
-                let _request_id = buf.get_u32();
-                let code = buf.get_u32();
+                let _request_id = codec::get_u32(&mut buf)?;
+                let code = codec::get_u32(&mut buf)?;
 
                 if code == StatusCode::Eof as u32 {
                     Ok(None) // EOF
@@ -610,42 +975,42 @@ impl Client {
         Ok(())
     }
 
+    /// Next packet from the server, without its length
+    ///
+    /// Packets are framed by a uint32 length, and SSH may split one across
+    /// channel messages or put several in one, so data is buffered until a
+    /// whole packet has arrived.
     async fn receive_packet(&self) -> Result<Vec<u8>> {
         let mut channel = self.channel.lock().await;
         let channel = channel
             .as_mut()
             .ok_or_else(|| Error::Connection("Channel closed".into()))?;
+        let mut received = self.received.lock().await;
 
-        // Wait for channel message
         loop {
-            if let Some(msg) = channel.wait().await {
-                match msg {
-                    ChannelMsg::Data { data } => {
-                        if data.len() < 4 {
-                            return Err(Error::Protocol("Packet too short".into()));
-                        }
-
-                        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
-
-                        if data.len() < 4 + len {
-                            return Err(Error::Protocol("Incomplete packet".into()));
-                        }
+            if received.len() >= 4 {
+                let len =
+                    u32::from_be_bytes([received[0], received[1], received[2], received[3]]) as usize;
+                if received.len() >= 4 + len {
+                    received.advance(4);
+                    return Ok(received.split_to(len).to_vec());
+                }
+            }
 
-                        return Ok(data[4..4 + len].to_vec());
-                    }
-                    ChannelMsg::Eof => {
-                        return Err(Error::Connection("Channel EOF".into()));
-                    }
-                    ChannelMsg::Close => {
-                        return Err(Error::Connection("Channel closed".into()));
-                    }
-                    _ => {
-                        // Ignore other messages
-                        continue;
-                    }
+            // Wait for channel message
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => received.extend_from_slice(&data),
+                Some(ChannelMsg::Eof) => {
+                    return Err(Error::Connection("Channel EOF".into()));
+                }
+                Some(ChannelMsg::Close) => {
+                    return Err(Error::Connection("Channel closed".into()));
+                }
+                // Ignore other messages
+                Some(_) => {}
+                None => {
+                    return Err(Error::Connection("Channel closed unexpectedly".into()));
                 }
-            } else {
-                return Err(Error::Connection("Channel closed unexpectedly".into()));
             }
         }
     }
@@ -673,8 +1038,8 @@ impl Client {
         }
 
         let mut buf = &response[1..];
-        let _resp_id = buf.get_u32();
-        let code = buf.get_u32();
+        let _resp_id = codec::get_u32(&mut buf)?;
+        let code = codec::get_u32(&mut buf)?;
         let message = codec::get_string(&mut buf).unwrap_or_default();
 
         if code == StatusCode::Ok as u32 {
@@ -699,7 +1064,7 @@ impl Client {
         }
 
         let mut buf = &response[1..];
-        let _request_id = buf.get_u32();
+        let _request_id = codec::get_u32(&mut buf)?;
         let handle = codec::get_bytes(&mut buf)?;
 
         Ok(handle.to_vec())
@@ -715,15 +1080,15 @@ impl Client {
         match msg_type {
             MessageType::Data => {
                 let mut buf = &response[1..];
-                let _request_id = buf.get_u32();
+                let _request_id = codec::get_u32(&mut buf)?;
                 let data = codec::get_bytes(&mut buf)?;
                 Ok(data.to_vec())
             }
             MessageType::Status => {
                 // Check for EOF
                 let mut buf = &response[1..];
-                let _request_id = buf.get_u32();
-                let code = buf.get_u32();
+                let _request_id = codec::get_u32(&mut buf)?;
+                let code = codec::get_u32(&mut buf)?;
 
                 if code == StatusCode::Eof as u32 {
                     Ok(Vec::new()) // EOF
@@ -754,7 +1119,7 @@ impl Client {
         }
 
         let mut buf = &response[1..];
-        let _request_id = buf.get_u32();
+        let _request_id = codec::get_u32(&mut buf)?;
         let attrs = FileAttrs::decode(&mut buf)?;
 
         Ok(attrs)
@@ -775,10 +1140,12 @@ impl Client {
         }
 
         let mut buf = &response[1..];
-        let _request_id = buf.get_u32();
-        let count = buf.get_u32() as usize;
+        let _request_id = codec::get_u32(&mut buf)?;
+        let count = codec::get_u32(&mut buf)? as usize;
 
-        let mut entries = Vec::with_capacity(count);
+        // The count comes from the wire: reserve no more than the remaining
+        // bytes could hold (each entry is at least two strings and a flags word)
+        let mut entries = Vec::with_capacity(count.min(buf.remaining() / 12));
 
         for _ in 0..count {
             let filename = codec::get_string(&mut buf)?;
@@ -799,6 +1166,31 @@ impl Client {
     }
 }
 
+/// File waiting to be uploaded by [`Client::sync_dir`]
+struct PendingUpload {
+    relative: String,
+    local: PathBuf,
+    remote: String,
+    mtime: u32,
+}
+
+/// Open a session channel and start the SFTP subsystem on it
+///
+/// # NIST 800-53: SC-8 (Transmission Confidentiality)
+async fn open_sftp_channel(session: &Handle<ClientHandler>) -> Result<Channel<Msg>> {
+    let channel = session
+        .channel_open_session()
+        .await
+        .map_err(|e| Error::Connection(format!("Failed to open channel: {}", e)))?;
+
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .map_err(|e| Error::Protocol(format!("Failed to start SFTP subsystem: {}", e)))?;
+
+    Ok(channel)
+}
+
 /// SSH client handler
 struct ClientHandler {}
 
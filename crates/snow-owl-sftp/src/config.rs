@@ -1,13 +1,16 @@
 //! Configuration for SFTP server and client
 
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use clap::ValueEnum;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::net::IpAddr;
 
 /// SFTP server configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     /// Server bind address
     #[serde(default = "default_bind_address")]
@@ -21,14 +24,27 @@ pub struct Config {
     #[serde(default = "default_root_dir")]
     pub root_dir: PathBuf,
 
-    /// SSH host key path
+    /// SSH host key file or directory, or a list of them
+    ///
+    /// Every key with a CNSA 2.0 host key algorithm is offered, so each
+    /// client negotiates one it supports (NIST 800-53: IA-3, SC-13)
     #[serde(default = "default_host_key_path")]
-    pub host_key_path: PathBuf,
+    pub host_key_path: HostKeyPaths,
 
     /// Authorized keys file path
     #[serde(default = "default_authorized_keys_path")]
     pub authorized_keys_path: PathBuf,
 
+    /// Accept password and keyboard-interactive authentication as well as
+    /// public keys (NIST 800-53: IA-2)
+    #[serde(default)]
+    pub password_auth: bool,
+
+    /// File of `username:hash` lines with Argon2 password hashes, checked
+    /// when `password_auth` is set (NIST 800-53: IA-5(1))
+    #[serde(default)]
+    pub password_file: Option<PathBuf>,
+
     /// Maximum concurrent connections
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
@@ -41,14 +57,46 @@ pub struct Config {
     #[serde(default)]
     pub verbose: bool,
 
-    /// Maximum packet size (RFC 4254 recommends 32768 bytes minimum)
+    /// Largest SFTP packet accepted from a client, in bytes; larger ones are
+    /// answered with `SSH_FX_BAD_MESSAGE` unparsed (RFC 4254 minimum 32768)
+    /// (NIST 800-53: SC-5)
     #[serde(default = "default_max_packet_size")]
+    #[schemars(range(min = 32768))]
     pub max_packet_size: u32,
 
+    /// Largest READ served in one reply, in bytes; longer requests get a
+    /// short read (NIST 800-53: SC-5)
+    #[serde(default = "default_max_read_len")]
+    #[schemars(range(min = 1))]
+    pub max_read_len: u32,
+
     /// Window size for flow control
     #[serde(default = "default_window_size")]
     pub window_size: u32,
 
+    /// Maximum bytes prefetched per file handle for sequential reads (0 = disabled)
+    #[serde(default = "default_read_ahead_max_bytes")]
+    pub read_ahead_max_bytes: usize,
+
+    /// Most entries returned by one SSH_FXP_READDIR (NIST 800-53: SC-5)
+    #[serde(default = "default_readdir_batch_size")]
+    #[schemars(range(min = 1))]
+    pub readdir_batch_size: usize,
+
+    /// Most file and directory handles one session may hold open; further
+    /// OPEN and OPENDIR requests fail until one is closed (NIST 800-53: SC-5)
+    #[serde(default = "default_max_open_handles")]
+    #[schemars(range(min = 1))]
+    pub max_open_handles: usize,
+
+    /// Maximum path length in bytes accepted from clients (NIST 800-53: SI-10)
+    #[serde(default = "default_max_path_length")]
+    pub max_path_length: usize,
+
+    /// Maximum length in bytes of a single path component (NIST 800-53: SI-10)
+    #[serde(default = "default_max_filename_length")]
+    pub max_filename_length: usize,
+
     /// Maximum authentication attempts per IP (NIST 800-53: AC-7)
     #[serde(default = "default_max_auth_attempts")]
     pub max_auth_attempts: u32,
@@ -65,6 +113,18 @@ pub struct Config {
     #[serde(default = "default_max_connections_per_user")]
     pub max_connections_per_user: usize,
 
+    /// Disconnect sessions with no SFTP activity for this many seconds, 0 = never (AC-12)
+    #[serde(default)]
+    pub session_idle_timeout_secs: u64,
+
+    /// Disconnect sessions older than this many seconds, 0 = never (AC-12)
+    #[serde(default)]
+    pub max_session_lifetime_secs: u64,
+
+    /// Warn daily about accounts expiring within this many days (NIST 800-53: AC-2)
+    #[serde(default = "default_account_expiry_warning_days")]
+    pub account_expiry_warning_days: u32,
+
     /// Logging configuration
     #[serde(default)]
     pub logging: LoggingConfig,
@@ -73,10 +133,71 @@ pub struct Config {
     #[serde(default)]
     pub users: HashMap<String, UserConfig>,
 
+    /// Root directory per username, used in place of `root_dir` for that
+    /// user's sessions (NIST 800-53: AC-3, AC-6)
+    #[serde(default)]
+    pub user_roots: HashMap<String, PathBuf>,
+
+    /// Root directory for users with neither a `user_roots` entry nor a
+    /// `home_dir`, with `{user}` replaced by the username, e.g.
+    /// `/srv/sftp/{user}` (NIST 800-53: AC-3, AC-6)
+    #[serde(default)]
+    pub user_root_template: Option<String>,
+
+    /// Create a user's root directory at login if it does not exist yet
+    #[serde(default)]
+    pub create_user_root: bool,
+
+    /// Named operation policies users share through `role` (NIST 800-53: AC-3, AC-6)
+    #[serde(default)]
+    pub roles: HashMap<String, RoleConfig>,
+
+    /// Per-operation authorization callback settings (NIST 800-53: AC-3)
+    #[serde(default)]
+    pub authorization: AuthorizationConfig,
+
+    /// Refuse every modifying operation for all users (NIST 800-53: AC-3, AC-6)
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Per-path permissions; the most specific matching rule applies (NIST 800-53: AC-3)
+    #[serde(default)]
+    pub path_rules: Vec<PathRule>,
+
+    /// How symbolic links under `root_dir` are created and followed (NIST 800-53: AC-3)
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+
+    /// Permission bits given to created files and directories and allowed in
+    /// SETSTAT (NIST 800-53: AC-3, AC-6)
+    #[serde(default)]
+    pub file_mode_policy: FileModePolicy,
+
+    /// What a WRITE starting past the end of the file does (NIST 800-53: SI-10)
+    #[serde(default)]
+    pub write_past_eof: WritePastEof,
+
+    /// What happens when an SFTP response cannot be written to the channel (NIST 800-53: SI-11)
+    #[serde(default)]
+    pub channel_write_failure: ChannelWriteFailure,
+
+    /// Superseded by `max_bytes_per_sec_global`, which wins when both are set
+    ///
     /// Global bandwidth limit in bytes per second (0 = unlimited)
     #[serde(default)]
+    #[schemars(extend("deprecated" = true))]
     pub global_bandwidth_limit: u64,
 
+    /// READ/WRITE payload cap for each session in bytes per second (NIST 800-53: SC-5)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub max_bytes_per_sec_per_session: Option<u64>,
+
+    /// READ/WRITE payload cap for all sessions together in bytes per second (NIST 800-53: SC-5)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub max_bytes_per_sec_global: Option<u64>,
+
     /// IP whitelist - if not empty, only these IPs are allowed
     #[serde(default)]
     pub ip_whitelist: Vec<IpAddr>,
@@ -85,6 +206,13 @@ pub struct Config {
     #[serde(default)]
     pub ip_blacklist: Vec<IpAddr>,
 
+    /// Unix socket answering debug commands such as `dump-state` (unset = disabled)
+    ///
+    /// The socket is created mode 0600; anyone who can open it sees every
+    /// session's user and handle counts (NIST 800-53: AC-6, SI-4).
+    #[serde(default)]
+    pub control_socket: Option<PathBuf>,
+
     /// Configuration file path for hot reload
     #[serde(skip)]
     pub config_file_path: Option<PathBuf>,
@@ -94,7 +222,7 @@ pub struct Config {
 ///
 /// NIST 800-53: AU-2 (Audit Events), AU-9 (Protection of Audit Information), AU-12 (Audit Generation)
 /// STIG: V-222648 (Audit Records)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct LoggingConfig {
     /// Log level (trace, debug, info, warn, error)
@@ -106,6 +234,11 @@ pub struct LoggingConfig {
     /// Enable structured audit logging for SIEM integration
     /// When enabled, all security-relevant events are logged as structured JSON
     pub audit_enabled: bool,
+    /// Buffering and batching of audit events between sessions and the sink
+    pub audit_channel: AuditChannelConfig,
+    /// Also append every audit event to this file, one JSON object per line
+    /// (NIST 800-53: AU-9). Created owner-only if missing
+    pub audit_file: Option<PathBuf>,
 }
 
 impl Default for LoggingConfig {
@@ -115,14 +248,63 @@ impl Default for LoggingConfig {
             format: LogFormat::Json,
             file: Some(PathBuf::from("/var/log/snow-owl/sftp-audit.json")),
             audit_enabled: true,
+            audit_channel: AuditChannelConfig::default(),
+            audit_file: None,
+        }
+    }
+}
+
+/// Audit event queue settings
+///
+/// Sessions hand events to a bounded queue; a writer thread delivers them to
+/// the sink in batches of up to `batch_size`, or after `flush_interval_ms`
+/// once a batch has started.
+///
+/// NIST 800-53: AU-4 (Audit Log Storage Capacity), AU-5 (Response to Audit Processing Failures)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AuditChannelConfig {
+    /// Events the queue holds before `overflow` applies
+    #[schemars(range(min = 1))]
+    pub capacity: usize,
+    /// Events written to the sink at once
+    #[schemars(range(min = 1))]
+    pub batch_size: usize,
+    /// Longest an event waits in a partial batch, in milliseconds
+    #[schemars(range(min = 1))]
+    pub flush_interval_ms: u64,
+    /// What happens to new events while the queue is full
+    pub overflow: AuditOverflow,
+}
+
+impl Default for AuditChannelConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            batch_size: 256,
+            flush_interval_ms: 1000,
+            overflow: AuditOverflow::Block,
         }
     }
 }
 
+/// Behaviour when the audit queue is full
+///
+/// NIST 800-53: AU-5 (Response to Audit Processing Failures)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOverflow {
+    /// The operation emitting the event waits for room, so no event is lost
+    #[default]
+    Block,
+    /// The event is discarded and counted; operations never wait on auditing
+    Drop,
+}
+
 /// Log format options
 ///
 /// NIST 800-53: AU-9 (Protection of Audit Information)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LogFormat {
     /// Plain text logging for human readability
@@ -132,11 +314,267 @@ pub enum LogFormat {
     Json,
 }
 
+/// Per-operation authorization settings
+///
+/// Applies to the [`Authorizer`](crate::authorization::Authorizer) consulted
+/// after the built-in checks for every file operation.
+///
+/// NIST 800-53: AC-3 (Access Enforcement), SC-24 (Fail in Known State)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AuthorizationConfig {
+    /// How long to wait for an authorization decision, in milliseconds
+    pub timeout_ms: u64,
+    /// What to do when the authorizer does not answer in time
+    pub fail_policy: FailPolicy,
+    /// How long a decision is reused for the same operation and path,
+    /// in milliseconds (0 disables caching)
+    pub cache_ttl_ms: u64,
+}
+
+impl Default for AuthorizationConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 2000,
+            fail_policy: FailPolicy::Closed,
+            cache_ttl_ms: 5000,
+        }
+    }
+}
+
+/// Outcome when an authorization decision times out
+///
+/// NIST 800-53: SC-24 (Fail in Known State)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FailPolicy {
+    /// Allow the operation and log a warning
+    Open,
+    /// Deny the operation
+    Closed,
+}
+
+/// Kind of access an operation needs on a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathAccess {
+    /// Open for reading, stat, list, read links
+    Read,
+    /// Create or modify files, directories, links and attributes
+    Write,
+    /// Remove files or directories
+    Delete,
+}
+
+impl PathAccess {
+    /// Lowercase name used in deny reasons
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// Permissions for paths matching a glob pattern
+///
+/// Patterns are matched against the path relative to `root_dir`; `*` stays
+/// within one path component and `**` spans directories. Paths no rule
+/// matches keep full access (subject to `read_only`).
+///
+/// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathRule {
+    /// Glob pattern, e.g. `uploads/**`
+    pub pattern: String,
+    /// Allow reading and listing
+    #[serde(default = "default_true")]
+    pub read: bool,
+    /// Allow creating and modifying
+    #[serde(default)]
+    pub write: bool,
+    /// Allow removing
+    #[serde(default)]
+    pub delete: bool,
+}
+
+impl PathRule {
+    /// Whether this rule grants `access`
+    pub const fn allows(&self, access: PathAccess) -> bool {
+        match access {
+            PathAccess::Read => self.read,
+            PathAccess::Write => self.write,
+            PathAccess::Delete => self.delete,
+        }
+    }
+
+    /// Number of literal (non-wildcard) characters; more is more specific
+    fn specificity(&self) -> usize {
+        self.pattern
+            .chars()
+            .filter(|c| !matches!(c, '*' | '?' | '[' | ']'))
+            .count()
+    }
+}
+
+/// Host key locations, written as one path or a list
+///
+/// A directory stands for every private key file in it.
+///
+/// ```toml
+/// host_key_path = "/etc/snow-owl/ssh_host_ed25519_key"
+/// host_key_path = ["/etc/snow-owl/ssh_host_ecdsa_key", "/etc/snow-owl/ssh_host_ed25519_key"]
+/// host_key_path = "/etc/snow-owl/host_keys"
+/// ```
+///
+/// NIST 800-53: IA-3 (Device Identification and Authentication)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum HostKeyPaths {
+    /// A single key file or directory
+    One(PathBuf),
+    /// Key files and directories, loaded in order
+    Many(Vec<PathBuf>),
+}
+
+impl HostKeyPaths {
+    /// Configured paths in order
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            Self::One(path) => std::slice::from_ref(path),
+            Self::Many(paths) => paths,
+        }
+    }
+}
+
+impl From<PathBuf> for HostKeyPaths {
+    fn from(path: PathBuf) -> Self {
+        Self::One(path)
+    }
+}
+
+/// Symbolic link policy for the served tree
+///
+/// `root_dir` is the mount every client path lives under; the policy decides
+/// whether links may point outside it.
+///
+/// NIST 800-53: AC-3 (Access Enforcement), SI-10 (Information Input Validation)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// SYMLINK is refused and existing links are treated as not found when followed
+    Deny,
+    /// Links must resolve inside `root_dir`, checked when created and again
+    /// every time one is followed
+    #[default]
+    InternalOnly,
+    /// Links are created and followed without checks
+    Allow,
+}
+
+impl SymlinkPolicy {
+    /// Name as written in the configuration file
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Deny => "deny",
+            Self::InternalOnly => "internal-only",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+/// Permission bits for files and directories clients create or change
+///
+/// Applies to OPEN with CREAT, MKDIR and the permissions of SETSTAT and
+/// FSETSTAT. The process umask still applies on top at creation, as it does
+/// for `open(2)`. Mode bits are ignored on Windows.
+///
+/// ```toml
+/// file_mode_policy = "client-requested"
+/// file_mode_policy = { fixed = 0o640 }
+/// file_mode_policy = { masked = 0o027 }
+/// ```
+///
+/// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileModePolicy {
+    /// The mode in the client's attributes is used as sent
+    #[default]
+    ClientRequested,
+    /// Every new file and directory gets this mode; SETSTAT may not grant
+    /// bits outside it
+    Fixed(#[schemars(range(max = 0o7777))] u32),
+    /// These bits are cleared from the client's mode; SETSTAT may not set them
+    Masked(#[schemars(range(max = 0o7777))] u32),
+}
+
+impl FileModePolicy {
+    /// Permission bits a mode may carry (`rwx` for all, setuid, setgid, sticky)
+    pub const PERMISSION_BITS: u32 = 0o7777;
+
+    /// Mode for a new file or directory
+    ///
+    /// `default` applies when the client sent no permissions (0o666 for
+    /// files, 0o777 for directories, as `open(2)` and `mkdir(2)` use).
+    pub fn creation_mode(self, requested: Option<u32>, default: u32) -> u32 {
+        let requested = requested.unwrap_or(default) & Self::PERMISSION_BITS;
+        match self {
+            Self::ClientRequested => requested,
+            Self::Fixed(mode) => mode & Self::PERMISSION_BITS,
+            Self::Masked(umask) => requested & !umask,
+        }
+    }
+
+    /// Whether SETSTAT may set `mode`
+    pub fn permits(self, mode: u32) -> bool {
+        let mode = mode & Self::PERMISSION_BITS;
+        match self {
+            Self::ClientRequested => true,
+            Self::Fixed(allowed) => mode & !allowed == 0,
+            Self::Masked(umask) => mode & umask == 0,
+        }
+    }
+}
+
+/// Handling of a WRITE whose offset lies beyond the current end of the file
+///
+/// A client resuming an upload should continue at the size reported by
+/// `stat-for-resume@snow-owl.dev`; a larger offset means it lost track of
+/// what the server already holds.
+///
+/// NIST 800-53: SI-10 (Information Input Validation)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum WritePastEof {
+    /// The gap is filled with zeros, as POSIX `pwrite` does
+    #[default]
+    ZeroFill,
+    /// The WRITE fails and the file is left unchanged
+    Reject,
+}
+
+/// Handling of an SFTP response the SSH channel would not accept
+///
+/// Part of the response may already be on the wire, so the channel is
+/// closed either way rather than framing further messages after a torn one.
+///
+/// NIST 800-53: SC-8 (Transmission Integrity), SI-11 (Error Handling)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelWriteFailure {
+    /// Close the SFTP channel and keep the SSH connection
+    #[default]
+    CloseChannel,
+    /// Close the channel and end the SSH connection
+    Disconnect,
+}
+
 /// Per-user configuration
 ///
 /// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
 /// STIG: V-222567 (User Access Control)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct UserConfig {
     /// User's home directory (chroot jail)
@@ -155,6 +593,9 @@ pub struct UserConfig {
     /// Maximum number of concurrent connections for this user
     pub max_connections: Option<usize>,
 
+    /// Account expiry; authentication is rejected from this instant on
+    pub expires_at: Option<DateTime<Utc>>,
+
     /// Time-based access restrictions
     pub access_schedule: Option<AccessSchedule>,
 
@@ -166,6 +607,9 @@ pub struct UserConfig {
 
     /// Denied operations - these operations are explicitly forbidden
     pub denied_operations: Vec<String>,
+
+    /// Name of an entry in `roles` whose policy applies on top of this one
+    pub role: Option<String>,
 }
 
 impl Default for UserConfig {
@@ -176,31 +620,95 @@ impl Default for UserConfig {
             disk_quota: 0,
             max_file_size: 0,
             max_connections: None,
+            expires_at: None,
             access_schedule: None,
             read_only: false,
             allowed_operations: None,
             denied_operations: Vec::new(),
+            role: None,
         }
     }
 }
 
+/// Operation policy shared by every user naming it in `role`
+///
+/// An operation must pass both the role and the user's own settings, so a
+/// user entry can narrow its role but never widen it.
+///
+/// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RoleConfig {
+    /// Only read, stat, lstat, opendir, readdir and readlink are permitted
+    pub read_only: bool,
+
+    /// Allowed operations - if specified, only these operations are permitted
+    pub allowed_operations: Option<Vec<String>>,
+
+    /// Denied operations - these operations are explicitly forbidden
+    pub denied_operations: Vec<String>,
+}
+
+impl RoleConfig {
+    /// Whether the role permits `operation`
+    pub fn permits(&self, operation: &str) -> bool {
+        operation_permitted(
+            self.read_only,
+            self.allowed_operations.as_deref(),
+            &self.denied_operations,
+            operation,
+        )
+    }
+}
+
+/// Operation check shared by users and roles
+///
+/// Denials win; an allow list, when set, replaces the read-only set.
+fn operation_permitted(
+    read_only: bool,
+    allowed: Option<&[String]>,
+    denied: &[String],
+    operation: &str,
+) -> bool {
+    if denied.iter().any(|denied| denied == operation) {
+        return false;
+    }
+    if let Some(allowed) = allowed {
+        return allowed.iter().any(|allowed| allowed == operation);
+    }
+    if read_only {
+        return ["read", "stat", "lstat", "opendir", "readdir", "readlink"].contains(&operation);
+    }
+    true
+}
+
 /// Access schedule configuration for time-based restrictions
 ///
 /// NIST 800-53: AC-2 (Account Management)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct AccessSchedule {
     /// Days of week when access is allowed (0 = Sunday, 6 = Saturday)
     /// Empty vec = all days allowed
+    #[schemars(inner(range(max = 6)))]
     pub allowed_days: Vec<u8>,
 
     /// Start hour (0-23) when access is allowed
+    #[schemars(range(max = 23))]
     pub start_hour: u8,
 
     /// End hour (0-23) when access is allowed
+    #[schemars(range(max = 23))]
     pub end_hour: u8,
 
     /// Timezone for schedule (e.g., "America/New_York", "UTC")
     pub timezone: String,
+
+    /// Explicit access windows; when set, these replace the day/hour fields above
+    pub windows: Vec<AccessWindow>,
+
+    /// Terminate sessions that are still open when their access window ends
+    pub terminate_sessions: bool,
 }
 
 impl Default for AccessSchedule {
@@ -210,8 +718,100 @@ impl Default for AccessSchedule {
             start_hour: 9,
             end_hour: 17,
             timezone: "UTC".to_string(),
+            windows: Vec::new(),
+            terminate_sessions: false,
+        }
+    }
+}
+
+/// A recurring access window in the schedule's timezone
+///
+/// A window whose `end` is not after its `start` runs past midnight into the
+/// following day (e.g. 22:00-02:00); `days` always names the starting day.
+///
+/// NIST 800-53: AC-2 (Account Management)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AccessWindow {
+    /// Days of week the window starts on (0 = Sunday, 6 = Saturday)
+    /// Empty vec = every day
+    #[serde(default)]
+    #[schemars(inner(range(max = 6)))]
+    pub days: Vec<u8>,
+
+    /// Local time the window opens (e.g. "00:00")
+    pub start: NaiveTime,
+
+    /// Local time the window closes (e.g. "06:00")
+    pub end: NaiveTime,
+}
+
+impl AccessSchedule {
+    /// Parsed schedule timezone, falling back to UTC if it is not recognised
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    /// Windows in effect, deriving one from the day/hour fields if none are listed
+    pub fn effective_windows(&self) -> Vec<AccessWindow> {
+        if !self.windows.is_empty() {
+            return self.windows.clone();
+        }
+
+        // Legacy fields never wrap past midnight: start >= end allows nothing
+        match (
+            NaiveTime::from_hms_opt(u32::from(self.start_hour), 0, 0),
+            NaiveTime::from_hms_opt(u32::from(self.end_hour), 0, 0),
+        ) {
+            (Some(start), Some(end)) if start < end => vec![AccessWindow {
+                days: self.allowed_days.clone(),
+                start,
+                end,
+            }],
+            _ => Vec::new(),
         }
     }
+
+    /// Whether access is allowed at `at`
+    pub fn is_open_at(&self, at: DateTime<Utc>) -> bool {
+        self.window_end_at(at).is_some()
+    }
+
+    /// End of the window that is open at `at`, or None outside every window
+    ///
+    /// When several windows are open, the latest end wins.
+    pub fn window_end_at(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let tz = self.tz();
+        let local = at.with_timezone(&tz).naive_local();
+        let today = local.date();
+        let time = local.time();
+
+        self.effective_windows()
+            .iter()
+            .filter_map(|window| {
+                let starts_on = |date: chrono::NaiveDate| {
+                    let day = u8::try_from(date.weekday().num_days_from_sunday()).unwrap_or(0);
+                    window.days.is_empty() || window.days.contains(&day)
+                };
+
+                let end_date = if window.start < window.end {
+                    (starts_on(today) && time >= window.start && time < window.end)
+                        .then_some(today)?
+                } else if starts_on(today) && time >= window.start {
+                    today.succ_opt()?
+                } else {
+                    let yesterday = today.pred_opt()?;
+                    (starts_on(yesterday) && time < window.end).then_some(today)?
+                };
+
+                // A DST gap at the end time falls back to an hour later
+                let end = end_date.and_time(window.end);
+                tz.from_local_datetime(&end)
+                    .earliest()
+                    .or_else(|| tz.from_local_datetime(&(end + Duration::hours(1))).earliest())
+                    .map(|end| end.with_timezone(&Utc))
+            })
+            .max()
+    }
 }
 
 impl Default for Config {
@@ -222,20 +822,45 @@ impl Default for Config {
             root_dir: default_root_dir(),
             host_key_path: default_host_key_path(),
             authorized_keys_path: default_authorized_keys_path(),
+            password_auth: false,
+            password_file: None,
             max_connections: default_max_connections(),
             timeout: default_timeout(),
             verbose: false,
             max_packet_size: default_max_packet_size(),
+            max_read_len: default_max_read_len(),
             window_size: default_window_size(),
+            read_ahead_max_bytes: default_read_ahead_max_bytes(),
+            readdir_batch_size: default_readdir_batch_size(),
+            max_open_handles: default_max_open_handles(),
+            max_path_length: default_max_path_length(),
+            max_filename_length: default_max_filename_length(),
             max_auth_attempts: default_max_auth_attempts(),
             rate_limit_window_secs: default_rate_limit_window(),
             lockout_duration_secs: default_lockout_duration(),
             max_connections_per_user: default_max_connections_per_user(),
+            session_idle_timeout_secs: 0,
+            max_session_lifetime_secs: 0,
+            account_expiry_warning_days: default_account_expiry_warning_days(),
             logging: LoggingConfig::default(),
             users: HashMap::new(),
+            user_roots: HashMap::new(),
+            user_root_template: None,
+            create_user_root: false,
+            roles: HashMap::new(),
+            authorization: AuthorizationConfig::default(),
+            read_only: false,
+            path_rules: Vec::new(),
+            symlinks: SymlinkPolicy::default(),
+            file_mode_policy: FileModePolicy::default(),
+            write_past_eof: WritePastEof::default(),
+            channel_write_failure: ChannelWriteFailure::default(),
             global_bandwidth_limit: 0,
+            max_bytes_per_sec_per_session: None,
+            max_bytes_per_sec_global: None,
             ip_whitelist: Vec::new(),
             ip_blacklist: Vec::new(),
+            control_socket: None,
             config_file_path: None,
         }
     }
@@ -299,6 +924,95 @@ impl Config {
             ));
         }
 
+        if self.password_auth
+            && let Some(path) = &self.password_file
+            && !path.is_file()
+        {
+            return Err(crate::Error::Config(format!(
+                "password_file not found: {:?}",
+                path
+            )));
+        }
+
+        if self.max_read_len == 0 || self.max_read_len > self.max_packet_size {
+            return Err(crate::Error::Config(
+                "max_read_len must be between 1 and max_packet_size".to_string(),
+            ));
+        }
+
+        if self.host_key_path.paths().is_empty() {
+            return Err(crate::Error::Config(
+                "host_key_path must name at least one key file or directory".to_string(),
+            ));
+        }
+
+        if self.readdir_batch_size == 0 {
+            return Err(crate::Error::Config(
+                "readdir_batch_size must be at least 1".to_string(),
+            ));
+        }
+
+        if self.max_open_handles == 0 {
+            return Err(crate::Error::Config(
+                "max_open_handles must be at least 1".to_string(),
+            ));
+        }
+
+        if self.max_bytes_per_sec_per_session == Some(0) || self.max_bytes_per_sec_global == Some(0)
+        {
+            return Err(crate::Error::Config(
+                "max_bytes_per_sec_per_session and max_bytes_per_sec_global must be non-zero; \
+                 omit them for no limit"
+                    .to_string(),
+            ));
+        }
+
+        let audit_channel = &self.logging.audit_channel;
+        if audit_channel.capacity == 0
+            || audit_channel.batch_size == 0
+            || audit_channel.flush_interval_ms == 0
+        {
+            return Err(crate::Error::Config(
+                "logging.audit_channel capacity, batch_size and flush_interval_ms must be non-zero"
+                    .to_string(),
+            ));
+        }
+
+        if let FileModePolicy::Fixed(mode) | FileModePolicy::Masked(mode) = self.file_mode_policy
+            && mode & !FileModePolicy::PERMISSION_BITS != 0
+        {
+            return Err(crate::Error::Config(format!(
+                "file_mode_policy mode {mode:o} has bits outside 7777"
+            )));
+        }
+
+        for rule in &self.path_rules {
+            if let Err(e) = glob::Pattern::new(&rule.pattern) {
+                return Err(crate::Error::Config(format!(
+                    "Invalid path rule pattern '{}': {}",
+                    rule.pattern, e
+                )));
+            }
+        }
+
+        for (username, root) in &self.user_roots {
+            if !root.is_dir() {
+                return Err(crate::Error::Config(format!(
+                    "User '{}' root directory does not exist or is not a directory: {:?}",
+                    username, root
+                )));
+            }
+        }
+
+        if let Some(template) = &self.user_root_template
+            && !template.contains(USER_PLACEHOLDER)
+        {
+            return Err(crate::Error::Config(format!(
+                "user_root_template '{}' does not contain {}",
+                template, USER_PLACEHOLDER
+            )));
+        }
+
         // Validate per-user configurations
         for (username, user_config) in &self.users {
             if let Some(ref home_dir) = user_config.home_dir {
@@ -316,6 +1030,15 @@ impl Config {
                 }
             }
 
+            if let Some(ref role) = user_config.role
+                && !self.roles.contains_key(role)
+            {
+                return Err(crate::Error::Config(format!(
+                    "User '{}' has unknown role: {}",
+                    username, role
+                )));
+            }
+
             if let Some(ref schedule) = user_config.access_schedule {
                 if schedule.start_hour > 23 || schedule.end_hour > 23 {
                     return Err(crate::Error::Config(format!(
@@ -323,7 +1046,8 @@ impl Config {
                         username
                     )));
                 }
-                for &day in &schedule.allowed_days {
+                let window_days = schedule.windows.iter().flat_map(|w| &w.days);
+                for &day in schedule.allowed_days.iter().chain(window_days) {
                     if day > 6 {
                         return Err(crate::Error::Config(format!(
                             "User '{}' has invalid day in access schedule (must be 0-6)",
@@ -331,12 +1055,57 @@ impl Config {
                         )));
                     }
                 }
+                if schedule.timezone.parse::<Tz>().is_err() {
+                    return Err(crate::Error::Config(format!(
+                        "User '{}' has unknown access schedule timezone: {}",
+                        username, schedule.timezone
+                    )));
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Server-wide bandwidth cap, if any
+    ///
+    /// `max_bytes_per_sec_global` takes precedence over the older
+    /// `global_bandwidth_limit`.
+    pub fn global_bytes_per_sec(&self) -> Option<u64> {
+        self.max_bytes_per_sec_global
+            .or((self.global_bandwidth_limit > 0).then_some(self.global_bandwidth_limit))
+    }
+
+    /// Directory a session's paths are resolved beneath
+    ///
+    /// A `user_roots` entry wins, then the user's `home_dir`, then
+    /// `user_root_template` filled in with the username; anonymous sessions
+    /// and users with none of these get `root_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template would be filled in with a username
+    /// that is not a plain file name (NIST 800-53: SI-10).
+    pub fn root_for(&self, username: Option<&str>) -> crate::Result<PathBuf> {
+        let Some(username) = username else {
+            return Ok(self.root_dir.clone());
+        };
+        if let Some(root) = self
+            .user_roots
+            .get(username)
+            .or_else(|| self.users.get(username).and_then(|user| user.home_dir.as_ref()))
+        {
+            return Ok(root.clone());
+        }
+        match &self.user_root_template {
+            Some(template) => {
+                validate_path_username(username)?;
+                Ok(PathBuf::from(template.replace(USER_PLACEHOLDER, username)))
+            }
+            None => Ok(self.root_dir.clone()),
+        }
+    }
+
     /// Get user-specific configuration
     pub fn get_user_config(&self, username: &str) -> Option<&UserConfig> {
         self.users.get(username)
@@ -365,53 +1134,91 @@ impl Config {
     ///
     /// NIST 800-53: AC-2 (Account Management)
     pub fn is_access_time_allowed(&self, username: &str) -> bool {
-        if let Some(user_config) = self.get_user_config(username) {
-            if let Some(ref schedule) = user_config.access_schedule {
-                use chrono::{Datelike, Timelike, Utc};
-
-                let now = Utc::now();
-                let day_of_week = now.weekday().num_days_from_sunday() as u8;
-                let hour = now.hour() as u8;
-
-                // Check day of week
-                if !schedule.allowed_days.is_empty() && !schedule.allowed_days.contains(&day_of_week) {
-                    return false;
-                }
-
-                // Check hour range
-                if hour < schedule.start_hour || hour >= schedule.end_hour {
-                    return false;
-                }
-            }
-        }
-
-        true
+        self.get_user_config(username)
+            .and_then(|user_config| user_config.access_schedule.as_ref())
+            .is_none_or(|schedule| schedule.is_open_at(Utc::now()))
     }
 
     /// Check if a user can perform a specific operation
     ///
     /// NIST 800-53: AC-3 (Access Enforcement)
     pub fn is_operation_allowed(&self, username: &str, operation: &str) -> bool {
-        if let Some(user_config) = self.get_user_config(username) {
-            // Check denied operations first
-            if user_config.denied_operations.contains(&operation.to_string()) {
-                return false;
-            }
+        let Some(user_config) = self.get_user_config(username) else {
+            return true;
+        };
+
+        operation_permitted(
+            user_config.read_only,
+            user_config.allowed_operations.as_deref(),
+            &user_config.denied_operations,
+            operation,
+        ) && self
+            .role_of(user_config)
+            .is_none_or(|role| role.permits(operation))
+    }
 
-            // If allowed_operations is set, operation must be in the list
-            if let Some(ref allowed) = user_config.allowed_operations {
-                return allowed.contains(&operation.to_string());
-            }
+    /// Role assigned to `user_config`, if any
+    pub fn role_of(&self, user_config: &UserConfig) -> Option<&RoleConfig> {
+        user_config
+            .role
+            .as_deref()
+            .and_then(|role| self.roles.get(role))
+    }
 
-            // Check read-only mode
-            if user_config.read_only {
-                let read_only_ops = vec!["read", "stat", "lstat", "opendir", "readdir", "readlink"];
-                return read_only_ops.contains(&operation);
-            }
+    /// Rule governing `relative_path`, if any
+    ///
+    /// The most specific matching rule wins; on a tie the first listed does.
+    pub fn matching_path_rule(&self, relative_path: &str) -> Option<&PathRule> {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        let relative_path = relative_path.trim_start_matches('/');
+
+        self.path_rules
+            .iter()
+            .filter(|rule| {
+                glob::Pattern::new(&rule.pattern)
+                    .is_ok_and(|pattern| pattern.matches_with(relative_path, options))
+            })
+            .rev()
+            .max_by_key(|rule| rule.specificity())
+    }
+
+    /// Check read-only mode and path rules for `access` to a path under `root_dir`
+    ///
+    /// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+    pub fn is_path_access_allowed(&self, relative_path: &Path, access: PathAccess) -> bool {
+        if self.read_only && access != PathAccess::Read {
+            return false;
         }
 
-        true
+        self.matching_path_rule(&relative_path.to_string_lossy())
+            .is_none_or(|rule| rule.allows(access))
+    }
+}
+
+/// Stands for the username in `user_root_template`
+const USER_PLACEHOLDER: &str = "{user}";
+
+/// Check that `username` can be placed in a path as a single file name
+///
+/// NIST 800-53: SI-10 (Information Input Validation), AC-3 (no escape from the root)
+fn validate_path_username(username: &str) -> crate::Result<()> {
+    if username.is_empty()
+        || username.contains("..")
+        || username.contains(['/', '\\', '\0'])
+    {
+        return Err(crate::Error::InvalidPath(format!(
+            "Username '{}' cannot name a root directory",
+            username.escape_default()
+        )));
     }
+    Ok(())
+}
+
+const fn default_true() -> bool {
+    true
 }
 
 fn default_bind_address() -> String {
@@ -426,8 +1233,8 @@ fn default_root_dir() -> PathBuf {
     PathBuf::from("/tmp/sftp")
 }
 
-fn default_host_key_path() -> PathBuf {
-    PathBuf::from("/etc/ssh/ssh_host_rsa_key")
+fn default_host_key_path() -> HostKeyPaths {
+    HostKeyPaths::One(PathBuf::from("/etc/ssh/ssh_host_ed25519_key"))
 }
 
 fn default_authorized_keys_path() -> PathBuf {
@@ -443,13 +1250,43 @@ fn default_timeout() -> u64 {
 }
 
 fn default_max_packet_size() -> u32 {
-    32768 // RFC 4254 minimum
+    1048576 // 1MB
+}
+
+fn default_max_read_len() -> u32 {
+    crate::server::MAX_READ_LENGTH
 }
 
 fn default_window_size() -> u32 {
     2097152 // 2MB
 }
 
+fn default_read_ahead_max_bytes() -> usize {
+    262144 // 256KB
+}
+
+// NIST 800-53: SC-5 (Denial of Service Protection)
+// Default: 100 entries per SSH_FXP_NAME reply
+fn default_readdir_batch_size() -> usize {
+    100
+}
+
+fn default_max_open_handles() -> usize {
+    crate::server::MAX_OPEN_HANDLES
+}
+
+// NIST 800-53: SI-10 (Information Input Validation)
+// Default: Linux PATH_MAX
+fn default_max_path_length() -> usize {
+    4096
+}
+
+// NIST 800-53: SI-10 (Information Input Validation)
+// Default: Linux NAME_MAX
+fn default_max_filename_length() -> usize {
+    255
+}
+
 // NIST 800-53: AC-7 (Unsuccessful Logon Attempts)
 // Default: 5 attempts before lockout
 fn default_max_auth_attempts() -> u32 {
@@ -473,3 +1310,9 @@ fn default_lockout_duration() -> u64 {
 fn default_max_connections_per_user() -> usize {
     10
 }
+
+// NIST 800-53: AC-2 (Account Management)
+// Default: start warning two weeks before an account expires
+fn default_account_expiry_warning_days() -> u32 {
+    14
+}
@@ -2,28 +2,86 @@
 //!
 //! NIST 800-53: AC-12 (Session Termination), AC-10 (Concurrent Session Control)
 //! STIG: V-222601 - The application must terminate sessions after organization-defined conditions
-//! Implementation: Tracks and limits concurrent connections per user
+//! Implementation: Tracks and limits concurrent connections per user, and reaps
+//! sessions that sit idle or outlive their maximum lifetime
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
+/// Upper bound on how often the reaper scans for expired sessions
+const MAX_REAP_INTERVAL: Duration = Duration::from_secs(30);
+
 /// Configuration for connection tracking
 #[derive(Debug, Clone)]
 pub struct ConnectionTrackerConfig {
     /// Maximum concurrent connections per user
     pub max_connections_per_user: usize,
+    /// Reap sessions with no activity for this many seconds (0 = never)
+    pub idle_timeout_secs: u64,
+    /// Reap sessions older than this many seconds regardless of activity (0 = never)
+    pub max_session_lifetime_secs: u64,
 }
 
 impl Default for ConnectionTrackerConfig {
     fn default() -> Self {
         Self {
             max_connections_per_user: 10,
+            idle_timeout_secs: 0,
+            max_session_lifetime_secs: 0,
         }
     }
 }
 
+/// Why a session was reaped
+///
+/// NIST 800-53: AC-12 (Session Termination)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReapReason {
+    /// No activity within `idle_timeout_secs`
+    Idle,
+    /// Open longer than `max_session_lifetime_secs`
+    LifetimeExceeded,
+}
+
+/// A registered session and the handle used to shut it down
+///
+/// The session handler should disconnect the client once `shutdown` is cancelled.
+#[derive(Debug, Clone)]
+pub struct SessionRegistration {
+    /// Connection ID used for activity updates and unregistering
+    pub connection_id: usize,
+    /// Cancelled when the tracker reaps the session
+    pub shutdown: CancellationToken,
+}
+
+/// Snapshot of one active session for metrics and status endpoints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSession {
+    /// Authenticated username
+    pub username: String,
+    /// Connection ID
+    pub connection_id: usize,
+    /// Time since the session was registered
+    pub connected_for: Duration,
+    /// Time since the last recorded activity
+    pub idle_for: Duration,
+}
+
+/// Per-connection tracking state
+#[derive(Debug)]
+struct TrackedSession {
+    id: usize,
+    started_at: Instant,
+    last_activity: Instant,
+    shutdown: CancellationToken,
+}
+
 /// Tracks active connections per user
 ///
 /// NIST 800-53: AC-10 (Concurrent Session Control), AC-12 (Session Termination)
@@ -31,8 +89,8 @@ impl Default for ConnectionTrackerConfig {
 /// Implementation: Enforces maximum concurrent connections per user
 pub struct ConnectionTracker {
     config: ConnectionTrackerConfig,
-    /// Maps username to list of connection IDs
-    connections: Arc<Mutex<HashMap<String, Vec<usize>>>>,
+    /// Maps username to its active sessions
+    connections: Arc<Mutex<HashMap<String, Vec<TrackedSession>>>>,
     next_connection_id: Arc<Mutex<usize>>,
 }
 
@@ -103,6 +161,25 @@ impl ConnectionTracker {
     /// # STIG: V-222601
     /// # Implementation: Tracks new connection and enforces limit
     pub async fn register_connection(&self, username: String) -> Option<usize> {
+        self.register_session(username)
+            .await
+            .map(|registration| registration.connection_id)
+    }
+
+    /// Register a new connection and get a token that is cancelled if it is reaped
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - Username of the connecting user
+    ///
+    /// # Returns
+    ///
+    /// Registration if successful, `None` if limit exceeded
+    ///
+    /// # NIST 800-53: AC-10 (Concurrent Session Control), AC-12 (Session Termination)
+    /// # STIG: V-222601
+    /// # Implementation: Tracks new connection with activity timestamps for reaping
+    pub async fn register_session(&self, username: String) -> Option<SessionRegistration> {
         let mut connections = self.connections.lock().await;
 
         // Check limit before registering
@@ -126,10 +203,17 @@ impl ConnectionTracker {
         drop(next_id);
 
         // Register connection
+        let now = Instant::now();
+        let shutdown = CancellationToken::new();
         connections
             .entry(username.clone())
             .or_insert_with(Vec::new)
-            .push(connection_id);
+            .push(TrackedSession {
+                id: connection_id,
+                started_at: now,
+                last_activity: now,
+                shutdown: shutdown.clone(),
+            });
 
         info!(
             "Registered connection {} for user '{}' ({}/{})",
@@ -139,7 +223,52 @@ impl ConnectionTracker {
             self.config.max_connections_per_user
         );
 
-        Some(connection_id)
+        Some(SessionRegistration {
+            connection_id,
+            shutdown,
+        })
+    }
+
+    /// Record activity on a connection, resetting its idle timer
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - Username owning the connection
+    /// * `connection_id` - Connection ID to update
+    ///
+    /// # NIST 800-53: AC-12 (Session Termination)
+    /// # Implementation: Called by the session handler for every SFTP packet
+    pub async fn record_activity(&self, username: &str, connection_id: usize) {
+        let mut connections = self.connections.lock().await;
+
+        if let Some(session) = connections
+            .get_mut(username)
+            .and_then(|sessions| sessions.iter_mut().find(|s| s.id == connection_id))
+        {
+            session.last_activity = Instant::now();
+        }
+    }
+
+    /// Get when a connection last recorded activity
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - Username owning the connection
+    /// * `connection_id` - Connection ID to look up
+    ///
+    /// # Returns
+    ///
+    /// The activity timestamp, or `None` if the connection is not registered
+    ///
+    /// # NIST 800-53: AC-12 (Session Termination)
+    /// # Implementation: Lets the session handler act on its idle deadline itself
+    pub async fn last_activity(&self, username: &str, connection_id: usize) -> Option<Instant> {
+        let connections = self.connections.lock().await;
+
+        connections
+            .get(username)
+            .and_then(|sessions| sessions.iter().find(|s| s.id == connection_id))
+            .map(|session| session.last_activity)
     }
 
     /// Unregister a connection
@@ -155,7 +284,7 @@ impl ConnectionTracker {
         let mut connections = self.connections.lock().await;
 
         if let Some(user_conns) = connections.get_mut(username) {
-            user_conns.retain(|&id| id != connection_id);
+            user_conns.retain(|session| session.id != connection_id);
 
             let remaining = user_conns.len();
 
@@ -201,6 +330,112 @@ impl ConnectionTracker {
 
         (total_users, total_connections)
     }
+
+    /// List active sessions, ordered by username then connection ID
+    ///
+    /// # Returns
+    ///
+    /// One entry per registered connection
+    pub async fn active_sessions(&self) -> Vec<ActiveSession> {
+        let connections = self.connections.lock().await;
+        let now = Instant::now();
+
+        let mut sessions: Vec<ActiveSession> = connections
+            .iter()
+            .flat_map(|(username, sessions)| {
+                sessions.iter().map(move |session| ActiveSession {
+                    username: username.clone(),
+                    connection_id: session.id,
+                    connected_for: now.duration_since(session.started_at),
+                    idle_for: now.duration_since(session.last_activity),
+                })
+            })
+            .collect();
+        drop(connections);
+
+        sessions.sort_by(|a, b| {
+            (a.username.as_str(), a.connection_id).cmp(&(b.username.as_str(), b.connection_id))
+        });
+        sessions
+    }
+
+    /// Unregister every session past the idle timeout or maximum lifetime
+    ///
+    /// Each reaped session's shutdown token is cancelled so its handler can
+    /// disconnect the client.
+    ///
+    /// # Returns
+    ///
+    /// Username, connection ID and reason for each reaped session
+    ///
+    /// # NIST 800-53: AC-12 (Session Termination), AC-10 (Concurrent Session Control)
+    /// # STIG: V-222601
+    /// # Implementation: Frees slots held by sessions that went quiet without disconnecting
+    pub async fn reap_expired(&self) -> Vec<(String, usize, ReapReason)> {
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_secs);
+        let max_lifetime = Duration::from_secs(self.config.max_session_lifetime_secs);
+        let now = Instant::now();
+
+        let mut reaped = Vec::new();
+        let mut connections = self.connections.lock().await;
+
+        for (username, sessions) in connections.iter_mut() {
+            sessions.retain(|session| {
+                let reason = if !max_lifetime.is_zero()
+                    && now.duration_since(session.started_at) >= max_lifetime
+                {
+                    ReapReason::LifetimeExceeded
+                } else if !idle_timeout.is_zero()
+                    && now.duration_since(session.last_activity) >= idle_timeout
+                {
+                    ReapReason::Idle
+                } else {
+                    return true;
+                };
+
+                warn!(
+                    "Reaping connection {} for user '{}': {:?}",
+                    session.id, username, reason
+                );
+                session.shutdown.cancel();
+                reaped.push((username.clone(), session.id, reason));
+                false
+            });
+        }
+        connections.retain(|_, sessions| !sessions.is_empty());
+
+        reaped
+    }
+
+    /// Start the background task that periodically reaps expired sessions
+    ///
+    /// # Returns
+    ///
+    /// The task handle, or `None` when neither limit is configured
+    ///
+    /// # NIST 800-53: AC-12 (Session Termination)
+    pub fn spawn_reaper(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        let interval = [
+            self.config.idle_timeout_secs,
+            self.config.max_session_lifetime_secs,
+        ]
+        .into_iter()
+        .filter(|&secs| secs > 0)
+        .min()
+        .map(|secs| Duration::from_secs(secs).min(MAX_REAP_INTERVAL))?;
+
+        let tracker = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let reaped = tracker.reap_expired().await;
+                if !reaped.is_empty() {
+                    info!("Reaped {} expired session(s)", reaped.len());
+                }
+            }
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -211,6 +446,7 @@ mod tests {
     async fn test_connection_limit_enforcement() {
         let config = ConnectionTrackerConfig {
             max_connections_per_user: 2,
+            ..Default::default()
         };
 
         let tracker = ConnectionTracker::new(config);
@@ -243,6 +479,7 @@ mod tests {
     async fn test_multiple_users() {
         let config = ConnectionTrackerConfig {
             max_connections_per_user: 2,
+            ..Default::default()
         };
 
         let tracker = ConnectionTracker::new(config);
@@ -272,6 +509,7 @@ mod tests {
     async fn test_cleanup_on_disconnect() {
         let config = ConnectionTrackerConfig {
             max_connections_per_user: 3,
+            ..Default::default()
         };
 
         let tracker = ConnectionTracker::new(config);
@@ -292,4 +530,75 @@ mod tests {
         let (users, _) = tracker.get_stats().await;
         assert_eq!(users, 0);
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_session_is_reaped_and_slot_freed() {
+        let config = ConnectionTrackerConfig {
+            max_connections_per_user: 1,
+            idle_timeout_secs: 60,
+            ..Default::default()
+        };
+
+        let tracker = Arc::new(ConnectionTracker::new(config));
+        let reaper = tracker.spawn_reaper().unwrap();
+
+        let session = tracker.register_session("alice".to_string()).await.unwrap();
+        assert!(!tracker.can_connect("alice").await);
+
+        // Activity keeps the session alive past the idle timeout
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        tracker.record_activity("alice", session.connection_id).await;
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert_eq!(tracker.get_connection_count("alice").await, 1);
+        assert!(!session.shutdown.is_cancelled());
+
+        // Then it goes quiet
+        tokio::time::sleep(Duration::from_secs(90)).await;
+        assert!(session.shutdown.is_cancelled());
+        assert_eq!(tracker.get_connection_count("alice").await, 0);
+        assert!(tracker.can_connect("alice").await);
+
+        reaper.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_session_lifetime_limit() {
+        let config = ConnectionTrackerConfig {
+            max_connections_per_user: 2,
+            max_session_lifetime_secs: 3600,
+            ..Default::default()
+        };
+
+        let tracker = ConnectionTracker::new(config);
+        let old = tracker.register_session("bob".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1800)).await;
+        let young = tracker.register_session("bob".to_string()).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1800)).await;
+        tracker.record_activity("bob", old.connection_id).await;
+
+        let reaped = tracker.reap_expired().await;
+
+        assert_eq!(
+            reaped,
+            vec![(
+                "bob".to_string(),
+                old.connection_id,
+                ReapReason::LifetimeExceeded
+            )]
+        );
+        assert!(old.shutdown.is_cancelled());
+        assert!(!young.shutdown.is_cancelled());
+
+        let sessions = tracker.active_sessions().await;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].connection_id, young.connection_id);
+        assert_eq!(sessions[0].connected_for, Duration::from_secs(1800));
+    }
+
+    #[tokio::test]
+    async fn test_no_reaper_without_limits() {
+        let tracker = Arc::new(ConnectionTracker::new(ConnectionTrackerConfig::default()));
+
+        assert!(tracker.spawn_reaper().is_none());
+    }
 }
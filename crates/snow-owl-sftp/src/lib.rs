@@ -18,28 +18,56 @@
 //! - Directory operations (list, create, remove)
 //! - File attribute management
 
+pub mod account_policy;
 pub mod audit;
+pub mod audit_channel;
 pub mod auth;
+pub mod authorization;
 pub mod cnsa;
 pub mod config;
 pub mod connection_tracker;
 pub mod error;
 pub mod metrics;
+pub mod mirror;
+pub mod password;
 pub mod protocol;
 pub mod rate_limit;
+pub mod read_ahead;
 pub mod server;
 pub mod client;
 pub mod user_mapping;
 pub mod transfer_resume;
+pub mod symlink;
+pub mod throttle;
 
+/// Fuzzing entry points (enable the `fuzzing` feature)
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
+
+pub use account_policy::{AccessDecision, AccountPolicy, Clock, SystemClock};
 pub use audit::{AuditEvent, AuditLogger, SessionInfo};
+pub use audit_channel::{AuditChannel, AuditSink, JsonFileSink, SharedAuditSink, TracingSink};
 pub use auth::AuthorizedKeys;
-pub use config::{AccessSchedule, Config, LogFormat, LoggingConfig, UserConfig};
-pub use connection_tracker::{ConnectionTracker, ConnectionTrackerConfig};
+pub use authorization::{
+    AuthorizationGate, Authorizer, Decision, Operation, OperationContext, StaticAuthorizer,
+};
+pub use config::{
+    AccessSchedule, AccessWindow, AuditChannelConfig, AuditOverflow, AuthorizationConfig,
+    ChannelWriteFailure, Config, FailPolicy, FileModePolicy, HostKeyPaths, LogFormat,
+    LoggingConfig, PathAccess, PathRule, RoleConfig, SymlinkPolicy, UserConfig, WritePastEof,
+};
+pub use connection_tracker::{
+    ActiveSession, ConnectionTracker, ConnectionTrackerConfig, ReapReason, SessionRegistration,
+};
 pub use error::{Error, Result};
 pub use metrics::{Metrics, MetricsSnapshot};
+pub use mirror::{SymlinkHandling, SyncFailure, SyncOptions, SyncReport};
+pub use password::{PasswordFile, PasswordVerifier, RejectAllPasswords};
 pub use rate_limit::{RateLimitConfig, RateLimiter};
+pub use read_ahead::ReadAhead;
 pub use server::Server;
 pub use client::Client;
 pub use user_mapping::{UserMapping, UserMappingRegistry};
 pub use transfer_resume::{TransferResumeManager, TransferState, TransferDirection};
+pub use symlink::{resolve_beneath, SymlinkViolation};
+pub use throttle::{SharedBucket, Throttle, TokenBucket};
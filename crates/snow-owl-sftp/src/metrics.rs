@@ -4,11 +4,24 @@
 //! STIG: V-222566 (Monitoring), V-222648 (Audit Records)
 //! Implementation: Comprehensive metrics tracking for server operations
 
+use crate::protocol::MessageType;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// Seconds of transfer history behind `current_bytes_per_sec`
+const THROUGHPUT_WINDOW_SECS: usize = 5;
+
+/// Upper bounds of the request latency histogram buckets, in microseconds
+///
+/// A request slower than the last bound lands in a final overflow bucket.
+pub const LATENCY_BUCKETS_MICROS: [u64; 12] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 1_000_000,
+];
 
 /// Server-wide metrics collection
 ///
@@ -53,9 +66,20 @@ struct MetricsInner {
     symlink_operations: AtomicU64,
     readlink_operations: AtomicU64,
 
+    /// Requests received, indexed by SFTP message type byte
+    requests_by_type: [AtomicU64; 256],
+    /// Requests answered with an error, indexed by SFTP message type byte
+    request_errors_by_type: [AtomicU64; 256],
+    /// Time taken to answer requests, indexed by SFTP message type byte
+    request_latency_by_type: [LatencyCounters; 256],
+
     // Data transfer metrics
     bytes_read: AtomicU64,
     bytes_written: AtomicU64,
+    /// Bytes moved in each of the last few seconds, indexed by second
+    throughput_bytes: [AtomicU64; THROUGHPUT_WINDOW_SECS],
+    /// Second (since `started`) each throughput slot currently counts
+    throughput_seconds: [AtomicU64; THROUGHPUT_WINDOW_SECS],
 
     // Error metrics
     protocol_errors: AtomicU64,
@@ -67,8 +91,59 @@ struct MetricsInner {
     // Performance metrics
     total_operations: AtomicU64,
 
+    // Resource gauges, which return to zero on an idle server
+    live_sessions: AtomicUsize,
+    open_file_handles: AtomicUsize,
+    open_dir_handles: AtomicUsize,
+    read_ahead_bytes: AtomicUsize,
+    queued_audit_events: AtomicUsize,
+    rate_limiter_entries: AtomicUsize,
+    tracked_connections: AtomicUsize,
+
     // Server start time
     start_time: DateTime<Utc>,
+    started: Instant,
+    /// Milliseconds after `started` at which the counters were last reset
+    window_started_ms: AtomicU64,
+}
+
+/// Lock-free latency histogram for one request type
+#[derive(Debug)]
+struct LatencyCounters {
+    /// Requests per bucket of [`LATENCY_BUCKETS_MICROS`], then the overflow
+    buckets: [AtomicU64; LATENCY_BUCKETS_MICROS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl LatencyCounters {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let bucket = LATENCY_BUCKETS_MICROS.partition_point(|&bound| bound < micros);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+}
+
+/// Request latency distribution for one SFTP message type
+///
+/// NIST 800-53: SI-4 (System Monitoring)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Requests per bucket: `buckets[i]` counts requests that took at most
+    /// `LATENCY_BUCKETS_MICROS[i]` and more than the previous bound; the
+    /// last entry counts requests slower than every bound
+    pub buckets: Vec<u64>,
+    /// Requests timed
+    pub count: u64,
+    /// Total time taken by those requests, in microseconds
+    pub sum_micros: u64,
 }
 
 /// Snapshot of current metrics
@@ -132,6 +207,17 @@ pub struct MetricsSnapshot {
     pub symlink_operations: u64,
     /// Symlink read operations
     pub readlink_operations: u64,
+    /// Requests received by SFTP message type (`open`, `read`, `extended`,
+    /// ...), whatever their outcome; types never received are omitted
+    #[serde(default)]
+    pub requests_by_type: BTreeMap<String, u64>,
+    /// Requests answered with an error status by SFTP message type; EOF
+    /// from READ and READDIR is not an error
+    #[serde(default)]
+    pub request_errors_by_type: BTreeMap<String, u64>,
+    /// Time taken to answer requests by SFTP message type
+    #[serde(default)]
+    pub request_latency_by_type: BTreeMap<String, LatencyHistogram>,
 
     /// Total bytes read from files
     pub bytes_read: u64,
@@ -139,6 +225,9 @@ pub struct MetricsSnapshot {
     pub bytes_written: u64,
     /// Total bytes transferred
     pub total_bytes: u64,
+    /// Read and write throughput over the last few complete seconds
+    #[serde(default)]
+    pub current_bytes_per_sec: f64,
 
     /// Protocol parsing errors
     pub protocol_errors: u64,
@@ -157,6 +246,28 @@ pub struct MetricsSnapshot {
     pub total_operations: u64,
     /// Operations per second rate
     pub operations_per_second: f64,
+
+    /// SFTP sessions currently holding state
+    #[serde(default)]
+    pub live_sessions: usize,
+    /// Open file handles across all sessions
+    #[serde(default)]
+    pub open_file_handles: usize,
+    /// Open directory handles across all sessions
+    #[serde(default)]
+    pub open_dir_handles: usize,
+    /// Bytes held in read-ahead buffers across all sessions
+    #[serde(default)]
+    pub read_ahead_bytes: usize,
+    /// Audit events waiting for the audit sink
+    #[serde(default)]
+    pub queued_audit_events: usize,
+    /// Addresses tracked by the authentication rate limiter
+    #[serde(default)]
+    pub rate_limiter_entries: usize,
+    /// Sessions registered with the connection tracker
+    #[serde(default)]
+    pub tracked_connections: usize,
 }
 
 /// Operation timing tracker
@@ -198,15 +309,29 @@ impl Metrics {
                 setstat_operations: AtomicU64::new(0),
                 symlink_operations: AtomicU64::new(0),
                 readlink_operations: AtomicU64::new(0),
+                requests_by_type: std::array::from_fn(|_| AtomicU64::new(0)),
+                request_errors_by_type: std::array::from_fn(|_| AtomicU64::new(0)),
+                request_latency_by_type: std::array::from_fn(|_| LatencyCounters::new()),
                 bytes_read: AtomicU64::new(0),
                 bytes_written: AtomicU64::new(0),
+                throughput_bytes: std::array::from_fn(|_| AtomicU64::new(0)),
+                throughput_seconds: std::array::from_fn(|_| AtomicU64::new(0)),
                 protocol_errors: AtomicU64::new(0),
                 permission_denied: AtomicU64::new(0),
                 file_not_found: AtomicU64::new(0),
                 io_errors: AtomicU64::new(0),
                 timeout_errors: AtomicU64::new(0),
                 total_operations: AtomicU64::new(0),
+                live_sessions: AtomicUsize::new(0),
+                open_file_handles: AtomicUsize::new(0),
+                open_dir_handles: AtomicUsize::new(0),
+                read_ahead_bytes: AtomicUsize::new(0),
+                queued_audit_events: AtomicUsize::new(0),
+                rate_limiter_entries: AtomicUsize::new(0),
+                tracked_connections: AtomicUsize::new(0),
                 start_time: Utc::now(),
+                started: Instant::now(),
+                window_started_ms: AtomicU64::new(0),
             }),
         }
     }
@@ -268,6 +393,7 @@ impl Metrics {
     pub fn record_file_read(&self, bytes: u64) {
         self.inner.file_reads.fetch_add(1, Ordering::Relaxed);
         self.inner.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+        self.record_throughput(bytes, self.inner.started.elapsed().as_secs());
         self.inner.total_operations.fetch_add(1, Ordering::Relaxed);
     }
 
@@ -275,6 +401,7 @@ impl Metrics {
     pub fn record_file_write(&self, bytes: u64) {
         self.inner.file_writes.fetch_add(1, Ordering::Relaxed);
         self.inner.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+        self.record_throughput(bytes, self.inner.started.elapsed().as_secs());
         self.inner.total_operations.fetch_add(1, Ordering::Relaxed);
     }
 
@@ -348,6 +475,72 @@ impl Metrics {
         self.inner.total_operations.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Record a request of `message_type` being received
+    ///
+    /// Also counts the matching file, directory or attribute operation.
+    /// READ and WRITE are counted with their byte counts by
+    /// [`record_file_read`](Self::record_file_read) and
+    /// [`record_file_write`](Self::record_file_write) once data has moved.
+    pub fn record_request(&self, message_type: MessageType) {
+        self.inner.requests_by_type[message_type as usize].fetch_add(1, Ordering::Relaxed);
+        match message_type {
+            MessageType::Open => self.record_file_open(),
+            MessageType::Close => self.record_file_close(),
+            MessageType::Remove => self.record_file_remove(),
+            MessageType::Rename => self.record_file_rename(),
+            MessageType::Opendir => self.record_dir_open(),
+            MessageType::Readdir => self.record_dir_read(),
+            MessageType::Mkdir => self.record_dir_create(),
+            MessageType::Rmdir => self.record_dir_remove(),
+            MessageType::Stat | MessageType::Lstat | MessageType::Fstat => self.record_stat(),
+            MessageType::Setstat | MessageType::Fsetstat => self.record_setstat(),
+            MessageType::Symlink => self.record_symlink(),
+            MessageType::Readlink => self.record_readlink(),
+            _ => {}
+        }
+    }
+
+    /// Record a request of `message_type` being answered after `elapsed`
+    ///
+    /// `failed` marks requests answered with an error status. Recording
+    /// touches only atomics, so it is cheap enough for every packet.
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    pub fn record_response(&self, message_type: MessageType, elapsed: Duration, failed: bool) {
+        let index = message_type as usize;
+        if failed {
+            self.inner.request_errors_by_type[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.request_latency_by_type[index].record(elapsed);
+    }
+
+    // Throughput
+
+    /// Count `bytes` against the one-second slot for `second`
+    ///
+    /// Lock-free; a slot being recycled by two threads at once can lose a
+    /// few bytes, which is acceptable for a rate gauge.
+    fn record_throughput(&self, bytes: u64, second: u64) {
+        let slot = (second % THROUGHPUT_WINDOW_SECS as u64) as usize;
+        if self.inner.throughput_seconds[slot].swap(second, Ordering::Relaxed) != second {
+            self.inner.throughput_bytes[slot].store(0, Ordering::Relaxed);
+        }
+        self.inner.throughput_bytes[slot].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Average bytes per second over the complete seconds before `second`
+    fn bytes_per_sec_at(&self, second: u64) -> f64 {
+        let window = (THROUGHPUT_WINDOW_SECS as u64).min(second).max(1);
+        let bytes: u64 = (0..THROUGHPUT_WINDOW_SECS)
+            .filter(|&slot| {
+                let counted = self.inner.throughput_seconds[slot].load(Ordering::Relaxed);
+                counted < second && second - counted <= window
+            })
+            .map(|slot| self.inner.throughput_bytes[slot].load(Ordering::Relaxed))
+            .sum();
+        bytes as f64 / window as f64
+    }
+
     // Error metrics
 
     /// Record a protocol error
@@ -375,6 +568,57 @@ impl Metrics {
         self.inner.timeout_errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    // Resource gauges
+
+    /// Record that session state was created
+    pub fn record_session_open(&self) {
+        self.inner.live_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that session state was released
+    pub fn record_session_close(&self) {
+        self.inner.live_sessions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Record a file (`dir == false`) or directory handle being allocated
+    pub fn record_handle_open(&self, dir: bool) {
+        self.handle_gauge(dir).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a file or directory handle being released
+    pub fn record_handle_close(&self, dir: bool) {
+        self.handle_gauge(dir).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn handle_gauge(&self, dir: bool) -> &AtomicUsize {
+        if dir {
+            &self.inner.open_dir_handles
+        } else {
+            &self.inner.open_file_handles
+        }
+    }
+
+    /// Record a read-ahead footprint changing from `previous` to `current` bytes
+    pub fn record_read_ahead_bytes(&self, previous: usize, current: usize) {
+        if current > previous {
+            self.inner.read_ahead_bytes.fetch_add(current - previous, Ordering::Relaxed);
+        } else {
+            self.inner.read_ahead_bytes.fetch_sub(previous - current, Ordering::Relaxed);
+        }
+    }
+
+    /// Update the gauges sampled from queues and tables owned elsewhere
+    pub fn set_table_sizes(
+        &self,
+        queued_audit_events: usize,
+        rate_limiter_entries: usize,
+        tracked_connections: usize,
+    ) {
+        self.inner.queued_audit_events.store(queued_audit_events, Ordering::Relaxed);
+        self.inner.rate_limiter_entries.store(rate_limiter_entries, Ordering::Relaxed);
+        self.inner.tracked_connections.store(tracked_connections, Ordering::Relaxed);
+    }
+
     // Snapshot and reporting
 
     /// Get a snapshot of current metrics
@@ -382,30 +626,65 @@ impl Metrics {
     /// NIST 800-53: AU-2 (Audit Events)
     /// Implementation: Create a consistent snapshot for reporting
     pub fn snapshot(&self) -> MetricsSnapshot {
+        self.read(false)
+    }
+
+    /// Get a snapshot and restart the counters from zero
+    ///
+    /// Each counter is swapped to zero atomically, so an increment racing
+    /// the reset lands in either this snapshot or the next one and is never
+    /// lost. Counters are swapped one at a time, so related counters (an
+    /// operation and `total_operations`) may straddle the reset. Gauges
+    /// (active connections, sessions, handles, queue and table sizes) and
+    /// the throughput rate describe current state and are not reset;
+    /// `operations_per_second` covers the time since the previous reset.
+    ///
+    /// NIST 800-53: AU-2 (Audit Events), SI-4 (System Monitoring)
+    pub fn snapshot_and_reset(&self) -> MetricsSnapshot {
+        self.read(true)
+    }
+
+    fn read(&self, reset: bool) -> MetricsSnapshot {
+        let take = |counter: &AtomicU64| {
+            if reset {
+                counter.swap(0, Ordering::Relaxed)
+            } else {
+                counter.load(Ordering::Relaxed)
+            }
+        };
+        let inner = &*self.inner;
+
         let now = Utc::now();
-        let uptime = now.signed_duration_since(self.inner.start_time);
+        let uptime = now.signed_duration_since(inner.start_time);
+        let elapsed_ms = inner.started.elapsed().as_millis() as u64;
+        let window_started_ms = if reset {
+            inner.window_started_ms.swap(elapsed_ms, Ordering::Relaxed)
+        } else {
+            inner.window_started_ms.load(Ordering::Relaxed)
+        };
+        let window_seconds = elapsed_ms.saturating_sub(window_started_ms) / 1000;
 
-        let auth_attempts = self.inner.auth_attempts.load(Ordering::Relaxed);
-        let auth_successes = self.inner.auth_successes.load(Ordering::Relaxed);
+        let auth_attempts = take(&inner.auth_attempts);
+        let auth_successes = take(&inner.auth_successes);
         let auth_success_rate = if auth_attempts > 0 {
             (auth_successes as f64 / auth_attempts as f64) * 100.0
         } else {
             0.0
         };
 
-        let bytes_read = self.inner.bytes_read.load(Ordering::Relaxed);
-        let bytes_written = self.inner.bytes_written.load(Ordering::Relaxed);
+        let bytes_read = take(&inner.bytes_read);
+        let bytes_written = take(&inner.bytes_written);
 
-        let protocol_errors = self.inner.protocol_errors.load(Ordering::Relaxed);
-        let permission_denied = self.inner.permission_denied.load(Ordering::Relaxed);
-        let file_not_found = self.inner.file_not_found.load(Ordering::Relaxed);
-        let io_errors = self.inner.io_errors.load(Ordering::Relaxed);
-        let timeout_errors = self.inner.timeout_errors.load(Ordering::Relaxed);
+        let protocol_errors = take(&inner.protocol_errors);
+        let permission_denied = take(&inner.permission_denied);
+        let file_not_found = take(&inner.file_not_found);
+        let io_errors = take(&inner.io_errors);
+        let timeout_errors = take(&inner.timeout_errors);
         let total_errors = protocol_errors + permission_denied + file_not_found + io_errors + timeout_errors;
 
-        let total_operations = self.inner.total_operations.load(Ordering::Relaxed);
-        let operations_per_second = if uptime.num_seconds() > 0 {
-            total_operations as f64 / uptime.num_seconds() as f64
+        let total_operations = take(&inner.total_operations);
+        let operations_per_second = if window_seconds > 0 {
+            total_operations as f64 / window_seconds as f64
         } else {
             0.0
         };
@@ -413,32 +692,66 @@ impl Metrics {
         MetricsSnapshot {
             timestamp: now,
             uptime_seconds: uptime.num_seconds(),
-            total_connections: self.inner.total_connections.load(Ordering::Relaxed),
-            active_connections: self.inner.active_connections.load(Ordering::Relaxed),
-            failed_connections: self.inner.failed_connections.load(Ordering::Relaxed),
-            rejected_connections: self.inner.rejected_connections.load(Ordering::Relaxed),
+            total_connections: take(&inner.total_connections),
+            active_connections: inner.active_connections.load(Ordering::Relaxed),
+            failed_connections: take(&inner.failed_connections),
+            rejected_connections: take(&inner.rejected_connections),
             auth_attempts,
             auth_successes,
-            auth_failures: self.inner.auth_failures.load(Ordering::Relaxed),
-            rate_limited_attempts: self.inner.rate_limited_attempts.load(Ordering::Relaxed),
+            auth_failures: take(&inner.auth_failures),
+            rate_limited_attempts: take(&inner.rate_limited_attempts),
             auth_success_rate,
-            file_opens: self.inner.file_opens.load(Ordering::Relaxed),
-            file_reads: self.inner.file_reads.load(Ordering::Relaxed),
-            file_writes: self.inner.file_writes.load(Ordering::Relaxed),
-            file_closes: self.inner.file_closes.load(Ordering::Relaxed),
-            file_removes: self.inner.file_removes.load(Ordering::Relaxed),
-            file_renames: self.inner.file_renames.load(Ordering::Relaxed),
-            dir_opens: self.inner.dir_opens.load(Ordering::Relaxed),
-            dir_reads: self.inner.dir_reads.load(Ordering::Relaxed),
-            dir_creates: self.inner.dir_creates.load(Ordering::Relaxed),
-            dir_removes: self.inner.dir_removes.load(Ordering::Relaxed),
-            stat_operations: self.inner.stat_operations.load(Ordering::Relaxed),
-            setstat_operations: self.inner.setstat_operations.load(Ordering::Relaxed),
-            symlink_operations: self.inner.symlink_operations.load(Ordering::Relaxed),
-            readlink_operations: self.inner.readlink_operations.load(Ordering::Relaxed),
+            file_opens: take(&inner.file_opens),
+            file_reads: take(&inner.file_reads),
+            file_writes: take(&inner.file_writes),
+            file_closes: take(&inner.file_closes),
+            file_removes: take(&inner.file_removes),
+            file_renames: take(&inner.file_renames),
+            dir_opens: take(&inner.dir_opens),
+            dir_reads: take(&inner.dir_reads),
+            dir_creates: take(&inner.dir_creates),
+            dir_removes: take(&inner.dir_removes),
+            stat_operations: take(&inner.stat_operations),
+            setstat_operations: take(&inner.setstat_operations),
+            symlink_operations: take(&inner.symlink_operations),
+            readlink_operations: take(&inner.readlink_operations),
+            requests_by_type: inner
+                .requests_by_type
+                .iter()
+                .enumerate()
+                .filter_map(|(byte, counter)| {
+                    let count = take(counter);
+                    (count > 0).then_some((type_name(byte)?, count))
+                })
+                .collect(),
+            request_errors_by_type: inner
+                .request_errors_by_type
+                .iter()
+                .enumerate()
+                .filter_map(|(byte, counter)| {
+                    let count = take(counter);
+                    (count > 0).then_some((type_name(byte)?, count))
+                })
+                .collect(),
+            request_latency_by_type: inner
+                .request_latency_by_type
+                .iter()
+                .enumerate()
+                .filter_map(|(byte, latency)| {
+                    let buckets: Vec<u64> = latency.buckets.iter().map(take).collect();
+                    let count = buckets.iter().sum();
+                    let histogram = LatencyHistogram {
+                        buckets,
+                        count,
+                        sum_micros: take(&latency.sum_micros),
+                    };
+                    (count > 0).then_some((type_name(byte)?, histogram))
+                })
+                .collect(),
             bytes_read,
             bytes_written,
             total_bytes: bytes_read + bytes_written,
+            current_bytes_per_sec: self.bytes_per_sec_at(elapsed_ms / 1000),
             protocol_errors,
             permission_denied,
             file_not_found,
@@ -447,6 +760,13 @@ impl Metrics {
             total_errors,
             total_operations,
             operations_per_second,
+            live_sessions: inner.live_sessions.load(Ordering::Relaxed),
+            open_file_handles: inner.open_file_handles.load(Ordering::Relaxed),
+            open_dir_handles: inner.open_dir_handles.load(Ordering::Relaxed),
+            read_ahead_bytes: inner.read_ahead_bytes.load(Ordering::Relaxed),
+            queued_audit_events: inner.queued_audit_events.load(Ordering::Relaxed),
+            rate_limiter_entries: inner.rate_limiter_entries.load(Ordering::Relaxed),
+            tracked_connections: inner.tracked_connections.load(Ordering::Relaxed),
         }
     }
 
@@ -465,6 +785,17 @@ impl Metrics {
         serde_json::to_string(&snapshot)
     }
 
+    /// Export metrics in the Prometheus text exposition format
+    ///
+    /// Metric names start with `snow_owl_sftp_`; per-request-type series
+    /// carry a `type` label. The host application serves the result from
+    /// its own metrics endpoint.
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    pub fn render_prometheus(&self) -> String {
+        self.snapshot().to_prometheus()
+    }
+
     /// Start timing an operation
     pub fn start_timer(&self, operation_name: &'static str) -> OperationTimer {
         OperationTimer {
@@ -497,7 +828,64 @@ impl OperationTimer {
     }
 }
 
+/// Lowercase name of the SFTP message type with type byte `byte`
+fn type_name(byte: usize) -> Option<String> {
+    let message_type = MessageType::try_from(u8::try_from(byte).ok()?).ok()?;
+    Some(format!("{message_type:?}").to_lowercase())
+}
+
 impl MetricsSnapshot {
+    /// Format in the Prometheus text exposition format
+    ///
+    /// See [`Metrics::render_prometheus`].
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP snow_owl_sftp_{name} {help}");
+            let _ = writeln!(out, "# TYPE snow_owl_sftp_{name} {kind}");
+            let _ = writeln!(out, "snow_owl_sftp_{name} {value}");
+        };
+        metric("connections_total", "counter", "Connections accepted", self.total_connections.to_string());
+        metric("connections_active", "gauge", "Connections currently open", self.active_connections.to_string());
+        metric("connections_rejected_total", "counter", "Connections rejected by limits", self.rejected_connections.to_string());
+        metric("auth_attempts_total", "counter", "Authentication attempts", self.auth_attempts.to_string());
+        metric("auth_failures_total", "counter", "Failed authentication attempts", self.auth_failures.to_string());
+        metric("auth_rate_limited_total", "counter", "Authentication attempts blocked by rate limiting", self.rate_limited_attempts.to_string());
+        metric("read_bytes_total", "counter", "Bytes read from files", self.bytes_read.to_string());
+        metric("written_bytes_total", "counter", "Bytes written to files", self.bytes_written.to_string());
+        metric("protocol_errors_total", "counter", "Packets the session could not handle", self.protocol_errors.to_string());
+        metric("sessions_active", "gauge", "SFTP sessions holding state", self.live_sessions.to_string());
+        metric("open_file_handles", "gauge", "Open file handles", self.open_file_handles.to_string());
+        metric("open_dir_handles", "gauge", "Open directory handles", self.open_dir_handles.to_string());
+
+        let mut by_type = |name: &str, help: &str, counts: &BTreeMap<String, u64>| {
+            let _ = writeln!(out, "# HELP snow_owl_sftp_{name} {help}");
+            let _ = writeln!(out, "# TYPE snow_owl_sftp_{name} counter");
+            for (kind, count) in counts {
+                let _ = writeln!(out, "snow_owl_sftp_{name}{{type=\"{kind}\"}} {count}");
+            }
+        };
+        by_type("requests_total", "Requests received by SFTP message type", &self.requests_by_type);
+        by_type("request_errors_total", "Requests answered with an error by SFTP message type", &self.request_errors_by_type);
+
+        let name = "snow_owl_sftp_request_duration_seconds";
+        let _ = writeln!(out, "# HELP {name} Time taken to answer requests by SFTP message type");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (kind, histogram) in &self.request_latency_by_type {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS_MICROS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let le = *bound as f64 / 1_000_000.0;
+                let _ = writeln!(out, "{name}_bucket{{type=\"{kind}\",le=\"{le}\"}} {cumulative}");
+            }
+            let _ = writeln!(out, "{name}_bucket{{type=\"{kind}\",le=\"+Inf\"}} {}", histogram.count);
+            let sum = histogram.sum_micros as f64 / 1_000_000.0;
+            let _ = writeln!(out, "{name}_sum{{type=\"{kind}\"}} {sum}");
+            let _ = writeln!(out, "{name}_count{{type=\"{kind}\"}} {}", histogram.count);
+        }
+        out
+    }
+
     /// Format as human-readable summary
     pub fn summary(&self) -> String {
         format!(
@@ -507,18 +895,22 @@ impl MetricsSnapshot {
              Files: {} opens, {} reads, {} writes, {} closes, {} removes, {} renames\n\
              Dirs: {} opens, {} reads, {} creates, {} removes\n\
              Advanced: {} stat, {} setstat, {} symlink, {} readlink\n\
-             Data: {} bytes read, {} bytes written ({} total)\n\
+             Data: {} bytes read, {} bytes written ({} total), {:.0} bytes/sec\n\
              Errors: {} total ({} protocol, {} permission, {} not_found, {} io, {} timeout)\n\
-             Performance: {} total ops, {:.2} ops/sec",
+             Performance: {} total ops, {:.2} ops/sec\n\
+             Resources: {} sessions, {} file handles, {} dir handles, {} read-ahead bytes, \
+             {} queued audit events, {} rate-limit entries, {} tracked connections",
             self.uptime_seconds,
             self.total_connections, self.active_connections, self.failed_connections, self.rejected_connections,
             self.auth_attempts, self.auth_successes, self.auth_success_rate, self.auth_failures, self.rate_limited_attempts,
             self.file_opens, self.file_reads, self.file_writes, self.file_closes, self.file_removes, self.file_renames,
             self.dir_opens, self.dir_reads, self.dir_creates, self.dir_removes,
             self.stat_operations, self.setstat_operations, self.symlink_operations, self.readlink_operations,
-            self.bytes_read, self.bytes_written, self.total_bytes,
+            self.bytes_read, self.bytes_written, self.total_bytes, self.current_bytes_per_sec,
             self.total_errors, self.protocol_errors, self.permission_denied, self.file_not_found, self.io_errors, self.timeout_errors,
-            self.total_operations, self.operations_per_second
+            self.total_operations, self.operations_per_second,
+            self.live_sessions, self.open_file_handles, self.open_dir_handles, self.read_ahead_bytes,
+            self.queued_audit_events, self.rate_limiter_entries, self.tracked_connections
         )
     }
 }
@@ -604,6 +996,55 @@ mod tests {
         assert_eq!(snapshot.total_errors, 5);
     }
 
+    #[test]
+    fn test_current_throughput_window() {
+        let metrics = Metrics::new();
+
+        metrics.record_throughput(4000, 10);
+        metrics.record_throughput(6000, 11);
+        // The second in progress is not counted yet
+        metrics.record_throughput(50_000, 12);
+        assert_eq!(metrics.bytes_per_sec_at(12), 2000.0);
+
+        // Slots age out of the window and are reused
+        assert_eq!(metrics.bytes_per_sec_at(17), 10_000.0);
+        assert_eq!(metrics.bytes_per_sec_at(18), 0.0);
+        metrics.record_throughput(500, 20);
+        assert_eq!(metrics.bytes_per_sec_at(21), 100.0);
+    }
+
+    #[test]
+    fn test_resource_gauges() {
+        let metrics = Metrics::new();
+
+        metrics.record_session_open();
+        metrics.record_handle_open(false);
+        metrics.record_handle_open(true);
+        metrics.record_read_ahead_bytes(0, 4096);
+        metrics.record_read_ahead_bytes(4096, 1024);
+        metrics.set_table_sizes(3, 2, 1);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.live_sessions, 1);
+        assert_eq!(snapshot.open_file_handles, 1);
+        assert_eq!(snapshot.open_dir_handles, 1);
+        assert_eq!(snapshot.read_ahead_bytes, 1024);
+        assert_eq!(snapshot.queued_audit_events, 3);
+        assert_eq!(snapshot.rate_limiter_entries, 2);
+        assert_eq!(snapshot.tracked_connections, 1);
+
+        metrics.record_handle_close(true);
+        metrics.record_handle_close(false);
+        metrics.record_read_ahead_bytes(1024, 0);
+        metrics.record_session_close();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.live_sessions, 0);
+        assert_eq!(snapshot.open_file_handles, 0);
+        assert_eq!(snapshot.open_dir_handles, 0);
+        assert_eq!(snapshot.read_ahead_bytes, 0);
+    }
+
     #[test]
     fn test_json_export() {
         let metrics = Metrics::new();
@@ -615,6 +1056,113 @@ mod tests {
         assert!(json.contains("\"bytes_read\": 100"));
     }
 
+    #[test]
+    fn test_snapshot_and_reset() {
+        let metrics = Metrics::new();
+        metrics.record_connection();
+        metrics.record_connection();
+        metrics.record_auth_attempt();
+        metrics.record_file_read(4096);
+        metrics.record_io_error();
+        metrics.record_handle_open(false);
+        metrics.record_request(MessageType::Read);
+        metrics.record_request(MessageType::Read);
+
+        let window = metrics.snapshot_and_reset();
+        assert_eq!(window.total_connections, 2);
+        assert_eq!(window.auth_attempts, 1);
+        assert_eq!(window.file_reads, 1);
+        assert_eq!(window.bytes_read, 4096);
+        assert_eq!(window.total_errors, 1);
+        assert_eq!(window.total_operations, 1);
+        assert_eq!(window.requests_by_type.get("read"), Some(&2));
+        assert_eq!(window.requests_by_type.len(), 1);
+
+        // Counters start again from zero; gauges keep describing current state
+        let after = metrics.snapshot();
+        assert_eq!(after.total_connections, 0);
+        assert_eq!(after.auth_attempts, 0);
+        assert_eq!(after.file_reads, 0);
+        assert_eq!(after.bytes_read, 0);
+        assert_eq!(after.total_errors, 0);
+        assert_eq!(after.total_operations, 0);
+        assert!(after.requests_by_type.is_empty());
+        assert_eq!(after.active_connections, 2);
+        assert_eq!(after.open_file_handles, 1);
+
+        metrics.record_file_open();
+        assert_eq!(metrics.snapshot_and_reset().file_opens, 1);
+        assert_eq!(metrics.snapshot().file_opens, 0);
+    }
+
+    #[test]
+    fn test_snapshot_and_reset_loses_no_counts() {
+        let metrics = Metrics::new();
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let metrics = metrics.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        metrics.record_file_write(1);
+                    }
+                })
+            })
+            .collect();
+
+        let mut windows = Vec::new();
+        while !writers.iter().all(|w| w.is_finished()) {
+            windows.push(metrics.snapshot_and_reset());
+        }
+        for writer in writers {
+            writer.join().expect("writer panicked");
+        }
+        windows.push(metrics.snapshot_and_reset());
+
+        assert_eq!(windows.iter().map(|w| w.file_writes).sum::<u64>(), 40_000);
+        assert_eq!(windows.iter().map(|w| w.bytes_written).sum::<u64>(), 40_000);
+    }
+
+    #[test]
+    fn test_request_latency_histogram() {
+        let metrics = Metrics::new();
+        metrics.record_response(MessageType::Read, Duration::from_micros(80), false);
+        metrics.record_response(MessageType::Read, Duration::from_micros(100), false);
+        metrics.record_response(MessageType::Read, Duration::from_millis(3), false);
+        metrics.record_response(MessageType::Read, Duration::from_secs(2), true);
+        metrics.record_response(MessageType::Stat, Duration::from_micros(300), true);
+
+        let snapshot = metrics.snapshot();
+        let read = &snapshot.request_latency_by_type["read"];
+        assert_eq!(read.count, 4);
+        assert_eq!(read.sum_micros, 2_003_180);
+        assert_eq!(read.buckets.len(), LATENCY_BUCKETS_MICROS.len() + 1);
+        // Bounds are inclusive; the last bucket catches anything slower
+        assert_eq!(read.buckets[0], 2);
+        assert_eq!(read.buckets[5], 1);
+        assert_eq!(read.buckets[LATENCY_BUCKETS_MICROS.len()], 1);
+        assert_eq!(snapshot.request_errors_by_type.get("read"), Some(&1));
+        assert_eq!(snapshot.request_errors_by_type.get("stat"), Some(&1));
+        assert_eq!(snapshot.request_latency_by_type.len(), 2);
+
+        let exposition = snapshot.to_prometheus();
+        let read_buckets: Vec<&str> = exposition
+            .lines()
+            .filter(|line| line.starts_with("snow_owl_sftp_request_duration_seconds_bucket{type=\"read\""))
+            .collect();
+        assert_eq!(read_buckets.len(), LATENCY_BUCKETS_MICROS.len() + 1);
+        // Prometheus buckets are cumulative
+        assert_eq!(read_buckets[0], "snow_owl_sftp_request_duration_seconds_bucket{type=\"read\",le=\"0.0001\"} 2");
+        assert_eq!(read_buckets[11], "snow_owl_sftp_request_duration_seconds_bucket{type=\"read\",le=\"1\"} 3");
+        assert_eq!(read_buckets[12], "snow_owl_sftp_request_duration_seconds_bucket{type=\"read\",le=\"+Inf\"} 4");
+        assert!(exposition.contains("snow_owl_sftp_request_duration_seconds_sum{type=\"read\"} 2.00318\n"));
+        assert!(exposition.contains("# TYPE snow_owl_sftp_request_duration_seconds histogram\n"));
+
+        metrics.snapshot_and_reset();
+        let after = metrics.snapshot();
+        assert!(after.request_latency_by_type.is_empty());
+        assert!(after.request_errors_by_type.is_empty());
+    }
+
     #[test]
     fn test_operation_timer() {
         let metrics = Metrics::new();
@@ -0,0 +1,367 @@
+//! Directory synchronization (mirror) planning
+//!
+//! [`Client::sync_dir`](crate::Client::sync_dir) makes a remote directory
+//! match a local tree, for pushing Windows PE trees to remote boot servers. The
+//! parts that need no connection live here: walking the local tree, deciding
+//! whether a remote file is current, and the report handed back.
+//!
+//! A remote file is current when its size and modification time (whole
+//! seconds) match the local file; uploads set the remote mtime to the local
+//! one so an unchanged tree re-syncs without transfers.
+//!
+//! ## NIST 800-53 Compliance
+//!
+//! - **CM-3 (Configuration Change Control)**: Each sync reports every change it made
+//! - **SI-7 (Software, Firmware, and Information Integrity)**: Remote trees kept identical to the source
+
+use crate::protocol::FileAttrs;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Transfers run at once when [`SyncOptions::concurrency`] is not set
+pub const DEFAULT_SYNC_CONCURRENCY: usize = 4;
+
+/// What a sync does with local symbolic links
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkHandling {
+    /// Leave links out of the sync (reported as skipped)
+    #[default]
+    Skip,
+    /// Sync what the link points to, as if it were a regular file or directory
+    Follow,
+    /// Create a remote link with the same target text
+    Recreate,
+}
+
+/// Options for [`Client::sync_dir`](crate::Client::sync_dir)
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Remove remote files and directories that are not in the local tree
+    pub delete_extraneous: bool,
+    /// Uploads run at once, each on its own SFTP channel; 1 uploads in turn
+    pub concurrency: usize,
+    /// Treatment of local symbolic links
+    pub symlinks: SymlinkHandling,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            delete_extraneous: false,
+            concurrency: DEFAULT_SYNC_CONCURRENCY,
+            symlinks: SymlinkHandling::default(),
+        }
+    }
+}
+
+/// A path that could not be synced, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncFailure {
+    /// Path relative to the synced directories, `/`-separated
+    pub path: String,
+    /// What went wrong
+    pub reason: String,
+}
+
+/// Outcome of a sync; paths are relative to the synced directories
+///
+/// NIST 800-53: CM-3 (Configuration Change Control)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Files uploaded and links recreated
+    pub uploaded: Vec<String>,
+    /// Files already current, and links left out under [`SymlinkHandling::Skip`]
+    pub skipped: Vec<String>,
+    /// Remote paths removed under [`SyncOptions::delete_extraneous`]
+    pub deleted: Vec<String>,
+    /// Paths that could not be synced
+    pub failed: Vec<SyncFailure>,
+}
+
+impl SyncReport {
+    /// Whether every path synced
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub(crate) fn fail(&mut self, path: &str, reason: impl ToString) {
+        self.failed.push(SyncFailure {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// Type of a local tree entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalKind {
+    /// Directory
+    Dir,
+    /// Regular file with its size and modification time (Unix seconds)
+    File {
+        /// Size in bytes
+        size: u64,
+        /// Modification time, whole seconds since the epoch
+        mtime: u32,
+    },
+    /// Symbolic link that is not followed
+    Symlink {
+        /// Link target as stored in the link
+        target: String,
+    },
+}
+
+/// Entry of the local tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalEntry {
+    /// Path relative to the local root, `/`-separated
+    pub relative: String,
+    /// Path on the local filesystem
+    pub path: PathBuf,
+    /// What the entry is
+    pub kind: LocalKind,
+}
+
+/// Type of a remote entry, from the mode bits in its attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteKind {
+    /// Directory
+    Dir,
+    /// Regular file
+    File,
+    /// Symbolic link
+    Symlink,
+    /// Anything else, or no mode reported
+    Other,
+}
+
+impl RemoteKind {
+    /// Kind of the entry `attrs` describe
+    #[must_use]
+    pub fn of(attrs: &FileAttrs) -> Self {
+        match attrs.permissions.map(|mode| mode & 0o170_000) {
+            Some(0o040_000) => Self::Dir,
+            Some(0o100_000) => Self::File,
+            Some(0o120_000) => Self::Symlink,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Whether a local file of `size` bytes modified at `mtime` must be uploaded
+/// over a remote file with `remote` attributes
+#[must_use]
+pub fn needs_upload(size: u64, mtime: u32, remote: &FileAttrs) -> bool {
+    remote.size != Some(size) || remote.mtime != Some(mtime)
+}
+
+/// Join a `/`-separated relative path onto a remote directory
+#[must_use]
+pub fn remote_join(base: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        base.to_string()
+    } else if base.ends_with('/') {
+        format!("{base}{relative}")
+    } else {
+        format!("{base}/{relative}")
+    }
+}
+
+/// Walk the tree under `root`, parents before their contents
+///
+/// Entries within a directory come in name order, followed by the contents
+/// of its subdirectories. Links are reported as
+/// [`LocalKind::Symlink`] unless `symlinks` is [`SymlinkHandling::Follow`];
+/// followed links that are broken or loop back into a directory already
+/// walked end up in the returned failures instead.
+///
+/// # Errors
+///
+/// Returns an error if `root` itself cannot be read.
+pub fn scan_local(
+    root: &Path,
+    symlinks: SymlinkHandling,
+) -> io::Result<(Vec<LocalEntry>, Vec<SyncFailure>)> {
+    let mut entries = Vec::new();
+    let mut failures = Vec::new();
+    let mut visited = HashSet::new();
+    visited.insert(root.canonicalize()?);
+
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut children = std::fs::read_dir(&dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        children.sort();
+
+        // Walked after this directory's other entries, in name order
+        let mut subdirs = Vec::new();
+        for path in children {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let relative = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+
+            let metadata = match std::fs::symlink_metadata(&path) {
+                Ok(m) => m,
+                Err(e) => {
+                    failures.push(SyncFailure { path: relative, reason: e.to_string() });
+                    continue;
+                }
+            };
+            let metadata = if metadata.file_type().is_symlink() {
+                if symlinks != SymlinkHandling::Follow {
+                    let target = std::fs::read_link(&path)?;
+                    entries.push(LocalEntry {
+                        relative,
+                        path,
+                        kind: LocalKind::Symlink {
+                            target: target.to_string_lossy().into_owned(),
+                        },
+                    });
+                    continue;
+                }
+                match std::fs::metadata(&path) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        failures.push(SyncFailure {
+                            path: relative,
+                            reason: format!("broken symbolic link: {e}"),
+                        });
+                        continue;
+                    }
+                }
+            } else {
+                metadata
+            };
+
+            if metadata.is_dir() {
+                // A followed link back into the tree would never end
+                if !visited.insert(path.canonicalize()?) {
+                    failures.push(SyncFailure {
+                        path: relative,
+                        reason: "symbolic link loop".to_string(),
+                    });
+                    continue;
+                }
+                entries.push(LocalEntry {
+                    relative: relative.clone(),
+                    path: path.clone(),
+                    kind: LocalKind::Dir,
+                });
+                subdirs.push((path, relative));
+            } else if metadata.is_file() {
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| u32::try_from(d.as_secs()).unwrap_or(u32::MAX));
+                entries.push(LocalEntry {
+                    relative,
+                    path,
+                    kind: LocalKind::File { size: metadata.len(), mtime },
+                });
+            } else {
+                failures.push(SyncFailure {
+                    path: relative,
+                    reason: "not a regular file or directory".to_string(),
+                });
+            }
+        }
+        // Reversed so the stack pops them in name order
+        pending.extend(subdirs.into_iter().rev());
+    }
+
+    Ok((entries, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    fn tree() -> TempDir {
+        let temp = TempDir::new().expect("temp dir");
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("boot/efi")).expect("mkdir");
+        std::fs::write(root.join("boot/bcd"), b"bcd").expect("write");
+        std::fs::write(root.join("boot/efi/bootx64.efi"), b"efi").expect("write");
+        std::fs::write(root.join("startnet.cmd"), b"wpeinit").expect("write");
+        symlink("boot/bcd", root.join("bcd-link")).expect("symlink");
+        temp
+    }
+
+    fn names(entries: &[LocalEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.relative.as_str()).collect()
+    }
+
+    #[test]
+    fn test_scan_lists_parents_before_contents() {
+        let temp = tree();
+        let (entries, failures) =
+            scan_local(temp.path(), SymlinkHandling::Skip).expect("scan");
+        assert!(failures.is_empty());
+        assert_eq!(
+            names(&entries),
+            ["bcd-link", "boot", "startnet.cmd", "boot/bcd", "boot/efi", "boot/efi/bootx64.efi"]
+        );
+        assert_eq!(
+            entries[0].kind,
+            LocalKind::Symlink { target: "boot/bcd".to_string() }
+        );
+        assert_eq!(entries[1].kind, LocalKind::Dir);
+        assert!(matches!(entries[3].kind, LocalKind::File { size: 3, .. }));
+    }
+
+    #[test]
+    fn test_scan_follows_links_and_reports_loops() {
+        let temp = tree();
+        symlink("..", temp.path().join("boot/up")).expect("symlink");
+        symlink("missing", temp.path().join("dangling")).expect("symlink");
+
+        let (entries, failures) =
+            scan_local(temp.path(), SymlinkHandling::Follow).expect("scan");
+        let link = entries.iter().find(|e| e.relative == "bcd-link").expect("bcd-link");
+        assert!(matches!(link.kind, LocalKind::File { size: 3, .. }));
+
+        let failed: Vec<_> = failures.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(failed, ["dangling", "boot/up"]);
+        assert!(failures[0].reason.starts_with("broken symbolic link"));
+        assert_eq!(failures[1].reason, "symbolic link loop");
+    }
+
+    #[test]
+    fn test_needs_upload_compares_size_and_mtime() {
+        let remote = FileAttrs {
+            size: Some(3),
+            mtime: Some(1_700_000_000),
+            ..FileAttrs::default()
+        };
+        assert!(!needs_upload(3, 1_700_000_000, &remote));
+        assert!(needs_upload(4, 1_700_000_000, &remote));
+        assert!(needs_upload(3, 1_700_000_001, &remote));
+        assert!(needs_upload(3, 1_700_000_000, &FileAttrs::default()));
+    }
+
+    #[test]
+    fn test_remote_kind_and_join() {
+        let attrs = |mode| FileAttrs { permissions: Some(mode), ..FileAttrs::default() };
+        assert_eq!(RemoteKind::of(&attrs(0o040_755)), RemoteKind::Dir);
+        assert_eq!(RemoteKind::of(&attrs(0o100_644)), RemoteKind::File);
+        assert_eq!(RemoteKind::of(&attrs(0o120_777)), RemoteKind::Symlink);
+        assert_eq!(RemoteKind::of(&FileAttrs::default()), RemoteKind::Other);
+
+        assert_eq!(remote_join("/winpe", "boot/bcd"), "/winpe/boot/bcd");
+        assert_eq!(remote_join("/", "boot"), "/boot");
+        assert_eq!(remote_join("/winpe", ""), "/winpe");
+    }
+}
@@ -0,0 +1,185 @@
+//! Password Authentication
+//!
+//! Password and keyboard-interactive logins are off unless `password_auth`
+//! is set; public key authentication stays the default. Passwords are
+//! checked by a [`PasswordVerifier`], so operators can plug in an existing
+//! credential store. The built-in [`PasswordFile`] reads `user:hash` lines
+//! holding Argon2 PHC strings, as produced by `argon2` or `htpasswd`-style
+//! tooling.
+//!
+//! NIST 800-53: IA-2 (Identification and Authentication), IA-5 (Authenticator Management)
+//! STIG: V-222542 - Passwords are stored only as cryptographic hashes
+
+use crate::{Error, Result};
+use argon2::{Argon2, PasswordHash, PasswordVerifier as _};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Hash checked for unknown usernames so they take as long as known ones
+///
+/// Argon2id at the `argon2` crate's default cost; no password is meant to
+/// match it.
+///
+/// NIST 800-53: IA-6 (Authentication Feedback)
+const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$ZHVtbXktc2FsdC1zbm93LW93bA$p+W1Q3QoPWQE/567bIg2tCyXxqp6SlZz+P2kSEUKM6M";
+
+/// Checks a user's password
+///
+/// Failures are rate limited and audited by the server whatever the
+/// verifier, so implementations only answer whether the password is
+/// correct. A verifier that cannot reach its store should answer `false`.
+///
+/// NIST 800-53: IA-2 (Identification and Authentication)
+#[async_trait::async_trait]
+pub trait PasswordVerifier: Send + Sync {
+    /// Whether `password` is correct for `username`
+    async fn verify(&self, username: &str, password: &str) -> bool;
+}
+
+/// Verifier used when none is configured; every password is wrong
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RejectAllPasswords;
+
+#[async_trait::async_trait]
+impl PasswordVerifier for RejectAllPasswords {
+    async fn verify(&self, _username: &str, _password: &str) -> bool {
+        false
+    }
+}
+
+/// Argon2 password hashes keyed by username
+///
+/// Each non-empty line that is not a `#` comment is
+/// `username:$argon2id$v=19$m=...,t=...,p=...$salt$hash`.
+///
+/// NIST 800-53: IA-5(1) (Password-Based Authentication)
+#[derive(Debug, Default, Clone)]
+pub struct PasswordFile {
+    hashes: Arc<HashMap<String, String>>,
+}
+
+impl PasswordFile {
+    /// Load a password file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a line is not a
+    /// `username:hash` pair with a valid PHC hash string.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::Config(format!("Failed to read password file {:?}: {}", path, e))
+        })?;
+        let file = Self::parse(&contents)?;
+        info!("Loaded {} password hashes from {:?}", file.hashes.len(), path);
+        Ok(file)
+    }
+
+    /// Parse password file contents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first malformed line.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut hashes = HashMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((username, hash)) = line.split_once(':') else {
+                return Err(Error::Config(format!(
+                    "Password file line {}: expected username:hash",
+                    index + 1
+                )));
+            };
+            // NIST 800-53: SI-10 - Refuse hashes that could never verify
+            PasswordHash::new(hash).map_err(|e| {
+                Error::Config(format!("Password file line {}: invalid hash: {}", index + 1, e))
+            })?;
+            hashes.insert(username.to_string(), hash.to_string());
+        }
+        Ok(Self {
+            hashes: Arc::new(hashes),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordVerifier for PasswordFile {
+    async fn verify(&self, username: &str, password: &str) -> bool {
+        // NIST 800-53: IA-6 - Verify unknown users against a dummy hash so the
+        // response time does not reveal which usernames exist
+        let (hash, known) = match self.hashes.get(username) {
+            Some(hash) => (hash.clone(), true),
+            None => (DUMMY_HASH.to_string(), false),
+        };
+        let password = password.to_string();
+        // Argon2 is deliberately slow; keep it off the async workers
+        let verified = tokio::task::spawn_blocking(move || {
+            PasswordHash::new(&hash).is_ok_and(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+        })
+        .await;
+        known
+            && verified.unwrap_or_else(|e| {
+                warn!("Password verification task failed: {}", e);
+                false
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use argon2::{Algorithm, Params, Version};
+
+    /// Argon2id hash with minimal cost so tests stay fast
+    fn cheap_hash(password: &str) -> String {
+        let params = Params::new(8, 1, 1, None).expect("params");
+        let salt = SaltString::from_b64("c25vdy1vd2wtdGVzdA").expect("salt");
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(password.as_bytes(), &salt)
+            .expect("hash")
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_password_file_verifies_hashes() {
+        let contents = format!(
+            "# deployment accounts\n\nimager:{}\nauditor:{}\n",
+            cheap_hash("correct horse"),
+            cheap_hash("battery staple")
+        );
+        let file = PasswordFile::parse(&contents).expect("parse");
+
+        assert!(file.verify("imager", "correct horse").await);
+        assert!(!file.verify("imager", "battery staple").await);
+        assert!(file.verify("auditor", "battery staple").await);
+        assert!(!file.verify("nobody", "correct horse").await);
+        assert!(!RejectAllPasswords.verify("imager", "correct horse").await);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_is_checked_against_dummy_hash() {
+        assert!(PasswordHash::new(DUMMY_HASH).is_ok());
+        let file = PasswordFile::parse(&format!("imager:{}\n", cheap_hash("correct horse")))
+            .expect("parse");
+
+        // Even the dummy hash's own password does not log in an unknown user
+        assert!(!file.verify("nobody", "snow-owl unknown user").await);
+        assert!(file.verify("imager", "correct horse").await);
+    }
+
+    #[test]
+    fn test_password_file_rejects_malformed_lines() {
+        assert!(PasswordFile::parse("imager").is_err());
+        assert!(PasswordFile::parse("imager:plaintext").is_err());
+        assert!(PasswordFile::parse("").expect("empty file").hashes.is_empty());
+    }
+}
@@ -7,10 +7,83 @@
 //! using the "sftp" subsystem.
 
 use bytes::{Buf, BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
 
 /// SFTP Protocol Version
 pub const SFTP_VERSION: u32 = 3;
 
+/// Highest SFTP version the server negotiates (draft-ietf-secsh-filexfer-04)
+pub const MAX_SFTP_VERSION: u32 = 4;
+
+/// Version to use with a client that sent `requested` in SSH_FXP_INIT
+///
+/// The server replies with `min(requested, MAX_SFTP_VERSION)`. Versions
+/// below 3 are not implemented, so such clients are offered v3, as before
+/// negotiation existed.
+#[must_use]
+pub fn negotiate_version(requested: u32) -> u32 {
+    requested.clamp(SFTP_VERSION, MAX_SFTP_VERSION)
+}
+
+/// OpenSSH hard link extension (OpenSSH PROTOCOL file)
+pub const EXT_HARDLINK: &str = "hardlink@openssh.com";
+
+/// OpenSSH fsync extension (OpenSSH PROTOCOL file)
+pub const EXT_FSYNC: &str = "fsync@openssh.com";
+
+/// OpenSSH rename extension that replaces an existing target (OpenSSH PROTOCOL file)
+pub const EXT_POSIX_RENAME: &str = "posix-rename@openssh.com";
+
+/// OpenSSH server limits extension: max packet, read and write lengths and
+/// open handles as four uint64s in SSH_FXP_EXTENDED_REPLY (OpenSSH PROTOCOL file)
+pub const EXT_LIMITS: &str = "limits@openssh.com";
+
+/// Upload resume extension: the size of a file as a uint64 in SSH_FXP_EXTENDED_REPLY
+pub const EXT_STAT_FOR_RESUME: &str = "stat-for-resume@snow-owl.dev";
+
+/// Capability discovery extension: a JSON [`ServerInfo`] as a string in
+/// SSH_FXP_EXTENDED_REPLY
+pub const EXT_SERVER_INFO: &str = "server-info@snow-owl.dev";
+
+/// Extensions advertised in SSH_FXP_VERSION as (name, data) pairs
+pub const SUPPORTED_EXTENSIONS: &[(&str, &str)] = &[
+    (EXT_HARDLINK, "1"),
+    (EXT_FSYNC, "1"),
+    (EXT_POSIX_RENAME, "1"),
+    (EXT_LIMITS, "1"),
+    (EXT_STAT_FOR_RESUME, "1"),
+    (EXT_SERVER_INFO, "1"),
+];
+
+/// What the server supports, as answered to `server-info@snow-owl.dev`
+///
+/// Limits are the ones applied to the requesting session, so `read_only`
+/// and `max_file_size` reflect that user's settings. Fields may be added;
+/// clients should ignore ones they do not know.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    /// Server software version
+    pub server_version: String,
+    /// Protocol version agreed in SSH_FXP_INIT
+    pub protocol_version: u32,
+    /// Lowest and highest protocol versions the server negotiates
+    pub supported_versions: (u32, u32),
+    /// Names of the supported SSH_FXP_EXTENDED requests
+    pub extensions: Vec<String>,
+    /// Largest SFTP packet accepted, in bytes
+    pub max_packet_size: u32,
+    /// Largest READ served in one reply; longer requests get a short read
+    pub max_read_length: u32,
+    /// Most handles one session may hold open
+    pub max_open_handles: usize,
+    /// Most entries returned by one SSH_FXP_READDIR
+    pub readdir_batch_size: usize,
+    /// Largest file the session may write, in bytes (0 = unlimited)
+    pub max_file_size: u64,
+    /// Whether every modifying operation is refused for this session
+    pub read_only: bool,
+}
+
 /// SFTP message types (as defined in the SFTP specification)
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -190,6 +263,20 @@ impl OpenFlags {
     }
 }
 
+/// SSH_FXP_REALPATH control byte (SFTP v6, draft-ietf-secsh-filexfer-13 section 8.9)
+///
+/// Older clients send no control byte, which behaves as `STAT_IF`.
+pub struct RealpathControl;
+
+impl RealpathControl {
+    /// Resolve without requiring the path to exist
+    pub const NO_CHECK: u8 = 0x01;
+    /// Include attributes when the path exists
+    pub const STAT_IF: u8 = 0x02;
+    /// Fail with NO_SUCH_FILE unless the path exists
+    pub const STAT_ALWAYS: u8 = 0x03;
+}
+
 /// File attributes (as defined in SFTP spec)
 #[derive(Debug, Clone, Default)]
 pub struct FileAttrs {
@@ -213,8 +300,54 @@ impl FileAttrs {
     const FLAG_PERMISSIONS: u32 = 0x00000004;
     const FLAG_ACMODTIME: u32 = 0x00000008;
 
-    /// Encode file attributes to bytes
+    // Version 4 flags (draft-ietf-secsh-filexfer-04 section 5)
+    const FLAG_ACCESSTIME: u32 = 0x00000008;
+    const FLAG_CREATETIME: u32 = 0x00000010;
+    const FLAG_MODIFYTIME: u32 = 0x00000020;
+    const FLAG_ACL: u32 = 0x00000040;
+    const FLAG_OWNERGROUP: u32 = 0x00000080;
+    const FLAG_SUBSECOND_TIMES: u32 = 0x00000100;
+    const FLAG_EXTENDED: u32 = 0x80000000;
+
+    // Version 4 file types
+    const TYPE_REGULAR: u8 = 1;
+    const TYPE_DIRECTORY: u8 = 2;
+    const TYPE_SYMLINK: u8 = 3;
+    const TYPE_SPECIAL: u8 = 4;
+    const TYPE_UNKNOWN: u8 = 5;
+
+    /// Unix file type bits of the mode (S_IFMT)
+    const MODE_TYPE_MASK: u32 = 0o170000;
+
+    /// Encode file attributes to bytes (version 3 layout)
     pub fn encode(&self) -> BytesMut {
+        self.encode_for(SFTP_VERSION)
+    }
+
+    /// Decode file attributes from bytes (version 3 layout)
+    pub fn decode(buf: &mut &[u8]) -> crate::Result<Self> {
+        Self::decode_for(buf, SFTP_VERSION)
+    }
+
+    /// Encode file attributes in the layout of a negotiated `version`
+    pub fn encode_for(&self, version: u32) -> BytesMut {
+        if version >= 4 {
+            self.encode_v4()
+        } else {
+            self.encode_v3()
+        }
+    }
+
+    /// Decode file attributes in the layout of a negotiated `version`
+    pub fn decode_for(buf: &mut &[u8], version: u32) -> crate::Result<Self> {
+        if version >= 4 {
+            Self::decode_v4(buf)
+        } else {
+            Self::decode_v3(buf)
+        }
+    }
+
+    fn encode_v3(&self) -> BytesMut {
         let mut buf = BytesMut::new();
         let mut flags = 0u32;
 
@@ -251,8 +384,7 @@ impl FileAttrs {
         buf
     }
 
-    /// Decode file attributes from bytes
-    pub fn decode(buf: &mut &[u8]) -> crate::Result<Self> {
+    fn decode_v3(buf: &mut &[u8]) -> crate::Result<Self> {
         if buf.remaining() < 4 {
             return Err(crate::Error::Protocol("Insufficient data for flags".into()));
         }
@@ -296,12 +428,244 @@ impl FileAttrs {
 
         Ok(attrs)
     }
+
+    /// Version 4 layout: an always-present type byte, owner and group as
+    /// strings, and 64-bit access and modify times flagged independently
+    ///
+    /// Owner and group are sent as the numeric uid and gid, which the draft
+    /// allows when no name mapping is available.
+    fn encode_v4(&self) -> BytesMut {
+        let mut buf = BytesMut::new();
+        let mut flags = 0u32;
+
+        if self.size.is_some() {
+            flags |= Self::FLAG_SIZE;
+        }
+        if self.uid.is_some() && self.gid.is_some() {
+            flags |= Self::FLAG_OWNERGROUP;
+        }
+        if self.permissions.is_some() {
+            flags |= Self::FLAG_PERMISSIONS;
+        }
+        if self.atime.is_some() {
+            flags |= Self::FLAG_ACCESSTIME;
+        }
+        if self.mtime.is_some() {
+            flags |= Self::FLAG_MODIFYTIME;
+        }
+
+        buf.put_u32(flags);
+        buf.put_u8(self.file_type());
+
+        if let Some(size) = self.size {
+            buf.put_u64(size);
+        }
+        if let (Some(uid), Some(gid)) = (self.uid, self.gid) {
+            codec::put_string(&mut buf, &uid.to_string());
+            codec::put_string(&mut buf, &gid.to_string());
+        }
+        if let Some(permissions) = self.permissions {
+            buf.put_u32(permissions);
+        }
+        if let Some(atime) = self.atime {
+            buf.put_u64(u64::from(atime));
+        }
+        if let Some(mtime) = self.mtime {
+            buf.put_u64(u64::from(mtime));
+        }
+
+        buf
+    }
+
+    /// Decode the version 4 layout
+    ///
+    /// Owner and group names that are not numeric IDs are ignored, as are
+    /// create times, sub-second times, ACLs and extended attributes; they are
+    /// still consumed so the rest of the packet parses.
+    fn decode_v4(buf: &mut &[u8]) -> crate::Result<Self> {
+        if buf.remaining() < 5 {
+            return Err(crate::Error::Protocol(
+                "Insufficient data for flags and type".into(),
+            ));
+        }
+
+        let flags = buf.get_u32();
+        let file_type = buf.get_u8();
+        let subsecond = flags & Self::FLAG_SUBSECOND_TIMES != 0;
+        let mut attrs = FileAttrs::default();
+
+        if flags & Self::FLAG_SIZE != 0 {
+            if buf.remaining() < 8 {
+                return Err(crate::Error::Protocol("Insufficient data for size".into()));
+            }
+            attrs.size = Some(buf.get_u64());
+        }
+
+        if flags & Self::FLAG_OWNERGROUP != 0 {
+            let owner = codec::get_string(buf)?;
+            let group = codec::get_string(buf)?;
+            if let (Ok(uid), Ok(gid)) = (owner.parse(), group.parse()) {
+                attrs.uid = Some(uid);
+                attrs.gid = Some(gid);
+            }
+        }
+
+        if flags & Self::FLAG_PERMISSIONS != 0 {
+            if buf.remaining() < 4 {
+                return Err(crate::Error::Protocol(
+                    "Insufficient data for permissions".into(),
+                ));
+            }
+            let mut permissions = buf.get_u32();
+            // The type byte is authoritative; carry it in the mode as v3 does
+            if permissions & Self::MODE_TYPE_MASK == 0 {
+                permissions |= Self::mode_type_bits(file_type);
+            }
+            attrs.permissions = Some(permissions);
+        }
+
+        if flags & Self::FLAG_ACCESSTIME != 0 {
+            attrs.atime = Some(Self::get_time_v4(buf, subsecond)?);
+        }
+        if flags & Self::FLAG_CREATETIME != 0 {
+            Self::get_time_v4(buf, subsecond)?;
+        }
+        if flags & Self::FLAG_MODIFYTIME != 0 {
+            attrs.mtime = Some(Self::get_time_v4(buf, subsecond)?);
+        }
+
+        if flags & Self::FLAG_ACL != 0 {
+            codec::get_bytes(buf)?;
+        }
+
+        if flags & Self::FLAG_EXTENDED != 0 {
+            if buf.remaining() < 4 {
+                return Err(crate::Error::Protocol(
+                    "Insufficient data for extended count".into(),
+                ));
+            }
+            for _ in 0..buf.get_u32() {
+                codec::get_bytes(buf)?;
+                codec::get_bytes(buf)?;
+            }
+        }
+
+        Ok(attrs)
+    }
+
+    /// Read a v4 int64 time, and its nanoseconds if present
+    ///
+    /// Times outside the `u32` range the attributes hold are clamped.
+    fn get_time_v4(buf: &mut &[u8], subsecond: bool) -> crate::Result<u32> {
+        let needed = if subsecond { 12 } else { 8 };
+        if buf.remaining() < needed {
+            return Err(crate::Error::Protocol("Insufficient data for time".into()));
+        }
+        let seconds = buf.get_i64();
+        if subsecond {
+            buf.get_u32();
+        }
+        Ok(u32::try_from(seconds.max(0)).unwrap_or(u32::MAX))
+    }
+
+    /// Version 4 type byte for these attributes, from the mode's type bits
+    fn file_type(&self) -> u8 {
+        match self.permissions.map(|mode| mode & Self::MODE_TYPE_MASK) {
+            Some(0o100000) => Self::TYPE_REGULAR,
+            Some(0o040000) => Self::TYPE_DIRECTORY,
+            Some(0o120000) => Self::TYPE_SYMLINK,
+            Some(0o010000 | 0o020000 | 0o060000 | 0o140000) => Self::TYPE_SPECIAL,
+            _ => Self::TYPE_UNKNOWN,
+        }
+    }
+
+    /// `ls -l` style line for the version 3 longname field
+    ///
+    /// draft-ietf-secsh-filexfer-02 section 7 leaves the format to the server
+    /// but recommends `ls -l` output, and GUI clients parse it for the mode,
+    /// size and date columns. Owner and group are numeric and the date is
+    /// UTC, showing the year instead of the time for files more than six
+    /// months old, as `ls` does.
+    #[must_use]
+    pub fn longname(&self, filename: &str) -> String {
+        self.longname_at(filename, chrono::Utc::now().timestamp())
+    }
+
+    fn longname_at(&self, filename: &str, now: i64) -> String {
+        const SIX_MONTHS: i64 = 182 * 24 * 60 * 60;
+
+        let mtime = chrono::DateTime::from_timestamp(i64::from(self.mtime.unwrap_or(0)), 0)
+            .unwrap_or_default();
+        let date = if (now - mtime.timestamp()).abs() < SIX_MONTHS {
+            mtime.format("%b %e %H:%M")
+        } else {
+            mtime.format("%b %e  %Y")
+        };
+
+        format!(
+            "{} {:>3} {:<8} {:<8} {:>8} {} {}",
+            self.mode_string(),
+            1,
+            self.uid.unwrap_or(0),
+            self.gid.unwrap_or(0),
+            self.size.unwrap_or(0),
+            date,
+            filename
+        )
+    }
+
+    /// `drwxr-xr-x` rendering of the mode, including setuid, setgid and sticky
+    fn mode_string(&self) -> String {
+        let mode = self.permissions.unwrap_or(0);
+        let mut out = String::with_capacity(10);
+        out.push(match mode & Self::MODE_TYPE_MASK {
+            0o040000 => 'd',
+            0o120000 => 'l',
+            0o020000 => 'c',
+            0o060000 => 'b',
+            0o010000 => 'p',
+            0o140000 => 's',
+            _ => '-',
+        });
+
+        // Owner, group and other, each with its special bit
+        for (shift, special, marker) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+            let bits = (mode >> shift) & 0o7;
+            out.push(if bits & 0o4 == 0 { '-' } else { 'r' });
+            out.push(if bits & 0o2 == 0 { '-' } else { 'w' });
+            out.push(match (mode & special != 0, bits & 0o1 != 0) {
+                (true, true) => marker,
+                (true, false) => marker.to_ascii_uppercase(),
+                (false, true) => 'x',
+                (false, false) => '-',
+            });
+        }
+        out
+    }
+
+    /// Mode type bits for a version 4 type byte, if it names one
+    fn mode_type_bits(file_type: u8) -> u32 {
+        match file_type {
+            Self::TYPE_REGULAR => 0o100000,
+            Self::TYPE_DIRECTORY => 0o040000,
+            Self::TYPE_SYMLINK => 0o120000,
+            _ => 0,
+        }
+    }
 }
 
 /// Helper functions for encoding/decoding SFTP protocol strings
 pub mod codec {
     use bytes::{Buf, BufMut, BytesMut};
 
+    /// Decode a uint32
+    pub fn get_u32(buf: &mut &[u8]) -> crate::Result<u32> {
+        if buf.remaining() < 4 {
+            return Err(crate::Error::Protocol("Insufficient data for uint32".into()));
+        }
+        Ok(buf.get_u32())
+    }
+
     /// Encode a string as SFTP string (length + data)
     pub fn put_string(buf: &mut BytesMut, s: &str) {
         buf.put_u32(s.len() as u32);
@@ -353,3 +717,130 @@ pub mod codec {
         Ok(bytes.to_vec())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version(3), 3);
+        assert_eq!(negotiate_version(4), 4);
+        assert_eq!(negotiate_version(6), MAX_SFTP_VERSION);
+        assert_eq!(negotiate_version(2), SFTP_VERSION);
+    }
+
+    #[test]
+    fn test_v4_attrs_layout_and_round_trip() -> crate::Result<()> {
+        let attrs = FileAttrs {
+            size: Some(1024),
+            uid: Some(1000),
+            gid: Some(100),
+            permissions: Some(0o040755),
+            atime: Some(1_700_000_000),
+            mtime: None,
+        };
+
+        let encoded = attrs.encode_for(4);
+        let mut buf = &encoded[..];
+        // SIZE | PERMISSIONS | ACCESSTIME | OWNERGROUP, then the type byte
+        assert_eq!(buf.get_u32(), 0x01 | 0x04 | 0x08 | 0x80);
+        assert_eq!(buf.get_u8(), 2);
+        assert_eq!(buf.get_u64(), 1024);
+        assert_eq!(codec::get_string(&mut buf)?, "1000");
+        assert_eq!(codec::get_string(&mut buf)?, "100");
+        assert_eq!(buf.get_u32(), 0o040755);
+        assert_eq!(buf.get_u64(), 1_700_000_000);
+        assert!(buf.is_empty());
+
+        let mut buf = &encoded[..];
+        let decoded = FileAttrs::decode_for(&mut buf, 4)?;
+        assert!(buf.is_empty());
+        assert_eq!(decoded.size, Some(1024));
+        assert_eq!(decoded.uid, Some(1000));
+        assert_eq!(decoded.gid, Some(100));
+        assert_eq!(decoded.permissions, Some(0o040755));
+        assert_eq!(decoded.atime, Some(1_700_000_000));
+        assert_eq!(decoded.mtime, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v4_decode_skips_unsupported_fields() -> crate::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.put_u32(0x04 | 0x10 | 0x20 | 0x40 | 0x80 | 0x100 | 0x8000_0000);
+        buf.put_u8(1);
+        codec::put_string(&mut buf, "alice@example.com");
+        codec::put_string(&mut buf, "staff@example.com");
+        buf.put_u32(0o644);
+        // createtime and modifytime, each with nanoseconds
+        buf.put_i64(5);
+        buf.put_u32(1);
+        buf.put_i64(1_234_567_890);
+        buf.put_u32(500);
+        codec::put_string(&mut buf, "acl");
+        buf.put_u32(1);
+        codec::put_string(&mut buf, "name@example.com");
+        codec::put_string(&mut buf, "value");
+        buf.put_u32(0xdead_beef);
+
+        let mut cursor = &buf[..];
+        let decoded = FileAttrs::decode_for(&mut cursor, 4)?;
+        assert_eq!(cursor, &0xdead_beef_u32.to_be_bytes()[..]);
+        assert_eq!(decoded.uid, None);
+        assert_eq!(decoded.gid, None);
+        // Type byte folded into the mode
+        assert_eq!(decoded.permissions, Some(0o100644));
+        assert_eq!(decoded.atime, None);
+        assert_eq!(decoded.mtime, Some(1_234_567_890));
+        Ok(())
+    }
+
+    #[test]
+    fn test_longname_matches_ls_format() {
+        // 2024-01-02 15:04:05 UTC
+        let mtime = 1_704_207_845;
+        let file = FileAttrs {
+            size: Some(1234),
+            uid: Some(1000),
+            gid: Some(100),
+            permissions: Some(0o100644),
+            atime: None,
+            mtime: Some(mtime),
+        };
+        assert_eq!(
+            file.longname_at("notes.txt", i64::from(mtime) + 3600),
+            "-rw-r--r--   1 1000     100          1234 Jan  2 15:04 notes.txt"
+        );
+        // More than six months old: the year replaces the time
+        assert_eq!(
+            file.longname_at("notes.txt", i64::from(mtime) + 200 * 86_400),
+            "-rw-r--r--   1 1000     100          1234 Jan  2  2024 notes.txt"
+        );
+
+        let dir = FileAttrs {
+            permissions: Some(0o041777),
+            ..file
+        };
+        assert!(dir.longname("tmp").starts_with("drwxrwxrwt "));
+        let setuid = FileAttrs {
+            permissions: Some(0o104754),
+            ..file
+        };
+        assert!(setuid.longname("su").starts_with("-rwsr-xr-- "));
+    }
+
+    #[test]
+    fn test_v3_layout_unchanged() {
+        let attrs = FileAttrs {
+            atime: Some(1),
+            mtime: Some(2),
+            ..FileAttrs::default()
+        };
+        assert_eq!(attrs.encode_for(3), attrs.encode());
+        assert_eq!(
+            &attrs.encode()[..],
+            &[0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 2][..]
+        );
+    }
+}
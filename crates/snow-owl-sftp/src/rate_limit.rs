@@ -7,10 +7,15 @@
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tracing::{debug, warn};
 
+/// Upper bound on how often expired records are purged
+const MAX_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Rate limiter configuration
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
@@ -101,8 +106,11 @@ impl RateLimiter {
     pub async fn check_allowed(&self, ip: IpAddr) -> bool {
         let mut attempts = self.attempts.lock().await;
 
-        // Get or create attempt record
-        let record = attempts.entry(ip).or_insert_with(AttemptRecord::new);
+        // Addresses without failures are not tracked, so connections from
+        // many addresses cannot grow the table
+        let Some(record) = attempts.get_mut(&ip) else {
+            return self.config.max_attempts > 0;
+        };
 
         // Check if currently locked out
         if let Some(lockout_until) = record.lockout_until {
@@ -184,18 +192,16 @@ impl RateLimiter {
     /// * `ip` - IP address of the successful attempt
     ///
     /// # NIST 800-53: AC-7 (Unsuccessful Logon Attempts)
-    /// # Implementation: Clears failure count on successful authentication
+    /// # Implementation: Forgets the address's failures on successful authentication
     pub async fn record_success(&self, ip: IpAddr) {
         let mut attempts = self.attempts.lock().await;
 
-        if let Some(record) = attempts.get_mut(&ip) {
+        if let Some(record) = attempts.remove(&ip) {
             if record.failed_attempts > 0 {
                 debug!(
                     "Clearing {} failed attempts for IP {} after successful auth",
                     record.failed_attempts, ip
                 );
-                record.failed_attempts = 0;
-                record.lockout_until = None;
             }
         }
     }
@@ -213,17 +219,12 @@ impl RateLimiter {
         // Remove entries where:
         // 1. Window has expired and no lockout
         // 2. Lockout has expired
+        // Either would be reset by the next check, so dropping them changes nothing
         attempts.retain(|ip, record| {
-            // If locked out, keep until lockout expires
-            if let Some(lockout_until) = record.lockout_until {
-                if now < lockout_until {
-                    return true; // Keep locked out entries
-                }
-            }
-
-            // Check if window has expired
-            let keep = now.duration_since(record.window_start) <= window_duration
-                || record.failed_attempts > 0;
+            let keep = match record.lockout_until {
+                Some(lockout_until) => now < lockout_until,
+                None => now.duration_since(record.window_start) <= window_duration,
+            };
 
             if !keep {
                 debug!("Cleaning up expired rate limit entry for IP {}", ip);
@@ -252,6 +253,26 @@ impl RateLimiter {
 
         (total, locked_out)
     }
+
+    /// Start the background task that periodically purges expired records
+    ///
+    /// Runs once per rate-limit window, at most every minute.
+    ///
+    /// # NIST 800-53: AC-7 (Unsuccessful Logon Attempts), SC-5 (Denial of Service Protection)
+    /// # Implementation: Bounds the table to addresses with recent failures
+    pub fn spawn_cleanup(self: &Arc<Self>) -> JoinHandle<()> {
+        let interval =
+            Duration::from_secs(self.config.window_secs.max(1)).min(MAX_CLEANUP_INTERVAL);
+
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                limiter.cleanup_expired().await;
+            }
+        })
+    }
 }
 
 #[cfg(test)]
@@ -330,4 +351,45 @@ mod tests {
         let (total, _locked) = limiter.get_stats().await;
         assert_eq!(total, 2);
     }
+
+    #[tokio::test]
+    async fn test_only_failures_are_tracked() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+        let ip1 = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let ip2 = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+        assert!(limiter.check_allowed(ip1).await);
+        assert_eq!(limiter.get_stats().await, (0, 0));
+
+        limiter.record_failure(ip1).await;
+        limiter.record_failure(ip2).await;
+        limiter.record_success(ip1).await;
+        assert_eq!(limiter.get_stats().await, (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_removes_expired_failures_and_lockouts() {
+        let config = RateLimitConfig {
+            max_attempts: 2,
+            window_secs: 60,
+            lockout_duration_secs: 300,
+        };
+        let limiter = RateLimiter::new(config);
+        let failed = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let locked = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+
+        limiter.record_failure(failed).await;
+        limiter.record_failure(locked).await;
+        limiter.record_failure(locked).await;
+
+        // Past the window: only the lockout is still meaningful
+        tokio::time::advance(Duration::from_secs(61)).await;
+        limiter.cleanup_expired().await;
+        assert_eq!(limiter.get_stats().await, (1, 1));
+
+        tokio::time::advance(Duration::from_secs(240)).await;
+        limiter.cleanup_expired().await;
+        assert_eq!(limiter.get_stats().await, (0, 0));
+        assert!(limiter.check_allowed(locked).await);
+    }
 }
@@ -0,0 +1,336 @@
+//! Sequential Read-Ahead
+//!
+//! Most SFTP clients download a file by issuing READ requests for
+//! consecutive offsets. Serving each request with a seek + read on the
+//! request path means every round trip pays the full disk latency. This
+//! module detects sequential access on a file handle and prefetches the
+//! next chunk in the background, so the following READ can be answered
+//! from memory.
+//!
+//! ## NIST 800-53 Compliance
+//!
+//! - **SC-5 (Denial of Service Protection)**: Prefetch buffers are bounded per handle
+//! - **SI-11 (Error Handling)**: Prefetch failures fall back to a direct read
+//!
+//! ## STIG Compliance
+//!
+//! - **V-222566 (Error Handling)**: Errors are never surfaced from the prefetch path
+
+use crate::server::read_at;
+use std::sync::Arc;
+use tokio::fs;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Number of consecutive sequential reads before prefetching starts
+pub const SEQUENTIAL_THRESHOLD: u32 = 2;
+
+/// Number of client-sized chunks to fetch ahead of the current offset
+pub const READ_AHEAD_CHUNKS: usize = 4;
+
+/// Result of a background prefetch: the offset that was read and the data
+/// (or error)
+type PrefetchResult = (u64, std::io::Result<Vec<u8>>);
+
+/// Per-handle read-ahead state
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection)
+/// Implementation: Bounded buffer filled by a single in-flight background read
+#[derive(Debug)]
+pub struct ReadAhead {
+    /// Duplicate of the handle's file, read positionally so prefetching
+    /// never moves the handle's position or reopens a path that may since
+    /// have been replaced
+    file: Arc<fs::File>,
+    /// Upper bound on buffered bytes for this handle
+    max_buffer: usize,
+    /// Offset the next sequential READ is expected at
+    next_offset: Option<u64>,
+    /// Number of consecutive sequential reads observed
+    sequential_run: u32,
+    /// Offset of the first byte in `buffer`
+    buffer_offset: u64,
+    /// Prefetched data not yet served
+    buffer: Vec<u8>,
+    /// In-flight background read
+    pending: Option<JoinHandle<PrefetchResult>>,
+    /// Reads answered from the prefetch buffer
+    hits: u64,
+    /// Bytes fetched by background reads
+    prefetched_bytes: u64,
+}
+
+impl ReadAhead {
+    /// Create read-ahead state for a file handle
+    ///
+    /// `file` should be a `try_clone` of the handle's file. `max_buffer`
+    /// bounds the number of bytes held in memory for this handle.
+    pub fn new(file: fs::File, max_buffer: usize) -> Self {
+        Self {
+            file: Arc::new(file),
+            max_buffer,
+            next_offset: None,
+            sequential_run: 0,
+            buffer_offset: 0,
+            buffer: Vec::new(),
+            pending: None,
+            hits: 0,
+            prefetched_bytes: 0,
+        }
+    }
+
+    /// Try to serve a READ from the prefetch buffer
+    ///
+    /// Returns `None` when the request must be served by a direct read
+    /// (non-sequential access, nothing buffered, prefetch error, or EOF).
+    /// A non-sequential request discards any buffered data.
+    pub async fn serve(&mut self, offset: u64, len: usize) -> Option<Vec<u8>> {
+        if self.next_offset != Some(offset) {
+            self.reset();
+            return None;
+        }
+
+        self.collect_pending().await;
+
+        let buffer_end = self.buffer_offset + self.buffer.len() as u64;
+        if len == 0 || offset < self.buffer_offset || offset >= buffer_end {
+            return None;
+        }
+
+        // Offset is inside the buffer; drop anything before it
+        let skip = usize::try_from(offset - self.buffer_offset).ok()?;
+        self.buffer.drain(..skip);
+        self.buffer_offset = offset;
+
+        let take = len.min(self.buffer.len());
+        let data: Vec<u8> = self.buffer.drain(..take).collect();
+        self.buffer_offset += take as u64;
+        self.hits += 1;
+
+        Some(data)
+    }
+
+    /// Record that `served` bytes were returned at `offset` for a request of
+    /// `len` bytes, and start a background prefetch once access is sequential
+    pub fn advance(&mut self, offset: u64, served: usize, len: usize) {
+        if self.next_offset == Some(offset) {
+            self.sequential_run = self.sequential_run.saturating_add(1);
+        } else {
+            self.sequential_run = 0;
+        }
+        self.next_offset = Some(offset.saturating_add(served as u64));
+
+        if self.sequential_run >= SEQUENTIAL_THRESHOLD {
+            self.start_prefetch(len);
+        }
+    }
+
+    /// Discard buffered data, e.g. after the file was written through this handle
+    pub fn invalidate(&mut self) {
+        self.reset();
+        self.next_offset = None;
+    }
+
+    /// Number of READs answered from the prefetch buffer
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Total bytes fetched by background reads
+    pub fn prefetched_bytes(&self) -> u64 {
+        self.prefetched_bytes
+    }
+
+    /// Bytes currently held in the prefetch buffer
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn reset(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.abort();
+        }
+        self.buffer.clear();
+        self.buffer_offset = 0;
+        self.sequential_run = 0;
+    }
+
+    /// Wait for the in-flight prefetch (if any) and append its data
+    async fn collect_pending(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        match pending.await {
+            Ok((read_offset, Ok(data))) => {
+                let buffer_end = self.buffer_offset + self.buffer.len() as u64;
+                if self.buffer.is_empty() {
+                    self.buffer_offset = read_offset;
+                    self.buffer = data;
+                } else if read_offset == buffer_end {
+                    self.buffer.extend_from_slice(&data);
+                }
+            }
+            Ok((read_offset, Err(e))) => {
+                // Leave it to the direct read to report the error
+                debug!("Read-ahead at offset {} failed: {}", read_offset, e);
+            }
+            Err(e) => {
+                debug!("Read-ahead task did not complete: {}", e);
+            }
+        }
+    }
+
+    /// Spawn a background read following the buffered data
+    fn start_prefetch(&mut self, len: usize) {
+        if self.pending.is_some() || self.max_buffer == 0 {
+            return;
+        }
+
+        let Some(next_offset) = self.next_offset else {
+            return;
+        };
+
+        // Continue after whatever is already buffered
+        let (read_offset, buffered) = if self.buffer.is_empty() {
+            (next_offset, 0)
+        } else {
+            (
+                self.buffer_offset + self.buffer.len() as u64,
+                self.buffer.len(),
+            )
+        };
+
+        let wanted = len.saturating_mul(READ_AHEAD_CHUNKS).min(self.max_buffer);
+        if buffered >= wanted {
+            return;
+        }
+        let chunk = wanted - buffered;
+
+        let file = Arc::clone(&self.file);
+        self.prefetched_bytes += chunk as u64;
+
+        self.pending = Some(tokio::spawn(async move {
+            (read_offset, read_at(&file, read_offset, chunk).await)
+        }));
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    async fn open(path: &Path) -> fs::File {
+        fs::File::open(path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sequential_reads_use_prefetch() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sequential.bin");
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &content).await.unwrap();
+
+        let chunk = 4096;
+        let file = open(&path).await;
+        let mut read_ahead = ReadAhead::new(file.try_clone().await.unwrap(), 64 * 1024);
+        let mut received = Vec::new();
+        let mut offset = 0u64;
+
+        loop {
+            let data = match read_ahead.serve(offset, chunk).await {
+                Some(data) => data,
+                None => read_at(&file, offset, chunk).await.unwrap(),
+            };
+            if data.is_empty() {
+                break;
+            }
+            read_ahead.advance(offset, data.len(), chunk);
+            offset += data.len() as u64;
+            received.extend_from_slice(&data);
+        }
+
+        assert_eq!(received, content);
+        assert!(read_ahead.hits() > 0, "prefetch path should serve reads");
+        assert!(read_ahead.prefetched_bytes() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_seek_discards_buffer() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("random.bin");
+        let content: Vec<u8> = (0..65_536u32).map(|i| (i % 7) as u8).collect();
+        fs::write(&path, &content).await.unwrap();
+
+        let mut read_ahead = ReadAhead::new(open(&path).await, 32 * 1024);
+        for i in 0..3u64 {
+            read_ahead.advance(i * 1024, 1024, 1024);
+        }
+
+        // A jump backwards is not sequential and must not be served
+        assert!(read_ahead.serve(0, 1024).await.is_none());
+        read_ahead.advance(0, 1024, 1024);
+        assert!(read_ahead.serve(1024, 1024).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_buffer_is_bounded() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("bounded.bin");
+        fs::write(&path, vec![1u8; 1024 * 1024]).await.unwrap();
+
+        let max_buffer = 8 * 1024;
+        let mut read_ahead = ReadAhead::new(open(&path).await, max_buffer);
+        for i in 0..3u64 {
+            read_ahead.advance(i * 32_768, 32_768, 32_768);
+        }
+
+        let data = read_ahead.serve(3 * 32_768, 32_768).await.unwrap();
+        assert_eq!(data.len(), max_buffer);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_reads_the_open_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("replaced.bin");
+        fs::write(&path, vec![1u8; 64 * 1024]).await.unwrap();
+
+        let mut read_ahead = ReadAhead::new(open(&path).await, 32 * 1024);
+
+        // Replacing the path must not change what the handle reads
+        let replacement = temp_dir.path().join("replacement.bin");
+        fs::write(&replacement, vec![2u8; 64 * 1024]).await.unwrap();
+        fs::rename(&replacement, &path).await.unwrap();
+
+        for i in 0..3u64 {
+            read_ahead.advance(i * 1024, 1024, 1024);
+        }
+        let data = read_ahead.serve(3 * 1024, 1024).await.unwrap();
+        assert_eq!(data, vec![1u8; 1024]);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_drops_prefetched_data() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("invalidate.bin");
+        fs::write(&path, vec![0u8; 64 * 1024]).await.unwrap();
+
+        let mut read_ahead = ReadAhead::new(open(&path).await, 32 * 1024);
+        for i in 0..3u64 {
+            read_ahead.advance(i * 1024, 1024, 1024);
+        }
+        read_ahead.invalidate();
+
+        assert!(read_ahead.serve(3 * 1024, 1024).await.is_none());
+    }
+}
@@ -4,24 +4,40 @@
 //! built on top of the SSH protocol (RFC 4251-4254).
 
 use crate::{
-    cnsa, AuthorizedKeys, Config, ConnectionTracker, ConnectionTrackerConfig, Error,
-    RateLimitConfig, RateLimiter, Result,
+    cnsa, resolve_beneath, AccessDecision, AccountPolicy, AuditChannel, AuditEvent, AuditSink,
+    AuthorizationGate, AuthorizedKeys, Authorizer, ChannelWriteFailure, Config,
+    ConnectionTracker, ConnectionTrackerConfig, Error, FileModePolicy, Metrics, Operation, OperationContext,
+    JsonFileSink, MetricsSnapshot, PasswordFile, PasswordVerifier, PathAccess, RateLimitConfig,
+    RateLimiter, ReadAhead, RejectAllPasswords, Result, SessionInfo, StaticAuthorizer,
+    SharedAuditSink, SymlinkPolicy, SymlinkViolation, TracingSink, WritePastEof,
 };
-use bytes::{BufMut, BytesMut};
+use crate::symlink::{client_link_target, host_link_target};
+use crate::throttle::{SharedBucket, Throttle};
+use bytes::{Buf, BufMut, BytesMut};
 use russh::server::{Auth, Handler, Msg, Server as SshServer, Session};
 use russh::{Channel, ChannelId, CryptoVec, MethodKind, MethodSet};
 use russh::keys::{PrivateKey, PublicKey};
+use snow_owl_core::AuditQueue;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, PoisonError, Weak};
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::Mutex;
+use tokio::task::JoinSet;
 use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-use crate::protocol::{codec, FileAttrs, MessageType, OpenFlags, StatusCode, SFTP_VERSION};
+use crate::protocol::{
+    codec, negotiate_version, FileAttrs, MessageType, OpenFlags, RealpathControl, ServerInfo,
+    StatusCode, EXT_FSYNC, EXT_HARDLINK, EXT_LIMITS, EXT_POSIX_RENAME, EXT_SERVER_INFO,
+    EXT_STAT_FOR_RESUME,
+    MAX_SFTP_VERSION, SFTP_VERSION, SUPPORTED_EXTENSIONS,
+};
 
 /// File operation timeout (30 seconds)
 ///
@@ -29,10 +45,47 @@ use crate::protocol::{codec, FileAttrs, MessageType, OpenFlags, StatusCode, SFTP
 /// Implementation: Prevent operations from hanging indefinitely
 const FILE_OP_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// How often gauges sampled from the rate limiter, connection tracker and
+/// audit queue are refreshed
+///
+/// NIST 800-53: SI-4 (System Monitoring)
+const GAUGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default largest READ served in one reply (`Config::max_read_len`)
+///
+/// The requested length comes from the wire, so it is clamped before a
+/// buffer is allocated; SFTP clients accept short reads and ask again.
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection), SI-10 (Input Validation)
+pub(crate) const MAX_READ_LENGTH: u32 = 256 * 1024;
+
+/// Default for the most file and directory handles one session may hold
+/// open (`max_open_handles`)
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection)
+pub(crate) const MAX_OPEN_HANDLES: usize = 1024;
+
+/// Bytes of a WRITE packet set aside for everything but the data, as in
+/// OpenSSH's `sftp-server`, when reporting the largest WRITE length
+const WRITE_HEADER_ALLOWANCE: u64 = 1024;
+
+/// Longest command accepted on the control socket
+const MAX_CONTROL_COMMAND_LEN: u64 = 256;
+
+/// Live sessions by session ID, for `dump-state`
+///
+/// Entries are removed when the connection's handler is dropped; the weak
+/// reference never keeps a session alive.
+type SessionRegistry = Arc<std::sync::Mutex<HashMap<String, Weak<Mutex<SftpSession>>>>>;
+
 /// SFTP Server
 pub struct Server {
     config: Arc<Config>,
     ssh_config: russh::server::Config,
+    authorizer: Arc<dyn Authorizer>,
+    password_verifier: Arc<dyn PasswordVerifier>,
+    audit: AuditChannel,
+    metrics: Metrics,
 }
 
 impl Server {
@@ -43,8 +96,9 @@ impl Server {
     pub async fn new(config: Config) -> Result<Self> {
         config.validate()?;
 
-        // Load host key
-        let key_pair = load_host_key(&config.host_key_path).await?;
+        // NIST 800-53: IA-3 - Load every CNSA 2.0 host key so each client
+        // negotiates an algorithm it trusts
+        let host_keys = load_host_keys(config.host_key_path.paths()).await?;
 
         // NSA CNSA 2.0: Configure cryptographic algorithms
         // Only CNSA 2.0 compliant algorithms are enabled
@@ -52,7 +106,7 @@ impl Server {
             inactivity_timeout: Some(std::time::Duration::from_secs(config.timeout)),
             auth_rejection_time: std::time::Duration::from_secs(3),
             auth_rejection_time_initial: Some(std::time::Duration::from_secs(0)),
-            keys: vec![key_pair],
+            keys: host_keys,
             ..Default::default()
         };
 
@@ -73,20 +127,171 @@ impl Server {
             host_key_algorithms = ?cnsa::CNSA_HOST_KEY_ALGORITHMS,
             "NSA CNSA 2.0 cipher suite enforced"
         );
+        info!(
+            "Offering host keys: {}",
+            ssh_config
+                .keys
+                .iter()
+                .map(|key| key.algorithm().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        // NIST 800-53: AU-12 - Sessions queue audit events; a writer thread
+        // delivers them in batches
+        let audit = spawn_audit_channel(&config, TracingSink)?;
+
+        // NIST 800-53: IA-2 - Public keys always; passwords only when enabled
+        let mut methods = MethodSet::empty();
+        methods.push(MethodKind::PublicKey);
+        let mut password_verifier: Arc<dyn PasswordVerifier> = Arc::new(RejectAllPasswords);
+        if config.password_auth {
+            methods.push(MethodKind::Password);
+            methods.push(MethodKind::KeyboardInteractive);
+            if let Some(path) = &config.password_file {
+                password_verifier = Arc::new(PasswordFile::load(path)?);
+            }
+        }
+        ssh_config.methods = methods;
 
+        let config = Arc::new(config);
         Ok(Self {
-            config: Arc::new(config),
+            authorizer: Arc::new(StaticAuthorizer::new(config.clone())),
+            password_verifier,
+            config,
             ssh_config,
+            audit,
+            metrics: Metrics::new(),
         })
     }
 
+    /// Handle to the server's metrics
+    ///
+    /// The resource gauges in each snapshot (sessions, handles, read-ahead
+    /// bytes, queued audit events, rate-limiter and connection-tracker
+    /// entries) all return to zero once the server is idle; anything else
+    /// points at a leak.
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.clone()
+    }
+
+    /// Current connection, authentication, operation and byte counters
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Handle to the server's audit queue
+    ///
+    /// Call [`AuditChannel::shutdown`] on it after the server stops so
+    /// queued events are written before the process exits.
+    ///
+    /// NIST 800-53: AU-5 (Response to Audit Processing Failures)
+    pub fn audit_channel(&self) -> AuditChannel {
+        self.audit.clone()
+    }
+
+    /// Also record security events in the shared audit trail
+    ///
+    /// Authentication attempts, policy violations and lockouts are forwarded
+    /// to `queue` (normally backed by the `audit_log` table) in addition to
+    /// the tracing log. Call before [`audit_channel`](Self::audit_channel)
+    /// so the handle refers to the forwarding channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the audit writer thread cannot be spawned.
+    ///
+    /// NIST 800-53: AU-9 (Protection of Audit Information)
+    pub fn with_audit_queue(mut self, queue: AuditQueue) -> Result<Self> {
+        let audit = spawn_audit_channel(&self.config, SharedAuditSink::new(queue))?;
+        std::mem::replace(&mut self.audit, audit).shutdown();
+        Ok(self)
+    }
+
+    /// Replace the per-operation authorizer
+    ///
+    /// The default enforces the per-user `read_only`, `allowed_operations`
+    /// and `denied_operations` settings.
+    ///
+    /// NIST 800-53: AC-3 (Access Enforcement)
+    pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+        self.authorizer = authorizer;
+        self
+    }
+
+    /// Replace the password verifier
+    ///
+    /// Used only when `password_auth` is set. The default checks
+    /// `password_file`, or rejects every password when no file is configured.
+    ///
+    /// NIST 800-53: IA-2 (Identification and Authentication)
+    pub fn with_password_verifier(mut self, verifier: Arc<dyn PasswordVerifier>) -> Self {
+        self.password_verifier = verifier;
+        self
+    }
+
     /// Run the SFTP server
     pub async fn run(self) -> Result<()> {
+        self.run_with_shutdown(CancellationToken::new(), Duration::ZERO)
+            .await
+    }
+
+    /// Run the server until `shutdown` is cancelled, then drain sessions
+    ///
+    /// Once the token is cancelled the listener is closed and no further
+    /// connections are accepted. Open sessions get `drain_timeout` to end
+    /// before they are aborted, and the future resolves after that, so the
+    /// audit events of every session are queued by the time it returns.
+    ///
+    /// NIST 800-53: SC-24 (Fail in Known State)
+    pub async fn run_with_shutdown(
+        self,
+        shutdown: CancellationToken,
+        drain_timeout: Duration,
+    ) -> Result<()> {
         let addr = format!("{}:{}", self.config.bind_address, self.config.port);
         info!("Starting SFTP server on {}", addr);
 
         let config = Arc::new(self.ssh_config);
-        let mut handler = SftpHandler::new(self.config.clone());
+        let mut handler = SftpHandler::new(
+            self.config.clone(),
+            self.authorizer.clone(),
+            self.audit.clone(),
+            self.metrics.clone(),
+        );
+        handler.password_verifier = self.password_verifier.clone();
+
+        // NIST 800-53: AC-2 - Daily warnings for accounts nearing expiry
+        handler.account_policy.spawn_expiry_warnings();
+
+        // NIST 800-53: AC-12 - Reap idle and over-age sessions
+        handler.connection_tracker.spawn_reaper();
+
+        // NIST 800-53: AC-7, SC-5 - Purge expired rate-limit records
+        handler.rate_limiter.spawn_cleanup();
+
+        // NIST 800-53: SI-4 - Keep the sampled gauges current
+        let sampler = handler.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(GAUGE_SAMPLE_INTERVAL);
+            loop {
+                ticker.tick().await;
+                sampler.sample_gauges().await;
+            }
+        });
+
+        if let Some(path) = self.config.control_socket.clone() {
+            let control = handler.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve_control_socket(&path, control).await {
+                    error!("Control socket {:?} failed: {}", path, e);
+                }
+            });
+        }
 
         // Create TCP listener
         let socket = tokio::net::TcpListener::bind(&addr)
@@ -96,22 +301,47 @@ impl Server {
         info!("SFTP server listening on {}", addr);
 
         // Accept connections loop
+        let mut sessions = JoinSet::new();
         loop {
-            let (stream, peer_addr) = socket
-                .accept()
-                .await
+            let accepted = tokio::select! {
+                accepted = socket.accept() => accepted,
+                Some(_) = sessions.join_next(), if !sessions.is_empty() => continue,
+                () = shutdown.cancelled() => break,
+            };
+            let (stream, peer_addr) = accepted
                 .map_err(|e| Error::Connection(format!("Failed to accept connection: {}", e)))?;
 
             let config = config.clone();
             let session_handler = handler.new_client(Some(peer_addr));
 
             // Spawn a task to handle this connection
-            tokio::spawn(async move {
+            sessions.spawn(async move {
                 if let Err(e) = russh::server::run_stream(config, stream, session_handler).await {
                     error!("Connection error: {}", e);
                 }
             });
         }
+
+        // NIST 800-53: SC-24 - Stop accepting, then let open sessions end
+        drop(socket);
+        info!(
+            "SFTP listener closed, waiting up to {:?} for {} session(s)",
+            drain_timeout,
+            sessions.len()
+        );
+        let drained = timeout(drain_timeout, async {
+            while sessions.join_next().await.is_some() {}
+        })
+        .await;
+        if drained.is_err() {
+            warn!(
+                "Drain timeout reached, aborting {} session(s)",
+                sessions.len()
+            );
+            sessions.abort_all();
+            while sessions.join_next().await.is_some() {}
+        }
+        Ok(())
     }
 }
 
@@ -120,15 +350,30 @@ impl Server {
 /// NIST 800-53: AC-7 (Unsuccessful Logon Attempts), AC-10 (Concurrent Session Control), AC-12 (Session Termination)
 /// STIG: V-222601 (Session termination)
 /// Implementation: Manages rate limiting and connection limits per user
+#[derive(Clone)]
 struct SftpHandler {
     config: Arc<Config>,
-    _clients: Arc<Mutex<HashMap<usize, SftpSession>>>,
+    sessions: SessionRegistry,
     rate_limiter: Arc<RateLimiter>,
     connection_tracker: Arc<ConnectionTracker>,
+    account_policy: Arc<AccountPolicy>,
+    authorizer: Arc<dyn Authorizer>,
+    /// Checks passwords when `password_auth` is set (NIST 800-53: IA-2)
+    password_verifier: Arc<dyn PasswordVerifier>,
+    audit: AuditChannel,
+    /// Bandwidth shared by every session (NIST 800-53: SC-5)
+    global_bandwidth: Option<Arc<SharedBucket>>,
+    /// Resource gauges (NIST 800-53: SI-4)
+    metrics: Metrics,
 }
 
 impl SftpHandler {
-    fn new(config: Arc<Config>) -> Self {
+    fn new(
+        config: Arc<Config>,
+        authorizer: Arc<dyn Authorizer>,
+        audit: AuditChannel,
+        metrics: Metrics,
+    ) -> Self {
         // NIST 800-53: AC-7 - Initialize rate limiter
         let rate_limit_config = RateLimitConfig {
             max_attempts: config.max_auth_attempts,
@@ -139,14 +384,92 @@ impl SftpHandler {
         // NIST 800-53: AC-10 - Initialize connection tracker
         let connection_tracker_config = ConnectionTrackerConfig {
             max_connections_per_user: config.max_connections_per_user,
+            idle_timeout_secs: config.session_idle_timeout_secs,
+            max_session_lifetime_secs: config.max_session_lifetime_secs,
         };
 
+        // NIST 800-53: SC-5 - One bucket for all sessions
+        let global_bandwidth = config
+            .global_bytes_per_sec()
+            .map(|rate| Arc::new(SharedBucket::new(rate)));
+
         Self {
+            account_policy: Arc::new(AccountPolicy::new(config.clone())),
+            global_bandwidth,
             config,
-            _clients: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(std::sync::Mutex::new(HashMap::new())),
             rate_limiter: Arc::new(RateLimiter::new(rate_limit_config)),
             connection_tracker: Arc::new(ConnectionTracker::new(connection_tracker_config)),
+            authorizer,
+            password_verifier: Arc::new(RejectAllPasswords),
+            audit,
+            metrics,
+        }
+    }
+
+    /// Copy the sizes of the audit queue and the rate-limiter and
+    /// connection-tracker tables into the metrics
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    async fn sample_gauges(&self) {
+        let (rate_limiter_entries, _) = self.rate_limiter.get_stats().await;
+        let (_, tracked_connections) = self.connection_tracker.get_stats().await;
+        self.metrics.set_table_sizes(
+            self.audit.queued(),
+            rate_limiter_entries,
+            tracked_connections,
+        );
+    }
+
+    /// Current gauges followed by one line per live session
+    ///
+    /// A session busy with a request is reported as such rather than
+    /// waited for.
+    ///
+    /// NIST 800-53: SI-4 (System Monitoring)
+    async fn dump_state(&self) -> String {
+        self.sample_gauges().await;
+        let snapshot = self.metrics.snapshot();
+
+        let mut out = String::new();
+        for (name, value) in [
+            ("live_sessions", snapshot.live_sessions),
+            ("open_file_handles", snapshot.open_file_handles),
+            ("open_dir_handles", snapshot.open_dir_handles),
+            ("read_ahead_bytes", snapshot.read_ahead_bytes),
+            ("queued_audit_events", snapshot.queued_audit_events),
+            ("rate_limiter_entries", snapshot.rate_limiter_entries),
+            ("tracked_connections", snapshot.tracked_connections),
+        ] {
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        let mut sessions: Vec<(String, Arc<Mutex<SftpSession>>)> = self
+            .sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter_map(|(id, session)| Some((id.clone(), session.upgrade()?)))
+            .collect();
+        sessions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (id, session) in sessions {
+            let Ok(session) = session.try_lock() else {
+                let _ = writeln!(out, "session {} busy", id);
+                continue;
+            };
+            let (files, dirs) = session.handle_counts();
+            let _ = writeln!(
+                out,
+                "session {} user={} files={} dirs={} read_ahead_bytes={}",
+                id,
+                session.session_info.username.as_deref().unwrap_or("-"),
+                files,
+                dirs,
+                session.read_ahead_bytes,
+            );
         }
+        out
     }
 }
 
@@ -154,7 +477,21 @@ impl SshServer for SftpHandler {
     type Handler = SftpSessionHandler;
 
     fn new_client(&mut self, peer_addr: Option<std::net::SocketAddr>) -> Self::Handler {
-        let session = SftpSession::new(self.config.clone());
+        let session = SftpSession::with_authorizer(
+            self.config.clone(),
+            self.authorizer.clone(),
+            self.audit.clone(),
+            self.metrics.clone(),
+            self.global_bandwidth.clone(),
+            peer_addr.map(|addr| addr.ip()),
+        );
+        let session_id = session.session_info.session_id.clone();
+        let session = Arc::new(Mutex::new(session));
+        self.metrics.record_connection();
+        self.sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(session_id.clone(), Arc::downgrade(&session));
 
         // NIST 800-53: AC-2 (Account Management)
         // Load authorized keys for this connection
@@ -167,13 +504,26 @@ impl SshServer for SftpHandler {
         }
 
         SftpSessionHandler {
-            session: Arc::new(Mutex::new(session)),
+            session,
+            session_id,
+            sessions: self.sessions.clone(),
             authorized_keys: Arc::new(Mutex::new(auth_keys)),
+            password_verifier: self.password_verifier.clone(),
+            password_auth: self.config.password_auth,
             rate_limiter: self.rate_limiter.clone(),
             connection_tracker: self.connection_tracker.clone(),
+            account_policy: self.account_policy.clone(),
+            audit: self.audit.clone(),
+            metrics: self.metrics.clone(),
             peer_addr: peer_addr.map(|addr| addr.ip()),
-            username: Arc::new(Mutex::new(None)),
-            connection_id: Arc::new(Mutex::new(None)),
+            write_failure: self.config.channel_write_failure,
+            channel_broken: false,
+            packets: PacketReader::new(self.config.max_packet_size as usize),
+            username: None,
+            connection_id: None,
+            shutdown: None,
+            termination_task: None,
+            reap_task: None,
         }
     }
 }
@@ -185,12 +535,285 @@ impl SshServer for SftpHandler {
 /// Implementation: Manages per-connection authentication and SFTP session with rate limiting and connection tracking
 struct SftpSessionHandler {
     session: Arc<Mutex<SftpSession>>,
+    /// Key of this connection in `sessions`
+    session_id: String,
+    sessions: SessionRegistry,
     authorized_keys: Arc<Mutex<AuthorizedKeys>>,
+    password_verifier: Arc<dyn PasswordVerifier>,
+    /// Whether password and keyboard-interactive logins are offered
+    password_auth: bool,
     rate_limiter: Arc<RateLimiter>,
     connection_tracker: Arc<ConnectionTracker>,
+    account_policy: Arc<AccountPolicy>,
+    audit: AuditChannel,
+    /// Connection and authentication counters (NIST 800-53: SI-4)
+    metrics: Metrics,
     peer_addr: Option<IpAddr>,
-    username: Arc<Mutex<Option<String>>>,
-    connection_id: Arc<Mutex<Option<usize>>>,
+    /// What a failed response write does to the connection (NIST 800-53: SI-11)
+    write_failure: ChannelWriteFailure,
+    /// Set once a response could not be written; nothing more is sent or processed
+    channel_broken: bool,
+    /// Splits channel data into SFTP packets
+    packets: PacketReader,
+    /// Set on authentication, before any other await, so a connection dropped
+    /// mid-authentication still frees its tracker slot
+    username: Option<String>,
+    connection_id: Option<usize>,
+    /// Cancelled by the connection tracker when the session is reaped
+    shutdown: Option<CancellationToken>,
+    /// Ends the session when the user's access window closes
+    termination_task: Option<tokio::task::JoinHandle<()>>,
+    /// Ends the session when it goes idle or the connection tracker reaps it
+    reap_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl SftpSessionHandler {
+    /// Methods a client whose credentials were refused may try next
+    fn remaining_methods(&self) -> MethodSet {
+        let mut methods = MethodSet::empty();
+        methods.push(MethodKind::PublicKey);
+        if self.password_auth {
+            methods.push(MethodKind::Password);
+            methods.push(MethodKind::KeyboardInteractive);
+        }
+        methods
+    }
+
+    /// Whether the peer is locked out or out of attempts for this window
+    ///
+    /// NIST 800-53: AC-7 (Unsuccessful Logon Attempts)
+    async fn rate_limited(&self, user: &str) -> bool {
+        if let Some(ip) = self.peer_addr
+            && !self.rate_limiter.check_allowed(ip).await
+        {
+            warn!(
+                "Rate limit exceeded for IP {}, rejecting authentication for user: {}",
+                ip, user
+            );
+            self.metrics.record_rate_limited();
+            // NIST 800-53: AU-2 (Audit Events) - Log rate limited attempt
+            return true;
+        }
+        false
+    }
+
+    /// Check a password with the verifier and finish the login
+    ///
+    /// NIST 800-53: IA-2 (Identification and Authentication), AC-7 (Unsuccessful Logon Attempts)
+    async fn password_login(&mut self, user: &str, password: &str, method: &str) -> Auth {
+        self.metrics.record_auth_attempt();
+
+        // NIST 800-53: AC-7 - Check rate limit before attempting authentication
+        if self.rate_limited(user).await {
+            return Auth::Reject {
+                proceed_with_methods: None,
+                partial_success: false,
+            };
+        }
+
+        if self.password_verifier.verify(user, password).await {
+            self.accept_login(user, method).await
+        } else {
+            self.reject_login(user, method, "bad_password").await
+        }
+    }
+
+    /// Refuse credentials that did not verify, counting the failure against the peer
+    ///
+    /// NIST 800-53: AC-7 (Unsuccessful Logon Attempts), AU-2 (Audit Events)
+    async fn reject_login(&self, user: &str, method: &str, reason: &str) -> Auth {
+        warn!("{} authentication failed for user: {}", method, user);
+        // NIST 800-53: AU-2 (Audit Events) - Log failed authentication
+        self.audit_auth_failure(user, reason);
+        self.metrics.record_auth_failure();
+        // NIST 800-53: AC-7 (Unsuccessful Logon Attempts) - Track failed attempts
+
+        if let Some(ip) = self.peer_addr {
+            self.rate_limiter.record_failure(ip).await;
+        }
+
+        Auth::Reject {
+            proceed_with_methods: Some(self.remaining_methods()),
+            partial_success: false,
+        }
+    }
+
+    /// Finish a login whose credentials verified: apply the account policy
+    /// and connection limits, then register the session
+    ///
+    /// NIST 800-53: AC-2(3) (Disable Accounts), AC-10 (Concurrent Session Control), AU-2 (Audit Events)
+    async fn accept_login(&mut self, user: &str, method: &str) -> Auth {
+        // NIST 800-53: AC-2(3) - Account expiry and access windows, checked after
+        // the credentials so the audit trail separates them from bad ones
+        let decision = self.account_policy.check(user);
+        if let Some(reason) = decision.reason() {
+            match decision {
+                AccessDecision::Expired { expired_at } => warn!(
+                    "User '{}' presented valid credentials but the account expired at {}",
+                    user, expired_at
+                ),
+                _ => warn!(
+                    "User '{}' presented valid credentials outside their access window",
+                    user
+                ),
+            }
+            self.audit_auth_failure(user, reason);
+            self.metrics.record_auth_failure();
+            return Auth::Reject {
+                proceed_with_methods: None,
+                partial_success: false,
+            };
+        }
+
+        // NIST 800-53: AC-3 - The session is confined to the user's own root
+        let root = match self.user_root(user).await {
+            Ok(root) => root,
+            Err(reason) => {
+                self.audit_auth_failure(user, reason);
+                self.metrics.record_auth_failure();
+                return Auth::Reject {
+                    proceed_with_methods: None,
+                    partial_success: false,
+                };
+            }
+        };
+
+        // NIST 800-53: AC-10 - Check concurrent session limit before accepting
+        if !self.connection_tracker.can_connect(user).await {
+            warn!(
+                "User '{}' exceeded maximum concurrent connections, rejecting authentication",
+                user
+            );
+            self.metrics.record_auth_failure();
+            self.metrics.record_rejected_connection();
+            // NIST 800-53: AU-2 (Audit Events) - Log connection limit rejection
+            return Auth::Reject {
+                proceed_with_methods: None, // Reject due to connection limit
+                partial_success: false,
+            };
+        }
+
+        info!("{} authentication succeeded for user: {}", method, user);
+        // NIST 800-53: AU-2 (Audit Events) - Log successful authentication
+
+        // NIST 800-53: AC-7 - Clear failed attempts on success
+        if let Some(ip) = self.peer_addr {
+            self.rate_limiter.record_success(ip).await;
+        }
+
+        // NIST 800-53: AC-10 - Register connection for user
+        if let Some(registration) = self
+            .connection_tracker
+            .register_session(user.to_string())
+            .await
+        {
+            self.username = Some(user.to_string());
+            self.connection_id = Some(registration.connection_id);
+            self.shutdown = Some(registration.shutdown);
+            self.session.lock().await.set_user(user.to_string(), root);
+            // NIST 800-53: AU-2 (Audit Events) - Record the accepted login
+            self.audit.record(AuditEvent::AuthAttempt {
+                client_ip: self.peer_addr,
+                username: user.to_string(),
+                timestamp: chrono::Utc::now(),
+                success: true,
+                reason: None,
+            });
+            self.metrics.record_auth_success();
+
+            Auth::Accept
+        } else {
+            warn!(
+                "Failed to register connection for user '{}' (connection limit reached)",
+                user
+            );
+            self.metrics.record_auth_failure();
+            self.metrics.record_rejected_connection();
+            Auth::Reject {
+                proceed_with_methods: None,
+                partial_success: false,
+            }
+        }
+    }
+
+    /// Root directory for `user`, created first when `create_user_root` is set
+    ///
+    /// Returns the audit reason on failure: a username that cannot be
+    /// placed in `user_root_template`, or a root that is not a directory.
+    ///
+    /// NIST 800-53: AC-3 (Access Enforcement), SI-10 (Information Input Validation)
+    async fn user_root(&self, user: &str) -> std::result::Result<PathBuf, &'static str> {
+        let config = self.session.lock().await.config.clone();
+        let root = config.root_for(Some(user)).map_err(|e| {
+            warn!("Rejecting login: {}", e);
+            "invalid_username"
+        })?;
+
+        if config.create_user_root && !fs::try_exists(&root).await.unwrap_or(false) {
+            let mut builder = fs::DirBuilder::new();
+            builder.recursive(true);
+            // NIST 800-53: AC-6 - Mode from the configured file mode policy
+            #[cfg(unix)]
+            builder.mode(config.file_mode_policy.creation_mode(None, 0o700));
+            match builder.create(&root).await {
+                Ok(()) => info!("Created root directory {:?} for user '{}'", root, user),
+                Err(e) => warn!(
+                    "Failed to create root directory {:?} for user '{}': {}",
+                    root, user, e
+                ),
+            }
+        }
+
+        if fs::metadata(&root).await.is_ok_and(|metadata| metadata.is_dir()) {
+            Ok(root)
+        } else {
+            warn!(
+                "Rejecting login for user '{}': root directory {:?} does not exist",
+                user, root
+            );
+            Err("user_root_unavailable")
+        }
+    }
+
+    /// Record a rejected authentication with its reason
+    ///
+    /// NIST 800-53: AU-2 (Audit Events), AU-3 (Content of Audit Records)
+    fn audit_auth_failure(&self, user: &str, reason: &str) {
+        self.audit.record(AuditEvent::AuthAttempt {
+            client_ip: self.peer_addr,
+            username: user.to_string(),
+            timestamp: chrono::Utc::now(),
+            success: false,
+            reason: Some(reason.to_string()),
+        });
+    }
+}
+
+impl Drop for SftpSessionHandler {
+    fn drop(&mut self) {
+        for task in [self.termination_task.take(), self.reap_task.take()]
+            .into_iter()
+            .flatten()
+        {
+            task.abort();
+        }
+
+        self.sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.session_id);
+        self.metrics.record_connection_close();
+
+        // NIST 800-53: AC-10 - Free the user's connection slot
+        if let (Some(user), Some(conn_id)) = (self.username.take(), self.connection_id) {
+            let tracker = self.connection_tracker.clone();
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move {
+                    tracker.unregister_connection(&user, conn_id).await;
+                });
+            }
+        }
+    }
 }
 
 impl Handler for SftpSessionHandler {
@@ -199,11 +822,62 @@ impl Handler for SftpSessionHandler {
     async fn channel_open_session(
         &mut self,
         channel: Channel<Msg>,
-        _session: &mut Session,
+        session: &mut Session,
     ) -> Result<bool> {
         info!("Channel opened for session");
-        let mut session = self.session.lock().await;
-        session.channel = Some(channel);
+
+        // NIST 800-53: AC-12 - End the session when the access window closes
+        if self.termination_task.is_none() {
+            if let Some(user) = self.username.clone() {
+                let handle = session.handle();
+                self.termination_task =
+                    self.account_policy.schedule_termination(&user, move || async move {
+                        let _ = handle
+                            .disconnect(
+                                russh::Disconnect::ByApplication,
+                                "Access window closed".to_string(),
+                                String::new(),
+                            )
+                            .await;
+                    });
+            }
+        }
+        // NIST 800-53: AC-12 - Close the handles of a session left idle, then
+        // end it; end it too when the connection tracker reaps it
+        if self.reap_task.is_none()
+            && let (Some(user), Some(connection_id), Some(shutdown)) =
+                (self.username.clone(), self.connection_id, self.shutdown.clone())
+        {
+            let handle = session.handle();
+            let channel_id = channel.id();
+            let sftp_session = self.session.clone();
+            let tracker = self.connection_tracker.clone();
+            let idle_timeout = Duration::from_secs(
+                self.session.lock().await.config.session_idle_timeout_secs,
+            );
+            self.reap_task = Some(tokio::spawn(async move {
+                let expiry =
+                    wait_for_expiry(&tracker, &user, connection_id, &shutdown, idle_timeout)
+                        .await;
+                let reason = if let Some(idle_for) = expiry {
+                    sftp_session.lock().await.terminate_idle(idle_for, idle_timeout);
+                    let _ = handle.close(channel_id).await;
+                    "Session idle timeout"
+                } else {
+                    "Session timed out"
+                };
+                let _ = handle
+                    .disconnect(
+                        russh::Disconnect::ByApplication,
+                        reason.to_string(),
+                        String::new(),
+                    )
+                    .await;
+            }));
+        }
+
+        let mut sftp_session = self.session.lock().await;
+        sftp_session.channel = Some(channel);
         Ok(true)
     }
 
@@ -236,101 +910,66 @@ impl Handler for SftpSessionHandler {
         user: &str,
         public_key: &PublicKey,
     ) -> Result<Auth> {
+        self.metrics.record_auth_attempt();
+
         // NIST 800-53: AC-7 - Check rate limit before attempting authentication
-        if let Some(ip) = self.peer_addr {
-            if !self.rate_limiter.check_allowed(ip).await {
-                warn!(
-                    "Rate limit exceeded for IP {}, rejecting authentication for user: {}",
-                    ip, user
-                );
-                // NIST 800-53: AU-2 (Audit Events) - Log rate limited attempt
-                return Ok(Auth::Reject {
-                    proceed_with_methods: None, // No other methods allowed when rate limited
-                    partial_success: false,
-                });
-            }
+        if self.rate_limited(user).await {
+            return Ok(Auth::Reject {
+                proceed_with_methods: None, // No other methods allowed when rate limited
+                partial_success: false,
+            });
         }
 
         // NIST 800-53: IA-2 - Verify identity through public key cryptography
-        let auth_keys = self.authorized_keys.lock().await;
-
-        if auth_keys.is_authorized(public_key) {
-            // NIST 800-53: AC-10 - Check concurrent session limit before accepting
-            if !self.connection_tracker.can_connect(user).await {
-                warn!(
-                    "User '{}' exceeded maximum concurrent connections, rejecting authentication",
-                    user
-                );
-                // NIST 800-53: AU-2 (Audit Events) - Log connection limit rejection
-                return Ok(Auth::Reject {
-                    proceed_with_methods: None, // Reject due to connection limit
-                    partial_success: false,
-                });
-            }
-
-            info!("Public key authentication succeeded for user: {}", user);
-            // NIST 800-53: AU-2 (Audit Events) - Log successful authentication
-
-            // NIST 800-53: AC-7 - Clear failed attempts on success
-            if let Some(ip) = self.peer_addr {
-                self.rate_limiter.record_success(ip).await;
-            }
-
-            // NIST 800-53: AC-10 - Register connection for user
-            if let Some(conn_id) = self
-                .connection_tracker
-                .register_connection(user.to_string())
-                .await
-            {
-                let mut username = self.username.lock().await;
-                *username = Some(user.to_string());
-
-                let mut connection_id = self.connection_id.lock().await;
-                *connection_id = Some(conn_id);
-
-                Ok(Auth::Accept)
-            } else {
-                warn!(
-                    "Failed to register connection for user '{}' (connection limit reached)",
-                    user
-                );
-                Ok(Auth::Reject {
-                    proceed_with_methods: None,
-                    partial_success: false,
-                })
-            }
+        let authorized = self.authorized_keys.lock().await.is_authorized(public_key);
+        if authorized {
+            Ok(self.accept_login(user, "Public key").await)
         } else {
-            warn!("Public key authentication failed for user: {}", user);
-            // NIST 800-53: AU-2 (Audit Events) - Log failed authentication
-            // NIST 800-53: AC-7 (Unsuccessful Logon Attempts) - Track failed attempts
-
-            if let Some(ip) = self.peer_addr {
-                self.rate_limiter.record_failure(ip).await;
-            }
+            Ok(self.reject_login(user, "Public key", "key_not_authorized").await)
+        }
+    }
 
-            Ok(Auth::Reject {
-                proceed_with_methods: Some({
-                    let mut methods = MethodSet::empty();
-                    methods.push(MethodKind::PublicKey);
-                    methods
-                }),
+    // NIST 800-53: IA-2 (Identification and Authentication), IA-5(1) (Password-Based Authentication)
+    // Implementation: Checks the password with the configured verifier, under the same
+    // rate limiting and connection limits as public keys
+    async fn auth_password(&mut self, user: &str, password: &str) -> Result<Auth> {
+        if !self.password_auth {
+            warn!("Password authentication disabled, rejecting user: {}", user);
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(self.remaining_methods()),
                 partial_success: false,
-            })
+            });
         }
+        Ok(self.password_login(user, password, "Password").await)
     }
 
-    async fn auth_password(&mut self, _user: &str, _password: &str) -> Result<Auth> {
-        // For demonstration, reject password auth
-        // In production, implement proper password verification
-        warn!("Password authentication rejected");
-        Ok(Auth::Reject {
-            proceed_with_methods: Some({
-                    let mut methods = MethodSet::empty();
-                    methods.push(MethodKind::PublicKey);
-                    methods
-                }),
-            partial_success: false,
-        })
+    // NIST 800-53: IA-2 (Identification and Authentication)
+    // Implementation: Prompts once for the password, then checks it as auth_password does
+    async fn auth_keyboard_interactive<'a>(
+        &'a mut self,
+        user: &str,
+        _submethods: &str,
+        response: Option<russh::server::Response<'a>>,
+    ) -> Result<Auth> {
+        if !self.password_auth {
+            warn!("Keyboard-interactive authentication disabled, rejecting user: {}", user);
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(self.remaining_methods()),
+                partial_success: false,
+            });
+        }
+        let Some(mut answers) = response else {
+            return Ok(Auth::Partial {
+                name: Cow::Borrowed(""),
+                instructions: Cow::Borrowed(""),
+                prompts: Cow::Owned(vec![(Cow::Borrowed("Password: "), false)]),
+            });
+        };
+        let password = answers
+            .next()
+            .and_then(|answer| String::from_utf8(answer.to_vec()).ok())
+            .unwrap_or_default();
+        Ok(self.password_login(user, &password, "Keyboard-interactive").await)
     }
 
     /// Handle SFTP data
@@ -344,37 +983,55 @@ impl Handler for SftpSessionHandler {
         data: &[u8],
         session: &mut Session,
     ) -> Result<()> {
-        let mut sess = self.session.lock().await;
+        // NIST 800-53: SI-11 - A torn response leaves nothing to answer on
+        if self.channel_broken {
+            debug!("Ignoring SFTP data on channel closed after a failed write");
+            return Ok(());
+        }
 
-        // NIST 800-53: SI-11 - Handle packet processing errors gracefully
-        let response = match sess.handle_sftp_packet(data).await {
-            Ok(resp) => resp,
-            Err(e) => {
-                // NIST 800-53: AU-2 - Log error
-                error!("SFTP packet handling error: {}", e);
+        // NIST 800-53: AC-12 - Reset the idle timer
+        if let (Some(user), Some(conn_id)) = (&self.username, self.connection_id) {
+            self.connection_tracker.record_activity(user, conn_id).await;
+        }
 
-                // NIST 800-53: AU-2 - Log security events
-                if e.is_security_event() {
-                    warn!("Security event during SFTP operation: {}", e);
+        // SSH may split a packet across messages or put several in one
+        self.packets.push(data);
+        while let Some(frame) = self.packets.next_frame() {
+            // NIST 800-53: SI-11 - Handle packet processing errors gracefully
+            let result = match frame {
+                Frame::Packet(packet) => process_packet(&self.session, &packet).await,
+                Frame::Oversized { len, head } => {
+                    self.session.lock().await.reject_oversized(len, &head)
                 }
+            };
+            let response = match result {
+                Ok(resp) => resp,
+                Err(e) => {
+                    // NIST 800-53: AU-2 - Log error
+                    error!("SFTP packet handling error: {}", e);
 
-                // Try to extract request ID for error response
-                // If we can't send an error response, the error will propagate
-                return Err(e);
-            }
-        };
+                    // NIST 800-53: AU-2 - Log security events
+                    if e.is_security_event() {
+                        warn!("Security event during SFTP operation: {}", e);
+                    }
+
+                    // Try to extract request ID for error response
+                    // If we can't send an error response, the error will propagate
+                    return Err(e);
+                }
+            };
 
-        if !response.is_empty() {
             // NIST 800-53: SC-8, SI-11 - Handle channel write errors (connection drops)
-            if let Err(e) = session.data(channel, CryptoVec::from_slice(&response)) {
-                error!("Failed to send response, channel may be closed: {}", e);
-                return Err(Error::channel_closed(format!(
-                    "Failed to send response: {}",
-                    e
-                )));
+            send_response(
+                &mut SessionChannel { session, channel },
+                &mut self.channel_broken,
+                self.write_failure,
+                &response,
+            )?;
+            if self.channel_broken {
+                break;
             }
         }
-
         Ok(())
     }
 
@@ -415,40 +1072,250 @@ struct SftpSession {
     config: Arc<Config>,
     channel: Option<Channel<Msg>>,
     handles: HashMap<Vec<u8>, FileHandle>,
+    /// Sequential read-ahead state, keyed by file handle
+    read_ahead: HashMap<Vec<u8>, ReadAhead>,
+    /// Bytes moved through each file handle, audited when it closes
+    handle_bytes: HashMap<Vec<u8>, HandleBytes>,
     next_handle_id: u32,
     initialized: bool,
+    /// Protocol version agreed in SSH_FXP_INIT; selects the attribute layout
+    version: u32,
+    /// Identity passed to the authorizer; the username is set on authentication
+    session_info: SessionInfo,
+    /// Per-operation authorization (NIST 800-53: AC-3)
+    authorization: AuthorizationGate,
+    /// Queue for this session's audit events (NIST 800-53: AU-12)
+    audit: AuditChannel,
+    /// Per-session and global bandwidth limits (NIST 800-53: SC-5)
+    throttle: Throttle,
+    /// Wait owed by the last packet, served after the session lock is released
+    throttle_delay: Duration,
+    /// Resource gauges (NIST 800-53: SI-4)
+    metrics: Metrics,
+    /// Read-ahead bytes last reported to `metrics`
+    read_ahead_bytes: usize,
+    /// Directory paths are confined to; `root_dir` until a login sets the user's own
+    root_dir: PathBuf,
 }
 
 impl SftpSession {
+    /// Session enforcing only the static per-user policy
+    #[cfg(test)]
     fn new(config: Arc<Config>) -> Self {
+        let authorizer = Arc::new(StaticAuthorizer::new(config.clone()));
+        let audit = AuditChannel::spawn(&config.logging.audit_channel, TracingSink)
+            .expect("Failed to start audit writer");
+        let global_bandwidth = config
+            .global_bytes_per_sec()
+            .map(|rate| Arc::new(SharedBucket::new(rate)));
+        Self::with_authorizer(config, authorizer, audit, Metrics::new(), global_bandwidth, None)
+    }
+
+    fn with_authorizer(
+        config: Arc<Config>,
+        authorizer: Arc<dyn Authorizer>,
+        audit: AuditChannel,
+        metrics: Metrics,
+        global_bandwidth: Option<Arc<SharedBucket>>,
+        client_ip: Option<IpAddr>,
+    ) -> Self {
+        metrics.record_session_open();
         Self {
+            authorization: AuthorizationGate::new(authorizer, &config.authorization),
+            audit,
+            throttle: Throttle::new(config.max_bytes_per_sec_per_session, global_bandwidth),
+            throttle_delay: Duration::ZERO,
+            session_info: SessionInfo::new(uuid::Uuid::new_v4().to_string(), client_ip),
+            root_dir: config.root_dir.clone(),
             config,
             channel: None,
             handles: HashMap::new(),
+            read_ahead: HashMap::new(),
+            handle_bytes: HashMap::new(),
             next_handle_id: 0,
             initialized: false,
+            version: SFTP_VERSION,
+            metrics,
+            read_ahead_bytes: 0,
         }
     }
-}
 
-impl Drop for SftpSession {
-    /// NIST 800-53: SI-11, AC-12 - Clean up all file handles on session end
-    /// STIG: V-222601
-    /// Implementation: Ensures all file handles are closed when session terminates
-    fn drop(&mut self) {
-        let handle_count = self.handles.len();
-        if handle_count > 0 {
-            info!("Cleaning up {} open file handles on session end", handle_count);
-            self.handles.clear();
+    /// Open (file, directory) handles
+    fn handle_counts(&self) -> (usize, usize) {
+        let dirs = self.handles.values().filter(|handle| handle.is_dir()).count();
+        (self.handles.len() - dirs, dirs)
+    }
+
+    /// Report the current read-ahead footprint to the metrics
+    fn update_read_ahead_gauge(&mut self) {
+        let bytes = self.read_ahead.values().map(ReadAhead::buffered_bytes).sum();
+        self.metrics.record_read_ahead_bytes(self.read_ahead_bytes, bytes);
+        self.read_ahead_bytes = bytes;
+    }
+
+    /// Close every open handle and drop read-ahead buffers
+    ///
+    /// Returns the number of handles closed.
+    fn release_handles(&mut self) -> usize {
+        let handles: Vec<_> = self.handles.drain().collect();
+        let handle_count = handles.len();
+        for (id, handle) in handles {
+            self.close_handle(&id, &handle);
         }
+        self.read_ahead.clear();
+        self.update_read_ahead_gauge();
+        handle_count
     }
-}
 
-impl SftpSession {
-    /// Handle incoming SFTP packet
+    /// Account for a handle removed from `handles` and audit its close
     ///
-    /// NIST 800-53: SI-11 (Error Handling)
-    /// STIG: V-222566
+    /// NIST 800-53: AU-2 (Audit Events), AU-3 (Content of Audit Records)
+    fn close_handle(&mut self, id: &[u8], handle: &FileHandle) {
+        self.metrics.record_handle_close(handle.is_dir());
+        let bytes = self.handle_bytes.remove(id).unwrap_or_default();
+        let FileHandle::File(_, path) = handle else {
+            return;
+        };
+        if bytes.read > 0 {
+            self.audit_file("READ", path, Some(bytes.read), None);
+        }
+        if bytes.written > 0 {
+            self.audit_file("WRITE", path, Some(bytes.written), None);
+        }
+        self.audit_file("CLOSE", path, None, None);
+    }
+
+    /// Record a file operation by this session's user
+    ///
+    /// The path is recorded as the client sees it, like authorization denials.
+    ///
+    /// NIST 800-53: AU-2 (Audit Events), AU-3 (Content of Audit Records)
+    fn audit_file(&self, operation: &str, path: &Path, bytes: Option<u64>, error: Option<&Error>) {
+        self.audit.record(AuditEvent::file_operation(
+            self.session_info.client_ip,
+            self.session_info.username.clone(),
+            operation,
+            self.client_path(path).display().to_string(),
+            bytes,
+            error.map(ToString::to_string),
+        ));
+    }
+
+    /// Record a rename by this session's user as `old -> new`
+    fn audit_rename(&self, old: &Path, new: &Path, error: Option<&Error>) {
+        self.audit.record(AuditEvent::file_operation(
+            self.session_info.client_ip,
+            self.session_info.username.clone(),
+            "RENAME",
+            format!(
+                "{} -> {}",
+                self.client_path(old).display(),
+                self.client_path(new).display()
+            ),
+            None,
+            error.map(ToString::to_string),
+        ));
+    }
+
+    /// Record a directory operation by this session's user
+    fn audit_directory(&self, operation: &str, path: &Path, error: Option<&Error>) {
+        self.audit.record(AuditEvent::directory_operation(
+            self.session_info.client_ip,
+            self.session_info.username.clone(),
+            operation,
+            self.client_path(path).display().to_string(),
+            error.map(ToString::to_string),
+        ));
+    }
+
+    /// Release the handles of a session that went idle and record why
+    ///
+    /// NIST 800-53: AC-12 (Session Termination), AU-2 (Audit Events)
+    fn terminate_idle(&mut self, idle_for: Duration, idle_timeout: Duration) {
+        let handle_count = self.release_handles();
+        warn!(
+            "Terminating SFTP session for {:?}: idle for {}s (limit {}s), {} handle(s) closed",
+            self.session_info.username,
+            idle_for.as_secs(),
+            idle_timeout.as_secs(),
+            handle_count
+        );
+        self.audit.record(AuditEvent::security(
+            self.session_info.client_ip,
+            self.session_info.username.clone(),
+            "session_idle_timeout",
+            format!(
+                "idle_secs={} limit_secs={} handles_closed={}",
+                idle_for.as_secs(),
+                idle_timeout.as_secs(),
+                handle_count
+            ),
+        ));
+    }
+}
+
+/// Wait until a session goes `idle_timeout` without activity or the
+/// connection tracker reaps it
+///
+/// Idleness is read from the tracker's activity timestamp, so the session
+/// acts at its own deadline instead of the reaper's next scan. Returns how
+/// long the session had been idle, or `None` if it was reaped for another
+/// reason. A zero `idle_timeout` only waits for the reaper.
+///
+/// NIST 800-53: AC-12 (Session Termination)
+/// STIG: V-222601
+async fn wait_for_expiry(
+    tracker: &ConnectionTracker,
+    username: &str,
+    connection_id: usize,
+    shutdown: &CancellationToken,
+    idle_timeout: Duration,
+) -> Option<Duration> {
+    loop {
+        let last_activity = if idle_timeout.is_zero() {
+            None
+        } else {
+            tracker.last_activity(username, connection_id).await
+        };
+        let Some(last_activity) = last_activity else {
+            shutdown.cancelled().await;
+            return None;
+        };
+
+        let deadline = last_activity + idle_timeout;
+        if tokio::time::Instant::now() >= deadline {
+            return Some(last_activity.elapsed());
+        }
+        tokio::select! {
+            // Activity since may have moved the deadline; check again
+            () = tokio::time::sleep_until(deadline) => {}
+            // The reaper may have seen the same idle deadline first
+            () = shutdown.cancelled() => {
+                return (tokio::time::Instant::now() >= deadline)
+                    .then(|| last_activity.elapsed());
+            }
+        }
+    }
+}
+
+impl Drop for SftpSession {
+    /// NIST 800-53: SI-11, AC-12 - Clean up all file handles on session end
+    /// STIG: V-222601
+    /// Implementation: Ensures all file handles are closed when session terminates
+    fn drop(&mut self) {
+        let handle_count = self.release_handles();
+        if handle_count > 0 {
+            info!("Cleaned up {} open file handles on session end", handle_count);
+        }
+        self.metrics.record_session_close();
+    }
+}
+
+impl SftpSession {
+    /// Handle incoming SFTP packet
+    ///
+    /// NIST 800-53: SI-11 (Error Handling)
+    /// STIG: V-222566
     /// Implementation: Robust error handling for all SFTP operations
     async fn handle_sftp_packet(&mut self, data: &[u8]) -> Result<Vec<u8>> {
         if data.is_empty() {
@@ -456,6 +1323,11 @@ impl SftpSession {
             return Err(Error::Protocol("Empty packet".into()));
         }
 
+        // NIST 800-53: SC-5 - Refuse oversized packets before parsing them
+        if data.len() > self.config.max_packet_size as usize {
+            return self.reject_oversized(data.len(), data);
+        }
+
         let mut buf = &data[..];
         let msg_type = MessageType::try_from(data[0])?;
         buf = &buf[1..];
@@ -468,13 +1340,18 @@ impl SftpSession {
             return Err(Error::Protocol("Session not initialized".into()));
         }
 
-        match msg_type {
+        // NIST 800-53: SI-4 - Count every request, whatever its outcome
+        self.metrics.record_request(msg_type);
+        let started = tokio::time::Instant::now();
+
+        let result = match msg_type {
             MessageType::Init => self.handle_init(&mut buf).await,
             MessageType::Open => self.handle_open(&mut buf).await,
             MessageType::Close => self.handle_close(&mut buf).await,
             MessageType::Read => self.handle_read(&mut buf).await,
             MessageType::Write => self.handle_write(&mut buf).await,
-            MessageType::Stat | MessageType::Lstat => self.handle_stat(&mut buf).await,
+            MessageType::Stat => self.handle_stat(&mut buf, true).await,
+            MessageType::Lstat => self.handle_stat(&mut buf, false).await,
             MessageType::Fstat => self.handle_fstat(&mut buf).await,
             MessageType::Setstat => self.handle_setstat(&mut buf).await,
             MessageType::Fsetstat => self.handle_fsetstat(&mut buf).await,
@@ -487,6 +1364,7 @@ impl SftpSession {
             MessageType::Rename => self.handle_rename(&mut buf).await,
             MessageType::Readlink => self.handle_readlink(&mut buf).await,
             MessageType::Symlink => self.handle_symlink(&mut buf).await,
+            MessageType::Extended => self.handle_extended(&mut buf).await,
             _ => {
                 warn!("Unimplemented message type: {:?}", msg_type);
                 Err(Error::NotSupported(format!(
@@ -494,7 +1372,11 @@ impl SftpSession {
                     msg_type
                 )))
             }
-        }
+        };
+
+        self.metrics
+            .record_response(msg_type, started.elapsed(), is_error_reply(&result));
+        result
     }
 
     async fn handle_init(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
@@ -504,16 +1386,397 @@ impl SftpSession {
             return Err(Error::Protocol("Invalid init packet".into()));
         };
 
-        info!("SFTP Init - Client version: {}", version);
+        // Reply with min(client, MAX_SFTP_VERSION); every later attribute
+        // block and NAME entry uses the agreed version's layout
+        self.version = negotiate_version(version);
+        info!(
+            "SFTP Init - Client version: {}, negotiated version: {}",
+            version, self.version
+        );
         self.initialized = true;
 
         let mut response = BytesMut::new();
         response.put_u8(MessageType::Version as u8);
-        response.put_u32(SFTP_VERSION);
+        response.put_u32(self.version);
+
+        // Advertise extensions so clients know which EXTENDED requests to use
+        for (name, data) in SUPPORTED_EXTENSIONS {
+            codec::put_string(&mut response, name);
+            codec::put_string(&mut response, data);
+        }
+
+        Ok(response.to_vec())
+    }
+
+    /// Dispatch an SSH_FXP_EXTENDED request by extension name
+    ///
+    /// Unknown extensions get an OP_UNSUPPORTED status instead of ending the session.
+    ///
+    /// NIST 800-53: SI-11 (Error Handling), CM-7 (Least Functionality)
+    /// STIG: V-222566
+    /// Implementation: Only the extensions listed in SUPPORTED_EXTENSIONS are served
+    async fn handle_extended(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
+        let request_id = self.read_u32(buf)?;
+        let extension = codec::get_string(buf)?;
+
+        debug!("Extended request: {}", extension);
+
+        match extension.as_str() {
+            EXT_HARDLINK => self.handle_hardlink(request_id, buf).await,
+            EXT_FSYNC => self.handle_fsync(request_id, buf).await,
+            EXT_POSIX_RENAME => self.handle_posix_rename(request_id, buf).await,
+            EXT_LIMITS => self.handle_limits(request_id),
+            EXT_STAT_FOR_RESUME => self.handle_stat_for_resume(request_id, buf).await,
+            EXT_SERVER_INFO => self.handle_server_info(request_id),
+            _ => {
+                warn!("Unsupported extended request: {}", extension);
+                self.send_status(
+                    request_id,
+                    StatusCode::OpUnsupported,
+                    "Extension not supported",
+                )
+            }
+        }
+    }
+
+    /// Create a hard link (hardlink@openssh.com)
+    ///
+    /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
+    /// STIG: V-222566, V-222596
+    /// Implementation: Both paths are confined to the root directory
+    async fn handle_hardlink(&mut self, request_id: u32, buf: &mut &[u8]) -> Result<Vec<u8>> {
+        let oldpath = codec::get_string(buf)?;
+        let newpath = codec::get_string(buf)?;
+
+        // NIST 800-53: AC-3, SI-10 - Validate and resolve both paths
+        let old_resolved = match self.resolve_link_path(&oldpath) {
+            Ok(p) => p,
+            Err(e) => {
+                // NIST 800-53: AU-2 - Log security event
+                if e.is_security_event() {
+                    warn!("Security event during hardlink (old path): {} - {}", oldpath, e);
+                }
+                return self.send_status_error(request_id, &e);
+            }
+        };
+
+        let new_resolved = match self.resolve_link_path(&newpath) {
+            Ok(p) => p,
+            Err(e) => {
+                // NIST 800-53: AU-2 - Log security event
+                if e.is_security_event() {
+                    warn!("Security event during hardlink (new path): {} - {}", newpath, e);
+                }
+                return self.send_status_error(request_id, &e);
+            }
+        };
+
+        debug!("Hardlink: {:?} -> {:?}", old_resolved, new_resolved);
+
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .operation_context(Operation::Hardlink, &old_resolved)
+            .with_target(self.client_path(&new_resolved));
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
+        // NIST 800-53: AC-12 - Timeout protection for link creation
+        let link_result =
+            timeout(FILE_OP_TIMEOUT, fs::hard_link(&old_resolved, &new_resolved)).await;
+
+        match link_result {
+            Ok(Ok(())) => {
+                info!("Hard linked {:?} to {:?}", new_resolved, old_resolved);
+                self.send_status(request_id, StatusCode::Ok, "Success")
+            }
+            Ok(Err(e)) => {
+                debug!(
+                    "Failed to hard link {:?} to {:?}: {}",
+                    new_resolved, old_resolved, e
+                );
+                match e.kind() {
+                    // SFTP v3 has no FILE_ALREADY_EXISTS; OpenSSH also answers FAILURE
+                    std::io::ErrorKind::AlreadyExists => {
+                        self.send_status(request_id, StatusCode::Failure, "File already exists")
+                    }
+                    std::io::ErrorKind::NotFound => self.send_status_error(
+                        request_id,
+                        &Error::FileNotFound(format!("Source not found: {}", oldpath)),
+                    ),
+                    std::io::ErrorKind::PermissionDenied => self.send_status_error(
+                        request_id,
+                        &Error::PermissionDenied("Access denied".to_string()),
+                    ),
+                    _ => self.send_status_error(request_id, &Error::Io(e)),
+                }
+            }
+            Err(_) => {
+                error!("Hardlink operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
+                self.send_status_error(
+                    request_id,
+                    &Error::timeout("Hardlink operation timed out"),
+                )
+            }
+        }
+    }
+
+    /// Rename, replacing any existing target (posix-rename@openssh.com)
+    ///
+    /// Unlike SSH_FXP_RENAME this is rename(2) semantics without exceptions:
+    /// an existing target is replaced in one step, so other clients see
+    /// either the old file or the new one and never a missing name.
+    ///
+    /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
+    /// STIG: V-222566, V-222596
+    /// Implementation: Both paths are confined to the root directory
+    async fn handle_posix_rename(&mut self, request_id: u32, buf: &mut &[u8]) -> Result<Vec<u8>> {
+        let oldpath = codec::get_string(buf)?;
+        let newpath = codec::get_string(buf)?;
+
+        // NIST 800-53: AC-3, SI-10 - Validate and resolve both paths
+        let old_resolved = match self.resolve_link_path(&oldpath) {
+            Ok(p) => p,
+            Err(e) => {
+                // NIST 800-53: AU-2 - Log security event
+                if e.is_security_event() {
+                    warn!("Security event during posix-rename (old path): {} - {}", oldpath, e);
+                }
+                return self.send_status_error(request_id, &e);
+            }
+        };
+
+        let new_resolved = match self.resolve_link_path(&newpath) {
+            Ok(p) => p,
+            Err(e) => {
+                // NIST 800-53: AU-2 - Log security event
+                if e.is_security_event() {
+                    warn!("Security event during posix-rename (new path): {} - {}", newpath, e);
+                }
+                return self.send_status_error(request_id, &e);
+            }
+        };
+
+        debug!("Posix-rename: {:?} -> {:?}", old_resolved, new_resolved);
+
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .operation_context(Operation::Rename, &old_resolved)
+            .with_target(self.client_path(&new_resolved));
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
+        // NIST 800-53: AC-12 - Timeout protection for rename operations
+        // fs::rename is rename(2) on Unix and MoveFileEx with
+        // MOVEFILE_REPLACE_EXISTING on Windows; both replace the target atomically
+        let rename_result = timeout(FILE_OP_TIMEOUT, fs::rename(&old_resolved, &new_resolved)).await;
+
+        match rename_result {
+            Ok(Ok(())) => {
+                info!("Renamed {:?} over {:?}", old_resolved, new_resolved);
+                self.audit_rename(&old_resolved, &new_resolved, None);
+                self.send_status(request_id, StatusCode::Ok, "Success")
+            }
+            Ok(Err(e)) => {
+                debug!(
+                    "Failed to posix-rename {:?} to {:?}: {}",
+                    old_resolved, new_resolved, e
+                );
+                let error = match e.kind() {
+                    std::io::ErrorKind::NotFound => {
+                        Error::FileNotFound(format!("Source not found: {}", oldpath))
+                    }
+                    std::io::ErrorKind::PermissionDenied => {
+                        Error::PermissionDenied("Access denied".to_string())
+                    }
+                    _ => Error::Io(e),
+                };
+                self.audit_rename(&old_resolved, &new_resolved, Some(&error));
+                self.send_status_error(request_id, &error)
+            }
+            Err(_) => {
+                error!("Posix-rename operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
+                let error = Error::timeout("Rename operation timed out");
+                self.audit_rename(&old_resolved, &new_resolved, Some(&error));
+                Ok(self.send_status_error(request_id, &error)?)
+            }
+        }
+    }
+
+    /// Flush a file handle to stable storage (fsync@openssh.com)
+    ///
+    /// NIST 800-53: SI-7 (Software, Firmware, and Information Integrity), SI-11 (Error Handling)
+    /// STIG: V-222566
+    /// Implementation: Lets backup clients confirm data is durable before committing
+    async fn handle_fsync(&mut self, request_id: u32, buf: &mut &[u8]) -> Result<Vec<u8>> {
+        let handle = codec::get_bytes(buf)?;
+
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.handle_context(Operation::Fsync, &handle);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
+        // NIST 800-53: SI-11 - Validate handle
+        let file = match self.handles.get(&handle) {
+            Some(FileHandle::File(file, _)) => file,
+            Some(FileHandle::Dir(_)) => {
+                return self.send_status_error(
+                    request_id,
+                    &Error::invalid_handle("Handle is not a file"),
+                );
+            }
+            None => {
+                warn!("Fsync attempt with invalid handle");
+                return self.send_status_error(
+                    request_id,
+                    &Error::invalid_handle("Handle does not exist or is closed"),
+                );
+            }
+        };
+
+        // NIST 800-53: AC-12 - Timeout protection for fsync
+        match timeout(FILE_OP_TIMEOUT, file.sync_all()).await {
+            Ok(Ok(())) => self.send_status(request_id, StatusCode::Ok, "Success"),
+            Ok(Err(e)) => {
+                warn!("Fsync failed: {}", e);
+                self.send_status_error(request_id, &Error::Io(e))
+            }
+            Err(_) => {
+                error!("Fsync operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
+                self.send_status_error(
+                    request_id,
+                    &Error::timeout("Fsync operation timed out"),
+                )
+            }
+        }
+    }
+
+    /// Report the server's limits to OpenSSH clients (limits@openssh.com)
+    ///
+    /// The reply is SSH_FXP_EXTENDED_REPLY carrying four uint64s: the largest
+    /// packet, READ length and WRITE length the server accepts, and
+    /// `max_open_handles`. OpenSSH sizes its request pipeline from these
+    /// instead of its conservative defaults.
+    ///
+    /// NIST 800-53: SC-5 (Denial of Service Protection)
+    fn handle_limits(&self, request_id: u32) -> Result<Vec<u8>> {
+        let max_packet = u64::from(self.config.max_packet_size);
+        let mut response = BytesMut::new();
+        response.put_u8(MessageType::ExtendedReply as u8);
+        response.put_u32(request_id);
+        response.put_u64(max_packet);
+        response.put_u64(u64::from(self.config.max_read_len));
+        response.put_u64(max_packet.saturating_sub(WRITE_HEADER_ALLOWANCE));
+        response.put_u64(self.config.max_open_handles as u64);
+        Ok(response.to_vec())
+    }
+
+    /// Describe the server's extensions and this session's limits
+    /// (server-info@snow-owl.dev)
+    ///
+    /// The reply is SSH_FXP_EXTENDED_REPLY carrying a [`ServerInfo`] as a JSON
+    /// string. No path or file content is disclosed.
+    ///
+    /// NIST 800-53: CM-7 (Least Functionality), SI-11 (Error Handling)
+    fn handle_server_info(&self, request_id: u32) -> Result<Vec<u8>> {
+        let user = self
+            .session_info
+            .username
+            .as_deref()
+            .and_then(|username| self.config.users.get(username));
+        let info = ServerInfo {
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: self.version,
+            supported_versions: (SFTP_VERSION, MAX_SFTP_VERSION),
+            extensions: SUPPORTED_EXTENSIONS
+                .iter()
+                .map(|(name, _)| (*name).to_string())
+                .collect(),
+            max_packet_size: self.config.max_packet_size,
+            max_read_length: self.config.max_read_len,
+            max_open_handles: self.config.max_open_handles,
+            readdir_batch_size: self.config.readdir_batch_size,
+            max_file_size: user.map_or(0, |user| user.max_file_size),
+            read_only: self.config.read_only
+                || user.is_some_and(|user| {
+                    user.read_only || self.config.role_of(user).is_some_and(|role| role.read_only)
+                }),
+        };
+        let json = serde_json::to_string(&info)
+            .map_err(|e| Error::Protocol(format!("Failed to encode server info: {}", e)))?;
 
+        let mut response = BytesMut::new();
+        response.put_u8(MessageType::ExtendedReply as u8);
+        response.put_u32(request_id);
+        codec::put_string(&mut response, &json);
         Ok(response.to_vec())
     }
 
+    /// Report how much of an interrupted upload the server holds
+    /// (stat-for-resume@snow-owl.dev)
+    ///
+    /// The reply is SSH_FXP_EXTENDED_REPLY carrying the file size as a uint64;
+    /// the client reopens without TRUNC and continues writing at that offset.
+    ///
+    /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
+    /// STIG: V-222566, V-222596
+    /// Implementation: Only regular files under the root directory are reported
+    async fn handle_stat_for_resume(
+        &mut self,
+        request_id: u32,
+        buf: &mut &[u8],
+    ) -> Result<Vec<u8>> {
+        let path = codec::get_string(buf)?;
+
+        // NIST 800-53: AC-3, SI-10 - Validate and resolve path
+        let resolved_path = match self.resolve_path(&path) {
+            Ok(p) => p,
+            Err(e) => {
+                // NIST 800-53: AU-2 - Log security event
+                if e.is_security_event() {
+                    warn!("Security event during stat-for-resume: {} - {}", path, e);
+                }
+                return self.send_status_error(request_id, &e);
+            }
+        };
+
+        debug!("Stat-for-resume request for: {:?}", resolved_path);
+
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Stat, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
+        // NIST 800-53: AC-12 - Timeout protection for metadata operations
+        match timeout(FILE_OP_TIMEOUT, fs::metadata(&resolved_path)).await {
+            Ok(Ok(metadata)) if metadata.is_file() => {
+                let mut response = BytesMut::new();
+                response.put_u8(MessageType::ExtendedReply as u8);
+                response.put_u32(request_id);
+                response.put_u64(metadata.len());
+                Ok(response.to_vec())
+            }
+            Ok(Ok(_)) => self.send_status(request_id, StatusCode::Failure, "Not a regular file"),
+            Ok(Err(e)) => {
+                debug!("Stat-for-resume failed for {:?}: {}", resolved_path, e);
+                self.send_status_error(
+                    request_id,
+                    &Error::FileNotFound(format!("File not found: {}", path)),
+                )
+            }
+            Err(_) => {
+                error!("Stat operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
+                self.send_status_error(
+                    request_id,
+                    &Error::timeout("Stat operation timed out"),
+                )
+            }
+        }
+    }
+
     /// Open file
     ///
     /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
@@ -523,7 +1786,7 @@ impl SftpSession {
         let request_id = self.read_u32(buf)?;
         let filename = codec::get_string(buf)?;
         let pflags = self.read_u32(buf)?;
-        let _attrs = FileAttrs::decode(buf)?;
+        let attrs = FileAttrs::decode_for(buf, self.version)?;
 
         let flags = OpenFlags(pflags);
 
@@ -541,17 +1804,21 @@ impl SftpSession {
 
         debug!("Opening file: {:?} with flags: {:?}", path, flags);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .operation_context(Operation::Open, &path)
+            .with_flags(pflags);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: SI-11 - Check for resource exhaustion
-        if self.handles.len() >= 1024 {
-            warn!("Maximum file handles reached (1024)");
-            return Ok(self.send_status_error(
-                request_id,
-                &Error::resource_exhaustion("Too many open file handles"),
-            )?);
+        if let Some(exhausted) = self.handle_limit_reached(request_id)? {
+            return Ok(exhausted);
         }
 
         // NIST 800-53: SI-11 - Handle file opening errors
-        let handle = match self.open_file(path.clone(), flags).await {
+        let handle = match self.open_file(path.clone(), flags, attrs.permissions).await {
             Ok(h) => h,
             Err(e) => {
                 debug!("Failed to open file {:?}: {}", path, e);
@@ -567,11 +1834,13 @@ impl SftpSession {
                     }
                     _ => e,
                 };
+                self.audit_file("OPEN", &path, None, Some(&error));
                 return Ok(self.send_status_error(request_id, &error)?);
             }
         };
 
         let handle_id = self.allocate_handle(handle);
+        self.audit_file("OPEN", &path, None, None);
 
         self.send_handle(request_id, &handle_id)
     }
@@ -596,7 +1865,10 @@ impl SftpSession {
         }
 
         // Remove handle (Drop trait will clean up resources)
-        self.handles.remove(&handle);
+        self.read_ahead.remove(&handle);
+        if let Some(file_handle) = self.handles.remove(&handle) {
+            self.close_handle(&handle, &file_handle);
+        }
 
         self.send_status(request_id, StatusCode::Ok, "Success")
     }
@@ -609,33 +1881,67 @@ impl SftpSession {
         let request_id = self.read_u32(buf)?;
         let handle = codec::get_bytes(buf)?;
         let offset = self.read_u64(buf)?;
-        let len = self.read_u32(buf)?;
-
-        debug!("Read request: offset={}, len={}", offset, len);
+        let requested = self.read_u32(buf)?;
+        // NIST 800-53: SC-5 - Never size a buffer from an unchecked wire value
+        let len = requested.min(self.config.max_read_len);
+
+        debug!("Read request: offset={}, len={} (requested {})", offset, len, requested);
+
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .handle_context(Operation::Read, &handle)
+            .map(|ctx| ctx.with_range(offset, u64::from(len)));
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
 
         // NIST 800-53: SI-11 - Validate handle
-        let file_handle = self.handles.get_mut(&handle).ok_or_else(|| {
+        let file_handle = self.handles.get(&handle).ok_or_else(|| {
             warn!("Read attempt with invalid handle");
             Error::invalid_handle("Handle does not exist or is closed")
         })?;
 
         match file_handle {
-            FileHandle::File(file, _path) => {
-                // NIST 800-53: SI-11 - Handle seek errors
-                if let Err(e) = file.seek(std::io::SeekFrom::Start(offset)).await {
-                    error!("Seek error at offset {}: {}", offset, e);
-                    return Ok(self.send_status_error(request_id, &Error::Io(e))?);
+            FileHandle::File(file, path) => {
+                // Prefetch through a duplicate of this handle's descriptor
+                if self.config.read_ahead_max_bytes > 0 && !self.read_ahead.contains_key(&handle) {
+                    match file.try_clone().await {
+                        Ok(clone) => {
+                            let read_ahead = ReadAhead::new(clone, self.config.read_ahead_max_bytes);
+                            self.read_ahead.insert(handle.clone(), read_ahead);
+                        }
+                        Err(e) => debug!("No read-ahead for {:?}: {}", path, e),
+                    }
                 }
 
-                let mut buffer = vec![0u8; len as usize];
+                // Serve sequential reads from the read-ahead buffer when possible
+                if let Some(read_ahead) = self.read_ahead.get_mut(&handle) {
+                    if let Some(data) = read_ahead.serve(offset, len as usize).await {
+                        read_ahead.advance(offset, data.len(), len as usize);
+                        self.handle_bytes.entry(handle).or_default().read += data.len() as u64;
+                        self.metrics.record_file_read(data.len() as u64);
+                        self.throttle_transfer(data.len());
+                        return self.send_data(request_id, &data);
+                    }
+                }
 
                 // NIST 800-53: AC-12 - Timeout protection for read operations
-                let read_result = timeout(FILE_OP_TIMEOUT, file.read(&mut buffer)).await;
+                // Positional, so pipelined reads at other offsets cannot move it
+                let read_result =
+                    timeout(FILE_OP_TIMEOUT, read_at(file, offset, len as usize)).await;
 
                 match read_result {
-                    Ok(Ok(0)) => self.send_status(request_id, StatusCode::Eof, "End of file"),
-                    Ok(Ok(n)) => {
-                        buffer.truncate(n);
+                    Ok(Ok(buffer)) if buffer.is_empty() => {
+                        self.send_status(request_id, StatusCode::Eof, "End of file")
+                    }
+                    Ok(Ok(buffer)) => {
+                        let n = buffer.len();
+                        if let Some(read_ahead) = self.read_ahead.get_mut(&handle) {
+                            read_ahead.advance(offset, n, len as usize);
+                        }
+                        self.handle_bytes.entry(handle).or_default().read += n as u64;
+                        self.metrics.record_file_read(n as u64);
+                        self.throttle_transfer(n);
                         self.send_data(request_id, &buffer)
                     }
                     Ok(Err(e)) => {
@@ -673,25 +1979,58 @@ impl SftpSession {
 
         debug!("Write request: offset={}, len={}", offset, data.len());
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .handle_context(Operation::Write, &handle)
+            .map(|ctx| ctx.with_range(offset, data.len() as u64));
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: SI-11 - Validate handle
-        let file_handle = self.handles.get_mut(&handle).ok_or_else(|| {
+        let file_handle = self.handles.get(&handle).ok_or_else(|| {
             warn!("Write attempt with invalid handle");
             Error::invalid_handle("Handle does not exist or is closed")
         })?;
 
         match file_handle {
             FileHandle::File(file, _path) => {
-                // NIST 800-53: SI-11 - Handle seek errors
-                if let Err(e) = file.seek(std::io::SeekFrom::Start(offset)).await {
-                    error!("Seek error at offset {}: {}", offset, e);
-                    return Ok(self.send_status_error(request_id, &Error::Io(e))?);
+                // NIST 800-53: SI-10 - A gap past the end is zero-filled by
+                // the filesystem unless the configuration refuses it
+                if self.config.write_past_eof == WritePastEof::Reject {
+                    let len = match file.metadata().await {
+                        Ok(metadata) => metadata.len(),
+                        Err(e) => return self.send_status_error(request_id, &Error::Io(e)),
+                    };
+                    if offset > len {
+                        warn!("Write at offset {} beyond end of file ({} bytes)", offset, len);
+                        return self.send_status(
+                            request_id,
+                            StatusCode::Failure,
+                            "Write offset beyond end of file",
+                        );
+                    }
                 }
 
-                // NIST 800-53: AC-12 - Timeout protection for write operations
-                let write_result = timeout(FILE_OP_TIMEOUT, file.write_all(&data)).await;
+                // Prefetched data for this handle may now be stale
+                if let Some(read_ahead) = self.read_ahead.get_mut(&handle) {
+                    read_ahead.invalidate();
+                }
+
+                let written = data.len();
+
+                // NIST 800-53: AC-12 - Timeout protection for write operations
+                // The write has completed when this returns, so a CLOSE sent
+                // after the reply cannot race the data to disk
+                let write_result = timeout(FILE_OP_TIMEOUT, write_at(file, offset, data)).await;
 
                 match write_result {
-                    Ok(Ok(())) => self.send_status(request_id, StatusCode::Ok, "Success"),
+                    Ok(Ok(())) => {
+                        self.handle_bytes.entry(handle).or_default().written += written as u64;
+                        self.metrics.record_file_write(written as u64);
+                        self.throttle_transfer(written);
+                        self.send_status(request_id, StatusCode::Ok, "Success")
+                    }
                     Ok(Err(e)) => {
                         error!("Write error: {}", e);
                         Ok(self.send_status_error(request_id, &Error::Io(e))?)
@@ -717,15 +2056,24 @@ impl SftpSession {
 
     /// Get file/directory attributes
     ///
+    /// STAT follows a final symbolic link; LSTAT (`follow` unset) reports the link itself.
+    ///
     /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
     /// STIG: V-222566, V-222596
     /// Implementation: Secure attribute retrieval with proper error handling
-    async fn handle_stat(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
+    async fn handle_stat(&mut self, buf: &mut &[u8], follow: bool) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
         let path = codec::get_string(buf)?;
+        // Version 4 appends the attribute flags the client wants; every
+        // supported attribute is returned regardless, so it is not read
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
-        let resolved_path = match self.resolve_path(&path) {
+        let resolved = if follow {
+            self.resolve_path(&path)
+        } else {
+            self.resolve_link_path(&path)
+        };
+        let resolved_path = match resolved {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -738,8 +2086,18 @@ impl SftpSession {
 
         debug!("Stat request for: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Stat, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for metadata operations
-        let metadata_result = timeout(FILE_OP_TIMEOUT, fs::metadata(&resolved_path)).await;
+        let metadata_result = if follow {
+            timeout(FILE_OP_TIMEOUT, fs::metadata(&resolved_path)).await
+        } else {
+            timeout(FILE_OP_TIMEOUT, fs::symlink_metadata(&resolved_path)).await
+        };
 
         match metadata_result {
             Ok(Ok(metadata)) => {
@@ -771,6 +2129,12 @@ impl SftpSession {
         let request_id = self.read_u32(buf)?;
         let handle = codec::get_bytes(buf)?;
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.handle_context(Operation::Fstat, &handle);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: SI-11 - Validate handle
         let file_handle = self.handles.get(&handle).ok_or_else(|| {
             warn!("Fstat attempt with invalid handle");
@@ -806,7 +2170,7 @@ impl SftpSession {
     async fn handle_setstat(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
         let path = codec::get_string(buf)?;
-        let attrs = FileAttrs::decode(buf)?;
+        let attrs = FileAttrs::decode_for(buf, self.version)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
         let resolved_path = match self.resolve_path(&path) {
@@ -822,6 +2186,12 @@ impl SftpSession {
 
         debug!("Setstat request for: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Setstat, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // Apply attributes
         if let Err(e) = self.apply_file_attrs(&resolved_path, &attrs).await {
             debug!("Failed to set attributes for {:?}: {}", resolved_path, e);
@@ -831,7 +2201,7 @@ impl SftpSession {
         self.send_status(request_id, StatusCode::Ok, "Success")
     }
 
-    /// Set attributes for file handle
+    /// Set attributes for a file or directory handle
     ///
     /// NIST 800-53: SI-11 (Error Handling), AC-3 (Access Enforcement)
     /// STIG: V-222566, V-222596
@@ -839,27 +2209,27 @@ impl SftpSession {
     async fn handle_fsetstat(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
         let handle = codec::get_bytes(buf)?;
-        let attrs = FileAttrs::decode(buf)?;
+        let attrs = FileAttrs::decode_for(buf, self.version)?;
 
         debug!("Fsetstat request");
 
-        // NIST 800-53: SI-11 - Validate handle
-        let file_handle = self.handles.get(&handle).ok_or_else(|| {
-            warn!("Fsetstat attempt with invalid handle");
-            Error::invalid_handle("Handle does not exist or is closed")
-        })?;
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.handle_context(Operation::Fsetstat, &handle);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
 
-        // Get the file path from the handle
-        let path = match file_handle {
-            FileHandle::File(_file, path) => path.clone(),
-            FileHandle::Dir(_) => {
-                warn!("Attempt to fsetstat directory handle");
-                return Ok(self.send_status_error(
-                    request_id,
-                    &Error::InvalidHandle("Cannot fsetstat directory handle".into()),
-                )?);
-            }
-        };
+        // NIST 800-53: SI-11 - Validate handle
+        // File and directory handles both keep the path they were opened with
+        let path = self
+            .handles
+            .get(&handle)
+            .ok_or_else(|| {
+                warn!("Fsetstat attempt with invalid handle");
+                Error::invalid_handle("Handle does not exist or is closed")
+            })?
+            .path()
+            .to_path_buf();
 
         // Apply attributes
         if let Err(e) = self.apply_file_attrs(&path, &attrs).await {
@@ -893,35 +2263,29 @@ impl SftpSession {
 
         debug!("Opening directory: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Opendir, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
+        // NIST 800-53: SC-5 - Directory handles count against the same limit
+        if let Some(exhausted) = self.handle_limit_reached(request_id)? {
+            return Ok(exhausted);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for directory operations
         let read_dir_result = timeout(FILE_OP_TIMEOUT, fs::read_dir(&resolved_path)).await;
 
         match read_dir_result {
             Ok(result) => match result {
                 Ok(read_dir) => {
+                    // Entries are read as READDIR requests arrive, not here
                     let handle = FileHandle::Dir(DirHandle {
-                        entries: Vec::new(),
-                        index: 0,
+                        path: resolved_path.clone(),
+                        read_dir: Some(read_dir),
                     });
                     let handle_id = self.allocate_handle(handle);
-
-                    // Read all entries
-                    if let Some(FileHandle::Dir(dir_handle)) = self.handles.get_mut(&handle_id) {
-                        let mut entries = Vec::new();
-                        let mut read_dir = read_dir;
-
-                        while let Ok(Some(entry)) = read_dir.next_entry().await {
-                            if let Ok(metadata) = entry.metadata().await {
-                                entries.push((
-                                    entry.file_name().to_string_lossy().to_string(),
-                                    metadata_to_attrs(&metadata),
-                                ));
-                            }
-                        }
-
-                        dir_handle.entries = entries;
-                    }
-
                     self.send_handle(request_id, &handle_id)
                 }
                 Err(e) => {
@@ -948,42 +2312,57 @@ impl SftpSession {
 
     /// Read directory entries
     ///
-    /// NIST 800-53: SI-11 (Error Handling)
+    /// Each call reads up to `readdir_batch_size` further entries from the
+    /// directory; SSH_FX_EOF is sent once it is exhausted. Entries are read
+    /// a batch at a time, so a handle on a directory of any size holds no
+    /// more than one batch in memory.
+    ///
+    /// NIST 800-53: SI-11 (Error Handling), AC-12 (operation timeout),
+    /// SC-5 (Denial of Service Protection)
     /// Implementation: Safe directory reading with handle validation
     async fn handle_readdir(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
         let handle = codec::get_bytes(buf)?;
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.handle_context(Operation::Readdir, &handle);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: SI-11 - Validate handle
         let file_handle = self.handles.get_mut(&handle).ok_or_else(|| {
             warn!("Readdir attempt with invalid handle");
             Error::invalid_handle("Handle does not exist or is closed")
         })?;
 
+        let batch_size = self.config.readdir_batch_size;
         match file_handle {
             FileHandle::Dir(dir_handle) => {
-                if dir_handle.index >= dir_handle.entries.len() {
+                let Ok(entries) =
+                    timeout(FILE_OP_TIMEOUT, dir_handle.next_batch(batch_size)).await
+                else {
+                    error!(
+                        "Readdir operation timed out after {} seconds",
+                        FILE_OP_TIMEOUT.as_secs()
+                    );
+                    return self.send_status_error(
+                        request_id,
+                        &Error::timeout("Directory operation timed out"),
+                    );
+                };
+                if entries.is_empty() {
                     return self.send_status(request_id, StatusCode::Eof, "End of directory");
                 }
 
                 let mut response = BytesMut::new();
                 response.put_u8(MessageType::Name as u8);
                 response.put_u32(request_id);
-
-                // Send up to 100 entries at once
-                let end = (dir_handle.index + 100).min(dir_handle.entries.len());
-                let count = end - dir_handle.index;
-                response.put_u32(count as u32);
-
-                for i in dir_handle.index..end {
-                    let (name, attrs) = &dir_handle.entries[i];
-                    codec::put_string(&mut response, name);
-                    codec::put_string(&mut response, name); // longname (same as shortname for now)
-                    response.put(attrs.encode());
+                response.put_u32(entries.len() as u32);
+                for (name, attrs) in &entries {
+                    put_name_entry(&mut response, self.version, name, attrs);
                 }
 
-                dir_handle.index = end;
-
                 Ok(response.to_vec())
             }
             FileHandle::File(_, _) => {
@@ -1006,7 +2385,7 @@ impl SftpSession {
         let filename = codec::get_string(buf)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
-        let path = match self.resolve_path(&filename) {
+        let path = match self.resolve_link_path(&filename) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1019,6 +2398,12 @@ impl SftpSession {
 
         debug!("Removing file: {:?}", path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Remove, &path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for file removal
         let remove_result = timeout(FILE_OP_TIMEOUT, fs::remove_file(&path)).await;
 
@@ -1026,6 +2411,7 @@ impl SftpSession {
             Ok(result) => match result {
                 Ok(_) => {
                     info!("File removed: {:?}", path);
+                    self.audit_file("DELETE", &path, None, None);
                     self.send_status(request_id, StatusCode::Ok, "Success")
                 }
                 Err(e) => {
@@ -1037,15 +2423,15 @@ impl SftpSession {
                     } else {
                         Error::Io(e)
                     };
+                    self.audit_file("DELETE", &path, None, Some(&error));
                     Ok(self.send_status_error(request_id, &error)?)
                 }
             },
             Err(_) => {
                 error!("Remove operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
-                Ok(self.send_status_error(
-                    request_id,
-                    &Error::timeout("Remove operation timed out"),
-                )?)
+                let error = Error::timeout("Remove operation timed out");
+                self.audit_file("DELETE", &path, None, Some(&error));
+                Ok(self.send_status_error(request_id, &error)?)
             }
         }
     }
@@ -1058,10 +2444,10 @@ impl SftpSession {
     async fn handle_mkdir(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
         let path = codec::get_string(buf)?;
-        let _attrs = FileAttrs::decode(buf)?;
+        let attrs = FileAttrs::decode_for(buf, self.version)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
-        let resolved_path = match self.resolve_path(&path) {
+        let resolved_path = match self.resolve_link_path(&path) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1074,13 +2460,26 @@ impl SftpSession {
 
         debug!("Creating directory: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Mkdir, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for directory creation
-        let mkdir_result = timeout(FILE_OP_TIMEOUT, fs::create_dir(&resolved_path)).await;
+        let mut builder = fs::DirBuilder::new();
+        // NIST 800-53: AC-6 - Mode from the configured file mode policy
+        #[cfg(unix)]
+        builder.mode(self.config.file_mode_policy.creation_mode(attrs.permissions, 0o777));
+        #[cfg(not(unix))]
+        let _ = attrs;
+        let mkdir_result = timeout(FILE_OP_TIMEOUT, builder.create(&resolved_path)).await;
 
         match mkdir_result {
             Ok(result) => match result {
                 Ok(_) => {
                     info!("Directory created: {:?}", resolved_path);
+                    self.audit_directory("MKDIR", &resolved_path, None);
                     self.send_status(request_id, StatusCode::Ok, "Success")
                 }
                 Err(e) => {
@@ -1092,15 +2491,15 @@ impl SftpSession {
                     } else {
                         Error::Io(e)
                     };
+                    self.audit_directory("MKDIR", &resolved_path, Some(&error));
                     Ok(self.send_status_error(request_id, &error)?)
                 }
             },
             Err(_) => {
                 error!("Mkdir operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
-                Ok(self.send_status_error(
-                    request_id,
-                    &Error::timeout("Directory creation timed out"),
-                )?)
+                let error = Error::timeout("Directory creation timed out");
+                self.audit_directory("MKDIR", &resolved_path, Some(&error));
+                Ok(self.send_status_error(request_id, &error)?)
             }
         }
     }
@@ -1115,7 +2514,7 @@ impl SftpSession {
         let path = codec::get_string(buf)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
-        let resolved_path = match self.resolve_path(&path) {
+        let resolved_path = match self.resolve_link_path(&path) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1128,6 +2527,12 @@ impl SftpSession {
 
         debug!("Removing directory: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Rmdir, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for directory removal
         let rmdir_result = timeout(FILE_OP_TIMEOUT, fs::remove_dir(&resolved_path)).await;
 
@@ -1135,6 +2540,7 @@ impl SftpSession {
             Ok(result) => match result {
                 Ok(_) => {
                     info!("Directory removed: {:?}", resolved_path);
+                    self.audit_directory("RMDIR", &resolved_path, None);
                     self.send_status(request_id, StatusCode::Ok, "Success")
                 }
                 Err(e) => {
@@ -1146,29 +2552,94 @@ impl SftpSession {
                     } else {
                         Error::Io(e)
                     };
+                    self.audit_directory("RMDIR", &resolved_path, Some(&error));
                     Ok(self.send_status_error(request_id, &error)?)
                 }
             },
             Err(_) => {
                 error!("Rmdir operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
-                Ok(self.send_status_error(
-                    request_id,
-                    &Error::timeout("Directory removal timed out"),
-                )?)
+                let error = Error::timeout("Directory removal timed out");
+                self.audit_directory("RMDIR", &resolved_path, Some(&error));
+                Ok(self.send_status_error(request_id, &error)?)
             }
         }
     }
 
     async fn handle_realpath(&mut self, buf: &mut &[u8]) -> Result<Vec<u8>> {
         let request_id = self.read_u32(buf)?;
-        let path = codec::get_string(buf)?;
+        let mut path = codec::get_string(buf)?;
+
+        // SFTP v6 clients may follow the path with a control byte and
+        // compose paths, each applied to the result so far; v3 sends neither
+        let mut control = RealpathControl::STAT_IF;
+        if let Some((&byte, rest)) = buf.split_first() {
+            control = byte;
+            *buf = rest;
+            while !buf.is_empty() {
+                let compose = codec::get_string(buf)?;
+                path = if compose.starts_with('/') {
+                    compose
+                } else {
+                    format!("{}/{}", path, compose)
+                };
+            }
+        }
+        if !matches!(
+            control,
+            RealpathControl::NO_CHECK | RealpathControl::STAT_IF | RealpathControl::STAT_ALWAYS
+        ) {
+            return self.send_status(
+                request_id,
+                StatusCode::BadMessage,
+                "Unknown REALPATH control byte",
+            );
+        }
 
         debug!("Realpath request for: {}", path);
 
-        let resolved = if path.is_empty() || path == "." {
-            "/".to_string()
-        } else {
-            path.clone()
+        // NIST 800-53: AC-3 - `..` may not climb above the session's root
+        // STIG: V-222396, V-222596
+        let Some(normalized) = normalize_client_path(&path) else {
+            warn!("Path traversal attempt detected: {}", path);
+            self.audit.record(AuditEvent::security(
+                self.session_info.client_ip,
+                self.session_info.username.clone(),
+                "path_traversal",
+                format!("path={}", path),
+            ));
+            let error = Error::PermissionDenied("Path escapes the root directory".into());
+            return self.send_status_error(request_id, &error);
+        };
+
+        // NIST 800-53: AC-3, SI-10 - Resolve as any other operation would
+        let resolved = match self.resolve_path(&normalized) {
+            Ok(p) => p,
+            Err(e) => return self.send_status_error(request_id, &e),
+        };
+
+        // Report the path relative to the root; a link leading elsewhere
+        // (symlinks = "allow") is shown as the root so host paths never leak
+        let canonical = canonicalize_existing(&resolved).await;
+        let root = fs::canonicalize(self.root_dir())
+            .await
+            .unwrap_or_else(|_| self.root_dir().to_path_buf());
+        let (resolved, target) = match canonical.strip_prefix(&root) {
+            Ok(inside) => (
+                Path::new("/").join(inside).to_string_lossy().into_owned(),
+                canonical.as_path(),
+            ),
+            Err(_) => ("/".to_string(), root.as_path()),
+        };
+
+        // The longname describes the target when it exists; a path about to
+        // be created has no attributes to show
+        let attrs = match fs::metadata(target).await {
+            Ok(metadata) => metadata_to_attrs(&metadata),
+            Err(_) if control == RealpathControl::STAT_ALWAYS => {
+                let error = Error::FileNotFound(format!("File not found: {}", resolved));
+                return self.send_status_error(request_id, &error);
+            }
+            Err(_) => FileAttrs::default(),
         };
 
         let mut response = BytesMut::new();
@@ -1176,9 +2647,7 @@ impl SftpSession {
         response.put_u32(request_id);
         response.put_u32(1); // count
 
-        codec::put_string(&mut response, &resolved);
-        codec::put_string(&mut response, &resolved); // longname
-        response.put(FileAttrs::default().encode());
+        put_name_entry(&mut response, self.version, &resolved, &attrs);
 
         Ok(response.to_vec())
     }
@@ -1194,7 +2663,7 @@ impl SftpSession {
         let newpath = codec::get_string(buf)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve both paths
-        let old_resolved = match self.resolve_path(&oldpath) {
+        let old_resolved = match self.resolve_link_path(&oldpath) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1205,7 +2674,7 @@ impl SftpSession {
             }
         };
 
-        let new_resolved = match self.resolve_path(&newpath) {
+        let new_resolved = match self.resolve_link_path(&newpath) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1218,6 +2687,14 @@ impl SftpSession {
 
         debug!("Rename: {:?} -> {:?}", old_resolved, new_resolved);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .operation_context(Operation::Rename, &old_resolved)
+            .with_target(self.client_path(&new_resolved));
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for rename operations
         let rename_result = timeout(FILE_OP_TIMEOUT, fs::rename(&old_resolved, &new_resolved)).await;
 
@@ -1225,6 +2702,7 @@ impl SftpSession {
             Ok(result) => match result {
                 Ok(_) => {
                     info!("Renamed {:?} to {:?}", old_resolved, new_resolved);
+                    self.audit_rename(&old_resolved, &new_resolved, None);
                     self.send_status(request_id, StatusCode::Ok, "Success")
                 }
                 Err(e) => {
@@ -1236,15 +2714,15 @@ impl SftpSession {
                     } else {
                         Error::Io(e)
                     };
+                    self.audit_rename(&old_resolved, &new_resolved, Some(&error));
                     Ok(self.send_status_error(request_id, &error)?)
                 }
             },
             Err(_) => {
                 error!("Rename operation timed out after {} seconds", FILE_OP_TIMEOUT.as_secs());
-                Ok(self.send_status_error(
-                    request_id,
-                    &Error::timeout("Rename operation timed out"),
-                )?)
+                let error = Error::timeout("Rename operation timed out");
+                self.audit_rename(&old_resolved, &new_resolved, Some(&error));
+                Ok(self.send_status_error(request_id, &error)?)
             }
         }
     }
@@ -1260,7 +2738,7 @@ impl SftpSession {
         let path = codec::get_string(buf)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate and resolve path
-        let resolved_path = match self.resolve_path(&path) {
+        let resolved_path = match self.resolve_link_path(&path) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1273,36 +2751,56 @@ impl SftpSession {
 
         debug!("Readlink request for: {:?}", resolved_path);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self.operation_context(Operation::Readlink, &resolved_path);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-12 - Timeout protection for readlink operation
         let readlink_result = timeout(FILE_OP_TIMEOUT, fs::read_link(&resolved_path)).await;
 
         match readlink_result {
             Ok(result) => match result {
                 Ok(target) => {
-                    // NIST 800-53: AC-3 - Security check: ensure target is within root
-                    // Convert target to string for response
-                    let target_str = target.to_string_lossy().to_string();
-
-                    // Check if the symlink target tries to escape root directory
-                    let absolute_target = if target.is_absolute() {
-                        target.clone()
-                    } else {
-                        // Resolve relative symlink against the symlink's parent directory
-                        if let Some(parent) = resolved_path.parent() {
-                            parent.join(&target)
-                        } else {
-                            target.clone()
+                    // NIST 800-53: AC-3 - Never reveal a host path; absolute targets
+                    // inside the root are returned relative to it
+                    let client_target = match self.config.symlinks {
+                        SymlinkPolicy::Deny => {
+                            let violation = SymlinkViolation::Denied(resolved_path.clone());
+                            let error = self.symlink_violation(&path, &violation);
+                            return self.send_status_error(request_id, &error);
+                        }
+                        SymlinkPolicy::InternalOnly => {
+                            // Relative targets are checked from the link's
+                            // directory, so a `../` chain planted on the host
+                            // is refused like an absolute path outside the root
+                            let root = self.root_dir();
+                            let link_dir = resolved_path.parent().unwrap_or(root);
+                            let checked = resolve_beneath(
+                                root,
+                                &link_dir.join(&target),
+                                true,
+                                SymlinkPolicy::InternalOnly,
+                            )
+                            .and_then(|_| {
+                                client_link_target(root, &target)
+                                    .ok_or_else(|| SymlinkViolation::Escapes(target.clone()))
+                            });
+                            match checked {
+                                Ok(client_target) => client_target,
+                                Err(violation) => {
+                                    let error = self.symlink_violation(&path, &violation);
+                                    return self.send_status_error(request_id, &error);
+                                }
+                            }
+                        }
+                        SymlinkPolicy::Allow => {
+                            client_link_target(self.root_dir(), &target)
+                                .unwrap_or_else(|| target.clone())
                         }
                     };
-
-                    // Security check: warn if symlink points outside root
-                    if !absolute_target.starts_with(&self.config.root_dir) {
-                        warn!(
-                            "Symlink {:?} points outside root directory to {:?}",
-                            resolved_path, absolute_target
-                        );
-                        // We still return the target but log the security concern
-                    }
+                    let target_str = client_target.to_string_lossy().to_string();
 
                     info!("Symlink {:?} -> {:?}", resolved_path, target);
 
@@ -1311,9 +2809,12 @@ impl SftpSession {
                     response.put_u32(request_id);
                     response.put_u32(1); // count
 
-                    codec::put_string(&mut response, &target_str);
-                    codec::put_string(&mut response, &target_str); // longname
-                    response.put(FileAttrs::default().encode());
+                    put_name_entry(
+                        &mut response,
+                        self.version,
+                        &target_str,
+                        &FileAttrs::default(),
+                    );
 
                     Ok(response.to_vec())
                 }
@@ -1364,7 +2865,7 @@ impl SftpSession {
         let targetpath = codec::get_string(buf)?;
 
         // NIST 800-53: AC-3, SI-10 - Validate linkpath (where symlink will be created)
-        let resolved_linkpath = match self.resolve_path(&linkpath) {
+        let resolved_linkpath = match self.resolve_link_path(&linkpath) {
             Ok(p) => p,
             Err(e) => {
                 // NIST 800-53: AU-2 - Log security event
@@ -1377,6 +2878,14 @@ impl SftpSession {
 
         debug!("Symlink request: {:?} -> {}", resolved_linkpath, targetpath);
 
+        // NIST 800-53: AC-3 - Consult the per-operation authorizer
+        let ctx = self
+            .operation_context(Operation::Symlink, &resolved_linkpath)
+            .with_target(&targetpath);
+        if let Some(denied) = self.authorize(request_id, ctx).await? {
+            return Ok(denied);
+        }
+
         // NIST 800-53: AC-3 - Security validation
         // Check if symlink already exists
         if resolved_linkpath.exists() {
@@ -1387,29 +2896,47 @@ impl SftpSession {
             )?);
         }
 
-        // NIST 800-53: AC-3 - Security check on target
-        // The target doesn't need to exist for symlink creation, but we should validate
-        // that if it's an absolute path, it's within our root directory
-        let target_path = PathBuf::from(&targetpath);
-        if target_path.is_absolute() {
-            // If target is absolute, it should be within root directory
-            if !target_path.starts_with(&self.config.root_dir) {
-                warn!(
-                    "Symlink target points outside root directory: {} -> {}",
-                    linkpath, targetpath
-                );
+        // NIST 800-53: AC-3 - Apply the symlink policy to the target. Clients see
+        // root_dir as `/`, so absolute targets are stored under root_dir; the
+        // target need not exist yet but must resolve inside the root
+        let target_path = match self.config.symlinks {
+            SymlinkPolicy::Deny => {
+                warn!("Symlink creation refused by policy: {} -> {}", linkpath, targetpath);
+                self.audit.record(AuditEvent::security(
+                    self.session_info.client_ip,
+                    self.session_info.username.clone(),
+                    "symlink_denied",
+                    format!("policy=deny path={} target={}", linkpath, targetpath),
+                ));
                 return Ok(self.send_status_error(
                     request_id,
-                    &Error::PermissionDenied("Symlink target outside root directory".into()),
+                    &Error::PermissionDenied("Symbolic links are disabled".into()),
                 )?);
             }
-        }
+            SymlinkPolicy::InternalOnly => {
+                let target_path = host_link_target(self.root_dir(), &targetpath);
+                let link_dir = resolved_linkpath
+                    .parent()
+                    .unwrap_or(self.root_dir());
+                if let Err(violation) = resolve_beneath(
+                    self.root_dir(),
+                    &link_dir.join(&target_path),
+                    true,
+                    SymlinkPolicy::InternalOnly,
+                ) {
+                    let error = self.symlink_violation(&linkpath, &violation);
+                    return self.send_status_error(request_id, &error);
+                }
+                target_path
+            }
+            SymlinkPolicy::Allow => PathBuf::from(&targetpath),
+        };
 
         // NIST 800-53: AC-12 - Timeout protection for symlink creation
         use tokio::fs::symlink;
         let symlink_result = timeout(
             FILE_OP_TIMEOUT,
-            symlink(&targetpath, &resolved_linkpath)
+            symlink(&target_path, &resolved_linkpath)
         ).await;
 
         match symlink_result {
@@ -1456,12 +2983,132 @@ impl SftpSession {
 
     // Helper methods
 
-    /// Resolve and validate path
+    /// Authorization context for `operation` on a resolved path
+    ///
+    /// The path is reported as the client sees it, relative to the root.
+    fn operation_context(&self, operation: Operation, path: &Path) -> OperationContext {
+        OperationContext::new(self.session_info.clone(), operation, self.client_path(path))
+    }
+
+    /// Directory this session's paths are confined to
+    ///
+    /// NIST 800-53: AC-3 - An authenticated user with a `user_roots` entry,
+    /// a `home_dir` or a `user_root_template` never sees `root_dir` or
+    /// another user's tree
+    fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// Record the authenticated user and confine the session to `root`
+    fn set_user(&mut self, username: String, root: PathBuf) {
+        self.session_info.set_username(username);
+        self.root_dir = root;
+    }
+
+    /// Client-visible form of a resolved path
+    fn client_path(&self, path: &Path) -> PathBuf {
+        Path::new("/").join(path.strip_prefix(self.root_dir()).unwrap_or(path))
+    }
+
+    /// Authorization context for `operation` on an open handle
+    ///
+    /// None when the handle does not exist; the caller's handle validation
+    /// reports that.
+    fn handle_context(&self, operation: Operation, handle: &[u8]) -> Option<OperationContext> {
+        let path = self.handles.get(handle)?.path().to_path_buf();
+        Some(self.operation_context(operation, &path))
+    }
+
+    /// Read-only mode and path rule check, returning the deny reason if refused
+    ///
+    /// NIST 800-53: AC-3 (Access Enforcement), AC-6 (Least Privilege)
+    /// Implementation: Paths are the client-visible form, i.e. relative to root_dir
+    fn path_policy_denial(&self, ctx: &OperationContext) -> Option<String> {
+        ctx.required_access()
+            .into_iter()
+            .find(|&(access, path)| !self.config.is_path_access_allowed(path, access))
+            .map(|(access, path)| {
+                if self.config.read_only && access != PathAccess::Read {
+                    "server is read-only".to_string()
+                } else {
+                    format!("{} not permitted on {}", access.name(), path.display())
+                }
+            })
+    }
+
+    /// Consult the path policy and the authorizer, returning a PERMISSION_DENIED reply if refused
+    ///
+    /// NIST 800-53: AC-3 (Access Enforcement), AU-2 (Audit Events)
+    /// Implementation: Runs after the built-in checks; read-only mode and path rules
+    /// are applied before the pluggable authorizer. The sanitized deny reason is sent
+    /// to the client and recorded in the audit log
+    async fn authorize(
+        &mut self,
+        request_id: u32,
+        ctx: impl Into<Option<OperationContext>>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(ctx) = ctx.into() else {
+            return Ok(None);
+        };
+        let reason = match self.path_policy_denial(&ctx) {
+            Some(reason) => reason,
+            None => match self.authorization.check(&ctx).await {
+                Ok(()) => return Ok(None),
+                Err(reason) => reason,
+            },
+        };
+
+        warn!(
+            "Denied {} on {:?} for {:?}: {}",
+            ctx.operation.name(),
+            ctx.path,
+            ctx.session.username,
+            reason
+        );
+        self.audit.record(AuditEvent::security(
+            ctx.session.client_ip,
+            ctx.session.username.clone(),
+            "operation_denied",
+            format!(
+                "operation={} path={} reason={}",
+                ctx.operation.name(),
+                ctx.path.display(),
+                reason
+            ),
+        ));
+        self.metrics.record_permission_denied();
+
+        self.send_status(
+            request_id,
+            StatusCode::PermissionDenied,
+            &format!("Permission denied: {}", reason),
+        )
+        .map(Some)
+    }
+
+    /// Resolve and validate path, following a final symbolic link
     ///
     /// NIST 800-53: SI-10 (Input Validation), AC-3 (Access Enforcement)
     /// STIG: V-222396, V-222596
     /// Implementation: Prevents path traversal attacks and validates input
     fn resolve_path(&self, path: &str) -> Result<PathBuf> {
+        self.resolve_path_following(path, true)
+    }
+
+    /// Resolve and validate path, leaving a final symbolic link in place
+    ///
+    /// Used by operations that act on the link itself (LSTAT, REMOVE, RENAME,
+    /// READLINK, SYMLINK); links in earlier components are still followed.
+    fn resolve_link_path(&self, path: &str) -> Result<PathBuf> {
+        self.resolve_path_following(path, false)
+    }
+
+    /// Validate `path` and expand symbolic links under the configured policy
+    ///
+    /// NIST 800-53: SI-10 (Input Validation), AC-3 (Access Enforcement)
+    /// STIG: V-222396, V-222596
+    /// Implementation: Links are re-checked on every use, not only when created
+    fn resolve_path_following(&self, path: &str, follow_final: bool) -> Result<PathBuf> {
         // NIST 800-53: SI-10 - Validate input
         if path.is_empty() {
             return Err(Error::InvalidPath("Empty path".to_string()));
@@ -1475,25 +3122,105 @@ impl SftpSession {
             ));
         }
 
+        // NIST 800-53: SI-10 - Bound path and component length before touching the filesystem
+        if path.len() > self.config.max_path_length {
+            warn!(
+                "Path length {} exceeds maximum of {}",
+                path.len(),
+                self.config.max_path_length
+            );
+            return Err(Error::InvalidPath("Path too long".to_string()));
+        }
+
+        if path
+            .split('/')
+            .any(|component| component.len() > self.config.max_filename_length)
+        {
+            warn!(
+                "Path component exceeds maximum length of {}",
+                self.config.max_filename_length
+            );
+            return Err(Error::InvalidPath("File name too long".to_string()));
+        }
+
         let path = if path.starts_with('/') {
             &path[1..]
         } else {
             path
         };
 
-        let resolved = self.config.root_dir.join(path);
+        let resolved = self.root_dir().join(path);
 
         // NIST 800-53: AC-3 - Ensure the path is within root_dir (prevent path traversal)
         // STIG: V-222396, V-222596
-        if !resolved.starts_with(&self.config.root_dir) {
+        if !resolved.starts_with(self.root_dir()) {
             warn!("Path traversal attempt detected: {}", path);
+            self.audit.record(AuditEvent::security(
+                self.session_info.client_ip,
+                self.session_info.username.clone(),
+                "path_traversal",
+                format!("path={}", path),
+            ));
             return Err(Error::InvalidPath("Invalid path".to_string()));
         }
 
-        Ok(resolved)
+        // NIST 800-53: AC-3 - Every link on the way must satisfy the symlink policy
+        resolve_beneath(
+            self.root_dir(),
+            &resolved,
+            follow_final,
+            self.config.symlinks,
+        )
+        .map_err(|violation| self.symlink_violation(path, &violation))
+    }
+
+    /// Audit a symbolic link policy violation and map it to the client error
+    ///
+    /// NIST 800-53: AU-2 (Audit Events), AC-3 (Access Enforcement)
+    /// Implementation: Links are hidden entirely in deny mode, so they are
+    /// reported as not found; escapes are refused as permission denied
+    fn symlink_violation(&self, path: &str, violation: &SymlinkViolation) -> Error {
+        let event = match violation {
+            SymlinkViolation::Denied(_) => "symlink_denied",
+            SymlinkViolation::Escapes(_) | SymlinkViolation::TooManyLinks(_) => {
+                "symlink_traversal_blocked"
+            }
+        };
+        warn!(
+            "Symlink policy blocked {} for {:?}: {}",
+            path, self.session_info.username, violation
+        );
+        self.audit.record(AuditEvent::security(
+            self.session_info.client_ip,
+            self.session_info.username.clone(),
+            event,
+            format!(
+                "policy={} path={} reason={}",
+                self.config.symlinks.name(),
+                path,
+                violation
+            ),
+        ));
+
+        match violation {
+            SymlinkViolation::Denied(_) => {
+                Error::FileNotFound(format!("File not found: {}", path))
+            }
+            SymlinkViolation::Escapes(_) => {
+                Error::PermissionDenied("Path leads outside the root directory".to_string())
+            }
+            SymlinkViolation::TooManyLinks(_) => {
+                Error::PermissionDenied("Too many levels of symbolic links".to_string())
+            }
+        }
     }
 
-    async fn open_file(&self, path: PathBuf, flags: OpenFlags) -> Result<FileHandle> {
+    async fn open_file(
+        &self,
+        path: PathBuf,
+        flags: OpenFlags,
+        permissions: Option<u32>,
+    ) -> Result<FileHandle> {
         let mut options = fs::OpenOptions::new();
 
         if flags.has_read() {
@@ -1507,13 +3234,25 @@ impl SftpSession {
         }
         if flags.has_creat() {
             options.create(true);
+            // NIST 800-53: AC-6 - Only takes effect if the file is created
+            #[cfg(unix)]
+            options.mode(self.config.file_mode_policy.creation_mode(permissions, 0o666));
         }
+        // Mode bits are ignored on Windows
+        #[cfg(not(unix))]
+        let _ = permissions;
         if flags.has_trunc() {
             options.truncate(true);
         }
         if flags.has_excl() {
             options.create_new(true);
         }
+        // NIST 800-53: AC-3 - The path was resolved with every link checked; refuse
+        // a link swapped into the final component since then
+        #[cfg(unix)]
+        if self.config.symlinks != SymlinkPolicy::Allow {
+            options.custom_flags(libc::O_NOFOLLOW);
+        }
 
         let file = options.open(&path).await?;
         Ok(FileHandle::File(file, path))
@@ -1528,6 +3267,18 @@ impl SftpSession {
         #[cfg(unix)]
         if let Some(permissions) = attrs.permissions {
             use std::os::unix::fs::PermissionsExt;
+            // NIST 800-53: AC-6 - Never grant bits the file mode policy withholds
+            if !self.config.file_mode_policy.permits(permissions) {
+                warn!(
+                    "Refused permissions {:o} on {:?}: outside file_mode_policy",
+                    permissions & FileModePolicy::PERMISSION_BITS,
+                    path
+                );
+                return Err(Error::PermissionDenied(format!(
+                    "Permissions {:o} not allowed by server policy",
+                    permissions & FileModePolicy::PERMISSION_BITS
+                )));
+            }
             let perms = std::fs::Permissions::from_mode(permissions);
             timeout(FILE_OP_TIMEOUT, fs::set_permissions(path, perms))
                 .await
@@ -1578,11 +3329,27 @@ impl SftpSession {
 
         // Apply timestamps if specified
         if attrs.atime.is_some() || attrs.mtime.is_some() {
-            // Note: Setting atime/mtime requires platform-specific code
-            // For now, we'll use a simplified approach with filetime crate if available
-            // or log that it's not supported
-            debug!("Timestamp modification requested but not fully implemented");
-            // TODO: Implement timestamp modification using filetime crate or platform-specific APIs
+            let secs = |time: u32| std::time::UNIX_EPOCH + Duration::from_secs(u64::from(time));
+            let mut times = std::fs::FileTimes::new();
+            if let Some(atime) = attrs.atime {
+                times = times.set_accessed(secs(atime));
+            }
+            if let Some(mtime) = attrs.mtime {
+                times = times.set_modified(secs(mtime));
+            }
+            let target = path.clone();
+            let set_times = tokio::task::spawn_blocking(move || {
+                std::fs::File::open(&target)?.set_times(times)
+            });
+            timeout(FILE_OP_TIMEOUT, set_times)
+                .await
+                .map_err(|_| Error::timeout("Set times operation timed out"))?
+                .map_err(|e| Error::Other(format!("Set times task failed: {}", e)))?
+                .map_err(|e| {
+                    warn!("Failed to set times on {:?}: {}", path, e);
+                    Error::PermissionDenied(format!("Cannot set times: {}", e))
+                })?;
+            info!("Set times on {:?}", path);
         }
 
         Ok(())
@@ -1593,11 +3360,50 @@ impl SftpSession {
         self.next_handle_id += 1;
 
         let handle_id = id.to_be_bytes().to_vec();
+        self.metrics.record_handle_open(handle.is_dir());
         self.handles.insert(handle_id.clone(), handle);
         handle_id
     }
 
+    /// Failure reply if the session already holds `max_open_handles` handles
+    ///
+    /// NIST 800-53: SC-5 (Denial of Service Protection)
+    fn handle_limit_reached(&self, request_id: u32) -> Result<Option<Vec<u8>>> {
+        if self.handles.len() < self.config.max_open_handles {
+            return Ok(None);
+        }
+        warn!("Maximum open handles reached ({})", self.config.max_open_handles);
+        self.send_status_error(
+            request_id,
+            &Error::resource_exhaustion("Too many open handles"),
+        )
+        .map(Some)
+    }
+
     /// Send STATUS response with explicit code and message
+    /// Answer a packet over `max_packet_size` without parsing it
+    ///
+    /// `head` is the start of the packet; its type and request id are enough
+    /// to refuse the request once the session is initialized.
+    ///
+    /// NIST 800-53: SC-5 (Denial of Service Protection)
+    fn reject_oversized(&self, len: usize, head: &[u8]) -> Result<Vec<u8>> {
+        warn!(
+            "Rejecting {}-byte SFTP packet (limit {})",
+            len,
+            self.config.max_packet_size
+        );
+        if !self.initialized || head.len() < 5 {
+            return Err(Error::Protocol("Packet exceeds max_packet_size".into()));
+        }
+        let request_id = u32::from_be_bytes([head[1], head[2], head[3], head[4]]);
+        self.send_status(
+            request_id,
+            StatusCode::BadMessage,
+            "Packet exceeds max_packet_size",
+        )
+    }
+
     fn send_status(&self, request_id: u32, code: StatusCode, msg: &str) -> Result<Vec<u8>> {
         let mut response = BytesMut::new();
         response.put_u8(MessageType::Status as u8);
@@ -1615,6 +3421,14 @@ impl SftpSession {
     /// STIG: V-222566
     /// Implementation: Uses sanitized error messages and proper status codes
     fn send_status_error(&self, request_id: u32, error: &Error) -> Result<Vec<u8>> {
+        // NIST 800-53: SI-4 - Count failures by kind
+        match error {
+            Error::PermissionDenied(_) => self.metrics.record_permission_denied(),
+            Error::FileNotFound(_) => self.metrics.record_file_not_found(),
+            Error::Io(_) => self.metrics.record_io_error(),
+            Error::Timeout(_) => self.metrics.record_timeout_error(),
+            _ => {}
+        }
         let code = error.to_status_code();
         let msg = error.sanitized_message();
 
@@ -1637,6 +3451,17 @@ impl SftpSession {
         Ok(response.to_vec())
     }
 
+    /// Charge `bytes` of READ/WRITE payload to the bandwidth limits
+    ///
+    /// The wait is added to `throttle_delay` rather than slept here, because
+    /// the caller still holds the session lock.
+    ///
+    /// NIST 800-53: SC-5 (Denial of Service Protection)
+    fn throttle_transfer(&mut self, bytes: usize) {
+        let delay = self.throttle.reserve(bytes as u64);
+        self.throttle_delay = self.throttle_delay.max(delay);
+    }
+
     fn send_data(&self, request_id: u32, data: &[u8]) -> Result<Vec<u8>> {
         let mut response = BytesMut::new();
         response.put_u8(MessageType::Data as u8);
@@ -1650,7 +3475,7 @@ impl SftpSession {
         let mut response = BytesMut::new();
         response.put_u8(MessageType::Attrs as u8);
         response.put_u32(request_id);
-        response.put(attrs.encode());
+        response.put(attrs.encode_for(self.version));
 
         Ok(response.to_vec())
     }
@@ -1676,6 +3501,13 @@ impl SftpSession {
     }
 }
 
+/// Bytes read and written through one file handle
+#[derive(Debug, Default, Clone, Copy)]
+struct HandleBytes {
+    read: u64,
+    written: u64,
+}
+
 /// File handle types
 ///
 /// NIST 800-53: SI-11 (Error Handling)
@@ -1685,6 +3517,20 @@ enum FileHandle {
     Dir(DirHandle),
 }
 
+impl FileHandle {
+    fn is_dir(&self) -> bool {
+        matches!(self, FileHandle::Dir(_))
+    }
+
+    /// Path the handle was opened with
+    fn path(&self) -> &Path {
+        match self {
+            FileHandle::File(_, path) => path,
+            FileHandle::Dir(dir) => &dir.path,
+        }
+    }
+}
+
 impl Drop for FileHandle {
     /// NIST 800-53: SI-11 - Ensure resources are cleaned up
     fn drop(&mut self) {
@@ -1699,37 +3545,3579 @@ impl Drop for FileHandle {
     }
 }
 
-struct DirHandle {
-    entries: Vec<(String, FileAttrs)>,
-    index: usize,
+/// Read up to `len` bytes at `offset` without using the file's cursor
+///
+/// Clients pipeline READs at different offsets against one handle; with
+/// positional I/O on a duplicate of the descriptor each request carries its
+/// own offset and none can land on another's position. A short or empty
+/// result means end of file.
+pub(crate) async fn read_at(file: &fs::File, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    let file = file.try_clone().await?.into_std().await;
+    tokio::task::spawn_blocking(move || {
+        let mut buffer = vec![0u8; len];
+        let n = pread(&file, &mut buffer, offset)?;
+        buffer.truncate(n);
+        Ok(buffer)
+    })
+    .await
+    .map_err(std::io::Error::other)?
 }
 
-fn metadata_to_attrs(metadata: &std::fs::Metadata) -> FileAttrs {
-    FileAttrs {
-        size: Some(metadata.len()),
-        uid: None,
-        gid: None,
-        permissions: Some(0o644), // Default permissions
-        atime: None,
-        mtime: metadata
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs() as u32),
+/// Write all of `data` at `offset` without using the file's cursor
+///
+/// A handle opened with APPEND still appends, as with a seek and write.
+async fn write_at(file: &fs::File, offset: u64, data: Vec<u8>) -> std::io::Result<()> {
+    let file = file.try_clone().await?.into_std().await;
+    tokio::task::spawn_blocking(move || pwrite_all(&file, &data, offset))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+#[cfg(unix)]
+fn pread(file: &std::fs::File, buffer: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buffer, offset)
+}
+
+#[cfg(unix)]
+fn pwrite_all(file: &std::fs::File, data: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, data, offset)
+}
+
+// Windows moves the file pointer as well, but no read or write relies on it
+#[cfg(windows)]
+fn pread(file: &std::fs::File, buffer: &mut [u8], offset: u64) -> std::io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buffer, offset)
+}
+
+#[cfg(windows)]
+fn pwrite_all(file: &std::fs::File, mut data: &[u8], mut offset: u64) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match std::os::windows::fs::FileExt::seek_write(file, data, offset)? {
+            0 => return Err(std::io::ErrorKind::WriteZero.into()),
+            n => {
+                data = &data[n..];
+                offset += n as u64;
+            }
+        }
     }
+    Ok(())
 }
 
-async fn load_host_key(path: &Path) -> Result<PrivateKey> {
-    // For development, generate a key if it doesn't exist
-    if !path.exists() {
-        return Err(Error::Config(format!(
-            "Host key not found at {:?}. Please generate one with: ssh-keygen -t ed25519 -f {} -N \"\"",
-            path,
-            path.display()
-        )));
+/// Feed `packets` through a fresh session rooted at `root`
+///
+/// Returns the length of each reply, or `None` where the packet ended in an
+/// error. Drives the fuzz targets; a panic in here is a parser bug.
+#[cfg(any(test, feature = "fuzzing"))]
+pub(crate) async fn replay_packets(root: &Path, packets: &[&[u8]]) -> Vec<Option<usize>> {
+    let config = Arc::new(Config {
+        root_dir: root.to_path_buf(),
+        ..Config::default()
+    });
+    let Ok(audit) = AuditChannel::spawn(&config.logging.audit_channel, TracingSink) else {
+        return Vec::new();
+    };
+    let authorizer = Arc::new(StaticAuthorizer::new(config.clone()));
+    let mut session =
+        SftpSession::with_authorizer(config, authorizer, audit.clone(), Metrics::new(), None, None);
+
+    let mut replies = Vec::with_capacity(packets.len());
+    for packet in packets {
+        let reply = session.handle_sftp_packet(packet).await;
+        replies.push(reply.ok().map(|reply| reply.len()));
     }
 
-    // Load the key from file (blocking I/O, but only happens at startup)
-    russh::keys::load_secret_key(path, None)
-        .map_err(|e| Error::Config(format!("Failed to load host key: {}", e)))
+    drop(session);
+    audit.shutdown();
+    replies
+}
+
+/// Bytes of an oversized packet kept to answer it: type and request id
+const OVERSIZED_HEAD: usize = 5;
+
+/// One SFTP packet taken off the channel, without its length
+#[derive(Debug, PartialEq, Eq)]
+enum Frame {
+    Packet(BytesMut),
+    /// A packet over the limit; only its first bytes are kept, the rest is
+    /// discarded as it arrives
+    Oversized { len: usize, head: Vec<u8> },
+}
+
+/// Splits channel data into SFTP packets
+///
+/// Each packet is a uint32 length followed by that many bytes
+/// (draft-ietf-secsh-filexfer-02 section 3). SSH delivers channel data in
+/// messages that need not line up with packets, so data is buffered until a
+/// whole packet has arrived. A packet over `max_len` is never buffered.
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection)
+struct PacketReader {
+    buf: BytesMut,
+    /// Bytes of an oversized packet still to drop
+    discard: usize,
+    max_len: usize,
+}
+
+impl PacketReader {
+    fn new(max_len: usize) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            discard: 0,
+            max_len,
+        }
+    }
+
+    fn push(&mut self, mut data: &[u8]) {
+        let dropped = self.discard.min(data.len());
+        self.discard -= dropped;
+        data = &data[dropped..];
+        self.buf.extend_from_slice(data);
+    }
+
+    fn next_frame(&mut self) -> Option<Frame> {
+        if self.discard > 0 || self.buf.len() < 4 {
+            return None;
+        }
+        let len = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+
+        if len > self.max_len {
+            let head_len = len.min(OVERSIZED_HEAD);
+            if self.buf.len() < 4 + head_len {
+                return None;
+            }
+            self.buf.advance(4);
+            let head = self.buf.split_to(head_len).to_vec();
+            let dropped = (len - head_len).min(self.buf.len());
+            self.buf.advance(dropped);
+            self.discard = len - head_len - dropped;
+            return Some(Frame::Oversized { len, head });
+        }
+
+        if self.buf.len() < 4 + len {
+            return None;
+        }
+        self.buf.advance(4);
+        Some(Frame::Packet(self.buf.split_to(len)))
+    }
+}
+
+/// Destination of the SFTP responses for one channel
+trait ResponseSink {
+    /// Queue one complete SFTP message, which is sent with its length
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Close the channel
+    fn close(&mut self);
+}
+
+/// The SSH channel an SFTP subsystem runs on
+struct SessionChannel<'a> {
+    session: &'a mut Session,
+    channel: ChannelId,
+}
+
+impl ResponseSink for SessionChannel<'_> {
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| Error::Protocol("Response exceeds the SFTP length field".into()))?;
+        let mut packet = Vec::with_capacity(4 + data.len());
+        packet.extend_from_slice(&len.to_be_bytes());
+        packet.extend_from_slice(data);
+        self.session
+            .data(self.channel, CryptoVec::from(packet))
+            .map_err(|e| Error::channel_closed(format!("Failed to send response: {}", e)))
+    }
+
+    fn close(&mut self) {
+        if let Err(e) = self.session.close(self.channel) {
+            debug!("Closing channel after failed write: {}", e);
+        }
+    }
+}
+
+/// Send one response with a single channel write
+///
+/// A failed write may have put part of the message on the wire, after which
+/// the client can no longer find packet boundaries. The channel is closed
+/// and `broken` set so nothing more is written; `policy` decides whether the
+/// SSH connection goes with it.
+///
+/// NIST 800-53: SC-8 (Transmission Integrity), SI-11 (Error Handling)
+fn send_response(
+    sink: &mut impl ResponseSink,
+    broken: &mut bool,
+    policy: ChannelWriteFailure,
+    response: &[u8],
+) -> Result<()> {
+    if response.is_empty() || *broken {
+        return Ok(());
+    }
+
+    let Err(e) = sink.write(response) else {
+        return Ok(());
+    };
+    error!("Failed to send response, closing channel: {}", e);
+    *broken = true;
+    sink.close();
+
+    match policy {
+        ChannelWriteFailure::CloseChannel => Ok(()),
+        ChannelWriteFailure::Disconnect => Err(e),
+    }
+}
+
+/// Client path with empty and `.` components removed
+///
+/// None if a `..` would climb above the root. The remaining `..` components
+/// are kept so they apply to where a symbolic link leads, as on POSIX.
+/// Relative paths start at the root, which is every session's working
+/// directory.
+fn normalize_client_path(path: &str) -> Option<String> {
+    let mut depth = 0usize;
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                depth = depth.checked_sub(1)?;
+                parts.push(part);
+            }
+            _ => {
+                depth += 1;
+                parts.push(part);
+            }
+        }
+    }
+    Some(format!("/{}", parts.join("/")))
+}
+
+/// Canonical form of `path`, with components that do not exist yet appended
+///
+/// The longest existing prefix is canonicalized by the filesystem; the rest
+/// is joined lexically so a path about to be created still resolves.
+async fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(mut canonical) = fs::canonicalize(existing).await {
+            for part in missing.iter().rev() {
+                if *part == std::path::Component::ParentDir {
+                    canonical.pop();
+                } else {
+                    canonical.push(part);
+                }
+            }
+            return canonical;
+        }
+
+        let mut components = existing.components();
+        let Some(last) = components.next_back() else {
+            return path.to_path_buf();
+        };
+        missing.push(last);
+        existing = components.as_path();
+    }
+}
+
+/// Whether a request was answered with an error
+///
+/// EOF ends every READ and READDIR loop, so it does not count as one.
+fn is_error_reply(result: &Result<Vec<u8>>) -> bool {
+    match result {
+        Ok(reply) if reply.first() == Some(&(MessageType::Status as u8)) => reply
+            .get(5..9)
+            .and_then(|code| code.try_into().ok())
+            .map(u32::from_be_bytes)
+            .is_some_and(|code| code != StatusCode::Ok as u32 && code != StatusCode::Eof as u32),
+        Ok(_) => false,
+        Err(_) => true,
+    }
+}
+
+/// Handle one SFTP packet and wait out any bandwidth delay it incurred
+///
+/// The session lock is released before sleeping, so the session's other
+/// channels are not stalled by a throttled transfer.
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection)
+async fn process_packet(session: &Mutex<SftpSession>, data: &[u8]) -> Result<Vec<u8>> {
+    let (response, delay) = {
+        let mut session = session.lock().await;
+        let response = session.handle_sftp_packet(data).await;
+        if response.is_err() {
+            session.metrics.record_protocol_error();
+        }
+        session.update_read_ahead_gauge();
+        (response?, std::mem::take(&mut session.throttle_delay))
+    };
+
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    Ok(response)
+}
+
+/// Answer debug commands on a Unix socket until the server stops
+///
+/// Each connection sends one command line and receives the reply; the only
+/// command is `dump-state`. A stale socket file from an earlier run is
+/// replaced, and the new one is readable by the server's user only.
+///
+/// NIST 800-53: SI-4 (System Monitoring), AC-6 (Least Privilege)
+#[cfg(unix)]
+async fn serve_control_socket(path: &Path, handler: SftpHandler) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::io::AsyncBufReadExt;
+
+    if fs::symlink_metadata(path).await.is_ok() {
+        fs::remove_file(path).await?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    info!("Control socket listening on {:?}", path);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut command = String::new();
+            let mut reader = tokio::io::BufReader::new(reader.take(MAX_CONTROL_COMMAND_LEN));
+            if reader.read_line(&mut command).await.is_err() {
+                return;
+            }
+
+            let reply = match command.trim() {
+                "dump-state" => handler.dump_state().await,
+                other => format!("error unknown command {:?}\n", other),
+            };
+            if let Err(e) = writer.write_all(reply.as_bytes()).await {
+                debug!("Control socket client went away: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(not(unix))]
+async fn serve_control_socket(_path: &Path, _handler: SftpHandler) -> Result<()> {
+    Err(Error::NotSupported("Control sockets need Unix domain sockets".into()))
+}
+
+/// Append one SSH_FXP_NAME entry in the layout of `version`
+///
+/// Version 3 carries an `ls -l` style longname after the filename; version 4
+/// dropped it.
+fn put_name_entry(response: &mut BytesMut, version: u32, name: &str, attrs: &FileAttrs) {
+    codec::put_string(response, name);
+    if version < 4 {
+        codec::put_string(response, &attrs.longname(name));
+    }
+    response.put(attrs.encode_for(version));
+}
+
+/// Open directory listing, read lazily
+///
+/// Dropping the handle (CLOSE, or the session ending) closes the directory
+/// stream wherever the listing stopped.
+struct DirHandle {
+    path: PathBuf,
+    /// `None` once the directory has been read to the end
+    read_dir: Option<fs::ReadDir>,
+}
+
+impl DirHandle {
+    /// Up to `limit` further entries; empty once the listing is exhausted
+    ///
+    /// Entries whose metadata can no longer be read (removed since the
+    /// directory was opened) are skipped. A read error ends the listing.
+    async fn next_batch(&mut self, limit: usize) -> Vec<(String, FileAttrs)> {
+        let mut entries = Vec::new();
+        while entries.len() < limit {
+            let Some(read_dir) = self.read_dir.as_mut() else {
+                break;
+            };
+            match read_dir.next_entry().await {
+                Ok(Some(entry)) => {
+                    if let Ok(metadata) = entry.metadata().await {
+                        entries.push((
+                            entry.file_name().to_string_lossy().into_owned(),
+                            metadata_to_attrs(&metadata),
+                        ));
+                    }
+                }
+                Ok(None) => self.read_dir = None,
+                Err(e) => {
+                    debug!("Listing of {:?} ended early: {}", self.path, e);
+                    self.read_dir = None;
+                }
+            }
+        }
+        entries
+    }
+}
+
+/// SFTP attributes for `metadata`
+///
+/// On Unix the mode keeps its file-type bits, so directories report S_IFDIR
+/// and clients can tell them from files, and the numeric owner and group
+/// are filled in. Elsewhere the mode is derived from the file type and the
+/// read-only flag, and ownership is left out.
+fn metadata_to_attrs(metadata: &std::fs::Metadata) -> FileAttrs {
+    #[cfg(unix)]
+    let (permissions, uid, gid) = {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.mode(), Some(metadata.uid()), Some(metadata.gid()))
+    };
+
+    #[cfg(not(unix))]
+    let (permissions, uid, gid) = {
+        let file_type = metadata.file_type();
+        let mut mode = if file_type.is_dir() {
+            0o040_755
+        } else if file_type.is_symlink() {
+            0o120_777
+        } else {
+            0o100_644
+        };
+        if metadata.permissions().readonly() {
+            mode &= !0o222;
+        }
+        (mode, None, None)
+    };
+    let mtime = unix_secs(metadata.modified());
+
+    FileAttrs {
+        size: Some(metadata.len()),
+        uid,
+        gid,
+        permissions: Some(permissions),
+        // Version 3 sends the two times together or not at all
+        atime: unix_secs(metadata.accessed()).or(mtime),
+        mtime,
+    }
+}
+
+/// Seconds since the Unix epoch for a file time, if the platform has it
+fn unix_secs(time: std::io::Result<std::time::SystemTime>) -> Option<u32> {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as u32)
+}
+
+/// Start the audit writer delivering to `sink`, also appending each event to
+/// `logging.audit_file` when one is configured
+///
+/// NIST 800-53: AU-9 (Protection of Audit Information), AU-12 (Audit Generation)
+fn spawn_audit_channel(config: &Config, sink: impl AuditSink) -> Result<AuditChannel> {
+    let channel = &config.logging.audit_channel;
+    match &config.logging.audit_file {
+        Some(path) => AuditChannel::spawn(channel, JsonFileSink::open(path, sink)?),
+        None => AuditChannel::spawn(channel, sink),
+    }
+}
+
+/// Load the host keys under `paths`
+///
+/// A directory contributes every file in it, in name order, that parses as a
+/// private key (`.pub` files are passed over). Keys whose algorithm is not a
+/// CNSA 2.0 host key algorithm (RSA included) are skipped, as is a second key
+/// for an algorithm already loaded, since only the first would ever be
+/// offered. If no key remains, the error names every key that was rejected.
+///
+/// NIST 800-53: IA-3 (Device Identification), SC-13 (Cryptographic Protection)
+async fn load_host_keys(paths: &[PathBuf]) -> Result<Vec<PrivateKey>> {
+    let mut keys: Vec<PrivateKey> = Vec::new();
+    let mut rejected = Vec::new();
+    for path in paths {
+        let mut loaded = Vec::new();
+        if path.is_dir() {
+            let mut files = Vec::new();
+            let mut entries = fs::read_dir(path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file = entry.path();
+                if file.is_file() && file.extension().is_none_or(|ext| ext != "pub") {
+                    files.push(file);
+                }
+            }
+            files.sort();
+            for file in files {
+                match russh::keys::load_secret_key(&file, None) {
+                    Ok(key) => loaded.push((file, key)),
+                    Err(e) => debug!("Not a host key, skipping {:?}: {}", file, e),
+                }
+            }
+        } else {
+            loaded.push((path.clone(), load_host_key(path).await?));
+        }
+
+        for (file, key) in loaded {
+            let algorithm = key.algorithm();
+            if !cnsa::is_host_key_compliant(&algorithm) {
+                warn!(
+                    "Skipping host key {:?}: {} is not a CNSA 2.0 host key algorithm",
+                    file,
+                    algorithm.as_str()
+                );
+                rejected.push(format!("{} ({})", file.display(), algorithm.as_str()));
+            } else if keys.iter().any(|k| k.algorithm() == algorithm) {
+                warn!(
+                    "Skipping host key {:?}: a {} key is already loaded",
+                    file,
+                    algorithm.as_str()
+                );
+            } else {
+                keys.push(key);
+            }
+        }
+    }
+
+    if keys.is_empty() {
+        let mut message =
+            "No CNSA 2.0 host key (ECDSA P-384 or Ed25519) found in host_key_path".to_string();
+        if !rejected.is_empty() {
+            let _ = write!(
+                message,
+                "; rejected {}, which CNSA 2.0 does not allow",
+                rejected.join(", ")
+            );
+        }
+        return Err(Error::Config(message));
+    }
+    Ok(keys)
+}
+
+async fn load_host_key(path: &Path) -> Result<PrivateKey> {
+    // For development, generate a key if it doesn't exist
+    if !path.exists() {
+        return Err(Error::Config(format!(
+            "Host key not found at {:?}. Please generate one with: ssh-keygen -t ed25519 -f {} -N \"\"",
+            path,
+            path.display()
+        )));
+    }
+
+    // Load the key from file (blocking I/O, but only happens at startup)
+    russh::keys::load_secret_key(path, None)
+        .map_err(|e| Error::Config(format!("Failed to load host key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{HostKeyPaths, PathRule, RoleConfig, UserConfig};
+    use bytes::Buf;
+    use tempfile::TempDir;
+
+    /// Session rooted in a fresh temp directory, already past INIT
+    async fn session() -> (SftpSession, TempDir) {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        let mut session = SftpSession::new(Arc::new(config));
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+        (session, root)
+    }
+
+    fn init_packet() -> Vec<u8> {
+        init_packet_version(SFTP_VERSION)
+    }
+
+    fn init_packet_version(version: u32) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Init as u8);
+        packet.put_u32(version);
+        packet.to_vec()
+    }
+
+    /// Raw SSH_FXP_EXTENDED packet with string arguments
+    fn extended_packet(request_id: u32, extension: &str, args: &[&[u8]]) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Extended as u8);
+        packet.put_u32(request_id);
+        codec::put_string(&mut packet, extension);
+        for arg in args {
+            codec::put_bytes(&mut packet, arg);
+        }
+        packet.to_vec()
+    }
+
+    /// Decode a STATUS reply into (request id, status code)
+    fn parse_status(reply: &[u8]) -> (u32, u32) {
+        assert_eq!(reply[0], MessageType::Status as u8);
+        let mut buf = &reply[1..];
+        let request_id = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        buf = &buf[4..];
+        let code = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        (request_id, code)
+    }
+
+    #[tokio::test]
+    async fn test_version_advertises_extensions() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        let mut session = SftpSession::new(Arc::new(config));
+
+        let reply = session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+
+        assert_eq!(reply[0], MessageType::Version as u8);
+        let mut buf = &reply[5..];
+        let mut extensions = Vec::new();
+        while !buf.is_empty() {
+            let name = codec::get_string(&mut buf).expect("extension name");
+            let data = codec::get_string(&mut buf).expect("extension data");
+            extensions.push((name, data));
+        }
+        assert!(extensions.contains(&(EXT_HARDLINK.to_string(), "1".to_string())));
+        assert!(extensions.contains(&(EXT_FSYNC.to_string(), "1".to_string())));
+        assert!(extensions.contains(&(EXT_POSIX_RENAME.to_string(), "1".to_string())));
+        assert!(extensions.contains(&(EXT_LIMITS.to_string(), "1".to_string())));
+        assert!(extensions.contains(&(EXT_STAT_FOR_RESUME.to_string(), "1".to_string())));
+        assert!(extensions.contains(&(EXT_SERVER_INFO.to_string(), "1".to_string())));
+    }
+
+    /// Send `server-info@snow-owl.dev` and decode the reply
+    async fn server_info(session: &mut SftpSession, request_id: u32) -> ServerInfo {
+        let packet = extended_packet(request_id, EXT_SERVER_INFO, &[]);
+        let reply = session.handle_sftp_packet(&packet).await.expect("EXTENDED failed");
+        assert_eq!(reply[0], MessageType::ExtendedReply as u8);
+        assert_eq!(u32::from_be_bytes([reply[1], reply[2], reply[3], reply[4]]), request_id);
+        let mut buf = &reply[5..];
+        let json = codec::get_string(&mut buf).expect("server info");
+        assert!(buf.is_empty());
+        serde_json::from_str(&json).expect("server info JSON")
+    }
+
+    #[tokio::test]
+    async fn test_server_info_describes_session() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let mut session = session_with(&root, |config| {
+            config.readdir_batch_size = 250;
+            config.max_open_handles = 64;
+            config.users.insert(
+                "auditor".to_string(),
+                crate::config::UserConfig {
+                    read_only: true,
+                    max_file_size: 1 << 30,
+                    ..Default::default()
+                },
+            );
+        })
+        .await;
+
+        let info = server_info(&mut session, 1).await;
+        assert_eq!(info.server_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.protocol_version, SFTP_VERSION);
+        assert_eq!(info.supported_versions, (SFTP_VERSION, MAX_SFTP_VERSION));
+        let advertised: Vec<&str> = SUPPORTED_EXTENSIONS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(info.extensions, advertised);
+        assert_eq!(info.max_read_length, Config::default().max_read_len);
+        assert_eq!(info.max_open_handles, 64);
+        assert_eq!(info.readdir_batch_size, 250);
+        assert_eq!(info.max_packet_size, Config::default().max_packet_size);
+        assert!(!info.read_only);
+        assert_eq!(info.max_file_size, 0);
+
+        // Per-user limits follow the authenticated user
+        session.session_info.set_username("auditor".to_string());
+        let info = server_info(&mut session, 2).await;
+        assert!(info.read_only);
+        assert_eq!(info.max_file_size, 1 << 30);
+    }
+
+    #[tokio::test]
+    async fn test_limits_report_configured_values() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let mut session = session_with(&root, |config| {
+            config.max_packet_size = 65536;
+            config.max_read_len = 32768;
+            config.max_open_handles = 16;
+        })
+        .await;
+
+        let reply = session
+            .handle_sftp_packet(&extended_packet(7, EXT_LIMITS, &[]))
+            .await
+            .expect("EXTENDED failed");
+        assert_eq!(reply[0], MessageType::ExtendedReply as u8);
+        let mut buf = &reply[1..];
+        assert_eq!(buf.get_u32(), 7);
+        let limits: Vec<u64> = (0..4).map(|_| buf.get_u64()).collect();
+        assert!(buf.is_empty());
+        // Max packet, READ and WRITE lengths, open handles
+        assert_eq!(limits, [65536, 32768, 65536 - 1024, 16]);
+    }
+
+    #[tokio::test]
+    async fn test_hardlink_status_replies() {
+        let (mut session, root) = session().await;
+        std::fs::write(root.path().join("source"), b"data").expect("write source");
+        std::fs::write(root.path().join("taken"), b"other").expect("write taken");
+
+        // Success: both names refer to the same file
+        let reply = session
+            .handle_sftp_packet(&extended_packet(1, EXT_HARDLINK, &[b"/source", b"/link"]))
+            .await
+            .expect("hardlink");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+        assert_eq!(
+            std::fs::read(root.path().join("link")).expect("read link"),
+            b"data"
+        );
+
+        // Existing target
+        let reply = session
+            .handle_sftp_packet(&extended_packet(2, EXT_HARDLINK, &[b"/source", b"/taken"]))
+            .await
+            .expect("hardlink");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Failure as u32));
+
+        // Missing source
+        let reply = session
+            .handle_sftp_packet(&extended_packet(3, EXT_HARDLINK, &[b"/missing", b"/new"]))
+            .await
+            .expect("hardlink");
+        assert_eq!(parse_status(&reply), (3, StatusCode::NoSuchFile as u32));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_posix_rename_replaces_existing_target() {
+        use std::io::Read;
+        use std::os::unix::fs::MetadataExt;
+
+        let (mut session, root) = session().await;
+        std::fs::write(root.path().join("config.new"), b"new").expect("write source");
+        std::fs::write(root.path().join("config"), b"old").expect("write target");
+        let new_inode = std::fs::metadata(root.path().join("config.new"))
+            .expect("stat source")
+            .ino();
+        // A reader that opened the target before the rename
+        let mut reader = std::fs::File::open(root.path().join("config")).expect("open target");
+
+        let reply = session
+            .handle_sftp_packet(&extended_packet(
+                1,
+                EXT_POSIX_RENAME,
+                &[b"/config.new", b"/config"],
+            ))
+            .await
+            .expect("posix-rename");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+
+        // The name now refers to the source inode: it was swapped, not
+        // truncated and rewritten, and the old reader still sees old data
+        let target = std::fs::metadata(root.path().join("config")).expect("stat target");
+        assert_eq!(target.ino(), new_inode);
+        assert_eq!(
+            std::fs::read(root.path().join("config")).expect("read target"),
+            b"new"
+        );
+        assert!(!root.path().join("config.new").exists());
+        let mut old = Vec::new();
+        reader.read_to_end(&mut old).expect("read old target");
+        assert_eq!(old, b"old");
+
+        // Missing source
+        let reply = session
+            .handle_sftp_packet(&extended_packet(2, EXT_POSIX_RENAME, &[b"/missing", b"/config"]))
+            .await
+            .expect("posix-rename");
+        assert_eq!(parse_status(&reply), (2, StatusCode::NoSuchFile as u32));
+    }
+
+    #[tokio::test]
+    async fn test_fsync_status_replies() {
+        let (mut session, root) = session().await;
+
+        let mut open = BytesMut::new();
+        open.put_u8(MessageType::Open as u8);
+        open.put_u32(1);
+        codec::put_string(&mut open, "/durable");
+        open.put_u32(OpenFlags::WRITE | OpenFlags::CREAT);
+        open.put(FileAttrs::default().encode());
+        let reply = session.handle_sftp_packet(&open).await.expect("open");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+        let mut buf = &reply[5..];
+        let handle = codec::get_bytes(&mut buf).expect("handle");
+
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(2, &handle, 0, b"install.wim"))
+            .await
+            .expect("write");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+
+        let reply = session
+            .handle_sftp_packet(&extended_packet(3, EXT_FSYNC, &[&handle]))
+            .await
+            .expect("fsync");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+        assert_eq!(
+            std::fs::read(root.path().join("durable")).expect("read back"),
+            b"install.wim"
+        );
+
+        let reply = session
+            .handle_sftp_packet(&extended_packet(4, EXT_FSYNC, &[b"bogus"]))
+            .await
+            .expect("fsync");
+        assert_eq!(parse_status(&reply), (4, StatusCode::BadMessage as u32));
+
+        // Directory handles are refused rather than silently synced
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Opendir, 5, &["/"]))
+            .await
+            .expect("opendir");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+        let mut buf = &reply[5..];
+        let dir_handle = codec::get_bytes(&mut buf).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&extended_packet(6, EXT_FSYNC, &[&dir_handle]))
+            .await
+            .expect("fsync");
+        assert_eq!(parse_status(&reply), (6, StatusCode::BadMessage as u32));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_extension_is_unsupported() {
+        let (mut session, _root) = session().await;
+
+        let reply = session
+            .handle_sftp_packet(&extended_packet(7, "statvfs@openssh.com", &[b"/"]))
+            .await
+            .expect("unknown extension must not end the session");
+
+        assert_eq!(parse_status(&reply), (7, StatusCode::OpUnsupported as u32));
+    }
+
+    #[tokio::test]
+    async fn test_overlong_path_rejected_early() {
+        let (session, _root) = session().await;
+        let path = format!("/{}", ["dir"; 2000].join("/"));
+
+        let err = session.resolve_path(&path).expect_err("over-long path");
+
+        assert!(matches!(err, Error::InvalidPath(ref msg) if msg == "Path too long"));
+    }
+
+    #[tokio::test]
+    async fn test_overlong_component_rejected_early() {
+        let (session, _root) = session().await;
+        let path = format!("/upload/{}", "a".repeat(256));
+
+        let err = session.resolve_path(&path).expect_err("over-long component");
+
+        assert!(matches!(err, Error::InvalidPath(ref msg) if msg == "File name too long"));
+        assert!(session
+            .resolve_path(&format!("/upload/{}", "a".repeat(255)))
+            .is_ok());
+    }
+
+    /// Denies anything that modifies files under /secret
+    struct SecretAuthorizer;
+
+    #[async_trait::async_trait]
+    impl Authorizer for SecretAuthorizer {
+        async fn authorize(&self, ctx: &OperationContext) -> crate::Decision {
+            let touches_secret = std::iter::once(&ctx.path)
+                .chain(ctx.target_path.as_ref())
+                .any(|path| path.starts_with("/secret"));
+            if touches_secret && !ctx.is_read_only() {
+                crate::Decision::deny("secret is write-protected\r\n")
+            } else {
+                crate::Decision::Allow
+            }
+        }
+    }
+
+    fn open_packet(request_id: u32, path: &str, pflags: u32) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Open as u8);
+        packet.put_u32(request_id);
+        codec::put_string(&mut packet, path);
+        packet.put_u32(pflags);
+        packet.put(FileAttrs::default().encode());
+        packet.to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_authorizer_denies_writes_under_secret() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir(root.path().join("secret")).expect("mkdir secret");
+        std::fs::write(root.path().join("secret/key.txt"), b"k").expect("write key");
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        let audit = AuditChannel::spawn(&config.logging.audit_channel, TracingSink)
+            .expect("Failed to start audit writer");
+        let mut session = SftpSession::with_authorizer(
+            Arc::new(config),
+            Arc::new(SecretAuthorizer),
+            audit,
+            Metrics::new(),
+            None,
+            None,
+        );
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+
+        let write = OpenFlags::WRITE | OpenFlags::CREAT;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/secret/new.txt", write))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::PermissionDenied as u32));
+        let mut message = &reply[9..];
+        assert_eq!(
+            codec::get_string(&mut message).expect("status message"),
+            "Permission denied: secret is write-protected"
+        );
+        assert!(!root.path().join("secret/new.txt").exists());
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(2, "/secret/key.txt", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(3, "/public.txt", write))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+        assert!(root.path().join("public.txt").exists());
+    }
+
+    /// Session past INIT over `root` with `configure` applied to the config
+    async fn session_with(root: &TempDir, configure: impl FnOnce(&mut Config)) -> SftpSession {
+        let mut config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        configure(&mut config);
+        let mut session = SftpSession::new(Arc::new(config));
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+        session
+    }
+
+    /// Sink keeping every file and directory event it is given
+    #[derive(Clone, Default)]
+    struct FileEventSink(Arc<std::sync::Mutex<Vec<AuditEvent>>>);
+
+    impl AuditSink for FileEventSink {
+        fn write_batch(&mut self, events: &[AuditEvent]) {
+            let mut kept = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            kept.extend(events.iter().cloned());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_operations_are_audited() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let config = Arc::new(Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        });
+        let sink = FileEventSink::default();
+        let audit = AuditChannel::spawn(&config.logging.audit_channel, sink.clone())
+            .expect("Failed to start audit writer");
+        let client_ip: IpAddr = "192.0.2.10".parse().expect("ip");
+        let mut session = SftpSession::with_authorizer(
+            config.clone(),
+            Arc::new(StaticAuthorizer::new(config)),
+            audit.clone(),
+            Metrics::new(),
+            None,
+            Some(client_ip),
+        );
+        session.session_info.set_username("deploy".to_string());
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT | OpenFlags::TRUNC;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/upload.txt", flags))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(2, &handle, 0, b"hello"))
+            .await
+            .expect("WRITE failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+        let reply = session
+            .handle_sftp_packet(&handle_packet(MessageType::Close, 3, &handle))
+            .await
+            .expect("CLOSE failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Remove, 4, &["/upload.txt"]))
+            .await
+            .expect("REMOVE failed");
+        assert_eq!(parse_status(&reply), (4, StatusCode::Ok as u32));
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Remove, 5, &["/upload.txt"]))
+            .await
+            .expect("REMOVE failed");
+        assert_eq!(parse_status(&reply), (5, StatusCode::NoSuchFile as u32));
+        drop(session);
+        audit.shutdown();
+
+        let events = sink.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let operations: Vec<_> = events
+            .iter()
+            .map(|event| match event {
+                AuditEvent::FileOperation {
+                    client_ip: ip,
+                    username,
+                    operation,
+                    path,
+                    success,
+                    bytes_transferred,
+                    ..
+                } => {
+                    assert_eq!(*ip, Some(client_ip));
+                    assert_eq!(username.as_deref(), Some("deploy"));
+                    assert_eq!(path, "/upload.txt");
+                    (operation.as_str(), *success, *bytes_transferred)
+                }
+                other => panic!("unexpected audit event {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            operations,
+            [
+                ("OPEN", true, None),
+                ("WRITE", true, Some(5)),
+                ("CLOSE", true, None),
+                ("DELETE", true, None),
+                ("DELETE", false, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_writes_update_metrics() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("boot.wim"), vec![7u8; 3000]).expect("write");
+        let config = Arc::new(Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        });
+        let metrics = Metrics::new();
+        let audit = AuditChannel::spawn(&config.logging.audit_channel, TracingSink)
+            .expect("Failed to start audit writer");
+        let mut session = SftpSession::with_authorizer(
+            config.clone(),
+            Arc::new(StaticAuthorizer::new(config)),
+            audit.clone(),
+            metrics.clone(),
+            None,
+            None,
+        );
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/boot.wim", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+        for (request_id, offset) in [(2, 0), (3, 2048), (4, 3000)] {
+            session
+                .handle_sftp_packet(&read_packet(request_id, &handle, offset, 2048))
+                .await
+                .expect("READ failed");
+        }
+        session
+            .handle_sftp_packet(&handle_packet(MessageType::Close, 5, &handle))
+            .await
+            .expect("CLOSE failed");
+
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT | OpenFlags::TRUNC;
+        let reply = session
+            .handle_sftp_packet(&open_packet(6, "/upload.log", flags))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+        session
+            .handle_sftp_packet(&write_at_packet(7, &handle, 0, b"deployed"))
+            .await
+            .expect("WRITE failed");
+        session
+            .handle_sftp_packet(&open_packet(8, "/missing.txt", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.bytes_read, 3000);
+        assert_eq!(snapshot.bytes_written, 8);
+        // The READ at end of file moved no data
+        assert_eq!(snapshot.file_reads, 2);
+        assert_eq!(snapshot.file_writes, 1);
+        assert_eq!(snapshot.file_opens, 3);
+        assert_eq!(snapshot.file_closes, 1);
+        assert_eq!(snapshot.file_not_found, 1);
+        let requests: Vec<_> = snapshot
+            .requests_by_type
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect();
+        assert_eq!(
+            requests,
+            [("close", 1), ("init", 1), ("open", 3), ("read", 3), ("write", 1)]
+        );
+
+        // EOF ends the READ loop without counting as an error
+        let errors: Vec<_> = snapshot
+            .request_errors_by_type
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect();
+        assert_eq!(errors, [("open", 1)]);
+        for (kind, count) in &snapshot.requests_by_type {
+            let latency = &snapshot.request_latency_by_type[kind];
+            assert_eq!(latency.count, *count, "{}", kind);
+            assert_eq!(latency.buckets.iter().sum::<u64>(), *count, "{}", kind);
+        }
+
+        let exposition = metrics.render_prometheus();
+        assert!(exposition.contains("snow_owl_sftp_read_bytes_total 3000\n"));
+        assert!(exposition.contains("snow_owl_sftp_request_errors_total{type=\"open\"} 1\n"));
+        assert!(exposition
+            .contains("snow_owl_sftp_request_duration_seconds_bucket{type=\"read\",le=\"+Inf\"} 3\n"));
+        assert!(exposition.contains("snow_owl_sftp_request_duration_seconds_count{type=\"read\"} 3\n"));
+        drop(session);
+        audit.shutdown();
+    }
+
+    /// Request carrying only path arguments (REMOVE, RMDIR, RENAME)
+    fn paths_packet(kind: MessageType, request_id: u32, paths: &[&str]) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(kind as u8);
+        packet.put_u32(request_id);
+        for path in paths {
+            codec::put_string(&mut packet, path);
+        }
+        packet.to_vec()
+    }
+
+    fn mkdir_packet(request_id: u32, path: &str) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Mkdir as u8);
+        packet.put_u32(request_id);
+        codec::put_string(&mut packet, path);
+        packet.put(FileAttrs::default().encode());
+        packet.to_vec()
+    }
+
+    /// Status code and message of a STATUS reply
+    fn status_message(reply: &[u8]) -> (u32, String) {
+        let (_, code) = parse_status(reply);
+        let mut message = &reply[9..];
+        (code, codec::get_string(&mut message).expect("status message"))
+    }
+
+    /// `**` read-only, `uploads/**` fully writable
+    fn uploads_only(config: &mut Config) {
+        config.path_rules = vec![
+            PathRule {
+                pattern: "**".to_string(),
+                read: true,
+                write: false,
+                delete: false,
+            },
+            PathRule {
+                pattern: "uploads/**".to_string(),
+                read: true,
+                write: true,
+                delete: true,
+            },
+        ];
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_denies_modifications() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("image.wim"), b"wim").expect("write image");
+        let mut session = session_with(&root, |config| config.read_only = true).await;
+        let denied = (
+            StatusCode::PermissionDenied as u32,
+            "Permission denied: server is read-only".to_string(),
+        );
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/image.wim", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("file handle");
+
+        let mut write_on_handle = BytesMut::new();
+        write_on_handle.put_u8(MessageType::Write as u8);
+        write_on_handle.put_u32(6);
+        codec::put_bytes(&mut write_on_handle, &handle);
+        write_on_handle.put_u64(0);
+        codec::put_bytes(&mut write_on_handle, b"bad");
+
+        let write = OpenFlags::WRITE | OpenFlags::TRUNC;
+        let requests = [
+            open_packet(2, "/image.wim", write),
+            paths_packet(MessageType::Remove, 3, &["/image.wim"]),
+            paths_packet(MessageType::Rename, 4, &["/image.wim", "/moved.wim"]),
+            mkdir_packet(5, "/new"),
+            write_on_handle.to_vec(),
+        ];
+        for request in &requests {
+            let reply = session.handle_sftp_packet(request).await.expect("request failed");
+            assert_eq!(status_message(&reply), denied);
+        }
+
+        assert_eq!(
+            std::fs::read(root.path().join("image.wim")).expect("read image"),
+            b"wim"
+        );
+        assert!(!root.path().join("moved.wim").exists());
+        assert!(!root.path().join("new").exists());
+    }
+
+    #[tokio::test]
+    async fn test_roles_restrict_their_users() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("image.wim"), b"wim").expect("write image");
+        let mut session = session_with(&root, |config| {
+            config.roles.insert(
+                "upload".to_string(),
+                RoleConfig {
+                    denied_operations: vec!["remove".to_string(), "rename".to_string()],
+                    ..RoleConfig::default()
+                },
+            );
+            config.users.insert(
+                "builder".to_string(),
+                UserConfig {
+                    role: Some("upload".to_string()),
+                    ..UserConfig::default()
+                },
+            );
+        })
+        .await;
+        session.session_info.set_username("builder".to_string());
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(
+                1,
+                "/new.wim",
+                OpenFlags::WRITE | OpenFlags::CREAT,
+            ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Remove, 2, &["/image.wim"]))
+            .await
+            .expect("REMOVE failed");
+        assert_eq!(
+            status_message(&reply),
+            (
+                StatusCode::PermissionDenied as u32,
+                "Permission denied: remove not permitted for this account".to_string()
+            )
+        );
+        assert!(root.path().join("image.wim").exists());
+    }
+
+    #[tokio::test]
+    async fn test_path_rule_allows_writes_only_under_uploads() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir(root.path().join("uploads")).expect("mkdir uploads");
+        std::fs::create_dir(root.path().join("images")).expect("mkdir images");
+        let mut session = session_with(&root, uploads_only).await;
+        let write = OpenFlags::WRITE | OpenFlags::CREAT;
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/uploads/new.bin", write))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(2, "/images/new.bin", write))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(
+            status_message(&reply),
+            (
+                StatusCode::PermissionDenied as u32,
+                "Permission denied: write not permitted on /images/new.bin".to_string()
+            )
+        );
+        assert!(!root.path().join("images/new.bin").exists());
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Remove, 3, &["/uploads/new.bin"]))
+            .await
+            .expect("REMOVE failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+    }
+
+    #[tokio::test]
+    async fn test_rename_needs_write_access_to_destination() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir(root.path().join("uploads")).expect("mkdir uploads");
+        std::fs::create_dir(root.path().join("images")).expect("mkdir images");
+        std::fs::write(root.path().join("uploads/a.wim"), b"wim").expect("write upload");
+        let mut session = session_with(&root, uploads_only).await;
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Rename,
+                1,
+                &["/uploads/a.wim", "/images/a.wim"],
+            ))
+            .await
+            .expect("RENAME failed");
+        assert_eq!(
+            status_message(&reply),
+            (
+                StatusCode::PermissionDenied as u32,
+                "Permission denied: write not permitted on /images/a.wim".to_string()
+            )
+        );
+        assert!(root.path().join("uploads/a.wim").exists());
+        assert!(!root.path().join("images/a.wim").exists());
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Rename,
+                2,
+                &["/uploads/a.wim", "/uploads/b.wim"],
+            ))
+            .await
+            .expect("RENAME failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+    }
+
+    /// First name of a NAME reply
+    fn first_name(reply: &[u8]) -> String {
+        assert_eq!(reply[0], MessageType::Name as u8);
+        let mut buf = &reply[9..];
+        codec::get_string(&mut buf).expect("name")
+    }
+
+    /// Name REALPATH reports for `path`
+    async fn realpath(session: &mut SftpSession, path: &str) -> String {
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Realpath, 1, &[path]))
+            .await
+            .expect("REALPATH failed");
+        first_name(&reply)
+    }
+
+    #[tokio::test]
+    async fn test_realpath_canonicalizes_within_root() {
+        let root = link_tree();
+        let mut session = session_with(&root, |_| {}).await;
+
+        assert_eq!(realpath(&mut session, ".").await, "/");
+        assert_eq!(realpath(&mut session, "").await, "/");
+        assert_eq!(realpath(&mut session, "dir/./sub/").await, "/dir/sub");
+        assert_eq!(realpath(&mut session, "dir/../dir/sub").await, "/dir/sub");
+        assert_eq!(
+            realpath(&mut session, "/dir/sub/../inside.txt").await,
+            "/dir/inside.txt"
+        );
+        // Missing components are kept so a file about to be created resolves
+        assert_eq!(
+            realpath(&mut session, "dir/new/../new.txt").await,
+            "/dir/new.txt"
+        );
+        assert_eq!(realpath(&mut session, "dir/sub/..").await, "/dir");
+        assert_eq!(realpath(&mut session, "/dir/sub/../..").await, "/");
+    }
+
+    #[tokio::test]
+    async fn test_realpath_refuses_escaping_root() {
+        let root = link_tree();
+        let mut session = session_with(&root, |_| {}).await;
+
+        let escapes = [
+            (1, ".."),
+            (2, "/../dir"),
+            (3, "dir/../../dir"),
+            (4, "dir/sub/../../.."),
+        ];
+        for (request_id, path) in escapes {
+            let reply = session
+                .handle_sftp_packet(&paths_packet(MessageType::Realpath, request_id, &[path]))
+                .await
+                .expect("REALPATH failed");
+            assert_eq!(
+                parse_status(&reply),
+                (request_id, StatusCode::PermissionDenied as u32),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_realpath_attrs_for_existing_paths() {
+        let root = link_tree();
+        let mut session = session_with(&root, |_| {}).await;
+
+        let name_attrs = |reply: &[u8]| {
+            assert_eq!(reply[0], MessageType::Name as u8);
+            let mut buf = &reply[9..];
+            codec::get_string(&mut buf).expect("filename");
+            codec::get_string(&mut buf).expect("longname");
+            FileAttrs::decode(&mut buf).expect("attrs")
+        };
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Realpath, 1, &["dir/inside.txt"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(name_attrs(&reply).size, Some(6));
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Realpath, 2, &["dir/missing.txt"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(first_name(&reply), "/dir/missing.txt");
+        assert_eq!(name_attrs(&reply).size, None);
+    }
+
+    #[tokio::test]
+    async fn test_realpath_v6_control_byte_and_compose_paths() {
+        let root = link_tree();
+        let mut session = session_with(&root, |_| {}).await;
+
+        let request = |request_id: u32, control: u8, paths: &[&str]| {
+            let packet = paths_packet(MessageType::Realpath, request_id, &paths[..1]);
+            let mut packet = BytesMut::from(&packet[..]);
+            packet.put_u8(control);
+            for path in &paths[1..] {
+                codec::put_string(&mut packet, path);
+            }
+            packet.to_vec()
+        };
+
+        let reply = session
+            .handle_sftp_packet(&request(1, RealpathControl::NO_CHECK, &["/dir", "sub", "../new"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(first_name(&reply), "/dir/new");
+
+        // An absolute compose path replaces what came before
+        let reply = session
+            .handle_sftp_packet(&request(2, RealpathControl::STAT_IF, &["/dir", "/dir/sub"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(first_name(&reply), "/dir/sub");
+
+        let reply = session
+            .handle_sftp_packet(&request(3, RealpathControl::STAT_ALWAYS, &["/dir/missing"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::NoSuchFile as u32));
+
+        let reply = session
+            .handle_sftp_packet(&request(4, 0x7f, &["/dir"]))
+            .await
+            .expect("REALPATH failed");
+        assert_eq!(parse_status(&reply), (4, StatusCode::BadMessage as u32));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_realpath_expands_symlink_components() {
+        let root = link_tree();
+        std::os::unix::fs::symlink("dir/sub", root.path().join("shortcut"))
+            .expect("symlink shortcut");
+        let mut session = session_with(&root, |config| {
+            config.symlinks = SymlinkPolicy::InternalOnly;
+        })
+        .await;
+
+        assert_eq!(realpath(&mut session, "/shortcut").await, "/dir/sub");
+        // `..` applies to where the link leads, as on POSIX
+        assert_eq!(
+            realpath(&mut session, "/shortcut/../inside.txt").await,
+            "/dir/inside.txt"
+        );
+        assert_eq!(
+            realpath(&mut session, "shortcut/new.txt").await,
+            "/dir/sub/new.txt"
+        );
+
+        // With links unrestricted, one leading outside is reported as the root
+        let outside = TempDir::new().expect("Failed to create temp dir");
+        std::os::unix::fs::symlink(outside.path(), root.path().join("away"))
+            .expect("symlink away");
+        let mut session = session_with(&root, |config| {
+            config.symlinks = SymlinkPolicy::Allow;
+        })
+        .await;
+        assert_eq!(realpath(&mut session, "/away").await, "/");
+        assert_eq!(realpath(&mut session, "/shortcut").await, "/dir/sub");
+    }
+
+    #[tokio::test]
+    async fn test_realpath_longname_describes_target() {
+        let root = link_tree();
+        let mut session = session_with(&root, |_| {}).await;
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Realpath, 1, &["dir/inside.txt"]))
+            .await
+            .expect("REALPATH failed");
+        let mut buf = &reply[9..];
+        assert_eq!(codec::get_string(&mut buf).expect("name"), "/dir/inside.txt");
+        let longname = codec::get_string(&mut buf).expect("longname");
+        assert_eq!(FileAttrs::decode(&mut buf).expect("attrs").size, Some(6));
+
+        // Mode, links, owner, group, size, three date fields, name
+        let fields: Vec<&str> = longname.split_whitespace().collect();
+        assert_eq!(fields.len(), 9, "{}", longname);
+        assert!(fields[0].starts_with("-rw"), "{}", longname);
+        assert_eq!(fields[4], "6");
+        assert_eq!(fields[8], "/dir/inside.txt");
+    }
+
+    /// `<root>/dir/inside.txt` and the directory `<root>/dir/sub`
+    fn link_tree() -> TempDir {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir_all(root.path().join("dir/sub")).expect("mkdir dir/sub");
+        std::fs::write(root.path().join("dir/inside.txt"), b"inside").expect("write inside");
+        root
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_internal_relative_symlink_is_followed() {
+        let root = link_tree();
+        let mut session = session_with(&root, |config| {
+            config.symlinks = SymlinkPolicy::InternalOnly;
+        })
+        .await;
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                1,
+                &["/dir/sub/link", "../inside.txt"],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(2, "/dir/sub/link", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+
+        // Absolute targets are stored under the root and read back mount-relative
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                3,
+                &["/abs", "/dir/inside.txt"],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+        assert_eq!(
+            std::fs::read_link(root.path().join("abs")).expect("read_link"),
+            root.path().join("dir/inside.txt")
+        );
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Readlink, 4, &["/abs"]))
+            .await
+            .expect("READLINK failed");
+        assert_eq!(first_name(&reply), "/dir/inside.txt");
+
+        // Targets resolving outside the root are refused at creation
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                5,
+                &["/dir/out", "../../.."],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (5, StatusCode::PermissionDenied as u32));
+        assert!(std::fs::symlink_metadata(root.path().join("dir/out")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_escaping_after_rename_is_blocked() {
+        let root = link_tree();
+        let mut session = session_with(&root, |config| {
+            config.symlinks = SymlinkPolicy::InternalOnly;
+        })
+        .await;
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                1,
+                &["/dir/sub/link", "../../dir/inside.txt"],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+
+        // Moving sub up one level makes ../../dir/inside.txt point above the root
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Rename,
+                2,
+                &["/dir/sub", "/sub"],
+            ))
+            .await
+            .expect("RENAME failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(3, "/sub/link", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::PermissionDenied as u32));
+
+        // The link itself is still visible and removable
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Remove, 4, &["/sub/link"]))
+            .await
+            .expect("REMOVE failed");
+        assert_eq!(parse_status(&reply), (4, StatusCode::Ok as u32));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_outside_root_are_refused() {
+        let root = link_tree();
+        let outside = root.path().parent().expect("parent").to_path_buf();
+        let mut session = session_with(&root, |config| {
+            config.symlinks = SymlinkPolicy::InternalOnly;
+        })
+        .await;
+
+        // Creating: absolute and relative targets that leave the root
+        for (request_id, target) in [(1, "/../outside.txt"), (2, "../../../outside.txt")] {
+            let reply = session
+                .handle_sftp_packet(&paths_packet(
+                    MessageType::Symlink,
+                    request_id,
+                    &["/dir/sub/escape", target],
+                ))
+                .await
+                .expect("SYMLINK failed");
+            assert_eq!(
+                parse_status(&reply),
+                (request_id, StatusCode::PermissionDenied as u32),
+                "{}",
+                target
+            );
+            assert!(std::fs::symlink_metadata(root.path().join("dir/sub/escape")).is_err());
+        }
+
+        // Reading: links planted on the host behind the server's back
+        std::os::unix::fs::symlink(outside.join("outside.txt"), root.path().join("dir/abs"))
+            .expect("symlink");
+        std::os::unix::fs::symlink("../../../outside.txt", root.path().join("dir/sub/rel"))
+            .expect("symlink");
+        for (request_id, link) in [(3, "/dir/abs"), (4, "/dir/sub/rel")] {
+            let reply = session
+                .handle_sftp_packet(&paths_packet(MessageType::Readlink, request_id, &[link]))
+                .await
+                .expect("READLINK failed");
+            assert_eq!(
+                parse_status(&reply),
+                (request_id, StatusCode::PermissionDenied as u32),
+                "{}",
+                link
+            );
+        }
+
+        // A relative link that stays inside is created and read back as written
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                5,
+                &["/dir/sub/ok", "../inside.txt"],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (5, StatusCode::Ok as u32));
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Readlink, 6, &["/dir/sub/ok"]))
+            .await
+            .expect("READLINK failed");
+        assert_eq!(first_name(&reply), "../inside.txt");
+    }
+
+    #[tokio::test]
+    async fn test_user_roots_are_disjoint() {
+        // Both roots sit side by side beneath the global root
+        let base = TempDir::new().expect("Failed to create temp dir");
+        for (user, file) in [("alice", "alice.txt"), ("bob", "bob.txt")] {
+            std::fs::create_dir(base.path().join(user)).expect("create root");
+            std::fs::write(base.path().join(user).join(file), user).expect("write file");
+        }
+        std::fs::write(base.path().join("global.txt"), b"global").expect("write file");
+        let roots = |config: &mut Config| {
+            for user in ["alice", "bob"] {
+                config
+                    .user_roots
+                    .insert(user.to_string(), base.path().join(user));
+            }
+        };
+
+        for (user, own, other) in [("alice", "alice.txt", "bob"), ("bob", "bob.txt", "alice")] {
+            let mut session = session_with(&base, roots).await;
+            let root = session.config.root_for(Some(user)).expect("user root");
+            session.set_user(user.to_string(), root);
+
+            let handle = opendir(&mut session, "/").await;
+            let mut names = Vec::new();
+            while let Some(entries) = readdir(&mut session, &handle).await {
+                names.extend(entries.into_iter().map(|(name, _)| name));
+            }
+            names.retain(|name| name != "." && name != "..");
+            assert_eq!(names, [own], "{}", user);
+
+            // Climbing out lands back in the user's own root
+            for (request_id, kind, path) in [
+                (1, MessageType::Stat, format!("../{other}/{other}.txt")),
+                (2, MessageType::Stat, format!("/../{other}/{other}.txt")),
+                (3, MessageType::Stat, "../global.txt".to_string()),
+                (4, MessageType::Realpath, format!("../{other}")),
+                (5, MessageType::Realpath, "/../..".to_string()),
+            ] {
+                let reply = session
+                    .handle_sftp_packet(&paths_packet(kind, request_id, &[&path]))
+                    .await
+                    .expect("request failed");
+                assert_eq!(
+                    parse_status(&reply),
+                    (request_id, StatusCode::PermissionDenied as u32),
+                    "{} as {}",
+                    path,
+                    user
+                );
+            }
+        }
+
+        // Without a login the global root still applies
+        let mut session = session_with(&base, roots).await;
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Stat, 1, &["/global.txt"]))
+            .await
+            .expect("STAT failed");
+        assert_eq!(reply[0], MessageType::Attrs as u8);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_deny_mode_hides_symlink_target() {
+        let root = link_tree();
+        std::os::unix::fs::symlink("dir/inside.txt", root.path().join("link"))
+            .expect("symlink");
+        let mut session =
+            session_with(&root, |config| config.symlinks = SymlinkPolicy::Deny).await;
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/link", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::NoSuchFile as u32));
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Readlink, 2, &["/link"]))
+            .await
+            .expect("READLINK failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::NoSuchFile as u32));
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(
+                MessageType::Symlink,
+                3,
+                &["/another", "dir/inside.txt"],
+            ))
+            .await
+            .expect("SYMLINK failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::PermissionDenied as u32));
+        assert!(std::fs::symlink_metadata(root.path().join("another")).is_err());
+    }
+
+    /// Session rooted at `root` that negotiated SFTP version 4
+    async fn v4_session(root: &TempDir) -> SftpSession {
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        let mut session = SftpSession::new(Arc::new(config));
+        let reply = session
+            .handle_sftp_packet(&init_packet_version(6))
+            .await
+            .expect("INIT failed");
+        assert_eq!(reply[0], MessageType::Version as u8);
+        assert_eq!(
+            u32::from_be_bytes([reply[1], reply[2], reply[3], reply[4]]),
+            4
+        );
+        session
+    }
+
+    #[tokio::test]
+    async fn test_init_negotiates_version() {
+        for (requested, agreed) in [(3, 3), (4, 4), (5, 4), (6, 4)] {
+            let mut session = SftpSession::new(Arc::new(Config::default()));
+            let reply = session
+                .handle_sftp_packet(&init_packet_version(requested))
+                .await
+                .expect("INIT failed");
+            assert_eq!(
+                u32::from_be_bytes([reply[1], reply[2], reply[3], reply[4]]),
+                agreed
+            );
+            assert_eq!(session.version, agreed);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_v4_attrs_round_trip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("file.bin"), b"12345").expect("write file");
+        let mut session = v4_session(&root).await;
+
+        // SETSTAT with a v4 attribute block: type byte and permissions only
+        let attrs = FileAttrs {
+            permissions: Some(0o100600),
+            ..FileAttrs::default()
+        };
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Setstat as u8);
+        packet.put_u32(1);
+        codec::put_string(&mut packet, "/file.bin");
+        packet.put(attrs.encode_for(4));
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("SETSTAT failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+        let mode = std::fs::metadata(root.path().join("file.bin"))
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // STAT with the v4 trailing flags; the reply uses the v4 layout
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Stat as u8);
+        packet.put_u32(2);
+        codec::put_string(&mut packet, "/file.bin");
+        packet.put_u32(0xffff_ffff);
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("STAT failed");
+        assert_eq!(reply[0], MessageType::Attrs as u8);
+        let mut buf = &reply[5..];
+        let stat = FileAttrs::decode_for(&mut buf, 4).expect("v4 attrs");
+        assert!(buf.is_empty());
+        assert_eq!(stat.size, Some(5));
+        assert!(stat.mtime.is_some());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stat_reports_mode_type_and_owner() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let (mut session, root) = session().await;
+        let script = root.path().join("setup.sh");
+        std::fs::write(&script, b"#!/bin/sh\n").expect("write script");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod script");
+        std::fs::create_dir(root.path().join("drivers")).expect("mkdir drivers");
+        std::fs::set_permissions(
+            root.path().join("drivers"),
+            std::fs::Permissions::from_mode(0o750),
+        )
+        .expect("chmod drivers");
+        let owner = std::fs::metadata(&script).expect("stat script");
+
+        for (path, mode) in [("/setup.sh", 0o100_755), ("/drivers", 0o040_750)] {
+            let reply = session
+                .handle_sftp_packet(&paths_packet(MessageType::Stat, 1, &[path]))
+                .await
+                .expect("STAT failed");
+            assert_eq!(reply[0], MessageType::Attrs as u8);
+            let mut buf = &reply[5..];
+            let attrs = FileAttrs::decode(&mut buf).expect("attrs");
+            assert_eq!(attrs.permissions, Some(mode), "{}", path);
+            assert_eq!(attrs.uid, Some(owner.uid()));
+            assert_eq!(attrs.gid, Some(owner.gid()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setstat_times_are_applied_and_reported() {
+        const ATIME: u32 = 1_600_000_000;
+        const MTIME: u32 = 1_700_000_000;
+
+        let (mut session, root) = session().await;
+        std::fs::write(root.path().join("boot.img"), b"image").expect("write file");
+
+        let attrs = FileAttrs {
+            atime: Some(ATIME),
+            mtime: Some(MTIME),
+            ..FileAttrs::default()
+        };
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Setstat as u8);
+        packet.put_u32(1);
+        codec::put_string(&mut packet, "/boot.img");
+        packet.put(attrs.encode());
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("SETSTAT failed");
+        assert_eq!(parse_status(&reply), (1, StatusCode::Ok as u32));
+
+        let metadata = std::fs::metadata(root.path().join("boot.img")).expect("metadata");
+        assert_eq!(unix_secs(metadata.modified()), Some(MTIME));
+        assert_eq!(unix_secs(metadata.accessed()), Some(ATIME));
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Stat, 2, &["/boot.img"]))
+            .await
+            .expect("STAT failed");
+        assert_eq!(reply[0], MessageType::Attrs as u8);
+        let mut buf = &reply[5..];
+        let stat = FileAttrs::decode(&mut buf).expect("attrs");
+        assert_eq!(stat.atime, Some(ATIME));
+        assert_eq!(stat.mtime, Some(MTIME));
+    }
+
+    #[tokio::test]
+    async fn test_v4_name_entries_omit_longname() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("only.txt"), b"abc").expect("write file");
+        let mut session = v4_session(&root).await;
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Opendir, 1, &["/"]))
+            .await
+            .expect("OPENDIR failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+        let mut buf = &reply[5..];
+        let handle = codec::get_bytes(&mut buf).expect("handle");
+
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Readdir as u8);
+        packet.put_u32(2);
+        codec::put_bytes(&mut packet, &handle);
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("READDIR failed");
+        assert_eq!(reply[0], MessageType::Name as u8);
+
+        // Each entry is filename then a v4 attribute block, with no longname
+        let mut buf = &reply[5..];
+        let count = buf.get_u32();
+        let mut names = Vec::new();
+        for _ in 0..count {
+            names.push(codec::get_string(&mut buf).expect("filename"));
+            let attrs = FileAttrs::decode_for(&mut buf, 4).expect("v4 attrs");
+            if names.last().map(String::as_str) == Some("only.txt") {
+                assert_eq!(attrs.size, Some(3));
+            }
+        }
+        assert!(buf.is_empty());
+        assert!(names.contains(&"only.txt".to_string()));
+
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Realpath, 3, &["."]))
+            .await
+            .expect("REALPATH failed");
+        let mut buf = &reply[9..];
+        assert_eq!(codec::get_string(&mut buf).expect("filename"), "/");
+        FileAttrs::decode_for(&mut buf, 4).expect("v4 attrs");
+        assert!(buf.is_empty());
+    }
+
+    fn handle_packet(kind: MessageType, request_id: u32, handle: &[u8]) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(kind as u8);
+        packet.put_u32(request_id);
+        codec::put_bytes(&mut packet, handle);
+        packet.to_vec()
+    }
+
+    /// Open `path` with OPENDIR and return the handle
+    async fn opendir(session: &mut SftpSession, path: &str) -> Vec<u8> {
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Opendir, 1, &[path]))
+            .await
+            .expect("OPENDIR failed");
+        reply_handle(&reply).expect("directory handle")
+    }
+
+    /// (filename, longname) pairs of one v3 READDIR, or `None` at EOF
+    async fn readdir(session: &mut SftpSession, handle: &[u8]) -> Option<Vec<(String, String)>> {
+        let reply = session
+            .handle_sftp_packet(&handle_packet(MessageType::Readdir, 2, handle))
+            .await
+            .expect("READDIR failed");
+        if reply[0] == MessageType::Status as u8 {
+            assert_eq!(parse_status(&reply), (2, StatusCode::Eof as u32));
+            return None;
+        }
+        assert_eq!(reply[0], MessageType::Name as u8);
+        let mut buf = &reply[5..];
+        let count = buf.get_u32();
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let name = codec::get_string(&mut buf).expect("filename");
+            let longname = codec::get_string(&mut buf).expect("longname");
+            FileAttrs::decode(&mut buf).expect("attrs");
+            entries.push((name, longname));
+        }
+        assert!(buf.is_empty());
+        Some(entries)
+    }
+
+    #[tokio::test]
+    async fn test_readdir_streams_large_directory() {
+        let (mut session, root) = session().await;
+        for i in 0..1000 {
+            std::fs::write(root.path().join(format!("drv{i:04}.inf")), b"x").expect("write file");
+        }
+
+        let batch_size = session.config.readdir_batch_size;
+        let handle = opendir(&mut session, "/").await;
+        let mut names = std::collections::HashSet::new();
+        let mut batches = 0;
+        while let Some(entries) = readdir(&mut session, &handle).await {
+            assert!(!entries.is_empty() && entries.len() <= batch_size);
+            for (name, longname) in entries {
+                assert!(longname.starts_with("-rw"), "{}", longname);
+                assert!(longname.ends_with(&format!(" {name}")), "{}", longname);
+                names.insert(name);
+            }
+            batches += 1;
+        }
+        assert_eq!(names.len(), 1000);
+        assert_eq!(batches, 1000 / batch_size);
+        // EOF is sticky
+        assert!(readdir(&mut session, &handle).await.is_none());
+
+        // Nothing is read at OPENDIR: files removed before the first READDIR
+        // are not listed
+        let handle = opendir(&mut session, "/").await;
+        for i in (0..1000).step_by(2) {
+            std::fs::remove_file(root.path().join(format!("drv{i:04}.inf"))).expect("remove");
+        }
+        let mut listed = 0;
+        while let Some(entries) = readdir(&mut session, &handle).await {
+            listed += entries.len();
+        }
+        assert_eq!(listed, 500);
+
+        // Closing mid-listing drops the directory stream
+        let handle = opendir(&mut session, "/").await;
+        assert!(readdir(&mut session, &handle).await.is_some());
+        let reply = session
+            .handle_sftp_packet(&handle_packet(MessageType::Close, 3, &handle))
+            .await
+            .expect("CLOSE failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+        assert!(!session.handles.contains_key(&handle));
+    }
+
+    #[tokio::test]
+    async fn test_readdir_batch_size_is_configurable() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        for i in 0..5000 {
+            std::fs::write(root.path().join(format!("f{i:04}")), b"").expect("write file");
+        }
+        let mut session = session_with(&root, |config| config.readdir_batch_size = 64).await;
+
+        let handle = opendir(&mut session, "/").await;
+        let mut names = std::collections::HashSet::new();
+        let mut batches = 0;
+        while let Some(entries) = readdir(&mut session, &handle).await {
+            assert!(!entries.is_empty() && entries.len() <= 64);
+            names.extend(entries.into_iter().map(|(name, _)| name));
+            batches += 1;
+        }
+        assert_eq!(names.len(), 5000);
+        assert_eq!(batches, 5000_usize.div_ceil(64));
+    }
+
+    #[tokio::test]
+    async fn test_read_length_is_clamped() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("boot.wim"), vec![7u8; 4096]).expect("write");
+        let mut session = session_with(&root, |config| config.max_read_len = 1024).await;
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/boot.wim", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&read_packet(2, &handle, 0, u32::MAX))
+            .await
+            .expect("READ failed");
+        assert_eq!(reply[0], MessageType::Data as u8);
+        let mut buf = &reply[5..];
+        assert_eq!(codec::get_bytes(&mut buf).expect("data").len(), 1024);
+        assert_eq!(server_info(&mut session, 3).await.max_read_length, 1024);
+    }
+
+    #[tokio::test]
+    async fn test_open_handles_limited_by_config() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("boot.wim"), b"wim").expect("write");
+        let mut session = session_with(&root, |config| config.max_open_handles = 2).await;
+
+        let mut handles = Vec::new();
+        for request_id in 1..=2 {
+            let reply = session
+                .handle_sftp_packet(&open_packet(request_id, "/boot.wim", OpenFlags::READ))
+                .await
+                .expect("OPEN failed");
+            handles.push(reply_handle(&reply).expect("handle"));
+        }
+
+        // Files and directories share the limit
+        let reply = session
+            .handle_sftp_packet(&open_packet(3, "/boot.wim", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Failure as u32));
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Opendir, 4, &["/"]))
+            .await
+            .expect("OPENDIR failed");
+        assert_eq!(parse_status(&reply), (4, StatusCode::Failure as u32));
+
+        // Closing a handle frees a slot
+        let reply = session
+            .handle_sftp_packet(&handle_packet(MessageType::Close, 5, &handles[0]))
+            .await
+            .expect("CLOSE failed");
+        assert_eq!(parse_status(&reply), (5, StatusCode::Ok as u32));
+        let reply = session
+            .handle_sftp_packet(&paths_packet(MessageType::Opendir, 6, &["/"]))
+            .await
+            .expect("OPENDIR failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+    }
+
+    #[tokio::test]
+    async fn test_reads_at_different_offsets_share_a_handle() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let contents: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root.path().join("boot.wim"), &contents).expect("write");
+        let mut session = session_with(&root, |_| {}).await;
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/boot.wim", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+
+        // Pipelined requests arrive in any order; each answers for its own offset
+        for (request_id, offset) in [(2, 4096usize), (3, 0), (4, 6000), (5, 4096)] {
+            let reply = session
+                .handle_sftp_packet(&read_packet(request_id, &handle, offset as u64, 1000))
+                .await
+                .expect("READ failed");
+            assert_eq!(reply[0], MessageType::Data as u8);
+            let mut buf = &reply[5..];
+            assert_eq!(codec::get_bytes(&mut buf).expect("data"), &contents[offset..offset + 1000]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writes_at_different_offsets_share_a_handle() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let mut session = session_with(&root, |_| {}).await;
+
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/upload.iso", flags))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+
+        for (request_id, offset, byte) in [(2, 512u64, b'b'), (3, 0, b'a'), (4, 1024, b'c')] {
+            let reply = session
+                .handle_sftp_packet(&write_at_packet(request_id, &handle, offset, &[byte; 512]))
+                .await
+                .expect("WRITE failed");
+            assert_eq!(parse_status(&reply), (request_id, StatusCode::Ok as u32));
+        }
+
+        let written = std::fs::read(root.path().join("upload.iso")).expect("read back");
+        let expected: Vec<u8> = [[b'a'; 512], [b'b'; 512], [b'c'; 512]].concat();
+        assert_eq!(written, expected);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_packet_is_rejected_unparsed() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let mut session = session_with(&root, |config| config.max_packet_size = 32768).await;
+
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/upload.iso", flags))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(2, &handle, 0, &vec![0u8; 32768]))
+            .await
+            .expect("WRITE failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::BadMessage as u32));
+        assert_eq!(std::fs::metadata(root.path().join("upload.iso")).expect("stat").len(), 0);
+
+        // A garbage packet over the limit is refused without being parsed
+        let reply = session
+            .handle_sftp_packet(&vec![0xffu8; 40000])
+            .await
+            .expect("oversized packet");
+        assert_eq!(parse_status(&reply), (u32::MAX, StatusCode::BadMessage as u32));
+
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(3, &handle, 0, &[0u8; 1024]))
+            .await
+            .expect("WRITE failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+    }
+
+    #[tokio::test]
+    async fn test_write_length_past_payload_is_rejected() {
+        let (mut session, root) = session().await;
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/upload.iso", flags))
+            .await
+            .expect("OPEN failed");
+        let handle = reply_handle(&reply).expect("handle");
+
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Write as u8);
+        packet.put_u32(2);
+        codec::put_bytes(&mut packet, &handle);
+        packet.put_u64(0);
+        packet.put_u32(u32::MAX);
+        packet.put_slice(b"short");
+        assert!(session.handle_sftp_packet(&packet).await.is_err());
+        assert_eq!(std::fs::metadata(root.path().join("upload.iso")).expect("stat").len(), 0);
+
+        // The session carries on after the malformed request
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(3, &handle, 0, b"short"))
+            .await
+            .expect("WRITE failed");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_attrs_are_rejected() {
+        let (mut session, root) = session().await;
+        std::fs::write(root.path().join("boot.wim"), b"boot").expect("write");
+
+        // SETSTAT claiming a size but ending after the flags
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Setstat as u8);
+        packet.put_u32(1);
+        codec::put_string(&mut packet, "/boot.wim");
+        packet.put_u32(0x0000_0001); // SSH_FILEXFER_ATTR_SIZE
+        packet.put_u32(0);
+        assert!(session.handle_sftp_packet(&packet).await.is_err());
+        assert_eq!(std::fs::read(root.path().join("boot.wim")).expect("read"), b"boot");
+
+        // MKDIR whose attrs stop partway through the flags word
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Mkdir as u8);
+        packet.put_u32(2);
+        codec::put_string(&mut packet, "/drivers");
+        packet.put_u16(0);
+        assert!(session.handle_sftp_packet(&packet).await.is_err());
+        assert!(!root.path().join("drivers").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fsetstat_on_directory_handle() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (mut session, root) = session().await;
+        std::fs::create_dir(root.path().join("drivers")).expect("create dir");
+        let handle = opendir(&mut session, "/drivers").await;
+
+        let attrs = FileAttrs {
+            permissions: Some(0o750),
+            ..FileAttrs::default()
+        };
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Fsetstat as u8);
+        packet.put_u32(2);
+        codec::put_bytes(&mut packet, &handle);
+        packet.put(attrs.encode());
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("FSETSTAT failed");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+
+        let mode = std::fs::metadata(root.path().join("drivers"))
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+
+    /// READ of `len` bytes at `offset`
+    fn read_packet(request_id: u32, handle: &[u8], offset: u64, len: u32) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Read as u8);
+        packet.put_u32(request_id);
+        codec::put_bytes(&mut packet, handle);
+        packet.put_u64(offset);
+        packet.put_u32(len);
+        packet.to_vec()
+    }
+
+    /// Session behind a lock, as the SSH handler holds it, with `name` opened for reading
+    async fn throttled_session(root: &TempDir, name: &str, rate: u64) -> (Mutex<SftpSession>, Vec<u8>) {
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            max_bytes_per_sec_per_session: Some(rate),
+            ..Config::default()
+        };
+        let session = Mutex::new(SftpSession::new(Arc::new(config)));
+        process_packet(&session, &init_packet())
+            .await
+            .expect("INIT failed");
+        let reply = process_packet(&session, &open_packet(1, name, OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        assert_eq!(reply[0], MessageType::Handle as u8);
+        let mut buf = &reply[5..];
+        let handle = codec::get_bytes(&mut buf).expect("handle");
+        (session, handle)
+    }
+
+    #[tokio::test]
+    async fn test_session_bandwidth_cap_paces_reads() {
+        const SIZE: usize = 48 * 1024;
+        const RATE: u64 = 32 * 1024;
+
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("image.wim"), vec![7u8; SIZE]).expect("write image");
+        let (session, handle) = throttled_session(&root, "/image.wim", RATE).await;
+
+        let started = tokio::time::Instant::now();
+        let mut offset = 0u64;
+        for request_id in 2.. {
+            let reply = process_packet(&session, &read_packet(request_id, &handle, offset, 8192))
+                .await
+                .expect("READ failed");
+            if reply[0] != MessageType::Data as u8 {
+                assert_eq!(parse_status(&reply), (request_id, StatusCode::Eof as u32));
+                break;
+            }
+            let mut buf = &reply[5..];
+            offset += codec::get_bytes(&mut buf).expect("data").len() as u64;
+        }
+
+        assert_eq!(offset, SIZE as u64);
+        let expected = Duration::from_secs_f64(SIZE as f64 / RATE as f64);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= expected.mul_f64(0.95), "finished in {:?}", elapsed);
+        assert!(elapsed < expected * 3, "finished in {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_throttle_wait_releases_session_lock() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("big.bin"), vec![0u8; 8192]).expect("write file");
+        let (session, handle) = throttled_session(&root, "/big.bin", 1024).await;
+        let session = Arc::new(session);
+
+        // An 8 KiB read at 1 KiB/s owes about eight seconds
+        let reader = tokio::spawn({
+            let session = session.clone();
+            async move { process_packet(&session, &read_packet(2, &handle, 0, 8192)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Other requests on the session are served while the read waits
+        let reply = timeout(
+            Duration::from_secs(1),
+            process_packet(&session, &paths_packet(MessageType::Realpath, 3, &["."])),
+        )
+        .await
+        .expect("session lock held across the throttle wait")
+        .expect("REALPATH failed");
+        assert_eq!(first_name(&reply), "/");
+        assert!(!reader.is_finished());
+        reader.abort();
+    }
+
+    /// Handler with the default policy rooted at `root`, sharing `metrics`
+    fn gauge_handler(
+        root: &TempDir,
+        configure: impl FnOnce(&mut Config),
+        metrics: &Metrics,
+    ) -> SftpHandler {
+        let mut config = Config {
+            root_dir: root.path().to_path_buf(),
+            ..Config::default()
+        };
+        configure(&mut config);
+        let config = Arc::new(config);
+        let audit = AuditChannel::spawn(&config.logging.audit_channel, TracingSink)
+            .expect("Failed to start audit writer");
+        let authorizer = Arc::new(StaticAuthorizer::new(config.clone()));
+        SftpHandler::new(config, authorizer, audit, metrics.clone())
+    }
+
+    /// Accepts `secret` as every user's password
+    struct FixedPassword;
+
+    #[async_trait::async_trait]
+    impl PasswordVerifier for FixedPassword {
+        async fn verify(&self, _username: &str, password: &str) -> bool {
+            password == "secret"
+        }
+    }
+
+    /// Handler with password authentication on, allowing three failures
+    fn password_handler(root: &TempDir, metrics: &Metrics) -> SftpHandler {
+        let mut handler = gauge_handler(
+            root,
+            |config| {
+                config.password_auth = true;
+                config.max_auth_attempts = 3;
+            },
+            metrics,
+        );
+        handler.password_verifier = Arc::new(FixedPassword);
+        handler
+    }
+
+    const PASSWORD_PEER: ([u8; 4], u16) = ([10, 0, 0, 7], 22);
+
+    #[tokio::test]
+    async fn test_correct_password_is_accepted() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = password_handler(&root, &metrics);
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+
+        let auth = connection.auth_password("imager", "secret").await;
+        assert!(matches!(auth, Ok(Auth::Accept)));
+        assert_eq!(connection.username.as_deref(), Some("imager"));
+        assert_eq!(metrics.snapshot().auth_successes, 1);
+
+        // Keyboard-interactive asks for the password first
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+        let auth = connection.auth_keyboard_interactive("imager", "", None).await;
+        assert!(matches!(auth, Ok(Auth::Partial { ref prompts, .. }) if prompts.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_rejected_and_counted() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = password_handler(&root, &metrics);
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+
+        let auth = connection.auth_password("imager", "guess").await;
+        assert!(
+            matches!(
+                &auth,
+                Ok(Auth::Reject { proceed_with_methods: Some(methods), .. })
+                    if methods.contains(&MethodKind::Password)
+                        && methods.contains(&MethodKind::PublicKey)
+            ),
+            "{:?}",
+            auth
+        );
+        assert!(connection.username.is_none());
+        assert_eq!(handler.rate_limiter.get_stats().await, (1, 0));
+        assert_eq!(metrics.snapshot().auth_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_wrong_passwords_lock_out_peer() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = password_handler(&root, &metrics);
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+
+        for _ in 0..3 {
+            let auth = connection.auth_password("imager", "guess").await;
+            assert!(matches!(auth, Ok(Auth::Reject { .. })));
+        }
+        assert_eq!(handler.rate_limiter.get_stats().await, (1, 1));
+
+        // Locked out: even the right password is refused, with nothing to try next
+        let auth = connection.auth_password("imager", "secret").await;
+        assert!(matches!(
+            auth,
+            Ok(Auth::Reject {
+                proceed_with_methods: None,
+                ..
+            })
+        ));
+        assert!(connection.username.is_none());
+        assert_eq!(metrics.snapshot().rate_limited_attempts, 1);
+
+        // Other addresses are unaffected
+        let mut other = handler.new_client(Some(([10, 0, 0, 8], 22).into()));
+        assert!(matches!(other.auth_password("imager", "secret").await, Ok(Auth::Accept)));
+    }
+
+    #[tokio::test]
+    async fn test_password_auth_is_off_by_default() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = gauge_handler(&root, |_| {}, &metrics);
+        handler.password_verifier = Arc::new(FixedPassword);
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+
+        let mut key_only = MethodSet::empty();
+        key_only.push(MethodKind::PublicKey);
+        let auth = connection.auth_password("imager", "secret").await;
+        assert!(
+            matches!(
+                &auth,
+                Ok(Auth::Reject { proceed_with_methods: Some(methods), .. }) if *methods == key_only
+            ),
+            "{:?}",
+            auth
+        );
+        let auth = connection.auth_keyboard_interactive("imager", "", None).await;
+        assert!(matches!(auth, Ok(Auth::Reject { .. })));
+        assert!(connection.username.is_none());
+    }
+
+    /// Password handler giving each user `<base>/users/<name>` as its root
+    fn template_handler(base: &TempDir, create_user_root: bool, metrics: &Metrics) -> SftpHandler {
+        let template = format!("{}/users/{{user}}", base.path().display());
+        let mut handler = gauge_handler(
+            base,
+            |config| {
+                config.password_auth = true;
+                config.user_root_template = Some(template);
+                config.create_user_root = create_user_root;
+            },
+            metrics,
+        );
+        handler.password_verifier = Arc::new(FixedPassword);
+        handler
+    }
+
+    #[tokio::test]
+    async fn test_user_root_template_separates_users() {
+        let base = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = template_handler(&base, true, &metrics);
+
+        for user in ["alice", "bob"] {
+            let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+            let auth = connection.auth_password(user, "secret").await;
+            assert!(matches!(auth, Ok(Auth::Accept)), "{}: {:?}", user, auth);
+            let mut session = connection.session.lock().await;
+            session
+                .handle_sftp_packet(&init_packet())
+                .await
+                .expect("INIT failed");
+
+            let flags = OpenFlags::WRITE | OpenFlags::CREAT | OpenFlags::TRUNC;
+            let reply = session
+                .handle_sftp_packet(&open_packet(1, "/report.txt", flags))
+                .await
+                .expect("OPEN failed");
+            let handle = reply_handle(&reply).expect("handle");
+            let reply = session
+                .handle_sftp_packet(&write_at_packet(2, &handle, 0, user.as_bytes()))
+                .await
+                .expect("WRITE failed");
+            assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+            let reply = session
+                .handle_sftp_packet(&handle_packet(MessageType::Close, 3, &handle))
+                .await
+                .expect("CLOSE failed");
+            assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+
+            // The user's root is `/`; the host path never shows
+            assert_eq!(realpath(&mut session, ".").await, "/");
+            let reply = session
+                .handle_sftp_packet(&paths_packet(MessageType::Realpath, 4, &["/../.."]))
+                .await
+                .expect("REALPATH failed");
+            assert_eq!(parse_status(&reply), (4, StatusCode::PermissionDenied as u32));
+        }
+
+        for user in ["alice", "bob"] {
+            let written = std::fs::read(base.path().join("users").join(user).join("report.txt"))
+                .expect("read report");
+            assert_eq!(written, user.as_bytes());
+        }
+        assert!(!base.path().join("report.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_user_root_template_rejects_unusable_roots() {
+        let base = TempDir::new().expect("Failed to create temp dir");
+        let metrics = Metrics::new();
+        let mut handler = template_handler(&base, true, &metrics);
+
+        // NIST 800-53: SI-10 - Refused before the name reaches a path
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+        let auth = connection.auth_password("../evil", "secret").await;
+        assert!(matches!(
+            auth,
+            Ok(Auth::Reject {
+                proceed_with_methods: None,
+                ..
+            })
+        ));
+        assert!(connection.username.is_none());
+        assert!(!base.path().join("evil").exists());
+        assert_eq!(metrics.snapshot().auth_failures, 1);
+
+        // Without create_user_root a root that does not exist refuses the login
+        let mut handler = template_handler(&base, false, &metrics);
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+        let auth = connection.auth_password("carol", "secret").await;
+        assert!(matches!(auth, Ok(Auth::Reject { .. })));
+        assert!(connection.username.is_none());
+        assert!(!base.path().join("users/carol").exists());
+
+        std::fs::create_dir_all(base.path().join("users/carol")).expect("create root");
+        let mut connection = handler.new_client(Some(PASSWORD_PEER.into()));
+        assert!(matches!(connection.auth_password("carol", "secret").await, Ok(Auth::Accept)));
+    }
+
+    /// Handle carried by an SSH_FXP_HANDLE reply
+    fn reply_handle(reply: &[u8]) -> Option<Vec<u8>> {
+        if reply.first() != Some(&(MessageType::Handle as u8)) {
+            return None;
+        }
+        let mut buf = reply.get(5..)?;
+        codec::get_bytes(&mut buf).ok()
+    }
+
+    #[tokio::test]
+    async fn test_resource_gauges_follow_session_lifetime() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("image.bin"), vec![1u8; 64 * 1024]).expect("write image");
+        let metrics = Metrics::new();
+        let mut handler = gauge_handler(&root, |_| {}, &metrics);
+
+        let connection = handler.new_client(None);
+        let session = connection.session.clone();
+        process_packet(&session, &init_packet()).await.expect("INIT failed");
+        let reply = process_packet(&session, &open_packet(1, "/image.bin", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let file = reply_handle(&reply).expect("file handle");
+        // Sequential reads start a prefetch that the later reads are served from
+        for (request_id, offset) in (2..8).zip((0..).step_by(4096)) {
+            process_packet(&session, &read_packet(request_id, &file, offset, 4096))
+                .await
+                .expect("READ failed");
+        }
+        let reply = process_packet(&session, &paths_packet(MessageType::Opendir, 8, &["/"]))
+            .await
+            .expect("OPENDIR failed");
+        assert!(reply_handle(&reply).is_some());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.live_sessions, 1);
+        assert_eq!(snapshot.open_file_handles, 1);
+        assert_eq!(snapshot.open_dir_handles, 1);
+        assert!(snapshot.read_ahead_bytes > 0);
+
+        let state = handler.dump_state().await;
+        assert!(state.contains("live_sessions 1\n"), "{}", state);
+        assert!(state.contains(" user=- files=1 dirs=1 read_ahead_bytes="), "{}", state);
+
+        // Gone without closing anything, as on an abrupt disconnect
+        drop(connection);
+        drop(session);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.live_sessions, 0);
+        assert_eq!(snapshot.open_file_handles, 0);
+        assert_eq!(snapshot.open_dir_handles, 0);
+        assert_eq!(snapshot.read_ahead_bytes, 0);
+        assert!(handler.dump_state().await.ends_with("tracked_connections 0\n"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_session_releases_handles() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("image.bin"), vec![1u8; 4096]).expect("write image");
+        let metrics = Metrics::new();
+        let mut handler = gauge_handler(&root, |_| {}, &metrics);
+        let connection = handler.new_client(None);
+        let session = connection.session.clone();
+        process_packet(&session, &init_packet()).await.expect("INIT failed");
+        let reply = process_packet(&session, &open_packet(1, "/image.bin", OpenFlags::READ))
+            .await
+            .expect("OPEN failed");
+        let file = reply_handle(&reply).expect("file handle");
+
+        let tracker = handler.connection_tracker.clone();
+        let registration = tracker
+            .register_session("alice".to_string())
+            .await
+            .expect("register session");
+        let idle_timeout = Duration::from_secs(60);
+        let watchdog = tokio::spawn({
+            let session = session.clone();
+            let tracker = tracker.clone();
+            let shutdown = registration.shutdown.clone();
+            async move {
+                let idle_for = wait_for_expiry(
+                    &tracker,
+                    "alice",
+                    registration.connection_id,
+                    &shutdown,
+                    idle_timeout,
+                )
+                .await;
+                if let Some(idle_for) = idle_for {
+                    session.lock().await.terminate_idle(idle_for, idle_timeout);
+                }
+                idle_for
+            }
+        });
+
+        // Activity keeps the session open past the first deadline
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        tracker
+            .record_activity("alice", registration.connection_id)
+            .await;
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert!(!watchdog.is_finished());
+        assert_eq!(metrics.snapshot().open_file_handles, 1);
+
+        // Going quiet past the timeout closes the handle at the deadline
+        let idle_for = watchdog.await.expect("watchdog panicked");
+        assert_eq!(idle_for, Some(idle_timeout));
+        assert!(session.lock().await.handles.is_empty());
+        assert_eq!(metrics.snapshot().open_file_handles, 0);
+        assert!(matches!(
+            process_packet(&session, &read_packet(3, &file, 0, 512)).await,
+            Err(Error::InvalidHandle(_))
+        ));
+    }
+
+    /// Session rooted at `root` with `write_past_eof` set, already past INIT
+    async fn resume_session(root: &TempDir, write_past_eof: WritePastEof) -> SftpSession {
+        let config = Config {
+            root_dir: root.path().to_path_buf(),
+            write_past_eof,
+            ..Config::default()
+        };
+        let mut session = SftpSession::new(Arc::new(config));
+        session
+            .handle_sftp_packet(&init_packet())
+            .await
+            .expect("INIT failed");
+        session
+    }
+
+    /// WRITE of `data` at `offset`
+    fn write_at_packet(request_id: u32, handle: &[u8], offset: u64, data: &[u8]) -> Vec<u8> {
+        let mut packet = BytesMut::new();
+        packet.put_u8(MessageType::Write as u8);
+        packet.put_u32(request_id);
+        codec::put_bytes(&mut packet, handle);
+        packet.put_u64(offset);
+        codec::put_bytes(&mut packet, data);
+        packet.to_vec()
+    }
+
+    /// Size reported by stat-for-resume@snow-owl.dev, or the status code on failure
+    async fn stat_for_resume(
+        session: &mut SftpSession,
+        request_id: u32,
+        path: &str,
+    ) -> std::result::Result<u64, u32> {
+        let packet = extended_packet(request_id, EXT_STAT_FOR_RESUME, &[path.as_bytes()]);
+        let reply = session
+            .handle_sftp_packet(&packet)
+            .await
+            .expect("stat-for-resume");
+        if reply[0] != MessageType::ExtendedReply as u8 {
+            return Err(parse_status(&reply).1);
+        }
+        let mut buf = &reply[1..];
+        assert_eq!(buf.get_u32(), request_id);
+        let size = buf.get_u64();
+        assert!(buf.is_empty());
+        Ok(size)
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_upload_resumes_at_reported_size() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        let image: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let (first, rest) = image.split_at(37_000);
+
+        // First connection uploads part of the file and drops without CLOSE
+        let mut session = resume_session(&root, WritePastEof::ZeroFill).await;
+        let flags = OpenFlags::WRITE | OpenFlags::CREAT | OpenFlags::TRUNC;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/boot.wim", flags))
+            .await
+            .expect("open");
+        let handle = reply_handle(&reply).expect("handle");
+        for (i, chunk) in first.chunks(8192).enumerate() {
+            let offset = (i * 8192) as u64;
+            let reply = session
+                .handle_sftp_packet(&write_at_packet(2, &handle, offset, chunk))
+                .await
+                .expect("write");
+            assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+        }
+        drop(session);
+
+        // A new connection asks where to continue and writes the remainder
+        let mut session = resume_session(&root, WritePastEof::ZeroFill).await;
+        let offset = stat_for_resume(&mut session, 3, "/boot.wim")
+            .await
+            .expect("size of partial upload");
+        assert_eq!(offset, first.len() as u64);
+
+        let reply = session
+            .handle_sftp_packet(&open_packet(4, "/boot.wim", OpenFlags::WRITE))
+            .await
+            .expect("reopen");
+        let handle = reply_handle(&reply).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(5, &handle, offset, rest))
+            .await
+            .expect("write");
+        assert_eq!(parse_status(&reply), (5, StatusCode::Ok as u32));
+        let mut close = BytesMut::new();
+        close.put_u8(MessageType::Close as u8);
+        close.put_u32(6);
+        codec::put_bytes(&mut close, &handle);
+        let reply = session.handle_sftp_packet(&close).await.expect("close");
+        assert_eq!(parse_status(&reply), (6, StatusCode::Ok as u32));
+
+        assert_eq!(std::fs::read(root.path().join("boot.wim")).expect("read"), image);
+        assert_eq!(
+            stat_for_resume(&mut session, 7, "/boot.wim").await,
+            Ok(image.len() as u64)
+        );
+        assert_eq!(
+            stat_for_resume(&mut session, 8, "/missing.wim").await,
+            Err(StatusCode::NoSuchFile as u32)
+        );
+        assert_eq!(
+            stat_for_resume(&mut session, 9, "/").await,
+            Err(StatusCode::Failure as u32)
+        );
+    }
+
+    /// Unencrypted key generated by ssh-keygen, or `None` if it is not installed
+    fn ssh_keygen(dir: &Path, name: &str, args: &[&str]) -> Option<PathBuf> {
+        let path = dir.join(name);
+        let status = std::process::Command::new("ssh-keygen")
+            .args(args)
+            .args(["-q", "-N", "", "-C", "", "-f"])
+            .arg(&path)
+            .status()
+            .ok()?;
+        status.success().then_some(path)
+    }
+
+    #[tokio::test]
+    async fn test_host_key_offered_for_each_cnsa_algorithm() {
+        use russh::keys::ssh_key::{Algorithm, EcdsaCurve};
+
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let Some(ed25519) = ssh_keygen(dir.path(), "ssh_host_ed25519_key", &["-t", "ed25519"])
+        else {
+            eprintln!("Skipping test: 'ssh-keygen' command not found");
+            return;
+        };
+        let ecdsa = ssh_keygen(dir.path(), "ssh_host_ecdsa_key", &["-t", "ecdsa", "-b", "384"])
+            .expect("ecdsa key");
+        let rsa = ssh_keygen(dir.path(), "ssh_host_rsa_key", &["-t", "rsa", "-b", "3072"])
+            .expect("rsa key");
+
+        // A client offering only `algorithm` connects if the server both
+        // allows it and holds a key for it
+        let client_algorithms = [
+            Algorithm::Ed25519,
+            Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP384,
+            },
+        ];
+        let assert_each_negotiates = |keys: &[PrivateKey]| {
+            assert_eq!(keys.len(), 2);
+            for algorithm in &client_algorithms {
+                assert!(
+                    cnsa::CNSA_HOST_KEY_ALGORITHMS.contains(algorithm)
+                        && keys.iter().any(|key| key.algorithm() == *algorithm),
+                    "no host key for {}",
+                    algorithm.as_str()
+                );
+            }
+        };
+
+        // A directory: the RSA key and the .pub files are passed over
+        let keys = load_host_keys(&[dir.path().to_path_buf()]).await.expect("load dir");
+        assert_each_negotiates(&keys);
+
+        let keys = load_host_keys(&[ed25519.clone(), ecdsa]).await.expect("load list");
+        assert_each_negotiates(&keys);
+
+        // A second key of an algorithm already loaded is dropped
+        let keys = load_host_keys(&[ed25519, dir.path().to_path_buf()])
+            .await
+            .expect("load duplicates");
+        assert_each_negotiates(&keys);
+
+        // RSA is not a CNSA 2.0 host key algorithm; the error names the key
+        match load_host_keys(&[rsa.clone()]).await {
+            Err(Error::Config(message)) => {
+                assert!(message.contains(&rsa.display().to_string()), "{}", message);
+                assert!(message.contains("ssh-rsa"), "{}", message);
+            }
+            other => panic!("RSA-only host keys loaded: {:?}", other.map(|keys| keys.len())),
+        }
+    }
+
+    /// Records the algorithm of the host key the server presented
+    struct HostKeyRecorder(Arc<std::sync::Mutex<Option<russh::keys::Algorithm>>>);
+
+    impl russh::client::Handler for HostKeyRecorder {
+        type Error = russh::Error;
+
+        async fn check_server_key(
+            &mut self,
+            server_public_key: &PublicKey,
+        ) -> std::result::Result<bool, Self::Error> {
+            *self.0.lock().expect("recorder lock") = Some(server_public_key.algorithm());
+            Ok(true)
+        }
+    }
+
+    /// Host key algorithm negotiated by a client that accepts only `algorithm`
+    async fn negotiated_host_key(
+        port: u16,
+        algorithm: russh::keys::Algorithm,
+    ) -> Option<russh::keys::Algorithm> {
+        let config = russh::client::Config {
+            preferred: russh::Preferred {
+                kex: Cow::Borrowed(cnsa::CNSA_KEX_ALGORITHMS),
+                key: Cow::Owned(vec![algorithm]),
+                cipher: Cow::Borrowed(cnsa::CNSA_CIPHERS),
+                mac: Cow::Borrowed(cnsa::CNSA_MAC_ALGORITHMS),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let seen = Arc::new(std::sync::Mutex::new(None));
+        let mut handle = russh::client::connect(
+            Arc::new(config),
+            ("127.0.0.1", port),
+            HostKeyRecorder(seen.clone()),
+        )
+        .await
+        .ok()?;
+        // Authentication starts only once the key exchange has finished
+        handle.authenticate_none("deploy").await.ok()?;
+        let algorithm = seen.lock().expect("recorder lock").take();
+        algorithm
+    }
+
+    #[tokio::test]
+    async fn test_ed25519_and_rsa_host_keys() {
+        use russh::keys::ssh_key::{Algorithm, HashAlg};
+
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let Some(ed25519) = ssh_keygen(dir.path(), "ssh_host_ed25519_key", &["-t", "ed25519"])
+        else {
+            eprintln!("Skipping test: 'ssh-keygen' command not found");
+            return;
+        };
+        let rsa = ssh_keygen(dir.path(), "ssh_host_rsa_key", &["-t", "rsa", "-b", "3072"])
+            .expect("rsa key");
+        let root = TempDir::new().expect("Failed to create temp dir");
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .expect("bind")
+            .local_addr()
+            .expect("local addr")
+            .port();
+        let mut config = Config::default();
+        config.bind_address = "127.0.0.1".to_string();
+        config.port = port;
+        config.root_dir = root.path().to_path_buf();
+        config.host_key_path = HostKeyPaths::Many(vec![ed25519, rsa]);
+        // The RSA key is skipped rather than failing startup
+        let server = Server::new(config).await.expect("Server should start");
+        let server_task = tokio::spawn(server.run());
+
+        // The listener binds inside the spawned task
+        let mut negotiated = None;
+        for _ in 0..50 {
+            negotiated = negotiated_host_key(port, Algorithm::Ed25519).await;
+            if negotiated.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(negotiated, Some(Algorithm::Ed25519));
+
+        // CNSA 2.0: a client that trusts only RSA finds no common host key
+        // algorithm and is refused during key exchange
+        let rsa_sha512 = Algorithm::Rsa {
+            hash: Some(HashAlg::Sha512),
+        };
+        assert_eq!(negotiated_host_key(port, rsa_sha512).await, None);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_listener_and_waits_for_sessions() {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let Some(host_key) = ssh_keygen(dir.path(), "ssh_host_ed25519_key", &["-t", "ed25519"])
+        else {
+            eprintln!("Skipping test: 'ssh-keygen' command not found");
+            return;
+        };
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .expect("bind")
+            .local_addr()
+            .expect("local addr")
+            .port();
+        let addr = format!("127.0.0.1:{}", port);
+        let mut config = Config::default();
+        config.bind_address = "127.0.0.1".to_string();
+        config.port = port;
+        config.root_dir = dir.path().to_path_buf();
+        config.host_key_path = host_key.into();
+
+        for drain_timeout in [Duration::from_secs(30), Duration::from_millis(200)] {
+            let server = Server::new(config.clone()).await.expect("Server should start");
+            let stop = CancellationToken::new();
+            let mut server_task =
+                tokio::spawn(server.run_with_shutdown(stop.clone(), drain_timeout));
+
+            // The listener binds inside the spawned task
+            let mut session = None;
+            for _ in 0..50 {
+                if let Ok(stream) = tokio::net::TcpStream::connect(&addr).await {
+                    session = Some(stream);
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            let session = session.expect("server never accepted a connection");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            stop.cancel();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert!(
+                tokio::net::TcpStream::connect(&addr).await.is_err(),
+                "listener still open after shutdown"
+            );
+
+            if drain_timeout > Duration::from_secs(1) {
+                // The open session holds the server until it ends
+                assert!(timeout(Duration::from_millis(300), &mut server_task)
+                    .await
+                    .is_err());
+                drop(session);
+            }
+            // Past the drain timeout the session is aborted instead
+            timeout(Duration::from_secs(5), server_task)
+                .await
+                .expect("server did not stop")
+                .expect("server task")
+                .expect("server result");
+        }
+    }
+
+    /// OPEN, MKDIR or SETSTAT of `path` carrying `permissions`
+    fn mode_packet(kind: MessageType, request_id: u32, path: &str, permissions: u32) -> Vec<u8> {
+        let attrs = FileAttrs {
+            permissions: Some(permissions),
+            ..FileAttrs::default()
+        };
+        let mut packet = BytesMut::new();
+        packet.put_u8(kind as u8);
+        packet.put_u32(request_id);
+        codec::put_string(&mut packet, path);
+        if kind == MessageType::Open {
+            packet.put_u32(OpenFlags::WRITE | OpenFlags::CREAT);
+        }
+        packet.put(attrs.encode());
+        packet.to_vec()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_file_mode_policy() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let cases = [
+            (FileModePolicy::ClientRequested, 0o644, 0o750, 0o755, None),
+            (FileModePolicy::Fixed(0o600), 0o600, 0o600, 0o400, Some(0o644)),
+            (FileModePolicy::Masked(0o077), 0o600, 0o700, 0o700, Some(0o640)),
+        ];
+        for (policy, file_mode, dir_mode, allowed, refused) in cases {
+            let root = TempDir::new().expect("Failed to create temp dir");
+            let mut session =
+                session_with(&root, |config| config.file_mode_policy = policy).await;
+            let mode_of = |name: &str| {
+                std::fs::metadata(root.path().join(name))
+                    .expect("metadata")
+                    .permissions()
+                    .mode()
+                    & 0o7777
+            };
+
+            let reply = session
+                .handle_sftp_packet(&mode_packet(MessageType::Open, 1, "/new.txt", 0o644))
+                .await
+                .expect("open");
+            assert!(reply_handle(&reply).is_some(), "{:?}", policy);
+            assert_eq!(mode_of("new.txt"), file_mode, "{:?}", policy);
+
+            let reply = session
+                .handle_sftp_packet(&mode_packet(MessageType::Mkdir, 2, "/dir", 0o750))
+                .await
+                .expect("mkdir");
+            assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+            assert_eq!(mode_of("dir"), dir_mode, "{:?}", policy);
+
+            let reply = session
+                .handle_sftp_packet(&mode_packet(MessageType::Setstat, 3, "/new.txt", allowed))
+                .await
+                .expect("setstat");
+            assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+            assert_eq!(mode_of("new.txt"), allowed, "{:?}", policy);
+
+            if let Some(refused) = refused {
+                let reply = session
+                    .handle_sftp_packet(&mode_packet(MessageType::Setstat, 4, "/new.txt", refused))
+                    .await
+                    .expect("setstat");
+                assert_eq!(parse_status(&reply), (4, StatusCode::PermissionDenied as u32));
+                assert_eq!(mode_of("new.txt"), allowed, "{:?}", policy);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_past_eof_policy() {
+        let root = TempDir::new().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("gap.bin"), b"abcd").expect("write file");
+
+        let mut session = resume_session(&root, WritePastEof::Reject).await;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/gap.bin", OpenFlags::WRITE))
+            .await
+            .expect("open");
+        let handle = reply_handle(&reply).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(2, &handle, 8, b"xy"))
+            .await
+            .expect("write");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Failure as u32));
+        // Appending exactly at the end is still allowed
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(3, &handle, 4, b"ef"))
+            .await
+            .expect("write");
+        assert_eq!(parse_status(&reply), (3, StatusCode::Ok as u32));
+        drop(session);
+        assert_eq!(std::fs::read(root.path().join("gap.bin")).expect("read"), b"abcdef");
+
+        let mut session = resume_session(&root, WritePastEof::ZeroFill).await;
+        let reply = session
+            .handle_sftp_packet(&open_packet(1, "/gap.bin", OpenFlags::WRITE))
+            .await
+            .expect("open");
+        let handle = reply_handle(&reply).expect("handle");
+        let reply = session
+            .handle_sftp_packet(&write_at_packet(2, &handle, 8, b"xy"))
+            .await
+            .expect("write");
+        assert_eq!(parse_status(&reply), (2, StatusCode::Ok as u32));
+        drop(session);
+        assert_eq!(
+            std::fs::read(root.path().join("gap.bin")).expect("read"),
+            b"abcdef\0\0xy"
+        );
+    }
+
+    /// Channel that accepts `fail_after` writes, then refuses everything
+    struct FailingSink {
+        fail_after: usize,
+        written: Vec<Vec<u8>>,
+        attempts: usize,
+        closed: bool,
+    }
+
+    impl ResponseSink for FailingSink {
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.attempts += 1;
+            if self.written.len() == self.fail_after {
+                return Err(Error::channel_closed("window closed"));
+            }
+            self.written.push(data.to_vec());
+            Ok(())
+        }
+
+        fn close(&mut self) {
+            self.closed = true;
+        }
+    }
+
+    #[test]
+    fn test_failed_response_write_closes_channel() {
+        for policy in [ChannelWriteFailure::CloseChannel, ChannelWriteFailure::Disconnect] {
+            let mut sink = FailingSink {
+                fail_after: 1,
+                written: Vec::new(),
+                attempts: 0,
+                closed: false,
+            };
+            let mut broken = false;
+
+            assert!(send_response(&mut sink, &mut broken, policy, b"first").is_ok());
+            assert!(!sink.closed);
+
+            let result = send_response(&mut sink, &mut broken, policy, b"second");
+            assert_eq!(result.is_err(), policy == ChannelWriteFailure::Disconnect);
+            assert!(sink.closed);
+            assert!(broken);
+
+            // Later responses are dropped without touching the channel
+            assert!(send_response(&mut sink, &mut broken, policy, b"third").is_ok());
+            assert_eq!(sink.attempts, 2);
+            assert_eq!(sink.written, vec![b"first".to_vec()]);
+        }
+    }
+
+    fn framed(payload: &[u8]) -> Vec<u8> {
+        let mut packet = u32::try_from(payload.len()).expect("length").to_be_bytes().to_vec();
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn test_packet_reader_reassembles_packets() {
+        let mut reader = PacketReader::new(16);
+        let stream = [framed(b"first"), framed(b"second"), framed(b"third")].concat();
+
+        // Split mid-length and mid-payload, then two packets in one message
+        reader.push(&stream[..2]);
+        assert_eq!(reader.next_frame(), None);
+        reader.push(&stream[2..7]);
+        assert_eq!(reader.next_frame(), None);
+        reader.push(&stream[7..]);
+        assert_eq!(reader.next_frame(), Some(Frame::Packet(BytesMut::from(&b"first"[..]))));
+        assert_eq!(reader.next_frame(), Some(Frame::Packet(BytesMut::from(&b"second"[..]))));
+        assert_eq!(reader.next_frame(), Some(Frame::Packet(BytesMut::from(&b"third"[..]))));
+        assert_eq!(reader.next_frame(), None);
+    }
+
+    #[test]
+    fn test_packet_reader_discards_oversized_packet() {
+        let mut reader = PacketReader::new(16);
+        let oversized = framed(&[7u8; 40]);
+
+        reader.push(&oversized[..12]);
+        assert_eq!(
+            reader.next_frame(),
+            Some(Frame::Oversized { len: 40, head: vec![7; OVERSIZED_HEAD] })
+        );
+        assert!(reader.buf.is_empty());
+
+        // The rest of it is dropped as it arrives, not buffered
+        reader.push(&oversized[12..30]);
+        assert!(reader.buf.is_empty());
+        reader.push(&[&oversized[30..], &framed(b"next")[..]].concat());
+        assert_eq!(reader.next_frame(), Some(Frame::Packet(BytesMut::from(&b"next"[..]))));
+        assert_eq!(reader.next_frame(), None);
+    }
+
+    /// Leak check: thousands of randomized sessions against an in-process
+    /// handler, after which every resource gauge must be back at its baseline
+    ///
+    /// Run with `cargo test -p snow-owl-sftp --features soak-test --lib soak`.
+    /// Failures report the seed; `SOAK_SEED` replays it and `SOAK_SESSIONS`
+    /// changes the number of sessions.
+    #[cfg(feature = "soak-test")]
+    mod soak {
+        use super::*;
+        use crate::MetricsSnapshot;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        use std::net::SocketAddr;
+
+        const AUTHORIZED_KEY: &str =
+            "AAAAC3NzaC1lZDI1NTE5AAAAIBJCKnTer1XN58sE2BWirtpbp9yAiLNZuygUxradzFNS";
+        const STRANGER_KEY: &str =
+            "AAAAC3NzaC1lZDI1NTE5AAAAIOHFTeJRpqHYdM496A/ztfF2ahTa1bfab4ZGEO0o6C2d";
+        const DEFAULT_SESSIONS: usize = 3000;
+        /// Sessions in flight at once, spread over `USERS` accounts
+        const CONCURRENCY: usize = 16;
+        const USERS: usize = 4;
+        /// Low enough that a user occasionally runs out of connection slots
+        const CONNECTIONS_PER_USER: usize = 6;
+        const CHUNK: u32 = 16 * 1024;
+
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        }
+
+        /// The gauges that must return to their baseline
+        fn gauges(snapshot: &MetricsSnapshot) -> [(&'static str, usize); 7] {
+            [
+                ("live_sessions", snapshot.live_sessions),
+                ("open_file_handles", snapshot.open_file_handles),
+                ("open_dir_handles", snapshot.open_dir_handles),
+                ("read_ahead_bytes", snapshot.read_ahead_bytes),
+                ("queued_audit_events", snapshot.queued_audit_events),
+                ("rate_limiter_entries", snapshot.rate_limiter_entries),
+                ("tracked_connections", snapshot.tracked_connections),
+            ]
+        }
+
+        fn write_packet(request_id: u32, handle: &[u8], data: &[u8]) -> Vec<u8> {
+            let mut packet = BytesMut::new();
+            packet.put_u8(MessageType::Write as u8);
+            packet.put_u32(request_id);
+            codec::put_bytes(&mut packet, handle);
+            packet.put_u64(0);
+            codec::put_bytes(&mut packet, data);
+            packet.to_vec()
+        }
+
+        fn handle_packet(kind: MessageType, request_id: u32, handle: &[u8]) -> Vec<u8> {
+            let mut packet = BytesMut::new();
+            packet.put_u8(kind as u8);
+            packet.put_u32(request_id);
+            codec::put_bytes(&mut packet, handle);
+            packet.to_vec()
+        }
+
+        /// One connection: authenticate, run a few operations, then
+        /// disconnect cleanly, abruptly, or with a request still in flight
+        async fn drive(mut connection: SftpSessionHandler, mut rng: StdRng) {
+            let key = if rng.gen_bool(0.1) { STRANGER_KEY } else { AUTHORIZED_KEY };
+            let key = russh::keys::parse_public_key_base64(key).expect("test key");
+            let user = format!("soak{}", rng.gen_range(0..USERS));
+            if rng.gen_bool(0.05) {
+                // Dropped mid-authentication: polled once, then abandoned
+                tokio::select! {
+                    biased;
+                    _ = connection.auth_publickey(&user, &key) => {}
+                    () = std::future::ready(()) => {}
+                }
+                return;
+            }
+            if !matches!(connection.auth_publickey(&user, &key).await, Ok(Auth::Accept)) {
+                return;
+            }
+
+            let session = connection.session.clone();
+            if process_packet(&session, &init_packet()).await.is_err() {
+                return;
+            }
+
+            let mut request_id = 1;
+            let mut next_id = || {
+                request_id += 1;
+                request_id
+            };
+            let mut open = Vec::new();
+            for _ in 0..rng.gen_range(0..8) {
+                let packet = match rng.gen_range(0..4) {
+                    0 => open_packet(next_id(), "/image.bin", OpenFlags::READ),
+                    1 => paths_packet(MessageType::Opendir, next_id(), &["/"]),
+                    2 => {
+                        let name = format!("/upload-{}.bin", rng.r#gen::<u32>());
+                        let flags = OpenFlags::WRITE | OpenFlags::CREAT | OpenFlags::TRUNC;
+                        open_packet(next_id(), &name, flags)
+                    }
+                    _ => paths_packet(MessageType::Stat, next_id(), &["/image.bin"]),
+                };
+                let reply = process_packet(&session, &packet).await;
+                let Some(handle) = reply.ok().as_deref().and_then(reply_handle) else {
+                    continue;
+                };
+
+                // Sequential reads start read-ahead; READDIR and WRITE fail
+                // harmlessly on the wrong kind of handle
+                for chunk in 0..rng.gen_range(0..6u64) {
+                    let packet = match rng.gen_range(0..3) {
+                        0 => read_packet(next_id(), &handle, chunk * u64::from(CHUNK), CHUNK),
+                        1 => handle_packet(MessageType::Readdir, next_id(), &handle),
+                        _ => write_packet(next_id(), &handle, &[0x5a; 512]),
+                    };
+                    let _ = process_packet(&session, &packet).await;
+                }
+                open.push(handle);
+
+                if rng.gen_bool(0.5)
+                    && let Some(handle) = open.pop()
+                {
+                    let close = handle_packet(MessageType::Close, next_id(), &handle);
+                    let _ = process_packet(&session, &close).await;
+                }
+            }
+
+            match rng.gen_range(0..3) {
+                0 => {
+                    for handle in open {
+                        let close = handle_packet(MessageType::Close, next_id(), &handle);
+                        let _ = process_packet(&session, &close).await;
+                    }
+                    drop(connection);
+                }
+                // Connection lost with handles still open
+                1 => drop(connection),
+                _ => {
+                    let packet = paths_packet(MessageType::Stat, next_id(), &["/image.bin"]);
+                    let request =
+                        tokio::spawn(async move { process_packet(&session, &packet).await });
+                    drop(connection);
+                    if rng.gen_bool(0.5) {
+                        request.abort();
+                    }
+                    let _ = request.await;
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn soak_gauges_return_to_baseline() {
+            let seed = env_or("SOAK_SEED", rand::random::<u64>());
+            let sessions = env_or("SOAK_SESSIONS", DEFAULT_SESSIONS);
+
+            let root = TempDir::new().expect("Failed to create temp dir");
+            std::fs::write(root.path().join("image.bin"), vec![0x5a; 256 * 1024])
+                .expect("write image");
+            let keys = TempDir::new().expect("Failed to create temp dir");
+            let keys_path = keys.path().join("authorized_keys");
+            std::fs::write(&keys_path, format!("ssh-ed25519 {} soak\n", AUTHORIZED_KEY))
+                .expect("write authorized_keys");
+
+            let metrics = Metrics::new();
+            let mut handler = gauge_handler(
+                &root,
+                |config| {
+                    config.authorized_keys_path = keys_path;
+                    config.max_connections_per_user = CONNECTIONS_PER_USER;
+                },
+                &metrics,
+            );
+            handler.sample_gauges().await;
+            let baseline = gauges(&metrics.snapshot());
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut running = tokio::task::JoinSet::new();
+            for _ in 0..sessions {
+                if running.len() >= CONCURRENCY {
+                    let finished = running.join_next().await.expect("running session");
+                    assert!(finished.is_ok(), "session panicked (seed {})", seed);
+                }
+                let peer = SocketAddr::from(([10, 0, 0, rng.r#gen::<u8>()], 22));
+                let connection = handler.new_client(Some(peer));
+                running.spawn(drive(connection, StdRng::seed_from_u64(rng.r#gen())));
+            }
+            while let Some(finished) = running.join_next().await {
+                assert!(finished.is_ok(), "session panicked (seed {})", seed);
+            }
+
+            // Dropped handlers unregister from a spawned task, and the audit
+            // writer drains on its own thread
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            loop {
+                handler.sample_gauges().await;
+                let snapshot = metrics.snapshot();
+                if (snapshot.tracked_connections == 0 && snapshot.queued_audit_events == 0)
+                    || std::time::Instant::now() > deadline
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            // Failure records outlive their sessions until the window and any
+            // lockout have passed; the periodic cleanup must then remove them
+            tokio::time::pause();
+            let retention =
+                handler.config.rate_limit_window_secs + handler.config.lockout_duration_secs;
+            tokio::time::advance(Duration::from_secs(retention + 1)).await;
+            handler.rate_limiter.cleanup_expired().await;
+            handler.sample_gauges().await;
+
+            let state = handler.dump_state().await;
+            assert_eq!(gauges(&metrics.snapshot()), baseline, "seed {}\n{}", seed, state);
+            assert!(
+                !state.contains("session "),
+                "sessions still registered (seed {})\n{}",
+                seed,
+                state
+            );
+            handler.audit.shutdown();
+        }
+    }
 }
@@ -0,0 +1,310 @@
+//! Symbolic link resolution beneath the served root
+//!
+//! Checking a link's target once, when it is created, is not enough: renaming
+//! a directory that contains a relative link changes where the link points,
+//! and links can be planted on the host outside SFTP. Paths are therefore
+//! walked one component at a time, expanding each link against the policy,
+//! so the path handed to the filesystem no longer contains any link that was
+//! not checked.
+//!
+//! ## NIST 800-53 Compliance
+//!
+//! - **AC-3 (Access Enforcement)**: Links cannot lead outside `root_dir`
+//! - **SI-10 (Information Input Validation)**: Link targets validated at creation and use
+//!
+//! ## STIG Compliance
+//!
+//! - **V-222396 (Path Traversal)**: `..` and link targets confined to the root
+
+use crate::config::SymlinkPolicy;
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+/// Link expansions allowed while resolving one path, matching Linux `MAXSYMLINKS`
+pub const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Why a path could not be resolved under the symlink policy
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SymlinkViolation {
+    /// A link was met while links are denied
+    #[error("symbolic links are disabled: {}", .0.display())]
+    Denied(PathBuf),
+    /// A link, or `..`, leads outside the root
+    #[error("symbolic link leads outside the root: {}", .0.display())]
+    Escapes(PathBuf),
+    /// Too many links, or a loop
+    #[error("too many levels of symbolic links: {}", .0.display())]
+    TooManyLinks(PathBuf),
+}
+
+/// Resolve `path` (inside `root`) with every link expanded under `policy`
+///
+/// Links in intermediate components are always followed; the final
+/// component is followed only when `follow_final` is set, so operations on
+/// the link itself (LSTAT, REMOVE, RENAME, READLINK) still see it.
+/// Components that do not exist yet are kept as they are, so the result can
+/// name a file about to be created.
+///
+/// Absolute link targets must name a path under `root`. With
+/// [`SymlinkPolicy::Allow`] the path is returned unchanged.
+///
+/// # Errors
+///
+/// Returns the violation for the first link (or `..`) that is not allowed.
+pub fn resolve_beneath(
+    root: &Path,
+    path: &Path,
+    follow_final: bool,
+    policy: SymlinkPolicy,
+) -> Result<PathBuf, SymlinkViolation> {
+    if policy == SymlinkPolicy::Allow {
+        return Ok(path.to_path_buf());
+    }
+
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| SymlinkViolation::Escapes(path.to_path_buf()))?;
+    let mut pending: VecDeque<OsString> = components(relative);
+    let mut resolved = root.to_path_buf();
+    let mut hops = 0;
+
+    while let Some(part) = pending.pop_front() {
+        if part == ".." {
+            if resolved == root {
+                return Err(SymlinkViolation::Escapes(path.to_path_buf()));
+            }
+            resolved.pop();
+            continue;
+        }
+
+        let candidate = resolved.join(&part);
+        let follow = follow_final || !pending.is_empty();
+        let is_link = std::fs::symlink_metadata(&candidate)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if !(is_link && follow) {
+            resolved = candidate;
+            continue;
+        }
+
+        if policy == SymlinkPolicy::Deny {
+            return Err(SymlinkViolation::Denied(candidate));
+        }
+
+        hops += 1;
+        if hops > MAX_SYMLINK_HOPS {
+            return Err(SymlinkViolation::TooManyLinks(path.to_path_buf()));
+        }
+        let Ok(target) = std::fs::read_link(&candidate) else {
+            // Removed since the metadata check; treat as a missing component
+            resolved = candidate;
+            continue;
+        };
+
+        // Relative targets continue from the link's directory
+        if target.is_absolute() {
+            let inside = target
+                .strip_prefix(root)
+                .map_err(|_| SymlinkViolation::Escapes(candidate.clone()))?;
+            resolved = root.to_path_buf();
+            prepend(&mut pending, inside);
+        } else {
+            prepend(&mut pending, &target);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Host target to store for a client-supplied link target
+///
+/// Absolute client paths are relative to the root (clients see `root` as
+/// `/`), so they are mapped onto the host; relative targets are kept as given.
+#[must_use]
+pub fn host_link_target(root: &Path, target: &str) -> PathBuf {
+    target
+        .strip_prefix('/')
+        .map_or_else(|| PathBuf::from(target), |inside| root.join(inside))
+}
+
+/// Client-visible form of a link target read from the host
+///
+/// Absolute targets under `root` are rewritten relative to the root so
+/// clients never see host paths. Returns `None` for absolute targets outside
+/// the root.
+#[must_use]
+pub fn client_link_target(root: &Path, target: &Path) -> Option<PathBuf> {
+    if target.is_absolute() {
+        target
+            .strip_prefix(root)
+            .ok()
+            .map(|inside| Path::new("/").join(inside))
+    } else {
+        Some(target.to_path_buf())
+    }
+}
+
+/// Normal and `..` components of a relative path; `.` is dropped
+fn components(path: &Path) -> VecDeque<OsString> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => None,
+        })
+        .collect()
+}
+
+/// Queue `path`'s components ahead of what is still pending
+fn prepend(pending: &mut VecDeque<OsString>, path: &Path) {
+    for component in components(path).into_iter().rev() {
+        pending.push_front(component);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    /// `<root>/dir/sub/link -> ../inside.txt` with `<root>/dir/inside.txt`
+    fn tree() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().expect("temp dir");
+        let root = temp.path().join("root");
+        std::fs::create_dir_all(root.join("dir/sub")).expect("mkdir");
+        std::fs::write(root.join("dir/inside.txt"), b"inside").expect("write");
+        std::fs::write(temp.path().join("outside.txt"), b"outside").expect("write");
+        symlink("../inside.txt", root.join("dir/sub/link")).expect("symlink");
+        (temp, root)
+    }
+
+    #[test]
+    fn test_internal_relative_link_is_expanded() {
+        let (_temp, root) = tree();
+        let resolved = resolve_beneath(
+            &root,
+            &root.join("dir/sub/link"),
+            true,
+            SymlinkPolicy::InternalOnly,
+        );
+        assert_eq!(resolved, Ok(root.join("dir/inside.txt")));
+
+        // Not following the final component leaves the link itself
+        let resolved = resolve_beneath(
+            &root,
+            &root.join("dir/sub/link"),
+            false,
+            SymlinkPolicy::InternalOnly,
+        );
+        assert_eq!(resolved, Ok(root.join("dir/sub/link")));
+    }
+
+    #[test]
+    fn test_link_escaping_after_rename_is_blocked() {
+        let (_temp, root) = tree();
+        symlink("../../dir/inside.txt", root.join("dir/sub/up")).expect("symlink");
+        assert_eq!(
+            resolve_beneath(
+                &root,
+                &root.join("dir/sub/up"),
+                true,
+                SymlinkPolicy::InternalOnly
+            ),
+            Ok(root.join("dir/inside.txt"))
+        );
+
+        // Once sub moves up a level, the same target points above the root
+        std::fs::rename(root.join("dir/sub"), root.join("sub")).expect("rename");
+        let resolved = resolve_beneath(
+            &root,
+            &root.join("sub/up"),
+            true,
+            SymlinkPolicy::InternalOnly,
+        );
+        assert!(matches!(resolved, Err(SymlinkViolation::Escapes(_))));
+    }
+
+    #[test]
+    fn test_absolute_targets_must_stay_under_root() {
+        let (temp, root) = tree();
+        symlink(root.join("dir"), root.join("abs-dir")).expect("symlink");
+        symlink(temp.path().join("outside.txt"), root.join("abs-out")).expect("symlink");
+
+        assert_eq!(
+            resolve_beneath(
+                &root,
+                &root.join("abs-dir/inside.txt"),
+                true,
+                SymlinkPolicy::InternalOnly
+            ),
+            Ok(root.join("dir/inside.txt"))
+        );
+        assert!(matches!(
+            resolve_beneath(
+                &root,
+                &root.join("abs-out"),
+                true,
+                SymlinkPolicy::InternalOnly
+            ),
+            Err(SymlinkViolation::Escapes(_))
+        ));
+        // Allow mode leaves the path to the filesystem
+        assert_eq!(
+            resolve_beneath(&root, &root.join("abs-out"), true, SymlinkPolicy::Allow),
+            Ok(root.join("abs-out"))
+        );
+    }
+
+    #[test]
+    fn test_deny_rejects_links_on_follow_only() {
+        let (_temp, root) = tree();
+        assert!(matches!(
+            resolve_beneath(&root, &root.join("dir/sub/link"), true, SymlinkPolicy::Deny),
+            Err(SymlinkViolation::Denied(_))
+        ));
+        assert_eq!(
+            resolve_beneath(
+                &root,
+                &root.join("dir/sub/link"),
+                false,
+                SymlinkPolicy::Deny
+            ),
+            Ok(root.join("dir/sub/link"))
+        );
+    }
+
+    #[test]
+    fn test_link_loop_is_bounded() {
+        let (_temp, root) = tree();
+        symlink("b", root.join("a")).expect("symlink");
+        symlink("a", root.join("b")).expect("symlink");
+
+        assert!(matches!(
+            resolve_beneath(&root, &root.join("a"), true, SymlinkPolicy::InternalOnly),
+            Err(SymlinkViolation::TooManyLinks(_))
+        ));
+    }
+
+    #[test]
+    fn test_link_target_mapping() {
+        let root = Path::new("/srv/sftp");
+        assert_eq!(
+            host_link_target(root, "/images/boot.wim"),
+            PathBuf::from("/srv/sftp/images/boot.wim")
+        );
+        assert_eq!(
+            host_link_target(root, "../boot.wim"),
+            PathBuf::from("../boot.wim")
+        );
+        assert_eq!(
+            client_link_target(root, Path::new("/srv/sftp/images/boot.wim")),
+            Some(PathBuf::from("/images/boot.wim"))
+        );
+        assert_eq!(
+            client_link_target(root, Path::new("../boot.wim")),
+            Some(PathBuf::from("../boot.wim"))
+        );
+        assert_eq!(client_link_target(root, Path::new("/etc/passwd")), None);
+    }
+}
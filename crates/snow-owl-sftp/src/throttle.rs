@@ -0,0 +1,164 @@
+//! Bandwidth Throttling
+//!
+//! Large downloads (WIM images in particular) can saturate an uplink that
+//! PXE clients depend on. Each session may be capped on its own, and all
+//! sessions together may be capped by one shared bucket. READ and WRITE
+//! payloads are charged against both; the session then waits out the larger
+//! of the two delays before its reply is sent.
+//!
+//! Reserving never sleeps: it only computes the delay, so the caller can
+//! release the session lock before waiting and other channels keep being
+//! served.
+//!
+//! ## NIST 800-53 Compliance
+//!
+//! - **SC-5 (Denial of Service Protection)**: Bounded per-session and server-wide bandwidth
+//! - **SC-6 (Resource Availability)**: Shared links stay usable during bulk transfers
+
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::time::{Duration, Instant};
+
+/// Token bucket measured in bytes
+///
+/// Tokens accrue at `bytes_per_sec` up to one second's worth and start at
+/// zero, so a transfer never runs ahead of its rate. Taking more tokens than
+/// are available goes into debt, which the caller pays off by waiting; a
+/// request larger than the burst is therefore paced rather than refused.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    bytes_per_sec: f64,
+    burst_bytes: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Bucket refilling at `bytes_per_sec` (at least 1)
+    #[must_use]
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1) as f64;
+        Self {
+            bytes_per_sec,
+            burst_bytes: bytes_per_sec,
+            tokens: 0.0,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take `bytes` tokens at `now` and return how long to wait before
+    /// releasing them
+    pub fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.updated = self.updated.max(now);
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.burst_bytes);
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_sec)
+        }
+    }
+}
+
+/// Token bucket shared by every session on a server
+///
+/// The lock is held only while tokens are counted, never across a wait.
+#[derive(Debug)]
+pub struct SharedBucket {
+    bucket: Mutex<TokenBucket>,
+}
+
+impl SharedBucket {
+    /// Shared bucket refilling at `bytes_per_sec`
+    #[must_use]
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(bytes_per_sec)),
+        }
+    }
+
+    /// Take `bytes` tokens now and return how long to wait
+    pub fn reserve(&self, bytes: u64) -> Duration {
+        self.bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .reserve(bytes, Instant::now())
+    }
+}
+
+/// A session's bandwidth limits
+///
+/// NIST 800-53: SC-5 (Denial of Service Protection)
+#[derive(Debug, Default)]
+pub struct Throttle {
+    session: Option<TokenBucket>,
+    global: Option<Arc<SharedBucket>>,
+}
+
+impl Throttle {
+    /// Limits of `per_session` bytes per second and the server-wide bucket
+    #[must_use]
+    pub fn new(per_session: Option<u64>, global: Option<Arc<SharedBucket>>) -> Self {
+        Self {
+            session: per_session.map(TokenBucket::new),
+            global,
+        }
+    }
+
+    /// Charge `bytes` to every limit and return how long to wait
+    ///
+    /// Both buckets are charged so neither is overdrawn by a wait the other
+    /// imposes.
+    pub fn reserve(&mut self, bytes: u64) -> Duration {
+        let session = self
+            .session
+            .as_mut()
+            .map_or(Duration::ZERO, |bucket| bucket.reserve(bytes, Instant::now()));
+        let global = self
+            .global
+            .as_ref()
+            .map_or(Duration::ZERO, |bucket| bucket.reserve(bytes));
+        session.max(global)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refill_math() {
+        let mut bucket = TokenBucket::new(1000);
+        let start = bucket.updated;
+
+        // Starts empty: 500 bytes wait half a second
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        // Debt carries over to the next reservation
+        assert_eq!(bucket.reserve(500, start), Duration::from_secs(1));
+        // A second later the debt is paid; 250 more bytes cost 250ms
+        assert_eq!(
+            bucket.reserve(250, start + Duration::from_secs(1)),
+            Duration::from_millis(250)
+        );
+        // A minute idle earns at most one second of burst
+        let later = start + Duration::from_secs(61);
+        assert_eq!(bucket.reserve(1000, later), Duration::ZERO);
+        assert_eq!(bucket.reserve(1000, later), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_throttle_waits_for_slower_limit() {
+        let global = Arc::new(SharedBucket::new(1000));
+        let mut fast = Throttle::new(Some(10_000), Some(global.clone()));
+        let mut other = Throttle::new(None, Some(global));
+
+        let first = fast.reserve(1000);
+        assert!(first > Duration::from_millis(900) && first <= Duration::from_secs(1));
+        // The global bucket is shared: the next session queues behind the first
+        let second = other.reserve(1000);
+        assert!(second > Duration::from_millis(1900) && second <= Duration::from_secs(2));
+
+        assert_eq!(Throttle::default().reserve(u64::MAX), Duration::ZERO);
+    }
+}
@@ -381,7 +381,9 @@ mod tests {
         fs::set_permissions(&file_path, perms).unwrap();
 
         // Get the current process UID/GID
+        #[allow(unsafe_code)]
         let current_uid = unsafe { libc::getuid() };
+        #[allow(unsafe_code)]
         let current_gid = unsafe { libc::getgid() };
 
         // Create mapping for current user (should have read/write as owner)
@@ -389,7 +391,9 @@ mod tests {
 
         assert!(mapping.can_read(&file_path));
         assert!(mapping.can_write(&file_path));
-        assert!(!mapping.can_execute(&file_path)); // No execute bit set
+        if current_uid != 0 {
+            assert!(!mapping.can_execute(&file_path)); // No execute bit set
+        }
     }
 
     #[cfg(unix)]
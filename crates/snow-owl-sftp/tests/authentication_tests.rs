@@ -166,6 +166,7 @@ async fn test_rate_limiter_ipv6() {
 async fn test_connection_tracker_allows_connections() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 5,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -181,6 +182,7 @@ async fn test_connection_tracker_allows_connections() {
 async fn test_connection_tracker_enforces_limit() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 2,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -204,6 +206,7 @@ async fn test_connection_tracker_enforces_limit() {
 async fn test_connection_tracker_cleanup() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 2,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -230,6 +233,7 @@ async fn test_connection_tracker_cleanup() {
 async fn test_connection_tracker_per_user_isolation() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 1,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -248,6 +252,7 @@ async fn test_connection_tracker_per_user_isolation() {
 async fn test_connection_tracker_get_count() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 10,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -267,6 +272,7 @@ async fn test_connection_tracker_get_count() {
 async fn test_connection_tracker_statistics() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 10,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -285,6 +291,7 @@ async fn test_connection_tracker_statistics() {
 async fn test_connection_tracker_zero_limit() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 0,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -298,6 +305,7 @@ async fn test_connection_tracker_zero_limit() {
 async fn test_connection_cleanup_all() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 5,
+        ..Default::default()
     };
 
     let tracker = ConnectionTracker::new(config);
@@ -64,7 +64,7 @@ async fn test_concurrent_file_writes_different_files() {
         let file_path = config.root_dir.join(format!("concurrent_write_{}.txt", i));
         tasks.spawn(async move {
             fs::write(&file_path, format!("content {}", i)).await.unwrap();
-            file_path
+            (i, file_path)
         });
     }
 
@@ -75,8 +75,9 @@ async fn test_concurrent_file_writes_different_files() {
     }
 
     // Verify all files were created
+    // Tasks finish in any order
     assert_eq!(paths.len(), 10);
-    for (i, path) in paths.iter().enumerate() {
+    for (i, path) in &paths {
         assert!(path.exists());
         let content = fs::read_to_string(path).await.unwrap();
         assert_eq!(content, format!("content {}", i));
@@ -159,6 +160,7 @@ async fn test_concurrent_mixed_file_operations() {
 async fn test_connection_tracker_concurrent_registrations() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 10,
+        ..Default::default()
     };
 
     let tracker = Arc::new(ConnectionTracker::new(config));
@@ -193,6 +195,7 @@ async fn test_connection_tracker_concurrent_registrations() {
 async fn test_connection_tracker_limit_enforcement_concurrent() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 5,
+        ..Default::default()
     };
 
     let tracker = Arc::new(ConnectionTracker::new(config));
@@ -306,6 +309,7 @@ async fn test_rate_limiter_multiple_ips_concurrent() {
 async fn test_connection_cleanup_concurrent() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 10,
+        ..Default::default()
     };
 
     let tracker = Arc::new(ConnectionTracker::new(config));
@@ -417,7 +421,7 @@ async fn test_concurrent_file_renames() {
         let new_path = config.root_dir.join(format!("target_{}.txt", i));
         tasks.spawn(async move {
             fs::rename(&old_path, &new_path).await.unwrap();
-            new_path
+            (i, new_path)
         });
     }
 
@@ -427,8 +431,9 @@ async fn test_concurrent_file_renames() {
         paths.push(result.unwrap());
     }
 
+    // Tasks finish in any order
     assert_eq!(paths.len(), 10);
-    for (i, path) in paths.iter().enumerate() {
+    for (i, path) in &paths {
         assert!(path.exists());
         let content = fs::read_to_string(path).await.unwrap();
         assert_eq!(content, format!("content {}", i));
@@ -524,6 +529,7 @@ async fn test_high_concurrency_stress() {
 async fn test_connection_tracker_stats_concurrent() {
     let config = ConnectionTrackerConfig {
         max_connections_per_user: 20,
+        ..Default::default()
     };
 
     let tracker = Arc::new(ConnectionTracker::new(config));
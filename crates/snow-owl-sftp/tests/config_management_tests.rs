@@ -5,11 +5,17 @@
 //! - IP whitelist/blacklist
 //! - Time-based access restrictions
 //! - Operation-based access control
-
-use snow_owl_sftp::{AccessSchedule, Config, UserConfig};
+//! - Read-only mode and per-path permission rules
+
+use snow_owl_core::config_schema::{
+    check_config_file, config_schema, undocumented_properties, validate_toml,
+};
+use snow_owl_sftp::{
+    AccessSchedule, Config, FileModePolicy, HostKeyPaths, PathAccess, PathRule, UserConfig,
+};
 use std::collections::HashMap;
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 
 #[test]
@@ -134,6 +140,8 @@ fn test_access_schedule_validation_invalid_hour() {
         start_hour: 25, // Invalid
         end_hour: 17,
         timezone: "UTC".to_string(),
+        windows: Vec::new(),
+        terminate_sessions: false,
     });
 
     config.users.insert("testuser".to_string(), user_config);
@@ -154,6 +162,8 @@ fn test_access_schedule_validation_invalid_day() {
         start_hour: 9,
         end_hour: 17,
         timezone: "UTC".to_string(),
+        windows: Vec::new(),
+        terminate_sessions: false,
     });
 
     config.users.insert("testuser".to_string(), user_config);
@@ -278,6 +288,8 @@ fn test_multiple_users_configuration() {
         start_hour: 9,
         end_hour: 17,
         timezone: "UTC".to_string(),
+        windows: Vec::new(),
+        terminate_sessions: false,
     });
 
     config.users.insert("user1".to_string(), user1);
@@ -318,7 +330,7 @@ fn test_config_with_all_features() {
     config.global_bandwidth_limit = 50_000_000; // 50 MB/s
 
     // IP whitelist
-    config.ip_whitelist.push("192.168.1.0".parse().expect("Failed to parse IP"));
+    config.ip_whitelist.push("192.168.1.1".parse().expect("Failed to parse IP"));
     config.ip_whitelist.push("10.0.0.0".parse().expect("Failed to parse IP"));
 
     // IP blacklist
@@ -354,3 +366,340 @@ fn test_config_with_all_features() {
     assert_eq!(user.max_file_size, 1_000_000_000);
     assert_eq!(user.max_connections, Some(3));
 }
+
+fn rule(pattern: &str, write: bool, delete: bool) -> PathRule {
+    PathRule {
+        pattern: pattern.to_string(),
+        read: true,
+        write,
+        delete,
+    }
+}
+
+#[test]
+fn test_read_only_mode() {
+    let mut config = Config::default();
+    config.read_only = true;
+    config.path_rules = vec![rule("**", true, true)];
+
+    assert!(config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Read));
+    assert!(!config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Write));
+    assert!(!config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Delete));
+}
+
+#[test]
+fn test_most_specific_path_rule_wins() {
+    let mut config = Config::default();
+    config.path_rules = vec![
+        rule("uploads/**", true, true),
+        rule("**", false, false),
+        rule("uploads/*.lock", false, false),
+    ];
+
+    assert!(config.is_path_access_allowed(Path::new("/uploads/x/a.wim"), PathAccess::Write));
+    assert!(!config.is_path_access_allowed(Path::new("/uploads/a.lock"), PathAccess::Delete));
+    // `*` does not cross directories
+    assert!(config.is_path_access_allowed(Path::new("/uploads/x/a.lock"), PathAccess::Delete));
+    assert!(!config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Write));
+    assert!(config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Read));
+}
+
+#[test]
+fn test_unmatched_path_keeps_full_access() {
+    let mut config = Config::default();
+    config.path_rules = vec![rule("images/**", false, false)];
+
+    assert!(config.is_path_access_allowed(Path::new("/other.txt"), PathAccess::Write));
+    assert!(!config.is_path_access_allowed(Path::new("/images/a.wim"), PathAccess::Write));
+}
+
+#[test]
+fn test_invalid_path_rule_pattern_rejected() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    config.path_rules = vec![rule("uploads/[", true, true)];
+
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_zero_readdir_batch_size_rejected() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    assert_eq!(config.readdir_batch_size, 100);
+    assert!(config.validate().is_ok());
+
+    config.readdir_batch_size = 0;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_zero_max_open_handles_rejected() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    assert_eq!(config.max_open_handles, 1024);
+    assert!(config.validate().is_ok());
+
+    config.max_open_handles = 0;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_config_schema_documents_every_field() {
+    let schema = config_schema::<Config>();
+    assert_eq!(undocumented_properties(&schema), Vec::<String>::new());
+}
+
+#[test]
+fn test_shipped_example_configs_satisfy_schema() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for example in ["config.example.toml", "examples/config.toml"] {
+        let report = check_config_file::<Config>(&crate_dir.join(example), None)
+            .expect("Failed to check example config");
+        assert!(report.is_valid(), "{example}: {:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{example}: {:?}", report.warnings);
+    }
+}
+
+#[test]
+fn test_schema_enforces_validation_limits() {
+    let schema = config_schema::<Config>();
+    let report = validate_toml(
+        &schema,
+        r#"
+max_packet_size = 1024
+max_bytes_per_sec_global = 0
+bind_adress = "0.0.0.0"
+
+[logging.audit_channel]
+batch_size = 0
+
+[users.alice.access_schedule]
+start_hour = 24
+allowed_days = [1, 7]
+"#,
+    )
+    .expect("Failed to validate config");
+
+    let mut fields: Vec<&str> = report
+        .errors
+        .iter()
+        .filter_map(|e| e.split(':').next())
+        .collect();
+    fields.sort_unstable();
+    assert_eq!(
+        fields,
+        [
+            "(root)",
+            "logging.audit_channel.batch_size",
+            "max_bytes_per_sec_global",
+            "max_packet_size",
+            "users.alice.access_schedule.allowed_days.1",
+            "users.alice.access_schedule.start_hour",
+        ]
+    );
+}
+
+#[test]
+fn test_deprecated_bandwidth_limit_warns() {
+    let schema = config_schema::<Config>();
+    let report = validate_toml(&schema, "global_bandwidth_limit = 1048576\n")
+        .expect("Failed to validate config");
+
+    assert!(report.is_valid(), "{:?}", report.errors);
+    assert_eq!(
+        report.warnings,
+        ["global_bandwidth_limit is deprecated: Superseded by `max_bytes_per_sec_global`, \
+          which wins when both are set"]
+    );
+}
+
+#[test]
+fn test_file_mode_policy_from_toml() {
+    let parse = |toml: &str| -> Config { toml::from_str(toml).expect("Failed to parse config") };
+
+    assert_eq!(Config::default().file_mode_policy, FileModePolicy::ClientRequested);
+    assert_eq!(
+        parse("file_mode_policy = { fixed = 0o640 }\n").file_mode_policy,
+        FileModePolicy::Fixed(0o640)
+    );
+    assert_eq!(
+        parse("file_mode_policy = { masked = 0o027 }\n").file_mode_policy,
+        FileModePolicy::Masked(0o027)
+    );
+
+    let policy = FileModePolicy::Masked(0o027);
+    assert_eq!(policy.creation_mode(None, 0o666), 0o640);
+    assert_eq!(policy.creation_mode(Some(0o100755), 0o666), 0o750);
+    assert!(policy.permits(0o100750));
+    assert!(!policy.permits(0o644));
+    assert!(FileModePolicy::Fixed(0o640).permits(0o600));
+    assert!(!FileModePolicy::Fixed(0o640).permits(0o660));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    config.file_mode_policy = FileModePolicy::Fixed(0o10644);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_host_key_path_accepts_one_path_or_a_list() {
+    let config: Config = toml::from_str("host_key_path = \"/etc/ssh/ssh_host_ed25519_key\"\n")
+        .expect("Failed to parse config");
+    assert_eq!(
+        config.host_key_path.paths(),
+        [PathBuf::from("/etc/ssh/ssh_host_ed25519_key")]
+    );
+
+    let config: Config = toml::from_str(
+        "host_key_path = [\"/etc/ssh/ssh_host_ecdsa_key\", \"/etc/ssh/ssh_host_ed25519_key\"]\n",
+    )
+    .expect("Failed to parse config");
+    assert_eq!(
+        config.host_key_path.paths(),
+        [
+            PathBuf::from("/etc/ssh/ssh_host_ecdsa_key"),
+            PathBuf::from("/etc/ssh/ssh_host_ed25519_key"),
+        ]
+    );
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    config.host_key_path = HostKeyPaths::Many(Vec::new());
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_user_roots_from_toml() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let imaging = temp_dir.path().join("imaging");
+    std::fs::create_dir(&imaging).expect("Failed to create dir");
+    let toml_str = format!(
+        "root_dir = {:?}\n[user_roots]\nimaging = {:?}\n",
+        temp_dir.path(),
+        imaging
+    );
+    let mut config: Config = toml::from_str(&toml_str).expect("Failed to parse config");
+    assert!(config.validate().is_ok());
+    assert_eq!(config.root_for(Some("imaging")).expect("root"), imaging);
+    assert_eq!(config.root_for(Some("other")).expect("root"), temp_dir.path());
+    assert_eq!(config.root_for(None).expect("root"), temp_dir.path());
+
+    config
+        .user_roots
+        .insert("drivers".to_string(), temp_dir.path().join("missing"));
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_user_role_from_toml() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let toml_str = format!(
+        "root_dir = {:?}\n\
+         [roles.download]\nread_only = true\n\
+         [users.kiosk]\nrole = \"download\"\n\
+         [users.narrow]\nrole = \"download\"\ndenied_operations = [\"readdir\"]\n",
+        temp_dir.path()
+    );
+    let mut config: Config = toml::from_str(&toml_str).expect("Failed to parse config");
+    assert!(config.validate().is_ok());
+
+    assert!(config.is_operation_allowed("kiosk", "read"));
+    assert!(config.is_operation_allowed("kiosk", "readdir"));
+    assert!(!config.is_operation_allowed("kiosk", "write"));
+    assert!(!config.is_operation_allowed("kiosk", "remove"));
+    // A user entry narrows its role but cannot widen it
+    assert!(!config.is_operation_allowed("narrow", "readdir"));
+    config.users.get_mut("narrow").unwrap().allowed_operations = Some(vec!["write".to_string()]);
+    assert!(!config.is_operation_allowed("narrow", "write"));
+
+    config.users.get_mut("kiosk").unwrap().role = Some("missing".to_string());
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_audit_file_from_toml() {
+    let config: Config = toml::from_str(
+        "[logging]\naudit_file = \"/var/log/snow-owl/sftp-audit.jsonl\"\n",
+    )
+    .expect("Failed to parse config");
+    assert_eq!(
+        config.logging.audit_file.as_deref(),
+        Some(Path::new("/var/log/snow-owl/sftp-audit.jsonl"))
+    );
+    // Other logging settings keep their defaults
+    assert_eq!(config.logging.audit_channel.batch_size, 256);
+    assert!(Config::default().logging.audit_file.is_none());
+}
+
+#[test]
+fn test_max_read_len_validation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    assert_eq!(config.max_packet_size, 1048576);
+    assert_eq!(config.max_read_len, 262144);
+    assert!(config.validate().is_ok());
+
+    config.max_read_len = 0;
+    assert!(config.validate().is_err());
+
+    // A READ reply may not be larger than the packets the server accepts
+    config.max_read_len = config.max_packet_size + 1;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_password_auth_settings() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config: Config = toml::from_str(&format!(
+        "root_dir = {:?}\npassword_auth = true\npassword_file = {:?}\n",
+        temp_dir.path(),
+        temp_dir.path().join("passwords")
+    ))
+    .expect("Failed to parse config");
+    assert!(config.password_auth);
+    assert!(!Config::default().password_auth);
+
+    // The password file must exist once password logins are enabled
+    assert!(config.validate().is_err());
+    std::fs::write(temp_dir.path().join("passwords"), "").expect("write password file");
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_user_root_template() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let imaging = temp_dir.path().join("imaging");
+    std::fs::create_dir(&imaging).expect("Failed to create dir");
+    let toml_str = format!(
+        "root_dir = {:?}\nuser_root_template = \"/srv/sftp/{{user}}\"\ncreate_user_root = true\n[user_roots]\nimaging = {:?}\n",
+        temp_dir.path(),
+        imaging
+    );
+    let mut config: Config = toml::from_str(&toml_str).expect("Failed to parse config");
+    assert!(config.validate().is_ok());
+    assert!(config.create_user_root);
+
+    // An explicit entry wins over the template
+    assert_eq!(config.root_for(Some("imaging")).expect("root"), imaging);
+    assert_eq!(
+        config.root_for(Some("deploy")).expect("root"),
+        PathBuf::from("/srv/sftp/deploy")
+    );
+    assert_eq!(config.root_for(None).expect("root"), temp_dir.path());
+
+    // NIST 800-53: SI-10 - Usernames that would leave the template's directory
+    for username in ["../evil", "a/b", "..", "", "x\\y", "nul\0"] {
+        assert!(config.root_for(Some(username)).is_err(), "{:?}", username);
+    }
+
+    config.user_root_template = Some("/srv/sftp/shared".to_string());
+    assert!(config.validate().is_err());
+}
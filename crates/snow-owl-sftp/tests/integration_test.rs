@@ -7,17 +7,24 @@ use std::path::PathBuf;
 
 #[tokio::test]
 async fn test_server_creation() {
-    let mut config = Config::default();
-    config.root_dir = PathBuf::from("/tmp/sftp_test");
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let host_key = temp_dir.path().join("ssh_host_ed25519_key");
+    let generated = std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&host_key)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !generated {
+        eprintln!("Skipping test: 'ssh-keygen' command not found");
+        return;
+    }
 
-    // Create test directory
-    std::fs::create_dir_all(&config.root_dir).unwrap();
+    let mut config = Config::default();
+    config.root_dir = temp_dir.path().to_path_buf();
+    config.host_key_path = host_key.into();
 
     let result = Server::new(config).await;
     assert!(result.is_ok(), "Server should be created successfully");
-
-    // Cleanup
-    std::fs::remove_dir_all("/tmp/sftp_test").ok();
 }
 
 #[test]
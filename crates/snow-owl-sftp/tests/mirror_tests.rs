@@ -0,0 +1,224 @@
+//! Directory synchronization (mirror) tests
+//!
+//! NIST 800-53: CM-3 (Configuration Change Control), SI-7 (Software, Firmware, and Information Integrity)
+//! Implementation: Mirrors local trees onto an in-process server with `Client::sync_dir`
+//!
+//! Keys are made with `ssh-keygen`; the tests are skipped when it is not installed.
+
+use snow_owl_sftp::{Client, Config, Server, SyncOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, UNIX_EPOCH};
+use tempfile::TempDir;
+
+/// Generate an Ed25519 key pair at `path` and `path.pub`
+fn generate_key(path: &Path) -> bool {
+    Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Running server and a client connected to it
+struct Fixture {
+    _temp_dir: TempDir,
+    local: PathBuf,
+    served: PathBuf,
+    client: Client,
+}
+
+/// Start a server on a free loopback port and connect to it
+async fn start() -> Option<Fixture> {
+    let temp_dir = TempDir::new().unwrap();
+    let local = temp_dir.path().join("local");
+    let served = temp_dir.path().join("served");
+    let keys = temp_dir.path().join("keys");
+    for dir in [&local, &served, &keys] {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    let client_key = keys.join("client_key");
+    let host_key = keys.join("host_key");
+    if !generate_key(&client_key) || !generate_key(&host_key) {
+        eprintln!("Skipping test: 'ssh-keygen' command not found");
+        return None;
+    }
+    let authorized_keys = keys.join("authorized_keys");
+    fs::copy(keys.join("client_key.pub"), &authorized_keys).unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut config = Config::default();
+    config.bind_address = "127.0.0.1".to_string();
+    config.port = port;
+    config.root_dir = served.clone();
+    config.host_key_path = host_key.into();
+    config.authorized_keys_path = authorized_keys;
+    let server = Server::new(config).await.expect("Server should start");
+    tokio::spawn(server.run());
+
+    // The listener binds inside the spawned task
+    for _ in 0..50 {
+        if let Ok(client) = Client::connect("127.0.0.1", port, "deploy", &client_key).await {
+            return Some(Fixture {
+                _temp_dir: temp_dir,
+                local,
+                served,
+                client,
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Server on port {} never accepted a connection", port);
+}
+
+/// Write a small WinPE-style tree under `root`
+fn populate(root: &Path) {
+    fs::create_dir_all(root.join("boot/efi")).unwrap();
+    fs::write(root.join("boot/bcd"), b"bcd store").unwrap();
+    fs::write(root.join("boot/efi/bootx64.efi"), b"efi loader").unwrap();
+    fs::write(root.join("startnet.cmd"), b"wpeinit\n").unwrap();
+}
+
+fn mtime_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .unwrap()
+        .modified()
+        .unwrap()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+const FILES: [&str; 3] = ["boot/bcd", "boot/efi/bootx64.efi", "startnet.cmd"];
+
+/// NIST 800-53: CM-3 - First sync creates the directories and uploads every file
+#[tokio::test]
+async fn test_initial_sync_uploads_tree() {
+    let Some(mut fx) = start().await else { return };
+    populate(&fx.local);
+
+    let report = fx
+        .client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("sync should succeed");
+
+    assert!(report.is_success(), "failures: {:?}", report.failed);
+    assert_eq!(report.uploaded, FILES);
+    assert!(report.skipped.is_empty());
+    assert!(report.deleted.is_empty());
+
+    let remote = fx.served.join("winpe");
+    for file in FILES {
+        assert_eq!(
+            fs::read(remote.join(file)).unwrap(),
+            fs::read(fx.local.join(file)).unwrap(),
+            "{} differs",
+            file
+        );
+        assert_eq!(mtime_secs(&remote.join(file)), mtime_secs(&fx.local.join(file)));
+    }
+}
+
+/// NIST 800-53: SI-7 - Syncing an unchanged tree again transfers nothing
+#[tokio::test]
+async fn test_resync_of_unchanged_tree_skips_everything() {
+    let Some(mut fx) = start().await else { return };
+    populate(&fx.local);
+
+    fx.client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("first sync should succeed");
+    let report = fx
+        .client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("second sync should succeed");
+
+    assert!(report.is_success(), "failures: {:?}", report.failed);
+    assert!(report.uploaded.is_empty(), "uploaded: {:?}", report.uploaded);
+    assert_eq!(report.skipped, FILES);
+    assert!(report.deleted.is_empty());
+}
+
+/// NIST 800-53: CM-3 - Only the changed file is uploaded again
+#[tokio::test]
+async fn test_resync_uploads_changed_file() {
+    let Some(mut fx) = start().await else { return };
+    populate(&fx.local);
+
+    fx.client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("first sync should succeed");
+    fs::write(fx.local.join("startnet.cmd"), b"wpeinit\nx:\\deploy.cmd\n").unwrap();
+
+    let options = SyncOptions {
+        concurrency: 1,
+        ..SyncOptions::default()
+    };
+    let report = fx
+        .client
+        .sync_dir(&fx.local, "/winpe", options)
+        .await
+        .expect("second sync should succeed");
+
+    assert!(report.is_success(), "failures: {:?}", report.failed);
+    assert_eq!(report.uploaded, ["startnet.cmd"]);
+    assert_eq!(report.skipped, ["boot/bcd", "boot/efi/bootx64.efi"]);
+    assert_eq!(
+        fs::read(fx.served.join("winpe/startnet.cmd")).unwrap(),
+        b"wpeinit\nx:\\deploy.cmd\n"
+    );
+}
+
+/// NIST 800-53: CM-3 - Remote extras are removed only when asked, contents first
+#[tokio::test]
+async fn test_delete_extraneous_removes_remote_extras() {
+    let Some(mut fx) = start().await else { return };
+    populate(&fx.local);
+
+    fx.client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("first sync should succeed");
+    let remote = fx.served.join("winpe");
+    fs::write(remote.join("old.wim"), b"stale image").unwrap();
+    fs::create_dir_all(remote.join("stale/dir")).unwrap();
+    fs::write(remote.join("stale/dir/x.txt"), b"x").unwrap();
+
+    let report = fx
+        .client
+        .sync_dir(&fx.local, "/winpe", SyncOptions::default())
+        .await
+        .expect("sync without deletion should succeed");
+    assert!(report.deleted.is_empty());
+    assert!(remote.join("old.wim").exists());
+
+    let options = SyncOptions {
+        delete_extraneous: true,
+        ..SyncOptions::default()
+    };
+    let report = fx
+        .client
+        .sync_dir(&fx.local, "/winpe", options)
+        .await
+        .expect("sync with deletion should succeed");
+
+    assert!(report.is_success(), "failures: {:?}", report.failed);
+    assert_eq!(report.deleted, ["stale/dir/x.txt", "stale/dir", "stale", "old.wim"]);
+    assert!(!remote.join("old.wim").exists());
+    assert!(!remote.join("stale").exists());
+    for file in FILES {
+        assert!(remote.join(file).exists(), "{} was deleted", file);
+    }
+}
@@ -4,7 +4,7 @@
 //! STIG: V-222566, V-222396
 //! Implementation: Tests for RFC-compliant protocol implementation
 
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 use snow_owl_sftp::protocol::{codec, FileAttrs, MessageType, OpenFlags, StatusCode, SFTP_VERSION};
 
 /// NIST 800-53: SI-11 - Test protocol message type conversions
@@ -170,14 +170,16 @@ fn test_file_attrs_empty() {
 /// NIST 800-53: SI-10 - Test string codec with various lengths
 #[test]
 fn test_codec_string_various_lengths() {
+    let long_x = "x".repeat(100);
+    let long_y = "y".repeat(1000);
     let test_cases = vec![
         "",
         "a",
         "Hello",
         "Hello, SFTP!",
-        &"x".repeat(100),
-        &"y".repeat(1000),
-        &"long string with unicode: 你好世界 🚀",
+        long_x.as_str(),
+        long_y.as_str(),
+        "long string with unicode: 你好世界 🚀",
     ];
 
     for test_string in test_cases {
@@ -0,0 +1,250 @@
+//! Windowsize reduction for clients on lossy links
+//!
+//! With RFC 7440 windows, a lost DATA block or ACK makes the server resend
+//! the whole window, so on a lossy link a large window multiplies what each
+//! loss costs. The windowsize cannot change once it has been negotiated, so
+//! the server remembers which clients needed retransmits and grants them a
+//! smaller window in the OACK of their next read request:
+//!
+//! - A transfer with at least `retransmit_threshold` window retransmissions
+//!   halves the window its client is granted, down to `min_windowsize`.
+//! - A transfer without retransmissions doubles it again; once the client
+//!   could have its requested window back, it is forgotten.
+//! - A reduction not renewed by another lossy transfer within `memory_secs`
+//!   expires.
+//!
+//! NIST 800-53 Controls:
+//! - SC-5: Denial of Service Protection (retransmission traffic bounded)
+
+use crate::config::AdaptiveWindowConfig;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Reduced windowsize for one client
+#[derive(Debug, Clone, Copy)]
+struct WindowCap {
+    windowsize: usize,
+    /// Last transfer that crossed the retransmit threshold
+    lossy_at: Instant,
+}
+
+/// Windowsize caps for clients whose recent read transfers were lossy
+#[derive(Debug)]
+pub struct AdaptiveWindow {
+    enabled: bool,
+    retransmit_threshold: u32,
+    min_windowsize: usize,
+    memory: Duration,
+    caps: Mutex<HashMap<IpAddr, WindowCap>>,
+}
+
+impl AdaptiveWindow {
+    pub fn new(config: &AdaptiveWindowConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            retransmit_threshold: config.retransmit_threshold.max(1),
+            min_windowsize: config.min_windowsize.max(1),
+            memory: Duration::from_secs(config.memory_secs),
+            caps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Windowsize to grant `client` when it requests `requested`
+    pub fn grant(&self, client: IpAddr, requested: usize) -> usize {
+        if !self.enabled {
+            return requested;
+        }
+        let mut caps = self.caps.lock().unwrap_or_else(PoisonError::into_inner);
+        match caps.get(&client) {
+            Some(cap) if cap.lossy_at.elapsed() >= self.memory => {
+                caps.remove(&client);
+                requested
+            }
+            Some(cap) => requested.min(cap.windowsize),
+            None => requested,
+        }
+    }
+
+    /// Start counting the windows of a read transfer to `client` that was
+    /// granted `windowsize` after requesting `requested`
+    pub fn report(
+        self: &Arc<Self>,
+        client: IpAddr,
+        requested: usize,
+        windowsize: usize,
+    ) -> WindowReport {
+        WindowReport {
+            window: self.enabled.then(|| Arc::clone(self)),
+            client,
+            requested,
+            windowsize,
+            windows_sent: 0,
+            retransmits: 0,
+        }
+    }
+
+    /// Number of clients currently granted a reduced windowsize
+    pub fn reduced_clients(&self) -> usize {
+        self.caps
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    fn record(&self, report: &WindowReport) {
+        let mut caps = self.caps.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        if report.retransmits >= self.retransmit_threshold {
+            let reduced = (report.windowsize / 2).max(self.min_windowsize);
+            if reduced < report.requested {
+                info!(
+                    "Client {} retransmitted {} windows at windowsize {}; granting at most {} for {}s",
+                    report.client,
+                    report.retransmits,
+                    report.windowsize,
+                    reduced,
+                    self.memory.as_secs()
+                );
+            }
+            caps.retain(|_, cap| now.duration_since(cap.lossy_at) < self.memory);
+            caps.insert(
+                report.client,
+                WindowCap {
+                    windowsize: reduced,
+                    lossy_at: now,
+                },
+            );
+        } else if report.retransmits == 0
+            && let Some(cap) = caps.get_mut(&report.client)
+        {
+            cap.windowsize = cap.windowsize.max(report.windowsize.saturating_mul(2));
+            if cap.windowsize >= report.requested {
+                caps.remove(&report.client);
+            }
+        }
+    }
+}
+
+/// Window counts of one read transfer, recorded when it is dropped
+///
+/// A transfer that never sent a window (a refused or failed request) is
+/// not recorded.
+#[derive(Debug)]
+pub struct WindowReport {
+    window: Option<Arc<AdaptiveWindow>>,
+    client: IpAddr,
+    requested: usize,
+    windowsize: usize,
+    windows_sent: u64,
+    retransmits: u32,
+}
+
+impl WindowReport {
+    /// Count a window transmission; `retransmission` is true when the
+    /// window was sent before
+    pub fn window_sent(&mut self, retransmission: bool) {
+        self.windows_sent += 1;
+        if retransmission {
+            self.retransmits = self.retransmits.saturating_add(1);
+        }
+    }
+}
+
+impl Drop for WindowReport {
+    fn drop(&mut self) {
+        if self.windows_sent > 0
+            && let Some(window) = self.window.take()
+        {
+            window.record(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10));
+    const OTHER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 11));
+
+    fn adaptive_window(enabled: bool) -> Arc<AdaptiveWindow> {
+        Arc::new(AdaptiveWindow::new(&AdaptiveWindowConfig {
+            enabled,
+            retransmit_threshold: 2,
+            min_windowsize: 2,
+            memory_secs: 300,
+        }))
+    }
+
+    /// Read transfer asking for `requested` that retransmitted `retransmits` windows
+    fn transfer(window: &Arc<AdaptiveWindow>, requested: usize, retransmits: u32) {
+        let granted = window.grant(CLIENT, requested);
+        let mut report = window.report(CLIENT, requested, granted);
+        report.window_sent(false);
+        for _ in 0..retransmits {
+            report.window_sent(true);
+        }
+    }
+
+    #[test]
+    fn test_lossy_transfers_shrink_and_clean_ones_restore() {
+        let window = adaptive_window(true);
+
+        // One retransmit is below the threshold
+        transfer(&window, 16, 1);
+        assert_eq!(window.grant(CLIENT, 16), 16);
+
+        transfer(&window, 16, 3);
+        assert_eq!(window.grant(CLIENT, 16), 8);
+        assert_eq!(window.grant(CLIENT, 4), 4);
+        assert_eq!(window.grant(OTHER, 16), 16);
+
+        transfer(&window, 16, 2);
+        transfer(&window, 16, 2);
+        transfer(&window, 16, 2);
+        assert_eq!(window.grant(CLIENT, 16), 2, "never below min_windowsize");
+
+        transfer(&window, 16, 0);
+        assert_eq!(window.grant(CLIENT, 16), 4);
+        transfer(&window, 16, 0);
+        transfer(&window, 16, 0);
+        assert_eq!(window.grant(CLIENT, 16), 16);
+        assert_eq!(window.reduced_clients(), 0);
+    }
+
+    #[test]
+    fn test_transfers_without_windows_are_not_recorded() {
+        let window = adaptive_window(true);
+        transfer(&window, 16, 4);
+
+        // A refused request drops its report without sending anything
+        drop(window.report(CLIENT, 16, 8));
+        assert_eq!(window.grant(CLIENT, 16), 8);
+    }
+
+    #[test]
+    fn test_disabled_grants_requested_windowsize() {
+        let window = adaptive_window(false);
+        transfer(&window, 16, 10);
+        assert_eq!(window.grant(CLIENT, 16), 16);
+        assert_eq!(window.reduced_clients(), 0);
+    }
+
+    #[test]
+    fn test_reduction_expires() {
+        let window = Arc::new(AdaptiveWindow::new(&AdaptiveWindowConfig {
+            enabled: true,
+            retransmit_threshold: 1,
+            min_windowsize: 1,
+            memory_secs: 0,
+        }));
+        transfer(&window, 8, 1);
+        assert_eq!(window.grant(CLIENT, 8), 8);
+        assert_eq!(window.reduced_clients(), 0);
+    }
+}
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
 use tracing::{Level, event};
 
+/// One in this many successful transfers is audited (see `LoggingConfig`)
+static SUCCESS_SAMPLE_RATE: AtomicU32 = AtomicU32::new(1);
+
 /// Security audit event types for SIEM integration
 ///
 /// NIST 800-53 Controls:
@@ -84,6 +88,10 @@ pub enum AuditEvent {
         throughput_bps: u64,
         /// Average block transfer time in milliseconds
         avg_block_time_ms: f64,
+        /// Successful transfers this event stands for when success auditing
+        /// is sampled; absent when every success is logged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sample_rate: Option<u32>,
     },
 
     /// File transfer failed
@@ -139,6 +147,10 @@ pub enum AuditEvent {
         /// Average time per block in milliseconds
         avg_block_time_ms: f64,
         file_created: bool,
+        /// Successful transfers this event stands for when success auditing
+        /// is sampled; absent when every success is logged
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sample_rate: Option<u32>,
     },
 
     /// Write operation failed
@@ -217,6 +229,16 @@ pub enum AuditEvent {
         remaining_clients: usize,
     },
 
+    /// Master role passed to another client of a multicast session
+    MulticastMasterPromoted {
+        #[serde(flatten)]
+        common: CommonFields,
+        session_id: String,
+        client_addr: String,
+        previous_master: String,
+        total_clients: usize,
+    },
+
     /// Multicast session completed
     MulticastSessionCompleted {
         #[serde(flatten)]
@@ -363,6 +385,7 @@ impl AuditEvent {
             | AuditEvent::WriteCompleted { common, .. }
             | AuditEvent::MulticastSessionCreated { common, .. }
             | AuditEvent::MulticastClientJoined { common, .. }
+            | AuditEvent::MulticastMasterPromoted { common, .. }
             | AuditEvent::MulticastSessionCompleted { common, .. }
             | AuditEvent::ConfigurationLoaded { common, .. } => common.severity.clone(),
 
@@ -394,6 +417,40 @@ impl AuditEvent {
 pub struct AuditLogger;
 
 impl AuditLogger {
+    /// Audit only one in `rate` successful transfers from now on
+    ///
+    /// Applied at startup and on every configuration reload; 0 is treated as 1.
+    pub fn set_success_sample_rate(rate: u32) {
+        SUCCESS_SAMPLE_RATE.store(rate.max(1), Ordering::Relaxed);
+    }
+
+    /// Whether routine success events for the transfer with `client_addr` are logged
+    ///
+    /// The decision is a hash of the client address and port, so every
+    /// event of one transfer gets the same answer while transfers from
+    /// successive client ports spread evenly across the sample.
+    ///
+    /// NIST 800-53: AU-12 (Audit Generation), AU-4 (Audit Log Storage Capacity)
+    pub fn success_sampled(client_addr: SocketAddr) -> bool {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let rate = SUCCESS_SAMPLE_RATE.load(Ordering::Relaxed);
+        if rate <= 1 {
+            return true;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        client_addr.hash(&mut hasher);
+        hasher.finish().is_multiple_of(u64::from(rate))
+    }
+
+    /// Current success sample rate, for events that record it
+    fn success_sample_rate() -> Option<u32> {
+        let rate = SUCCESS_SAMPLE_RATE.load(Ordering::Relaxed);
+        (rate > 1).then_some(rate)
+    }
+
     /// Log server startup
     pub fn server_started(bind_addr: &str, root_dir: &str, multicast_enabled: bool) {
         AuditEvent::ServerStarted {
@@ -434,6 +491,10 @@ impl AuditLogger {
         options: serde_json::Value,
         correlation_id: &str,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::ReadRequest {
             common: CommonFields::with_correlation("info", correlation_id.to_string()),
             client_addr: client_addr.to_string(),
@@ -451,6 +512,10 @@ impl AuditLogger {
         mode: &str,
         options: serde_json::Value,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::ReadRequest {
             common: CommonFields::new("info"),
             client_addr: client_addr.to_string(),
@@ -481,6 +546,10 @@ impl AuditLogger {
         block_size: usize,
         correlation_id: &str,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::TransferStarted {
             common: CommonFields::with_correlation("info", correlation_id.to_string()),
             client_addr: client_addr.to_string(),
@@ -500,6 +569,10 @@ impl AuditLogger {
         mode: &str,
         block_size: usize,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::TransferStarted {
             common: CommonFields::new("info"),
             client_addr: client_addr.to_string(),
@@ -520,6 +593,10 @@ impl AuditLogger {
         duration_ms: u64,
         correlation_id: &str,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         // Calculate performance metrics
         let throughput_bps = if duration_ms > 0 {
             (bytes_transferred * 1000) / duration_ms
@@ -542,6 +619,7 @@ impl AuditLogger {
             duration_ms,
             throughput_bps,
             avg_block_time_ms,
+            sample_rate: Self::success_sample_rate(),
         }
         .log();
     }
@@ -554,6 +632,10 @@ impl AuditLogger {
         blocks_sent: u16,
         duration_ms: u64,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         // Calculate performance metrics
         let throughput_bps = if duration_ms > 0 {
             (bytes_transferred * 1000) / duration_ms
@@ -576,6 +658,7 @@ impl AuditLogger {
             duration_ms,
             throughput_bps,
             avg_block_time_ms,
+            sample_rate: Self::success_sample_rate(),
         }
         .log();
     }
@@ -600,6 +683,10 @@ impl AuditLogger {
         mode: &str,
         options: serde_json::Value,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::WriteRequest {
             common: CommonFields::new("info"),
             client_addr: client_addr.to_string(),
@@ -623,6 +710,10 @@ impl AuditLogger {
 
     /// Log write started
     pub fn write_started(client_addr: SocketAddr, filename: &str, mode: &str, block_size: usize) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         AuditEvent::WriteStarted {
             common: CommonFields::new("info"),
             client_addr: client_addr.to_string(),
@@ -642,6 +733,10 @@ impl AuditLogger {
         duration_ms: u64,
         file_created: bool,
     ) {
+        if !Self::success_sampled(client_addr) {
+            return;
+        }
+
         let throughput_bps = if duration_ms > 0 {
             (bytes_received * 1000) / duration_ms
         } else {
@@ -664,6 +759,7 @@ impl AuditLogger {
             throughput_bps,
             avg_block_time_ms,
             file_created,
+            sample_rate: Self::success_sample_rate(),
         }
         .log();
     }
@@ -789,6 +885,44 @@ impl AuditLogger {
         .log();
     }
 
+    /// Log multicast master promotion
+    pub fn multicast_master_promoted(
+        session_id: &str,
+        client_addr: SocketAddr,
+        previous_master: SocketAddr,
+        total_clients: usize,
+    ) {
+        AuditEvent::MulticastMasterPromoted {
+            common: CommonFields::new("info"),
+            session_id: session_id.to_string(),
+            client_addr: client_addr.to_string(),
+            previous_master: previous_master.to_string(),
+            total_clients,
+        }
+        .log();
+    }
+
+    /// Log multicast session completed
+    pub fn multicast_session_completed(
+        session_id: &str,
+        total_blocks: u16,
+        total_clients: usize,
+        duration_ms: u64,
+        bytes_transferred: u64,
+        retransmission_count: usize,
+    ) {
+        AuditEvent::MulticastSessionCompleted {
+            common: CommonFields::new("info"),
+            session_id: session_id.to_string(),
+            total_blocks,
+            total_clients,
+            duration_ms,
+            bytes_transferred,
+            retransmission_count,
+        }
+        .log();
+    }
+
     /// Log symlink access denied
     pub fn symlink_access_denied(client_addr: SocketAddr, requested_path: &str) {
         AuditEvent::SymlinkAccessDenied {
@@ -818,3 +952,62 @@ impl AuditLogger {
         .log();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use tracing_subscriber::Layer;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+
+    /// Counts emitted info and error events
+    #[derive(Clone, Default)]
+    struct LevelCounts {
+        info: Arc<AtomicUsize>,
+        error: Arc<AtomicUsize>,
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for LevelCounts {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            match *event.metadata().level() {
+                Level::INFO => self.info.fetch_add(1, Ordering::Relaxed),
+                Level::ERROR => self.error.fetch_add(1, Ordering::Relaxed),
+                _ => 0,
+            };
+        }
+    }
+
+    #[test]
+    fn test_success_sampling_keeps_every_error() {
+        const RATE: u32 = 10;
+        const TRANSFERS: usize = 5000;
+
+        let counts = LevelCounts::default();
+        let subscriber = tracing_subscriber::registry().with(counts.clone());
+        AuditLogger::set_success_sample_rate(RATE);
+        tracing::subscriber::with_default(subscriber, || {
+            for n in 0..TRANSFERS {
+                let client = SocketAddr::from(([10, 0, (n / 250) as u8, (n % 250) as u8], 49152));
+                AuditLogger::transfer_started(client, "boot.wim", 4096, "octet", 1468);
+                AuditLogger::transfer_completed(client, "boot.wim", 4096, 3, 12);
+                AuditLogger::transfer_failed(client, "bootmgr.efi", "Timeout", 1);
+            }
+        });
+        AuditLogger::set_success_sample_rate(1);
+
+        // Start and completion of a transfer are sampled together
+        let info = counts.info.load(Ordering::Relaxed);
+        assert_eq!(info % 2, 0);
+        let sampled = info / 2;
+        let expected = TRANSFERS / RATE as usize;
+        assert!(
+            sampled > expected * 7 / 10 && sampled < expected * 13 / 10,
+            "{} of {} successful transfers logged, expected about {}",
+            sampled,
+            TRANSFERS,
+            expected
+        );
+        assert_eq!(counts.error.load(Ordering::Relaxed), TRANSFERS);
+    }
+}
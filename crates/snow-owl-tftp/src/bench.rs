@@ -0,0 +1,570 @@
+//! Load generator for capacity validation of a TFTP server
+//!
+//! Before a new deployment server goes into service, operators want to know
+//! whether it sustains the expected number of concurrent PXE clients. The
+//! benchmark runs `clients` loops that each download the same file over and
+//! over (RRQ with blksize/windowsize options), starting the loops evenly
+//! across a ramp-up period so the server is not hit by a thundering herd.
+//!
+//! Only transfers that start after the ramp-up and finish inside the
+//! following steady-state window are counted, so figures are not skewed by
+//! warm-up or by transfers cut off at the end. Each client buffers one copy
+//! of the file in memory while it downloads, so benchmark with a file of
+//! representative size (a boot loader or `boot.sdi`), not a full image.
+//!
+//! NIST 800-53 Controls:
+//! - CP-2(2): Capacity Planning (measured throughput per server)
+//! - SC-5: Denial of Service Protection (bounded retries per transfer)
+
+use crate::Opcode;
+use crate::receive::{ReceiveError, ReceiveParams, ack_packet, receive_windowed};
+use crate::report::percentile;
+use bytes::{BufMut, BytesMut};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+/// Version of the [`BenchReport`] JSON/CSV layout
+///
+/// Bumped whenever a field is renamed or removed, so trend tooling can tell
+/// old result files apart.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Pause after a failed transfer before the client retries
+///
+/// Keeps a misconfigured run (wrong file name) from flooding the server with
+/// requests that fail immediately.
+const FAILURE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Benchmark parameters
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// Server to benchmark
+    pub server: SocketAddr,
+    /// File requested by every client
+    pub file: String,
+    /// Number of concurrent client loops
+    pub clients: usize,
+    /// Requested block size (RFC 2348)
+    pub block_size: usize,
+    /// Requested window size (RFC 7440)
+    pub windowsize: usize,
+    /// Period over which the client loops are started
+    pub ramp_up: Duration,
+    /// Steady-state window in which transfers are measured
+    pub duration: Duration,
+    /// Time to wait for a reply before retransmitting
+    pub timeout: Duration,
+    /// Retransmissions allowed without progress before a transfer fails
+    pub max_retries: u32,
+}
+
+/// One transfer attempted by a client loop
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferSample {
+    /// Start of the transfer, relative to the start of the benchmark
+    pub started: Duration,
+    /// Time from RRQ to the final ACK
+    pub elapsed: Duration,
+    /// Bytes received (0 for failed transfers)
+    pub bytes: u64,
+    /// RRQs and ACKs re-sent to recover from loss
+    pub retransmits: u32,
+    /// Error category for failed transfers
+    pub error: Option<String>,
+}
+
+/// Outcome of a single download
+#[derive(Debug)]
+pub struct Download {
+    pub bytes: u64,
+    pub retransmits: u32,
+}
+
+/// Why a single download failed
+#[derive(Debug)]
+pub struct DownloadError {
+    /// Short, stable category used as the key of [`BenchReport::errors`]
+    pub kind: String,
+    pub message: String,
+    /// Retransmissions made before the failure
+    pub retransmits: u32,
+}
+
+impl DownloadError {
+    fn new(kind: impl Into<String>, message: impl Into<String>, retransmits: u32) -> Self {
+        Self {
+            kind: kind.into(),
+            message: message.into(),
+            retransmits,
+        }
+    }
+}
+
+impl std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// Build an RRQ for `file` in octet mode with blksize and windowsize options
+fn rrq_packet(file: &str, block_size: usize, windowsize: usize) -> BytesMut {
+    let mut packet = BytesMut::new();
+    packet.put_u16(Opcode::Rrq as u16);
+    for field in [
+        file,
+        "octet",
+        "blksize",
+        &block_size.to_string(),
+        "windowsize",
+        &windowsize.to_string(),
+    ] {
+        packet.put_slice(field.as_bytes());
+        packet.put_u8(0);
+    }
+    packet
+}
+
+/// Look up a numeric option in an OACK body (RFC 2347)
+fn oack_option(body: &[u8], name: &str) -> Option<usize> {
+    let mut fields = body.split(|&b| b == 0);
+    while let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+        if String::from_utf8_lossy(key).eq_ignore_ascii_case(name) {
+            return String::from_utf8_lossy(value).parse().ok();
+        }
+    }
+    None
+}
+
+/// Error category for a TFTP ERROR packet
+fn server_error_kind(code: u16) -> String {
+    format!("server_error_{}", code)
+}
+
+/// Download `config.file` once from `config.server`
+pub async fn download(config: &BenchConfig) -> std::result::Result<Download, DownloadError> {
+    let bind_addr = if config.server.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let socket = UdpSocket::bind(bind_addr)
+        .await
+        .map_err(|e| DownloadError::new("io", e.to_string(), 0))?;
+
+    let rrq = rrq_packet(&config.file, config.block_size, config.windowsize);
+    let mut buf = vec![0u8; crate::MAX_PACKET_SIZE];
+    let mut retransmits = 0;
+
+    // RFC 2347: the OACK comes from the server's transfer TID, not port 69
+    let (size, transfer_addr) = loop {
+        socket
+            .send_to(&rrq, config.server)
+            .await
+            .map_err(|e| DownloadError::new("io", e.to_string(), retransmits))?;
+        match tokio::time::timeout(config.timeout, socket.recv_from(&mut buf)).await {
+            Ok(Ok(reply)) => break reply,
+            Ok(Err(e)) => return Err(DownloadError::new("io", e.to_string(), retransmits)),
+            Err(_) if retransmits < config.max_retries => retransmits += 1,
+            Err(_) => {
+                return Err(DownloadError::new(
+                    "timeout",
+                    "no reply to read request",
+                    retransmits,
+                ));
+            }
+        }
+    };
+
+    if size < 2 {
+        return Err(DownloadError::new("protocol", "runt reply", retransmits));
+    }
+    let opcode = u16::from_be_bytes([buf[0], buf[1]]);
+    if opcode == Opcode::Error as u16 && size >= 4 {
+        let code = u16::from_be_bytes([buf[2], buf[3]]);
+        let message = String::from_utf8_lossy(&buf[4..size])
+            .trim_end_matches('\0')
+            .to_string();
+        return Err(DownloadError::new(
+            server_error_kind(code),
+            message,
+            retransmits,
+        ));
+    }
+    if opcode != Opcode::Oack as u16 {
+        return Err(DownloadError::new(
+            "options_rejected",
+            format!("expected OACK, got opcode {}", opcode),
+            retransmits,
+        ));
+    }
+
+    // The server may lower either option; use what it agreed to
+    let body = &buf[2..size];
+    let block_size = oack_option(body, "blksize").unwrap_or(crate::DEFAULT_BLOCK_SIZE);
+    let windowsize = oack_option(body, "windowsize").unwrap_or(1);
+
+    socket
+        .connect(transfer_addr)
+        .await
+        .map_err(|e| DownloadError::new("io", e.to_string(), retransmits))?;
+
+    let params = ReceiveParams {
+        block_size,
+        windowsize,
+        timeout: config.timeout,
+        max_retries: config.max_retries,
+        max_file_size: 0,
+        size_hint: None,
+        resume_after: 0,
+    };
+    match receive_windowed(&socket, &ack_packet(0), params).await {
+        Ok(received) => Ok(Download {
+            bytes: received.data.len() as u64,
+            retransmits: retransmits + received.reacks,
+        }),
+        Err(ReceiveError::ClientError { code, message }) => Err(DownloadError::new(
+            server_error_kind(code),
+            message,
+            retransmits,
+        )),
+        Err(ReceiveError::Timeout { expected_block }) => Err(DownloadError::new(
+            "timeout",
+            format!("no progress waiting for block {}", expected_block),
+            retransmits,
+        )),
+        Err(ReceiveError::FileTooLarge { size, .. }) => Err(DownloadError::new(
+            "protocol",
+            format!("file too large ({} bytes)", size),
+            retransmits,
+        )),
+        Err(ReceiveError::Io(e)) => Err(DownloadError::new("io", e.to_string(), retransmits)),
+    }
+}
+
+/// Run the benchmark: ramp up the client loops, then measure for `duration`
+pub async fn run_bench(config: &BenchConfig) -> BenchReport {
+    let start = Instant::now();
+    let window_end = start + config.ramp_up + config.duration;
+    let clients = config.clients.max(1);
+
+    let mut tasks = JoinSet::new();
+    for client in 0..clients {
+        let config = config.clone();
+        let delay = config.ramp_up.mul_f64(client as f64 / clients as f64);
+        tasks.spawn(async move {
+            tokio::time::sleep_until(start + delay).await;
+
+            let mut samples = Vec::new();
+            while Instant::now() < window_end {
+                let started = start.elapsed();
+                // Transfers still running at the end of the window are dropped
+                let Ok(result) = tokio::time::timeout_at(window_end, download(&config)).await
+                else {
+                    break;
+                };
+                let elapsed = start.elapsed() - started;
+                match result {
+                    Ok(done) => samples.push(TransferSample {
+                        started,
+                        elapsed,
+                        bytes: done.bytes,
+                        retransmits: done.retransmits,
+                        error: None,
+                    }),
+                    Err(e) => {
+                        tracing::debug!("Benchmark client {} transfer failed: {}", client, e);
+                        samples.push(TransferSample {
+                            started,
+                            elapsed,
+                            bytes: 0,
+                            retransmits: e.retransmits,
+                            error: Some(e.kind),
+                        });
+                        tokio::time::sleep(FAILURE_BACKOFF).await;
+                    }
+                }
+            }
+            samples
+        });
+    }
+
+    let mut samples = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(client_samples) = result {
+            samples.extend(client_samples);
+        }
+    }
+    BenchReport::from_samples(config, &samples)
+}
+
+/// Benchmark results, stable for JSON/CSV trend tracking
+///
+/// Fields are only ever added; renaming or removing one bumps
+/// [`REPORT_SCHEMA_VERSION`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub schema_version: u32,
+    pub server: String,
+    pub file: String,
+    pub clients: usize,
+    pub block_size: usize,
+    pub windowsize: usize,
+    pub ramp_up_secs: f64,
+    pub duration_secs: f64,
+    /// Transfers completed inside the measurement window
+    pub transfers_completed: u64,
+    /// Transfers failed inside the measurement window
+    pub transfers_failed: u64,
+    /// Bytes moved by completed transfers
+    pub bytes_transferred: u64,
+    /// Bytes per second of completed transfers over the measurement window
+    pub goodput_bps: f64,
+    pub p50_transfer_ms: Option<u64>,
+    pub p95_transfer_ms: Option<u64>,
+    pub p99_transfer_ms: Option<u64>,
+    pub max_transfer_ms: Option<u64>,
+    /// RRQs and ACKs re-sent across all measured transfers
+    pub retransmits: u64,
+    /// Failed transfers by error category
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl BenchReport {
+    /// CSV header matching [`BenchReport::csv_row`]
+    pub const CSV_HEADER: &'static str = "schema_version,server,file,clients,block_size,\
+        windowsize,ramp_up_secs,duration_secs,transfers_completed,transfers_failed,\
+        bytes_transferred,goodput_bps,p50_transfer_ms,p95_transfer_ms,p99_transfer_ms,\
+        max_transfer_ms,retransmits,errors";
+
+    /// Aggregate the samples that fall inside the measurement window
+    ///
+    /// A sample counts if it started after the ramp-up and ended before the
+    /// window closed.
+    pub fn from_samples(config: &BenchConfig, samples: &[TransferSample]) -> Self {
+        let window_start = config.ramp_up;
+        let window_end = config.ramp_up + config.duration;
+
+        let mut transfers_completed = 0;
+        let mut transfers_failed = 0;
+        let mut bytes_transferred = 0u64;
+        let mut retransmits = 0u64;
+        let mut errors = BTreeMap::new();
+        let mut durations = Vec::new();
+
+        for sample in samples.iter().filter(|sample| {
+            sample.started >= window_start && sample.started + sample.elapsed <= window_end
+        }) {
+            retransmits += u64::from(sample.retransmits);
+            match &sample.error {
+                None => {
+                    transfers_completed += 1;
+                    bytes_transferred = bytes_transferred.saturating_add(sample.bytes);
+                    durations.push(sample.elapsed.as_millis() as u64);
+                }
+                Some(kind) => {
+                    transfers_failed += 1;
+                    *errors.entry(kind.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        durations.sort_unstable();
+
+        let duration_secs = config.duration.as_secs_f64();
+        let goodput_bps = if duration_secs > 0.0 {
+            bytes_transferred as f64 / duration_secs
+        } else {
+            0.0
+        };
+
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            server: config.server.to_string(),
+            file: config.file.clone(),
+            clients: config.clients,
+            block_size: config.block_size,
+            windowsize: config.windowsize,
+            ramp_up_secs: config.ramp_up.as_secs_f64(),
+            duration_secs,
+            transfers_completed,
+            transfers_failed,
+            bytes_transferred,
+            goodput_bps,
+            p50_transfer_ms: percentile(&durations, 50),
+            p95_transfer_ms: percentile(&durations, 95),
+            p99_transfer_ms: percentile(&durations, 99),
+            max_transfer_ms: durations.last().copied(),
+            retransmits,
+            errors,
+        }
+    }
+
+    /// One CSV line matching [`BenchReport::CSV_HEADER`]
+    ///
+    /// Errors are written as `kind=count` pairs separated by `;`.
+    pub fn csv_row(&self) -> String {
+        let ms = |value: Option<u64>| value.map_or(String::new(), |ms| ms.to_string());
+        let errors = self
+            .errors
+            .iter()
+            .map(|(kind, count)| format!("{}={}", kind, count))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{:.0},{},{},{},{},{},{}",
+            self.schema_version,
+            self.server,
+            csv_field(&self.file),
+            self.clients,
+            self.block_size,
+            self.windowsize,
+            self.ramp_up_secs,
+            self.duration_secs,
+            self.transfers_completed,
+            self.transfers_failed,
+            self.bytes_transferred,
+            self.goodput_bps,
+            ms(self.p50_transfer_ms),
+            ms(self.p95_transfer_ms),
+            ms(self.p99_transfer_ms),
+            ms(self.max_transfer_ms),
+            self.retransmits,
+            errors
+        )
+    }
+}
+
+/// Quote a CSV field if it contains a separator or quote
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |value: Option<u64>| value.map_or("-".to_string(), |ms| format!("{} ms", ms));
+
+        writeln!(f, "TFTP benchmark: {} {}", self.server, self.file)?;
+        writeln!(
+            f,
+            "  Clients:       {} (blksize {}, windowsize {})",
+            self.clients, self.block_size, self.windowsize
+        )?;
+        writeln!(
+            f,
+            "  Window:        {:.1}s after {:.1}s ramp-up",
+            self.duration_secs, self.ramp_up_secs
+        )?;
+        writeln!(f, "  Completed:     {}", self.transfers_completed)?;
+        writeln!(f, "  Failed:        {}", self.transfers_failed)?;
+        writeln!(
+            f,
+            "  Goodput:       {:.2} MB/s",
+            self.goodput_bps / 1_048_576.0
+        )?;
+        writeln!(f, "  p50 transfer:  {}", ms(self.p50_transfer_ms))?;
+        writeln!(f, "  p95 transfer:  {}", ms(self.p95_transfer_ms))?;
+        writeln!(f, "  p99 transfer:  {}", ms(self.p99_transfer_ms))?;
+        writeln!(f, "  Max transfer:  {}", ms(self.max_transfer_ms))?;
+        write!(f, "  Retransmits:   {}", self.retransmits)?;
+        for (kind, count) in &self.errors {
+            write!(f, "\n  Error {}: {}", kind, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BenchConfig {
+        BenchConfig {
+            server: "127.0.0.1:69".parse().unwrap(),
+            file: "boot.sdi".to_string(),
+            clients: 4,
+            block_size: 1428,
+            windowsize: 8,
+            ramp_up: Duration::from_secs(2),
+            duration: Duration::from_secs(10),
+            timeout: Duration::from_secs(1),
+            max_retries: 3,
+        }
+    }
+
+    fn sample(started_ms: u64, elapsed_ms: u64, error: Option<&str>) -> TransferSample {
+        TransferSample {
+            started: Duration::from_millis(started_ms),
+            elapsed: Duration::from_millis(elapsed_ms),
+            bytes: if error.is_none() { 1_000_000 } else { 0 },
+            retransmits: 1,
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_only_steady_state_samples_count() {
+        let samples = [
+            // Started during ramp-up
+            sample(1_000, 500, None),
+            // Measured
+            sample(2_000, 100, None),
+            sample(3_000, 300, None),
+            sample(4_000, 200, Some("timeout")),
+            sample(5_000, 200, Some("server_error_1")),
+            sample(6_000, 200, Some("timeout")),
+            // Ends after the window closes
+            sample(11_900, 500, None),
+        ];
+
+        let report = BenchReport::from_samples(&config(), &samples);
+
+        assert_eq!(report.transfers_completed, 2);
+        assert_eq!(report.transfers_failed, 3);
+        assert_eq!(report.bytes_transferred, 2_000_000);
+        assert!((report.goodput_bps - 200_000.0).abs() < f64::EPSILON);
+        assert_eq!(report.p50_transfer_ms, Some(100));
+        assert_eq!(report.max_transfer_ms, Some(300));
+        assert_eq!(report.retransmits, 5);
+        assert_eq!(report.errors.get("timeout"), Some(&2));
+        assert_eq!(report.errors.get("server_error_1"), Some(&1));
+    }
+
+    #[test]
+    fn test_report_schema_is_stable() {
+        let report = BenchReport::from_samples(&config(), &[sample(2_000, 100, None)]);
+        let json = serde_json::to_value(&report).unwrap();
+
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let header: Vec<&str> = BenchReport::CSV_HEADER.split(',').collect();
+        let mut sorted_header = header.clone();
+        sorted_header.sort_unstable();
+        assert_eq!(keys, sorted_header);
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+
+        let row = report.csv_row();
+        assert_eq!(row.split(',').count(), header.len());
+        assert!(row.starts_with("1,127.0.0.1:69,boot.sdi,4,1428,8,"));
+    }
+
+    #[test]
+    fn test_oack_option_lookup() {
+        let body = b"blksize\x001428\x00WINDOWSIZE\x004\x00";
+        assert_eq!(oack_option(body, "blksize"), Some(1428));
+        assert_eq!(oack_option(body, "windowsize"), Some(4));
+        assert_eq!(oack_option(body, "tsize"), None);
+    }
+}
@@ -1,24 +1,46 @@
 // Snow-Owl TFTP Server Binary
 #![allow(dead_code)]
 
+use snow_owl_tftp::adaptive_window::{AdaptiveWindow, WindowReport};
 use snow_owl_tftp::audit::AuditLogger;
+use snow_owl_tftp::bench::{BenchConfig, BenchReport, download, run_bench};
 use snow_owl_tftp::buffer_pool::BufferPool;
 use snow_owl_tftp::config::{
-    self, default_multicast_addr_for_version, load_config, validate_config, write_config,
-    LogFormat, MulticastConfig, MulticastIpVersion, SocketConfig, TftpConfig, WriteConfig,
+    self, default_multicast_addr_for_version, is_mode_allowed, is_read_allowed, load_config, validate_config,
+    write_config, AllowedTransferMode, LogFormat, MulticastConfig, MulticastIpVersion, RetryPolicy, SocketConfig, TftpConfig, TsizeMismatchPolicy, WriteConfig,
 };
+use snow_owl_tftp::deployment_tracking::{DEPLOYMENT_EVENT_QUEUE, DeploymentTracker};
+use snow_owl_tftp::directory_index::build_directory_index;
+use snow_owl_tftp::events::{TransferDirection, TransferEvent, TransferEvents, TransferWatch};
 use snow_owl_tftp::multicast::MulticastTftpServer;
-use snow_owl_tftp::worker_pool::WorkerPool;
-use snow_owl_tftp::{Result, TftpError, TransferMode, TftpOptions, MAX_BLOCK_SIZE, MAX_PACKET_SIZE, MAX_RETRIES};
+use snow_owl_tftp::priority::{TransferScheduler, TransferTicket};
+use snow_owl_tftp::receive::{
+    DeclaredSizeError, ReceiveError, ReceiveParams, ack_packet, check_declared_size, preallocate,
+    receive_windowed,
+};
+use snow_owl_tftp::report::SlaReport;
+use snow_owl_tftp::status;
+use snow_owl_tftp::temp_files::{self, TempFileRegistry};
+use snow_owl_tftp::throttle::TokenBucket;
+use snow_owl_tftp::virtual_path::{DatabaseResolver, VirtualPathError, VirtualRoots};
+use snow_owl_tftp::worker_pool::{RequestHandler, WorkerPool, drain_transfers};
+use snow_owl_tftp::{
+    MAX_BLOCK_NUMBER, MAX_BLOCK_SIZE, MAX_PACKET_SIZE, MAX_RETRIES, OptionList, Result, TftpError,
+    TftpOptions, TransferMode,
+};
 
 use bytes::{Buf, BufMut, BytesMut};
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use snow_owl_core::ShutdownCoordinator;
+use snow_owl_core::config_schema::{check_config_file, config_schema};
+use snow_owl_db::Database;
 use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 
 // Phase 2: Batch operations and zero-copy transfers
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
@@ -28,8 +50,12 @@ use std::os::unix::io::AsRawFd;
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, reload};
 
 // RFC 1350 - The TFTP Protocol (Revision 2)
 //
@@ -336,6 +362,27 @@ fn create_optimized_socket(bind_addr: SocketAddr, config: &SocketConfig) -> Resu
     Ok(tokio_socket)
 }
 
+/// Local address for a per-transfer socket replying to `client_addr`
+///
+/// Binds to `transfer_ip` when it can reach the client's address family, so
+/// replies leave from the address the request was sent to; otherwise to the
+/// wildcard address of the client's family (dual-stack support).
+fn transfer_bind_addr(transfer_ip: Option<IpAddr>, client_addr: SocketAddr) -> SocketAddr {
+    let ip = match (transfer_ip, client_addr.ip()) {
+        (Some(IpAddr::V4(ip)), IpAddr::V4(_)) => IpAddr::V4(ip),
+        // A dual-stack listener reports IPv4 clients as v4-mapped addresses
+        (Some(IpAddr::V4(ip)), IpAddr::V6(client)) if client.to_ipv4_mapped().is_some() => {
+            IpAddr::V6(ip.to_ipv6_mapped())
+        }
+        (Some(IpAddr::V6(ip)), IpAddr::V6(client)) if client.to_ipv4_mapped().is_none() => {
+            IpAddr::V6(ip)
+        }
+        (_, IpAddr::V4(_)) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        (_, IpAddr::V6(_)) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+    };
+    SocketAddr::new(ip, 0)
+}
+
 /// Creates a per-transfer socket with the correct address family for IPv4/IPv6 support
 ///
 /// This is a lightweight version of create_optimized_socket for ephemeral
@@ -350,6 +397,15 @@ fn create_transfer_socket(bind_addr: SocketAddr) -> Result<UdpSocket> {
     let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))
         .map_err(|e| TftpError::Tftp(format!("Failed to create transfer socket: {}", e)))?;
 
+    // A dual-stack listener on [::] reports IPv4 clients as v4-mapped
+    // addresses (::ffff:a.b.c.d); the reply socket must accept them even on
+    // hosts where net.ipv6.bindv6only defaults to 1.
+    if domain == Domain::IPV6 {
+        socket
+            .set_only_v6(false)
+            .map_err(|e| TftpError::Tftp(format!("Failed to clear IPV6_V6ONLY: {}", e)))?;
+    }
+
     // Bind the socket
     socket
         .bind(&bind_addr.into())
@@ -371,6 +427,9 @@ fn create_transfer_socket(bind_addr: SocketAddr) -> Result<UdpSocket> {
 #[derive(Parser, Debug)]
 #[command(name = "snow-owl-tftp", about = "Standalone TFTP server")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the TOML configuration file
     #[arg(long, default_value = "/etc/snow-owl/tftp.toml")]
     config: PathBuf,
@@ -383,6 +442,26 @@ struct Cli {
     #[arg(long)]
     check_config: bool,
 
+    /// Print a transfer SLA report from the audit log and exit
+    #[arg(long)]
+    sla_report: bool,
+
+    /// Audit log to report on (defaults to logging.file)
+    #[arg(long, requires = "sla_report")]
+    audit_log: Option<PathBuf>,
+
+    /// Only report transfers at or after this RFC 3339 time
+    #[arg(long, requires = "sla_report")]
+    since: Option<DateTime<Utc>>,
+
+    /// Only report transfers before this RFC 3339 time
+    #[arg(long, requires = "sla_report")]
+    until: Option<DateTime<Utc>>,
+
+    /// Print the SLA report as JSON
+    #[arg(long, requires = "sla_report")]
+    json: bool,
+
     /// Create the root directory if it does not exist
     #[arg(long)]
     create_root_dir: bool,
@@ -422,6 +501,262 @@ struct Cli {
     /// Retransmission timeout in seconds
     #[arg(long)]
     retransmit_timeout_secs: Option<u64>,
+
+    /// Also reload the configuration file when it changes on disk
+    #[arg(long)]
+    watch_config: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Measure how many concurrent clients a server sustains, then exit
+    Bench(BenchArgs),
+    /// Export or check the configuration file schema
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the JSON Schema for the configuration file
+    Schema,
+    /// Check a configuration file against the schema without starting
+    Validate {
+        /// Configuration file to check (defaults to --config)
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Validate against this exported schema instead of the built-in one
+        #[arg(long, num_args = 0..=1, value_name = "SCHEMA_FILE")]
+        schema: Option<Option<PathBuf>>,
+    },
+}
+
+/// Run the `config` subcommands
+///
+/// NIST 800-53 CM-3: Configuration Change Control (checked before rollout)
+fn run_config_command(command: &ConfigCommand, config_path: &Path) -> Result<()> {
+    match command {
+        ConfigCommand::Schema => {
+            let schema = config_schema::<TftpConfig>();
+            let json = serde_json::to_string_pretty(&schema)
+                .map_err(|e| TftpError::Tftp(format!("Failed to serialize schema: {}", e)))?;
+            println!("{}", json);
+        }
+        ConfigCommand::Validate { file, schema } => {
+            let file = file.as_deref().unwrap_or(config_path);
+            let report = check_config_file::<TftpConfig>(file, schema.clone().flatten().as_deref())
+                .map_err(|e| TftpError::Tftp(e.to_string()))?;
+            for warning in &report.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            for error in &report.errors {
+                eprintln!("error: {}", error);
+            }
+            if !report.is_valid() {
+                return Err(TftpError::Tftp(format!(
+                    "{} failed validation with {} error(s)",
+                    file.display(),
+                    report.errors.len()
+                )));
+            }
+            println!("Config OK: {}", file.display());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+struct BenchArgs {
+    /// Server to benchmark
+    #[arg(long, required_unless_present = "self_test")]
+    server: Option<SocketAddr>,
+
+    /// File every client downloads
+    #[arg(long, default_value = "bench.bin")]
+    file: String,
+
+    /// Number of concurrent clients
+    #[arg(long, default_value_t = 10)]
+    clients: usize,
+
+    /// Requested block size (RFC 2348)
+    #[arg(long, default_value_t = 1428)]
+    blksize: usize,
+
+    /// Requested window size (RFC 7440)
+    #[arg(long, default_value_t = 4)]
+    windowsize: usize,
+
+    /// Seconds of steady-state measurement
+    #[arg(long, default_value_t = 30.0)]
+    duration: f64,
+
+    /// Seconds over which clients are started; not measured
+    #[arg(long, default_value_t = 5.0)]
+    ramp_up: f64,
+
+    /// Seconds to wait for a reply before retransmitting
+    #[arg(long, default_value_t = 1.0)]
+    timeout: f64,
+
+    /// Retransmissions without progress before a transfer fails
+    #[arg(long, default_value_t = 5)]
+    max_retries: u32,
+
+    /// Serve a generated file from a temporary directory and benchmark it
+    #[arg(long, conflicts_with = "server")]
+    self_test: bool,
+
+    /// Size in bytes of the file generated for --self-test
+    #[arg(long, default_value_t = 1_048_576, requires = "self_test")]
+    self_test_size: u64,
+
+    /// Write the report as JSON to this file
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Append the report as a CSV row to this file (header written once)
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Exit non-zero if goodput falls below this many bytes per second
+    #[arg(long)]
+    min_goodput: Option<f64>,
+}
+
+impl BenchArgs {
+    fn bench_config(&self, server: SocketAddr) -> Result<BenchConfig> {
+        let seconds = |name: &str, value: f64| {
+            std::time::Duration::try_from_secs_f64(value)
+                .map_err(|_| TftpError::Tftp(format!("--{} must be a non-negative number", name)))
+        };
+        if self.clients == 0 {
+            return Err(TftpError::Tftp("--clients must be at least 1".to_string()));
+        }
+        if !(8..=MAX_BLOCK_SIZE).contains(&self.blksize) {
+            return Err(TftpError::Tftp(format!(
+                "--blksize must be between 8 and {}",
+                MAX_BLOCK_SIZE
+            )));
+        }
+        if !(1..=65535).contains(&self.windowsize) {
+            return Err(TftpError::Tftp(
+                "--windowsize must be between 1 and 65535".to_string(),
+            ));
+        }
+
+        Ok(BenchConfig {
+            server,
+            file: self.file.clone(),
+            clients: self.clients,
+            block_size: self.blksize,
+            windowsize: self.windowsize,
+            ramp_up: seconds("ramp-up", self.ramp_up)?,
+            duration: seconds("duration", self.duration)?,
+            timeout: seconds("timeout", self.timeout)?,
+            max_retries: self.max_retries,
+        })
+    }
+}
+
+/// Run the `bench` subcommand
+///
+/// NIST 800-53 CP-2(2): Capacity planning for a new deployment server
+async fn run_bench_command(args: &BenchArgs) -> Result<()> {
+    let report = match args.server {
+        Some(server) => run_bench(&args.bench_config(server)?).await,
+        None => {
+            let config = args.bench_config(SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, 0)))?;
+            bench_self_test(config, args.self_test_size).await?
+        }
+    };
+    println!("{}", report);
+
+    if let Some(path) = &args.json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| TftpError::Tftp(format!("Failed to serialize report: {}", e)))?;
+        std::fs::write(path, json)?;
+    }
+    if let Some(path) = &args.csv {
+        use std::io::Write;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", BenchReport::CSV_HEADER)?;
+        }
+        writeln!(file, "{}", report.csv_row())?;
+    }
+
+    if let Some(min_goodput) = args.min_goodput
+        && report.goodput_bps < min_goodput
+    {
+        return Err(TftpError::Tftp(format!(
+            "Goodput {:.0} B/s is below the required {:.0} B/s",
+            report.goodput_bps, min_goodput
+        )));
+    }
+    Ok(())
+}
+
+/// Benchmark a server started on loopback over a temporary root
+///
+/// Sanity-checks a host before pointing real clients at it: the numbers show
+/// what the machine itself can serve with no network in between. The
+/// `server` in `config` is replaced with the local server's address.
+async fn bench_self_test(mut config: BenchConfig, file_size: u64) -> Result<BenchReport> {
+    let root_dir =
+        std::env::temp_dir().join(format!("snow_owl_tftp_bench_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root_dir)?;
+    let content: Vec<u8> = (0..file_size).map(|i| (i % 251) as u8).collect();
+    std::fs::write(root_dir.join(&config.file), content)?;
+
+    let port = std::net::UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0))?
+        .local_addr()?
+        .port();
+    let mut tftp_config = TftpConfig {
+        root_dir: root_dir.clone(),
+        bind_addr: SocketAddr::new(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), port),
+        ..TftpConfig::default()
+    };
+    tftp_config.logging.audit_enabled = false;
+    tftp_config.logging.file = None;
+    config.server = tftp_config.bind_addr;
+
+    let server = TftpServer::new(
+        root_dir.clone(),
+        tftp_config.bind_addr,
+        tftp_config.max_file_size_bytes,
+        tftp_config.write_config.clone(),
+        false,
+        Arc::new(tftp_config),
+    );
+    let server_task = tokio::spawn(async move { server.run().await });
+
+    // Wait for the listener before starting the clock
+    let mut ready = Err(TftpError::Tftp("Self-test server did not start".to_string()));
+    for _ in 0..20 {
+        match download(&config).await {
+            Ok(_) => {
+                ready = Ok(());
+                break;
+            }
+            Err(e) => {
+                ready = Err(TftpError::Tftp(format!("Self-test server not ready: {}", e)));
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        }
+    }
+
+    let result = match ready {
+        Ok(()) => Ok(run_bench(&config).await),
+        Err(e) => Err(e),
+    };
+    server_task.abort();
+    let _ = std::fs::remove_dir_all(&root_dir);
+    result
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -465,6 +800,361 @@ enum TftpErrorCode {
     OptionNegotiation = 8, // RFC 2347 - Option negotiation failure
 }
 
+/// Settings that can change on reload without a restart
+///
+/// Each request takes a snapshot when it arrives, so transfers already in
+/// progress finish under the settings they started with. `root_dir` is
+/// carried along but a reload never changes it.
+#[derive(Debug, Clone)]
+struct ReloadableSettings {
+    root_dir: PathBuf,
+    max_file_size_bytes: u64,
+    write_config: WriteConfig,
+    read_allowed_patterns: Vec<String>,
+    allowed_transfer_modes: Vec<AllowedTransferMode>,
+    audit_enabled: bool,
+    max_bytes_per_sec: Option<u64>,
+}
+
+impl ReloadableSettings {
+    fn from_config(config: &TftpConfig) -> Self {
+        Self {
+            root_dir: config.root_dir.clone(),
+            max_file_size_bytes: config.max_file_size_bytes,
+            write_config: config.write_config.clone(),
+            read_allowed_patterns: config.read_allowed_patterns.clone(),
+            allowed_transfer_modes: config.allowed_transfer_modes.clone(),
+            audit_enabled: config.logging.audit_enabled,
+            max_bytes_per_sec: config.max_bytes_per_sec,
+        }
+    }
+}
+
+/// Handle for replacing the log filter installed at startup
+type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// How often `--watch-config` checks the configuration file for changes
+const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+type SharedSettings = Arc<RwLock<Arc<ReloadableSettings>>>;
+
+/// Handle for swapping in reloaded settings while the server runs
+///
+/// NIST 800-53 Controls:
+/// - CM-3: Configuration Change Control (validated, atomic changes)
+/// - CM-6: Configuration Settings (apply settings without service interruption)
+#[derive(Clone)]
+pub struct ConfigReloader {
+    bind_addr: SocketAddr,
+    settings: SharedSettings,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl ConfigReloader {
+    /// Also apply `logging.level` on reload through `handle`
+    fn with_log_filter(mut self, handle: LogFilterHandle) -> Self {
+        self.log_filter = Some(handle);
+        self
+    }
+
+    /// Validate `config` and atomically replace the reloadable settings
+    ///
+    /// The listening socket is already bound and running transfers hold
+    /// paths under the root, so a changed `bind_addr` or `root_dir` rejects
+    /// the whole reload; those need a restart.
+    pub fn apply(&self, config: &TftpConfig) -> Result<()> {
+        if config.bind_addr != self.bind_addr {
+            warn!(
+                "Ignoring config reload: bind_addr change ({} -> {}) requires a restart",
+                self.bind_addr, config.bind_addr
+            );
+            return Err(TftpError::Tftp(format!(
+                "bind_addr change ({} -> {}) requires a restart",
+                self.bind_addr, config.bind_addr
+            )));
+        }
+        let root_dir = self
+            .settings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .root_dir
+            .clone();
+        if config.root_dir != root_dir {
+            warn!(
+                "Ignoring config reload: root_dir change ({} -> {}) requires a restart",
+                root_dir.display(),
+                config.root_dir.display()
+            );
+            return Err(TftpError::Tftp(format!(
+                "root_dir change ({} -> {}) requires a restart",
+                root_dir.display(),
+                config.root_dir.display()
+            )));
+        }
+        validate_config(config, false)?;
+
+        let settings = Arc::new(ReloadableSettings::from_config(config));
+        AuditLogger::set_success_sample_rate(config.logging.success_sample_rate);
+        if let Some(handle) = &self.log_filter
+            && let Err(e) = handle.reload(EnvFilter::new(&config.logging.level))
+        {
+            warn!(
+                "Could not apply logging.level {}: {}",
+                config.logging.level, e
+            );
+        }
+        info!(
+            "Configuration reloaded: write_enabled={}, audit_enabled={}, log_level={}",
+            settings.write_config.enabled, settings.audit_enabled, config.logging.level
+        );
+        *self
+            .settings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = settings;
+        Ok(())
+    }
+
+    /// Whether audit logging is enabled in the current settings
+    pub fn audit_enabled(&self) -> bool {
+        self.settings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .audit_enabled
+    }
+}
+
+/// Load `config_path`, re-apply command-line `overrides` and swap in the
+/// result, keeping the current settings if any step fails
+///
+/// NIST 800-53 Controls:
+/// - CM-3: Configuration Change Control (reload audited)
+/// - AU-12: Audit Generation (configuration_loaded / configuration_error)
+fn reload_config(
+    reloader: &ConfigReloader,
+    config_path: &Path,
+    overrides: &dyn Fn(&mut TftpConfig),
+) -> Result<()> {
+    let result = load_config(config_path).and_then(|mut config| {
+        overrides(&mut config);
+        reloader.apply(&config)
+    });
+
+    match &result {
+        Ok(()) => {
+            if reloader.audit_enabled() {
+                AuditLogger::configuration_loaded(config_path);
+            }
+        }
+        Err(e) => {
+            error!("Config reload failed, keeping current settings: {}", e);
+            if reloader.audit_enabled() {
+                AuditLogger::configuration_error(config_path, &e.to_string());
+            }
+        }
+    }
+    result
+}
+
+/// Reload the configuration file each time the process receives SIGHUP
+///
+/// The signal handler is installed before this returns, so a SIGHUP sent
+/// afterwards is never lost. `overrides` re-applies command-line settings
+/// on top of the file.
+///
+/// NIST 800-53 Controls:
+/// - CM-3: Configuration Change Control (reload without restart)
+#[cfg(unix)]
+fn spawn_sighup_reload(
+    reloader: ConfigReloader,
+    config_path: PathBuf,
+    overrides: impl Fn(&mut TftpConfig) + Send + 'static,
+) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    Ok(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("SIGHUP received, reloading {}", config_path.display());
+            let _ = reload_config(&reloader, &config_path, &overrides);
+        }
+    }))
+}
+
+/// Log a snapshot of every multicast session each time the process
+/// receives SIGUSR1
+///
+/// NIST 800-53 Controls:
+/// - SI-4: System Monitoring (on-demand multicast session dump)
+#[cfg(unix)]
+fn spawn_sigusr1_session_dump(
+    multicast_server: Arc<MulticastTftpServer>,
+) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut user1 = signal(SignalKind::user_defined1())?;
+    Ok(tokio::spawn(async move {
+        while user1.recv().await.is_some() {
+            info!("SIGUSR1 received, dumping multicast sessions");
+            multicast_server.log_sessions().await;
+        }
+    }))
+}
+
+/// Reload the configuration file whenever its modification time or size
+/// changes, checking every `interval`
+///
+/// A file that disappears is left alone until it comes back, so an editor
+/// replacing it does not trigger a failed reload.
+///
+/// NIST 800-53 Controls:
+/// - CM-3: Configuration Change Control (reload without restart)
+fn spawn_config_watch(
+    reloader: ConfigReloader,
+    config_path: PathBuf,
+    interval: std::time::Duration,
+    overrides: impl Fn(&mut TftpConfig) + Send + 'static,
+) -> tokio::task::JoinHandle<()> {
+    fn file_version(path: &Path) -> Option<(std::time::SystemTime, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    let mut seen = file_version(&config_path);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let current = file_version(&config_path);
+            if current.is_none() || current == seen {
+                continue;
+            }
+            seen = current;
+            info!("{} changed, reloading", config_path.display());
+            let _ = reload_config(&reloader, &config_path, &overrides);
+        }
+    })
+}
+
+/// How long a read request may take to bind its transfer socket before an
+/// identical RRQ is treated as a new request
+const PENDING_READ_TTL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Read requests accepted on the listening port whose transfer socket is not
+/// bound yet, keyed by client address and filename
+///
+/// A client whose first OACK/DATA is lost or slow retransmits its RRQ.
+/// Without this, every copy would start its own transfer and the transfers
+/// would fight over the client's ACKs.
+///
+/// NIST 800-53 Controls:
+/// - SC-5: Denial of Service Protection (one transfer per request)
+#[derive(Clone, Default)]
+struct PendingReads {
+    entries: Arc<std::sync::Mutex<HashMap<PendingKey, PendingEntry>>>,
+    next_id: Arc<std::sync::atomic::AtomicU64>,
+}
+
+/// Client address and requested filename
+type PendingKey = (SocketAddr, String);
+
+struct PendingEntry {
+    id: u64,
+    started: std::time::Instant,
+}
+
+impl PendingReads {
+    /// Register a read request
+    ///
+    /// Returns `None` if the same client requested the same file less than
+    /// [`PENDING_READ_TTL`] ago and that transfer is still establishing.
+    fn begin(&self, client_addr: SocketAddr, filename: &str) -> Option<PendingRead> {
+        let key = (client_addr, filename.to_string());
+        let now = std::time::Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get(&key)
+            && now.duration_since(entry.started) < PENDING_READ_TTL
+        {
+            return None;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        entries.insert(key.clone(), PendingEntry { id, started: now });
+        Some(PendingRead {
+            reads: self.clone(),
+            key,
+            id,
+        })
+    }
+}
+
+/// Registration of an establishing read; removed when dropped
+struct PendingRead {
+    reads: PendingReads,
+    key: PendingKey,
+    id: u64,
+}
+
+impl Drop for PendingRead {
+    fn drop(&mut self) {
+        let mut entries = self
+            .reads
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // An expired entry may already belong to a newer request
+        if entries.get(&self.key).is_some_and(|entry| entry.id == self.id) {
+            entries.remove(&self.key);
+        }
+    }
+}
+
+/// Cuts a NETASCII stream into DATA block payloads
+///
+/// Output is cut into blocks after conversion, so every block but the last
+/// carries exactly `block_size` bytes. Each byte converts on its own, so
+/// reads are converted separately and the surplus carries over to the next
+/// block. When the converted length is an exact multiple of `block_size`
+/// the last block is empty (RFC 1350).
+struct NetasciiBlocks {
+    block_size: usize,
+    read_buffer: Vec<u8>,
+    pending: Vec<u8>,
+    input_done: bool,
+}
+
+impl NetasciiBlocks {
+    fn new(block_size: usize) -> Self {
+        Self {
+            block_size,
+            read_buffer: vec![0u8; block_size],
+            pending: Vec::new(),
+            input_done: false,
+        }
+    }
+
+    /// Next block's payload; shorter than `block_size` only for the last
+    async fn next_block(
+        &mut self,
+        reader: &mut (impl tokio::io::AsyncRead + Unpin),
+    ) -> std::io::Result<Vec<u8>> {
+        while self.pending.len() < self.block_size && !self.input_done {
+            let bytes_read = reader.read(&mut self.read_buffer).await?;
+            if bytes_read == 0 {
+                self.input_done = true;
+            } else {
+                let converted = TransferMode::convert_to_netascii(&self.read_buffer[..bytes_read]);
+                self.pending.extend_from_slice(&converted);
+            }
+        }
+        let rest = self
+            .pending
+            .split_off(self.block_size.min(self.pending.len()));
+        Ok(std::mem::replace(&mut self.pending, rest))
+    }
+}
+
 // RFC 1350 - Transfer modes
 ///
 /// NIST Controls:
@@ -472,15 +1162,18 @@ enum TftpErrorCode {
 // TransferMode and TftpOptions are now imported from snow_owl_tftp library at the top of the file
 
 pub struct TftpServer {
-    root_dir: PathBuf,
     bind_addr: SocketAddr,
     multicast_server: Option<Arc<MulticastTftpServer>>,
-    max_file_size_bytes: u64,
-    write_config: WriteConfig,
-    audit_enabled: bool,
+    settings: SharedSettings,
     buffer_pool: BufferPool,
     config: Arc<TftpConfig>,
     active_clients: Arc<AtomicUsize>,
+    virtual_roots: Option<Arc<VirtualRoots>>,
+    pending_reads: PendingReads,
+    scheduler: Arc<TransferScheduler>,
+    temp_files: Arc<TempFileRegistry>,
+    adaptive_window: Arc<AdaptiveWindow>,
+    transfer_events: TransferEvents,
 }
 
 impl TftpServer {
@@ -492,16 +1185,29 @@ impl TftpServer {
         audit_enabled: bool,
         config: Arc<TftpConfig>,
     ) -> Self {
-        Self {
+        let settings = ReloadableSettings {
             root_dir,
-            bind_addr,
-            multicast_server: None,
             max_file_size_bytes,
             write_config,
+            read_allowed_patterns: config.read_allowed_patterns.clone(),
+            allowed_transfer_modes: config.allowed_transfer_modes.clone(),
             audit_enabled,
+            max_bytes_per_sec: config.max_bytes_per_sec,
+        };
+
+        Self {
+            bind_addr,
+            multicast_server: None,
+            settings: Arc::new(RwLock::new(Arc::new(settings))),
             buffer_pool: BufferPool::new_default(),
+            scheduler: Arc::new(TransferScheduler::new(&config)),
+            adaptive_window: Arc::new(AdaptiveWindow::new(&config.adaptive_windowsize)),
             config,
             active_clients: Arc::new(AtomicUsize::new(0)),
+            virtual_roots: None,
+            pending_reads: PendingReads::default(),
+            temp_files: Arc::new(TempFileRegistry::default()),
+            transfer_events: TransferEvents::default(),
         }
     }
 
@@ -514,14 +1220,128 @@ impl TftpServer {
     /// - SC-5: Denial of Service Protection (multicast efficiency)
     pub fn with_multicast(mut self, config: MulticastConfig) -> Self {
         if config.enabled {
-            let multicast_server =
-                MulticastTftpServer::new(config, self.root_dir.clone(), self.audit_enabled);
+            // Multicast sessions keep the startup root_dir across reloads
+            let settings = self.settings();
+            let multicast_server = MulticastTftpServer::new(
+                config,
+                settings.root_dir.clone(),
+                settings.audit_enabled,
+            );
             self.multicast_server = Some(Arc::new(multicast_server));
             info!("Multicast TFTP support enabled");
         }
         self
     }
 
+    /// Multicast session manager, when multicast is enabled
+    pub fn multicast_server(&self) -> Option<Arc<MulticastTftpServer>> {
+        self.multicast_server.clone()
+    }
+
+    /// Serve read requests under a virtual prefix through a path resolver
+    ///
+    /// Matching requests bypass the TFTP root; the resolver's answer is
+    /// still checked to be a canonical file inside the allowed roots.
+    ///
+    /// NIST Controls:
+    /// - AC-3: Access Enforcement (resolved files confined to allowed roots)
+    /// - CM-8: System Component Inventory (image locations from the database)
+    pub fn with_virtual_roots(mut self, virtual_roots: VirtualRoots) -> Self {
+        self.virtual_roots = Some(Arc::new(virtual_roots));
+        info!("Virtual image paths enabled");
+        self
+    }
+
+    /// Send a Started and then a Completed or Failed event for every unicast
+    /// read and write to `sender`
+    ///
+    /// Events are emitted at the same points as the audit log's transfer
+    /// records, whether or not auditing is enabled. A full channel drops
+    /// events instead of delaying transfers.
+    ///
+    /// NIST Controls:
+    /// - AU-12: Audit Record Generation (transfer outcomes for other components)
+    pub fn with_transfer_events(
+        mut self,
+        sender: tokio::sync::mpsc::Sender<TransferEvent>,
+    ) -> Self {
+        self.transfer_events = TransferEvents::new(sender);
+        self
+    }
+
+    /// Snapshot of the current reloadable settings
+    fn settings(&self) -> Arc<ReloadableSettings> {
+        self.settings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Priority classes, shared pacing and per-class transfer metrics
+    pub fn scheduler(&self) -> Arc<TransferScheduler> {
+        self.scheduler.clone()
+    }
+
+    /// Remove stale upload temp files now, then every
+    /// `temp_sweep_interval_secs` while writes are enabled, until `stop`
+    ///
+    /// The root and write settings are read again for each sweep so a
+    /// reload applies to the next one.
+    ///
+    /// NIST 800-53 SC-4: Information in Shared Resources (interrupted uploads)
+    fn spawn_temp_file_sweeper(&self, stop: CancellationToken) {
+        let settings = self.settings.clone();
+        let registry = self.temp_files.clone();
+        let interval = self.settings().write_config.temp_sweep_interval();
+
+        tokio::spawn(async move {
+            // The first tick completes immediately: the startup sweep
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut startup = true;
+
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => return,
+                    _ = ticker.tick() => {}
+                }
+
+                let current = settings
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone();
+                if !std::mem::take(&mut startup) && !current.write_config.enabled {
+                    continue;
+                }
+                let Some(max_age) = current.write_config.temp_max_age() else {
+                    continue;
+                };
+
+                let registry = registry.clone();
+                let swept = tokio::task::spawn_blocking(move || {
+                    temp_files::sweep(&current.root_dir, max_age, &registry)
+                })
+                .await;
+                match swept {
+                    Ok(removed) if !removed.is_empty() => {
+                        info!("Removed {} stale upload temp file(s)", removed.len());
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Temp file sweep failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Handle for applying a reloaded configuration to this server
+    pub fn reloader(&self) -> ConfigReloader {
+        ConfigReloader {
+            bind_addr: self.bind_addr,
+            settings: self.settings.clone(),
+            log_filter: None,
+        }
+    }
+
     /// Run the TFTP server main loop
     ///
     /// NIST 800-53 Controls:
@@ -532,6 +1352,21 @@ impl TftpServer {
     /// STIG V-222563: Applications must produce audit records
     /// STIG V-222564: Applications must protect audit information
     pub async fn run(&self) -> Result<()> {
+        self.run_with_shutdown(CancellationToken::new()).await
+    }
+
+    /// Run the server until `shutdown` is cancelled, then drain transfers
+    ///
+    /// Once the token is cancelled no further requests are read from the
+    /// listening socket. Transfers already in progress get
+    /// `shutdown_drain_timeout_secs` to finish before they are aborted, and
+    /// the future resolves after that.
+    ///
+    /// NIST 800-53 Controls:
+    /// - SC-24: Fail in Known State (bounded, ordered shutdown)
+    /// - AU-3: Content of Audit Records (log all requests)
+    /// - SC-7: Boundary Protection (enforce network boundaries)
+    pub async fn run_with_shutdown(&self, shutdown: CancellationToken) -> Result<()> {
         // Phase 1: Create optimized socket with platform-specific performance tuning
         let socket = Arc::new(create_optimized_socket(
             self.bind_addr,
@@ -539,27 +1374,51 @@ impl TftpServer {
         )?);
         info!("TFTP server listening on {}", self.bind_addr);
 
+        // Stops with the server, including when this future is dropped
+        let sweep_token = shutdown.child_token();
+        let _stop_sweep = sweep_token.clone().drop_guard();
+        self.spawn_temp_file_sweeper(sweep_token);
+
         // Phase 4: Check if worker pool is enabled
-        if self.config.performance.platform.worker_pool.enabled {
+        if self.config.performance.worker_pool_enabled() {
             info!("Worker pool enabled - using Phase 4 multi-threaded architecture");
             let pool = WorkerPool::new(self.config.clone());
-            return pool
-                .start(
-                    socket,
-                    self.root_dir.clone(),
-                    self.write_config.clone(),
-                    self.max_file_size_bytes,
-                    self.audit_enabled,
+            // NIST SI-4: Optional JSON status endpoint for monitoring
+            if let Some(addr) = self.config.status_bind_addr {
+                let listener = tokio::net::TcpListener::bind(addr).await?;
+                let stats = pool.stats();
+                tokio::spawn(status::serve(
+                    listener,
+                    stats,
                     self.multicast_server.clone(),
+                    shutdown.child_token(),
+                ));
+            }
+            let result = pool
+                .start_with_shutdown(
+                    socket,
+                    self.request_handler(),
+                    shutdown,
+                    self.config.shutdown_drain_timeout(),
                 )
                 .await;
+            self.scheduler.log_summary();
+            return result;
         } else {
             info!("Worker pool disabled - using Phase 3 single-threaded architecture");
+            if let Some(addr) = self.config.status_bind_addr {
+                warn!(
+                    "status_bind_addr {} ignored: the status listener requires the worker pool",
+                    addr
+                );
+            }
         }
 
         // Performance optimization: Use buffer pool to avoid allocations
         let buffer_pool = self.buffer_pool.clone();
         let active_clients = self.active_clients.clone();
+        // Per-client transfers, tracked so shutdown can wait for them
+        let mut transfers = JoinSet::new();
 
         // Phase 2: Batch receiving configuration
         #[cfg(any(target_os = "linux", target_os = "freebsd"))]
@@ -592,7 +1451,13 @@ impl TftpServer {
         }
 
         loop {
-            // Phase 2: Adaptive batching - decide whether to use batch receiving
+            if shutdown.is_cancelled() {
+                break;
+            }
+            // Reap finished transfers so the set only holds running ones
+            while transfers.try_join_next().is_some() {}
+
+            // Phase 2: Adaptive batching - decide whether to use batch receiving
             #[cfg(any(target_os = "linux", target_os = "freebsd"))]
             let use_batch_recv = if adaptive_batching_enabled {
                 let current_clients = active_clients.load(Ordering::Relaxed);
@@ -627,13 +1492,28 @@ impl TftpServer {
                             buf.clear();
                             buf.extend_from_slice(&buffers[i][..*size]);
 
-                            let root_dir = self.root_dir.clone();
+                            let settings = self.settings();
+                            let root_dir = settings.root_dir.clone();
                             let multicast_server = self.multicast_server.clone();
-                            let max_file_size = self.max_file_size_bytes;
-                            let write_config = self.write_config.clone();
-                            let audit_enabled = self.audit_enabled;
+                            let max_file_size = settings.max_file_size_bytes;
+                            let write_config = settings.write_config.clone();
+                            let read_allowed_patterns = settings.read_allowed_patterns.clone();
+                            let allowed_transfer_modes = settings.allowed_transfer_modes.clone();
+                            let audit_enabled = settings.audit_enabled;
                             let file_io_config = self.config.performance.platform.file_io.clone();
                             let default_windowsize = self.config.performance.default_windowsize;
+                            let retry_policy = self.config.retry_policy();
+                            let max_bytes_per_sec = settings.max_bytes_per_sec;
+                            let virtual_roots = self.virtual_roots.clone();
+                            let pending_reads = self.pending_reads.clone();
+                            let allow_block_rollover = self.config.allow_block_rollover;
+                            let drop_non_request_opcodes = self.config.drop_non_request_opcodes;
+                            let directory_index = self.config.directory_index_limit();
+                            let scheduler = self.scheduler.clone();
+                            let temp_files = self.temp_files.clone();
+                            let adaptive_window = self.adaptive_window.clone();
+                            let transfer_ip = self.config.transfer_ip();
+                            let transfer_events = self.transfer_events.clone();
                             let pool = buffer_pool.clone();
                             let addr = *client_addr;
                             let client_counter = active_clients.clone();
@@ -641,7 +1521,7 @@ impl TftpServer {
                             // Increment active clients counter
                             client_counter.fetch_add(1, Ordering::Relaxed);
 
-                            tokio::spawn(async move {
+                            transfers.spawn(async move {
                                 if let Err(e) = Self::handle_client(
                                     buf.to_vec(),
                                     addr,
@@ -649,9 +1529,23 @@ impl TftpServer {
                                     multicast_server,
                                     max_file_size,
                                     write_config,
+                                    read_allowed_patterns,
+                                    allowed_transfer_modes,
                                     audit_enabled,
                                     file_io_config,
                                     default_windowsize,
+                                    allow_block_rollover,
+                                    directory_index,
+                                    retry_policy,
+                                    max_bytes_per_sec,
+                                    virtual_roots,
+                                    drop_non_request_opcodes,
+                                    pending_reads,
+                                    scheduler,
+                                    temp_files,
+                                    adaptive_window,
+                                    transfer_ip,
+                                    transfer_events,
                                 )
                                 .await
                                 {
@@ -681,26 +1575,50 @@ impl TftpServer {
             let mut buf = buffer_pool.acquire().await;
             buf.resize(MAX_PACKET_SIZE, 0);
 
-            match socket.recv_from(&mut buf).await {
+            let received = tokio::select! {
+                _ = shutdown.cancelled() => None,
+                received = socket.recv_from(&mut buf) => Some(received),
+            };
+            let Some(received) = received else {
+                buffer_pool.release(buf).await;
+                break;
+            };
+
+            match received {
                 Ok((size, client_addr)) => {
                     // Take ownership of the data without copying
                     let mut data = buf;
                     data.truncate(size);
 
-                    let root_dir = self.root_dir.clone();
+                    let settings = self.settings();
+                    let root_dir = settings.root_dir.clone();
                     let multicast_server = self.multicast_server.clone();
-                    let max_file_size = self.max_file_size_bytes;
-                    let write_config = self.write_config.clone();
-                    let audit_enabled = self.audit_enabled;
+                    let max_file_size = settings.max_file_size_bytes;
+                    let write_config = settings.write_config.clone();
+                    let read_allowed_patterns = settings.read_allowed_patterns.clone();
+                    let allowed_transfer_modes = settings.allowed_transfer_modes.clone();
+                    let audit_enabled = settings.audit_enabled;
                     let file_io_config = self.config.performance.platform.file_io.clone();
                     let default_windowsize = self.config.performance.default_windowsize;
+                    let retry_policy = self.config.retry_policy();
+                    let max_bytes_per_sec = settings.max_bytes_per_sec;
+                    let virtual_roots = self.virtual_roots.clone();
+                    let pending_reads = self.pending_reads.clone();
+                    let allow_block_rollover = self.config.allow_block_rollover;
+                    let drop_non_request_opcodes = self.config.drop_non_request_opcodes;
+                    let directory_index = self.config.directory_index_limit();
+                    let scheduler = self.scheduler.clone();
+                    let temp_files = self.temp_files.clone();
+                    let adaptive_window = self.adaptive_window.clone();
+                    let transfer_ip = self.config.transfer_ip();
+                    let transfer_events = self.transfer_events.clone();
                     let pool = buffer_pool.clone();
                     let client_counter = active_clients.clone();
 
                     // Increment active clients counter
                     client_counter.fetch_add(1, Ordering::Relaxed);
 
-                    tokio::spawn(async move {
+                    transfers.spawn(async move {
                         if let Err(e) = Self::handle_client(
                             data.to_vec(),
                             client_addr,
@@ -708,9 +1626,23 @@ impl TftpServer {
                             multicast_server,
                             max_file_size,
                             write_config,
+                            read_allowed_patterns,
+                            allowed_transfer_modes,
                             audit_enabled,
                             file_io_config,
                             default_windowsize,
+                            allow_block_rollover,
+                            directory_index,
+                            retry_policy,
+                            max_bytes_per_sec,
+                            virtual_roots,
+                            drop_non_request_opcodes,
+                            pending_reads,
+                            scheduler,
+                            temp_files,
+                            adaptive_window,
+                            transfer_ip,
+                            transfer_events,
                         )
                         .await
                         {
@@ -730,6 +1662,92 @@ impl TftpServer {
                 }
             }
         }
+
+        // NIST 800-53 SC-24: Let in-flight transfers finish, within a bound
+        info!("Shutdown requested, no longer accepting TFTP requests");
+        drain_transfers(&mut transfers, self.config.shutdown_drain_timeout()).await;
+        self.scheduler.log_summary();
+
+        Ok(())
+    }
+
+    /// Build the request handler used by worker pool threads
+    ///
+    /// Workers hand each initial RRQ/WRQ to `handle_client`, so pooled and
+    /// non-pooled transfers share the same validation, negotiation and audit path.
+    fn request_handler(&self) -> RequestHandler {
+        let settings = self.settings.clone();
+        let multicast_server = self.multicast_server.clone();
+        let file_io_config = self.config.performance.platform.file_io.clone();
+        let default_windowsize = self.config.performance.default_windowsize;
+        let retry_policy = self.config.retry_policy();
+        let allow_block_rollover = self.config.allow_block_rollover;
+        let drop_non_request_opcodes = self.config.drop_non_request_opcodes;
+        let directory_index = self.config.directory_index_limit();
+        let active_clients = self.active_clients.clone();
+        let virtual_roots = self.virtual_roots.clone();
+        let pending_reads = self.pending_reads.clone();
+        let scheduler = self.scheduler.clone();
+        let temp_files = self.temp_files.clone();
+        let adaptive_window = self.adaptive_window.clone();
+        let transfer_ip = self.config.transfer_ip();
+        let transfer_events = self.transfer_events.clone();
+
+        Arc::new(move |data, client_addr| {
+            // Snapshot per request so a reload applies to the next transfer
+            let settings = settings
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
+            let root_dir = settings.root_dir.clone();
+            let multicast_server = multicast_server.clone();
+            let max_file_size = settings.max_file_size_bytes;
+            let write_config = settings.write_config.clone();
+            let read_allowed_patterns = settings.read_allowed_patterns.clone();
+            let allowed_transfer_modes = settings.allowed_transfer_modes.clone();
+            let audit_enabled = settings.audit_enabled;
+            let max_bytes_per_sec = settings.max_bytes_per_sec;
+            let file_io_config = file_io_config.clone();
+            let active_clients = active_clients.clone();
+            let virtual_roots = virtual_roots.clone();
+            let pending_reads = pending_reads.clone();
+            let scheduler = scheduler.clone();
+            let temp_files = temp_files.clone();
+            let adaptive_window = adaptive_window.clone();
+            let transfer_events = transfer_events.clone();
+
+            Box::pin(async move {
+                active_clients.fetch_add(1, Ordering::Relaxed);
+                let result = Self::handle_client(
+                    data,
+                    client_addr,
+                    root_dir,
+                    multicast_server,
+                    max_file_size,
+                    write_config,
+                    read_allowed_patterns,
+                    allowed_transfer_modes,
+                    audit_enabled,
+                    file_io_config,
+                    default_windowsize,
+                    allow_block_rollover,
+                    directory_index,
+                    retry_policy,
+                    max_bytes_per_sec,
+                    virtual_roots,
+                    drop_non_request_opcodes,
+                    pending_reads,
+                    scheduler,
+                    temp_files,
+                    adaptive_window,
+                    transfer_ip,
+                    transfer_events,
+                )
+                .await;
+                active_clients.fetch_sub(1, Ordering::Relaxed);
+                result
+            })
+        })
     }
 
     /// Handle individual TFTP client requests
@@ -751,9 +1769,23 @@ impl TftpServer {
         multicast_server: Option<Arc<MulticastTftpServer>>,
         max_file_size_bytes: u64,
         write_config: WriteConfig,
+        read_allowed_patterns: Vec<String>,
+        allowed_transfer_modes: Vec<AllowedTransferMode>,
         audit_enabled: bool,
         file_io_config: config::FileIoConfig,
         default_windowsize: usize,
+        allow_block_rollover: bool,
+        directory_index: Option<usize>,
+        retry_policy: RetryPolicy,
+        max_bytes_per_sec: Option<u64>,
+        virtual_roots: Option<Arc<VirtualRoots>>,
+        drop_non_request_opcodes: bool,
+        pending_reads: PendingReads,
+        scheduler: Arc<TransferScheduler>,
+        temp_files: Arc<TempFileRegistry>,
+        adaptive_window: Arc<AdaptiveWindow>,
+        transfer_ip: Option<IpAddr>,
+        transfer_events: TransferEvents,
     ) -> Result<()> {
         let mut bytes = BytesMut::from(&data[..]);
 
@@ -777,6 +1809,15 @@ impl TftpServer {
                 let filename = Self::parse_string(&mut bytes)?;
                 let mode_str = Self::parse_string(&mut bytes)?;
 
+                // A retransmitted RRQ must not start a second transfer
+                let Some(pending) = pending_reads.begin(client_addr, &filename) else {
+                    debug!(
+                        "Ignoring retransmitted RRQ from {} for {}: transfer still starting",
+                        client_addr, filename
+                    );
+                    return Ok(());
+                };
+
                 // Validate transfer mode
                 let mode = TransferMode::from_str(&mode_str)?;
 
@@ -789,6 +1830,7 @@ impl TftpServer {
                     );
                     Self::send_error(
                         client_addr,
+                        transfer_ip,
                         TftpErrorCode::IllegalOperation,
                         "MAIL mode not supported",
                     )
@@ -796,12 +1838,63 @@ impl TftpServer {
                     return Ok(());
                 }
 
+                // NIST CM-7: Only the transfer modes allowed in configuration
+                if !is_mode_allowed(&allowed_transfer_modes, &mode_str) {
+                    warn!(
+                        "RRQ from {}: {} mode not allowed for {}",
+                        client_addr, mode_str, filename
+                    );
+
+                    if audit_enabled {
+                        AuditLogger::read_denied(
+                            client_addr,
+                            &filename,
+                            &format!("transfer mode {} not allowed", mode_str),
+                        );
+                    }
+
+                    Self::send_error(
+                        client_addr,
+                        transfer_ip,
+                        TftpErrorCode::IllegalOperation,
+                        "Transfer mode not allowed",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
+                // NIST AC-3: Only serve files matching read_allowed_patterns, if configured
+                if !is_read_allowed(&read_allowed_patterns, &filename) {
+                    warn!(
+                        "RRQ from {}: {} not in read allowed patterns",
+                        client_addr, filename
+                    );
+
+                    if audit_enabled {
+                        AuditLogger::read_denied(
+                            client_addr,
+                            &filename,
+                            "file not in read_allowed_patterns",
+                        );
+                    }
+
+                    Self::send_error(
+                        client_addr,
+                        transfer_ip,
+                        TftpErrorCode::AccessViolation,
+                        "File not allowed for reading",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
                 // Parse options (RFC 2347)
                 let mut options = TftpOptions {
                     windowsize: default_windowsize,
                     ..TftpOptions::default()
                 };
-                let mut requested_options = HashMap::new();
+                let mut requested_windowsize = default_windowsize;
+                let mut requested_options = OptionList::new();
                 let mut multicast_requested = false;
 
                 while bytes.remaining() > 0 {
@@ -824,7 +1917,7 @@ impl TftpServer {
                 }
 
                 // Process options
-                let mut negotiated_options = HashMap::new();
+                let mut negotiated_options = OptionList::new();
 
                 // RFC 2347: Option negotiation
                 // Server MUST either accept option with valid value or omit from OACK
@@ -903,11 +1996,21 @@ impl TftpServer {
                             // RFC 7440 - Windowsize Option (valid range: 1-65535 blocks)
                             match value.parse::<usize>() {
                                 Ok(size) if (1..=65535).contains(&size) => {
-                                    // Server can accept or negotiate down
-                                    // For now, accept client's windowsize if valid
-                                    options.windowsize = size;
-                                    negotiated_options
-                                        .insert("windowsize".to_string(), size.to_string());
+                                    // Server can accept or negotiate down; clients
+                                    // on lossy links get a smaller window (SC-5)
+                                    requested_windowsize = size;
+                                    options.windowsize =
+                                        adaptive_window.grant(client_addr.ip(), size);
+                                    if options.windowsize < size {
+                                        debug!(
+                                            "Granting windowsize {} to {} (requested {}) after recent retransmits",
+                                            options.windowsize, client_addr, size
+                                        );
+                                    }
+                                    negotiated_options.insert(
+                                        "windowsize".to_string(),
+                                        options.windowsize.to_string(),
+                                    );
                                 }
                                 Ok(size) => {
                                     warn!(
@@ -961,14 +2064,10 @@ impl TftpServer {
                         );
 
                         // Create a response socket for this client
-                        // Use IPv6 unspecified if client is IPv6, IPv4 otherwise (dual-stack support)
-                        let bind_addr = if client_addr.is_ipv6() {
-                            SocketAddr::new(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), 0)
-                        } else {
-                            SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0)
-                        };
+                        let bind_addr = transfer_bind_addr(transfer_ip, client_addr);
                         let response_socket = Arc::new(create_transfer_socket(bind_addr)?);
                         response_socket.connect(client_addr).await?;
+                        drop(pending);
 
                         // Delegate to multicast server
                         return mcast_server
@@ -988,6 +2087,7 @@ impl TftpServer {
                         );
                         Self::send_error(
                             client_addr,
+                            transfer_ip,
                             TftpErrorCode::OptionNegotiation,
                             "Multicast not supported",
                         )
@@ -996,46 +2096,90 @@ impl TftpServer {
                     }
                 }
 
-                // Validate filename (prevent directory traversal)
-                let file_path = match Self::validate_and_resolve_path(&root_dir, &filename) {
-                    Ok(path) => path,
-                    Err(e) => {
-                        // Audit log: Path validation failure
-                        if audit_enabled {
-                            if filename.contains("..") {
-                                AuditLogger::path_traversal_attempt(
-                                    client_addr,
-                                    &filename,
-                                    "directory traversal attempt",
-                                );
-                            } else {
-                                AuditLogger::access_violation(
-                                    client_addr,
-                                    &filename,
-                                    &e.to_string(),
-                                );
+                // Virtual image paths resolve through the images database instead of root_dir
+                let file_path = if let Some(roots) =
+                    virtual_roots.as_ref().filter(|roots| roots.matches(&filename))
+                {
+                    match roots.resolve(&filename).await {
+                        Ok(path) => path,
+                        Err(e) => {
+                            let code = match e {
+                                VirtualPathError::NotFound => TftpErrorCode::FileNotFound,
+                                VirtualPathError::AccessDenied(_) => {
+                                    TftpErrorCode::AccessViolation
+                                }
+                            };
+                            warn!(
+                                "RRQ from {}: virtual path {} not served: {}",
+                                client_addr, filename, e
+                            );
+                            if audit_enabled {
+                                AuditLogger::read_denied(client_addr, &filename, &e.to_string());
                             }
+
+                            Self::send_error(client_addr, transfer_ip, code, &e.to_string())
+                                .await?;
+                            return Ok(());
                         }
+                    }
+                } else {
+                    // Validate filename (prevent directory traversal)
+                    match Self::validate_and_resolve_path(&root_dir, &filename) {
+                        Ok(path) => path,
+                        Err(e) => {
+                            // Audit log: Path validation failure
+                            if audit_enabled {
+                                if filename.contains("..") {
+                                    AuditLogger::path_traversal_attempt(
+                                        client_addr,
+                                        &filename,
+                                        "directory traversal attempt",
+                                    );
+                                } else {
+                                    AuditLogger::access_violation(
+                                        client_addr,
+                                        &filename,
+                                        &e.to_string(),
+                                    );
+                                }
+                            }
 
-                        Self::send_error(
-                            client_addr,
-                            TftpErrorCode::AccessViolation,
-                            &e.to_string(),
-                        )
-                        .await?;
-                        return Ok(());
+                            Self::send_error(
+                                client_addr,
+                                transfer_ip,
+                                TftpErrorCode::AccessViolation,
+                                &e.to_string(),
+                            )
+                            .await?;
+                            return Ok(());
+                        }
                     }
                 };
 
+                let window_report = adaptive_window.report(
+                    client_addr.ip(),
+                    requested_windowsize,
+                    options.windowsize,
+                );
                 Self::handle_read_request(
                     file_path,
                     client_addr,
+                    transfer_ip,
                     mode,
                     options,
                     negotiated_options,
                     max_file_size_bytes,
                     audit_enabled,
                     &file_io_config,
+                    allow_block_rollover,
+                    directory_index,
+                    retry_policy,
+                    max_bytes_per_sec,
+                    pending,
+                    &filename,
+                    &scheduler,
+                    window_report,
+                    &transfer_events,
                 )
                 .await?;
             }
@@ -1064,6 +2208,7 @@ impl TftpServer {
 
                     Self::send_error(
                         client_addr,
+                        transfer_ip,
                         TftpErrorCode::AccessViolation,
                         "Write not supported",
                     )
@@ -1091,6 +2236,7 @@ impl TftpServer {
 
                     Self::send_error(
                         client_addr,
+                        transfer_ip,
                         TftpErrorCode::IllegalOperation,
                         "MAIL mode not supported",
                     )
@@ -1098,12 +2244,37 @@ impl TftpServer {
                     return Ok(());
                 }
 
+                // NIST CM-7: Only the transfer modes allowed in configuration
+                if !is_mode_allowed(&allowed_transfer_modes, &mode_str) {
+                    warn!(
+                        "WRQ from {}: {} mode not allowed for {}",
+                        client_addr, mode_str, filename
+                    );
+
+                    if audit_enabled {
+                        AuditLogger::write_request_denied(
+                            client_addr,
+                            &filename,
+                            &format!("transfer mode {} not allowed", mode_str),
+                        );
+                    }
+
+                    Self::send_error(
+                        client_addr,
+                        transfer_ip,
+                        TftpErrorCode::IllegalOperation,
+                        "Transfer mode not allowed",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
                 // Parse options (RFC 2347)
                 let mut options = TftpOptions {
                     windowsize: default_windowsize,
                     ..TftpOptions::default()
                 };
-                let mut requested_options = HashMap::new();
+                let mut requested_options = OptionList::new();
 
                 while bytes.remaining() > 0 {
                     let option_name = match Self::parse_string(&mut bytes) {
@@ -1121,7 +2292,7 @@ impl TftpServer {
 
                 // RFC 2347: Option negotiation
                 // Server MUST either accept option with valid value or omit from OACK
-                let mut negotiated_options = HashMap::new();
+                let mut negotiated_options = OptionList::new();
 
                 for (name, value) in &requested_options {
                     match name.as_str() {
@@ -1236,6 +2407,30 @@ impl TftpServer {
                     );
                 }
 
+                // NIST AC-3: Virtual image paths are read-only
+                if virtual_roots
+                    .as_ref()
+                    .is_some_and(|roots| roots.matches(&filename))
+                {
+                    warn!("WRQ from {}: {} is a virtual path", client_addr, filename);
+                    if audit_enabled {
+                        AuditLogger::write_request_denied(
+                            client_addr,
+                            &filename,
+                            "virtual paths are read-only",
+                        );
+                    }
+
+                    Self::send_error(
+                        client_addr,
+                        transfer_ip,
+                        TftpErrorCode::AccessViolation,
+                        "Virtual paths are read-only",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
                 // Validate filename (prevent directory traversal)
                 let file_path = match Self::validate_and_resolve_path(&root_dir, &filename) {
                     Ok(path) => path,
@@ -1259,6 +2454,7 @@ impl TftpServer {
 
                         Self::send_error(
                             client_addr,
+                            transfer_ip,
                             TftpErrorCode::AccessViolation,
                             &e.to_string(),
                         )
@@ -1284,6 +2480,7 @@ impl TftpServer {
 
                     Self::send_error(
                         client_addr,
+                        transfer_ip,
                         TftpErrorCode::AccessViolation,
                         "File not allowed for writing",
                     )
@@ -1311,6 +2508,7 @@ impl TftpServer {
 
                     Self::send_error(
                         client_addr,
+                        transfer_ip,
                         TftpErrorCode::FileExists,
                         "File already exists",
                     )
@@ -1318,22 +2516,77 @@ impl TftpServer {
                     return Ok(());
                 }
 
+                // RFC 2349: Refuse an upload that cannot fit before any DATA is sent
+                if let Some(size) = options.transfer_size
+                    && let Err(e) = check_declared_size(size, max_file_size_bytes, &root_dir)
+                {
+                    warn!("WRQ from {}: {}", client_addr, e);
+
+                    if audit_enabled {
+                        match e {
+                            DeclaredSizeError::ExceedsLimit { size, max } => {
+                                AuditLogger::file_size_limit_exceeded(
+                                    client_addr,
+                                    &filename,
+                                    size,
+                                    max,
+                                );
+                            }
+                            DeclaredSizeError::InsufficientSpace { .. } => {
+                                AuditLogger::write_request_denied(
+                                    client_addr,
+                                    &filename,
+                                    &e.to_string(),
+                                );
+                            }
+                        }
+                    }
+
+                    Self::send_error(
+                        client_addr,
+                        transfer_ip,
+                        TftpErrorCode::DiskFull,
+                        &e.to_string(),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
                 Self::handle_write_request(
                     file_path,
                     client_addr,
+                    transfer_ip,
                     mode,
                     options,
                     negotiated_options,
                     max_file_size_bytes,
                     !file_exists,
                     audit_enabled,
+                    retry_policy,
+                    write_config.tsize_mismatch,
+                    &temp_files,
+                    &transfer_events,
                 )
                 .await?;
             }
+            TftpOpcode::Error => {
+                // RFC 1350: ERROR packets are never acknowledged, so answering
+                // one could start an error loop between two hosts
+                debug!("Ignoring ERROR packet from {} on listening port", client_addr);
+            }
+            _ if drop_non_request_opcodes => {
+                // Usually a retransmit from a client mid-transfer that lost
+                // track of the transfer TID
+                debug!(
+                    "Ignoring {:?} packet from {} on listening port",
+                    opcode, client_addr
+                );
+            }
             _ => {
                 warn!("Unexpected opcode from {}: {:?}", client_addr, opcode);
                 Self::send_error(
                     client_addr,
+                    transfer_ip,
                     TftpErrorCode::IllegalOperation,
                     "Unexpected opcode",
                 )
@@ -1354,23 +2607,55 @@ impl TftpServer {
     async fn handle_read_request(
         file_path: PathBuf,
         client_addr: SocketAddr,
+        transfer_ip: Option<IpAddr>,
         mode: TransferMode,
         options: TftpOptions,
-        mut negotiated_options: HashMap<String, String>,
+        mut negotiated_options: OptionList,
         max_file_size_bytes: u64,
         audit_enabled: bool,
         file_io_config: &config::FileIoConfig,
+        allow_block_rollover: bool,
+        directory_index: Option<usize>,
+        retry_policy: RetryPolicy,
+        max_bytes_per_sec: Option<u64>,
+        pending: PendingRead,
+        filename: &str,
+        scheduler: &Arc<TransferScheduler>,
+        window_report: WindowReport,
+        transfer_events: &TransferEvents,
     ) -> Result<()> {
         let start_time = std::time::Instant::now();
         // RFC 1350: Each transfer connection uses a new TID (Transfer ID)
-        // Use IPv6 unspecified if client is IPv6, IPv4 otherwise (dual-stack support)
-        let bind_addr = if client_addr.is_ipv6() {
-            SocketAddr::new(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), 0)
-        } else {
-            SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0)
-        };
-        let socket = create_transfer_socket(bind_addr)?;
+        let socket = create_transfer_socket(transfer_bind_addr(transfer_ip, client_addr))?;
         socket.connect(client_addr).await?;
+        // Replies now come from this TID; a later RRQ is a new request
+        drop(pending);
+
+        // A directory is answered with a generated index when enabled, and
+        // otherwise with an explicit error rather than a failed read
+        // NIST AC-3: Access Enforcement (hidden entries are never listed)
+        if let Ok(metadata) = tokio::fs::metadata(&file_path).await
+            && metadata.is_dir()
+        {
+            let class = scheduler.classifier().classify(filename, None);
+            return Self::serve_directory_index(
+                &socket,
+                client_addr,
+                &file_path,
+                mode,
+                &options,
+                negotiated_options,
+                directory_index,
+                allow_block_rollover,
+                audit_enabled,
+                start_time,
+                retry_policy,
+                scheduler.begin(class, start_time),
+                window_report,
+                transfer_events,
+            )
+            .await;
+        }
 
         // Open and validate file
         let mut file = match File::open(&file_path).await {
@@ -1426,6 +2711,10 @@ impl TftpServer {
             return Ok(());
         }
 
+        // NIST SC-6: small boot-path files are scheduled ahead of images
+        let class = scheduler.classifier().classify(filename, Some(file_size));
+        let ticket = scheduler.begin(class, start_time);
+
         // Audit log: Transfer started
         if audit_enabled {
             let mode_str = match mode {
@@ -1441,6 +2730,13 @@ impl TftpServer {
                 options.block_size,
             );
         }
+        let watch = transfer_events.start(
+            client_addr,
+            &file_path,
+            TransferDirection::Read,
+            Some(file_size),
+            start_time,
+        );
 
         let block_size = options.block_size;
         let timeout = tokio::time::Duration::from_secs(options.timeout);
@@ -1454,53 +2750,60 @@ impl TftpServer {
             file.read_to_end(&mut raw_data).await?;
             let file_data = TransferMode::convert_to_netascii(&raw_data);
 
-            // RFC 2349: Update tsize with converted size
-            if negotiated_options.contains_key("tsize") {
-                negotiated_options.insert("tsize".to_string(), file_data.len().to_string());
-            }
-
-            // RFC 2347: Send OACK if options were negotiated
-            if !negotiated_options.is_empty() {
-                debug!("Sending OACK with options: {:?}", negotiated_options);
-                let oack_packet = Self::build_oack_packet(&negotiated_options);
-                Self::send_with_retry(&socket, &oack_packet, timeout).await?;
-                match Self::wait_for_ack(&socket, 0, timeout).await {
-                    Ok(()) => {}
-                    Err(e) => {
-                        error!("Failed to receive ACK for OACK: {}", e);
-                        return Ok(());
-                    }
-                }
-            }
-
-            Self::send_file_data_buffered(
+            Self::send_buffered_content(
                 &socket,
                 &file_data,
-                block_size,
-                options.windowsize,
-                timeout,
                 client_addr,
                 &file_path,
-                start_time,
+                &options,
+                negotiated_options,
+                allow_block_rollover,
                 audit_enabled,
+                start_time,
+                retry_policy,
+                ticket,
+                window_report,
+                watch,
             )
             .await
         } else {
             // Large files or OCTET mode - use streaming approach
-            // RFC 2349: Update tsize with file size
-            if negotiated_options.contains_key("tsize") {
+            // RFC 2349: Update tsize with file size. The converted size of a
+            // streamed NETASCII file is unknown until it has been sent, so
+            // tsize is left out of the OACK instead
+            if mode == TransferMode::Netascii {
+                negotiated_options.remove("tsize");
+            } else if negotiated_options.contains_key("tsize") {
                 negotiated_options.insert("tsize".to_string(), file_size.to_string());
             }
 
+            // NETASCII conversion only grows the data, so the raw size is a lower bound
+            if Self::reject_block_overflow(
+                &socket,
+                client_addr,
+                &file_path,
+                &options,
+                &negotiated_options,
+                file_size,
+                allow_block_rollover,
+                audit_enabled,
+            )
+            .await?
+            {
+                watch.fail("block count exceeds the 16-bit block number");
+                return Ok(());
+            }
+
             // RFC 2347: Send OACK if options were negotiated
             if !negotiated_options.is_empty() {
                 debug!("Sending OACK with options: {:?}", negotiated_options);
                 let oack_packet = Self::build_oack_packet(&negotiated_options);
                 Self::send_with_retry(&socket, &oack_packet, timeout).await?;
-                match Self::wait_for_ack(&socket, 0, timeout).await {
+                match Self::wait_for_ack(&socket, 0, timeout, retry_policy).await {
                     Ok(()) => {}
                     Err(e) => {
                         error!("Failed to receive ACK for OACK: {}", e);
+                        watch.fail(format!("no ACK for OACK: {}", e));
                         return Ok(());
                     }
                 }
@@ -1518,60 +2821,301 @@ impl TftpServer {
                 &file_path,
                 start_time,
                 audit_enabled,
+                retry_policy,
+                max_bytes_per_sec,
+                ticket,
+                window_report,
+                watch,
             )
             .await
         }
     }
 
-    /// Send file data using buffered approach (for small NETASCII files)
-    /// RFC 7440: Supports windowsize for sending multiple blocks before ACK
+    /// Answer an RRQ for a directory
+    ///
+    /// With `directory_index` set, a listing capped at that many entries is
+    /// sent as the file content (tsize reflects the generated size);
+    /// otherwise the client gets an "Is a directory" error.
+    ///
+    /// NIST 800-53 Controls:
+    /// - AC-3: Access Enforcement (listing only when explicitly enabled)
+    /// - AU-2: Audit Events (index transfers are audited like file reads)
     #[allow(clippy::too_many_arguments)]
-    async fn send_file_data_buffered(
+    async fn serve_directory_index(
         socket: &UdpSocket,
-        file_data: &[u8],
-        block_size: usize,
-        windowsize: usize,
-        timeout: tokio::time::Duration,
         client_addr: SocketAddr,
-        file_path: &Path,
-        start_time: std::time::Instant,
+        dir_path: &Path,
+        mode: TransferMode,
+        options: &TftpOptions,
+        negotiated_options: OptionList,
+        directory_index: Option<usize>,
+        allow_block_rollover: bool,
         audit_enabled: bool,
+        start_time: std::time::Instant,
+        retry_policy: RetryPolicy,
+        ticket: TransferTicket,
+        window_report: WindowReport,
+        transfer_events: &TransferEvents,
     ) -> Result<()> {
-        if file_data.is_empty() {
-            // Send a single empty data block
-            let mut data_packet = BytesMut::with_capacity(4);
-            data_packet.put_u16(TftpOpcode::Data as u16);
-            data_packet.put_u16(1);
-
-            Self::send_with_retry(socket, &data_packet, timeout).await?;
-            Self::wait_for_ack(socket, 1, timeout).await?;
-
-            debug!("Transfer complete: empty file");
-
+        let Some(max_entries) = directory_index else {
             if audit_enabled {
-                let duration_ms = start_time.elapsed().as_millis() as u64;
-                AuditLogger::transfer_completed(
+                AuditLogger::read_denied(
                     client_addr,
-                    &file_path.display().to_string(),
-                    0,
-                    1,
-                    duration_ms,
+                    &dir_path.display().to_string(),
+                    "Is a directory",
                 );
             }
-            return Ok(());
-        }
-
-        let mut block_num: u16 = 1;
-        let mut offset = 0;
-
-        // RFC 7440: Sliding window transmission
-        // Send windowsize blocks, then wait for ACK of the last block
-        // RFC 1350: Track if we need to send empty final block for exact multiple
-        let mut eof_sent = false;
+            return Self::send_error_on_socket(
+                socket,
+                TftpErrorCode::AccessViolation,
+                "Is a directory",
+            )
+            .await;
+        };
 
-        while offset <= file_data.len() && !eof_sent {
-            let window_start_block = block_num;
-            let mut window_packets = Vec::with_capacity(windowsize);
+        let index = match build_directory_index(dir_path, max_entries).await {
+            Ok(index) => index,
+            Err(e) => {
+                warn!("Failed to list directory {}: {}", dir_path.display(), e);
+                if audit_enabled {
+                    AuditLogger::read_denied(
+                        client_addr,
+                        &dir_path.display().to_string(),
+                        "Directory not readable",
+                    );
+                }
+                return Self::send_error_on_socket(
+                    socket,
+                    TftpErrorCode::AccessViolation,
+                    "Access denied",
+                )
+                .await;
+            }
+        };
+
+        let (file_data, mode_str) = match mode {
+            TransferMode::Netascii => (TransferMode::convert_to_netascii(&index), "netascii"),
+            TransferMode::Octet => (index, "octet"),
+            TransferMode::Mail => (index, "mail"),
+        };
+
+        if audit_enabled {
+            AuditLogger::transfer_started(
+                client_addr,
+                &dir_path.display().to_string(),
+                file_data.len() as u64,
+                mode_str,
+                options.block_size,
+            );
+        }
+        let watch = transfer_events.start(
+            client_addr,
+            dir_path,
+            TransferDirection::Read,
+            Some(file_data.len() as u64),
+            start_time,
+        );
+
+        Self::send_buffered_content(
+            socket,
+            &file_data,
+            client_addr,
+            dir_path,
+            options,
+            negotiated_options,
+            allow_block_rollover,
+            audit_enabled,
+            start_time,
+            retry_policy,
+            ticket,
+            window_report,
+            watch,
+        )
+        .await
+    }
+
+    /// Send content already held in memory, including option negotiation
+    ///
+    /// RFC 2349: tsize is answered with the size of `file_data` as sent.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_buffered_content(
+        socket: &UdpSocket,
+        file_data: &[u8],
+        client_addr: SocketAddr,
+        file_path: &Path,
+        options: &TftpOptions,
+        mut negotiated_options: OptionList,
+        allow_block_rollover: bool,
+        audit_enabled: bool,
+        start_time: std::time::Instant,
+        retry_policy: RetryPolicy,
+        ticket: TransferTicket,
+        window_report: WindowReport,
+        watch: TransferWatch,
+    ) -> Result<()> {
+        let timeout = tokio::time::Duration::from_secs(options.timeout);
+
+        // RFC 2349: Update tsize with converted size
+        if negotiated_options.contains_key("tsize") {
+            negotiated_options.insert("tsize".to_string(), file_data.len().to_string());
+        }
+
+        if Self::reject_block_overflow(
+            socket,
+            client_addr,
+            file_path,
+            options,
+            &negotiated_options,
+            file_data.len() as u64,
+            allow_block_rollover,
+            audit_enabled,
+        )
+        .await?
+        {
+            watch.fail("block count exceeds the 16-bit block number");
+            return Ok(());
+        }
+
+        // RFC 2347: Send OACK if options were negotiated
+        if !negotiated_options.is_empty() {
+            debug!("Sending OACK with options: {:?}", negotiated_options);
+            let oack_packet = Self::build_oack_packet(&negotiated_options);
+            Self::send_with_retry(socket, &oack_packet, timeout).await?;
+            match Self::wait_for_ack(socket, 0, timeout, retry_policy).await {
+                Ok(()) => {}
+                Err(e) => {
+                    error!("Failed to receive ACK for OACK: {}", e);
+                    watch.fail(format!("no ACK for OACK: {}", e));
+                    return Ok(());
+                }
+            }
+        }
+
+        Self::send_file_data_buffered(
+            socket,
+            file_data,
+            options.block_size,
+            options.windowsize,
+            timeout,
+            client_addr,
+            file_path,
+            start_time,
+            audit_enabled,
+            retry_policy,
+            ticket,
+            window_report,
+            watch,
+        )
+        .await
+    }
+
+    /// Refuse a negotiated RRQ whose block count would wrap the 16-bit block number
+    ///
+    /// A client that negotiated options learns the size from the tsize probe and
+    /// may have picked a small blksize; without rollover support it would see
+    /// block 0 after 65535 and abort mid-transfer. RFC 2348 does not allow the
+    /// server to raise blksize on its own, so the request is rejected up front
+    /// with an Option Negotiation error (RFC 2347) instead.
+    ///
+    /// Returns true if the request was rejected.
+    ///
+    /// NIST 800-53 Controls:
+    /// - SI-10: Information Input Validation (validate negotiated options)
+    /// - AU-2: Audit Events (log denied transfers)
+    #[allow(clippy::too_many_arguments)]
+    async fn reject_block_overflow(
+        socket: &UdpSocket,
+        client_addr: SocketAddr,
+        file_path: &Path,
+        options: &TftpOptions,
+        negotiated_options: &OptionList,
+        transfer_size: u64,
+        allow_block_rollover: bool,
+        audit_enabled: bool,
+    ) -> Result<bool> {
+        if allow_block_rollover
+            || negotiated_options.is_empty()
+            || !options.exceeds_block_space(transfer_size)
+        {
+            return Ok(false);
+        }
+
+        let message = format!(
+            "File needs {} blocks at blksize {}, more than {} without rollover; request a larger blksize or enable block rollover",
+            options.block_count(transfer_size),
+            options.block_size,
+            MAX_BLOCK_NUMBER
+        );
+        warn!(
+            "Rejecting RRQ from {} for {}: {}",
+            client_addr,
+            file_path.display(),
+            message
+        );
+
+        if audit_enabled {
+            AuditLogger::read_denied(client_addr, &file_path.display().to_string(), &message);
+        }
+
+        Self::send_error_on_socket(socket, TftpErrorCode::OptionNegotiation, &message).await?;
+        Ok(true)
+    }
+
+    /// Send file data using buffered approach (for small NETASCII files)
+    /// RFC 7440: Supports windowsize for sending multiple blocks before ACK
+    #[allow(clippy::too_many_arguments)]
+    async fn send_file_data_buffered(
+        socket: &UdpSocket,
+        file_data: &[u8],
+        block_size: usize,
+        windowsize: usize,
+        timeout: tokio::time::Duration,
+        client_addr: SocketAddr,
+        file_path: &Path,
+        start_time: std::time::Instant,
+        audit_enabled: bool,
+        retry_policy: RetryPolicy,
+        ticket: TransferTicket,
+        mut window_report: WindowReport,
+        mut watch: TransferWatch,
+    ) -> Result<()> {
+        if file_data.is_empty() {
+            // Send a single empty data block
+            let mut data_packet = BytesMut::with_capacity(4);
+            data_packet.put_u16(TftpOpcode::Data as u16);
+            data_packet.put_u16(1);
+
+            Self::send_with_retry(socket, &data_packet, timeout).await?;
+            Self::wait_for_ack(socket, 1, timeout, retry_policy).await?;
+
+            debug!("Transfer complete: empty file");
+            ticket.complete();
+            watch.complete(0);
+
+            if audit_enabled {
+                let duration_ms = start_time.elapsed().as_millis() as u64;
+                AuditLogger::transfer_completed(
+                    client_addr,
+                    &file_path.display().to_string(),
+                    0,
+                    1,
+                    duration_ms,
+                );
+            }
+            return Ok(());
+        }
+
+        let mut block_num: u16 = 1;
+        let mut offset = 0;
+
+        // RFC 7440: Sliding window transmission
+        // Send windowsize blocks, then wait for ACK of the last block
+        // RFC 1350: Track if we need to send empty final block for exact multiple
+        let mut eof_sent = false;
+
+        while offset <= file_data.len() && !eof_sent {
+            let window_start_block = block_num;
+            let mut window_packets = Vec::with_capacity(windowsize);
             let mut blocks_in_window = 0;
             let mut temp_offset = offset;
             let mut temp_block_num = block_num;
@@ -1608,15 +3152,22 @@ impl TftpServer {
             let last_block_in_window = window_packets.last().unwrap().0;
 
             loop {
-                if retries >= MAX_RETRIES {
+                if retries >= retry_policy.max_retries {
                     error!(
                         "Max retries exceeded for window starting at block {} after {} attempts",
-                        window_start_block, MAX_RETRIES
+                        window_start_block, retry_policy.max_retries
                     );
+                    watch.fail("max retries exceeded");
                     return Ok(());
                 }
 
+                // Back off exponentially before each retransmission
+                if retries > 0 {
+                    tokio::time::sleep(retry_policy.backoff(retries)).await;
+                }
+
                 // Send all packets in window
+                window_report.window_sent(retries > 0);
                 for (_, packet, _) in &window_packets {
                     socket.send(packet).await?;
                 }
@@ -1662,6 +3213,8 @@ impl TftpServer {
                         blk_num,
                         file_data.len()
                     );
+                    ticket.complete();
+                    watch.complete(file_data.len() as u64);
                     if audit_enabled {
                         let duration_ms = start_time.elapsed().as_millis() as u64;
                         AuditLogger::transfer_completed(
@@ -1675,6 +3228,7 @@ impl TftpServer {
                     return Ok(());
                 }
             }
+            watch.progress(offset as u64);
         }
 
         Ok(())
@@ -1685,6 +3239,14 @@ impl TftpServer {
     #[allow(clippy::too_many_arguments)]
     /// Send file data using streaming approach (for large files and OCTET mode)
     /// RFC 7440: Supports windowsize for sending multiple blocks before ACK
+    ///
+    /// With `max_bytes_per_sec` set, each window (and each retransmission of
+    /// it) is held back until the transfer's token bucket allows it, before
+    /// the ACK timeout starts. The shared `max_bytes_per_sec_total` budget is
+    /// applied the same way through `ticket`, which lets critical windows
+    /// through without waiting when prioritization is enabled.
+    ///
+    /// NIST SC-5: Denial of Service Protection (per-transfer bandwidth cap)
     #[allow(clippy::too_many_arguments)]
     async fn send_file_data_streaming(
         socket: &UdpSocket,
@@ -1698,6 +3260,11 @@ impl TftpServer {
         file_path: &Path,
         start_time: std::time::Instant,
         audit_enabled: bool,
+        retry_policy: RetryPolicy,
+        max_bytes_per_sec: Option<u64>,
+        ticket: TransferTicket,
+        mut window_report: WindowReport,
+        mut watch: TransferWatch,
     ) -> Result<()> {
         if file_size == 0 {
             // Send a single empty data block
@@ -1706,9 +3273,11 @@ impl TftpServer {
             data_packet.put_u16(1);
 
             Self::send_with_retry(socket, &data_packet, timeout).await?;
-            Self::wait_for_ack(socket, 1, timeout).await?;
+            Self::wait_for_ack(socket, 1, timeout, retry_policy).await?;
 
             debug!("Transfer complete: empty file (streaming mode)");
+            ticket.complete();
+            watch.complete(0);
 
             if audit_enabled {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
@@ -1726,8 +3295,12 @@ impl TftpServer {
         let mut block_num: u16 = 1;
         let mut bytes_transferred: u64 = 0;
         let mut read_buffer = vec![0u8; block_size];
-        let mut netascii_buffer = Vec::new();
+        let mut netascii = NetasciiBlocks::new(block_size);
         let mut eof_reached = false;
+        // Credit is capped at one window so idle time waiting for ACKs
+        // cannot be spent as a burst later
+        let mut throttle = max_bytes_per_sec
+            .map(|rate| TokenBucket::new(rate, (block_size * windowsize) as u64));
 
         // RFC 7440: Sliding window transmission for streaming
         loop {
@@ -1737,28 +3310,18 @@ impl TftpServer {
 
             // Build a window of packets by reading from file
             while blocks_in_window < windowsize && !eof_reached {
-                let bytes_read = file.read(&mut read_buffer).await?;
-
-                // RFC 1350: When file size is exact multiple of block size,
-                // must send final empty DATA packet to signal EOF
-                let is_final = bytes_read < block_size;
-
                 // Determine block data based on mode
-                let block_data = if bytes_read > 0 {
-                    if mode == TransferMode::Netascii {
-                        netascii_buffer.clear();
-                        netascii_buffer.extend_from_slice(
-                            TransferMode::convert_to_netascii(&read_buffer[..bytes_read]).as_slice(),
-                        );
-                        netascii_buffer.clone()
-                    } else {
-                        read_buffer[..bytes_read].to_vec()
-                    }
+                let block_data = if mode == TransferMode::Netascii {
+                    netascii.next_block(&mut file).await?
                 } else {
-                    // Empty block for EOF signaling
-                    Vec::new()
+                    let bytes_read = file.read(&mut read_buffer).await?;
+                    read_buffer[..bytes_read].to_vec()
                 };
 
+                // RFC 1350: When file size is exact multiple of block size,
+                // must send final empty DATA packet to signal EOF
+                let is_final = block_data.len() < block_size;
+
                 let mut data_packet = BytesMut::with_capacity(4 + block_data.len());
                 data_packet.put_u16(TftpOpcode::Data as u16);
                 data_packet.put_u16(block_num);
@@ -1783,17 +3346,35 @@ impl TftpServer {
             // Send all blocks in the window with retry
             let mut retries = 0;
             let last_block_in_window = window_packets.last().unwrap().0;
+            let window_bytes: u64 = window_packets
+                .iter()
+                .map(|(_, _, bytes, _)| *bytes as u64)
+                .sum();
 
             loop {
-                if retries >= MAX_RETRIES {
+                if retries >= retry_policy.max_retries {
                     error!(
                         "Max retries exceeded for window starting at block {} after {} attempts",
-                        window_start_block, MAX_RETRIES
+                        window_start_block, retry_policy.max_retries
                     );
+                    watch.fail("max retries exceeded");
                     return Ok(());
                 }
 
+                // Back off exponentially before each retransmission
+                if retries > 0 {
+                    tokio::time::sleep(retry_policy.backoff(retries)).await;
+                }
+
+                // Pace before sending so the wait never counts against the
+                // ACK timeout below
+                if let Some(throttle) = throttle.as_mut() {
+                    throttle.acquire(window_bytes).await;
+                }
+                ticket.pace(window_bytes).await;
+
                 // Send all packets in window
+                window_report.window_sent(retries > 0);
                 for (_, packet, _, _) in &window_packets {
                     socket.send(packet).await?;
                 }
@@ -1836,6 +3417,8 @@ impl TftpServer {
                         "Transfer complete: {} blocks sent ({} bytes, streaming mode)",
                         blk_num, bytes_transferred
                     );
+                    ticket.complete();
+                    watch.complete(bytes_transferred);
                     if audit_enabled {
                         let duration_ms = start_time.elapsed().as_millis() as u64;
                         AuditLogger::transfer_completed(
@@ -1849,6 +3432,7 @@ impl TftpServer {
                     return Ok(());
                 }
             }
+            watch.progress(bytes_transferred);
 
             if eof_reached {
                 break;
@@ -1869,23 +3453,22 @@ impl TftpServer {
     async fn handle_write_request(
         file_path: PathBuf,
         client_addr: SocketAddr,
+        transfer_ip: Option<IpAddr>,
         mode: TransferMode,
         options: TftpOptions,
-        negotiated_options: HashMap<String, String>,
+        negotiated_options: OptionList,
         max_file_size_bytes: u64,
         file_created: bool,
         audit_enabled: bool,
+        retry_policy: RetryPolicy,
+        tsize_mismatch: TsizeMismatchPolicy,
+        temp_files: &Arc<TempFileRegistry>,
+        transfer_events: &TransferEvents,
     ) -> Result<()> {
         let start_time = std::time::Instant::now();
 
         // RFC 1350: Each transfer connection uses a new TID (Transfer ID)
-        // Use IPv6 unspecified if client is IPv6, IPv4 otherwise (dual-stack support)
-        let bind_addr = if client_addr.is_ipv6() {
-            SocketAddr::new(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), 0)
-        } else {
-            SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0)
-        };
-        let socket = create_transfer_socket(bind_addr)?;
+        let socket = create_transfer_socket(transfer_bind_addr(transfer_ip, client_addr))?;
         socket.connect(client_addr).await?;
 
         // Audit log: Write started
@@ -1902,193 +3485,93 @@ impl TftpServer {
                 options.block_size,
             );
         }
+        let mut watch = transfer_events.start(
+            client_addr,
+            &file_path,
+            TransferDirection::Write,
+            options.transfer_size,
+            start_time,
+        );
 
         let block_size = options.block_size;
         let windowsize = options.windowsize;
         let timeout = tokio::time::Duration::from_secs(options.timeout);
 
-        // RFC 2347: Send OACK if options were negotiated, or ACK block 0 to begin transfer
-        if !negotiated_options.is_empty() {
+        // RFC 2347: Start with OACK if options were negotiated, or ACK block 0
+        let initial_packet = if !negotiated_options.is_empty() {
             debug!("Sending OACK with options: {:?}", negotiated_options);
-
-            let oack_packet = Self::build_oack_packet(&negotiated_options);
-            Self::send_with_retry(&socket, &oack_packet, timeout).await?;
-        } else {
-            // No options - send ACK of block 0 to signal ready to receive
-            let mut ack_packet = BytesMut::with_capacity(4);
-            ack_packet.put_u16(TftpOpcode::Ack as u16);
-            ack_packet.put_u16(0);
-            Self::send_with_retry(&socket, &ack_packet, timeout).await?;
-        }
-
-        // Receive file data blocks
-        // Performance optimization: Pre-allocate buffer with expected size if available
-        let mut received_data = if let Some(expected_size) = options.transfer_size {
-            Vec::with_capacity(expected_size as usize)
+            Self::build_oack_packet(&negotiated_options)
         } else {
-            // Default pre-allocation for 1MB
-            Vec::with_capacity(1_048_576)
+            ack_packet(0).to_vec()
         };
-        let mut expected_block: u16 = 1;
-        let mut buf = vec![0u8; MAX_PACKET_SIZE];
-
-        loop {
-            // Wait for DATA packet
-            match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
-                Ok(Ok(size)) => {
-                    if size < 4 {
-                        warn!("Received invalid DATA packet (too small)");
-                        continue;
-                    }
-
-                    let mut data_bytes = BytesMut::from(&buf[..size]);
-                    let opcode = data_bytes.get_u16();
-
-                    // Check for ERROR packet from client
-                    if opcode == TftpOpcode::Error as u16 {
-                        let error_code = data_bytes.get_u16();
-                        let error_msg = Self::parse_string(&mut data_bytes).unwrap_or_default();
-
-                        if audit_enabled {
-                            AuditLogger::write_failed(
-                                client_addr,
-                                &file_path.display().to_string(),
-                                &format!("Client sent error {}: {}", error_code, error_msg),
-                                expected_block.wrapping_sub(1),
-                            );
-                        }
-
-                        return Err(TftpError::Tftp(format!(
-                            "Client sent error {}: {}",
-                            error_code, error_msg
-                        )));
-                    }
-
-                    if opcode != TftpOpcode::Data as u16 {
-                        warn!("Expected DATA, got opcode {}", opcode);
-                        continue;
-                    }
-
-                    let block_num = data_bytes.get_u16();
-
-                    // Handle block number
-                    if block_num < expected_block {
-                        // Duplicate block - re-send ACK
-                        debug!("Received duplicate block {}", block_num);
-                        let mut ack_packet = BytesMut::with_capacity(4);
-                        ack_packet.put_u16(TftpOpcode::Ack as u16);
-                        ack_packet.put_u16(block_num);
-                        socket.send(&ack_packet).await?;
-                        continue;
-                    } else if block_num > expected_block {
-                        // Out of order - error
-                        warn!(
-                            "Block mismatch: expected {}, got {}",
-                            expected_block, block_num
-                        );
-                        Self::send_error_on_socket(
-                            &socket,
-                            TftpErrorCode::IllegalOperation,
-                            "Out of order block",
-                        )
-                        .await?;
-                        return Ok(());
-                    }
-
-                    // Get data from packet
-                    let block_data = &data_bytes[..];
-                    let data_len = block_data.len();
-
-                    // Security: Check cumulative file size
-                    // NIST SC-5: Denial of Service Protection
-                    if max_file_size_bytes > 0
-                        && (received_data.len() + data_len) > max_file_size_bytes as usize
-                    {
-                        error!(
-                            "Write exceeds maximum file size {} for {}",
-                            max_file_size_bytes,
-                            file_path.display()
-                        );
-
-                        if audit_enabled {
-                            AuditLogger::file_size_limit_exceeded(
-                                client_addr,
-                                &file_path.display().to_string(),
-                                (received_data.len() + data_len) as u64,
-                                max_file_size_bytes,
-                            );
-                        }
-
-                        Self::send_error_on_socket(
-                            &socket,
-                            TftpErrorCode::DiskFull,
-                            "File too large",
-                        )
-                        .await?;
-                        return Ok(());
-                    }
-
-                    // Append data to buffer
-                    received_data.extend_from_slice(block_data);
-
-                    // RFC 7440: Only send ACK when we've received:
-                    // 1. The last block in a window, OR
-                    // 2. The final block (< block_size)
-                    let is_final_block = data_len < block_size;
-                    let blocks_in_current_window = (block_num - 1) % windowsize as u16 + 1;
-                    let should_ack =
-                        blocks_in_current_window == windowsize as u16 || is_final_block;
 
-                    if should_ack {
-                        // Send ACK for the last block in window
-                        let mut ack_packet = BytesMut::with_capacity(4);
-                        ack_packet.put_u16(TftpOpcode::Ack as u16);
-                        ack_packet.put_u16(block_num);
-                        socket.send(&ack_packet).await?;
-
-                        debug!(
-                            "Received block {} (ACK sent, {} bytes, total: {} bytes)",
-                            block_num,
-                            data_len,
-                            received_data.len()
-                        );
-                    } else {
-                        debug!(
-                            "Received block {} (buffered in window, {} bytes, total: {} bytes)",
-                            block_num,
-                            data_len,
-                            received_data.len()
-                        );
-                    }
+        // RFC 7440: Windowed receive with gap detection and re-ACK of the
+        // last in-order block
+        let params = ReceiveParams {
+            block_size,
+            windowsize,
+            timeout,
+            max_retries: retry_policy.max_retries,
+            max_file_size: max_file_size_bytes,
+            size_hint: options.transfer_size,
+            resume_after: 0,
+        };
 
-                    // RFC 1350: Transfer complete when data packet < block_size
-                    if is_final_block {
-                        info!(
-                            "Write complete: {} blocks received ({} bytes)",
-                            block_num,
-                            received_data.len()
+        let (received_data, expected_block) =
+            match receive_windowed(&socket, &initial_packet, params).await {
+                Ok(received) => {
+                    info!(
+                        "Write complete: {} blocks received ({} bytes)",
+                        received.last_block,
+                        received.data.len()
+                    );
+                    (received.data, received.last_block)
+                }
+                Err(ReceiveError::ClientError { code, message }) => {
+                    watch.fail(format!("Client sent error {}: {}", code, message));
+                    if audit_enabled {
+                        AuditLogger::write_failed(
+                            client_addr,
+                            &file_path.display().to_string(),
+                            &format!("Client sent error {}: {}", code, message),
+                            0,
                         );
-                        break;
                     }
 
-                    expected_block = expected_block.wrapping_add(1);
+                    return Err(TftpError::Tftp(format!(
+                        "Client sent error {}: {}",
+                        code, message
+                    )));
                 }
-                Ok(Err(e)) => {
-                    error!("Error receiving DATA: {}", e);
+                Err(ReceiveError::FileTooLarge { size, last_block }) => {
+                    error!(
+                        "Write exceeds maximum file size {} for {}",
+                        max_file_size_bytes,
+                        file_path.display()
+                    );
 
                     if audit_enabled {
-                        AuditLogger::write_failed(
+                        AuditLogger::file_size_limit_exceeded(
                             client_addr,
                             &file_path.display().to_string(),
-                            &e.to_string(),
-                            expected_block.wrapping_sub(1),
+                            size,
+                            max_file_size_bytes,
                         );
                     }
+                    debug!("Rejected write after block {}", last_block);
+                    watch.progress(size);
+                    watch.fail("File too large");
 
-                    return Err(e.into());
+                    Self::send_error_on_socket(&socket, TftpErrorCode::DiskFull, "File too large")
+                        .await?;
+                    return Ok(());
                 }
-                Err(_) => {
-                    error!("Timeout waiting for DATA block {}", expected_block);
+                Err(ReceiveError::Timeout { expected_block }) => {
+                    watch.fail("timeout waiting for data");
+                    error!(
+                        "Timeout waiting for DATA block {} after {} retries",
+                        expected_block, retry_policy.max_retries
+                    );
 
                     if audit_enabled {
                         AuditLogger::write_failed(
@@ -2113,8 +3596,22 @@ impl TftpServer {
                         expected_block
                     )));
                 }
-            }
-        }
+                Err(ReceiveError::Io(e)) => {
+                    error!("Error receiving DATA: {}", e);
+                    watch.fail(e.to_string());
+
+                    if audit_enabled {
+                        AuditLogger::write_failed(
+                            client_addr,
+                            &file_path.display().to_string(),
+                            &e.to_string(),
+                            0,
+                        );
+                    }
+
+                    return Err(e.into());
+                }
+            };
 
         // Convert data if NETASCII mode
         let final_data = if mode == TransferMode::Netascii {
@@ -2123,6 +3620,7 @@ impl TftpServer {
         } else {
             received_data
         };
+        watch.progress(final_data.len() as u64);
 
         // RFC 2349: Validate transfer size if client specified expected size
         // Check if actual received size matches the tsize option (if provided and non-zero)
@@ -2149,8 +3647,18 @@ impl TftpServer {
                 );
             }
 
-            // Note: RFC 2349 doesn't specify error behavior for size mismatch
-            // We log a warning but still write the file since data was transferred successfully
+            // Note: RFC 2349 doesn't specify error behavior for size mismatch,
+            // so whether the data is kept is left to the operator
+            if tsize_mismatch == TsizeMismatchPolicy::Reject {
+                watch.fail("Transfer size mismatch");
+                Self::send_error_on_socket(
+                    &socket,
+                    TftpErrorCode::NotDefined,
+                    "Transfer size mismatch",
+                )
+                .await?;
+                return Ok(());
+            }
             debug!(
                 "Proceeding with write despite size mismatch (expected: {}, actual: {})",
                 expected_size,
@@ -2159,13 +3667,15 @@ impl TftpServer {
         }
 
         // Write file to disk
-        match Self::write_file_safely(&file_path, &final_data).await {
+        let declared_size = options.transfer_size.filter(|&size| size > 0);
+        match Self::write_file_safely(&file_path, &final_data, declared_size, temp_files).await {
             Ok(()) => {
                 debug!(
                     "File written successfully: {} ({} bytes)",
                     file_path.display(),
                     final_data.len()
                 );
+                watch.complete(final_data.len() as u64);
 
                 // Audit log: Write completed
                 if audit_enabled {
@@ -2182,6 +3692,7 @@ impl TftpServer {
             }
             Err(e) => {
                 error!("Failed to write file {}: {}", file_path.display(), e);
+                watch.fail(e.to_string());
 
                 if audit_enabled {
                     AuditLogger::write_failed(
@@ -2206,6 +3717,7 @@ impl TftpServer {
     /// RFC 1350 NETASCII Specification:
     /// - Lines are terminated with CR+LF (0x0D 0x0A)
     /// - Converts network standard (CR+LF) to Unix line endings (LF)
+    /// - CR+NUL (RFC 764) is a literal CR
     fn convert_from_netascii(data: &[u8]) -> Vec<u8> {
         let mut result = Vec::with_capacity(data.len());
         let mut i = 0;
@@ -2217,6 +3729,10 @@ impl TftpServer {
                 // CR+LF sequence - convert to LF
                 result.push(b'\n');
                 i += 2;
+            } else if byte == b'\r' && i + 1 < data.len() && data[i + 1] == b'\0' {
+                // CR+NUL sequence - literal CR
+                result.push(b'\r');
+                i += 2;
             } else if byte == b'\r' {
                 // Bare CR - convert to LF
                 result.push(b'\n');
@@ -2236,23 +3752,40 @@ impl TftpServer {
     /// NIST 800-53 Controls:
     /// - SI-7: Software, Firmware, and Information Integrity (atomic writes)
     /// - CM-5: Access Restrictions for Change (safe file modification)
-    async fn write_file_safely(file_path: &Path, data: &[u8]) -> Result<()> {
+    ///
+    /// `declared_size` (the client's tsize) is reserved up front so large
+    /// uploads are laid out contiguously. The temp file is registered in
+    /// `temp_files` while it is in use so the stale temp file sweep skips it.
+    async fn write_file_safely(
+        file_path: &Path,
+        data: &[u8],
+        declared_size: Option<u64>,
+        temp_files: &Arc<TempFileRegistry>,
+    ) -> Result<()> {
         // Create parent directory if needed
         if let Some(parent) = file_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
 
         // Write to temporary file first, then rename for atomicity
-        let temp_path = file_path.with_extension(".tftp-tmp");
+        let temp = temp_files.register(temp_files::temp_path(file_path));
 
         // Write data to temp file
-        let mut file = tokio::fs::File::create(&temp_path).await?;
-        file.write_all(data).await?;
+        let mut file = tokio::fs::File::create(temp.path()).await?;
+        if let Some(size) = declared_size
+            && let Err(e) = preallocate(&file, size)
+        {
+            debug!("Preallocating {} bytes failed: {}", size, e);
+        }
+        file.write_all(data).await?;
+        if declared_size.is_some_and(|size| size != data.len() as u64) {
+            file.set_len(data.len() as u64).await?;
+        }
         file.flush().await?;
         drop(file);
 
         // Atomic rename
-        tokio::fs::rename(&temp_path, file_path).await?;
+        tokio::fs::rename(temp.path(), file_path).await?;
 
         Ok(())
     }
@@ -2387,11 +3920,12 @@ impl TftpServer {
         socket: &UdpSocket,
         expected_block: u16,
         timeout: tokio::time::Duration,
+        retry_policy: RetryPolicy,
     ) -> Result<()> {
         // Performance optimization: ACK packets are exactly 4 bytes
         let mut ack_buf = [0u8; 16]; // Small buffer, ACKs are 4 bytes
 
-        for retry in 0..MAX_RETRIES {
+        for retry in 0..retry_policy.max_retries {
             match tokio::time::timeout(timeout, socket.recv(&mut ack_buf)).await {
                 Ok(Ok(size)) => {
                     if size < 4 {
@@ -2438,7 +3972,7 @@ impl TftpServer {
                     error!("Error receiving ACK: {}", e);
                 }
                 Err(_) => {
-                    if retry < MAX_RETRIES - 1 {
+                    if retry + 1 < retry_policy.max_retries {
                         debug!("Timeout waiting for ACK (retry {})", retry + 1);
                     }
                 }
@@ -2452,7 +3986,7 @@ impl TftpServer {
     }
 
     // Build OACK packet (RFC 2347)
-    fn build_oack_packet(options: &HashMap<String, String>) -> Vec<u8> {
+    fn build_oack_packet(options: &OptionList) -> Vec<u8> {
         let mut packet = BytesMut::new();
         packet.put_u16(TftpOpcode::Oack as u16);
 
@@ -2584,16 +4118,11 @@ impl TftpServer {
     // RFC 1350: Send ERROR packet
     async fn send_error(
         client_addr: SocketAddr,
+        transfer_ip: Option<IpAddr>,
         error_code: TftpErrorCode,
         message: &str,
     ) -> Result<()> {
-        // Use IPv6 unspecified if client is IPv6, IPv4 otherwise (dual-stack support)
-        let bind_addr = if client_addr.is_ipv6() {
-            SocketAddr::new(std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), 0)
-        } else {
-            SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), 0)
-        };
-        let socket = create_transfer_socket(bind_addr)?;
+        let socket = create_transfer_socket(transfer_bind_addr(transfer_ip, client_addr))?;
         socket.connect(client_addr).await?;
         Self::send_error_on_socket(&socket, error_code, message).await
     }
@@ -2623,14 +4152,20 @@ impl TftpServer {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    match &cli.command {
+        Some(Command::Bench(args)) => return run_bench_command(args).await,
+        Some(Command::Config(command)) => return run_config_command(command, &cli.config),
+        None => {}
+    }
+
     let mut config = if cli.config.exists() {
         load_config(&cli.config)?
     } else {
         TftpConfig::default()
     };
 
-    if let Some(root_dir) = cli.root_dir {
-        config.root_dir = root_dir;
+    if let Some(root_dir) = &cli.root_dir {
+        config.root_dir = root_dir.clone();
     }
     if let Some(bind_addr) = cli.bind {
         config.bind_addr = bind_addr;
@@ -2669,6 +4204,23 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // NIST 800-53 AU-6/AU-7: Audit review and report generation
+    if cli.sla_report {
+        let audit_log = cli
+            .audit_log
+            .or(config.logging.file)
+            .ok_or_else(|| TftpError::Tftp("--audit-log or logging.file is required".to_string()))?;
+        let report = SlaReport::from_audit_log(&audit_log, cli.since, cli.until)?;
+        if cli.json {
+            let json = serde_json::to_string_pretty(&report)
+                .map_err(|e| TftpError::Tftp(format!("Failed to serialize report: {}", e)))?;
+            println!("{}", json);
+        } else {
+            println!("{}", report);
+        }
+        return Ok(());
+    }
+
     if cli.create_root_dir {
         tokio::fs::create_dir_all(&config.root_dir).await?;
     }
@@ -2684,7 +4236,7 @@ async fn main() -> Result<()> {
     // Initialize logging with JSON support for SIEM integration
     // NIST 800-53 AU-9: Protection of Audit Information
     // NIST 800-53 AU-12: Audit Generation
-    let _log_guard = if let Some(ref log_file) = config.logging.file {
+    let (writer, _log_guard) = if let Some(ref log_file) = config.logging.file {
         let dir = match log_file.parent() {
             Some(path) => path,
             None => std::path::Path::new("."),
@@ -2695,42 +4247,26 @@ async fn main() -> Result<()> {
             .ok_or_else(|| TftpError::Tftp("logging.file must include a file name".to_string()))?;
         let file_appender = tracing_appender::rolling::never(dir, file_name);
         let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-
-        match config.logging.format {
-            LogFormat::Json => {
-                tracing_subscriber::fmt()
-                    .json()
-                    .with_env_filter(EnvFilter::new(config.logging.level.clone()))
-                    .with_writer(non_blocking)
-                    .init();
-            }
-            LogFormat::Text => {
-                tracing_subscriber::fmt()
-                    .with_env_filter(EnvFilter::new(config.logging.level.clone()))
-                    .with_writer(non_blocking)
-                    .init();
-            }
-        }
-
-        Some(guard)
+        (BoxMakeWriter::new(non_blocking), Some(guard))
     } else {
-        match config.logging.format {
-            LogFormat::Json => {
-                tracing_subscriber::fmt()
-                    .json()
-                    .with_env_filter(EnvFilter::new(config.logging.level.clone()))
-                    .init();
-            }
-            LogFormat::Text => {
-                tracing_subscriber::fmt()
-                    .with_env_filter(EnvFilter::new(config.logging.level.clone()))
-                    .init();
-            }
-        }
-
-        None
+        (BoxMakeWriter::new(std::io::stdout), None)
     };
 
+    // NIST 800-53 CM-3: The level is behind a reload layer so a config
+    // reload can change it
+    let (filter, log_filter) = reload::Layer::new(EnvFilter::new(&config.logging.level));
+    let subscriber = tracing_subscriber::registry().with(filter);
+    match config.logging.format {
+        LogFormat::Json => subscriber
+            .with(tracing_subscriber::fmt::layer().json().with_writer(writer))
+            .init(),
+        LogFormat::Text => subscriber
+            .with(tracing_subscriber::fmt::layer().with_writer(writer))
+            .init(),
+    }
+
+    AuditLogger::set_success_sample_rate(config.logging.success_sample_rate);
+
     // Audit log: Server startup
     if config.logging.audit_enabled {
         AuditLogger::server_started(
@@ -2750,5 +4286,1761 @@ async fn main() -> Result<()> {
         config_arc.clone(),
     )
     .with_multicast(config_arc.multicast.clone());
-    server.run().await
+
+    // NIST CM-8: Serve image files from the locations recorded in the database
+    let server = if config_arc.virtual_roots.enabled {
+        let database_url = config_arc
+            .virtual_roots
+            .database_url
+            .as_deref()
+            .unwrap_or_default();
+        let db = Database::new(database_url).await.map_err(|e| {
+            TftpError::Tftp(format!("Failed to connect to images database: {}", e))
+        })?;
+        server.with_virtual_roots(VirtualRoots::new(
+            &config_arc.virtual_roots.prefix,
+            Arc::new(DatabaseResolver::new(Arc::new(db))),
+            config_arc.virtual_roots.allowed_roots.clone(),
+        ))
+    } else {
+        server
+    };
+
+    // NIST AU-12: Record boot file transfers against active deployments
+    let server = if config_arc.deployment_tracking.enabled {
+        let database_url = config_arc
+            .deployment_tracking
+            .database_url
+            .as_deref()
+            .unwrap_or_default();
+        let db = Database::new(database_url).await.map_err(|e| {
+            TftpError::Tftp(format!("Failed to connect to deployments database: {}", e))
+        })?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(DEPLOYMENT_EVENT_QUEUE);
+        tokio::spawn(DeploymentTracker::new(Arc::new(db)).run(receiver));
+        server.with_transfer_events(sender)
+    } else {
+        server
+    };
+
+    // NIST CM-3: Apply config file changes on SIGHUP (and, with
+    // --watch-config, when the file changes) without dropping transfers
+    let reloader = server.reloader().with_log_filter(log_filter);
+    let root_override = cli.root_dir.clone();
+    let bind_override = cli.bind;
+    let overrides = move |config: &mut TftpConfig| {
+        if let Some(root_dir) = &root_override {
+            config.root_dir = root_dir.clone();
+        }
+        if let Some(bind_addr) = bind_override {
+            config.bind_addr = bind_addr;
+        }
+    };
+    #[cfg(unix)]
+    let reload_task = if cli.config.exists() {
+        Some(spawn_sighup_reload(
+            reloader.clone(),
+            cli.config.clone(),
+            overrides.clone(),
+        )?)
+    } else {
+        None
+    };
+    // NIST SI-4: SIGUSR1 logs multicast group membership and progress
+    #[cfg(unix)]
+    let dump_task = server
+        .multicast_server()
+        .map(spawn_sigusr1_session_dump)
+        .transpose()?;
+    let watch_task = if cli.watch_config && cli.config.exists() {
+        info!(
+            "Watching {} for changes every {}s",
+            cli.config.display(),
+            CONFIG_WATCH_INTERVAL.as_secs()
+        );
+        Some(spawn_config_watch(
+            reloader,
+            cli.config.clone(),
+            CONFIG_WATCH_INTERVAL,
+            overrides,
+        ))
+    } else {
+        None
+    };
+
+    let drain_timeout = config_arc.shutdown_drain_timeout();
+    let stop = CancellationToken::new();
+    let server_stop = stop.clone();
+    let mut server_task = tokio::spawn(async move { server.run_with_shutdown(server_stop).await });
+
+    tokio::select! {
+        result = &mut server_task => {
+            return result.map_err(|e| TftpError::Tftp(format!("Server task failed: {}", e)))?;
+        }
+        signal = shutdown_signal() => {
+            info!("{} received, draining in-flight transfers", signal);
+        }
+    }
+
+    // NIST 800-53 SC-24: Fail in Known State
+    // The server aborts transfers still running at the drain timeout; the
+    // phase allows a little longer for that to complete
+    let mut shutdown = ShutdownCoordinator::new("snow-owl-tftp");
+    shutdown.register(
+        "tftp",
+        drain_timeout + std::time::Duration::from_secs(5),
+        move || async move {
+            stop.cancel();
+            match server_task.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("TFTP server stopped with error: {}", e),
+                Err(e) => warn!("TFTP server task failed: {}", e),
+            }
+        },
+    );
+    #[cfg(unix)]
+    if let Some(reload_task) = reload_task {
+        reload_task.abort();
+    }
+    #[cfg(unix)]
+    if let Some(dump_task) = dump_task {
+        dump_task.abort();
+    }
+    if let Some(watch_task) = watch_task {
+        watch_task.abort();
+    }
+    shutdown.run("signal").await;
+
+    Ok(())
+}
+
+/// Wait for SIGINT or SIGTERM, returning the signal's name
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    result = tokio::signal::ctrl_c() => {
+                        if let Err(e) = result {
+                            warn!("Failed to listen for SIGINT: {}", e);
+                        }
+                        "SIGINT"
+                    }
+                    _ = terminate.recv() => "SIGTERM",
+                }
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                if let Err(e) = tokio::signal::ctrl_c().await {
+                    warn!("Failed to listen for SIGINT: {}", e);
+                }
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for shutdown signal: {}", e);
+        }
+        "Ctrl+C"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snow_owl_tftp::virtual_path::{PathResolver, ResolveFuture};
+    use snow_owl_tftp::{ErrorCode, TftpClient};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use tokio::time::{Duration, timeout};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!(
+            "snow_owl_tftp_server_test_{}_{}",
+            name,
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Start a server on a free port of `ip` serving `root_dir`
+    fn start_server(
+        ip: IpAddr,
+        root_dir: PathBuf,
+    ) -> (SocketAddr, tokio::task::JoinHandle<Result<()>>) {
+        start_server_with(ip, root_dir, |_| {})
+    }
+
+    /// Like `start_server`, with `configure` applied to the config first
+    fn start_server_with(
+        ip: IpAddr,
+        root_dir: PathBuf,
+        configure: impl FnOnce(&mut TftpConfig),
+    ) -> (SocketAddr, tokio::task::JoinHandle<Result<()>>) {
+        start_server_until(ip, root_dir, configure, CancellationToken::new())
+    }
+
+    /// Like `start_server_with`, serving until `shutdown` is cancelled
+    fn start_server_until(
+        ip: IpAddr,
+        root_dir: PathBuf,
+        configure: impl FnOnce(&mut TftpConfig),
+        shutdown: CancellationToken,
+    ) -> (SocketAddr, tokio::task::JoinHandle<Result<()>>) {
+        // Reserve a free port for the server's well-known socket
+        let port = std::net::UdpSocket::bind(SocketAddr::new(ip, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let bind_addr = SocketAddr::new(ip, port);
+
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            bind_addr,
+            ..TftpConfig::default()
+        };
+        config.logging.audit_enabled = false;
+        configure(&mut config);
+        let config = Arc::new(config);
+        let server = TftpServer::new(
+            root_dir,
+            bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            config.clone(),
+        );
+
+        (
+            bind_addr,
+            tokio::spawn(async move { server.run_with_shutdown(shutdown).await }),
+        )
+    }
+
+    /// Append null-terminated strings (filename, mode, option names and values)
+    fn put_strings(packet: &mut BytesMut, strings: &[&str]) {
+        for s in strings {
+            packet.put_slice(s.as_bytes());
+            packet.put_u8(0);
+        }
+    }
+
+    /// Send `request` until the server answers, returning the reply and its source
+    async fn request(
+        client: &UdpSocket,
+        server: SocketAddr,
+        request: &[u8],
+    ) -> (Vec<u8>, SocketAddr) {
+        // Retry until the server task has bound its socket
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        for _ in 0..50 {
+            client.send_to(request, server).await.unwrap();
+            if let Ok(result) = timeout(Duration::from_millis(100), client.recv_from(&mut buf)).await
+            {
+                let (len, from) = result.unwrap();
+                return (buf[..len].to_vec(), from);
+            }
+        }
+        panic!("server did not answer the request");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_in_flight_transfer() {
+        let root_dir = temp_dir("drain");
+        let content: Vec<u8> = (0..1300u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root_dir.join("boot.bin"), &content).unwrap();
+
+        let stop = CancellationToken::new();
+        let (server_addr, mut server_task) = start_server_until(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            root_dir,
+            |_| {},
+            stop.clone(),
+        );
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet"]);
+        let (mut packet, transfer_addr) = request(&client, server_addr, &rrq).await;
+
+        // Shut down after the first DATA block, before it is acknowledged
+        stop.cancel();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!server_task.is_finished());
+
+        // New requests are no longer answered
+        let late = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        late.send_to(&rrq, server_addr).await.unwrap();
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        assert!(
+            timeout(Duration::from_millis(300), late.recv_from(&mut buf))
+                .await
+                .is_err()
+        );
+
+        let mut received = Vec::new();
+        let mut expected_block = 1u16;
+        loop {
+            let mut data = &packet[..];
+            assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+            assert_eq!(data.get_u16(), expected_block);
+            received.extend_from_slice(data);
+
+            let mut ack = BytesMut::new();
+            ack.put_u16(TftpOpcode::Ack as u16);
+            ack.put_u16(expected_block);
+            client.send_to(&ack, transfer_addr).await.unwrap();
+
+            if data.len() < 512 {
+                break;
+            }
+            expected_block += 1;
+            let (n, _) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+                .await
+                .expect("timed out waiting for DATA")
+                .unwrap();
+            packet = buf[..n].to_vec();
+        }
+        assert_eq!(received, content);
+
+        // The server resolves once the transfer it was draining has finished
+        timeout(Duration::from_secs(5), &mut server_task)
+            .await
+            .expect("server did not stop after draining")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_request_over_ipv6_loopback() {
+        let root_dir = temp_dir("ipv6");
+        let content: Vec<u8> = (0..1300u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root_dir.join("boot.bin"), &content).unwrap();
+
+        let (server_addr, server_task) = start_server(IpAddr::V6(Ipv6Addr::LOCALHOST), root_dir);
+
+        let client = UdpSocket::bind("[::1]:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet"]);
+
+        let (mut packet, transfer_addr) = request(&client, server_addr, &rrq).await;
+
+        // RFC 1350: the reply comes from a new TID on the client's address family
+        assert!(transfer_addr.is_ipv6());
+        assert_ne!(transfer_addr.port(), server_addr.port());
+
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut received = Vec::new();
+        let mut expected_block = 1u16;
+        loop {
+            let mut data = &packet[..];
+            assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+            assert_eq!(data.get_u16(), expected_block);
+            received.extend_from_slice(data);
+
+            let mut ack = BytesMut::new();
+            ack.put_u16(TftpOpcode::Ack as u16);
+            ack.put_u16(expected_block);
+            client.send_to(&ack, transfer_addr).await.unwrap();
+
+            if data.len() < 512 {
+                break;
+            }
+            expected_block += 1;
+            let (n, from) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+                .await
+                .expect("timed out waiting for DATA")
+                .unwrap();
+            assert_eq!(from, transfer_addr);
+            packet = buf[..n].to_vec();
+        }
+
+        assert_eq!(received, content);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_error_reply_over_ipv6_loopback() {
+        let (server_addr, server_task) =
+            start_server(IpAddr::V6(Ipv6Addr::LOCALHOST), temp_dir("ipv6_error"));
+
+        let client = UdpSocket::bind("[::1]:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["missing.bin", "octet"]);
+
+        let (reply, from) = request(&client, server_addr, &rrq).await;
+
+        // The ERROR is sent from a socket in the client's address family
+        assert!(from.is_ipv6());
+        assert_eq!(&reply[..4], &[0, 5, 0, TftpErrorCode::FileNotFound as u8]);
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_directory_request_without_index_is_rejected() {
+        let root_dir = temp_dir("dir");
+        std::fs::create_dir_all(root_dir.join("configs")).unwrap();
+
+        let (server_addr, server_task) = start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir);
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["configs/", "octet"]);
+
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::AccessViolation as u16);
+        put_strings(&mut expected, &["Is a directory"]);
+        assert_eq!(reply, expected.to_vec());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_read_allowed_patterns() {
+        let root_dir = temp_dir("read_patterns");
+        std::fs::write(root_dir.join("grubx64.efi"), b"efi").unwrap();
+        std::fs::write(root_dir.join("secrets.txt"), b"secret").unwrap();
+
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.read_allowed_patterns = vec!["*.efi".to_string(), "boot/*".to_string()];
+            });
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["grubx64.efi", "octet"]);
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Data as u16);
+        expected.put_u16(1);
+        expected.put_slice(b"efi");
+        assert_eq!(reply, expected.to_vec());
+
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["secrets.txt", "octet"]);
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::AccessViolation as u16);
+        put_strings(&mut expected, &["File not allowed for reading"]);
+        assert_eq!(reply, expected.to_vec());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_allowed_transfer_modes_reject_netascii() {
+        let root_dir = temp_dir("transfer_modes");
+        std::fs::write(root_dir.join("boot.cfg"), b"cfg").unwrap();
+
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.allowed_transfer_modes = vec![AllowedTransferMode::Octet];
+            });
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.cfg", "netascii"]);
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::IllegalOperation as u16);
+        put_strings(&mut expected, &["Transfer mode not allowed"]);
+        assert_eq!(reply, expected.to_vec());
+
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.cfg", "OCTET"]);
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Data as u16);
+        expected.put_u16(1);
+        expected.put_slice(b"cfg");
+        assert_eq!(reply, expected.to_vec());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_wrq_with_tsize_over_limit_is_refused() {
+        let root_dir = temp_dir("tsize_cap");
+        let (server_addr, server_task) = start_server_with(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            root_dir.clone(),
+            |config| {
+                config.max_file_size_bytes = 1024;
+                config.write_config.enabled = true;
+                config.write_config.allowed_patterns = vec!["*.bin".to_string()];
+            },
+        );
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut wrq = BytesMut::new();
+        wrq.put_u16(TftpOpcode::Wrq as u16);
+        put_strings(&mut wrq, &["upload.bin", "octet", "tsize", "1025"]);
+        let (reply, _) = request(&client, server_addr, &wrq).await;
+
+        // Refused in place of the OACK, before any DATA is sent
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::DiskFull as u16);
+        put_strings(
+            &mut expected,
+            &["declared size 1025 exceeds limit of 1024 bytes"],
+        );
+        assert_eq!(reply, expected.to_vec());
+        assert!(!root_dir.join("upload.bin").exists());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_tsize_mismatch_reject_policy_discards_upload() {
+        let root_dir = temp_dir("tsize_reject");
+        let (server_addr, server_task) = start_server_with(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            root_dir.clone(),
+            |config| {
+                config.write_config.enabled = true;
+                config.write_config.allowed_patterns = vec!["*.bin".to_string()];
+                config.write_config.tsize_mismatch = TsizeMismatchPolicy::Reject;
+            },
+        );
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut wrq = BytesMut::new();
+        wrq.put_u16(TftpOpcode::Wrq as u16);
+        put_strings(&mut wrq, &["upload.bin", "octet", "tsize", "10"]);
+        let (oack, transfer_addr) = request(&client, server_addr, &wrq).await;
+        assert_eq!(
+            u16::from_be_bytes([oack[0], oack[1]]),
+            TftpOpcode::Oack as u16
+        );
+
+        // Four bytes instead of the ten declared
+        let mut data = BytesMut::new();
+        data.put_u16(TftpOpcode::Data as u16);
+        data.put_u16(1);
+        data.put_slice(b"boot");
+        client.send_to(&data, transfer_addr).await.unwrap();
+
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let reply = loop {
+            let (len, _) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+                .await
+                .expect("no ERROR for mismatched upload")
+                .unwrap();
+            if u16::from_be_bytes([buf[0], buf[1]]) != TftpOpcode::Ack as u16 {
+                break buf[..len].to_vec();
+            }
+        };
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::NotDefined as u16);
+        put_strings(&mut expected, &["Transfer size mismatch"]);
+        assert_eq!(reply, expected.to_vec());
+        assert!(!root_dir.join("upload.bin").exists());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_configured_retries() {
+        let root_dir = temp_dir("retries");
+        std::fs::write(root_dir.join("boot.bin"), vec![1u8; 100]).unwrap();
+
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.max_retries = 3;
+                config.retry_backoff_ms = 50;
+            });
+
+        // Black hole: acknowledge the OACK, then never ACK any DATA
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet", "timeout", "1"]);
+        let (oack, transfer_addr) = request(&client, server_addr, &rrq).await;
+        assert_eq!(u16::from_be_bytes([oack[0], oack[1]]), TftpOpcode::Oack as u16);
+
+        let mut ack = BytesMut::new();
+        ack.put_u16(TftpOpcode::Ack as u16);
+        ack.put_u16(0);
+        client.send_to(&ack, transfer_addr).await.unwrap();
+
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut attempts = Vec::new();
+        let started = tokio::time::Instant::now();
+        while let Ok(result) = timeout(Duration::from_secs(3), client.recv_from(&mut buf)).await {
+            let (len, _) = result.unwrap();
+            let mut data = &buf[..len];
+            assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+            assert_eq!(data.get_u16(), 1);
+            attempts.push(started.elapsed());
+        }
+
+        assert_eq!(attempts.len(), 3);
+        // 1s timeout per attempt plus 50ms, then 100ms of backoff
+        assert!(attempts[1] >= Duration::from_millis(1050));
+        assert!(attempts[2] - attempts[1] >= Duration::from_millis(1100));
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oack_preserves_request_order() {
+        let root_dir = temp_dir("oack");
+        std::fs::write(root_dir.join("boot.bin"), vec![7u8; 3000]).unwrap();
+
+        let (server_addr, server_task) = start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir);
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(
+            &mut rrq,
+            &["boot.bin", "octet", "blksize", "1024", "tsize", "0", "windowsize", "4"],
+        );
+
+        let (oack, _) = request(&client, server_addr, &rrq).await;
+
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Oack as u16);
+        put_strings(
+            &mut expected,
+            &["blksize", "1024", "tsize", "3000", "windowsize", "4"],
+        );
+        assert_eq!(oack, expected.to_vec());
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_rrq_overflowing_block_space_is_refused_before_data() {
+        // At blksize 8, 65535 * 8 bytes needs a final empty block 65536
+        let root_dir = temp_dir("block_overflow");
+        std::fs::write(root_dir.join("large.bin"), vec![3u8; 65535 * 8]).unwrap();
+        std::fs::write(root_dir.join("fits.bin"), vec![3u8; 65535 * 8 - 1]).unwrap();
+
+        let (server_addr, server_task) = start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir);
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(
+            &mut rrq,
+            &["large.bin", "octet", "blksize", "8", "tsize", "0"],
+        );
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+
+        // Refused in place of the OACK
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::OptionNegotiation as u16);
+        put_strings(
+            &mut expected,
+            &[
+                "File needs 65536 blocks at blksize 8, more than 65535 without rollover; request a larger blksize or enable block rollover",
+            ],
+        );
+        assert_eq!(reply, expected.to_vec());
+
+        // And no DATA follows
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        while let Ok(result) = timeout(Duration::from_millis(500), client.recv_from(&mut buf)).await
+        {
+            let (len, _) = result.unwrap();
+            assert_ne!(
+                u16::from_be_bytes([buf[0], buf[1]]),
+                TftpOpcode::Data as u16,
+                "DATA sent after the refusal: {:?}",
+                &buf[..len]
+            );
+        }
+
+        // One byte less fits in 65535 blocks and is negotiated as usual
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(
+            &mut rrq,
+            &["fits.bin", "octet", "blksize", "8", "tsize", "0"],
+        );
+        let (oack, _) = request(&client, server_addr, &rrq).await;
+        assert_eq!(
+            u16::from_be_bytes([oack[0], oack[1]]),
+            TftpOpcode::Oack as u16
+        );
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_lossy_client_is_granted_smaller_window() {
+        let root_dir = temp_dir("adaptive_window");
+        std::fs::write(root_dir.join("boot.bin"), vec![7u8; 4 * 512]).unwrap();
+
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.adaptive_windowsize.enabled = true;
+                config.adaptive_windowsize.retransmit_threshold = 1;
+            });
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet", "blksize", "512"]);
+        put_strings(&mut rrq, &["windowsize", "8", "timeout", "1"]);
+        let oack_with = |windowsize: &str| {
+            let mut oack = BytesMut::new();
+            oack.put_u16(TftpOpcode::Oack as u16);
+            put_strings(
+                &mut oack,
+                &["blksize", "512", "windowsize", windowsize, "timeout", "1"],
+            );
+            oack.to_vec()
+        };
+
+        let (oack, transfer_addr) = request(&client, server_addr, &rrq).await;
+        assert_eq!(oack, oack_with("8"));
+        let mut ack = BytesMut::new();
+        ack.put_u16(TftpOpcode::Ack as u16);
+        ack.put_u16(0);
+        client.send_to(&ack, transfer_addr).await.unwrap();
+
+        // Four full blocks and the empty final one fit in one window; lose
+        // its ACK once so the whole window is retransmitted
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        for attempt in 0..2 {
+            for expected_block in 1..=5u16 {
+                let (len, _) = timeout(Duration::from_secs(3), client.recv_from(&mut buf))
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let mut data = &buf[..len];
+                assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+                assert_eq!(data.get_u16(), expected_block, "attempt {}", attempt);
+            }
+        }
+        let mut ack = BytesMut::new();
+        ack.put_u16(TftpOpcode::Ack as u16);
+        ack.put_u16(5);
+        client.send_to(&ack, transfer_addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The next request from the same client is granted half the window
+        let (oack, _) = request(&client, server_addr, &rrq).await;
+        assert_eq!(oack, oack_with("4"));
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_cap_paces_windows_without_retransmits() {
+        let root_dir = temp_dir("throttle");
+        let content: Vec<u8> = (0..8292u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root_dir.join("boot.wim"), &content).unwrap();
+
+        // Each 4 KiB window waits ~1.4s for tokens, longer than the 1s ACK
+        // timeout, so pacing that ate into the timeout would retransmit
+        let rate = 3000u64;
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.max_bytes_per_sec = Some(rate);
+            });
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(
+            &mut rrq,
+            &["boot.wim", "octet", "blksize", "1024", "windowsize", "4", "timeout", "1"],
+        );
+        let started = tokio::time::Instant::now();
+        let (oack, transfer_addr) = request(&client, server_addr, &rrq).await;
+        assert_eq!(u16::from_be_bytes([oack[0], oack[1]]), TftpOpcode::Oack as u16);
+
+        let ack = |block: u16| {
+            let mut ack = BytesMut::new();
+            ack.put_u16(TftpOpcode::Ack as u16);
+            ack.put_u16(block);
+            ack
+        };
+        client.send_to(&ack(0), transfer_addr).await.unwrap();
+
+        // RFC 7440: acknowledge only the last block of each window
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut received = Vec::new();
+        let mut expected_block = 1u16;
+        loop {
+            let (n, _) = timeout(Duration::from_secs(3), client.recv_from(&mut buf))
+                .await
+                .expect("timed out waiting for DATA")
+                .unwrap();
+            let mut data = &buf[..n];
+            assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+            assert_eq!(data.get_u16(), expected_block, "block resent or skipped");
+            received.extend_from_slice(data);
+
+            let last = data.len() < 1024;
+            if last || expected_block.is_multiple_of(4) {
+                client.send_to(&ack(expected_block), transfer_addr).await.unwrap();
+            }
+            if last {
+                break;
+            }
+            expected_block += 1;
+        }
+        let elapsed = started.elapsed();
+
+        assert_eq!(received, content);
+        let floor = Duration::from_secs_f64(content.len() as f64 / rate as f64);
+        assert!(
+            elapsed >= floor.mul_f64(0.95),
+            "transfer took {:?}, expected at least {:?}",
+            elapsed,
+            floor
+        );
+
+        // Nothing further arrives once the final ACK is in
+        assert!(
+            timeout(Duration::from_millis(1500), client.recv_from(&mut buf))
+                .await
+                .is_err()
+        );
+
+        server_task.abort();
+    }
+    /// Time a critical download while bulk downloads saturate the shared budget
+    async fn critical_latency_under_bulk_load(prioritize: bool) -> Duration {
+        let root_dir = temp_dir("priority");
+        std::fs::write(root_dir.join("install.wim"), vec![0x5a; 1 << 20]).unwrap();
+        std::fs::write(root_dir.join("boot.sdi"), vec![0xa5; 4000]).unwrap();
+
+        let (server_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir, |config| {
+                config.max_bytes_per_sec_total = Some(50_000);
+                config.priority.enabled = prioritize;
+                config.priority.critical_patterns = vec!["boot.sdi".to_string()];
+            });
+
+        let download = |file: &str, windowsize: usize| snow_owl_tftp::bench::BenchConfig {
+            server: server_addr,
+            file: file.to_string(),
+            clients: 1,
+            block_size: 1428,
+            windowsize,
+            ramp_up: Duration::ZERO,
+            duration: Duration::ZERO,
+            timeout: Duration::from_secs(5),
+            max_retries: 5,
+        };
+
+        // Three bulk transfers keep the bucket up to three 22 KB windows in
+        // debt; staying below adaptive_batch_threshold keeps the listener
+        // on plain recv_from
+        let bulk = download("install.wim", 16);
+        let bulk_tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let bulk = bulk.clone();
+                tokio::spawn(async move { snow_owl_tftp::bench::download(&bulk).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let started = tokio::time::Instant::now();
+        let critical = snow_owl_tftp::bench::download(&download("boot.sdi", 4))
+            .await
+            .expect("critical download failed");
+        let elapsed = started.elapsed();
+        assert_eq!(critical.bytes, 4000);
+
+        for task in bulk_tasks {
+            task.abort();
+        }
+        server_task.abort();
+        elapsed
+    }
+
+    #[tokio::test]
+    async fn test_critical_transfer_not_starved_by_bulk_pacing() {
+        let prioritized = critical_latency_under_bulk_load(true).await;
+        assert!(
+            prioritized < Duration::from_millis(250),
+            "critical transfer took {:?} with prioritization",
+            prioritized
+        );
+
+        // Without prioritization the same request queues behind bulk debt
+        let inverted = critical_latency_under_bulk_load(false).await;
+        assert!(
+            inverted > Duration::from_millis(500),
+            "critical transfer took only {:?} without prioritization",
+            inverted
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sighup_reload_applies_to_new_requests_only() {
+        let root_dir = temp_dir("reload");
+        let content: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(root_dir.join("boot.bin"), &content).unwrap();
+        let config_dir = temp_dir("reload_config");
+        let config_path = config_dir.join("tftp.toml");
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            ..TftpConfig::default()
+        };
+        config.logging.audit_enabled = false;
+        config.logging.file = Some(config_dir.join("tftp.log"));
+        write_config(&config_path, &config).unwrap();
+
+        let server = TftpServer::new(
+            root_dir.clone(),
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        );
+        let reloader = server.reloader();
+        let reload_task =
+            spawn_sighup_reload(reloader.clone(), config_path.clone(), |_| {}).unwrap();
+        let server_task = tokio::spawn(async move { server.run().await });
+
+        // Start a transfer and hold it open after the first block
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet"]);
+        let (first, transfer_addr) = request(&client, config.bind_addr, &rrq).await;
+
+        // Deny boot.bin for new reads and reload
+        config.read_allowed_patterns = vec!["*.efi".to_string()];
+        write_config(&config_path, &config).unwrap();
+        // SAFETY: raise() only delivers a signal; the SIGHUP handler is installed
+        assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+        timeout(Duration::from_secs(2), async {
+            while reloader.settings.read().unwrap().read_allowed_patterns.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("config was not reloaded");
+
+        // A new request sees the new pattern
+        let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (reply, _) = request(&other, config.bind_addr, &rrq).await;
+        let mut expected = BytesMut::new();
+        expected.put_u16(TftpOpcode::Error as u16);
+        expected.put_u16(TftpErrorCode::AccessViolation as u16);
+        put_strings(&mut expected, &["File not allowed for reading"]);
+        assert_eq!(reply, expected.to_vec());
+
+        // The transfer already in progress still completes
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut packet = first;
+        let mut received = Vec::new();
+        let mut expected_block = 1u16;
+        loop {
+            let mut data = &packet[..];
+            assert_eq!(data.get_u16(), TftpOpcode::Data as u16);
+            assert_eq!(data.get_u16(), expected_block);
+            received.extend_from_slice(data);
+
+            let mut ack = BytesMut::new();
+            ack.put_u16(TftpOpcode::Ack as u16);
+            ack.put_u16(expected_block);
+            client.send_to(&ack, transfer_addr).await.unwrap();
+
+            if data.len() < 512 {
+                break;
+            }
+            expected_block += 1;
+            let (n, _) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+                .await
+                .expect("in-flight transfer was dropped")
+                .unwrap();
+            packet = buf[..n].to_vec();
+        }
+        assert_eq!(received, content);
+
+        reload_task.abort();
+        server_task.abort();
+    }
+
+    #[test]
+    fn test_reload_rejects_bind_addr_change() {
+        let root_dir = temp_dir("reload_bind");
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            ..TftpConfig::default()
+        };
+        config.logging.file = None;
+        let server = TftpServer::new(
+            root_dir,
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        );
+        let reloader = server.reloader();
+
+        let mut changed = config.clone();
+        changed.bind_addr = SocketAddr::new(config.bind_addr.ip(), config.bind_addr.port() + 1);
+        changed.max_file_size_bytes = 1;
+
+        assert!(reloader.apply(&changed).is_err());
+        assert_eq!(
+            server.settings().max_file_size_bytes,
+            config.max_file_size_bytes
+        );
+        assert!(reloader.apply(&config).is_ok());
+    }
+
+    #[test]
+    fn test_reload_rejects_root_dir_change() {
+        let root_dir = temp_dir("reload_root");
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            ..TftpConfig::default()
+        };
+        config.logging.file = None;
+        let server = TftpServer::new(
+            root_dir,
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        );
+        let reloader = server.reloader();
+
+        let mut changed = config.clone();
+        changed.root_dir = temp_dir("reload_root_other");
+        changed.max_file_size_bytes = 1;
+
+        let err = reloader.apply(&changed).unwrap_err();
+        assert!(err.to_string().contains("root_dir change"), "{}", err);
+        assert_eq!(server.settings().root_dir, config.root_dir);
+        assert_eq!(
+            server.settings().max_file_size_bytes,
+            config.max_file_size_bytes
+        );
+    }
+
+    #[test]
+    fn test_reload_applies_log_level_and_rate_limit() {
+        let root_dir = temp_dir("reload_level");
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            ..TftpConfig::default()
+        };
+        config.logging.file = None;
+        let server = TftpServer::new(
+            root_dir,
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        );
+        let (_filter, handle) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let reloader = server.reloader().with_log_filter(handle.clone());
+
+        config.logging.level = "debug".to_string();
+        config.max_bytes_per_sec = Some(1_000_000);
+        reloader.apply(&config).unwrap();
+
+        assert_eq!(handle.with_current(|f| f.to_string()).unwrap(), "debug");
+        assert_eq!(server.settings().max_bytes_per_sec, Some(1_000_000));
+    }
+
+    /// Server on a free loopback port whose config is also written to a file
+    fn reloadable_server(name: &str) -> (TftpConfig, PathBuf, TftpServer) {
+        let root_dir = temp_dir(name);
+        let config_dir = temp_dir(&format!("{}_config", name));
+        let config_path = config_dir.join("tftp.toml");
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            ..TftpConfig::default()
+        };
+        config.logging.audit_enabled = false;
+        config.logging.file = Some(config_dir.join("tftp.log"));
+        write_config(&config_path, &config).unwrap();
+
+        let server = TftpServer::new(
+            root_dir,
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        );
+        (config, config_path, server)
+    }
+
+    #[tokio::test]
+    async fn test_reload_enables_writes() {
+        let (mut config, config_path, server) = reloadable_server("reload_writes");
+        let reloader = server.reloader();
+        let server_task = tokio::spawn(async move { server.run().await });
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut wrq = BytesMut::new();
+        wrq.put_u16(TftpOpcode::Wrq as u16);
+        put_strings(&mut wrq, &["upload.bin", "octet"]);
+        let (reply, _) = request(&client, config.bind_addr, &wrq).await;
+        let mut refused = BytesMut::new();
+        refused.put_u16(TftpOpcode::Error as u16);
+        refused.put_u16(TftpErrorCode::AccessViolation as u16);
+        put_strings(&mut refused, &["Write not supported"]);
+        assert_eq!(reply, refused.to_vec());
+
+        config.write_config.enabled = true;
+        config.write_config.allowed_patterns = vec!["*.bin".to_string()];
+        write_config(&config_path, &config).unwrap();
+        reload_config(&reloader, &config_path, &|_| {}).unwrap();
+
+        // Accepted with ACK 0 (no options requested)
+        let (reply, _) = request(&client, config.bind_addr, &wrq).await;
+        assert_eq!(reply, [0, TftpOpcode::Ack as u8, 0, 0]);
+
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_config_watch_reloads_changed_file() {
+        let (mut config, config_path, server) = reloadable_server("reload_watch");
+        let watch = spawn_config_watch(
+            server.reloader(),
+            config_path.clone(),
+            Duration::from_millis(20),
+            |_| {},
+        );
+
+        config.max_file_size_bytes = 4096;
+        write_config(&config_path, &config).unwrap();
+        timeout(Duration::from_secs(2), async {
+            while server.settings().max_file_size_bytes != 4096 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("changed config file was not reloaded");
+
+        watch.abort();
+    }
+
+    /// NETASCII encoding of `data` converted in two reads split at `at`
+    fn netascii_in_two_reads(data: &[u8], at: usize) -> Vec<u8> {
+        let mut encoded = TransferMode::convert_to_netascii(&data[..at]);
+        encoded.extend_from_slice(&TransferMode::convert_to_netascii(&data[at..]));
+        encoded
+    }
+
+    #[test]
+    fn test_netascii_cr_lf_straddling_reads() {
+        // CR is the last byte of a 4096-byte read and LF the first of the next
+        let mut data = vec![b'a'; 4095];
+        data.extend_from_slice(b"\r\nnext line\n");
+
+        let encoded = netascii_in_two_reads(&data, 4096);
+        assert_eq!(encoded, TransferMode::convert_to_netascii(&data));
+        assert_eq!(&encoded[4095..4099], b"\r\0\r\n");
+        assert!(!encoded.windows(2).any(|pair| pair == b"\r\r"));
+        assert_eq!(TftpServer::convert_from_netascii(&encoded), data);
+    }
+
+    #[test]
+    fn test_netascii_bare_cr_and_lf_at_read_boundary() {
+        for (tail, head) in [(b'\r', b'x'), (b'x', b'\n'), (b'\r', b'\r'), (b'\n', b'\n')] {
+            let mut data = vec![b'a'; 511];
+            data.push(tail);
+            data.push(head);
+            data.extend_from_slice(b"end");
+
+            let encoded = netascii_in_two_reads(&data, 512);
+            assert_eq!(encoded, TransferMode::convert_to_netascii(&data));
+            assert_eq!(TftpServer::convert_from_netascii(&encoded), data);
+        }
+    }
+
+    #[test]
+    fn test_netascii_round_trips_arbitrary_bytes() {
+        let mut data: Vec<u8> = (0..=255).collect();
+        // Dense runs of CR, LF and NUL in every order
+        let mut state: u32 = 0x2545_f491;
+        for _ in 0..8192 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            data.push([b'\r', b'\n', b'\0', b'x'][(state % 4) as usize]);
+        }
+
+        let encoded = TransferMode::convert_to_netascii(&data);
+        assert_eq!(TftpServer::convert_from_netascii(&encoded), data);
+        for at in [1, 255, 256, 4096, data.len() - 1] {
+            assert_eq!(netascii_in_two_reads(&data, at), encoded);
+        }
+    }
+
+    /// Every NETASCII block cut from `data`, up to and including the short last one
+    async fn netascii_blocks(data: &[u8], block_size: usize) -> Vec<Vec<u8>> {
+        let mut reader = data;
+        let mut blocks = NetasciiBlocks::new(block_size);
+        let mut out = Vec::new();
+        loop {
+            let block = blocks.next_block(&mut reader).await.unwrap();
+            let last = block.len() < block_size;
+            out.push(block);
+            if last {
+                return out;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_netascii_blocks_straddle_reads_and_round_trip() {
+        for block_size in [8, 512, 1428] {
+            // Line ends and bare CRs land on every offset of a block,
+            // including the last byte of a read
+            let mut data = Vec::new();
+            for line in 0..3 * block_size {
+                data.extend(std::iter::repeat_n(b'x', line % (block_size + 3)));
+                data.extend_from_slice(if line % 3 == 0 { b"\r" } else { b"\n" });
+            }
+
+            let blocks = netascii_blocks(&data, block_size).await;
+            let (last, full) = blocks.split_last().unwrap();
+            assert!(full.iter().all(|block| block.len() == block_size));
+            assert!(last.len() < block_size);
+
+            let encoded = blocks.concat();
+            assert_eq!(encoded, TransferMode::convert_to_netascii(&data));
+            let decoded = TftpServer::convert_from_netascii(&encoded);
+            assert_eq!(decoded, data, "block size {}", block_size);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_netascii_blocks_exact_multiple_ends_with_empty_block() {
+        // Seven bytes and a newline encode to exactly two 8-byte blocks
+        let blocks = netascii_blocks(b"abcdefg\n", 8).await;
+        assert_eq!(blocks, [b"abcdefg\r".to_vec(), b"\n".to_vec()]);
+
+        let blocks = netascii_blocks(b"abcdef\nabcdef\n", 8).await;
+        assert_eq!(
+            blocks,
+            [b"abcdef\r\n".to_vec(), b"abcdef\r\n".to_vec(), Vec::new()]
+        );
+
+        let blocks = netascii_blocks(b"", 8).await;
+        assert_eq!(blocks, [Vec::<u8>::new()]);
+    }
+
+    #[test]
+    fn test_pending_read_blocks_duplicates_until_dropped() {
+        let reads = PendingReads::default();
+        let client: SocketAddr = "10.0.0.5:2000".parse().unwrap();
+
+        let first = reads.begin(client, "boot.wim").unwrap();
+        assert!(reads.begin(client, "boot.wim").is_none());
+        // Other files and other clients are independent
+        let other_file = reads.begin(client, "boot.sdi").unwrap();
+        let other_client = reads.begin("10.0.0.6:2000".parse().unwrap(), "boot.wim");
+        assert!(other_client.is_some());
+
+        drop(first);
+        assert!(reads.begin(client, "boot.wim").is_some());
+        drop(other_file);
+    }
+
+    /// Resolver that answers after a delay, like a slow database lookup
+    struct SlowResolver {
+        file: PathBuf,
+        lookups: AtomicUsize,
+    }
+
+    impl PathResolver for SlowResolver {
+        fn resolve<'a>(&'a self, _image: &'a str, _rest: &'a str) -> ResolveFuture<'a> {
+            Box::pin(async move {
+                self.lookups.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok(Some(self.file.clone()))
+            })
+        }
+    }
+
+    // Several transfers push the listener into blocking batch receives,
+    // which would starve a single-threaded test runtime
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_retransmitted_rrq_starts_one_transfer() {
+        let root_dir = temp_dir("dup_rrq").canonicalize().unwrap();
+        std::fs::write(root_dir.join("boot.wim"), vec![7u8; 4096]).unwrap();
+        let resolver = Arc::new(SlowResolver {
+            file: root_dir.join("boot.wim"),
+            lookups: AtomicUsize::new(0),
+        });
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            ..TftpConfig::default()
+        };
+        config.logging.audit_enabled = false;
+        let server = TftpServer::new(
+            root_dir.clone(),
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        )
+        .with_virtual_roots(VirtualRoots::new(
+            "images",
+            resolver.clone(),
+            vec![root_dir.clone()],
+        ));
+        let server_task = tokio::spawn(async move { server.run().await });
+
+        // The first reply proves the server is up; its transfer is left to
+        // time out. It reads a plain file so it never reaches the resolver.
+        let mut probe = BytesMut::new();
+        probe.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut probe, &["boot.wim", "octet"]);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        request(&client, config.bind_addr, &probe).await;
+
+        // Original and retransmission arrive while the lookup is in progress
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["images/win11", "octet"]);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&rrq, config.bind_addr).await.unwrap();
+        client.send_to(&rrq, config.bind_addr).await.unwrap();
+
+        let mut sources = std::collections::HashSet::new();
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+        while let Ok(result) = tokio::time::timeout_at(deadline, client.recv_from(&mut buf)).await {
+            let (len, from) = result.unwrap();
+            assert_eq!(&buf[..2], &[0, TftpOpcode::Data as u8], "{:?}", &buf[..len]);
+            sources.insert(from);
+        }
+
+        assert_eq!(sources.len(), 1, "expected one transfer, got {:?}", sources);
+        assert_eq!(resolver.lookups.load(Ordering::Relaxed), 1);
+        server_task.abort();
+    }
+
+    async fn next_event(events: &mut tokio::sync::mpsc::Receiver<TransferEvent>) -> TransferEvent {
+        timeout(Duration::from_secs(2), events.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transfers_emit_events() {
+        let root_dir = temp_dir("transfer_events").canonicalize().unwrap();
+        std::fs::write(root_dir.join("boot.wim"), vec![7u8; 100]).unwrap();
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut config = TftpConfig {
+            root_dir: root_dir.clone(),
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            ..TftpConfig::default()
+        };
+        config.logging.audit_enabled = false;
+        config.write_config.enabled = true;
+        config.write_config.allowed_patterns = vec!["*.log".to_string()];
+        let (sender, mut events) = tokio::sync::mpsc::channel(16);
+        let server = TftpServer::new(
+            root_dir.clone(),
+            config.bind_addr,
+            config.max_file_size_bytes,
+            config.write_config.clone(),
+            false,
+            Arc::new(config.clone()),
+        )
+        .with_transfer_events(sender);
+        let server_task = tokio::spawn(async move { server.run().await });
+
+        // Read: one short DATA block, acknowledged
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.wim", "octet"]);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (reply, from) = request(&client, config.bind_addr, &rrq).await;
+        assert_eq!(&reply[..4], &[0, TftpOpcode::Data as u8, 0, 1]);
+        assert_eq!(reply.len(), 4 + 100);
+        client.send_to(&[0, 4, 0, 1], from).await.unwrap();
+
+        let started = next_event(&mut events).await;
+        assert_eq!(started.direction(), TransferDirection::Read);
+        assert_eq!(started.path(), root_dir.join("boot.wim"));
+        assert!(matches!(
+            started,
+            TransferEvent::Started {
+                size: Some(100),
+                ..
+            }
+        ));
+        assert!(matches!(
+            next_event(&mut events).await,
+            TransferEvent::Completed { bytes: 100, .. }
+        ));
+
+        // Write: ACK 0, one short DATA block, final ACK
+        let mut wrq = BytesMut::new();
+        wrq.put_u16(TftpOpcode::Wrq as u16);
+        put_strings(&mut wrq, &["deploy.log", "octet"]);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (reply, from) = request(&client, config.bind_addr, &wrq).await;
+        assert_eq!(&reply[..4], &[0, TftpOpcode::Ack as u8, 0, 0]);
+        let mut data = vec![0, TftpOpcode::Data as u8, 0, 1];
+        data.extend_from_slice(b"done");
+        client.send_to(&data, from).await.unwrap();
+        let mut buf = [0u8; 16];
+        timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let started = next_event(&mut events).await;
+        assert_eq!(started.direction(), TransferDirection::Write);
+        assert!(matches!(started, TransferEvent::Started { size: None, .. }));
+        assert!(matches!(
+            next_event(&mut events).await,
+            TransferEvent::Completed { bytes: 4, .. }
+        ));
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_stray_ack_on_listening_port() {
+        let mut ack = BytesMut::new();
+        ack.put_u16(TftpOpcode::Ack as u16);
+        ack.put_u16(7);
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["missing.bin", "octet"]);
+
+        // Default: answered with Illegal Operation
+        let (server_addr, server_task) =
+            start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), temp_dir("stray_ack"));
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (reply, _) = request(&client, server_addr, &ack).await;
+        assert_eq!(
+            &reply[..4],
+            &[0, 5, 0, TftpErrorCode::IllegalOperation as u8]
+        );
+        server_task.abort();
+
+        // Configured to drop: no reply, requests are still served
+        let (server_addr, server_task) = start_server_with(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            temp_dir("stray_ack_drop"),
+            |config| config.drop_non_request_opcodes = true,
+        );
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (reply, _) = request(&client, server_addr, &rrq).await;
+        assert_eq!(&reply[..4], &[0, 5, 0, TftpErrorCode::FileNotFound as u8]);
+
+        client.send_to(&ack, server_addr).await.unwrap();
+        let mut buf = [0u8; 64];
+        assert!(
+            timeout(Duration::from_millis(300), client.recv_from(&mut buf))
+                .await
+                .is_err(),
+            "stray ACK must not be answered"
+        );
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_bench_self_test_reports_sane_numbers() {
+        let cli = Cli::parse_from([
+            "snow-owl-tftp",
+            "bench",
+            "--self-test",
+            "--self-test-size",
+            "65536",
+            "--clients",
+            "4",
+            "--ramp-up",
+            "0.2",
+            "--duration",
+            "1",
+        ]);
+        let Some(Command::Bench(args)) = cli.command else {
+            panic!("bench subcommand not parsed");
+        };
+        let config = args
+            .bench_config(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .unwrap();
+
+        let report = bench_self_test(config, args.self_test_size).await.unwrap();
+
+        assert!(report.transfers_completed > 0, "{}", report);
+        assert_eq!(report.transfers_failed, 0, "{}", report);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.bytes_transferred, report.transfers_completed * 65536);
+        // Loopback should manage far more than 100 KB/s even on a busy CI host
+        assert!(report.goodput_bps > 100_000.0, "{}", report);
+        assert!(report.p50_transfer_ms <= report.max_transfer_ms);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_replies_come_from_request_address() {
+        let secondary = Ipv4Addr::new(127, 0, 0, 2);
+        if std::net::UdpSocket::bind((secondary, 0)).is_err() {
+            eprintln!("skipping: no secondary loopback address {}", secondary);
+            return;
+        }
+        let root_dir = temp_dir("transfer_ip");
+        std::fs::write(root_dir.join("boot.bin"), b"boot").unwrap();
+        let mut rrq = BytesMut::new();
+        rrq.put_u16(TftpOpcode::Rrq as u16);
+        put_strings(&mut rrq, &["boot.bin", "octet"]);
+
+        // Derived from bind_addr: a wildcard transfer socket would answer a
+        // client on 127.0.0.1 from 127.0.0.1, not the address it asked
+        let (server_addr, server_task) = start_server(IpAddr::V4(secondary), root_dir.clone());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (data, transfer_addr) = request(&client, server_addr, &rrq).await;
+        assert_eq!(&data[..2], &(TftpOpcode::Data as u16).to_be_bytes());
+        assert_eq!(transfer_addr.ip(), IpAddr::V4(secondary));
+        server_task.abort();
+
+        // Explicit bind_transfer_ip on a wildcard listener
+        let (wildcard_addr, server_task) =
+            start_server_with(IpAddr::V4(Ipv4Addr::UNSPECIFIED), root_dir, |config| {
+                config.bind_transfer_ip = Some(IpAddr::V4(secondary));
+            });
+        let server_addr = SocketAddr::new(IpAddr::V4(secondary), wildcard_addr.port());
+        let (_, transfer_addr) = request(&client, server_addr, &rrq).await;
+        assert_eq!(transfer_addr.ip(), IpAddr::V4(secondary));
+        server_task.abort();
+
+        // Families the configured address cannot reach fall back to the wildcard
+        let v6_client = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 1069);
+        let mapped_client = SocketAddr::new(IpAddr::V6(secondary.to_ipv6_mapped()), 1069);
+        assert_eq!(
+            transfer_bind_addr(Some(IpAddr::V4(secondary)), v6_client).ip(),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        );
+        assert_eq!(
+            transfer_bind_addr(Some(IpAddr::V4(secondary)), mapped_client).ip(),
+            IpAddr::V6(secondary.to_ipv6_mapped())
+        );
+        assert_eq!(
+            transfer_bind_addr(None, mapped_client).ip(),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        );
+    }
+
+    /// Checksum for comparing transferred files
+    fn checksum(data: &[u8]) -> u64 {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// (blksize, windowsize) pairs the client negotiates with the server
+    const CLIENT_OPTIONS: [(usize, usize); 4] = [(512, 1), (1468, 4), (8192, 16), (65464, 2)];
+
+    // Concurrent transfers push the listener into blocking batch receives
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_client_downloads_with_negotiated_options() {
+        let root_dir = temp_dir("client_get");
+        let content: Vec<u8> = (0..300_001u32).map(|i| (i * 31 % 251) as u8).collect();
+        std::fs::write(root_dir.join("boot.wim"), &content).unwrap();
+        // A multiple of 1468, so that transfer ends with an empty block
+        std::fs::write(root_dir.join("even.bin"), vec![7u8; 1468 * 8]).unwrap();
+        let (server_addr, server_task) =
+            start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir.clone());
+
+        let mut transfers = tokio::task::JoinSet::new();
+        for (block_size, windowsize) in CLIENT_OPTIONS {
+            for file in ["boot.wim", "even.bin"] {
+                transfers.spawn(async move {
+                    let options = TftpOptions {
+                        block_size,
+                        timeout: 1,
+                        transfer_size: Some(0),
+                        windowsize,
+                    };
+                    let mut downloaded = Vec::new();
+                    let transfer = TftpClient::new(server_addr)
+                        .get(file, &mut downloaded, options)
+                        .await
+                        .unwrap();
+                    (file, block_size, windowsize, transfer, downloaded)
+                });
+            }
+        }
+
+        while let Some(result) = transfers.join_next().await {
+            let (file, block_size, windowsize, transfer, downloaded) = result.unwrap();
+            let expected = std::fs::read(root_dir.join(file)).unwrap();
+            assert_eq!(
+                checksum(&downloaded),
+                checksum(&expected),
+                "{} with blksize {} windowsize {}",
+                file,
+                block_size,
+                windowsize
+            );
+            assert_eq!(transfer.bytes, expected.len() as u64);
+            assert_eq!(transfer.options.block_size, block_size);
+            assert_eq!(transfer.options.windowsize, windowsize);
+            assert_eq!(transfer.options.transfer_size, Some(expected.len() as u64));
+        }
+        server_task.abort();
+    }
+
+    // Concurrent transfers push the listener into blocking batch receives
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_client_uploads_with_negotiated_options() {
+        let root_dir = temp_dir("client_put");
+        let (server_addr, server_task) = start_server_with(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            root_dir.clone(),
+            |config| {
+                config.write_config.enabled = true;
+                config.write_config.allowed_patterns = vec!["*.bin".to_string()];
+            },
+        );
+        let content: Vec<u8> = (0..200_003u32).map(|i| (i * 17 % 253) as u8).collect();
+
+        let mut transfers = tokio::task::JoinSet::new();
+        for (block_size, windowsize) in CLIENT_OPTIONS {
+            let content = content.clone();
+            transfers.spawn(async move {
+                let name = format!("upload_{}_{}.bin", block_size, windowsize);
+                let options = TftpOptions {
+                    block_size,
+                    timeout: 1,
+                    transfer_size: Some(content.len() as u64),
+                    windowsize,
+                };
+                let transfer = TftpClient::new(server_addr)
+                    .put(&name, content.as_slice(), options)
+                    .await
+                    .unwrap();
+                (name, transfer)
+            });
+        }
+
+        while let Some(result) = transfers.join_next().await {
+            let (name, transfer) = result.unwrap();
+            assert_eq!(transfer.bytes, content.len() as u64);
+
+            // The file is stored after the final ACK; give the server a moment
+            let path = root_dir.join(&name);
+            let mut uploaded = Vec::new();
+            for _ in 0..50 {
+                uploaded = std::fs::read(&path).unwrap_or_default();
+                if uploaded.len() == content.len() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            assert_eq!(checksum(&uploaded), checksum(&content), "{}", name);
+        }
+        server_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_client_reports_server_errors() {
+        let root_dir = temp_dir("client_errors");
+        let (server_addr, server_task) = start_server(IpAddr::V4(Ipv4Addr::LOCALHOST), root_dir);
+        let client = TftpClient::new(server_addr);
+        let options = TftpOptions {
+            timeout: 1,
+            ..TftpOptions::default()
+        };
+
+        let error = client
+            .get("missing.bin", Vec::new(), options.clone())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                TftpError::Peer {
+                    code: ErrorCode::FileNotFound,
+                    ..
+                }
+            ),
+            "{}",
+            error
+        );
+
+        // Uploads are disabled by default
+        let error = client
+            .put("upload.bin", &b"boot"[..], options)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                TftpError::Peer {
+                    code: ErrorCode::AccessViolation,
+                    ..
+                }
+            ),
+            "{}",
+            error
+        );
+        server_task.abort();
+    }
 }
@@ -0,0 +1,723 @@
+//! TFTP client
+//!
+//! Downloads (RRQ) and uploads (WRQ) against any RFC 1350 server, so CI can
+//! smoke-test our own server and migrations can pull files off other
+//! vendors' servers. Options that differ from the RFC 1350 defaults are
+//! requested: blksize (RFC 2348), timeout and tsize (RFC 2349) and
+//! windowsize (RFC 7440). The server's OACK is checked against the request
+//! before it is used, and a server that ignores options is served with the
+//! defaults.
+//!
+//! Data is passed through unchanged in every mode; netascii translation is
+//! left to the caller.
+//!
+//! NIST 800-53 Controls:
+//! - SC-5: Denial of Service Protection (bounded retransmissions)
+//! - SI-10: Information Input Validation (OACK values checked against the request)
+
+use crate::receive::{
+    DatagramSocket, ReceiveError, ReceiveParams, ReceiveSummary, ack_packet, receive_windowed_into,
+};
+use crate::{
+    DEFAULT_BLOCK_SIZE, DEFAULT_TIMEOUT_SECS, ErrorCode, MAX_BLOCK_SIZE, MAX_PACKET_SIZE,
+    MAX_RETRIES, Opcode, OptionList, Result, TftpError, TftpOptions, TransferMode,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UdpSocket;
+use tracing::debug;
+
+/// Smallest receive buffer requested for a transfer socket
+const MIN_RECV_BUFFER: usize = 512 * 1024;
+
+/// Completed client transfer
+#[derive(Debug, Clone)]
+pub struct ClientTransfer {
+    /// Bytes downloaded or uploaded
+    pub bytes: u64,
+    /// Options in effect after negotiation; `transfer_size` is the size the
+    /// server announced (RRQ) or acknowledged (WRQ)
+    pub options: TftpOptions,
+    /// Requests, ACKs and windows re-sent to recover from loss
+    pub retransmits: u32,
+}
+
+/// TFTP client for one server
+///
+/// Each transfer uses its own ephemeral port as its transfer ID.
+#[derive(Debug, Clone)]
+pub struct TftpClient {
+    server: SocketAddr,
+    mode: TransferMode,
+    max_retries: u32,
+}
+
+impl TftpClient {
+    /// Client for the server listening at `server`, in octet mode
+    pub fn new(server: SocketAddr) -> Self {
+        Self {
+            server,
+            mode: TransferMode::Octet,
+            max_retries: MAX_RETRIES,
+        }
+    }
+
+    /// Transfer mode named in requests
+    pub fn with_mode(mut self, mode: TransferMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Retransmissions allowed without progress before a transfer fails
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Download `remote` into `writer` (RRQ)
+    ///
+    /// Setting `options.transfer_size` (to any value) requests tsize; in
+    /// octet mode the download then fails if its length differs from the
+    /// size the server announced. After the final ACK the client dallies for
+    /// one timeout, re-ACKing the final block if the server repeats it.
+    pub async fn get(
+        &self,
+        remote: &str,
+        mut writer: impl AsyncWrite + Unpin,
+        options: TftpOptions,
+    ) -> Result<ClientTransfer> {
+        let (request, asked) = self.request(Opcode::Rrq, remote, &options)?;
+        let timeout = Duration::from_secs(options.timeout);
+        let socket = self.bind(&options)?;
+        let (reply, retransmits) = self.start(&socket, &request, timeout).await?;
+
+        let (negotiated, resume_after) = match reply_opcode(&reply) {
+            Some(Opcode::Oack) => (negotiate(&socket, &reply, &options, &asked)?, 0),
+            Some(Opcode::Data) if block_number(&reply) == Some(1) => {
+                // The server ignored the options; RFC 1350 defaults apply
+                writer.write_all(&reply[4..]).await?;
+                (plain_options(&options), 1)
+            }
+            _ => return Err(unexpected_reply(&socket, &reply)),
+        };
+
+        let first_block = if resume_after == 1 {
+            reply.len() - 4
+        } else {
+            0
+        };
+        let summary = if resume_after == 1 && first_block < DEFAULT_BLOCK_SIZE {
+            socket.send(&ack_packet(1)).await?;
+            ReceiveSummary {
+                bytes: first_block as u64,
+                last_block: 1,
+                reacks: 0,
+            }
+        } else {
+            let params = ReceiveParams {
+                block_size: negotiated.block_size,
+                windowsize: negotiated.windowsize,
+                timeout,
+                max_retries: self.max_retries,
+                max_file_size: 0,
+                size_hint: None,
+                resume_after,
+            };
+            let initial_packet = ack_packet(resume_after);
+            let mut summary =
+                match receive_windowed_into(&socket, &initial_packet, params, &mut writer).await {
+                    Ok(summary) => summary,
+                    Err(e) => return Err(abort(&socket, receive_error(e))),
+                };
+            summary.bytes += first_block as u64;
+            summary
+        };
+        writer.flush().await?;
+        dally(&socket, summary.last_block, timeout, self.max_retries).await;
+
+        if self.mode == TransferMode::Octet
+            && let Some(size) = negotiated.transfer_size
+            && size != summary.bytes
+        {
+            return Err(TftpError::Tftp(format!(
+                "Received {} bytes of {}, server announced {}",
+                summary.bytes, remote, size
+            )));
+        }
+
+        debug!(
+            "Downloaded {} ({} bytes, {} blocks)",
+            remote, summary.bytes, summary.last_block
+        );
+        Ok(ClientTransfer {
+            bytes: summary.bytes,
+            options: negotiated,
+            retransmits: retransmits + summary.reacks,
+        })
+    }
+
+    /// Upload `reader` as `remote` (WRQ)
+    ///
+    /// Setting `options.transfer_size` declares the upload's size to the
+    /// server, which may refuse it up front.
+    pub async fn put(
+        &self,
+        remote: &str,
+        mut reader: impl AsyncRead + Unpin,
+        options: TftpOptions,
+    ) -> Result<ClientTransfer> {
+        let (request, asked) = self.request(Opcode::Wrq, remote, &options)?;
+        let timeout = Duration::from_secs(options.timeout);
+        let socket = self.bind(&options)?;
+        let (reply, retransmits) = self.start(&socket, &request, timeout).await?;
+
+        let negotiated = match reply_opcode(&reply) {
+            Some(Opcode::Oack) => negotiate(&socket, &reply, &options, &asked)?,
+            Some(Opcode::Ack) if block_number(&reply) == Some(0) => plain_options(&options),
+            _ => return Err(unexpected_reply(&socket, &reply)),
+        };
+
+        let sent = match send_windowed(&socket, &mut reader, &negotiated, self.max_retries).await {
+            Ok(sent) => sent,
+            Err(e) => return Err(abort(&socket, e)),
+        };
+
+        debug!("Uploaded {} ({} bytes)", remote, sent.bytes);
+        Ok(ClientTransfer {
+            bytes: sent.bytes,
+            options: negotiated,
+            retransmits: retransmits + sent.retransmits,
+        })
+    }
+
+    /// Request packet for `remote` and the options it asks for
+    ///
+    /// Only values that differ from the RFC 1350 defaults are requested.
+    /// tsize is requested whenever `transfer_size` is set: as 0 on a
+    /// download, for the server to fill in, and as the declared size on an
+    /// upload.
+    fn request(
+        &self,
+        opcode: Opcode,
+        remote: &str,
+        options: &TftpOptions,
+    ) -> Result<(BytesMut, OptionList)> {
+        if self.mode == TransferMode::Mail {
+            return Err(TftpError::Tftp("mail mode is not supported".into()));
+        }
+        if !(8..=MAX_BLOCK_SIZE).contains(&options.block_size) {
+            return Err(TftpError::Tftp(format!(
+                "blksize must be between 8 and {}",
+                MAX_BLOCK_SIZE
+            )));
+        }
+        if !(1..=255).contains(&options.timeout) {
+            return Err(TftpError::Tftp(
+                "timeout must be between 1 and 255 seconds".into(),
+            ));
+        }
+        if !(1..=65535).contains(&options.windowsize) {
+            return Err(TftpError::Tftp(
+                "windowsize must be between 1 and 65535".into(),
+            ));
+        }
+
+        let mut asked = OptionList::new();
+        if options.block_size != DEFAULT_BLOCK_SIZE {
+            asked.insert("blksize", options.block_size.to_string());
+        }
+        if options.timeout != DEFAULT_TIMEOUT_SECS {
+            asked.insert("timeout", options.timeout.to_string());
+        }
+        if let Some(size) = options.transfer_size {
+            let size = if opcode == Opcode::Wrq { size } else { 0 };
+            asked.insert("tsize", size.to_string());
+        }
+        if options.windowsize != 1 {
+            asked.insert("windowsize", options.windowsize.to_string());
+        }
+
+        let mut packet = BytesMut::new();
+        packet.put_u16(opcode as u16);
+        let options = asked.iter().flat_map(|(n, v)| [n.as_str(), v.as_str()]);
+        for field in [remote, self.mode.as_str()].into_iter().chain(options) {
+            packet.put_slice(field.as_bytes());
+            packet.put_u8(0);
+        }
+        Ok((packet, asked))
+    }
+
+    /// Ephemeral UDP socket in the server's address family
+    ///
+    /// The receive buffer holds two full windows, so a burst of DATA is not
+    /// dropped before it is read.
+    fn bind(&self, options: &TftpOptions) -> Result<UdpSocket> {
+        let (domain, bind_addr): (Domain, SocketAddr) = if self.server.is_ipv6() {
+            (Domain::IPV6, (Ipv6Addr::UNSPECIFIED, 0).into())
+        } else {
+            (Domain::IPV4, (Ipv4Addr::UNSPECIFIED, 0).into())
+        };
+
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        let window_bytes = options.windowsize * (options.block_size + 4) * 2;
+        socket.set_recv_buffer_size(window_bytes.max(MIN_RECV_BUFFER))?;
+        socket.bind(&bind_addr.into())?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    /// Send `request` until the server replies, then lock the socket to the
+    /// transfer ID the reply came from
+    ///
+    /// Returns the reply and the number of times the request was re-sent.
+    /// An ERROR reply fails the transfer.
+    async fn start(
+        &self,
+        socket: &UdpSocket,
+        request: &[u8],
+        timeout: Duration,
+    ) -> Result<(Vec<u8>, u32)> {
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut retransmits = 0;
+
+        let (size, transfer_id) = loop {
+            socket.send_to(request, self.server).await?;
+            match tokio::time::timeout(timeout, socket.recv_from(&mut buf)).await {
+                Ok(reply) => break reply?,
+                Err(_) if retransmits < self.max_retries => {
+                    retransmits += 1;
+                    debug!(
+                        "No reply from {}, re-sending request ({}/{})",
+                        self.server, retransmits, self.max_retries
+                    );
+                }
+                Err(_) => {
+                    return Err(TftpError::Tftp(format!("No reply from {}", self.server)));
+                }
+            }
+        };
+
+        // RFC 1350: the server answers from a new port chosen for this
+        // transfer; datagrams from anywhere else are dropped from here on
+        socket.connect(transfer_id).await?;
+
+        let reply = buf[..size].to_vec();
+        if reply_opcode(&reply) == Some(Opcode::Error) {
+            return Err(peer_error(&reply));
+        }
+        Ok((reply, retransmits))
+    }
+}
+
+/// Totals of a completed upload
+struct Sent {
+    bytes: u64,
+    retransmits: u32,
+}
+
+/// RFC 1350 defaults, used for every option the server does not acknowledge
+fn plain_options(options: &TftpOptions) -> TftpOptions {
+    TftpOptions {
+        block_size: DEFAULT_BLOCK_SIZE,
+        timeout: options.timeout,
+        transfer_size: None,
+        windowsize: 1,
+    }
+}
+
+/// Options in effect after an OACK body, or why it cannot be accepted
+///
+/// RFC 2347 lets the server acknowledge only options that were requested.
+/// blksize and windowsize may be lowered but never raised, and timeout must
+/// be echoed unchanged.
+fn accept_oack(
+    body: &[u8],
+    options: &TftpOptions,
+    asked: &OptionList,
+) -> std::result::Result<TftpOptions, String> {
+    let mut negotiated = plain_options(options);
+    let mut fields = body.split(|&b| b == 0);
+    while let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        if !asked.contains_key(&name) {
+            return Err(format!("unrequested option {}", name));
+        }
+        let value: u64 = String::from_utf8_lossy(value)
+            .parse()
+            .map_err(|_| format!("invalid {} value", name))?;
+
+        match name.as_str() {
+            "blksize" if (8..=options.block_size as u64).contains(&value) => {
+                negotiated.block_size = value as usize;
+            }
+            "windowsize" if (1..=options.windowsize as u64).contains(&value) => {
+                negotiated.windowsize = value as usize;
+            }
+            "timeout" if value == options.timeout => {}
+            "tsize" => negotiated.transfer_size = Some(value),
+            _ => return Err(format!("{} {} exceeds the request", name, value)),
+        }
+    }
+    Ok(negotiated)
+}
+
+/// Accept the server's OACK, or refuse it with ERROR 8 (RFC 2347)
+fn negotiate(
+    socket: &UdpSocket,
+    oack: &[u8],
+    options: &TftpOptions,
+    asked: &OptionList,
+) -> Result<TftpOptions> {
+    accept_oack(&oack[2..], options, asked).map_err(|reason| {
+        send_error(socket, ErrorCode::OptionNegotiationFailed, &reason);
+        TftpError::Tftp(format!("Option negotiation failed: {}", reason))
+    })
+}
+
+/// Upload `reader` with the RFC 7440 windowed send state machine
+///
+/// - Up to `windowsize` DATA blocks are sent, then the sender waits for an ACK
+/// - ACK of a block in the window: slide past it and send the next window
+/// - ACK of the block before the window (the receiver saw a gap or a
+///   duplicate): send the window again; a stop-and-wait sender waits for
+///   its timer instead, so a duplicate ACK cannot double every later block
+/// - Timer expiry: send the window again
+/// - More than `max_retries` re-sends without progress: fail
+///
+/// The final block is shorter than `block_size`, and empty when the data
+/// is a multiple of it.
+async fn send_windowed<S: DatagramSocket, R: AsyncRead + Unpin>(
+    socket: &S,
+    reader: &mut R,
+    options: &TftpOptions,
+    max_retries: u32,
+) -> Result<Sent> {
+    let timeout = Duration::from_secs(options.timeout);
+    let windowsize = options.windowsize.max(1);
+    let mut window: VecDeque<Bytes> = VecDeque::with_capacity(windowsize);
+    // Block number of the front of the window
+    let mut base: u16 = 1;
+    let mut next_block: u16 = 1;
+    let mut eof = false;
+    let mut bytes = 0u64;
+    let mut retries = 0u32;
+    let mut retransmits = 0u32;
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+
+    loop {
+        while window.len() < windowsize && !eof {
+            let block = read_block(reader, options.block_size).await?;
+            eof = block.len() < options.block_size;
+            bytes += block.len() as u64;
+            window.push_back(data_packet(next_block, &block));
+            next_block = next_block.wrapping_add(1);
+        }
+        if window.is_empty() {
+            return Ok(Sent { bytes, retransmits });
+        }
+
+        for packet in &window {
+            socket.send(packet).await?;
+        }
+
+        let resend = loop {
+            let size = match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+                Ok(received) => received?,
+                Err(_) => break true,
+            };
+            if size < 4 {
+                continue;
+            }
+            match Opcode::from_u16(u16::from_be_bytes([buf[0], buf[1]])) {
+                Some(Opcode::Error) => return Err(peer_error(&buf[..size])),
+                Some(Opcode::Ack) => {}
+                _ => continue,
+            }
+
+            // Blocks of the window the ACK covers; 0 for the block before it
+            let acked = u16::from_be_bytes([buf[2], buf[3]])
+                .wrapping_sub(base)
+                .wrapping_add(1) as usize;
+            if acked == 0 && windowsize > 1 {
+                break true;
+            }
+            if (1..=window.len()).contains(&acked) {
+                if acked < window.len() {
+                    // The rest of the window is sent again with the next one
+                    retransmits += 1;
+                }
+                window.drain(..acked);
+                base = base.wrapping_add(acked as u16);
+                retries = 0;
+                break false;
+            }
+            // An ACK from an earlier window
+        };
+
+        if resend {
+            retries += 1;
+            if retries > max_retries {
+                return Err(TftpError::Tftp(format!(
+                    "Timed out waiting for ACK of block {}",
+                    base
+                )));
+            }
+            retransmits += 1;
+            debug!(
+                "Re-sending window from block {} ({}/{})",
+                base, retries, max_retries
+            );
+        }
+    }
+}
+
+/// Read up to `block_size` bytes, short only at the end of the input
+async fn read_block<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    block_size: usize,
+) -> std::io::Result<Vec<u8>> {
+    let mut block = vec![0u8; block_size];
+    let mut filled = 0;
+    while filled < block_size {
+        match reader.read(&mut block[filled..]).await? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    block.truncate(filled);
+    Ok(block)
+}
+
+/// Wait one timeout after the final ACK, re-ACKing the final block each
+/// time the server repeats it because the ACK was lost (RFC 1350 Section 6)
+async fn dally(socket: &UdpSocket, last_block: u16, timeout: Duration, max_retries: u32) {
+    let final_ack = ack_packet(last_block);
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+    for _ in 0..=max_retries {
+        let size = match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+            Ok(Ok(size)) => size,
+            // Nothing more from the server, or its port has closed
+            Ok(Err(_)) | Err(_) => return,
+        };
+        let packet = &buf[..size];
+        if reply_opcode(packet) == Some(Opcode::Data) && block_number(packet) == Some(last_block) {
+            debug!("Server repeated final block {}, re-ACKing", last_block);
+            if socket.send(&final_ack).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn data_packet(block_num: u16, data: &[u8]) -> Bytes {
+    let mut packet = BytesMut::with_capacity(4 + data.len());
+    packet.put_u16(Opcode::Data as u16);
+    packet.put_u16(block_num);
+    packet.put_slice(data);
+    packet.freeze()
+}
+
+fn reply_opcode(packet: &[u8]) -> Option<Opcode> {
+    let opcode = packet.get(..2)?;
+    Opcode::from_u16(u16::from_be_bytes([opcode[0], opcode[1]]))
+}
+
+/// Block number of a DATA or ACK packet
+fn block_number(packet: &[u8]) -> Option<u16> {
+    let block = packet.get(2..4)?;
+    Some(u16::from_be_bytes([block[0], block[1]]))
+}
+
+/// Error for an ERROR packet from the server
+fn peer_error(packet: &[u8]) -> TftpError {
+    let code = block_number(packet).unwrap_or(0);
+    let message = packet.get(4..).unwrap_or_default();
+    let end = message
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(message.len());
+    TftpError::Peer {
+        code: ErrorCode::from_u16(code).unwrap_or(ErrorCode::NotDefined),
+        message: String::from_utf8_lossy(&message[..end]).into_owned(),
+    }
+}
+
+fn receive_error(e: ReceiveError) -> TftpError {
+    match e {
+        ReceiveError::ClientError { code, message } => TftpError::Peer {
+            code: ErrorCode::from_u16(code).unwrap_or(ErrorCode::NotDefined),
+            message,
+        },
+        ReceiveError::Timeout { expected_block } => {
+            TftpError::Tftp(format!("Timed out waiting for block {}", expected_block))
+        }
+        ReceiveError::FileTooLarge { size, .. } => {
+            TftpError::Tftp(format!("File too large ({} bytes)", size))
+        }
+        ReceiveError::Io(e) => TftpError::Io(e),
+    }
+}
+
+/// Tell the server a transfer failed locally, then return the error
+///
+/// Errors the server sent or timeouts need no reply.
+fn abort(socket: &UdpSocket, error: TftpError) -> TftpError {
+    if let TftpError::Io(e) = &error {
+        send_error(socket, ErrorCode::NotDefined, &e.to_string());
+    }
+    error
+}
+
+/// Refuse a reply that cannot start the transfer with ERROR 4
+fn unexpected_reply(socket: &UdpSocket, reply: &[u8]) -> TftpError {
+    let message = format!(
+        "Unexpected reply opcode {}",
+        reply
+            .get(..2)
+            .map_or(0, |op| u16::from_be_bytes([op[0], op[1]]))
+    );
+    send_error(socket, ErrorCode::IllegalOperation, &message);
+    TftpError::Tftp(message)
+}
+
+/// Send an ERROR packet, best effort: the transfer is over either way
+fn send_error(socket: &UdpSocket, code: ErrorCode, message: &str) {
+    let mut packet = BytesMut::with_capacity(5 + message.len());
+    packet.put_u16(Opcode::Error as u16);
+    packet.put_u16(code as u16);
+    packet.put_slice(message.as_bytes());
+    packet.put_u8(0);
+    if let Err(e) = socket.try_send(&packet) {
+        debug!("Could not send ERROR {}: {}", code as u16, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(packet: &[u8]) -> Vec<String> {
+        packet[2..]
+            .split(|&b| b == 0)
+            .map(|f| String::from_utf8_lossy(f).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_request_asks_only_for_non_default_options() {
+        let client = TftpClient::new("127.0.0.1:69".parse().unwrap());
+
+        let (rrq, asked) = client
+            .request(Opcode::Rrq, "boot.bin", &TftpOptions::default())
+            .unwrap();
+        assert!(asked.is_empty());
+        assert_eq!(fields(&rrq), ["boot.bin", "octet", ""]);
+
+        let options = TftpOptions {
+            block_size: 1468,
+            transfer_size: Some(4096),
+            windowsize: 8,
+            ..TftpOptions::default()
+        };
+        let (rrq, _) = client.request(Opcode::Rrq, "boot.bin", &options).unwrap();
+        assert_eq!(
+            fields(&rrq),
+            [
+                "boot.bin",
+                "octet",
+                "blksize",
+                "1468",
+                "tsize",
+                "0",
+                "windowsize",
+                "8",
+                ""
+            ]
+        );
+        let (wrq, _) = client.request(Opcode::Wrq, "boot.bin", &options).unwrap();
+        assert_eq!(fields(&wrq)[4..6], ["tsize", "4096"]);
+
+        let options = TftpOptions {
+            block_size: MAX_BLOCK_SIZE + 1,
+            ..TftpOptions::default()
+        };
+        assert!(client.request(Opcode::Rrq, "boot.bin", &options).is_err());
+    }
+
+    #[test]
+    fn test_oack_may_lower_but_not_raise_options() {
+        let options = TftpOptions {
+            block_size: 8192,
+            timeout: 2,
+            transfer_size: Some(0),
+            windowsize: 16,
+        };
+        let client = TftpClient::new("127.0.0.1:69".parse().unwrap());
+        let (_, asked) = client.request(Opcode::Rrq, "boot.bin", &options).unwrap();
+
+        let negotiated = accept_oack(
+            b"blksize\x001468\x00windowsize\x004\x00tsize\x00123\x00",
+            &options,
+            &asked,
+        )
+        .unwrap();
+        assert_eq!(negotiated.block_size, 1468);
+        assert_eq!(negotiated.windowsize, 4);
+        assert_eq!(negotiated.transfer_size, Some(123));
+        assert_eq!(negotiated.timeout, 2);
+
+        // Options left out of the OACK fall back to the RFC 1350 defaults
+        let negotiated = accept_oack(b"TSIZE\x00123\x00", &options, &asked).unwrap();
+        assert_eq!(negotiated.block_size, DEFAULT_BLOCK_SIZE);
+        assert_eq!(negotiated.windowsize, 1);
+
+        assert!(accept_oack(b"blksize\x0016384\x00", &options, &asked).is_err());
+        assert!(accept_oack(b"timeout\x005\x00", &options, &asked).is_err());
+        assert!(accept_oack(b"multicast\x00,,\x00", &options, &asked).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retransmits_request_and_reacks_repeated_final_block() {
+        let listener = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_PACKET_SIZE];
+            // Lose the first request
+            listener.recv_from(&mut buf).await.unwrap();
+            let (_, client_addr) = listener.recv_from(&mut buf).await.unwrap();
+
+            // Answer a plain RRQ with DATA 1, then repeat it as if the ACK was lost
+            let transfer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            transfer.connect(client_addr).await.unwrap();
+            let data = data_packet(1, b"menu.ipxe");
+            let mut acks = Vec::new();
+            for _ in 0..2 {
+                transfer.send(&data).await.unwrap();
+                let n = transfer.recv(&mut buf).await.unwrap();
+                acks.push(buf[..n].to_vec());
+            }
+            acks
+        });
+
+        let options = TftpOptions {
+            timeout: 1,
+            ..TftpOptions::default()
+        };
+        let mut downloaded = Vec::new();
+        let transfer = TftpClient::new(server_addr)
+            .get("menu.ipxe", &mut downloaded, options)
+            .await
+            .unwrap();
+
+        assert_eq!(downloaded, b"menu.ipxe");
+        assert_eq!(transfer.bytes, 9);
+        assert_eq!(transfer.retransmits, 1);
+        assert_eq!(transfer.options.block_size, DEFAULT_BLOCK_SIZE);
+        let acks = server.await.unwrap();
+        assert_eq!(acks, [ack_packet(1).to_vec(), ack_packet(1).to_vec()]);
+    }
+}
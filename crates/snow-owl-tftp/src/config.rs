@@ -1,7 +1,9 @@
 use clap::ValueEnum;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::error::{Result, TftpError};
 
@@ -13,9 +15,8 @@ use crate::error::{Result, TftpError};
 /// - CM-5: Access Restrictions for Change (control file modifications)
 ///
 /// STIG V-222602: Applications must enforce access restrictions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
-#[derive(Default)]
 pub struct WriteConfig {
     /// Enable write operations (disabled by default for security)
     pub enabled: bool,
@@ -28,20 +29,235 @@ pub struct WriteConfig {
     /// Examples: ["*.txt", "configs/*.cfg", "firmware/device-*.bin"]
     /// Empty list means no writes are allowed
     pub allowed_patterns: Vec<String>,
+
+    /// What happens when an upload's final size differs from its non-zero tsize
+    /// Default: warn (the file is kept)
+    pub tsize_mismatch: TsizeMismatchPolicy,
+
+    /// Seconds after which an upload temp file (`*.tftp-tmp`) left by an
+    /// interrupted write is removed; temp files of uploads in progress are
+    /// never removed. 0 disables the cleanup.
+    /// Default: 3600
+    pub temp_max_age_secs: u64,
+
+    /// Seconds between temp file sweeps while writes are enabled (a sweep
+    /// also runs at startup)
+    /// Default: 600
+    #[schemars(range(min = 1))]
+    pub temp_sweep_interval_secs: u64,
+}
+
+impl Default for WriteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_overwrite: false,
+            allowed_patterns: Vec::new(),
+            tsize_mismatch: TsizeMismatchPolicy::default(),
+            temp_max_age_secs: 3600,
+            temp_sweep_interval_secs: 600,
+        }
+    }
+}
+
+impl WriteConfig {
+    /// Age at which leftover upload temp files are removed, or `None` when
+    /// the cleanup is disabled
+    pub fn temp_max_age(&self) -> Option<Duration> {
+        (self.temp_max_age_secs > 0).then(|| Duration::from_secs(self.temp_max_age_secs))
+    }
+
+    pub fn temp_sweep_interval(&self) -> Duration {
+        Duration::from_secs(self.temp_sweep_interval_secs)
+    }
+}
+
+/// Handling of a WRQ whose received size differs from its declared tsize (RFC 2349)
+///
+/// NIST 800-53 SI-7: Software, Firmware, and Information Integrity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TsizeMismatchPolicy {
+    /// Log and audit the mismatch, then keep the file
+    #[default]
+    Warn,
+    /// Discard the upload and send the client an ERROR
+    Reject,
+}
+
+/// Transfer mode a request may ask for (RFC 1350 section 1)
+///
+/// MAIL is obsolete and refused whatever this allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AllowedTransferMode {
+    Octet,
+    Netascii,
+}
+
+impl AllowedTransferMode {
+    /// Mode name as it appears on the wire
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Octet => "octet",
+            Self::Netascii => "netascii",
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct TftpConfig {
+    /// Directory files are served from; must be an absolute path
+    #[schemars(regex(pattern = r"^/"))]
     pub root_dir: PathBuf,
+    /// Listening address and port (port must be non-zero)
+    /// Default: [::]:69
     pub bind_addr: SocketAddr,
+    /// Local address per-transfer sockets bind to, so DATA, OACK and ERROR
+    /// replies leave from the address clients sent their requests to
+    /// Unset derives it from bind_addr; with a wildcard bind_addr, transfer
+    /// sockets bind to the wildcard address of the client's family
+    /// Default: None
+    pub bind_transfer_ip: Option<IpAddr>,
+    /// Multicast TFTP (RFC 2090) settings
     pub multicast: MulticastConfig,
+    /// Log output and audit settings
     pub logging: LoggingConfig,
+    /// Write request (WRQ) settings; writes are disabled by default
     pub write_config: WriteConfig,
+    /// Glob patterns a read request must match, relative to root_dir
+    /// Examples: ["*.efi", "*.ipxe", "boot/*"]
+    /// Empty list allows reading any file under root_dir
+    pub read_allowed_patterns: Vec<String>,
+    /// Transfer modes RRQs and WRQs may use ("octet", "netascii")
+    /// Requests for any other mode get an Illegal Operation error
+    /// Default: ["octet", "netascii"]
+    #[schemars(length(min = 1))]
+    pub allowed_transfer_modes: Vec<AllowedTransferMode>,
+    /// Throughput tuning and platform-specific optimizations
     pub performance: PerformanceConfig,
     /// Maximum file size in bytes that can be served (default: 100MB)
     /// Set to 0 for unlimited (not recommended for security)
     pub max_file_size_bytes: u64,
+    /// Allow block numbers to roll over from 65535 to 0 on large transfers
+    /// When false, an RRQ with negotiated options whose file would need more
+    /// than 65535 blocks at the requested blksize is rejected with an Option
+    /// Negotiation error (RFC 2347) instead of wrapping mid-transfer
+    /// Default: false
+    pub allow_block_rollover: bool,
+    /// Silently ignore packets on the listening port whose opcode is not RRQ
+    /// or WRQ instead of answering with an Illegal Operation error
+    /// Stray DATA/ACK retransmits from clients mid-transfer otherwise each get
+    /// an error reply and a log warning. ERROR packets are never answered.
+    /// Default: false
+    pub drop_non_request_opcodes: bool,
+    /// Answer an RRQ for a directory with a generated plain-text index
+    /// When false, such requests get an explicit "Is a directory" error
+    /// Default: false
+    pub serve_directory_index: bool,
+    /// Maximum number of entries listed in a generated directory index
+    pub directory_index_max_entries: usize,
+    /// Transmission attempts per DATA window (and receive timeouts per
+    /// expected block) before a transfer is abandoned
+    /// Raise for lossy WAN links, lower for faster failure on a LAN
+    #[schemars(range(min = 1))]
+    pub max_retries: u32,
+    /// Base delay before retransmitting a window, doubled on each further
+    /// attempt (0 retransmits as soon as the timeout expires)
+    pub retry_backoff_ms: u64,
+    /// Seconds in-flight transfers get to finish on shutdown before they are
+    /// aborted; new requests are refused as soon as shutdown starts
+    /// Default: 30
+    pub shutdown_drain_timeout_secs: u64,
+    /// Cap on each read transfer's payload rate in bytes per second
+    /// Windows are held back until the cap allows them; the ACK timeout only
+    /// starts once a window is sent. Unset means unlimited.
+    /// Default: None
+    #[schemars(range(min = 1))]
+    pub max_bytes_per_sec: Option<u64>,
+    /// Cap on the combined payload rate of all read transfers in bytes per second
+    /// Windows wait for this shared budget after any per-transfer cap; with
+    /// priority enabled, critical transfers are charged but never held back.
+    /// Unset means unlimited.
+    /// Default: None
+    #[schemars(range(min = 1))]
+    pub max_bytes_per_sec_total: Option<u64>,
+    /// Priority classes that let small boot-critical files overtake bulk transfers
+    pub priority: PriorityConfig,
+    /// Smaller windowsizes for clients whose recent reads needed retransmits
+    pub adaptive_windowsize: AdaptiveWindowConfig,
+    /// Serve image files from the database under a virtual path prefix
+    pub virtual_roots: VirtualRootsConfig,
+    /// Record boot file reads against the requesting machine's deployment
+    pub deployment_tracking: DeploymentTrackingConfig,
+    /// Address of an HTTP listener serving worker pool statistics as JSON at
+    /// `/status` and a liveness check at `/healthz` (port must be non-zero)
+    /// Only served while the worker pool is enabled
+    /// Default: None (no listener)
+    pub status_bind_addr: Option<SocketAddr>,
+}
+
+impl TftpConfig {
+    /// Address per-transfer sockets bind to, or `None` for the wildcard
+    pub fn transfer_ip(&self) -> Option<IpAddr> {
+        self.bind_transfer_ip
+            .or(Some(self.bind_addr.ip()))
+            .filter(|ip| !ip.is_unspecified())
+    }
+
+    /// Entry cap for directory indexes, or `None` when indexes are disabled
+    pub fn directory_index_limit(&self) -> Option<usize> {
+        self.serve_directory_index
+            .then_some(self.directory_index_max_entries)
+    }
+
+    /// Retransmission limits for transfers
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            backoff_base: Duration::from_millis(self.retry_backoff_ms),
+        }
+    }
+
+    /// How long shutdown waits for in-flight transfers
+    pub fn shutdown_drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_drain_timeout_secs)
+    }
+}
+
+/// Upper bound on a single retransmission backoff delay
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How often and how patiently a transfer retransmits before giving up
+///
+/// NIST 800-53 Controls:
+/// - SC-5: Denial of Service Protection (bounded retransmission)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff_base: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retransmission number `retry` (1 for the first retransmission)
+    ///
+    /// Doubles from `backoff_base` and is capped at 30 seconds.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        if retry == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u32 << (retry - 1).min(16);
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(MAX_RETRY_BACKOFF)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        TftpConfig::default().retry_policy()
+    }
 }
 
 impl Default for TftpConfig {
@@ -49,24 +265,53 @@ impl Default for TftpConfig {
         Self {
             root_dir: PathBuf::from("/var/lib/snow-owl/tftp"),
             bind_addr: SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 69),
+            bind_transfer_ip: None,
             multicast: MulticastConfig::default(),
             logging: LoggingConfig::default(),
             write_config: WriteConfig::default(),
+            read_allowed_patterns: Vec::new(),
+            allowed_transfer_modes: vec![AllowedTransferMode::Octet, AllowedTransferMode::Netascii],
             performance: PerformanceConfig::default(),
             max_file_size_bytes: 104_857_600, // 100 MB default
+            allow_block_rollover: false,
+            drop_non_request_opcodes: false,
+            serve_directory_index: false,
+            directory_index_max_entries: 1000,
+            max_retries: crate::MAX_RETRIES,
+            retry_backoff_ms: 0,
+            shutdown_drain_timeout_secs: 30,
+            max_bytes_per_sec: None,
+            max_bytes_per_sec_total: None,
+            priority: PriorityConfig::default(),
+            adaptive_windowsize: AdaptiveWindowConfig::default(),
+            virtual_roots: VirtualRootsConfig::default(),
+            deployment_tracking: DeploymentTrackingConfig::default(),
+            status_bind_addr: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct LoggingConfig {
+    /// Minimum log level (trace, debug, info, warn, error)
     pub level: String,
+    /// Log line format
     pub format: LogFormat,
+    /// Audit log file; its parent directory must exist
     pub file: Option<PathBuf>,
     /// Enable structured audit logging for SIEM integration
     /// When enabled, all security-relevant events are logged as structured JSON
     pub audit_enabled: bool,
+    /// Audit one in N successful transfers (1 = all)
+    ///
+    /// The choice is made per client address and port, so a transfer's
+    /// request, start and completion events are kept or dropped together.
+    /// Denials, failures and security events are always logged. With N > 1
+    /// each logged completion records N as its `sample_rate`, and the SLA
+    /// report counts it N times.
+    #[schemars(range(min = 1))]
+    pub success_sample_rate: u32,
 }
 
 impl Default for LoggingConfig {
@@ -76,11 +321,12 @@ impl Default for LoggingConfig {
             format: LogFormat::Json,
             file: Some(PathBuf::from("/var/log/snow-owl/tftp-audit.json")),
             audit_enabled: true,
+            success_sample_rate: 1,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LogFormat {
     /// Plain text logging for human readability
@@ -90,16 +336,133 @@ pub enum LogFormat {
     Json,
 }
 
+/// Transfer priority classes for mass boot
+///
+/// A read request is critical when its filename matches `critical_patterns`
+/// or the file is at most `critical_max_bytes`; everything else is bulk.
+/// Classes are always counted, so the effect of `enabled` can be measured.
+///
+/// NIST 800-53 Controls:
+/// - SC-5: Denial of Service Protection (bulk transfers cannot starve boot files)
+/// - SC-6: Resource Availability (priority-based allocation)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PriorityConfig {
+    /// Schedule critical transfers ahead of bulk ones
+    /// Critical windows are never delayed by max_bytes_per_sec_total, and
+    /// critical requests go to any worker with room when theirs is full.
+    /// Default: false
+    pub enabled: bool,
+    /// Glob patterns, relative to root_dir, of files that are always critical
+    /// Examples: ["boot/BCD", "boot/boot.sdi", "*.ipxe"]
+    pub critical_patterns: Vec<String>,
+    /// Files of at most this many bytes are critical (0 = patterns only)
+    pub critical_max_bytes: u64,
+}
+
+/// Windowsize reduction for clients on lossy links
+///
+/// RFC 7440 retransmits a whole window when its ACK is lost, and a windowsize
+/// cannot change once negotiated, so a client whose read transfer needed
+/// `retransmit_threshold` window retransmissions is granted half that window
+/// in the OACK of its next RRQ. A clean transfer doubles it again.
+///
+/// NIST 800-53 Controls:
+/// - SC-5: Denial of Service Protection (retransmission traffic bounded)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AdaptiveWindowConfig {
+    /// Grant reduced windowsizes to clients whose recent reads were lossy
+    /// Default: false
+    pub enabled: bool,
+    /// Window retransmissions during one read transfer that mark its client
+    /// as lossy
+    /// Default: 2
+    #[schemars(range(min = 1))]
+    pub retransmit_threshold: u32,
+    /// Smallest windowsize a lossy client is granted
+    /// Default: 1
+    #[schemars(range(min = 1, max = 65535))]
+    pub min_windowsize: usize,
+    /// Seconds a reduced windowsize lasts after the client's last lossy transfer
+    /// Default: 300
+    #[schemars(range(min = 1))]
+    pub memory_secs: u64,
+}
+
+impl Default for AdaptiveWindowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retransmit_threshold: 2,
+            min_windowsize: 1,
+            memory_secs: 300,
+        }
+    }
+}
+
+/// Virtual paths resolved through the images database
+///
+/// NIST 800-53 Controls:
+/// - AC-3: Access Enforcement (resolved files confined to allowed_roots)
+/// - CM-8: System Component Inventory (single source of image locations)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct VirtualRootsConfig {
+    /// Resolve requests under `prefix` through the images database
+    pub enabled: bool,
+    /// Request prefix for virtual paths, e.g. `images/<uuid-or-name>/boot.sdi`
+    pub prefix: String,
+    /// PostgreSQL URL of the Snow Owl images database
+    pub database_url: Option<String>,
+    /// Directories resolved image files must be inside (absolute paths)
+    #[schemars(inner(regex(pattern = r"^/")))]
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+impl Default for VirtualRootsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prefix: "images/".to_string(),
+            database_url: None,
+            allowed_roots: Vec::new(),
+        }
+    }
+}
+
+/// Deployment steps recorded from finished reads
+///
+/// NIST 800-53 Controls:
+/// - AU-12: Audit Record Generation (boot file delivery per deployment)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeploymentTrackingConfig {
+    /// Append a step to the audit trail when a machine with an active
+    /// deployment finishes or fails a read
+    pub enabled: bool,
+    /// PostgreSQL URL of the Snow Owl database holding machines and deployments
+    pub database_url: Option<String>,
+}
+
 /// Multicast TFTP configuration (RFC 2090)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct MulticastConfig {
+    /// Offer the RFC 2090 multicast option to clients
     pub enabled: bool,
+    /// Multicast group address; must match `multicast_ip_version`
     pub multicast_addr: IpAddr,
+    /// Address family of the multicast group
     pub multicast_ip_version: MulticastIpVersion,
+    /// UDP port of the multicast group
+    #[schemars(range(min = 1024))]
     pub multicast_port: u16,
+    /// Maximum clients sharing one multicast session
     pub max_clients: usize,
+    /// Seconds without an ACK before the master client is replaced
     pub master_timeout_secs: u64,
+    /// Seconds before unacknowledged blocks are sent again
     pub retransmit_timeout_secs: u64,
 }
 
@@ -117,10 +480,12 @@ impl Default for MulticastConfig {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MulticastIpVersion {
+    /// IPv4 multicast (224.0.0.0/4)
     V4,
+    /// IPv6 multicast (ff00::/8)
     V6,
 }
 
@@ -239,8 +604,142 @@ pub fn validate_config(config: &TftpConfig, validate_bind: bool) -> Result<()> {
             .map_err(|e| TftpError::Tftp(format!("logging.file not writable: {}", e)))?;
     }
 
+    // NIST SC-7: Transfer sockets can only reply to clients the listener
+    // accepts; only a dual-stack [::] listener takes both families
+    if let Some(ip) = config.bind_transfer_ip
+        && ip.is_ipv4() != config.bind_addr.is_ipv4()
+        && config.bind_addr.ip() != IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    {
+        return Err(TftpError::Tftp(format!(
+            "bind_transfer_ip {} is not in the address family of bind_addr {}",
+            ip, config.bind_addr
+        )));
+    }
+
+    // NIST SC-7: Multicast clients negotiate over the listener, so the group
+    // must be reachable from the family the listener accepts
+    if config.multicast.enabled
+        && config.multicast.multicast_addr.is_ipv4() != config.bind_addr.is_ipv4()
+        && config.bind_addr.ip() != IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    {
+        return Err(TftpError::Tftp(format!(
+            "multicast.multicast_addr {} is not in the address family of bind_addr {}",
+            config.multicast.multicast_addr, config.bind_addr
+        )));
+    }
+
+    if config.status_bind_addr.is_some_and(|addr| addr.port() == 0) {
+        return Err(TftpError::Tftp(
+            "status_bind_addr port must be non-zero".to_string(),
+        ));
+    }
+
     validate_multicast_config(&config.multicast)?;
     validate_write_config(&config.write_config)?;
+    validate_read_allowed_patterns(&config.read_allowed_patterns)?;
+
+    // NIST CM-6: An empty list would refuse every request
+    if config.allowed_transfer_modes.is_empty() {
+        return Err(TftpError::Tftp(
+            "allowed_transfer_modes must list at least one mode".to_string(),
+        ));
+    }
+    validate_priority_config(&config.priority)?;
+    validate_adaptive_window_config(&config.adaptive_windowsize)?;
+    validate_virtual_roots_config(&config.virtual_roots)?;
+    if config.deployment_tracking.enabled && config.deployment_tracking.database_url.is_none() {
+        return Err(TftpError::Tftp(
+            "deployment_tracking.database_url is required when deployment tracking is enabled"
+                .to_string(),
+        ));
+    }
+
+    // NIST SC-5: A transfer needs at least one attempt
+    if config.max_retries == 0 {
+        return Err(TftpError::Tftp(
+            "max_retries must be at least 1".to_string(),
+        ));
+    }
+
+    // NIST AU-12: Sampling may thin out routine events but never disable them
+    if config.logging.success_sample_rate == 0 {
+        return Err(TftpError::Tftp(
+            "logging.success_sample_rate must be at least 1".to_string(),
+        ));
+    }
+
+    // NIST SC-5: A zero cap would stall every transfer
+    if config.max_bytes_per_sec == Some(0) {
+        return Err(TftpError::Tftp(
+            "max_bytes_per_sec must be at least 1 (omit it for no limit)".to_string(),
+        ));
+    }
+    if config.max_bytes_per_sec_total == Some(0) {
+        return Err(TftpError::Tftp(
+            "max_bytes_per_sec_total must be at least 1 (omit it for no limit)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_priority_config(config: &PriorityConfig) -> Result<()> {
+    // NIST CM-6: Reject patterns that would silently never match
+    for pattern in &config.critical_patterns {
+        if let Err(e) = glob::Pattern::new(pattern) {
+            return Err(TftpError::Tftp(format!(
+                "Invalid priority.critical_patterns entry '{}': {}",
+                pattern, e
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_adaptive_window_config(config: &AdaptiveWindowConfig) -> Result<()> {
+    if config.retransmit_threshold == 0 {
+        return Err(TftpError::Tftp(
+            "adaptive_windowsize.retransmit_threshold must be at least 1".to_string(),
+        ));
+    }
+    if !(1..=65535).contains(&config.min_windowsize) {
+        return Err(TftpError::Tftp(
+            "adaptive_windowsize.min_windowsize must be in range 1-65535".to_string(),
+        ));
+    }
+    if config.memory_secs == 0 {
+        return Err(TftpError::Tftp(
+            "adaptive_windowsize.memory_secs must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_virtual_roots_config(config: &VirtualRootsConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    if config.prefix.trim_matches('/').is_empty() {
+        return Err(TftpError::Tftp(
+            "virtual_roots.prefix must not be empty".to_string(),
+        ));
+    }
+    if config.database_url.is_none() {
+        return Err(TftpError::Tftp(
+            "virtual_roots.database_url is required when virtual roots are enabled".to_string(),
+        ));
+    }
+    // NIST AC-3: Resolved files must be confined somewhere explicit
+    if config.allowed_roots.is_empty() {
+        return Err(TftpError::Tftp(
+            "virtual_roots.allowed_roots must list at least one directory".to_string(),
+        ));
+    }
+    if let Some(root) = config.allowed_roots.iter().find(|root| !root.is_absolute()) {
+        return Err(TftpError::Tftp(format!(
+            "virtual_roots.allowed_roots entry must be absolute: {}",
+            root.display()
+        )));
+    }
     Ok(())
 }
 
@@ -259,6 +758,55 @@ pub(crate) fn validate_multicast_config(config: &MulticastConfig) -> Result<()>
     Ok(())
 }
 
+/// Check if a requested filename may be read based on configured patterns
+///
+/// The filename is matched as requested, with backslashes treated as
+/// separators and leading slashes removed. An empty pattern list allows
+/// every file.
+///
+/// NIST 800-53 Controls:
+/// - AC-3: Access Enforcement (pattern-based access control)
+/// - AC-6: Least Privilege (expose only boot artifacts)
+///
+/// STIG V-222602: Applications must enforce access restrictions
+pub fn is_read_allowed(patterns: &[String], filename: &str) -> bool {
+    if patterns.is_empty() {
+        return true;
+    }
+
+    let filename = filename.replace('\\', "/");
+    let filename = filename.trim_start_matches('/');
+    patterns
+        .iter()
+        .any(|pattern| glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches(filename)))
+}
+
+/// Whether a request's transfer mode is in `allowed` (case-insensitive)
+pub fn is_mode_allowed(allowed: &[AllowedTransferMode], mode: &str) -> bool {
+    allowed
+        .iter()
+        .any(|allowed| allowed.name().eq_ignore_ascii_case(mode))
+}
+
+pub(crate) fn validate_read_allowed_patterns(patterns: &[String]) -> Result<()> {
+    // NIST CM-6: Reject patterns that would silently never match
+    for pattern in patterns {
+        if pattern.trim().is_empty() {
+            return Err(TftpError::Tftp(
+                "read_allowed_patterns cannot contain empty patterns".to_string(),
+            ));
+        }
+        if let Err(e) = glob::Pattern::new(pattern) {
+            return Err(TftpError::Tftp(format!(
+                "Invalid read_allowed_patterns entry '{}': {}",
+                pattern, e
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 pub(crate) fn validate_write_config(config: &WriteConfig) -> Result<()> {
     // NIST AC-3: If writes are enabled, require at least one allowed pattern
     // STIG V-222602: Enforce explicit access restrictions
@@ -270,6 +818,12 @@ pub(crate) fn validate_write_config(config: &WriteConfig) -> Result<()> {
         ));
     }
 
+    if config.temp_sweep_interval_secs == 0 {
+        return Err(TftpError::Tftp(
+            "write_config.temp_sweep_interval_secs must be at least 1".to_string(),
+        ));
+    }
+
     // Validate patterns are not overly permissive
     // NIST AC-6: Least Privilege
     for pattern in &config.allowed_patterns {
@@ -295,6 +849,9 @@ pub(crate) fn validate_write_config(config: &WriteConfig) -> Result<()> {
 #[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
+    use snow_owl_core::config_schema::{
+        check_config_file, config_schema, undocumented_properties, validate_toml,
+    };
 
     fn temp_dir(name: &str) -> std::io::Result<PathBuf> {
         let mut dir = std::env::temp_dir();
@@ -415,6 +972,50 @@ file = "{}/tftp.log"
         Ok(())
     }
 
+    #[test]
+    fn rejects_multicast_group_outside_bind_family()
+    -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let log_dir = temp_dir("mcast_family_log")?;
+        let mut config = TftpConfig::default();
+        config.root_dir = temp_dir("mcast-family")?;
+        config.logging.file = Some(log_dir.join("tftp.log"));
+        config.multicast.enabled = true;
+
+        // IPv6 group on an IPv4 listener
+        config.bind_addr = "127.0.0.1:6969".parse()?;
+        match validate_config(&config, false) {
+            Ok(()) => return Err("expected error for IPv6 group on IPv4 bind".into()),
+            Err(err) => {
+                assert!(format!("{err}").contains(
+                    "multicast.multicast_addr ff12::8000:1 is not in the address family of \
+                     bind_addr 127.0.0.1:6969"
+                ));
+            }
+        }
+
+        // IPv4 group on an IPv6-only listener
+        config.bind_addr = "[::1]:6969".parse()?;
+        config.multicast.multicast_ip_version = MulticastIpVersion::V4;
+        config.multicast.multicast_addr =
+            default_multicast_addr_for_version(MulticastIpVersion::V4);
+        match validate_config(&config, false) {
+            Ok(()) => return Err("expected error for IPv4 group on IPv6 bind".into()),
+            Err(err) => {
+                assert!(format!("{err}").contains("is not in the address family of bind_addr"));
+            }
+        }
+
+        // A dual-stack listener accepts either family
+        config.bind_addr = "[::]:6969".parse()?;
+        validate_config(&config, false)?;
+
+        // Disabled multicast is not checked
+        config.bind_addr = "[::1]:6969".parse()?;
+        config.multicast.enabled = false;
+        validate_config(&config, false)?;
+        Ok(())
+    }
+
     #[test]
     fn rejects_logging_file_with_missing_parent()
     -> std::result::Result<(), Box<dyn std::error::Error>> {
@@ -546,6 +1147,233 @@ file = "{}/tftp.log"
         validate_config(&config, false)?;
         Ok(())
     }
+
+    #[test]
+    fn read_patterns_allow_matching_path() {
+        let patterns = vec!["*.efi".to_string(), "boot/*".to_string()];
+
+        assert!(is_read_allowed(&patterns, "grubx64.efi"));
+        assert!(is_read_allowed(&patterns, "/boot/bcd"));
+        assert!(is_read_allowed(&patterns, "boot\\bcd"));
+        assert!(is_read_allowed(&[], "anything/at/all.txt"));
+    }
+
+    #[test]
+    fn read_patterns_deny_other_paths() {
+        let patterns = vec!["*.efi".to_string(), "boot/*".to_string()];
+
+        assert!(!is_read_allowed(&patterns, "secrets.txt"));
+        assert!(!is_read_allowed(&patterns, "configs/boot.cfg"));
+    }
+
+    #[test]
+    fn transfer_modes_default_to_octet_and_netascii() {
+        let config = TftpConfig::default();
+        assert!(is_mode_allowed(&config.allowed_transfer_modes, "octet"));
+        assert!(is_mode_allowed(&config.allowed_transfer_modes, "NetASCII"));
+        assert!(!is_mode_allowed(&config.allowed_transfer_modes, "mail"));
+
+        let octet_only = [AllowedTransferMode::Octet];
+        assert!(is_mode_allowed(&octet_only, "OCTET"));
+        assert!(!is_mode_allowed(&octet_only, "netascii"));
+
+        let parsed: TftpConfig = toml::from_str(r#"allowed_transfer_modes = ["octet"]"#).unwrap();
+        assert_eq!(parsed.allowed_transfer_modes, octet_only);
+    }
+
+    #[test]
+    fn rejects_invalid_read_pattern() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let log_dir = temp_dir("read_pattern_log")?;
+        let mut config = TftpConfig::default();
+        config.root_dir = temp_dir("read-pattern")?;
+        config.logging.file = Some(log_dir.join("tftp.log"));
+        config.read_allowed_patterns = vec!["boot/[".to_string()];
+
+        match validate_config(&config, false) {
+            Err(err) => {
+                assert!(format!("{err}").contains("Invalid read_allowed_patterns entry"));
+                Ok(())
+            }
+            Ok(_) => Err("expected invalid read pattern error".into()),
+        }
+    }
+
+    #[test]
+    fn retry_backoff_doubles_from_base() {
+        let policy = TftpConfig {
+            max_retries: 8,
+            retry_backoff_ms: 100,
+            ..TftpConfig::default()
+        }
+        .retry_policy();
+
+        assert_eq!(policy.max_retries, 8);
+        assert_eq!(policy.backoff(0), Duration::ZERO);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(40), Duration::from_secs(30));
+        assert_eq!(RetryPolicy::default().backoff(2), Duration::ZERO);
+    }
+
+    #[test]
+    fn rejects_zero_max_retries() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let log_dir = temp_dir("retries_log")?;
+        let mut config = TftpConfig::default();
+        config.root_dir = temp_dir("retries")?;
+        config.logging.file = Some(log_dir.join("tftp.log"));
+        config.max_retries = 0;
+
+        match validate_config(&config, false) {
+            Err(err) => {
+                assert!(format!("{err}").contains("max_retries must be at least 1"));
+                Ok(())
+            }
+            Ok(_) => Err("expected max_retries error".into()),
+        }
+    }
+
+    #[test]
+    fn rejects_zero_max_bytes_per_sec() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let log_dir = temp_dir("throttle_log")?;
+        let mut config = TftpConfig::default();
+        config.root_dir = temp_dir("throttle")?;
+        config.logging.file = Some(log_dir.join("tftp.log"));
+        config.max_bytes_per_sec = Some(0);
+
+        match validate_config(&config, false) {
+            Err(err) => {
+                assert!(format!("{err}").contains("max_bytes_per_sec must be at least 1"));
+                Ok(())
+            }
+            Ok(_) => Err("expected max_bytes_per_sec error".into()),
+        }
+    }
+
+    #[test]
+    fn deployment_tracking_requires_database_url()
+    -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let log_dir = temp_dir("tracking_log")?;
+        let mut config = TftpConfig::default();
+        config.root_dir = temp_dir("tracking")?;
+        config.logging.file = Some(log_dir.join("tftp.log"));
+        config.deployment_tracking.enabled = true;
+
+        match validate_config(&config, false) {
+            Err(err) => {
+                assert!(format!("{err}").contains("deployment_tracking.database_url is required"));
+            }
+            Ok(_) => return Err("expected deployment_tracking error".into()),
+        }
+
+        config.deployment_tracking.database_url = Some("postgres://localhost/snow_owl".into());
+        validate_config(&config, false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn virtual_roots_require_allowed_roots() {
+        let mut config = VirtualRootsConfig {
+            enabled: true,
+            database_url: Some("postgres://localhost/snow_owl".to_string()),
+            ..VirtualRootsConfig::default()
+        };
+        assert!(validate_virtual_roots_config(&config).is_err());
+
+        config.allowed_roots = vec![PathBuf::from("/srv/images")];
+        assert!(validate_virtual_roots_config(&config).is_ok());
+
+        config.allowed_roots = vec![PathBuf::from("images")];
+        assert!(validate_virtual_roots_config(&config).is_err());
+    }
+
+    #[test]
+    fn schema_documents_every_field() {
+        let schema = config_schema::<TftpConfig>();
+        assert_eq!(undocumented_properties(&schema), Vec::<String>::new());
+    }
+
+    #[test]
+    fn shipped_example_configs_satisfy_schema() {
+        let crate_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        for example in [
+            "examples/phase1-optimized.toml",
+            "examples/phase2-optimized.toml",
+            "tests/integration-test/server-config.toml",
+        ] {
+            let report = check_config_file::<TftpConfig>(&crate_dir.join(example), None)
+                .unwrap_or_else(|e| panic!("{example}: {e}"));
+            assert!(report.is_valid(), "{example}: {:?}", report.errors);
+            assert!(
+                report.warnings.is_empty(),
+                "{example}: {:?}",
+                report.warnings
+            );
+        }
+    }
+
+    #[test]
+    fn schema_enforces_validation_limits() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let schema = config_schema::<TftpConfig>();
+        let report = validate_toml(
+            &schema,
+            r#"
+root_dir = "relative/tftp"
+max_retries = 0
+max_bytes_per_sec = 0
+
+[logging]
+success_sample_rate = 0
+
+[multicast]
+multicast_port = 80
+
+[performance]
+default_block_size = 70000
+"#,
+        )?;
+        let mut fields: Vec<&str> = report
+            .errors
+            .iter()
+            .filter_map(|e| e.split(':').next())
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "logging.success_sample_rate",
+                "max_bytes_per_sec",
+                "max_retries",
+                "multicast.multicast_port",
+                "performance.default_block_size",
+                "root_dir",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deprecated_settings_warn_but_validate() -> std::result::Result<(), Box<dyn std::error::Error>>
+    {
+        let schema = config_schema::<TftpConfig>();
+        let report = validate_toml(
+            &schema,
+            r#"
+[performance]
+audit_sampling_rate = 0.5
+
+[performance.platform.worker_pool]
+enabled = true
+"#,
+        )?;
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert_eq!(report.warnings.len(), 2);
+        assert!(report.warnings[0].starts_with("performance.audit_sampling_rate is deprecated: "));
+        assert!(
+            report.warnings[1]
+                .starts_with("performance.platform.worker_pool.enabled is deprecated: ")
+        );
+        Ok(())
+    }
 }
 
 fn default_multicast_port() -> u16 {
@@ -567,18 +1395,20 @@ fn default_retransmit_timeout() -> u64 {
 /// Performance tuning configuration
 ///
 /// These settings control performance optimizations for high-throughput scenarios
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct PerformanceConfig {
     /// Default block size for transfers (bytes)
     /// RFC 1350 standard is 512, but larger sizes improve throughput
     /// Valid range: 512-65464
+    #[schemars(range(min = 512, max = 65464))]
     pub default_block_size: usize,
 
     /// Default window size for RFC 7440 sliding window (blocks)
     /// RFC 7440: Valid range 1-65535, default 1 for RFC 1350 compatibility
     /// Higher values improve throughput on high-latency networks
     /// Recommended: 4-16 for typical networks, 32+ for high-latency links
+    #[schemars(range(min = 1, max = 65535))]
     pub default_windowsize: usize,
 
     /// Buffer pool size for packet reuse
@@ -590,15 +1420,31 @@ pub struct PerformanceConfig {
     /// Larger files use streaming to minimize memory usage
     pub streaming_threshold: u64,
 
+    /// Deprecated and ignored; use `logging.success_sample_rate` instead
     /// Audit log sampling rate (0.0-1.0)
-    /// 1.0 = log all events, 0.5 = log 50% of events
-    /// Lower values reduce audit overhead for high-volume servers
+    #[schemars(range(min = 0.0, max = 1.0), extend("deprecated" = true))]
     pub audit_sampling_rate: f64,
 
+    /// Route incoming requests through the worker pool (Phase 4)
+    /// When enabled, a master task receives packets, workers dispatch
+    /// requests and a sender task transmits responses from the server port.
+    /// Pool sizing is configured under `platform.worker_pool`.
+    /// Default: false (per-packet task spawning)
+    pub use_worker_pool: bool,
+
     /// Platform-specific performance optimizations (Linux/BSD)
     pub platform: PlatformPerformanceConfig,
 }
 
+impl PerformanceConfig {
+    /// Whether the worker pool should serve requests
+    ///
+    /// `platform.worker_pool.enabled` is still honoured for existing configs.
+    pub fn worker_pool_enabled(&self) -> bool {
+        self.use_worker_pool || self.platform.worker_pool.enabled
+    }
+}
+
 impl Default for PerformanceConfig {
     fn default() -> Self {
         Self {
@@ -607,6 +1453,7 @@ impl Default for PerformanceConfig {
             buffer_pool_size: 128,
             streaming_threshold: 1_048_576, // 1MB
             audit_sampling_rate: 1.0,       // Log everything by default
+            use_worker_pool: false,
             platform: PlatformPerformanceConfig::default(),
         }
     }
@@ -616,7 +1463,7 @@ impl Default for PerformanceConfig {
 /// Phase 1: Socket tuning and file I/O hints
 /// Phase 2: Zero-copy operations and batch syscalls
 /// Phase 4: Worker thread pool
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(default)]
 pub struct PlatformPerformanceConfig {
     /// Socket-level optimizations
@@ -638,7 +1485,7 @@ pub struct PlatformPerformanceConfig {
 // Derived Default implementation
 
 /// Socket-level performance configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct SocketConfig {
     /// Receive buffer size in KB (SO_RCVBUF)
@@ -673,7 +1520,7 @@ impl Default for SocketConfig {
 }
 
 /// File I/O optimization configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct FileIoConfig {
     /// Use POSIX_FADV_SEQUENTIAL hint for sequential file reads
@@ -704,7 +1551,7 @@ impl Default for FileIoConfig {
 
 /// Batch packet operation configuration (Phase 2)
 /// Uses sendmmsg()/recvmmsg() for reduced syscall overhead
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct BatchConfig {
     /// Enable sendmmsg() for batch packet sending (Linux 3.0+, FreeBSD 11.0+)
@@ -762,7 +1609,7 @@ impl Default for BatchConfig {
 
 /// Zero-copy transfer configuration (Phase 2)
 /// Reduces CPU usage and memory bandwidth for large file transfers
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct ZeroCopyConfig {
     /// Use sendfile() for zero-copy transfers (Linux only)
@@ -805,18 +1652,20 @@ impl Default for ZeroCopyConfig {
 
 /// Worker thread pool configuration (Phase 4)
 /// NGINX-style multi-threaded architecture for multi-core utilization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct WorkerPoolConfig {
+    /// Superseded by `performance.use_worker_pool`; either switch enables the pool
     /// Enable worker thread pool (Phase 4)
-    /// When disabled, uses Phase 3 single-threaded Tokio architecture
     /// Default: false (opt-in for Phase 4)
+    #[schemars(extend("deprecated" = true))]
     pub enabled: bool,
 
     /// Number of worker threads
     /// Recommended: number of CPU cores - 2 (reserve for master/sender)
     /// Valid range: 1-32
     /// Default: auto-detected (CPU cores - 2, min 1, max 8)
+    #[schemars(range(min = 1, max = 32))]
     pub worker_count: usize,
 
     /// Channel buffer size between master and workers (packets per worker)
@@ -837,6 +1686,24 @@ pub struct WorkerPoolConfig {
     /// Linux-only feature
     /// Default: false
     pub enable_cpu_affinity: bool,
+
+    /// Seconds between worker pool statistics log lines (0 disables them)
+    /// Default: 60
+    pub stats_log_interval_secs: u64,
+
+    /// Reset the pool's counters after each statistics line, so each line
+    /// (and `/status` in between) covers one interval instead of the pool's
+    /// lifetime; queue depths and active clients are never reset
+    /// Default: false
+    pub stats_log_reset: bool,
+}
+
+impl WorkerPoolConfig {
+    /// Period of the statistics log line, or `None` when it is disabled
+    pub fn stats_log_interval(&self) -> Option<std::time::Duration> {
+        (self.stats_log_interval_secs > 0)
+            .then(|| std::time::Duration::from_secs(self.stats_log_interval_secs))
+    }
 }
 
 impl Default for WorkerPoolConfig {
@@ -852,12 +1719,14 @@ impl Default for WorkerPoolConfig {
             sender_channel_size: 512,
             load_balance_strategy: LoadBalanceStrategy::RoundRobin,
             enable_cpu_affinity: false,
+            stats_log_interval_secs: 60,
+            stats_log_reset: false,
         }
     }
 }
 
 /// Load balancing strategy for worker thread pool
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum LoadBalanceStrategy {
     /// Simple round-robin distribution
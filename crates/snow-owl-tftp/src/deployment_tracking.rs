@@ -0,0 +1,148 @@
+//! Deployment steps from TFTP transfers
+//!
+//! A machine being deployed pulls its boot files over TFTP before the WinPE
+//! agent has anything to report. With deployment tracking enabled, every
+//! finished read is matched to the requesting machine by client address,
+//! and if that machine has an active deployment the outcome is appended to
+//! the shared audit trail against the deployment, next to the API's own
+//! records. Reads from addresses no machine has registered are ignored.
+//!
+//! NIST 800-53 Controls:
+//! - AU-3: Content of Audit Records (deployment, file, client and outcome)
+//! - AU-12: Audit Record Generation (boot file delivery recorded per deployment)
+//! - CM-8: System Component Inventory (transfers tied to known machines)
+
+use crate::events::{TransferDirection, TransferEvent};
+use snow_owl_core::{AuditRecord, Result};
+use snow_owl_db::Database;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Events queued for the tracker before transfers start dropping them
+pub const DEPLOYMENT_EVENT_QUEUE: usize = 1024;
+
+/// Appends deployment steps for transfer events
+pub struct DeploymentTracker {
+    db: Arc<Database>,
+}
+
+impl DeploymentTracker {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Record events until every sender is dropped
+    pub async fn run(self, mut events: mpsc::Receiver<TransferEvent>) {
+        while let Some(event) = events.recv().await {
+            if let Err(e) = self.record(&event).await {
+                warn!(
+                    "Failed to record deployment step for {} from {}: {}",
+                    event.path().display(),
+                    event.client_addr(),
+                    e
+                );
+            }
+        }
+    }
+
+    async fn record(&self, event: &TransferEvent) -> Result<()> {
+        let Some(step) = deployment_step(event) else {
+            return Ok(());
+        };
+        let ip = event.client_addr().ip().to_canonical();
+        let Some(machine) = self.db.get_machine_by_ip(ip).await? else {
+            return Ok(());
+        };
+        let Some(deployment) = self
+            .db
+            .get_active_deployment_for_machine(machine.id)
+            .await?
+        else {
+            debug!("No active deployment for machine {} at {}", machine.id, ip);
+            return Ok(());
+        };
+
+        self.db
+            .append_audit_event(&step.with_resource_id("deployment", deployment.id))
+            .await
+    }
+}
+
+/// Audit record for a finished read, before the deployment is known
+///
+/// Started events and writes are not deployment steps.
+pub fn deployment_step(event: &TransferEvent) -> Option<AuditRecord> {
+    if event.direction() != TransferDirection::Read {
+        return None;
+    }
+    let record = match event {
+        TransferEvent::Started { .. } => return None,
+        TransferEvent::Completed { .. } => AuditRecord::new("deployment.tftp_transfer", true),
+        TransferEvent::Failed { error, .. } => {
+            AuditRecord::failure("deployment.tftp_transfer", error.as_str())
+        }
+    };
+    Some(
+        record
+            .with_resource("deployment", event.path().display().to_string())
+            .with_ip(event.client_addr().ip().to_canonical()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn test_finished_reads_are_steps() {
+        let client_addr = "[::ffff:192.168.1.50]:2000".parse().unwrap();
+        let path = PathBuf::from("/srv/tftp/boot/boot.wim");
+
+        let completed = TransferEvent::Completed {
+            client_addr,
+            path: path.clone(),
+            direction: TransferDirection::Read,
+            bytes: 4096,
+            duration: Duration::from_secs(2),
+        };
+        let step = deployment_step(&completed).unwrap();
+        assert_eq!(step.action, "deployment.tftp_transfer");
+        assert!(step.success);
+        assert_eq!(step.resource.as_deref(), Some("/srv/tftp/boot/boot.wim"));
+        assert_eq!(step.ip_address, Some("192.168.1.50".parse().unwrap()));
+
+        let failed = TransferEvent::Failed {
+            client_addr,
+            path: path.clone(),
+            direction: TransferDirection::Read,
+            bytes: 512,
+            duration: Duration::from_secs(30),
+            error: "transfer ended before completion".to_string(),
+        };
+        let step = deployment_step(&failed).unwrap();
+        assert!(!step.success);
+        assert_eq!(
+            step.error_message.as_deref(),
+            Some("transfer ended before completion")
+        );
+
+        let started = TransferEvent::Started {
+            client_addr,
+            path: path.clone(),
+            direction: TransferDirection::Read,
+            size: Some(4096),
+        };
+        assert!(deployment_step(&started).is_none());
+        let upload = TransferEvent::Completed {
+            client_addr,
+            path,
+            direction: TransferDirection::Write,
+            bytes: 4096,
+            duration: Duration::from_secs(2),
+        };
+        assert!(deployment_step(&upload).is_none());
+    }
+}
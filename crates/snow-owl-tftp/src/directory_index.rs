@@ -0,0 +1,112 @@
+//! Directory index generation
+//!
+//! Some recovery tooling requests a directory path (e.g. `configs/`) and
+//! expects a plain-text listing back, the way tftpd-hpa setups used to ship
+//! a MANIFEST file. When enabled, the server answers such a request with a
+//! generated index instead of an error.
+//!
+//! The index is deterministic: one entry per line, sorted by name, files as
+//! `name<TAB>size` and subdirectories as `name/`. Hidden entries (leading
+//! `.`) and symlinks are never listed, matching what the server would
+//! refuse to serve anyway.
+//!
+//! NIST 800-53 Controls:
+//! - AC-3: Access Enforcement (hidden entries and symlinks are not disclosed)
+//! - SC-5: Denial of Service Protection (entry count is capped)
+
+use std::path::Path;
+
+/// Build the index for `dir`, listing at most `max_entries` entries
+///
+/// When entries are dropped by the cap, a final `# truncated: N more entries`
+/// line is appended so clients can tell the listing is incomplete.
+pub async fn build_directory_index(dir: &Path, max_entries: usize) -> std::io::Result<Vec<u8>> {
+    let mut entries = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+            // Names that are not valid UTF-8 cannot be requested over TFTP
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let file_type = entry.file_type().await?;
+        if file_type.is_dir() {
+            entries.push(format!("{}/", name));
+        } else if file_type.is_file() {
+            let size = entry.metadata().await?.len();
+            entries.push(format!("{}\t{}", name, size));
+        }
+    }
+
+    entries.sort();
+
+    let mut index = String::new();
+    for entry in entries.iter().take(max_entries) {
+        index.push_str(entry);
+        index.push('\n');
+    }
+    if entries.len() > max_entries {
+        index.push_str(&format!(
+            "# truncated: {} more entries\n",
+            entries.len() - max_entries
+        ));
+    }
+
+    Ok(index.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture_dir() -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("snow_owl_tftp_index_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("drivers")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join("pxelinux.cfg"), b"default menu\n").unwrap();
+        std::fs::write(dir.join("boot.ipxe"), b"#!ipxe\n").unwrap();
+        std::fs::write(dir.join(".secret"), b"hidden").unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_index_matches_expected_bytes() {
+        let dir = fixture_dir();
+
+        let index = build_directory_index(&dir, usize::MAX).await.unwrap();
+
+        assert_eq!(
+            index,
+            b"boot.ipxe\t7\ndrivers/\npxelinux.cfg\t13\n".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_omits_hidden_entries() {
+        let dir = fixture_dir();
+
+        let index = build_directory_index(&dir, usize::MAX).await.unwrap();
+        let index = String::from_utf8(index).unwrap();
+
+        assert!(!index.contains(".secret"));
+        assert!(!index.contains(".git"));
+    }
+
+    #[tokio::test]
+    async fn test_cap_truncates_with_marker() {
+        let dir = fixture_dir();
+
+        let index = build_directory_index(&dir, 2).await.unwrap();
+
+        assert_eq!(
+            index,
+            b"boot.ipxe\t7\ndrivers/\n# truncated: 1 more entries\n".to_vec()
+        );
+    }
+}
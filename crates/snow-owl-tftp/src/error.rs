@@ -5,6 +5,10 @@ pub enum TftpError {
     #[error("TFTP error: {0}")]
     Tftp(String),
 
+    /// ERROR packet from the other end of a transfer (RFC 1350)
+    #[error("{} from peer: {message}", .code.as_str())]
+    Peer { code: ErrorCode, message: String },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -13,3 +17,49 @@ pub enum TftpError {
 }
 
 pub type Result<T> = std::result::Result<T, TftpError>;
+
+// TFTP Error Codes (RFC 1350)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ErrorCode {
+    NotDefined = 0,
+    FileNotFound = 1,
+    AccessViolation = 2,
+    DiskFull = 3,
+    IllegalOperation = 4,
+    UnknownTransferId = 5,
+    FileAlreadyExists = 6,
+    NoSuchUser = 7,
+    OptionNegotiationFailed = 8, // RFC 2347
+}
+
+impl ErrorCode {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(ErrorCode::NotDefined),
+            1 => Some(ErrorCode::FileNotFound),
+            2 => Some(ErrorCode::AccessViolation),
+            3 => Some(ErrorCode::DiskFull),
+            4 => Some(ErrorCode::IllegalOperation),
+            5 => Some(ErrorCode::UnknownTransferId),
+            6 => Some(ErrorCode::FileAlreadyExists),
+            7 => Some(ErrorCode::NoSuchUser),
+            8 => Some(ErrorCode::OptionNegotiationFailed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::NotDefined => "Not defined",
+            ErrorCode::FileNotFound => "File not found",
+            ErrorCode::AccessViolation => "Access violation",
+            ErrorCode::DiskFull => "Disk full or allocation exceeded",
+            ErrorCode::IllegalOperation => "Illegal TFTP operation",
+            ErrorCode::UnknownTransferId => "Unknown transfer ID",
+            ErrorCode::FileAlreadyExists => "File already exists",
+            ErrorCode::NoSuchUser => "No such user",
+            ErrorCode::OptionNegotiationFailed => "Option negotiation failed",
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Transfer lifecycle events for other components
+//!
+//! The audit log records transfers for operators; these events carry the
+//! same start and finish points to code that acts on them, such as
+//! deployment tracking noting that a machine has pulled its boot files.
+//! A [`TransferWatch`] is started alongside each transfer and emits exactly
+//! one of Completed or Failed, so a transfer that ends early on an error or
+//! timeout is still reported.
+//!
+//! Events are sent without waiting: if the receiver falls behind, further
+//! events are dropped with a warning rather than slowing transfers down.
+//!
+//! NIST 800-53 Controls:
+//! - AU-12: Audit Record Generation (transfer outcomes for other components)
+//! - SI-4: System Monitoring (deployment progress visible outside the TFTP server)
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Which way the file moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// RRQ: the client downloaded the file
+    Read,
+    /// WRQ: the client uploaded the file
+    Write,
+}
+
+/// Start or outcome of one transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferEvent {
+    Started {
+        client_addr: SocketAddr,
+        path: PathBuf,
+        direction: TransferDirection,
+        /// Size of the file being read, or the size the client declared for a write
+        size: Option<u64>,
+    },
+    Completed {
+        client_addr: SocketAddr,
+        path: PathBuf,
+        direction: TransferDirection,
+        bytes: u64,
+        duration: Duration,
+    },
+    Failed {
+        client_addr: SocketAddr,
+        path: PathBuf,
+        direction: TransferDirection,
+        /// Bytes acknowledged (reads) or received (writes) before the failure
+        bytes: u64,
+        duration: Duration,
+        error: String,
+    },
+}
+
+impl TransferEvent {
+    pub fn client_addr(&self) -> SocketAddr {
+        match self {
+            Self::Started { client_addr, .. }
+            | Self::Completed { client_addr, .. }
+            | Self::Failed { client_addr, .. } => *client_addr,
+        }
+    }
+
+    /// File on disk the transfer read or wrote
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Started { path, .. }
+            | Self::Completed { path, .. }
+            | Self::Failed { path, .. } => path,
+        }
+    }
+
+    pub fn direction(&self) -> TransferDirection {
+        match self {
+            Self::Started { direction, .. }
+            | Self::Completed { direction, .. }
+            | Self::Failed { direction, .. } => *direction,
+        }
+    }
+}
+
+/// Where transfer events go; does nothing unless built with a sender
+#[derive(Debug, Clone, Default)]
+pub struct TransferEvents {
+    sender: Option<mpsc::Sender<TransferEvent>>,
+}
+
+impl TransferEvents {
+    /// Deliver events to `sender`
+    pub fn new(sender: mpsc::Sender<TransferEvent>) -> Self {
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Emit Started for a transfer that began at `started`
+    pub fn start(
+        &self,
+        client_addr: SocketAddr,
+        path: &Path,
+        direction: TransferDirection,
+        size: Option<u64>,
+        started: Instant,
+    ) -> TransferWatch {
+        self.emit(TransferEvent::Started {
+            client_addr,
+            path: path.to_path_buf(),
+            direction,
+            size,
+        });
+        TransferWatch {
+            events: self.clone(),
+            client_addr,
+            path: path.to_path_buf(),
+            direction,
+            started,
+            bytes: 0,
+            done: false,
+        }
+    }
+
+    fn emit(&self, event: TransferEvent) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        match sender.try_send(event) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(event)) => {
+                warn!(
+                    "Transfer event receiver is behind; dropped event for {} from {}",
+                    event.path().display(),
+                    event.client_addr()
+                );
+            }
+            // The receiver has shut down; nobody is listening any more
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// One transfer being reported; dropping it without calling
+/// [`complete`](Self::complete) or [`fail`](Self::fail) emits Failed
+#[derive(Debug)]
+pub struct TransferWatch {
+    events: TransferEvents,
+    client_addr: SocketAddr,
+    path: PathBuf,
+    direction: TransferDirection,
+    started: Instant,
+    bytes: u64,
+    done: bool,
+}
+
+impl TransferWatch {
+    /// Record that `bytes` in total have been transferred so far
+    pub fn progress(&mut self, bytes: u64) {
+        self.bytes = bytes;
+    }
+
+    /// Emit Completed with the final byte count
+    pub fn complete(mut self, bytes: u64) {
+        self.done = true;
+        self.events.emit(TransferEvent::Completed {
+            client_addr: self.client_addr,
+            path: std::mem::take(&mut self.path),
+            direction: self.direction,
+            bytes,
+            duration: self.started.elapsed(),
+        });
+    }
+
+    /// Emit Failed with `error` as the reason
+    pub fn fail(mut self, error: impl Into<String>) {
+        self.emit_failed(error.into());
+    }
+
+    fn emit_failed(&mut self, error: String) {
+        self.done = true;
+        self.events.emit(TransferEvent::Failed {
+            client_addr: self.client_addr,
+            path: std::mem::take(&mut self.path),
+            direction: self.direction,
+            bytes: self.bytes,
+            duration: self.started.elapsed(),
+            error,
+        });
+    }
+}
+
+impl Drop for TransferWatch {
+    fn drop(&mut self) {
+        if !self.done {
+            self.emit_failed("transfer ended before completion".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> SocketAddr {
+        "192.168.1.50:2000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_watch_reports_one_outcome() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let events = TransferEvents::new(tx);
+        let path = Path::new("/srv/tftp/boot.wim");
+
+        let watch = events.start(
+            client(),
+            path,
+            TransferDirection::Read,
+            Some(4),
+            Instant::now(),
+        );
+        watch.complete(4);
+
+        let mut watch = events.start(
+            client(),
+            path,
+            TransferDirection::Read,
+            Some(4),
+            Instant::now(),
+        );
+        watch.progress(2);
+        drop(watch);
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(TransferEvent::Started { size: Some(4), .. })
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(TransferEvent::Completed { bytes: 4, .. })
+        ));
+        assert!(matches!(rx.try_recv(), Ok(TransferEvent::Started { .. })));
+        match rx.try_recv() {
+            Ok(TransferEvent::Failed { bytes, error, .. }) => {
+                assert_eq!(bytes, 2);
+                assert_eq!(error, "transfer ended before completion");
+            }
+            other => panic!("expected Failed, got {:?}", other),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_full_receiver_drops_events() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let events = TransferEvents::new(tx);
+
+        let watch = events.start(
+            client(),
+            Path::new("/srv/tftp/boot.wim"),
+            TransferDirection::Write,
+            None,
+            Instant::now(),
+        );
+        // Does not block even though the channel is full
+        watch.fail("timeout waiting for data");
+
+        assert!(matches!(rx.try_recv(), Ok(TransferEvent::Started { .. })));
+        assert!(rx.try_recv().is_err());
+    }
+}
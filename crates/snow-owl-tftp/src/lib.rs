@@ -2,11 +2,24 @@
 #![allow(dead_code)]
 
 // Public modules - shared between server and client
+pub mod adaptive_window;
 pub mod audit;
+pub mod bench;
 pub mod buffer_pool;
+pub mod client;
 pub mod config;
+pub mod deployment_tracking;
+pub mod directory_index;
 pub mod error;
+pub mod events;
 pub mod multicast;
+pub mod priority;
+pub mod receive;
+pub mod report;
+pub mod status;
+pub mod temp_files;
+pub mod throttle;
+pub mod virtual_path;
 pub mod worker_pool;
 
 // Server module stub (to be properly implemented)
@@ -28,7 +41,7 @@ pub mod server {
             _client_addr: std::net::SocketAddr,
             _mode: TransferMode,
             _options: TftpOptions,
-            _negotiated_options: std::collections::HashMap<String, String>,
+            _negotiated_options: OptionList,
             _max_file_size: u64,
             _audit_enabled: bool,
             _file_io_config: &config::FileIoConfig,
@@ -42,7 +55,7 @@ pub mod server {
             _client_addr: std::net::SocketAddr,
             _mode: TransferMode,
             _options: TftpOptions,
-            _negotiated_options: std::collections::HashMap<String, String>,
+            _negotiated_options: OptionList,
             _max_file_size: u64,
             _file_created: bool,
             _audit_enabled: bool,
@@ -54,8 +67,10 @@ pub mod server {
 
 pub use server::TftpServer;
 
+pub use client::TftpClient;
+
 // Re-export commonly used types
-pub use error::{Result, TftpError};
+pub use error::{ErrorCode, Result, TftpError};
 pub use config::TftpConfig;
 
 // RFC 1350 - The TFTP Protocol (Revision 2)
@@ -64,6 +79,7 @@ pub const MAX_BLOCK_SIZE: usize = 65464; // RFC 2348 maximum block size
 pub const MAX_PACKET_SIZE: usize = 65468; // Max block size + 4 byte header
 pub const DEFAULT_TIMEOUT_SECS: u64 = 5;
 pub const MAX_RETRIES: u32 = 5;
+pub const MAX_BLOCK_NUMBER: u64 = 65535; // 16-bit block number, first DATA block is 1
 
 // TFTP Opcodes (RFC 1350)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -94,37 +110,6 @@ impl Opcode {
     }
 }
 
-// TFTP Error Codes (RFC 1350)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u16)]
-pub enum ErrorCode {
-    NotDefined = 0,
-    FileNotFound = 1,
-    AccessViolation = 2,
-    DiskFull = 3,
-    IllegalOperation = 4,
-    UnknownTransferId = 5,
-    FileAlreadyExists = 6,
-    NoSuchUser = 7,
-    OptionNegotiationFailed = 8, // RFC 2347
-}
-
-impl ErrorCode {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            ErrorCode::NotDefined => "Not defined",
-            ErrorCode::FileNotFound => "File not found",
-            ErrorCode::AccessViolation => "Access violation",
-            ErrorCode::DiskFull => "Disk full or allocation exceeded",
-            ErrorCode::IllegalOperation => "Illegal TFTP operation",
-            ErrorCode::UnknownTransferId => "Unknown transfer ID",
-            ErrorCode::FileAlreadyExists => "File already exists",
-            ErrorCode::NoSuchUser => "No such user",
-            ErrorCode::OptionNegotiationFailed => "Option negotiation failed",
-        }
-    }
-}
-
 // Transfer Mode (RFC 1350)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransferMode {
@@ -146,6 +131,15 @@ impl TransferMode {
         }
     }
 
+    /// Mode name as sent in RRQ/WRQ packets
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferMode::Netascii => "netascii",
+            TransferMode::Octet => "octet",
+            TransferMode::Mail => "mail",
+        }
+    }
+
     /// Convert binary data to NETASCII format (RFC 1350)
     pub fn convert_to_netascii(data: &[u8]) -> Vec<u8> {
         let mut result = Vec::with_capacity(data.len());
@@ -175,6 +169,21 @@ pub struct TftpOptions {
     pub windowsize: usize,              // RFC 7440 - Windowsize Option (1-65535 blocks)
 }
 
+impl TftpOptions {
+    /// Number of DATA blocks needed to send `transfer_size` bytes
+    ///
+    /// Includes the final short (possibly empty) block that ends the transfer.
+    pub fn block_count(&self, transfer_size: u64) -> u64 {
+        transfer_size / self.block_size as u64 + 1
+    }
+
+    /// True if the transfer needs more blocks than the 16-bit block number
+    /// can address without rolling over to 0
+    pub fn exceeds_block_space(&self, transfer_size: u64) -> bool {
+        self.block_count(transfer_size) > MAX_BLOCK_NUMBER
+    }
+}
+
 impl Default for TftpOptions {
     fn default() -> Self {
         Self {
@@ -185,3 +194,132 @@ impl Default for TftpOptions {
         }
     }
 }
+
+/// RFC 2347 option name/value pairs in the order the client sent them
+///
+/// Some strict clients (older iPXE builds) expect the OACK to echo options
+/// in request order, so options are carried as an ordered list rather than
+/// a map. Setting an option that is already present keeps its position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionList(Vec<(String, String)>);
+
+impl OptionList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an option, replacing the value in place if it is already present
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.0.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    /// Drop an option, returning its value if it was present
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let idx = self.0.iter().position(|(n, _)| n == name)?;
+        Some(self.0.remove(idx).1)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, String)> {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a OptionList {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Serialized as a map so audit records keep their existing shape
+impl serde::Serialize for OptionList {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_count_includes_final_block() {
+        let options = TftpOptions::default();
+        assert_eq!(options.block_count(0), 1);
+        assert_eq!(options.block_count(511), 1);
+        assert_eq!(options.block_count(512), 2);
+        assert_eq!(options.block_count(1000), 2);
+    }
+
+    #[test]
+    fn test_small_blksize_exceeds_block_space() {
+        // 512-byte blocks address at most 65535 * 512 - 1 bytes without rollover
+        let options = TftpOptions {
+            block_size: 512,
+            ..TftpOptions::default()
+        };
+        assert!(!options.exceeds_block_space(65_535 * 512 - 1));
+        assert!(options.exceeds_block_space(65_535 * 512));
+        assert!(options.exceeds_block_space(100 * 1024 * 1024));
+
+        // A larger blksize brings the same file back within range
+        let options = TftpOptions {
+            block_size: 8192,
+            ..TftpOptions::default()
+        };
+        assert!(!options.exceeds_block_space(100 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_option_list_keeps_request_order() {
+        let mut options = OptionList::new();
+        options.insert("windowsize", "8");
+        options.insert("blksize", "1468");
+        options.insert("tsize", "0");
+        options.insert("windowsize", "4");
+
+        let names: Vec<&str> = options.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["windowsize", "blksize", "tsize"]);
+        assert_eq!(options.get("windowsize"), Some("4"));
+
+        assert_eq!(options.remove("blksize").as_deref(), Some("1468"));
+        assert_eq!(options.remove("blksize"), None);
+        let names: Vec<&str> = options.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["windowsize", "tsize"]);
+    }
+}
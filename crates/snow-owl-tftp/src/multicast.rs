@@ -1,4 +1,6 @@
 use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
@@ -7,11 +9,11 @@ use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use tokio::net::UdpSocket;
 use tokio::sync::RwLock;
-use tokio::time::{Duration, timeout};
+use tokio::time::{Duration, Instant, timeout};
 use tracing::{debug, error, info, warn};
 
 use crate::audit::AuditLogger;
-use crate::config::MulticastConfig;
+use crate::config::{MulticastConfig, MulticastIpVersion, default_multicast_addr_for_version};
 use crate::error::{Result, TftpError};
 use crate::{DEFAULT_BLOCK_SIZE, TftpOpcode, TftpOptions, TransferMode};
 
@@ -44,6 +46,8 @@ struct ClientState {
     acked_blocks: HashSet<u16>,
     /// Last activity timestamp
     last_seen: std::time::Instant,
+    /// When the client joined the session
+    joined_at: DateTime<Utc>,
     /// Whether this client is the master client
     is_master: bool,
 }
@@ -54,11 +58,11 @@ impl ClientState {
             addr,
             acked_blocks: HashSet::new(),
             last_seen: std::time::Instant::now(),
+            joined_at: Utc::now(),
             is_master,
         }
     }
 
-    #[allow(dead_code)]
     fn mark_acked(&mut self, block_num: u16) {
         self.acked_blocks.insert(block_num);
         self.last_seen = std::time::Instant::now();
@@ -67,6 +71,55 @@ impl ClientState {
     fn has_acked(&self, block_num: u16) -> bool {
         self.acked_blocks.contains(&block_num)
     }
+
+    fn snapshot(&self) -> MulticastClientSnapshot {
+        MulticastClientSnapshot {
+            addr: self.addr,
+            joined_at: self.joined_at,
+            is_master: self.is_master,
+            blocks_acked: self.acked_blocks.len(),
+        }
+    }
+}
+
+/// A client of a multicast session, as reported in a session snapshot
+///
+/// NIST Controls:
+/// - SI-4: System Monitoring (session membership)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MulticastClientSnapshot {
+    pub addr: SocketAddr,
+    pub joined_at: DateTime<Utc>,
+    pub is_master: bool,
+    /// Distinct blocks the client has acknowledged
+    pub blocks_acked: usize,
+}
+
+/// Point-in-time view of a multicast session
+///
+/// Returned by [`MulticastTftpServer::sessions_snapshot`] for the status
+/// endpoint and the SIGUSR1 session dump.
+///
+/// NIST Controls:
+/// - SI-4: System Monitoring (group membership and retransmissions)
+/// - AU-3: Content of Audit Records (master client and progress)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MulticastSessionSnapshot {
+    pub session_id: String,
+    pub filename: String,
+    pub multicast_addr: IpAddr,
+    pub multicast_port: u16,
+    pub master_client: Option<SocketAddr>,
+    /// Clients still receiving the file, in join order
+    pub clients: Vec<MulticastClientSnapshot>,
+    /// Clients that acknowledged the final block and left the session
+    pub finished_clients: usize,
+    /// Blocks in the file; 0 until the transfer has read it
+    pub total_blocks: u16,
+    /// DATA packets sent to the group, not counting retransmissions
+    pub blocks_sent: u64,
+    /// DATA packets sent again for clients that missed them
+    pub retransmits: u64,
 }
 
 /// Multicast session state
@@ -98,10 +151,17 @@ pub struct MulticastSession {
     /// Master client address
     master_client: Option<SocketAddr>,
     /// Total number of blocks in file
-    #[allow(dead_code)]
     total_blocks: u16,
     /// Blocks that need retransmission
     retransmit_queue: HashSet<u16>,
+    /// Clients that joined over the session's lifetime
+    joined_clients: usize,
+    /// Clients that acknowledged the final block and left
+    finished_clients: usize,
+    /// DATA packets sent to the group, not counting retransmissions
+    blocks_sent: u64,
+    /// DATA packets sent again for clients that missed them
+    retransmits: u64,
 }
 
 impl MulticastSession {
@@ -136,6 +196,10 @@ impl MulticastSession {
             master_client: None,
             total_blocks: 0,
             retransmit_queue: HashSet::new(),
+            joined_clients: 0,
+            finished_clients: 0,
+            blocks_sent: 0,
+            retransmits: 0,
         }
     }
 
@@ -171,6 +235,7 @@ impl MulticastSession {
         }
 
         self.clients.insert(addr, ClientState::new(addr, is_master));
+        self.joined_clients += 1;
         info!(
             "Session {}: added client {} ({}/{} clients)",
             self.session_id,
@@ -186,16 +251,33 @@ impl MulticastSession {
     ///
     /// RFC 2090: Track which blocks each client has received
     ///
+    /// Returns whether the ACK was for the final block, so the client has
+    /// the whole file and can [`finish_client`](Self::finish_client).
+    ///
     /// NIST Controls:
     /// - AU-3: Content of Audit Records (ACK tracking)
     /// - SC-5(2): Capacity, Bandwidth, and Redundancy (per-client state)
-    pub fn record_ack(&mut self, addr: SocketAddr, block_num: u16) {
-        if let Some(client) = self.clients.get_mut(&addr) {
-            client.mark_acked(block_num);
-            debug!(
-                "Session {}: client {} ACKed block {}",
-                self.session_id, addr, block_num
-            );
+    pub fn record_ack(&mut self, addr: SocketAddr, block_num: u16) -> bool {
+        let Some(client) = self.clients.get_mut(&addr) else {
+            return false;
+        };
+        client.mark_acked(block_num);
+        debug!(
+            "Session {}: client {} ACKed block {}",
+            self.session_id, addr, block_num
+        );
+        self.total_blocks != 0 && block_num == self.total_blocks
+    }
+
+    /// Remove a client that has received the whole file
+    ///
+    /// RFC 2090: A finishing master hands the role to another client
+    ///
+    /// NIST Controls:
+    /// - AU-2: Audit Events (client completion and master promotion)
+    pub fn finish_client(&mut self, addr: SocketAddr, audit_enabled: bool) {
+        if self.remove_client(addr, "completed", audit_enabled) {
+            self.finished_clients += 1;
         }
     }
 
@@ -276,23 +358,40 @@ impl MulticastSession {
                 "Session {}: removing inactive client {}",
                 self.session_id, addr
             );
-            self.clients.remove(&addr);
+            self.remove_client(addr, "timeout", audit_enabled);
+        }
+    }
 
-            // Audit log: Client removed
-            if audit_enabled {
-                AuditLogger::multicast_client_removed(
-                    &self.session_id,
-                    addr,
-                    "timeout",
-                    self.clients.len(),
-                );
-            }
+    /// Remove a client, electing a new master if it held the role
+    ///
+    /// Returns whether the client was in the session.
+    ///
+    /// NIST Controls:
+    /// - AU-2: Audit Events (client removal logging)
+    fn remove_client(&mut self, addr: SocketAddr, reason: &str, audit_enabled: bool) -> bool {
+        if self.clients.remove(&addr).is_none() {
+            return false;
+        }
+        info!(
+            "Session {}: client {} left ({})",
+            self.session_id, addr, reason
+        );
 
-            // RFC 2090: Elect new master if master client times out
-            if Some(addr) == self.master_client {
-                self.elect_new_master();
-            }
+        // Audit log: Client removed
+        if audit_enabled {
+            AuditLogger::multicast_client_removed(
+                &self.session_id,
+                addr,
+                reason,
+                self.clients.len(),
+            );
+        }
+
+        // RFC 2090: Elect new master if the master client leaves
+        if Some(addr) == self.master_client {
+            self.elect_new_master(audit_enabled);
         }
+        true
     }
 
     /// Elect a new master client
@@ -302,17 +401,22 @@ impl MulticastSession {
     /// NIST Controls:
     /// - AC-3: Access Enforcement (master role assignment)
     /// - AU-2: Audit Events (master election logging)
-    fn elect_new_master(&mut self) {
+    fn elect_new_master(&mut self, audit_enabled: bool) {
         // Clear old master
-        if let Some(old_master) = self.master_client
+        let old_master = self.master_client;
+        if let Some(old_master) = old_master
             && let Some(client) = self.clients.get_mut(&old_master)
         {
             client.is_master = false;
         }
 
-        // Elect first available client as new master
-        if let Some((addr, _)) = self.clients.iter().next() {
-            let new_master = *addr;
+        // Elect the longest-joined client as new master
+        let next = self
+            .clients
+            .values()
+            .min_by_key(|client| (client.joined_at, client.addr))
+            .map(|client| client.addr);
+        if let Some(new_master) = next {
             self.master_client = Some(new_master);
             if let Some(client) = self.clients.get_mut(&new_master) {
                 client.is_master = true;
@@ -321,6 +425,16 @@ impl MulticastSession {
                 "Session {}: elected new master client {}",
                 self.session_id, new_master
             );
+
+            // Audit log: Master role passed on
+            if audit_enabled && let Some(old_master) = old_master {
+                AuditLogger::multicast_master_promoted(
+                    &self.session_id,
+                    new_master,
+                    old_master,
+                    self.clients.len(),
+                );
+            }
         } else {
             self.master_client = None;
             info!(
@@ -344,6 +458,29 @@ impl MulticastSession {
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
+
+    /// Current membership and progress of the session
+    ///
+    /// NIST Controls:
+    /// - SI-4: System Monitoring (session state for operators)
+    pub fn snapshot(&self) -> MulticastSessionSnapshot {
+        let mut clients: Vec<MulticastClientSnapshot> =
+            self.clients.values().map(ClientState::snapshot).collect();
+        clients.sort_by_key(|client| (client.joined_at, client.addr));
+
+        MulticastSessionSnapshot {
+            session_id: self.session_id.clone(),
+            filename: self.file_path.display().to_string(),
+            multicast_addr: self.multicast_addr,
+            multicast_port: self.multicast_port,
+            master_client: self.master_client,
+            clients,
+            finished_clients: self.finished_clients,
+            total_blocks: self.total_blocks,
+            blocks_sent: self.blocks_sent,
+            retransmits: self.retransmits,
+        }
+    }
 }
 
 /// Multicast TFTP server manager
@@ -409,14 +546,17 @@ impl MulticastTftpServer {
             .await
             .map_err(|_| TftpError::Tftp("File not found".to_string()))?;
 
-        // Find or create multicast session for this file
-        let session_key = format!("{}:{:?}", filename, mode);
+        // Find or create multicast session for this file; clients of different
+        // address families cannot share a group, so each family gets its own session
+        let multicast_addr = self.group_for_client(client_addr);
+        let session_key = format!("{}:{:?}:{}", filename, mode, multicast_addr);
         let session = self
             .get_or_create_session(
                 session_key.clone(),
                 file_path.clone(),
                 mode.clone(),
                 options.clone(),
+                multicast_addr,
             )
             .await?;
 
@@ -440,23 +580,144 @@ impl MulticastTftpServer {
 
         drop(session_lock);
 
+        // RFC 2090: The client ACKs to the port the OACK came from
+        tokio::spawn(Self::receive_acks(
+            Arc::clone(&session),
+            response_socket,
+            client_addr,
+            Duration::from_secs(self.config.master_timeout_secs * 2),
+            self.audit_enabled,
+        ));
+
         // If this is the first client (master), start the transfer
         if is_master {
             let session_clone = Arc::clone(&session);
+            let sessions = Arc::clone(&self.sessions);
             let config = self.config.clone();
             let audit_enabled = self.audit_enabled;
             tokio::spawn(async move {
                 if let Err(e) =
-                    Self::run_multicast_transfer(session_clone, config, audit_enabled).await
+                    Self::run_multicast_transfer(Arc::clone(&session_clone), config, audit_enabled)
+                        .await
                 {
                     error!("Multicast transfer failed: {}", e);
                 }
+
+                // Later requests for the file start a new session
+                let mut sessions = sessions.write().await;
+                if sessions
+                    .get(&session_key)
+                    .is_some_and(|current| Arc::ptr_eq(current, &session_clone))
+                {
+                    sessions.remove(&session_key);
+                }
             });
         }
 
         Ok(())
     }
 
+    /// Current membership and progress of every multicast session
+    ///
+    /// Sessions are ordered by file name, then session ID.
+    ///
+    /// NIST Controls:
+    /// - SI-4: System Monitoring (multicast group membership)
+    /// - AU-3: Content of Audit Records (master client and retransmissions)
+    pub async fn sessions_snapshot(&self) -> Vec<MulticastSessionSnapshot> {
+        let sessions: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        let mut snapshots = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            snapshots.push(session.read().await.snapshot());
+        }
+        snapshots.sort_by(|a, b| (&a.filename, &a.session_id).cmp(&(&b.filename, &b.session_id)));
+        snapshots
+    }
+
+    /// Log a snapshot of every multicast session, one line per session
+    ///
+    /// NIST Controls:
+    /// - SI-4: System Monitoring (on-demand session dump)
+    pub async fn log_sessions(&self) {
+        let snapshots = self.sessions_snapshot().await;
+        info!("{} multicast session(s) active", snapshots.len());
+        for snapshot in snapshots {
+            match serde_json::to_string(&snapshot) {
+                Ok(json) => {
+                    info!(multicast_session = %json, "Multicast session {}", snapshot.session_id)
+                }
+                Err(e) => warn!("Failed to serialize multicast session: {}", e),
+            }
+        }
+    }
+
+    /// Record the ACKs a client sends back on its response socket
+    ///
+    /// RFC 2090: The client leaves the session once it acknowledges the
+    /// final block. An ERROR packet removes it at once; after `idle` without
+    /// a packet the listener stops and the inactivity sweep takes over.
+    ///
+    /// NIST Controls:
+    /// - AU-3: Content of Audit Records (per-client progress)
+    /// - SC-5: Denial of Service Protection (idle listeners end)
+    async fn receive_acks(
+        session: Arc<RwLock<MulticastSession>>,
+        socket: Arc<UdpSocket>,
+        client_addr: SocketAddr,
+        idle: Duration,
+        audit_enabled: bool,
+    ) {
+        let mut buf = [0u8; 516];
+        loop {
+            let len = match timeout(idle, socket.recv(&mut buf)).await {
+                Ok(Ok(len)) => len,
+                Ok(Err(e)) => {
+                    debug!("Multicast client {} receive failed: {}", client_addr, e);
+                    break;
+                }
+                Err(_) => break,
+            };
+            if len < 4 {
+                continue;
+            }
+
+            let opcode = u16::from_be_bytes([buf[0], buf[1]]);
+            let mut session = session.write().await;
+            if opcode == TftpOpcode::Ack as u16 {
+                let block_num = u16::from_be_bytes([buf[2], buf[3]]);
+                if session.record_ack(client_addr, block_num) {
+                    session.finish_client(client_addr, audit_enabled);
+                    break;
+                }
+            } else if opcode == TftpOpcode::Error as u16 {
+                session.remove_client(client_addr, "client error", audit_enabled);
+                break;
+            }
+        }
+    }
+
+    /// Select the multicast group for a client
+    ///
+    /// A client can only join a group in its own address family. IPv4 clients
+    /// (including v4-mapped addresses seen on a dual-stack listener) of a
+    /// server configured with an IPv6 group are given the default IPv4 group,
+    /// and IPv6 clients of an IPv4-configured server the default IPv6 group.
+    ///
+    /// NIST Controls:
+    /// - SC-7: Boundary Protection (group scoped to the client's network)
+    fn group_for_client(&self, client_addr: SocketAddr) -> IpAddr {
+        let configured = self.config.multicast_addr;
+        let client_is_v4 = client_addr.ip().to_canonical().is_ipv4();
+
+        if configured.is_ipv4() == client_is_v4 {
+            configured
+        } else if client_is_v4 {
+            default_multicast_addr_for_version(MulticastIpVersion::V4)
+        } else {
+            default_multicast_addr_for_version(MulticastIpVersion::V6)
+        }
+    }
+
     /// Get existing session or create new one
     ///
     /// NIST Controls:
@@ -468,6 +729,7 @@ impl MulticastTftpServer {
         file_path: PathBuf,
         mode: TransferMode,
         options: TftpOptions,
+        multicast_addr: IpAddr,
     ) -> Result<Arc<RwLock<MulticastSession>>> {
         let mut sessions = self.sessions.write().await;
 
@@ -480,7 +742,7 @@ impl MulticastTftpServer {
             file_path.clone(),
             mode,
             options,
-            self.config.multicast_addr,
+            multicast_addr,
             self.config.multicast_port,
             self.config.max_clients,
         )));
@@ -491,7 +753,7 @@ impl MulticastTftpServer {
             AuditLogger::multicast_session_created(
                 session_lock.session_id(),
                 &file_path.display().to_string(),
-                &self.config.multicast_addr.to_string(),
+                &multicast_addr.to_string(),
                 self.config.multicast_port,
             );
         }
@@ -561,8 +823,9 @@ impl MulticastTftpServer {
         config: MulticastConfig,
         audit_enabled: bool,
     ) -> Result<()> {
+        let started = Instant::now();
         let (file_data, multicast_addr, multicast_port, block_size, mode) = {
-            let session_lock = session.read().await;
+            let mut session_lock = session.write().await;
             let mut file = File::open(&session_lock.file_path).await?;
 
             // Read and optionally convert file data
@@ -575,6 +838,9 @@ impl MulticastTftpServer {
                 file.read_to_end(&mut raw_data).await?;
                 raw_data
             };
+            // Block numbers wrap like the ones sent
+            session_lock.total_blocks =
+                file_data.len().div_ceil(session_lock.options.block_size) as u16;
 
             (
                 file_data,
@@ -606,6 +872,7 @@ impl MulticastTftpServer {
                 multicast_port,
             )
             .await?;
+            session.write().await.blocks_sent += 1;
 
             // Wait for all clients to ACK (with timeout)
             let ack_result = timeout(retransmit_timeout, async {
@@ -656,8 +923,8 @@ impl MulticastTftpServer {
         }
 
         // Handle retransmissions
-        Self::handle_retransmissions(
-            session,
+        let retransmission_rounds = Self::handle_retransmissions(
+            Arc::clone(&session),
             &socket,
             &file_data,
             block_size,
@@ -666,6 +933,19 @@ impl MulticastTftpServer {
         )
         .await?;
 
+        // Audit log: Multicast session completed
+        if audit_enabled {
+            let session_lock = session.read().await;
+            AuditLogger::multicast_session_completed(
+                session_lock.session_id(),
+                session_lock.total_blocks,
+                session_lock.joined_clients,
+                started.elapsed().as_millis() as u64,
+                file_data.len() as u64,
+                retransmission_rounds,
+            );
+        }
+
         Ok(())
     }
 
@@ -743,6 +1023,8 @@ impl MulticastTftpServer {
     ///
     /// RFC 2090: Selective retransmission for missed blocks
     ///
+    /// Returns the number of retransmission rounds run.
+    ///
     /// NIST Controls:
     /// - SC-5(2): Capacity, Bandwidth, and Redundancy (targeted retransmission)
     async fn handle_retransmissions(
@@ -752,8 +1034,9 @@ impl MulticastTftpServer {
         block_size: usize,
         config: MulticastConfig,
         audit_enabled: bool,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         let max_retries = 3;
+        let mut rounds = 0;
         let retry_timeout = Duration::from_secs(config.retransmit_timeout_secs);
 
         for retry in 0..max_retries {
@@ -766,6 +1049,7 @@ impl MulticastTftpServer {
             if retransmit_blocks.is_empty() {
                 break;
             }
+            rounds += 1;
 
             info!(
                 "Retransmission round {} for {} blocks: {:?}",
@@ -800,7 +1084,7 @@ impl MulticastTftpServer {
                 let bytes_to_send = std::cmp::min(block_size, file_data.len() - offset);
                 let block_data = &file_data[offset..offset + bytes_to_send];
 
-                let session_lock = session.read().await;
+                let mut session_lock = session.write().await;
                 Self::send_multicast_data(
                     socket,
                     block_num,
@@ -809,6 +1093,7 @@ impl MulticastTftpServer {
                     session_lock.multicast_port,
                 )
                 .await?;
+                session_lock.retransmits += 1;
                 drop(session_lock);
 
                 // Wait for ACKs
@@ -816,7 +1101,7 @@ impl MulticastTftpServer {
             }
         }
 
-        Ok(())
+        Ok(rounds)
     }
 
     /// Validate and resolve file path for multicast transfers
@@ -884,3 +1169,176 @@ impl MulticastTftpServer {
         Ok(file_path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    /// Formatted log output shared with the test
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn session() -> MulticastSession {
+        MulticastSession::new(
+            PathBuf::from("/srv/tftp/boot.wim"),
+            TransferMode::Octet,
+            TftpOptions::default(),
+            "239.255.0.1".parse().unwrap(),
+            1758,
+            10,
+        )
+    }
+
+    #[test]
+    fn test_finishing_master_promotes_next_client() {
+        let first: SocketAddr = "10.0.0.1:2001".parse().unwrap();
+        let second: SocketAddr = "10.0.0.2:2002".parse().unwrap();
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        let snapshot = tracing::subscriber::with_default(subscriber, || {
+            let mut session = session();
+            session.total_blocks = 2;
+            assert!(session.add_client(first).unwrap());
+            assert!(!session.add_client(second).unwrap());
+
+            assert!(!session.record_ack(first, 1));
+            assert!(session.record_ack(first, 2));
+            session.finish_client(first, true);
+            session.snapshot()
+        });
+
+        assert_eq!(snapshot.master_client, Some(second));
+        assert_eq!(snapshot.finished_clients, 1);
+        assert_eq!(snapshot.clients.len(), 1);
+        assert_eq!(snapshot.clients[0].addr, second);
+        assert!(snapshot.clients[0].is_master);
+
+        let log = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            log.contains(r#""event_type":"multicast_client_removed""#),
+            "{}",
+            log
+        );
+        assert!(log.contains(r#""reason":"completed""#), "{}", log);
+        assert!(
+            log.contains(r#""event_type":"multicast_master_promoted""#),
+            "{}",
+            log
+        );
+        assert!(
+            log.contains(&format!(r#""previous_master":"{}""#, first)),
+            "{}",
+            log
+        );
+    }
+
+    /// Join `server`'s session for `filename` as a client bound to `client`
+    ///
+    /// Returns the address of the response socket the client ACKs to.
+    async fn join(server: &MulticastTftpServer, client: &UdpSocket, filename: &str) -> SocketAddr {
+        let client_addr = client.local_addr().unwrap();
+        let response_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        response_socket.connect(client_addr).await.unwrap();
+        server
+            .handle_multicast_request(
+                filename.to_string(),
+                TransferMode::Octet,
+                TftpOptions::default(),
+                client_addr,
+                response_socket,
+            )
+            .await
+            .unwrap();
+
+        let mut buf = [0u8; 516];
+        let (len, from) = timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .expect("OACK should arrive")
+            .unwrap();
+        assert_eq!(
+            u16::from_be_bytes([buf[0], buf[1]]),
+            TftpOpcode::Oack as u16
+        );
+        assert!(buf[2..len].starts_with(b"multicast\0"));
+        from
+    }
+
+    /// Poll the snapshot of the only session until `ready` holds
+    async fn wait_for(
+        server: &MulticastTftpServer,
+        ready: impl Fn(&MulticastSessionSnapshot) -> bool,
+    ) -> MulticastSessionSnapshot {
+        for _ in 0..50 {
+            if let [snapshot] = server.sessions_snapshot().await.as_slice()
+                && ready(snapshot)
+            {
+                return snapshot.clone();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("session never reached the expected state");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_snapshot_tracks_membership_and_master() {
+        let dir = std::env::temp_dir().join(format!(
+            "snow_owl_tftp_multicast_test_{}",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("bootx64.efi"), vec![0x5a; 100]).unwrap();
+        let config = MulticastConfig {
+            enabled: true,
+            multicast_addr: "239.255.0.1".parse().unwrap(),
+            ..Default::default()
+        };
+        let server = MulticastTftpServer::new(config, dir.clone(), true);
+
+        let first = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let first_addr = first.local_addr().unwrap();
+        let second_addr = second.local_addr().unwrap();
+        let first_ack_to = join(&server, &first, "bootx64.efi").await;
+        join(&server, &second, "bootx64.efi").await;
+
+        // One block for a 100 byte file, read once the transfer starts
+        let snapshot = wait_for(&server, |s| s.total_blocks == 1).await;
+        assert!(snapshot.filename.ends_with("bootx64.efi"));
+        assert_eq!(snapshot.multicast_addr.to_string(), "239.255.0.1");
+        assert_eq!(snapshot.multicast_port, 1758);
+        assert_eq!(snapshot.master_client, Some(first_addr));
+        let members: Vec<_> = snapshot.clients.iter().map(|c| c.addr).collect();
+        assert_eq!(members, [first_addr, second_addr]);
+        assert!(snapshot.clients[0].is_master);
+        assert!(!snapshot.clients[1].is_master);
+        assert!(snapshot.clients[0].joined_at <= snapshot.clients[1].joined_at);
+
+        // The master ACKs the final block and leaves
+        first.send_to(&[0, 4, 0, 1], first_ack_to).await.unwrap();
+        let snapshot = wait_for(&server, |s| s.finished_clients == 1).await;
+        assert_eq!(snapshot.master_client, Some(second_addr));
+        assert_eq!(snapshot.clients.len(), 1);
+        assert_eq!(snapshot.clients[0].addr, second_addr);
+        assert!(snapshot.clients[0].is_master);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
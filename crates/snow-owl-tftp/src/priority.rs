@@ -0,0 +1,389 @@
+//! Transfer priority classes for mass boot
+//!
+//! When a rack boots at once, the small files that unblock the next boot
+//! stage (BCD, `boot.sdi`, an iPXE script) queue behind multi-gigabyte image
+//! transfers even though each needs only a handful of packets. Read requests
+//! are classified as [`PriorityClass::Critical`] or [`PriorityClass::Bulk`]
+//! by filename pattern and file size, and with prioritization enabled:
+//!
+//! - Pacing: critical windows are charged to the shared budget
+//!   (`max_bytes_per_sec_total`) but never wait for it; the bulk transfers
+//!   behind them absorb the debt.
+//! - Dispatch: a critical request whose worker queue is full is handed to
+//!   any worker with room instead of being dropped.
+//!
+//! Per-class counters and completion latency are kept either way, so the
+//! effect of turning prioritization on can be measured.
+//!
+//! NIST 800-53 Controls:
+//! - SC-5: Denial of Service Protection (bulk transfers cannot starve boot files)
+//! - SC-6: Resource Availability (priority-based resource allocation)
+
+use crate::config::{PriorityConfig, TftpConfig};
+use crate::throttle::TokenBucket;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+use tracing::info;
+
+/// Completion latencies kept per class for the percentile
+const LATENCY_SAMPLES: usize = 1024;
+
+/// Scheduling class of a read transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PriorityClass {
+    /// Small boot-path files that gate the next boot stage
+    Critical,
+    /// Everything else (images, drivers, installers)
+    Bulk,
+}
+
+impl PriorityClass {
+    pub const ALL: [Self; 2] = [Self::Critical, Self::Bulk];
+
+    /// Name used in logs and metrics
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Critical => "critical",
+            Self::Bulk => "bulk",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Critical => 0,
+            Self::Bulk => 1,
+        }
+    }
+}
+
+/// Assigns a [`PriorityClass`] from the configured patterns and size threshold
+#[derive(Debug, Clone, Default)]
+pub struct Classifier {
+    patterns: Vec<glob::Pattern>,
+    max_bytes: u64,
+}
+
+impl Classifier {
+    /// Build from configuration; patterns were checked by `validate_config`,
+    /// so any that fail to parse here are skipped
+    pub fn new(config: &PriorityConfig) -> Self {
+        Self {
+            patterns: config
+                .critical_patterns
+                .iter()
+                .filter_map(|p| glob::Pattern::new(p).ok())
+                .collect(),
+            max_bytes: config.critical_max_bytes,
+        }
+    }
+
+    /// Class of `filename`, with `size` the file length when it is known
+    ///
+    /// A file is critical if its path (relative to the root, `/` separated)
+    /// or its final component matches a pattern, or if it is no larger than
+    /// `critical_max_bytes`.
+    pub fn classify(&self, filename: &str, size: Option<u64>) -> PriorityClass {
+        let path = filename.replace('\\', "/");
+        let path = path.trim_start_matches('/');
+        let base = path.rsplit('/').next().unwrap_or(path);
+
+        let by_name = self
+            .patterns
+            .iter()
+            .any(|p| p.matches(path) || p.matches(base));
+        let by_size = self.max_bytes > 0 && size.is_some_and(|s| s <= self.max_bytes);
+
+        if by_name || by_size {
+            PriorityClass::Critical
+        } else {
+            PriorityClass::Bulk
+        }
+    }
+
+    /// Class of a raw datagram from its filename alone, or `None` if it is
+    /// not a read request
+    ///
+    /// Used before the request is parsed, when the file size is not known.
+    pub fn classify_request(&self, packet: &[u8]) -> Option<PriorityClass> {
+        // RFC 1350: RRQ is opcode 1
+        let (opcode, rest) = packet.split_at_checked(2)?;
+        if opcode != [0, 1] {
+            return None;
+        }
+        let filename = rest.split(|&b| b == 0).next()?;
+        Some(self.classify(&String::from_utf8_lossy(filename), None))
+    }
+}
+
+/// Counters for one class
+#[derive(Debug, Default)]
+struct ClassStats {
+    requests: AtomicU64,
+    completed: AtomicU64,
+    failed: AtomicU64,
+    latencies_ms: Mutex<VecDeque<u64>>,
+}
+
+impl ClassStats {
+    fn record_latency(&self, ms: u64) {
+        let mut samples = self
+            .latencies_ms
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if samples.len() == LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(ms);
+    }
+
+    fn p95_ms(&self) -> Option<u64> {
+        let mut samples: Vec<u64> = self
+            .latencies_ms
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .copied()
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let rank = (samples.len() * 95).div_ceil(100);
+        Some(samples[rank - 1])
+    }
+}
+
+/// Point-in-time view of one class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClassSnapshot {
+    pub requests: u64,
+    pub completed: u64,
+    pub failed: u64,
+    /// 95th percentile completion time over the most recent transfers
+    pub p95_ms: Option<u64>,
+}
+
+/// Classification, shared pacing and per-class metrics for read transfers
+#[derive(Debug)]
+pub struct TransferScheduler {
+    enabled: bool,
+    classifier: Classifier,
+    /// Budget shared by all read transfers (`max_bytes_per_sec_total`)
+    pacer: Option<Mutex<TokenBucket>>,
+    stats: [ClassStats; 2],
+}
+
+impl TransferScheduler {
+    pub fn new(config: &TftpConfig) -> Self {
+        Self {
+            enabled: config.priority.enabled,
+            classifier: Classifier::new(&config.priority),
+            // A tenth of a second of burst keeps the aggregate rate smooth
+            pacer: config
+                .max_bytes_per_sec_total
+                .map(|rate| Mutex::new(TokenBucket::new(rate, rate / 10))),
+            stats: Default::default(),
+        }
+    }
+
+    /// Whether critical transfers are scheduled ahead of bulk ones
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn classifier(&self) -> &Classifier {
+        &self.classifier
+    }
+
+    /// Start tracking a transfer of `class` that was requested at `started`
+    pub fn begin(self: &Arc<Self>, class: PriorityClass, started: Instant) -> TransferTicket {
+        self.stats[class.index()]
+            .requests
+            .fetch_add(1, Ordering::Relaxed);
+        TransferTicket {
+            scheduler: Arc::clone(self),
+            class,
+            started,
+            done: false,
+        }
+    }
+
+    /// Wait until a window of `bytes` may be sent under the shared budget
+    ///
+    /// Every window is charged so the aggregate rate holds, but with
+    /// prioritization enabled a critical window is sent immediately.
+    pub async fn pace(&self, class: PriorityClass, bytes: u64) {
+        let Some(pacer) = &self.pacer else {
+            return;
+        };
+        let delay = pacer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .reserve(bytes, tokio::time::Instant::now());
+
+        if self.enabled && class == PriorityClass::Critical {
+            return;
+        }
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    pub fn snapshot(&self, class: PriorityClass) -> ClassSnapshot {
+        let stats = &self.stats[class.index()];
+        ClassSnapshot {
+            requests: stats.requests.load(Ordering::Relaxed),
+            completed: stats.completed.load(Ordering::Relaxed),
+            failed: stats.failed.load(Ordering::Relaxed),
+            p95_ms: stats.p95_ms(),
+        }
+    }
+
+    /// Log the per-class counters
+    pub fn log_summary(&self) {
+        for class in PriorityClass::ALL {
+            let s = self.snapshot(class);
+            info!(
+                "Transfer class {}: {} requested, {} completed, {} failed, p95 {}",
+                class.name(),
+                s.requests,
+                s.completed,
+                s.failed,
+                s.p95_ms
+                    .map_or_else(|| "n/a".to_string(), |ms| format!("{}ms", ms))
+            );
+        }
+    }
+}
+
+/// One tracked transfer; dropping it without calling
+/// [`complete`](Self::complete) counts it as failed
+#[derive(Debug)]
+pub struct TransferTicket {
+    scheduler: Arc<TransferScheduler>,
+    class: PriorityClass,
+    started: Instant,
+    done: bool,
+}
+
+impl TransferTicket {
+    pub fn class(&self) -> PriorityClass {
+        self.class
+    }
+
+    /// Wait for the shared budget before sending a window of `bytes`
+    pub async fn pace(&self, bytes: u64) {
+        self.scheduler.pace(self.class, bytes).await;
+    }
+
+    /// Record a successful transfer and its completion time
+    pub fn complete(mut self) {
+        self.done = true;
+        let stats = &self.scheduler.stats[self.class.index()];
+        stats.completed.fetch_add(1, Ordering::Relaxed);
+        stats.record_latency(self.started.elapsed().as_millis() as u64);
+    }
+}
+
+impl Drop for TransferTicket {
+    fn drop(&mut self) {
+        if !self.done {
+            self.scheduler.stats[self.class.index()]
+                .failed
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(enabled: bool) -> TftpConfig {
+        TftpConfig {
+            max_bytes_per_sec_total: Some(1000),
+            priority: PriorityConfig {
+                enabled,
+                critical_patterns: vec!["boot.sdi".to_string(), "**/*.ipxe".to_string()],
+                critical_max_bytes: 4096,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_classify_by_pattern_and_size() {
+        let classifier = Classifier::new(&config(true).priority);
+
+        assert_eq!(
+            classifier.classify("boot/boot.sdi", Some(3_170_304)),
+            PriorityClass::Critical
+        );
+        assert_eq!(
+            classifier.classify("\\menus\\main.ipxe", None),
+            PriorityClass::Critical
+        );
+        assert_eq!(
+            classifier.classify("bcd", Some(262)),
+            PriorityClass::Critical
+        );
+        assert_eq!(
+            classifier.classify("sources/boot.wim", Some(1 << 30)),
+            PriorityClass::Bulk
+        );
+        assert_eq!(classifier.classify("bcd", None), PriorityClass::Bulk);
+
+        assert_eq!(
+            classifier.classify_request(b"\x00\x01boot.sdi\x00octet\x00"),
+            Some(PriorityClass::Critical)
+        );
+        assert_eq!(
+            classifier.classify_request(b"\x00\x02boot.sdi\x00octet\x00"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ticket_counts_and_p95() {
+        let scheduler = Arc::new(TransferScheduler::new(&config(true)));
+
+        for ms in 1..=100 {
+            let started = Instant::now() - Duration::from_millis(ms);
+            scheduler.begin(PriorityClass::Bulk, started).complete();
+        }
+        drop(scheduler.begin(PriorityClass::Bulk, Instant::now()));
+
+        let bulk = scheduler.snapshot(PriorityClass::Bulk);
+        assert_eq!((bulk.requests, bulk.completed, bulk.failed), (101, 100, 1));
+        // Elapsed time only grows, so the 95th sample is at least 95ms
+        assert!(
+            (95..200).contains(&bulk.p95_ms.unwrap()),
+            "{:?}",
+            bulk.p95_ms
+        );
+
+        let critical = scheduler.snapshot(PriorityClass::Critical);
+        assert_eq!(critical, ClassSnapshot::default());
+    }
+
+    #[tokio::test]
+    async fn test_pace_never_delays_critical_when_enabled() {
+        for (enabled, expect_wait) in [(true, false), (false, true)] {
+            let mut config = config(enabled);
+            config.max_bytes_per_sec_total = Some(100_000);
+            let scheduler = TransferScheduler::new(&config);
+
+            let start = Instant::now();
+            scheduler.pace(PriorityClass::Critical, 10_000).await;
+            assert_eq!(start.elapsed() >= Duration::from_millis(90), expect_wait);
+
+            // Bulk always waits, and pays for the critical window too
+            let start = Instant::now();
+            scheduler.pace(PriorityClass::Bulk, 10_000).await;
+            assert!(start.elapsed() >= Duration::from_millis(90));
+        }
+    }
+}
@@ -0,0 +1,628 @@
+// RFC 7440 windowed receive path for write requests (WRQ)
+//
+// The sender transmits up to `windowsize` DATA blocks before waiting for an
+// ACK. A lost datagram inside a window shows up at the receiver as a block
+// from further ahead than expected. RFC 7440 Section 4 requires the receiver
+// to discard such blocks and ACK the last block received in order, so the
+// sender restarts the window from there.
+//
+// NIST 800-53 Controls:
+// - SC-5: Denial of Service Protection (bounded retries and file size)
+// - SI-10: Information Input Validation (block sequence validation)
+
+use crate::{MAX_PACKET_SIZE, Opcode};
+use bytes::{BufMut, BytesMut};
+use std::future::Future;
+use std::io;
+use std::os::fd::AsFd;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::UdpSocket;
+use tracing::debug;
+
+/// Connected datagram transport used by the receive loop
+///
+/// Implemented for `tokio::net::UdpSocket`; tests substitute a lossy mock.
+pub trait DatagramSocket {
+    fn send(&self, buf: &[u8]) -> impl Future<Output = io::Result<usize>> + Send;
+    fn recv(&self, buf: &mut [u8]) -> impl Future<Output = io::Result<usize>> + Send;
+}
+
+impl DatagramSocket for UdpSocket {
+    fn send(&self, buf: &[u8]) -> impl Future<Output = io::Result<usize>> + Send {
+        UdpSocket::send(self, buf)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> impl Future<Output = io::Result<usize>> + Send {
+        UdpSocket::recv(self, buf)
+    }
+}
+
+/// Parameters for a windowed receive
+#[derive(Debug, Clone, Copy)]
+pub struct ReceiveParams {
+    /// Negotiated block size (RFC 2348)
+    pub block_size: usize,
+    /// Negotiated window size (RFC 7440)
+    pub windowsize: usize,
+    /// Time to wait for the next datagram before re-ACKing
+    pub timeout: Duration,
+    /// Re-ACKs allowed without forward progress before giving up
+    pub max_retries: u32,
+    /// Maximum accepted file size in bytes (0 = unlimited)
+    pub max_file_size: u64,
+    /// Expected size for pre-allocation (RFC 2349 tsize)
+    pub size_hint: Option<u64>,
+    /// DATA block already received before the receive starts, as when a
+    /// server answers a plain RRQ with block 1; 0 when `initial_packet` is
+    /// the OACK or ACK 0
+    pub resume_after: u16,
+}
+
+/// Data received from a completed transfer
+#[derive(Debug)]
+pub struct ReceivedData {
+    /// File contents as sent on the wire
+    pub data: Vec<u8>,
+    /// Block number of the final block
+    pub last_block: u16,
+    /// ACKs re-sent after a timeout, gap or duplicate, each of which asks
+    /// the sender to retransmit
+    pub reacks: u32,
+}
+
+/// Totals of a completed transfer written to a caller's writer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiveSummary {
+    /// Bytes written, as sent on the wire
+    pub bytes: u64,
+    /// Block number of the final block
+    pub last_block: u16,
+    /// ACKs re-sent after a timeout, gap or duplicate
+    pub reacks: u32,
+}
+
+/// Reasons a windowed receive can fail
+#[derive(Debug)]
+pub enum ReceiveError {
+    /// Client aborted the transfer with an ERROR packet
+    ClientError { code: u16, message: String },
+    /// No forward progress after `max_retries` re-ACKs
+    Timeout { expected_block: u16 },
+    /// Accepting the next block would exceed `max_file_size`
+    FileTooLarge { size: u64, last_block: u16 },
+    /// Socket error
+    Io(io::Error),
+}
+
+impl From<io::Error> for ReceiveError {
+    fn from(e: io::Error) -> Self {
+        ReceiveError::Io(e)
+    }
+}
+
+/// Build an ACK packet for `block_num`
+pub fn ack_packet(block_num: u16) -> BytesMut {
+    let mut packet = BytesMut::with_capacity(4);
+    packet.put_u16(Opcode::Ack as u16);
+    packet.put_u16(block_num);
+    packet
+}
+
+/// Receive a file into memory using the RFC 7440 windowed receive state machine
+///
+/// See [`receive_windowed_into`] for the state machine.
+pub async fn receive_windowed<S: DatagramSocket>(
+    socket: &S,
+    initial_packet: &[u8],
+    params: ReceiveParams,
+) -> Result<ReceivedData, ReceiveError> {
+    let mut data = match params.size_hint {
+        Some(size) => Vec::with_capacity(usize::try_from(size).unwrap_or(0).min(1 << 30)),
+        None => Vec::with_capacity(1_048_576),
+    };
+    let summary = receive_windowed_into(socket, initial_packet, params, &mut data).await?;
+    Ok(ReceivedData {
+        data,
+        last_block: summary.last_block,
+        reacks: summary.reacks,
+    })
+}
+
+/// Receive a file into `writer` using the RFC 7440 windowed receive state machine
+///
+/// `initial_packet` is the OACK (or ACK of block 0) that starts the transfer,
+/// or the ACK of `params.resume_after`. It is sent first and re-sent if the
+/// next DATA block never arrives.
+///
+/// State machine:
+/// - In-order block: append, ACK at the end of each window or on the final block
+/// - Block from ahead of the window (gap): discard, ACK the last in-order block once
+/// - Block already received (duplicate): discard, ACK the last in-order block once
+/// - Timer expiry: ACK the last in-order block again
+/// - More than `max_retries` re-ACKs without progress: fail
+pub async fn receive_windowed_into<S: DatagramSocket, W: AsyncWrite + Unpin>(
+    socket: &S,
+    initial_packet: &[u8],
+    params: ReceiveParams,
+    writer: &mut W,
+) -> Result<ReceiveSummary, ReceiveError> {
+    let windowsize = params.windowsize.max(1);
+    let mut received: u64 = 0;
+
+    // Highest block received in order. Block 0 is ambiguous: it is both the
+    // starting point and, after a rollover (65535 -> 0), a real DATA block,
+    // so whether any DATA has arrived is tracked separately.
+    let mut last_good: u16 = params.resume_after;
+    let mut started = params.resume_after != 0;
+    let mut blocks_since_ack: usize = 0;
+    let mut retries: u32 = 0;
+    let mut reack_sent = false;
+    let mut reacks: u32 = 0;
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+
+    socket.send(initial_packet).await?;
+
+    loop {
+        let size = match tokio::time::timeout(params.timeout, socket.recv(&mut buf)).await {
+            Ok(Ok(size)) => size,
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => {
+                retries += 1;
+                if retries > params.max_retries {
+                    return Err(ReceiveError::Timeout {
+                        expected_block: last_good.wrapping_add(1),
+                    });
+                }
+
+                debug!(
+                    "Window timer expired, re-ACKing block {} (retry {}/{})",
+                    last_good, retries, params.max_retries
+                );
+                resend_last(socket, initial_packet, started.then_some(last_good)).await?;
+                reacks += 1;
+                blocks_since_ack = 0;
+                reack_sent = true;
+                continue;
+            }
+        };
+
+        if size < 4 {
+            debug!("Ignoring runt packet ({} bytes)", size);
+            continue;
+        }
+
+        let opcode = u16::from_be_bytes([buf[0], buf[1]]);
+        if opcode == Opcode::Error as u16 {
+            let code = u16::from_be_bytes([buf[2], buf[3]]);
+            let message_bytes = &buf[4..size];
+            let end = message_bytes
+                .iter()
+                .position(|&b| b == 0)
+                .unwrap_or(message_bytes.len());
+            return Err(ReceiveError::ClientError {
+                code,
+                message: String::from_utf8_lossy(&message_bytes[..end]).into_owned(),
+            });
+        }
+
+        if opcode != Opcode::Data as u16 {
+            debug!("Expected DATA, got opcode {}", opcode);
+            continue;
+        }
+
+        let block_num = u16::from_be_bytes([buf[2], buf[3]]);
+        let expected = last_good.wrapping_add(1);
+
+        if block_num != expected {
+            // Blocks up to a window ahead indicate loss; anything else is a
+            // retransmission of data we already have
+            let ahead = block_num.wrapping_sub(expected) as usize;
+            if ahead < windowsize {
+                debug!(
+                    "Gap in window: expected block {}, got {}; discarding",
+                    expected, block_num
+                );
+            } else {
+                debug!("Duplicate block {} (last in order: {})", block_num, last_good);
+            }
+
+            if !reack_sent {
+                retries += 1;
+                if retries > params.max_retries {
+                    return Err(ReceiveError::Timeout {
+                        expected_block: expected,
+                    });
+                }
+                resend_last(socket, initial_packet, started.then_some(last_good)).await?;
+                reacks += 1;
+                blocks_since_ack = 0;
+                reack_sent = true;
+            }
+            continue;
+        }
+
+        let block_data = &buf[4..size];
+        let data_len = block_data.len();
+
+        // NIST SC-5: Enforce cumulative size limit before buffering
+        let new_size = received + data_len as u64;
+        if params.max_file_size > 0 && new_size > params.max_file_size {
+            return Err(ReceiveError::FileTooLarge {
+                size: new_size,
+                last_block: last_good,
+            });
+        }
+
+        writer.write_all(block_data).await?;
+        received = new_size;
+        last_good = block_num;
+        started = true;
+        blocks_since_ack += 1;
+        retries = 0;
+        reack_sent = false;
+
+        // RFC 7440: ACK at the end of each window, RFC 1350: or on the final block
+        let is_final_block = data_len < params.block_size;
+        if blocks_since_ack >= windowsize || is_final_block {
+            socket.send(&ack_packet(block_num)).await?;
+            blocks_since_ack = 0;
+        }
+
+        if is_final_block {
+            return Ok(ReceiveSummary {
+                bytes: received,
+                last_block: block_num,
+                reacks,
+            });
+        }
+    }
+}
+
+/// Re-send the packet acknowledging the last in-order block
+///
+/// Before any DATA has arrived (`last_good` is `None`) this is the packet
+/// that started the transfer (OACK or ACK 0), so a lost OACK is
+/// retransmitted as well.
+async fn resend_last<S: DatagramSocket>(
+    socket: &S,
+    initial_packet: &[u8],
+    last_good: Option<u16>,
+) -> io::Result<()> {
+    match last_good {
+        Some(block) => socket.send(&ack_packet(block)).await?,
+        None => socket.send(initial_packet).await?,
+    };
+    Ok(())
+}
+
+/// Why a WRQ's declared size (RFC 2349 tsize) is refused before any DATA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclaredSizeError {
+    /// Larger than the configured maximum file size
+    ExceedsLimit { size: u64, max: u64 },
+    /// Larger than the space left on the filesystem holding the root directory
+    InsufficientSpace { size: u64, available: u64 },
+}
+
+impl std::fmt::Display for DeclaredSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExceedsLimit { size, max } => {
+                write!(f, "declared size {} exceeds limit of {} bytes", size, max)
+            }
+            Self::InsufficientSpace { size, available } => write!(
+                f,
+                "declared size {} exceeds {} bytes of free space",
+                size, available
+            ),
+        }
+    }
+}
+
+/// Check a WRQ's declared size against the size cap and free space in `root_dir`
+///
+/// A tsize of 0 means the client does not know the size and always passes.
+/// If free space cannot be determined only the cap is enforced.
+///
+/// NIST 800-53 SC-5: Denial of Service Protection (fail before the transfer)
+pub fn check_declared_size(
+    size: u64,
+    max_file_size: u64,
+    root_dir: &Path,
+) -> Result<(), DeclaredSizeError> {
+    if size == 0 {
+        return Ok(());
+    }
+    if max_file_size > 0 && size > max_file_size {
+        return Err(DeclaredSizeError::ExceedsLimit {
+            size,
+            max: max_file_size,
+        });
+    }
+    match available_space(root_dir) {
+        Ok(available) if size > available => {
+            Err(DeclaredSizeError::InsufficientSpace { size, available })
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            debug!(
+                "Cannot determine free space in {}: {}",
+                root_dir.display(),
+                e
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Bytes available to unprivileged writers on the filesystem holding `path`
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let stat = nix::sys::statvfs::statvfs(path).map_err(io::Error::from)?;
+    // Field widths differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let available = (stat.blocks_available() as u64).saturating_mul(stat.fragment_size() as u64);
+    Ok(available)
+}
+
+/// Reserve `len` bytes for the file behind `fd` so a large upload lands in few extents
+///
+/// Uses posix_fallocate where available. Filesystems that cannot
+/// preallocate fall back to extending the file, which keeps the final size
+/// right but allocates nothing.
+pub fn preallocate<Fd: AsFd>(fd: Fd, len: u64) -> io::Result<()> {
+    let len =
+        libc::off_t::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    match nix::fcntl::posix_fallocate(fd.as_fd(), 0, len) {
+        Ok(()) => return Ok(()),
+        Err(nix::errno::Errno::EOPNOTSUPP | nix::errno::Errno::EINVAL) => {}
+        Err(e) => return Err(e.into()),
+    }
+    nix::unistd::ftruncate(fd, len).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashSet, VecDeque};
+    use std::sync::Mutex;
+
+    /// Simulated RFC 7440 sender behind a socket that drops every Nth datagram
+    ///
+    /// Block numbers roll over 65535 -> 0, and ACKs are mapped back to block
+    /// indexes relative to the last ACK, as a real sender would.
+    struct LossySender {
+        blocks: Vec<Vec<u8>>,
+        windowsize: usize,
+        drop_every: usize,
+        /// Block indexes lost on their first transmission only
+        drop_once: Mutex<HashSet<usize>>,
+        state: Mutex<SenderState>,
+    }
+
+    struct SenderState {
+        queue: VecDeque<(usize, Vec<u8>)>,
+        acked: usize,
+        transmitted: usize,
+        dropped: usize,
+    }
+
+    impl LossySender {
+        fn new(data: &[u8], block_size: usize, windowsize: usize, drop_every: usize) -> Self {
+            let mut blocks: Vec<Vec<u8>> = data.chunks(block_size).map(|c| c.to_vec()).collect();
+            if data.len().is_multiple_of(block_size) {
+                blocks.push(Vec::new());
+            }
+            Self {
+                blocks,
+                windowsize,
+                drop_every,
+                drop_once: Mutex::new(HashSet::new()),
+                state: Mutex::new(SenderState {
+                    queue: VecDeque::new(),
+                    acked: 0,
+                    transmitted: 0,
+                    dropped: 0,
+                }),
+            }
+        }
+
+        /// Queue the window following block index `acked` (restarting any window in flight)
+        fn on_ack(&self, acked: usize) {
+            let mut state = self.state.lock().unwrap();
+            state.queue.clear();
+            state.acked = acked;
+            let end = (acked + self.windowsize).min(self.blocks.len());
+            for index in acked..end {
+                let mut packet = BytesMut::new();
+                packet.put_u16(Opcode::Data as u16);
+                packet.put_u16((index + 1) as u16);
+                packet.put_slice(&self.blocks[index]);
+                state.queue.push_back((index, packet.to_vec()));
+            }
+        }
+
+        /// Block index acknowledged by an ACK for wire block number `block`
+        fn acked_index(&self, block: u16) -> usize {
+            let acked = self.state.lock().unwrap().acked;
+            acked + block.wrapping_sub(acked as u16) as usize
+        }
+
+        fn next_datagram(&self) -> Option<Vec<u8>> {
+            let mut state = self.state.lock().unwrap();
+            while let Some((index, packet)) = state.queue.pop_front() {
+                state.transmitted += 1;
+                if state.transmitted.is_multiple_of(self.drop_every)
+                    || self.drop_once.lock().unwrap().remove(&index)
+                {
+                    state.dropped += 1;
+                    continue;
+                }
+                return Some(packet);
+            }
+            None
+        }
+    }
+
+    impl DatagramSocket for LossySender {
+        fn send(&self, buf: &[u8]) -> impl Future<Output = io::Result<usize>> + Send {
+            let len = buf.len();
+            let opcode = u16::from_be_bytes([buf[0], buf[1]]);
+            let acked = if opcode == Opcode::Ack as u16 {
+                self.acked_index(u16::from_be_bytes([buf[2], buf[3]]))
+            } else {
+                // OACK starts the transfer like ACK 0
+                0
+            };
+            self.on_ack(acked);
+            async move { Ok(len) }
+        }
+
+        fn recv(&self, buf: &mut [u8]) -> impl Future<Output = io::Result<usize>> + Send {
+            let next = self.next_datagram();
+            async move {
+                match next {
+                    Some(packet) => {
+                        buf[..packet.len()].copy_from_slice(&packet);
+                        Ok(packet.len())
+                    }
+                    // Sender is waiting for an ACK; let the receiver's timer fire
+                    None => std::future::pending().await,
+                }
+            }
+        }
+    }
+
+    fn params(block_size: usize, windowsize: usize) -> ReceiveParams {
+        ReceiveParams {
+            block_size,
+            windowsize,
+            timeout: Duration::from_millis(50),
+            max_retries: 5,
+            max_file_size: 0,
+            size_hint: None,
+            resume_after: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lossy_window_receives_intact_file() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 256) as u8).collect();
+        let socket = LossySender::new(&data, 512, 8, 7);
+        let oack = [0u8, Opcode::Oack as u8];
+
+        let received = receive_windowed(&socket, &oack, params(512, 8))
+            .await
+            .expect("transfer should survive packet loss");
+
+        assert_eq!(received.data, data);
+        assert_eq!(received.last_block as usize, data.len() / 512 + 1);
+        assert!(socket.state.lock().unwrap().dropped > 0);
+        assert!(received.reacks > 0);
+    }
+
+    #[tokio::test]
+    async fn test_block_size_multiple_ends_with_empty_block() {
+        let data = vec![0xAAu8; 512 * 16];
+        let socket = LossySender::new(&data, 512, 4, 5);
+
+        let received = receive_windowed(&socket, &ack_packet(0), params(512, 4))
+            .await
+            .unwrap();
+
+        assert_eq!(received.data, data);
+        assert_eq!(received.last_block, 17);
+    }
+
+    #[tokio::test]
+    async fn test_no_progress_times_out() {
+        let data = vec![1u8; 4096];
+        // Every datagram is dropped
+        let socket = LossySender::new(&data, 512, 8, 1);
+
+        let result = receive_windowed(&socket, &ack_packet(0), params(512, 8)).await;
+
+        assert!(matches!(
+            result,
+            Err(ReceiveError::Timeout { expected_block: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_file_size_enforced() {
+        let data = vec![1u8; 4096];
+        let socket = LossySender::new(&data, 512, 8, 1000);
+        let mut p = params(512, 8);
+        p.max_file_size = 1024;
+
+        let result = receive_windowed(&socket, &ack_packet(0), p).await;
+
+        assert!(matches!(
+            result,
+            Err(ReceiveError::FileTooLarge { last_block: 2, .. })
+        ));
+    }
+    #[tokio::test]
+    async fn test_window_survives_block_number_wrap() {
+        // 65540 full blocks plus a short one: block numbers run 1..=65535, 0, 1, ..., 5
+        let block_size = 8;
+        let data: Vec<u8> = (0..block_size * 65_540 + 3)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let socket = LossySender::new(&data, block_size, 4, usize::MAX);
+        // Lose a block just before the wrap, and the block after 0 so the
+        // receiver has to re-ACK block 0 itself
+        socket.drop_once.lock().unwrap().extend([65_533, 65_536]);
+
+        // An OACK start makes a mistaken re-send of the initial packet visible
+        let oack = [0u8, Opcode::Oack as u8];
+
+        let received = receive_windowed(&socket, &oack, params(block_size, 4))
+            .await
+            .expect("transfer should survive block number wrap");
+
+        assert_eq!(received.data.len(), data.len());
+        assert!(received.data == data, "file corrupted across wrap");
+        assert_eq!(received.last_block, 5);
+        assert_eq!(socket.state.lock().unwrap().dropped, 2);
+    }
+
+    #[test]
+    fn test_declared_size_checked_against_cap_and_free_space() {
+        let dir = std::env::temp_dir();
+
+        // Unknown size always passes
+        assert_eq!(check_declared_size(0, 100, &dir), Ok(()));
+        assert_eq!(check_declared_size(100, 100, &dir), Ok(()));
+        assert_eq!(
+            check_declared_size(101, 100, &dir),
+            Err(DeclaredSizeError::ExceedsLimit {
+                size: 101,
+                max: 100
+            })
+        );
+        // No cap, but no filesystem has this much room
+        assert!(matches!(
+            check_declared_size(u64::MAX, 0, &dir),
+            Err(DeclaredSizeError::InsufficientSpace { .. })
+        ));
+    }
+
+    #[test]
+    fn test_preallocate_sets_file_length() {
+        let path = std::env::temp_dir().join(format!(
+            "tftp-prealloc-{}-{}",
+            std::process::id(),
+            line!()
+        ));
+        let file = std::fs::File::create(&path).unwrap();
+
+        preallocate(&file, 1_000_000).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 1_000_000);
+
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
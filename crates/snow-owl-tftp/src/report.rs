@@ -0,0 +1,426 @@
+//! Transfer SLA reporting from the audit log
+//!
+//! After a deployment wave, operators want to know how many transfers ran,
+//! how many succeeded, how long they took and how much data moved. All of
+//! that is already in the audit log as `transfer_completed`,
+//! `transfer_failed`, `write_completed`, `write_failed` and
+//! `multicast_session_completed` events, so the report is computed by
+//! replaying the log rather than keeping separate counters.
+//!
+//! The log is read line by line. Both the JSON and text log formats are
+//! understood, as are bare audit event objects (one per line).
+//!
+//! NIST 800-53 Controls:
+//! - AU-6: Audit Review, Analysis, and Reporting
+//! - AU-7: Audit Reduction and Report Generation
+
+use crate::audit::AuditEvent;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Outcome of one transfer, extracted from an audit event
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferRecord {
+    pub timestamp: DateTime<Utc>,
+    pub success: bool,
+    pub bytes: u64,
+    /// Known only for completed transfers
+    pub duration_ms: Option<u64>,
+    /// Transfers this record stands for; above 1 for a success logged
+    /// while success auditing was sampled
+    pub weight: u64,
+}
+
+impl TransferRecord {
+    /// Extract a transfer outcome from an audit event
+    ///
+    /// Returns `None` for events that do not end a transfer, or whose
+    /// timestamp cannot be parsed.
+    pub fn from_event(event: &AuditEvent) -> Option<Self> {
+        let (common, success, bytes, duration_ms, sample_rate) = match event {
+            AuditEvent::TransferCompleted {
+                common,
+                bytes_transferred,
+                duration_ms,
+                sample_rate,
+                ..
+            } => (common, true, *bytes_transferred, Some(*duration_ms), *sample_rate),
+            AuditEvent::MulticastSessionCompleted {
+                common,
+                bytes_transferred,
+                duration_ms,
+                ..
+            } => (common, true, *bytes_transferred, Some(*duration_ms), None),
+            AuditEvent::WriteCompleted {
+                common,
+                bytes_received,
+                duration_ms,
+                sample_rate,
+                ..
+            } => (common, true, *bytes_received, Some(*duration_ms), *sample_rate),
+            AuditEvent::TransferFailed { common, .. } | AuditEvent::WriteFailed { common, .. } => {
+                (common, false, 0, None, None)
+            }
+            _ => return None,
+        };
+
+        let timestamp = DateTime::parse_from_rfc3339(&common.timestamp)
+            .ok()?
+            .with_timezone(&Utc);
+        Some(Self {
+            timestamp,
+            success,
+            bytes,
+            duration_ms,
+            weight: u64::from(sample_rate.unwrap_or(1).max(1)),
+        })
+    }
+}
+
+/// Parse one audit log line into an audit event
+///
+/// Accepts a JSON-format tracing line (`fields.audit_event`), a text-format
+/// line ending in `audit_event={...}`, or a bare audit event object.
+pub fn parse_audit_line(line: &str) -> Option<AuditEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(line) {
+        if let Some(inner) = value
+            .get("fields")
+            .and_then(|fields| fields.get("audit_event"))
+            .and_then(|event| event.as_str())
+        {
+            return serde_json::from_str(inner).ok();
+        }
+        return serde_json::from_value(value).ok();
+    }
+
+    let (_, event) = line.split_once("audit_event=")?;
+    serde_json::Deserializer::from_str(event)
+        .into_iter::<AuditEvent>()
+        .next()?
+        .ok()
+}
+
+/// Aggregate transfer statistics over a time window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlaReport {
+    /// Start of the window (inclusive), if bounded
+    pub since: Option<DateTime<Utc>>,
+    /// End of the window (exclusive), if bounded
+    pub until: Option<DateTime<Utc>>,
+    pub total_transfers: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Percentage of transfers that succeeded (100 when there were none)
+    pub success_rate: f64,
+    /// Median duration of successful transfers
+    pub p50_duration_ms: Option<u64>,
+    /// 95th percentile duration of successful transfers
+    pub p95_duration_ms: Option<u64>,
+    /// Bytes moved by successful transfers
+    pub bytes_transferred: u64,
+}
+
+impl SlaReport {
+    /// Build a report from transfer records falling inside `[since, until)`
+    ///
+    /// Each record counts `weight` times, so a report over a sampled audit
+    /// log estimates the successful transfers that were not logged.
+    pub fn from_records(
+        records: impl IntoIterator<Item = TransferRecord>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        let mut succeeded = 0;
+        let mut failed = 0;
+        let mut bytes_transferred = 0u64;
+        let mut durations = Vec::new();
+
+        let in_window = |at: &DateTime<Utc>| {
+            since.is_none_or(|since| *at >= since) && until.is_none_or(|until| *at < until)
+        };
+        for record in records.into_iter().filter(|r| in_window(&r.timestamp)) {
+            if record.success {
+                succeeded += record.weight;
+                bytes_transferred =
+                    bytes_transferred.saturating_add(record.bytes.saturating_mul(record.weight));
+                durations.extend(record.duration_ms.map(|ms| (ms, record.weight)));
+            } else {
+                failed += record.weight;
+            }
+        }
+        durations.sort_unstable();
+
+        let total_transfers = succeeded + failed;
+        let success_rate = if total_transfers == 0 {
+            100.0
+        } else {
+            succeeded as f64 * 100.0 / total_transfers as f64
+        };
+
+        Self {
+            since,
+            until,
+            total_transfers,
+            succeeded,
+            failed,
+            success_rate,
+            p50_duration_ms: weighted_percentile(&durations, 50),
+            p95_duration_ms: weighted_percentile(&durations, 95),
+            bytes_transferred,
+        }
+    }
+
+    /// Build a report from audit events
+    pub fn from_events(
+        events: impl IntoIterator<Item = AuditEvent>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self::from_records(
+            events
+                .into_iter()
+                .filter_map(|event| TransferRecord::from_event(&event)),
+            since,
+            until,
+        )
+    }
+
+    /// Build a report by streaming an audit log file
+    ///
+    /// Lines that are not audit events are skipped.
+    pub fn from_audit_log(
+        path: &Path,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Self> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            if let Some(record) =
+                parse_audit_line(&line?).and_then(|event| TransferRecord::from_event(&event))
+            {
+                records.push(record);
+            }
+        }
+        Ok(Self::from_records(records, since, until))
+    }
+}
+
+impl std::fmt::Display for SlaReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bound = |at: Option<DateTime<Utc>>| at.map_or("-".to_string(), |at| at.to_rfc3339());
+        let ms = |value: Option<u64>| value.map_or("-".to_string(), |ms| format!("{} ms", ms));
+
+        writeln!(
+            f,
+            "Transfer SLA report ({} .. {})",
+            bound(self.since),
+            bound(self.until)
+        )?;
+        writeln!(f, "  Transfers:     {}", self.total_transfers)?;
+        writeln!(f, "  Succeeded:     {}", self.succeeded)?;
+        writeln!(f, "  Failed:        {}", self.failed)?;
+        writeln!(f, "  Success rate:  {:.2}%", self.success_rate)?;
+        writeln!(f, "  p50 duration:  {}", ms(self.p50_duration_ms))?;
+        writeln!(f, "  p95 duration:  {}", ms(self.p95_duration_ms))?;
+        write!(
+            f,
+            "  Bytes moved:   {} ({:.2} MB)",
+            self.bytes_transferred,
+            self.bytes_transferred as f64 / 1_048_576.0
+        )
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+pub(crate) fn percentile(sorted: &[u64], pct: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+/// Nearest-rank percentile of sorted `(value, weight)` pairs, each value
+/// counted `weight` times
+fn weighted_percentile(sorted: &[(u64, u64)], pct: u64) -> Option<u64> {
+    let total: u64 = sorted.iter().map(|&(_, weight)| weight).sum();
+    let rank = (pct * total).div_ceil(100).max(1);
+    let mut seen = 0;
+    sorted.iter().find_map(|&(value, weight)| {
+        seen += weight;
+        (seen >= rank).then_some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::CommonFields;
+
+    fn common(timestamp: &str) -> CommonFields {
+        CommonFields {
+            timestamp: timestamp.to_string(),
+            hostname: "tftp01".to_string(),
+            service: "snow-owl-tftp".to_string(),
+            severity: "info".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    fn completed(timestamp: &str, bytes: u64, duration_ms: u64) -> AuditEvent {
+        AuditEvent::TransferCompleted {
+            common: common(timestamp),
+            client_addr: "10.0.0.5:1069".to_string(),
+            filename: "boot.wim".to_string(),
+            bytes_transferred: bytes,
+            blocks_sent: 1,
+            duration_ms,
+            throughput_bps: 0,
+            avg_block_time_ms: 0.0,
+            sample_rate: None,
+        }
+    }
+
+    fn failed(timestamp: &str) -> AuditEvent {
+        AuditEvent::TransferFailed {
+            common: common(timestamp),
+            client_addr: "10.0.0.6:1069".to_string(),
+            filename: "boot.wim".to_string(),
+            error: "timeout".to_string(),
+            blocks_sent: 3,
+        }
+    }
+
+    fn at(timestamp: &str) -> Option<DateTime<Utc>> {
+        Some(
+            DateTime::parse_from_rfc3339(timestamp)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn test_report_aggregates() {
+        // 20 successes taking 100..=2000 ms, 5 failures, one event outside the window
+        let mut events: Vec<AuditEvent> = (1..=20)
+            .map(|i| completed("2026-03-02T10:00:00Z", 1_000_000, i * 100))
+            .collect();
+        events.extend((0..5).map(|_| failed("2026-03-02T11:00:00Z")));
+        events.push(completed("2026-03-01T09:00:00Z", 999, 5));
+
+        let report = SlaReport::from_events(
+            events,
+            at("2026-03-02T00:00:00Z"),
+            at("2026-03-03T00:00:00Z"),
+        );
+
+        assert_eq!(report.total_transfers, 25);
+        assert_eq!(report.succeeded, 20);
+        assert_eq!(report.failed, 5);
+        assert!((report.success_rate - 80.0).abs() < f64::EPSILON);
+        assert_eq!(report.p50_duration_ms, Some(1000));
+        assert_eq!(report.p95_duration_ms, Some(1900));
+        assert_eq!(report.bytes_transferred, 20_000_000);
+    }
+
+    #[test]
+    fn test_sampled_successes_are_weighted() {
+        // Logged at one in 10: three successes stand for 30
+        let mut events: Vec<AuditEvent> = [100, 200, 300]
+            .into_iter()
+            .map(|ms| {
+                let mut event = completed("2026-03-02T10:00:00Z", 1000, ms);
+                if let AuditEvent::TransferCompleted { sample_rate, .. } = &mut event {
+                    *sample_rate = Some(10);
+                }
+                event
+            })
+            .collect();
+        events.extend((0..10).map(|_| failed("2026-03-02T11:00:00Z")));
+
+        // The rate survives the trip through the log
+        let lines: Vec<String> = events
+            .iter()
+            .map(|event| serde_json::to_string(event).unwrap())
+            .collect();
+        assert!(lines[0].contains("\"sample_rate\":10"));
+        assert!(!lines[3].contains("sample_rate"));
+        let parsed = lines.iter().filter_map(|line| parse_audit_line(line));
+
+        let report = SlaReport::from_events(parsed, None, None);
+
+        assert_eq!(report.succeeded, 30);
+        assert_eq!(report.failed, 10);
+        assert_eq!(report.total_transfers, 40);
+        assert!((report.success_rate - 75.0).abs() < f64::EPSILON);
+        assert_eq!(report.p50_duration_ms, Some(200));
+        assert_eq!(report.p95_duration_ms, Some(300));
+        assert_eq!(report.bytes_transferred, 30_000);
+    }
+
+    #[test]
+    fn test_empty_window() {
+        let report = SlaReport::from_events(Vec::new(), None, None);
+
+        assert_eq!(report.total_transfers, 0);
+        assert!((report.success_rate - 100.0).abs() < f64::EPSILON);
+        assert_eq!(report.p50_duration_ms, None);
+    }
+
+    #[test]
+    fn test_parse_log_formats() {
+        let event = serde_json::to_string(&completed("2026-03-02T10:00:00Z", 42, 7)).unwrap();
+        let json_line = serde_json::json!({
+            "timestamp": "2026-03-02T10:00:00.000Z",
+            "level": "INFO",
+            "fields": { "audit_event": event },
+            "target": "snow_owl_tftp::audit",
+        })
+        .to_string();
+        let text_line = format!(
+            "2026-03-02T10:00:00.000Z  INFO snow_owl_tftp::audit: audit_event={}",
+            event
+        );
+
+        for line in [json_line.as_str(), text_line.as_str(), event.as_str()] {
+            let record = parse_audit_line(line)
+                .and_then(|event| TransferRecord::from_event(&event))
+                .unwrap();
+            assert!(record.success);
+            assert_eq!(record.bytes, 42);
+            assert_eq!(record.duration_ms, Some(7));
+        }
+        assert!(parse_audit_line("Server listening on [::]:69").is_none());
+    }
+
+    #[test]
+    fn test_report_from_log_file() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("snow_owl_tftp_report_{}.log", uuid::Uuid::new_v4()));
+        let lines: Vec<String> = [
+            completed("2026-03-02T10:00:00Z", 512, 40),
+            failed("2026-03-02T10:01:00Z"),
+        ]
+        .iter()
+        .map(|event| serde_json::to_string(event).unwrap())
+        .chain(std::iter::once("not an audit line".to_string()))
+        .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let report = SlaReport::from_audit_log(&path, None, None).unwrap();
+
+        assert_eq!(report.total_transfers, 2);
+        assert!((report.success_rate - 50.0).abs() < f64::EPSILON);
+        assert_eq!(report.bytes_transferred, 512);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
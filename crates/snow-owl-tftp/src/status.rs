@@ -0,0 +1,196 @@
+//! Worker pool status listener
+//!
+//! A minimal HTTP/1.1 endpoint for monitoring the worker pool:
+//! - `GET /status` returns the pool's [`PoolStatsSnapshot`] as JSON
+//! - `GET /healthz` returns `ok` while the pool is running
+//! - `GET /multicast` returns a [`MulticastSessionSnapshot`] per multicast
+//!   session as a JSON array, when multicast is enabled
+//!
+//! Every response closes its connection. Requests are read with a size and
+//! time limit so a stalled monitoring client cannot hold a task open.
+//!
+//! NIST 800-53 Controls:
+//! - SI-4: System Monitoring (queue depths, drops and worker latency)
+//! - SC-5: Denial of Service Protection (bounded request reads)
+//!
+//! [`PoolStatsSnapshot`]: crate::worker_pool::PoolStatsSnapshot
+//! [`MulticastSessionSnapshot`]: crate::multicast::MulticastSessionSnapshot
+
+use crate::multicast::MulticastTftpServer;
+use crate::worker_pool::PoolStats;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Largest request head read before the connection is answered
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// Time a client gets to send its request head
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve status requests on `listener` until `shutdown` is cancelled
+pub async fn serve(
+    listener: TcpListener,
+    stats: PoolStats,
+    multicast: Option<Arc<MulticastTftpServer>>,
+    shutdown: CancellationToken,
+) {
+    if let Ok(addr) = listener.local_addr() {
+        info!("Worker pool status listening on http://{}/status", addr);
+    }
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Status listener accept failed: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let stats = stats.clone();
+        let multicast = multicast.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &stats, multicast.as_deref()).await {
+                debug!("Status request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Answer one request and close the connection
+async fn handle_connection(
+    mut stream: TcpStream,
+    stats: &PoolStats,
+    multicast: Option<&MulticastTftpServer>,
+) -> std::io::Result<()> {
+    let head = match tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream)).await {
+        Ok(head) => head?,
+        Err(_) => return Ok(()),
+    };
+    let request_line = head.lines().next().unwrap_or_default();
+    let (status, content_type, body) = respond(request_line, stats, multicast).await;
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read up to the blank line ending the request head, or `MAX_REQUEST_BYTES`
+async fn read_request_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut head = Vec::with_capacity(512);
+    let mut buf = [0u8; 1024];
+    while head.len() < MAX_REQUEST_BYTES && !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Status line, content type and body for `request_line`
+async fn respond(
+    request_line: &str,
+    stats: &PoolStats,
+    multicast: Option<&MulticastTftpServer>,
+) -> (&'static str, &'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return ("400 Bad Request", "text/plain", "bad request\n".to_string());
+    };
+    let path = target.split('?').next().unwrap_or(target);
+
+    match (method, path) {
+        ("GET", "/status") => json_response(serde_json::to_string(&stats.snapshot())),
+        ("GET", "/healthz") => ("200 OK", "text/plain", "ok\n".to_string()),
+        ("GET", "/multicast") => match multicast {
+            Some(server) => json_response(serde_json::to_string(&server.sessions_snapshot().await)),
+            None => (
+                "404 Not Found",
+                "text/plain",
+                "multicast not enabled\n".to_string(),
+            ),
+        },
+        (_, "/status" | "/healthz" | "/multicast") => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    }
+}
+
+/// Status line, content type and body for a serialized JSON document
+fn json_response(json: serde_json::Result<String>) -> (&'static str, &'static str, String) {
+    match json {
+        Ok(json) => ("200 OK", "application/json", json),
+        Err(e) => (
+            "500 Internal Server Error",
+            "text/plain",
+            format!("{}\n", e),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TftpConfig;
+    use crate::worker_pool::{PoolStatsSnapshot, WorkerPool};
+    use std::sync::Arc;
+
+    async fn get(addr: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_status_and_healthz() {
+        let mut config = TftpConfig::default();
+        config.performance.platform.worker_pool.worker_count = 3;
+        let pool = WorkerPool::new(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        tokio::spawn(serve(listener, pool.stats(), None, shutdown.clone()));
+
+        let response = get(addr, "GET /status HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let snapshot: PoolStatsSnapshot = serde_json::from_str(body).unwrap();
+        assert_eq!(snapshot, pool.stats_snapshot());
+        assert_eq!(snapshot.workers.len(), 3);
+
+        let response = get(addr, "GET /healthz HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nok\n"));
+
+        let response = get(addr, "POST /status HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405 "));
+        let response = get(addr, "GET /metrics HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 "));
+        let response = get(addr, "GET /multicast HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 "));
+        assert!(response.ends_with("multicast not enabled\n"));
+
+        shutdown.cancel();
+    }
+}
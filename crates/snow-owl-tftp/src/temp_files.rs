@@ -0,0 +1,229 @@
+//! Cleanup of temp files left behind by interrupted uploads
+//!
+//! A WRQ is written to a `.tftp-tmp` file next to its destination and renamed
+//! into place once complete. If the server stops or the write fails between
+//! the two, the temp file stays in the root forever. [`sweep`] removes temp
+//! files older than `temp_max_age_secs`, at startup and periodically while
+//! writes are enabled. Uploads register their temp file in a
+//! [`TempFileRegistry`] for as long as they hold it, and a registered file is
+//! never removed whatever its age.
+//!
+//! NIST 800-53 Controls:
+//! - SC-4: Information in Shared Resources (partial uploads do not linger)
+//! - SI-12: Information Management and Retention
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Suffix shared by every upload temp file
+pub const TEMP_SUFFIX: &str = ".tftp-tmp";
+
+/// Temp file an upload to `file_path` is written to before the rename
+pub fn temp_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension(TEMP_SUFFIX)
+}
+
+/// Temp files held by uploads in progress
+///
+/// Counted rather than a set, since two uploads whose names differ only in
+/// extension share a temp path.
+#[derive(Debug, Default)]
+pub struct TempFileRegistry {
+    active: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl TempFileRegistry {
+    /// Mark `path` as in use until the returned guard is dropped
+    ///
+    /// Register before creating the file, so a sweep can never see it
+    /// unregistered.
+    pub fn register(self: &Arc<Self>, path: PathBuf) -> ActiveTempFile {
+        *self.lock().entry(path.clone()).or_default() += 1;
+        ActiveTempFile {
+            registry: Arc::clone(self),
+            path,
+        }
+    }
+
+    pub fn is_active(&self, path: &Path) -> bool {
+        self.lock().contains_key(path)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, usize>> {
+        self.active.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Registration of one upload's temp file, released on drop
+#[derive(Debug)]
+pub struct ActiveTempFile {
+    registry: Arc<TempFileRegistry>,
+    path: PathBuf,
+}
+
+impl ActiveTempFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ActiveTempFile {
+    fn drop(&mut self) {
+        let mut active = self.registry.lock();
+        if let Some(count) = active.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Remove temp files under `root` last modified at least `max_age` ago
+///
+/// Walks the whole tree without following symlinks. Files registered in
+/// `registry` are skipped; the check and the removal happen under the
+/// registry lock so an upload cannot claim a file as it is deleted.
+/// Unreadable directories and failed removals are logged and skipped.
+/// Returns the removed paths.
+///
+/// Blocking; run it on a blocking thread from async code.
+pub fn sweep(root: &Path, max_age: Duration, registry: &TempFileRegistry) -> Vec<PathBuf> {
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Temp file sweep skipped {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if !file_type.is_file() || !entry.file_name().to_string_lossy().ends_with(TEMP_SUFFIX) {
+                continue;
+            }
+
+            // A modification time in the future counts as fresh
+            let age = match entry.metadata().and_then(|m| m.modified()) {
+                Ok(modified) => now.duration_since(modified).unwrap_or_default(),
+                Err(_) => continue,
+            };
+            if age < max_age {
+                continue;
+            }
+
+            match remove_unless_active(&path, registry) {
+                Ok(true) => {
+                    info!(
+                        "Removed stale upload temp file {} ({}s old)",
+                        path.display(),
+                        age.as_secs()
+                    );
+                    removed.push(path);
+                }
+                Ok(false) => debug!("Temp file {} belongs to an active upload", path.display()),
+                Err(e) => warn!("Failed to remove temp file {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    removed
+}
+
+fn remove_unless_active(path: &Path, registry: &TempFileRegistry) -> io::Result<bool> {
+    let active = registry.lock();
+    if active.contains_key(path) {
+        return Ok(false);
+    }
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        // Renamed into place since the directory was read
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, FileTimes};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "snow_owl_temp_files_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_aged(path: &Path, age: Duration) {
+        let file = File::create(path).unwrap();
+        let modified = SystemTime::now() - age;
+        file.set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sweep_removes_stale_and_keeps_active() {
+        let root = temp_dir("sweep");
+        std::fs::create_dir(root.join("configs")).unwrap();
+        let hour = Duration::from_secs(3600);
+
+        let stale = temp_path(&root.join("configs/switch1.cfg"));
+        let active = temp_path(&root.join("firmware.bin"));
+        let fresh = temp_path(&root.join("log.txt"));
+        let not_temp = root.join("configs/switch2.cfg");
+        write_aged(&stale, 2 * hour);
+        write_aged(&active, 2 * hour);
+        write_aged(&fresh, Duration::from_secs(60));
+        write_aged(&not_temp, 2 * hour);
+
+        let registry = Arc::new(TempFileRegistry::default());
+        let upload = registry.register(active.clone());
+
+        let removed = sweep(&root, hour, &registry);
+        assert_eq!(removed, vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(active.exists() && fresh.exists() && not_temp.exists());
+
+        // Once the upload lets go of it, an old temp file is fair game
+        drop(upload);
+        assert!(!registry.is_active(&active));
+        assert_eq!(sweep(&root, hour, &registry), vec![active]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_registration_is_counted() {
+        let registry = Arc::new(TempFileRegistry::default());
+        let path = temp_path(Path::new("/srv/tftp/a.cfg"));
+        assert_eq!(path, temp_path(Path::new("/srv/tftp/a.bin")));
+
+        let first = registry.register(path.clone());
+        let second = registry.register(path.clone());
+        drop(first);
+        assert!(registry.is_active(&path));
+        drop(second);
+        assert!(!registry.is_active(&path));
+    }
+}
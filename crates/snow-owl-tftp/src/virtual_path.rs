@@ -0,0 +1,359 @@
+//! Virtual paths served straight from the images database
+//!
+//! Operators otherwise have to copy boot files into the TFTP root and keep
+//! them in step with the images table. With virtual roots enabled, a read
+//! request such as `images/<uuid-or-name>/boot.sdi` is resolved through a
+//! [`PathResolver`] instead of the TFTP root, so the database stays the one
+//! source of truth for where images live.
+//!
+//! Whatever a resolver returns must still be a canonical regular file (no
+//! symlinks anywhere in the path) inside one of the configured allowed roots.
+//!
+//! NIST 800-53 Controls:
+//! - AC-3: Access Enforcement (resolved files confined to allowed roots)
+//! - SI-10: Information Input Validation (traversal rejected before lookup)
+//! - CM-8: System Component Inventory (image locations come from the database)
+
+use crate::error::Result;
+use snow_owl_db::Database;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Future returned by [`PathResolver::resolve`]
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<PathBuf>>> + Send + 'a>>;
+
+/// Maps a virtual image path to a file on disk
+pub trait PathResolver: Send + Sync {
+    /// Resolve `rest` (possibly empty) below the image named by `image`
+    ///
+    /// `Ok(None)` means the image or file is unknown. `rest` never contains
+    /// `..` components.
+    fn resolve<'a>(&'a self, image: &'a str, rest: &'a str) -> ResolveFuture<'a>;
+}
+
+/// Why a virtual path could not be served
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum VirtualPathError {
+    /// Unknown image or file; reported as TFTP File not found
+    #[error("File not found")]
+    NotFound,
+    /// Resolution escaped the allowed roots or hit a symlink
+    #[error("{0}")]
+    AccessDenied(String),
+}
+
+/// Read-only request prefix served through a [`PathResolver`]
+pub struct VirtualRoots {
+    prefix: String,
+    resolver: Arc<dyn PathResolver>,
+    allowed_roots: Vec<PathBuf>,
+}
+
+impl VirtualRoots {
+    /// Serve requests under `prefix` (e.g. `images/`) from `resolver`
+    ///
+    /// Resolved files must lie inside one of `allowed_roots`; with no roots
+    /// every request is denied.
+    pub fn new(prefix: &str, resolver: Arc<dyn PathResolver>, allowed_roots: Vec<PathBuf>) -> Self {
+        let prefix = format!("{}/", prefix.trim_matches('/'));
+        // Roots that cannot be canonicalized can never contain a canonical file
+        let allowed_roots = allowed_roots
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .collect();
+
+        Self {
+            prefix,
+            resolver,
+            allowed_roots,
+        }
+    }
+
+    /// Remainder of `filename` after the virtual prefix, if it has the prefix
+    fn strip_prefix<'a>(&self, filename: &'a str) -> Option<&'a str> {
+        filename.trim_start_matches('/').strip_prefix(&self.prefix)
+    }
+
+    /// Whether `filename` is a virtual path
+    pub fn matches(&self, filename: &str) -> bool {
+        self.strip_prefix(&filename.replace('\\', "/")).is_some()
+    }
+
+    /// Resolve a virtual path to a canonical file inside the allowed roots
+    pub async fn resolve(&self, filename: &str) -> std::result::Result<PathBuf, VirtualPathError> {
+        let filename = filename.replace('\\', "/");
+        let Some(virtual_path) = self.strip_prefix(&filename) else {
+            return Err(VirtualPathError::NotFound);
+        };
+
+        // NIST SI-10: Reject traversal before anything reaches the resolver
+        if virtual_path
+            .split('/')
+            .any(|component| component == ".." || component == ".")
+        {
+            return Err(VirtualPathError::AccessDenied(
+                "Invalid filename".to_string(),
+            ));
+        }
+
+        let (image, rest) = virtual_path.split_once('/').unwrap_or((virtual_path, ""));
+        if image.is_empty() {
+            return Err(VirtualPathError::NotFound);
+        }
+
+        let path = match self.resolver.resolve(image, rest.trim_matches('/')).await {
+            Ok(Some(path)) => path,
+            Ok(None) => return Err(VirtualPathError::NotFound),
+            Err(e) => {
+                warn!("Failed to resolve virtual path {}: {}", filename, e);
+                return Err(VirtualPathError::NotFound);
+            }
+        };
+
+        self.ensure_canonical_file(&path)
+    }
+
+    /// Accept only regular, symlink-free files inside an allowed root
+    ///
+    /// STIG V-222604: Validate file type and reject symbolic links
+    fn ensure_canonical_file(&self, path: &Path) -> std::result::Result<PathBuf, VirtualPathError> {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Err(VirtualPathError::NotFound),
+        };
+        if metadata.file_type().is_symlink() {
+            return Err(VirtualPathError::AccessDenied(
+                "Symlinks are not allowed".to_string(),
+            ));
+        }
+        if !metadata.is_file() {
+            return Err(VirtualPathError::NotFound);
+        }
+
+        // A path that changes on canonicalization is relative, not normalized,
+        // or passes through a symlinked directory
+        let canonical = path
+            .canonicalize()
+            .map_err(|_| VirtualPathError::NotFound)?;
+        if canonical != path {
+            return Err(VirtualPathError::AccessDenied(
+                "Path is not canonical".to_string(),
+            ));
+        }
+
+        if !self
+            .allowed_roots
+            .iter()
+            .any(|root| canonical.starts_with(root))
+        {
+            return Err(VirtualPathError::AccessDenied("Access denied".to_string()));
+        }
+
+        Ok(canonical)
+    }
+}
+
+/// Resolver backed by the images table
+///
+/// `images/<uuid-or-name>` serves the image file itself; any further path is
+/// taken relative to the directory holding the image file, so boot files
+/// stored next to a WIM (e.g. `boot.sdi`) are reachable too.
+pub struct DatabaseResolver {
+    db: Arc<Database>,
+}
+
+impl DatabaseResolver {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+impl PathResolver for DatabaseResolver {
+    fn resolve<'a>(&'a self, image: &'a str, rest: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let by_id = match Uuid::parse_str(image) {
+                Ok(id) => self.db.get_image_by_id(id).await.map_err(db_error)?,
+                Err(_) => None,
+            };
+            let found = match by_id {
+                Some(found) => Some(found),
+                None => self.db.get_image_by_name(image).await.map_err(db_error)?,
+            };
+
+            Ok(found.and_then(|found| {
+                if rest.is_empty() {
+                    Some(found.file_path)
+                } else {
+                    found.file_path.parent().map(|dir| dir.join(rest))
+                }
+            }))
+        })
+    }
+}
+
+fn db_error(e: snow_owl_core::SnowOwlError) -> crate::error::TftpError {
+    crate::error::TftpError::Tftp(format!("Image lookup failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Image name -> file, recording the lookups it receives
+    #[derive(Default)]
+    struct MemoryResolver {
+        images: HashMap<String, PathBuf>,
+        lookups: Mutex<Vec<String>>,
+    }
+
+    impl PathResolver for MemoryResolver {
+        fn resolve<'a>(&'a self, image: &'a str, rest: &'a str) -> ResolveFuture<'a> {
+            Box::pin(async move {
+                self.lookups
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}|{}", image, rest));
+                Ok(self.images.get(image).map(|file| {
+                    if rest.is_empty() {
+                        file.clone()
+                    } else {
+                        file.parent().unwrap().join(rest)
+                    }
+                }))
+            })
+        }
+    }
+
+    struct Fixture {
+        base: PathBuf,
+        roots: VirtualRoots,
+        resolver: Arc<MemoryResolver>,
+        images_dir: PathBuf,
+    }
+
+    /// `<tmp>/images/win11/install.wim` plus boot.sdi, and `<tmp>/outside.wim`
+    fn fixture(extra: impl FnOnce(&Path, &mut HashMap<String, PathBuf>)) -> Fixture {
+        let base = std::env::temp_dir().join(format!("snow_owl_tftp_vpath_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&base).unwrap();
+        let base = base.canonicalize().unwrap();
+        let images_dir = base.join("images");
+        std::fs::create_dir_all(images_dir.join("win11")).unwrap();
+        std::fs::write(images_dir.join("win11/install.wim"), b"wim").unwrap();
+        std::fs::write(images_dir.join("win11/boot.sdi"), b"sdi").unwrap();
+        std::fs::write(base.join("outside.wim"), b"nope").unwrap();
+
+        let mut images = HashMap::new();
+        images.insert("win11".to_string(), images_dir.join("win11/install.wim"));
+        extra(&base, &mut images);
+
+        let resolver = Arc::new(MemoryResolver {
+            images,
+            ..MemoryResolver::default()
+        });
+        let roots = VirtualRoots::new("images", resolver.clone(), vec![images_dir.clone()]);
+        Fixture {
+            base,
+            roots,
+            resolver,
+            images_dir,
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.base);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_image_and_sibling_files() {
+        let f = fixture(|_, _| {});
+
+        assert!(f.roots.matches("/images/win11"));
+        assert!(!f.roots.matches("boot/pxeboot.n12"));
+        assert_eq!(
+            f.roots.resolve("images/win11").await,
+            Ok(f.images_dir.join("win11/install.wim"))
+        );
+        assert_eq!(
+            f.roots.resolve("\\images\\win11\\boot.sdi").await,
+            Ok(f.images_dir.join("win11/boot.sdi"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_image_is_not_found() {
+        let f = fixture(|_, _| {});
+
+        assert_eq!(
+            f.roots.resolve("images/win10/boot.sdi").await,
+            Err(VirtualPathError::NotFound)
+        );
+        assert_eq!(
+            f.roots.resolve("images/win11/missing.efi").await,
+            Err(VirtualPathError::NotFound)
+        );
+        assert_eq!(
+            f.roots.resolve("images/").await,
+            Err(VirtualPathError::NotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traversal_rejected_before_lookup() {
+        let f = fixture(|_, _| {});
+
+        for attempt in ["images/win11/../../outside.wim", "images/../outside.wim"] {
+            assert_eq!(
+                f.roots.resolve(attempt).await,
+                Err(VirtualPathError::AccessDenied(
+                    "Invalid filename".to_string()
+                ))
+            );
+        }
+        assert!(f.resolver.lookups.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolved_path_must_stay_in_allowed_roots() {
+        let f = fixture(|base, images| {
+            images.insert("rogue".to_string(), base.join("outside.wim"));
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(base.join("outside.wim"), base.join("images/link.wim"))
+                    .unwrap();
+                images.insert("linked".to_string(), base.join("images/link.wim"));
+                images.insert(
+                    "dotted".to_string(),
+                    base.join("images/win11/../win11/install.wim"),
+                );
+            }
+        });
+
+        assert_eq!(
+            f.roots.resolve("images/rogue").await,
+            Err(VirtualPathError::AccessDenied("Access denied".to_string()))
+        );
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                f.roots.resolve("images/linked").await,
+                Err(VirtualPathError::AccessDenied(
+                    "Symlinks are not allowed".to_string()
+                ))
+            );
+            assert_eq!(
+                f.roots.resolve("images/dotted").await,
+                Err(VirtualPathError::AccessDenied(
+                    "Path is not canonical".to_string()
+                ))
+            );
+        }
+    }
+}
@@ -12,17 +12,24 @@
 // - 4-8x CPU core utilization
 // - 30-50% latency reduction under load
 
-use crate::config::{LoadBalanceStrategy, TftpConfig, WriteConfig};
+use crate::config::{LoadBalanceStrategy, TftpConfig};
 use crate::error::{Result, TftpError};
+use crate::priority::{Classifier, PriorityClass};
 use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
 use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::Instant;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::io::Interest;
 use tokio::net::UdpSocket;
 use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
@@ -54,6 +61,16 @@ pub struct OutgoingPacket {
     pub timestamp: Instant,
 }
 
+/// Boxed future returned by a [`RequestHandler`]
+pub type RequestFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Serves an initial RRQ/WRQ routed to a worker
+///
+/// Receives the raw request datagram and the client address, and runs the
+/// whole transfer (option negotiation and the DATA/ACK exchange on a new TID
+/// per RFC 1350). The server supplies its transfer logic through this hook.
+pub type RequestHandler = Arc<dyn Fn(Vec<u8>, SocketAddr) -> RequestFuture + Send + Sync>;
+
 /// Master thread statistics
 #[derive(Debug, Default)]
 pub struct MasterStats {
@@ -63,6 +80,27 @@ pub struct MasterStats {
     pub errors: AtomicU64,
 }
 
+impl MasterStats {
+    /// Current counters
+    pub fn snapshot(&self) -> MasterStatsSnapshot {
+        self.read(false)
+    }
+
+    /// Current counters, restarting each from zero
+    pub fn snapshot_and_reset(&self) -> MasterStatsSnapshot {
+        self.read(true)
+    }
+
+    fn read(&self, reset: bool) -> MasterStatsSnapshot {
+        MasterStatsSnapshot {
+            packets_received: take(&self.packets_received, reset),
+            batches_received: take(&self.batches_received, reset),
+            packets_dropped: take(&self.packets_dropped, reset),
+            errors: take(&self.errors, reset),
+        }
+    }
+}
+
 /// Worker thread statistics
 #[derive(Debug)]
 pub struct WorkerStats {
@@ -81,6 +119,28 @@ impl WorkerStats {
             errors: AtomicU64::new(0),
         }
     }
+
+    /// Current counters; `queue_depth` is left at 0
+    pub fn snapshot(&self) -> WorkerStatsSnapshot {
+        self.read(false)
+    }
+
+    /// Current counters, restarting each from zero; `queue_depth` is left at 0
+    pub fn snapshot_and_reset(&self) -> WorkerStatsSnapshot {
+        self.read(true)
+    }
+
+    fn read(&self, reset: bool) -> WorkerStatsSnapshot {
+        let processed = take(&self.packets_processed, reset);
+        let total_time = take(&self.total_processing_time_us, reset);
+        WorkerStatsSnapshot {
+            worker_id: self.worker_id,
+            packets_processed: processed,
+            avg_processing_time_us: total_time.checked_div(processed).unwrap_or(0),
+            errors: take(&self.errors, reset),
+            queue_depth: 0,
+        }
+    }
 }
 
 /// Sender thread statistics
@@ -91,6 +151,213 @@ pub struct SenderStats {
     pub errors: AtomicU64,
 }
 
+impl SenderStats {
+    /// Current counters; `queue_depth` is left at 0
+    pub fn snapshot(&self) -> SenderStatsSnapshot {
+        self.read(false)
+    }
+
+    /// Current counters, restarting each from zero; `queue_depth` is left at 0
+    pub fn snapshot_and_reset(&self) -> SenderStatsSnapshot {
+        self.read(true)
+    }
+
+    fn read(&self, reset: bool) -> SenderStatsSnapshot {
+        SenderStatsSnapshot {
+            packets_sent: take(&self.packets_sent, reset),
+            batches_sent: take(&self.batches_sent, reset),
+            errors: take(&self.errors, reset),
+            queue_depth: 0,
+        }
+    }
+}
+
+/// Load `counter`, or swap it to zero when `reset` is set
+///
+/// The swap is atomic, so an increment racing a reset is counted in either
+/// the returned value or the next one, never lost.
+fn take(counter: &AtomicU64, reset: bool) -> u64 {
+    if reset {
+        counter.swap(0, Ordering::Relaxed)
+    } else {
+        counter.load(Ordering::Relaxed)
+    }
+}
+
+/// Master counters in a [`PoolStatsSnapshot`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MasterStatsSnapshot {
+    pub packets_received: u64,
+    pub batches_received: u64,
+    /// Packets dropped because every eligible worker queue was full
+    pub packets_dropped: u64,
+    pub errors: u64,
+}
+
+/// Per-worker counters in a [`PoolStatsSnapshot`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerStatsSnapshot {
+    pub worker_id: usize,
+    pub packets_processed: u64,
+    /// Mean time to process one packet, in microseconds
+    pub avg_processing_time_us: u64,
+    pub errors: u64,
+    /// Packets waiting in this worker's queue (approximate)
+    pub queue_depth: usize,
+}
+
+/// Sender counters in a [`PoolStatsSnapshot`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderStatsSnapshot {
+    pub packets_sent: u64,
+    pub batches_sent: u64,
+    pub errors: u64,
+    /// Responses waiting in the sender's queue (approximate)
+    pub queue_depth: usize,
+}
+
+/// Point-in-time copy of a worker pool's statistics
+///
+/// Counters are read one at a time without a lock, so a snapshot taken
+/// under load may be off by the packets handled while it was taken.
+///
+/// NIST 800-53 SI-4: System Monitoring
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStatsSnapshot {
+    pub master: MasterStatsSnapshot,
+    pub workers: Vec<WorkerStatsSnapshot>,
+    pub sender: SenderStatsSnapshot,
+    /// Transfers started by the workers that are still running
+    pub active_clients: usize,
+}
+
+impl PoolStatsSnapshot {
+    /// Log the snapshot as one structured line
+    pub fn log(&self) {
+        let workers = self
+            .workers
+            .iter()
+            .map(|w| {
+                format!(
+                    "{}:processed={},avg={}us,errors={},queue={}",
+                    w.worker_id,
+                    w.packets_processed,
+                    w.avg_processing_time_us,
+                    w.errors,
+                    w.queue_depth
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        info!(
+            packets_received = self.master.packets_received,
+            packets_dropped = self.master.packets_dropped,
+            receive_errors = self.master.errors,
+            packets_sent = self.sender.packets_sent,
+            send_errors = self.sender.errors,
+            sender_queue = self.sender.queue_depth,
+            active_clients = self.active_clients,
+            workers = %workers,
+            "Worker pool statistics"
+        );
+    }
+}
+
+/// Shared handle on a worker pool's statistics
+///
+/// Stays valid after the pool is spawned, so a status endpoint or the
+/// periodic log task can hold a clone for as long as the pool runs. It does
+/// not keep the pool's queues open.
+#[derive(Clone)]
+pub struct PoolStats {
+    master: Arc<MasterStats>,
+    workers: Vec<Arc<WorkerStats>>,
+    sender: Arc<SenderStats>,
+    worker_queues: Vec<mpsc::WeakSender<IncomingPacket>>,
+    sender_queue: mpsc::WeakSender<OutgoingPacket>,
+    transfers: Transfers,
+}
+
+impl PoolStats {
+    /// Copy the current counters, queue depths and active transfer count
+    pub fn snapshot(&self) -> PoolStatsSnapshot {
+        self.read(false)
+    }
+
+    /// Copy the current statistics and restart the counters from zero
+    ///
+    /// Lets a reporter read and clear one window at a time without losing
+    /// counts between windows. Queue depths and `active_clients` describe
+    /// current state and are not reset.
+    ///
+    /// NIST 800-53 SI-4: System Monitoring
+    pub fn snapshot_and_reset(&self) -> PoolStatsSnapshot {
+        self.read(true)
+    }
+
+    fn read(&self, reset: bool) -> PoolStatsSnapshot {
+        let master = self.master.read(reset);
+
+        let workers = self
+            .workers
+            .iter()
+            .zip(&self.worker_queues)
+            .map(|(stats, queue)| WorkerStatsSnapshot {
+                queue_depth: queue_depth(queue),
+                ..stats.read(reset)
+            })
+            .collect();
+
+        let sender = SenderStatsSnapshot {
+            queue_depth: queue_depth(&self.sender_queue),
+            ..self.sender.read(reset)
+        };
+
+        let active_clients = {
+            let mut transfers = self
+                .transfers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            // Reap finished transfers so only running ones are counted
+            while transfers.try_join_next().is_some() {}
+            transfers.len()
+        };
+
+        PoolStatsSnapshot {
+            master,
+            workers,
+            sender,
+            active_clients,
+        }
+    }
+}
+
+/// Messages queued on a channel, or 0 once it has closed
+fn queue_depth<T>(queue: &mpsc::WeakSender<T>) -> usize {
+    queue
+        .upgrade()
+        .map_or(0, |tx| tx.max_capacity() - tx.capacity())
+}
+
+/// Log a statistics line every `period` until the task is aborted
+///
+/// With `reset`, each line covers only the period since the previous one.
+async fn log_stats_periodically(stats: PoolStats, period: Duration, reset: bool) {
+    let mut ticker = tokio::time::interval(period);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick completes immediately
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        if reset {
+            stats.snapshot_and_reset().log();
+        } else {
+            stats.snapshot().log();
+        }
+    }
+}
+
 /// Worker thread pool handle
 pub struct WorkerPool {
     /// Worker channels for sending packets to workers
@@ -107,6 +374,8 @@ pub struct WorkerPool {
     master_stats: Arc<MasterStats>,
     worker_stats: Vec<Arc<WorkerStats>>,
     sender_stats: Arc<SenderStats>,
+    /// Transfers started by the workers
+    transfers: Transfers,
 }
 
 impl WorkerPool {
@@ -147,105 +416,158 @@ impl WorkerPool {
             master_stats: Arc::new(MasterStats::default()),
             worker_stats,
             sender_stats: Arc::new(SenderStats::default()),
+            transfers: Transfers::default(),
         }
     }
 
-    /// Start the worker pool
+    /// Start the worker pool and serve until Ctrl+C
     ///
     /// Spawns:
     /// - Master receiver thread
     /// - N worker threads
     /// - Sender thread
-    pub async fn start(
+    pub async fn start(self, socket: Arc<UdpSocket>, handler: RequestHandler) -> Result<()> {
+        let pool = self.spawn(socket, handler);
+
+        // Keep pool alive
+        tokio::signal::ctrl_c().await?;
+        info!("Shutdown signal received, stopping worker pool");
+
+        // Print final statistics
+        pool.print_stats();
+
+        Ok(())
+    }
+
+    /// Serve until `shutdown` is cancelled, then drain in-flight transfers
+    ///
+    /// No new requests are read once the token is cancelled; transfers
+    /// already started get up to `drain_timeout` to finish before they are
+    /// aborted.
+    pub async fn start_with_shutdown(
         self,
         socket: Arc<UdpSocket>,
-        _root_dir: std::path::PathBuf,
-        _write_config: WriteConfig,
-        _max_file_size_bytes: u64,
-        _audit_enabled: bool,
-        _multicast_server: Option<Arc<crate::multicast::MulticastTftpServer>>,
+        handler: RequestHandler,
+        shutdown: CancellationToken,
+        drain_timeout: Duration,
     ) -> Result<()> {
+        let pool = self.spawn(socket, handler);
+
+        shutdown.cancelled().await;
+        info!("Shutdown requested, stopping worker pool");
+        pool.print_stats();
+        pool.shutdown(drain_timeout).await;
+
+        Ok(())
+    }
+
+    /// Spawn the master, worker and sender tasks
+    ///
+    /// Initial RRQ/WRQ packets are passed to `handler`; the tasks run until
+    /// the returned [`RunningWorkerPool`] is dropped.
+    pub fn spawn(self, socket: Arc<UdpSocket>, handler: RequestHandler) -> RunningWorkerPool {
         let worker_count = self.config.performance.platform.worker_pool.worker_count;
 
         info!("Starting worker pool with {} workers", worker_count);
 
-        // Clone stats references before moving self
-        let master_stats = self.master_stats.clone();
-        let worker_stats = self.worker_stats.clone();
-        let sender_stats = self.sender_stats.clone();
+        let mut tasks = Vec::with_capacity(worker_count + 3);
+        let stats = self.stats();
+        let transfers = self.transfers;
+
+        // NIST SI-4: Periodic statistics line
+        let pool_config = &self.config.performance.platform.worker_pool;
+        if let Some(period) = pool_config.stats_log_interval() {
+            tasks.push(tokio::spawn(log_stats_periodically(
+                stats.clone(),
+                period,
+                pool_config.stats_log_reset,
+            )));
+        }
 
         // Spawn master receiver thread
-        let _master_handle = {
+        {
             let socket = socket.clone();
             let config = self.config.clone();
             let workers = self.worker_senders.clone();
             let stats = self.master_stats.clone();
 
-            tokio::spawn(async move {
+            tasks.push(tokio::spawn(async move {
                 if let Err(e) = master_receiver_loop(socket, workers, config, stats).await {
                     error!("Master receiver loop failed: {}", e);
                 }
-            })
-        };
+            }));
+        }
 
         // Spawn worker threads
-        let mut _worker_handles: Vec<tokio::task::JoinHandle<()>> = Vec::new();
         for (worker_id, rx) in self.worker_receivers.into_iter().enumerate() {
             let sender_tx = self.sender_tx.clone();
-            let config = self.config.clone();
             let stats = self.worker_stats[worker_id].clone();
-            let root_dir = _root_dir.clone();
-            let write_config = _write_config.clone();
-            let max_file_size = _max_file_size_bytes;
-            let audit_enabled = _audit_enabled;
-            let multicast_server = _multicast_server.clone();
+            let handler = handler.clone();
+            let drop_non_request_opcodes = self.config.drop_non_request_opcodes;
+            let transfers = transfers.clone();
 
-            let handle = tokio::spawn(async move {
+            tasks.push(tokio::spawn(async move {
                 if let Err(e) = worker_thread(
                     worker_id,
                     rx,
                     sender_tx,
-                    config,
                     stats,
-                    root_dir,
-                    write_config,
-                    max_file_size,
-                    audit_enabled,
-                    multicast_server,
+                    handler,
+                    drop_non_request_opcodes,
+                    transfers,
                 )
                 .await
                 {
                     error!("Worker {} failed: {}", worker_id, e);
                 }
-            });
-
-            _worker_handles.push(handle);
+            }));
         }
 
         // Spawn sender thread
-        let _sender_handle = {
+        {
             let socket = socket.clone();
             let config = self.config.clone();
             let rx = self.sender_receiver;
             let stats = self.sender_stats.clone();
 
-            tokio::spawn(async move {
+            tasks.push(tokio::spawn(async move {
                 if let Err(e) = sender_thread(rx, socket, config, stats).await {
                     error!("Sender thread failed: {}", e);
                 }
-            })
-        };
+            }));
+        }
 
         info!("Worker pool started successfully");
 
-        // Keep pool alive
-        tokio::signal::ctrl_c().await?;
-        info!("Shutdown signal received, stopping worker pool");
+        RunningWorkerPool {
+            master_stats: self.master_stats,
+            worker_stats: self.worker_stats,
+            sender_stats: self.sender_stats,
+            stats,
+            tasks,
+            transfers,
+        }
+    }
 
-        // Print final statistics
-        print_stats_impl(&master_stats, &worker_stats, &sender_stats);
+    /// Shared handle on this pool's statistics, valid after it is spawned
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            master: self.master_stats.clone(),
+            workers: self.worker_stats.clone(),
+            sender: self.sender_stats.clone(),
+            worker_queues: self
+                .worker_senders
+                .iter()
+                .map(mpsc::Sender::downgrade)
+                .collect(),
+            sender_queue: self.sender_tx.downgrade(),
+            transfers: self.transfers.clone(),
+        }
+    }
 
-        Ok(())
+    /// Current statistics
+    pub fn stats_snapshot(&self) -> PoolStatsSnapshot {
+        self.stats().snapshot()
     }
 
     /// Get master statistics
@@ -302,6 +624,119 @@ impl WorkerPool {
     }
 }
 
+/// Transfers started by the workers, shared so shutdown can drain them
+type Transfers = Arc<Mutex<JoinSet<()>>>;
+
+/// Handle to a started worker pool
+///
+/// Dropping the handle stops the master, worker and sender tasks.
+/// Transfers already handed to the request handler run to completion;
+/// use [`RunningWorkerPool::shutdown`] to wait for them instead.
+pub struct RunningWorkerPool {
+    master_stats: Arc<MasterStats>,
+    worker_stats: Vec<Arc<WorkerStats>>,
+    sender_stats: Arc<SenderStats>,
+    stats: PoolStats,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+    transfers: Transfers,
+}
+
+impl RunningWorkerPool {
+    /// Get master statistics
+    pub fn master_stats(&self) -> &MasterStats {
+        &self.master_stats
+    }
+
+    /// Get worker statistics
+    pub fn worker_stats(&self, worker_id: usize) -> Option<&WorkerStats> {
+        self.worker_stats.get(worker_id).map(|s| s.as_ref())
+    }
+
+    /// Get sender statistics
+    pub fn sender_stats(&self) -> &SenderStats {
+        &self.sender_stats
+    }
+
+    /// Shared handle on this pool's statistics
+    pub fn stats(&self) -> PoolStats {
+        self.stats.clone()
+    }
+
+    /// Current statistics
+    pub fn stats_snapshot(&self) -> PoolStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    /// Print statistics
+    pub fn print_stats(&self) {
+        print_stats_impl(&self.master_stats, &self.worker_stats, &self.sender_stats);
+    }
+
+    /// Stop the pool and drain the transfers it started
+    ///
+    /// The master, worker and sender tasks stop first so no new request is
+    /// picked up, then in-flight transfers get up to `drain_timeout` to
+    /// finish. Returns the number of transfers that had to be aborted.
+    pub async fn shutdown(mut self, drain_timeout: Duration) -> usize {
+        for task in &self.tasks {
+            task.abort();
+        }
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+
+        let mut transfers = std::mem::take(
+            &mut *self
+                .transfers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        drain_transfers(&mut transfers, drain_timeout).await
+    }
+}
+
+/// Wait up to `timeout` for `transfers` to finish, then abort the rest
+///
+/// NIST 800-53 SC-24: Fail in Known State (bounded shutdown)
+///
+/// Returns the number of transfers that had to be aborted.
+pub async fn drain_transfers(transfers: &mut JoinSet<()>, timeout: Duration) -> usize {
+    if transfers.is_empty() {
+        return 0;
+    }
+    info!(
+        "Waiting up to {:?} for {} in-flight transfer(s)",
+        timeout,
+        transfers.len()
+    );
+
+    let finished = tokio::time::timeout(timeout, async {
+        while transfers.join_next().await.is_some() {}
+    })
+    .await;
+    if finished.is_ok() {
+        info!("All in-flight transfers completed");
+        return 0;
+    }
+
+    let aborted = transfers.len();
+    warn!(
+        "Drain timeout reached, aborting {} in-flight transfer(s)",
+        aborted
+    );
+    transfers.abort_all();
+    while transfers.join_next().await.is_some() {}
+    aborted
+}
+
+impl Drop for RunningWorkerPool {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
 /// Print statistics (standalone function)
 fn print_stats_impl(
     master_stats: &Arc<MasterStats>,
@@ -388,6 +823,11 @@ async fn master_receiver_loop(
         .worker_pool
         .load_balance_strategy;
     let worker_count = workers.len();
+    // NIST SC-6: critical requests may use any worker's queue
+    let classifier = config
+        .priority
+        .enabled
+        .then(|| Classifier::new(&config.priority));
 
     let mut worker_index: usize = 0;
 
@@ -431,14 +871,50 @@ async fn master_receiver_loop(
             let worker_idx = select_worker(strategy, &client_addr, worker_count, &mut worker_index);
 
             // Send to worker (non-blocking)
-            if let Err(e) = workers[worker_idx].try_send(packet) {
-                warn!("Worker {} channel full, dropping packet: {}", worker_idx, e);
+            if let Err(packet) = dispatch(&workers, worker_idx, packet, classifier.as_ref()) {
+                warn!(
+                    "Worker {} channel full, dropping packet from {}",
+                    worker_idx, packet.addr
+                );
                 stats.packets_dropped.fetch_add(1, Ordering::Relaxed);
             }
         }
     }
 }
 
+/// Queue `packet` on worker `preferred` without blocking
+///
+/// When that queue is full and `classifier` marks the packet as a critical
+/// read request, the other workers are tried in turn so a boot file is not
+/// dropped behind a backlog of bulk requests. Returns the chosen worker, or
+/// the packet if no worker took it.
+fn dispatch(
+    workers: &[mpsc::Sender<IncomingPacket>],
+    preferred: usize,
+    packet: IncomingPacket,
+    classifier: Option<&Classifier>,
+) -> std::result::Result<usize, IncomingPacket> {
+    let mut packet = match workers[preferred].try_send(packet) {
+        Ok(()) => return Ok(preferred),
+        Err(mpsc::error::TrySendError::Full(packet)) => packet,
+        Err(mpsc::error::TrySendError::Closed(packet)) => return Err(packet),
+    };
+
+    let critical = classifier.and_then(|c| c.classify_request(&packet.data));
+    if critical != Some(PriorityClass::Critical) {
+        return Err(packet);
+    }
+
+    for offset in 1..workers.len() {
+        let idx = (preferred + offset) % workers.len();
+        match workers[idx].try_send(packet) {
+            Ok(()) => return Ok(idx),
+            Err(e) => packet = e.into_inner(),
+        }
+    }
+    Err(packet)
+}
+
 /// Internal batch receive function
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 async fn batch_recv_packets_internal(
@@ -457,60 +933,65 @@ async fn batch_recv_packets_internal(
         .map(|_| vec![0u8; MAX_PACKET_SIZE])
         .collect();
 
-    let mut iovecs: Vec<Vec<IoSliceMut>> = buffers
-        .iter_mut()
-        .map(|buf| vec![IoSliceMut::new(buf)])
-        .collect();
-
-    let mut headers = MultiHeaders::<SockaddrStorage>::preallocate(batch_size, None);
-
     let timeout = if timeout_us > 0 {
         Some(TimeSpec::from_duration(Duration::from_micros(timeout_us)))
     } else {
         None
     };
 
-    // Perform batch receive
-    match recvmmsg(
-        socket_fd,
-        &mut headers,
-        iovecs.iter_mut(),
-        MsgFlags::empty(),
-        timeout,
-    ) {
-        Ok(msgs_received) => {
-            let mut results = Vec::new();
+    // Perform batch receive once the socket is readable. async_io clears the
+    // readiness flag on EAGAIN, so an idle socket parks the master instead of
+    // spinning on recvmmsg().
+    let msg_info = socket
+        .async_io(Interest::READABLE, || {
+            // Headers hold raw pointers, so build them per attempt rather
+            // than keeping them alive across the await
+            let mut iovecs: Vec<Vec<IoSliceMut>> = buffers
+                .iter_mut()
+                .map(|buf| vec![IoSliceMut::new(buf)])
+                .collect();
+            let mut headers = MultiHeaders::<SockaddrStorage>::preallocate(batch_size, None);
+
+            let msgs_received = recvmmsg(
+                socket_fd,
+                &mut headers,
+                iovecs.iter_mut(),
+                MsgFlags::empty(),
+                timeout,
+            )?;
 
             // Collect message info before accessing buffers
-            let msg_info: Vec<_> = msgs_received
+            Ok(msgs_received
                 .into_iter()
                 .enumerate()
                 .filter_map(|(i, msg)| msg.address.map(|addr| (i, msg.bytes, addr)))
-                .collect();
-
-            // Now we can access buffers without borrow conflicts
-            for (i, bytes_received, addr_storage) in msg_info {
-                let addr = sockaddr_to_std(&addr_storage)?;
-                let data = buffers[i][..bytes_received].to_vec();
-                results.push((data, addr));
-            }
-
-            Ok(results)
-        }
-        Err(nix::errno::Errno::EAGAIN) => Ok(Vec::new()),
-        Err(e) => Err(TftpError::Tftp(format!("recvmmsg error: {}", e))),
+                .collect::<Vec<_>>())
+        })
+        .await
+        .map_err(|e| TftpError::Tftp(format!("recvmmsg error: {}", e)))?;
+
+    // Now we can access buffers without borrow conflicts
+    let mut results = Vec::with_capacity(msg_info.len());
+    for (i, bytes_received, addr_storage) in msg_info {
+        let addr = sockaddr_to_std(&addr_storage)?;
+        let data = buffers[i][..bytes_received].to_vec();
+        results.push((data, addr));
     }
+
+    Ok(results)
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
 async fn batch_recv_packets_internal(
-    _socket: &UdpSocket,
+    socket: &UdpSocket,
     _batch_size: usize,
     _timeout_us: u64,
 ) -> Result<Vec<(Vec<u8>, SocketAddr)>> {
-    Err(TftpError::Tftp(
-        "recvmmsg not supported on this platform".into(),
-    ))
+    // recvmmsg() unavailable: receive one datagram per call
+    let mut buf = vec![0u8; MAX_PACKET_SIZE];
+    let (size, addr) = socket.recv_from(&mut buf).await?;
+    buf.truncate(size);
+    Ok(vec![(buf, addr)])
 }
 
 /// Sender thread: Batch send outgoing packets
@@ -581,13 +1062,10 @@ async fn worker_thread(
     worker_id: usize,
     mut rx: mpsc::Receiver<IncomingPacket>,
     tx: mpsc::Sender<OutgoingPacket>,
-    config: Arc<TftpConfig>,
     stats: Arc<WorkerStats>,
-    root_dir: std::path::PathBuf,
-    write_config: WriteConfig,
-    max_file_size: u64,
-    audit_enabled: bool,
-    multicast_server: Option<Arc<crate::multicast::MulticastTftpServer>>,
+    handler: RequestHandler,
+    drop_non_request_opcodes: bool,
+    transfers: Transfers,
 ) -> Result<()> {
     info!("Worker {} starting", worker_id);
 
@@ -595,25 +1073,11 @@ async fn worker_thread(
         let start = std::time::Instant::now();
 
         // Process TFTP packet
-        match process_tftp_packet(
-            packet,
-            &tx,
-            &config,
-            &root_dir,
-            &write_config,
-            max_file_size,
-            audit_enabled,
-            multicast_server.as_ref(),
-        )
-        .await
+        if let Err(e) =
+            process_tftp_packet(packet, &tx, &handler, drop_non_request_opcodes, &transfers).await
         {
-            Ok(_) => {
-                // Packet processed successfully
-            }
-            Err(e) => {
-                error!("Worker {}: Error processing packet: {}", worker_id, e);
-                stats.errors.fetch_add(1, Ordering::Relaxed);
-            }
+            error!("Worker {}: Error processing packet: {}", worker_id, e);
+            stats.errors.fetch_add(1, Ordering::Relaxed);
         }
 
         // Update statistics
@@ -631,33 +1095,30 @@ async fn worker_thread(
 /// Process a single TFTP packet and generate responses
 ///
 /// This function handles initial TFTP requests (RRQ/WRQ). For ongoing transfers
-/// (DATA/ACK packets), those are handled by existing per-client tasks spawned
-/// by handle_read_request and handle_write_request.
+/// (DATA/ACK packets), those are handled by the per-transfer sockets created
+/// by the request handler.
 ///
 /// The worker pool architecture is most beneficial for the initial packet processing
 /// and distributing load across cores. Once a transfer is established, the existing
 /// single-threaded async architecture is efficient for that session.
+///
+/// Any other known opcode on the listening port is answered with an Illegal
+/// Operation error, or ignored when `drop_non_request_opcodes` is set. ERROR
+/// packets are never answered.
 async fn process_tftp_packet(
     packet: IncomingPacket,
     tx: &mpsc::Sender<OutgoingPacket>,
-    config: &TftpConfig,
-    root_dir: &std::path::Path,
-    write_config: &WriteConfig,
-    max_file_size: u64,
-    audit_enabled: bool,
-    multicast_server: Option<&Arc<crate::multicast::MulticastTftpServer>>,
+    handler: &RequestHandler,
+    drop_non_request_opcodes: bool,
+    transfers: &Transfers,
 ) -> Result<()> {
-    use bytes::{Buf, BytesMut};
-
-    let mut bytes = BytesMut::from(&packet.data[..]);
-
     // Validate minimum packet size
-    if bytes.len() < 2 {
+    if packet.data.len() < 2 {
         send_error_response(tx, packet.addr, packet.timestamp, 4, "Packet too small").await?;
         return Ok(());
     }
 
-    let opcode = bytes.get_u16();
+    let opcode = u16::from_be_bytes([packet.data[0], packet.data[1]]);
 
     // Parse opcode
     let opcode = match opcode {
@@ -681,313 +1142,40 @@ async fn process_tftp_packet(
     };
 
     match opcode {
-        TftpOpcode::Rrq => {
-            // Handle Read Request
-            if let Err(e) = handle_read_request_worker(
-                packet.addr,
-                &mut bytes,
-                root_dir,
-                config,
-                max_file_size,
-                audit_enabled,
-                multicast_server,
-            )
-            .await
-            {
-                error!("Read request failed: {}", e);
-                send_error_response(tx, packet.addr, packet.timestamp, 0, &e.to_string()).await?;
-            }
-        }
-        TftpOpcode::Wrq => {
-            // Handle Write Request
-            if let Err(e) = handle_write_request_worker(
-                packet.addr,
-                &mut bytes,
-                root_dir,
-                write_config,
-                config,
-                max_file_size,
-                audit_enabled,
-            )
-            .await
-            {
-                error!("Write request failed: {}", e);
-                send_error_response(tx, packet.addr, packet.timestamp, 0, &e.to_string()).await?;
-            }
-        }
-        TftpOpcode::Data | TftpOpcode::Ack => {
-            // These packets are part of ongoing transfers
-            // In the current architecture, these should not reach workers because
-            // ongoing transfers create connected sockets that receive directly
-            debug!(
-                "Worker received {:?} packet from {} - ongoing transfers should use connected sockets",
-                opcode, packet.addr
-            );
+        TftpOpcode::Rrq | TftpOpcode::Wrq => {
+            // Transfers are long-lived; run them outside the worker so a slow
+            // client never stalls the requests queued behind it
+            let client_addr = packet.addr;
+            let transfer = handler(packet.data, client_addr);
+            let mut transfers = transfers.lock().unwrap_or_else(PoisonError::into_inner);
+            // Reap finished transfers so the set only holds running ones
+            while transfers.try_join_next().is_some() {}
+            transfers.spawn(async move {
+                if let Err(e) = transfer.await {
+                    error!("Transfer failed for {}: {}", client_addr, e);
+                }
+            });
         }
         TftpOpcode::Error => {
             debug!("Worker received ERROR packet from {}", packet.addr);
         }
-        TftpOpcode::Oack => {
-            warn!(
-                "Worker received OACK packet from {} - unexpected (OACK is server->client)",
-                packet.addr
+        TftpOpcode::Data | TftpOpcode::Ack | TftpOpcode::Oack if drop_non_request_opcodes => {
+            // Ongoing transfers use their own connected sockets, so these are
+            // stray retransmits from clients that lost track of the transfer TID
+            debug!(
+                "Worker ignoring {:?} packet from {} on listening port",
+                opcode, packet.addr
             );
         }
-    }
-
-    Ok(())
-}
-
-/// Handle Read Request in worker thread
-async fn handle_read_request_worker(
-    client_addr: std::net::SocketAddr,
-    bytes: &mut BytesMut,
-    root_dir: &std::path::Path,
-    config: &TftpConfig,
-    max_file_size: u64,
-    audit_enabled: bool,
-    _multicast_server: Option<&Arc<crate::multicast::MulticastTftpServer>>,
-) -> Result<()> {
-    use crate::{TftpOptions, TransferMode};
-
-    // Parse request packet
-    let (filename, mode_str, requested_options) = parse_request_packet(bytes)?;
-
-    // Validate and parse transfer mode
-    let mode = TransferMode::from_str(&mode_str)?;
-    if mode == TransferMode::Mail {
-        return Err(TftpError::Tftp("MAIL mode not supported".into()));
-    }
-
-    info!(
-        "Worker processing RRQ: file={}, mode={:?}, from={}",
-        filename, mode, client_addr
-    );
-
-    // Validate and resolve file path (prevent directory traversal)
-    let file_path = crate::TftpServer::validate_and_resolve_path(root_dir, &filename)?;
-
-    // Negotiate options
-    let default_windowsize = config.performance.platform.batch.max_batch_size;
-    let mut options = TftpOptions {
-        windowsize: default_windowsize,
-        ..TftpOptions::default()
-    };
-    let mut negotiated_options = std::collections::HashMap::new();
-
-    for (name, value) in &requested_options {
-        match name.as_str() {
-            "blksize" => {
-                if let Ok(size) = value.parse::<usize>() {
-                    if (8..=crate::MAX_BLOCK_SIZE).contains(&size) {
-                        options.block_size = size;
-                        negotiated_options.insert("blksize".to_string(), size.to_string());
-                    }
-                }
-            }
-            "timeout" => {
-                if let Ok(t) = value.parse::<u64>() {
-                    if (1..=255).contains(&t) {
-                        options.timeout = t;
-                        negotiated_options.insert("timeout".to_string(), t.to_string());
-                    }
-                }
-            }
-            "tsize" => {
-                // For RRQ, server responds with actual file size
-                negotiated_options.insert("tsize".to_string(), "0".to_string());
-            }
-            "windowsize" => {
-                if let Ok(w) = value.parse::<usize>() {
-                    if (1..=65535).contains(&w) {
-                        options.windowsize = w;
-                        negotiated_options.insert("windowsize".to_string(), w.to_string());
-                    }
-                }
-            }
-            _ => {
-                // Unknown option - ignore per RFC 2347
-            }
-        }
-    }
-
-    // Clone config for spawned task (required for 'static lifetime)
-    let file_io_config = config.performance.platform.file_io.clone();
-
-    // Spawn transfer task using existing handle_read_request
-    tokio::spawn(async move {
-        if let Err(e) = crate::TftpServer::handle_read_request(
-            file_path,
-            client_addr,
-            mode,
-            options,
-            negotiated_options,
-            max_file_size,
-            audit_enabled,
-            &file_io_config,
-        )
-        .await
-        {
-            error!("Read transfer failed for {}: {}", client_addr, e);
-        }
-    });
-
-    Ok(())
-}
-
-/// Handle Write Request in worker thread
-async fn handle_write_request_worker(
-    client_addr: std::net::SocketAddr,
-    bytes: &mut BytesMut,
-    root_dir: &std::path::Path,
-    write_config: &WriteConfig,
-    config: &TftpConfig,
-    max_file_size: u64,
-    audit_enabled: bool,
-) -> Result<()> {
-    use crate::{TftpOptions, TransferMode};
-
-    // Parse request packet
-    let (filename, mode_str, requested_options) = parse_request_packet(bytes)?;
-
-    // Validate and parse transfer mode
-    let mode = TransferMode::from_str(&mode_str)?;
-    if mode == TransferMode::Mail {
-        return Err(TftpError::Tftp("MAIL mode not supported".into()));
-    }
-
-    info!(
-        "Worker processing WRQ: file={}, mode={:?}, from={}",
-        filename, mode, client_addr
-    );
-
-    // Check if writes are enabled
-    if !write_config.enabled {
-        return Err(TftpError::Tftp("Write operations not allowed".into()));
-    }
-
-    // Validate and resolve file path
-    let file_path = crate::TftpServer::validate_and_resolve_path(root_dir, &filename)?;
-
-    // Check if file exists
-    let file_exists = tokio::fs::metadata(&file_path).await.is_ok();
-    let file_created = !file_exists;
-
-    if file_exists && !write_config.allow_overwrite {
-        return Err(TftpError::Tftp("File already exists".into()));
-    }
-
-    // Negotiate options
-    let default_windowsize = config.performance.platform.batch.max_batch_size;
-    let mut options = TftpOptions {
-        windowsize: default_windowsize,
-        ..TftpOptions::default()
-    };
-    let mut negotiated_options = std::collections::HashMap::new();
-
-    for (name, value) in &requested_options {
-        match name.as_str() {
-            "blksize" => {
-                if let Ok(size) = value.parse::<usize>() {
-                    if (8..=crate::MAX_BLOCK_SIZE).contains(&size) {
-                        options.block_size = size;
-                        negotiated_options.insert("blksize".to_string(), size.to_string());
-                    }
-                }
-            }
-            "timeout" => {
-                if let Ok(t) = value.parse::<u64>() {
-                    if (1..=255).contains(&t) {
-                        options.timeout = t;
-                        negotiated_options.insert("timeout".to_string(), t.to_string());
-                    }
-                }
-            }
-            "tsize" => {
-                // For WRQ, acknowledge the transfer size
-                negotiated_options.insert("tsize".to_string(), value.clone());
-            }
-            "windowsize" => {
-                if let Ok(w) = value.parse::<usize>() {
-                    if (1..=65535).contains(&w) {
-                        options.windowsize = w;
-                        negotiated_options.insert("windowsize".to_string(), w.to_string());
-                    }
-                }
-            }
-            _ => {
-                // Unknown option - ignore per RFC 2347
-            }
+        TftpOpcode::Data | TftpOpcode::Ack | TftpOpcode::Oack => {
+            warn!("Unexpected opcode from {}: {:?}", packet.addr, opcode);
+            send_error_response(tx, packet.addr, packet.timestamp, 4, "Unexpected opcode").await?;
         }
     }
 
-    // Spawn transfer task using existing handle_write_request
-    tokio::spawn(async move {
-        if let Err(e) = crate::TftpServer::handle_write_request(
-            file_path,
-            client_addr,
-            mode,
-            options,
-            negotiated_options,
-            max_file_size,
-            file_created,
-            audit_enabled,
-        )
-        .await
-        {
-            error!("Write transfer failed for {}: {}", client_addr, e);
-        }
-    });
-
     Ok(())
 }
 
-/// Parse a TFTP request packet (RRQ or WRQ)
-fn parse_request_packet(
-    bytes: &mut BytesMut,
-) -> Result<(String, String, std::collections::HashMap<String, String>)> {
-    use bytes::Buf;
-
-    // Parse filename (null-terminated string)
-    let filename = parse_null_terminated_string(bytes)?;
-
-    // Parse mode (null-terminated string)
-    let mode = parse_null_terminated_string(bytes)?;
-
-    // Parse options (pairs of null-terminated strings)
-    let mut options = std::collections::HashMap::new();
-    while bytes.remaining() > 0 {
-        match parse_null_terminated_string(bytes) {
-            Ok(option_name) => match parse_null_terminated_string(bytes) {
-                Ok(option_value) => {
-                    options.insert(option_name.to_lowercase(), option_value);
-                }
-                Err(_) => break,
-            },
-            Err(_) => break,
-        }
-    }
-
-    Ok((filename, mode, options))
-}
-
-/// Parse a null-terminated string from a byte buffer
-fn parse_null_terminated_string(bytes: &mut BytesMut) -> Result<String> {
-    use bytes::Buf;
-
-    let mut result = Vec::new();
-    while bytes.remaining() > 0 {
-        let byte = bytes.get_u8();
-        if byte == 0 {
-            return String::from_utf8(result)
-                .map_err(|e| TftpError::Tftp(format!("Invalid UTF-8 in string: {}", e)));
-        }
-        result.push(byte);
-    }
-
-    Err(TftpError::Tftp("Missing null terminator".to_string()))
-}
-
 /// Send an error response via the sender channel
 async fn send_error_response(
     tx: &mpsc::Sender<OutgoingPacket>,
@@ -1051,27 +1239,46 @@ async fn batch_send_packets_internal(
     // Empty control messages (shared across all messages)
     let control_msgs: &[ControlMessage] = &[];
 
-    match sendmmsg(
-        socket_fd,
-        &mut MultiHeaders::preallocate(packets.len(), None),
-        iovecs.iter(),
-        &addrs,
-        control_msgs,
-        MsgFlags::empty(),
-    ) {
-        Ok(sent) => Ok(sent.count()),
-        Err(e) => Err(TftpError::Tftp(format!("sendmmsg error: {}", e))),
+    // sendmmsg() may accept only part of the batch; keep going until every
+    // datagram has been handed to the kernel
+    let mut sent_total = 0;
+    while sent_total < packets.len() {
+        let sent = socket
+            .async_io(Interest::WRITABLE, || {
+                let mut headers = MultiHeaders::preallocate(packets.len() - sent_total, None);
+                let sent = sendmmsg(
+                    socket_fd,
+                    &mut headers,
+                    iovecs[sent_total..].iter(),
+                    &addrs[sent_total..],
+                    control_msgs,
+                    MsgFlags::empty(),
+                )?
+                .count();
+                Ok(sent)
+            })
+            .await
+            .map_err(|e| TftpError::Tftp(format!("sendmmsg error: {}", e)))?;
+
+        if sent == 0 {
+            break;
+        }
+        sent_total += sent;
     }
+
+    Ok(sent_total)
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
 async fn batch_send_packets_internal(
-    _socket: &UdpSocket,
-    _packets: &[OutgoingPacket],
+    socket: &UdpSocket,
+    packets: &[OutgoingPacket],
 ) -> Result<usize> {
-    Err(TftpError::Tftp(
-        "sendmmsg not supported on this platform".into(),
-    ))
+    // sendmmsg() unavailable: send one datagram per call
+    for packet in packets {
+        socket.send_to(&packet.data, packet.addr).await?;
+    }
+    Ok(packets.len())
 }
 
 /// Helper: Convert SockaddrStorage to std::net::SocketAddr
@@ -1094,6 +1301,46 @@ fn sockaddr_to_std(addr_storage: &SockaddrStorage) -> Result<SocketAddr> {
 mod tests {
     use super::*;
     use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    fn pool_config(worker_count: usize) -> Arc<TftpConfig> {
+        let mut config = TftpConfig::default();
+        config.performance.use_worker_pool = true;
+        config.performance.platform.worker_pool.worker_count = worker_count;
+        config.performance.platform.worker_pool.load_balance_strategy =
+            LoadBalanceStrategy::RoundRobin;
+        Arc::new(config)
+    }
+
+    fn rrq_packet(filename: &str) -> Vec<u8> {
+        let mut packet = vec![0, 1];
+        packet.extend_from_slice(filename.as_bytes());
+        packet.push(0);
+        packet.extend_from_slice(b"octet");
+        packet.push(0);
+        packet
+    }
+
+    /// Stand-in transfer: answer each request with one DATA block carrying
+    /// the requested filename, sent from a fresh TID
+    fn echo_handler() -> RequestHandler {
+        Arc::new(|data, client_addr| {
+            Box::pin(async move {
+                let transfer = UdpSocket::bind("127.0.0.1:0").await?;
+                let mut reply = vec![0, 3, 0, 1];
+                reply.extend_from_slice(&data[2..]);
+                transfer.send_to(&reply, client_addr).await?;
+                Ok(())
+            })
+        })
+    }
+
+    fn total_processed(pool: &RunningWorkerPool, worker_count: usize) -> u64 {
+        (0..worker_count)
+            .filter_map(|id| pool.worker_stats(id))
+            .map(|stats| stats.packets_processed.load(Ordering::Relaxed))
+            .sum()
+    }
 
     #[test]
     fn test_select_worker_round_robin() {
@@ -1145,4 +1392,291 @@ mod tests {
         // Note: They might happen to hash to the same worker, so we just check validity
         assert!(worker2 < worker_count);
     }
+
+    #[test]
+    fn test_dispatch_moves_critical_requests_off_full_queue() {
+        let (full, _full_rx) = mpsc::channel(1);
+        let (idle, mut idle_rx) = mpsc::channel(1);
+        let workers = vec![full, idle];
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 12345);
+        let packet = |name: &str| IncomingPacket {
+            data: rrq_packet(name),
+            addr,
+            timestamp: Instant::now(),
+        };
+
+        let classifier = Classifier::new(&crate::config::PriorityConfig {
+            enabled: true,
+            critical_patterns: vec!["boot.sdi".to_string()],
+            critical_max_bytes: 0,
+        });
+        assert_eq!(
+            dispatch(&workers, 0, packet("install.wim"), None).ok(),
+            Some(0)
+        );
+
+        // Worker 0 is now full: bulk is dropped, critical goes to worker 1
+        assert!(dispatch(&workers, 0, packet("install.wim"), Some(&classifier)).is_err());
+        assert!(dispatch(&workers, 0, packet("boot.sdi"), None).is_err());
+        assert_eq!(
+            dispatch(&workers, 0, packet("boot.sdi"), Some(&classifier)).ok(),
+            Some(1)
+        );
+        assert_eq!(idle_rx.try_recv().unwrap().data, rrq_packet("boot.sdi"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_pool_spreads_transfers_across_workers() {
+        let worker_count = 4;
+        let transfers = 8;
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = socket.local_addr().unwrap();
+        let pool = WorkerPool::new(pool_config(worker_count)).spawn(socket, echo_handler());
+
+        let clients: Vec<_> = (0..transfers)
+            .map(|i| {
+                tokio::spawn(async move {
+                    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                    let filename = format!("file{}.bin", i);
+                    client
+                        .send_to(&rrq_packet(&filename), server_addr)
+                        .await
+                        .unwrap();
+
+                    let mut buf = [0u8; 512];
+                    let (len, from) =
+                        tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+                            .await
+                            .expect("transfer response")
+                            .unwrap();
+
+                    assert_ne!(from, server_addr, "transfer must use a new TID");
+                    assert_eq!(&buf[..4], &[0, 3, 0, 1]);
+                    assert!(buf[4..len].starts_with(filename.as_bytes()));
+                })
+            })
+            .collect();
+        for client in clients {
+            client.await.unwrap();
+        }
+
+        // Workers record a packet once its transfer has been dispatched
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while total_processed(&pool, worker_count) < transfers
+            && tokio::time::Instant::now() < deadline
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(total_processed(&pool, worker_count), transfers);
+        assert_eq!(
+            pool.master_stats().packets_received.load(Ordering::Relaxed),
+            transfers
+        );
+        let busy_workers = (0..worker_count)
+            .filter_map(|id| pool.worker_stats(id))
+            .filter(|stats| stats.packets_processed.load(Ordering::Relaxed) > 0)
+            .count();
+        assert!(
+            busy_workers > 1,
+            "expected packets on multiple workers, got {}",
+            busy_workers
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sender_transmits_worker_responses() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = socket.local_addr().unwrap();
+        let pool = WorkerPool::new(pool_config(2)).spawn(socket, echo_handler());
+
+        // Unknown opcode is answered by the worker through the sender task
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&[0, 9, 0, 0], server_addr).await.unwrap();
+
+        let mut buf = [0u8; 512];
+        let (len, from) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .expect("error response")
+            .unwrap();
+
+        assert_eq!(from, server_addr);
+        assert_eq!(&buf[..4], &[0, 5, 0, 4]);
+        assert_eq!(&buf[4..len], b"Illegal TFTP operation\0");
+        assert_eq!(pool.sender_stats().packets_sent.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stray_ack_dropped_when_configured() {
+        let mut config = (*pool_config(1)).clone();
+        config.drop_non_request_opcodes = true;
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = socket.local_addr().unwrap();
+        let pool = WorkerPool::new(Arc::new(config)).spawn(socket, echo_handler());
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&[0, 4, 0, 7], server_addr).await.unwrap();
+        client
+            .send_to(&rrq_packet("after.bin"), server_addr)
+            .await
+            .unwrap();
+
+        // The first reply is the transfer, not an error for the ACK
+        let mut buf = [0u8; 512];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .expect("transfer response")
+            .unwrap();
+        assert_eq!(&buf[..4], &[0, 3, 0, 1]);
+        assert!(buf[4..len].starts_with(b"after.bin"));
+        assert_eq!(pool.sender_stats().packets_sent.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reports_queue_depths() {
+        let pool = WorkerPool::new(pool_config(2));
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 12345);
+        for _ in 0..3 {
+            pool.worker_senders[1]
+                .try_send(IncomingPacket {
+                    data: rrq_packet("queued.bin"),
+                    addr,
+                    timestamp: Instant::now(),
+                })
+                .unwrap();
+        }
+
+        let snapshot = pool.stats_snapshot();
+        let depths: Vec<_> = snapshot.workers.iter().map(|w| w.queue_depth).collect();
+        assert_eq!(depths, [0, 3]);
+        assert_eq!(snapshot.sender.queue_depth, 0);
+        assert_eq!(snapshot.active_clients, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stats_snapshot_is_coherent() {
+        let worker_count = 2;
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = socket.local_addr().unwrap();
+        // Transfers stay open until released, so they count as active clients
+        let release = Arc::new(tokio::sync::Notify::new());
+        let handler: RequestHandler = {
+            let release = release.clone();
+            Arc::new(move |_, _| {
+                let release = release.clone();
+                Box::pin(async move {
+                    release.notified().await;
+                    Ok(())
+                })
+            })
+        };
+        let pool = WorkerPool::new(pool_config(worker_count)).spawn(socket, handler);
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for i in 0..3 {
+            client
+                .send_to(&rrq_packet(&format!("file{}.bin", i)), server_addr)
+                .await
+                .unwrap();
+        }
+        client.send_to(&[0, 9, 0, 0], server_addr).await.unwrap();
+
+        let mut buf = [0u8; 512];
+        tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .expect("error response")
+            .unwrap();
+        // Counters are updated after the work they count; wait for them to settle
+        let settled = |s: &PoolStatsSnapshot| s.active_clients == 3 && s.sender.packets_sent == 1;
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !settled(&pool.stats_snapshot()) && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let snapshot = pool.stats_snapshot();
+        assert_eq!(snapshot.workers.len(), worker_count);
+        assert_eq!(snapshot.master.packets_received, 4);
+        assert_eq!(snapshot.master.packets_dropped, 0);
+        let processed: u64 = snapshot.workers.iter().map(|w| w.packets_processed).sum();
+        assert_eq!(processed, snapshot.master.packets_received);
+        assert!(snapshot.workers.iter().all(|w| w.queue_depth == 0));
+        assert_eq!(snapshot.sender.packets_sent, 1);
+        assert_eq!(snapshot.active_clients, 3);
+
+        // Round-trips through JSON for the status endpoint
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PoolStatsSnapshot>(&json).unwrap(),
+            snapshot
+        );
+
+        release.notify_waiters();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while pool.stats_snapshot().active_clients > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(pool.stats_snapshot().active_clients, 0);
+    }
+
+    #[test]
+    fn test_snapshot_and_reset_starts_new_window() {
+        let pool = WorkerPool::new(pool_config(2));
+        let stats = pool.stats();
+        pool.master_stats
+            .packets_received
+            .fetch_add(5, Ordering::Relaxed);
+        pool.master_stats
+            .packets_dropped
+            .fetch_add(1, Ordering::Relaxed);
+        pool.worker_stats[0]
+            .packets_processed
+            .fetch_add(4, Ordering::Relaxed);
+        pool.worker_stats[0]
+            .total_processing_time_us
+            .fetch_add(400, Ordering::Relaxed);
+        pool.sender_stats
+            .packets_sent
+            .fetch_add(3, Ordering::Relaxed);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 12345);
+        pool.worker_senders[0]
+            .try_send(IncomingPacket {
+                data: rrq_packet("queued.bin"),
+                addr,
+                timestamp: Instant::now(),
+            })
+            .unwrap();
+
+        let window = stats.snapshot_and_reset();
+        assert_eq!(window.master.packets_received, 5);
+        assert_eq!(window.master.packets_dropped, 1);
+        assert_eq!(window.workers[0].packets_processed, 4);
+        assert_eq!(window.workers[0].avg_processing_time_us, 100);
+        assert_eq!(window.sender.packets_sent, 3);
+
+        // Counters restart from zero; the queued packet is still queued
+        let after = stats.snapshot();
+        assert_eq!(after.master, MasterStatsSnapshot::default());
+        assert_eq!(after.workers[0].packets_processed, 0);
+        assert_eq!(after.workers[0].avg_processing_time_us, 0);
+        assert_eq!(after.workers[0].queue_depth, 1);
+        assert_eq!(after.sender.packets_sent, 0);
+
+        pool.master_stats
+            .packets_received
+            .fetch_add(2, Ordering::Relaxed);
+        assert_eq!(stats.snapshot_and_reset().master.packets_received, 2);
+        assert_eq!(stats.snapshot().master.packets_received, 0);
+    }
+
+    #[tokio::test]
+    async fn test_drain_aborts_transfers_after_timeout() {
+        let mut transfers = JoinSet::new();
+        transfers.spawn(async {});
+        transfers.spawn(std::future::pending::<()>());
+
+        let aborted = drain_transfers(&mut transfers, Duration::from_millis(50)).await;
+
+        assert_eq!(aborted, 1);
+        assert!(transfers.is_empty());
+    }
 }
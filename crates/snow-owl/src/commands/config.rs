@@ -0,0 +1,61 @@
+use anyhow::Result;
+use snow_owl_core::ServerConfig;
+use snow_owl_core::config_schema::{check_config_file, config_schema};
+use std::path::Path;
+
+use crate::ConfigCommands;
+
+pub fn handle(config_path: &Path, command: ConfigCommands) -> Result<()> {
+    match command {
+        ConfigCommands::Schema => schema(),
+        ConfigCommands::Validate { file, schema } => validate(
+            file.as_deref().unwrap_or(config_path),
+            schema.flatten().as_deref(),
+        ),
+    }
+}
+
+/// Print the JSON Schema for the server configuration file
+fn schema() -> Result<()> {
+    let schema = config_schema::<ServerConfig>();
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+/// Check a configuration file before it is deployed
+///
+/// NIST CM-3: Configuration Change Control (fails the pipeline, not the service)
+fn validate(file: &Path, schema: Option<&Path>) -> Result<()> {
+    let report = check_config_file::<ServerConfig>(file, schema)?;
+
+    for warning in &report.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &report.errors {
+        eprintln!("error: {}", error);
+    }
+
+    if !report.is_valid() {
+        anyhow::bail!(
+            "{} failed validation with {} error(s)",
+            file.display(),
+            report.errors.len()
+        );
+    }
+
+    println!("Config OK: {}", file.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_config_satisfies_schema() {
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.example.toml");
+        let report = check_config_file::<ServerConfig>(&example, None).unwrap();
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+}
@@ -1,9 +1,13 @@
 use anyhow::Result;
-use snow_owl_core::{Deployment, DeploymentStatus};
+use snow_owl_core::{Deployment, DeploymentStatus, Machine, ServerConfig};
 use snow_owl_db::Database;
+use snow_owl_http::CreateDeploymentRequest;
+use std::collections::HashMap;
 use std::path::Path;
 use uuid::Uuid;
 
+use crate::commands::group::find_group;
+use crate::commands::image::find_image;
 use crate::{DeployCommands, config};
 
 pub async fn handle(config_path: &Path, command: DeployCommands) -> Result<()> {
@@ -12,9 +16,23 @@ pub async fn handle(config_path: &Path, command: DeployCommands) -> Result<()> {
 
     match command {
         DeployCommands::List => list(&db).await?,
-        DeployCommands::Create { machine, image } => create(&db, machine, image).await?,
+        DeployCommands::Create {
+            machine,
+            image,
+            group,
+        } => match group {
+            Some(group) => create_for_group(&db, group, image).await?,
+            // clap requires a machine whenever --group is absent
+            None => create(&db, machine.unwrap_or_default(), image).await?,
+        },
         DeployCommands::Status { id } => status(&db, id).await?,
         DeployCommands::Cancel { id } => cancel(&db, id).await?,
+        DeployCommands::DryRun {
+            machine,
+            image,
+            driver_packs,
+            output,
+        } => dry_run(&db, &config, machine, image, driver_packs, &output).await?,
     }
 
     Ok(())
@@ -29,15 +47,20 @@ async fn list(db: &Database) -> Result<()> {
     }
 
     println!(
-        "\n{:<36} {:<36} {:<36} {:<12}",
-        "ID", "Machine", "Image", "Status"
+        "\n{:<36} {:<36} {:<36} {:<12} {:<7} Error",
+        "ID", "Machine", "Image", "Status", "Retries"
     );
-    println!("{}", "-".repeat(130));
+    println!("{}", "-".repeat(150));
 
     for deployment in deployments {
         println!(
-            "{:<36} {:<36} {:<36} {:?}",
-            deployment.id, deployment.machine_id, deployment.image_id, deployment.status
+            "{:<36} {:<36} {:<36} {:<12} {:<7} {}",
+            deployment.id,
+            deployment.machine_id,
+            deployment.image_id,
+            format!("{:?}", deployment.status),
+            deployment.retry_count,
+            deployment.error_message.as_deref().unwrap_or("")
         );
     }
 
@@ -61,15 +84,7 @@ async fn create(db: &Database, machine_id: String, image_id: String) -> Result<(
         .await?
         .ok_or_else(|| anyhow::anyhow!("Image not found"))?;
 
-    let deployment = Deployment {
-        id: Uuid::new_v4(),
-        machine_id: machine_uuid,
-        image_id: image_uuid,
-        status: DeploymentStatus::Pending,
-        started_at: chrono::Utc::now(),
-        completed_at: None,
-        error_message: None,
-    };
+    let deployment = Deployment::pending(machine_uuid, image_uuid, Vec::new());
 
     db.create_deployment(&deployment).await?;
 
@@ -86,6 +101,78 @@ async fn create(db: &Database, machine_id: String, image_id: String) -> Result<(
     Ok(())
 }
 
+/// Deploy `image` to every machine in `group` in one transaction
+///
+/// Machines that already have an active deployment are skipped and listed
+/// rather than failing the whole group.
+async fn create_for_group(db: &Database, group: String, image: String) -> Result<()> {
+    let group = find_group(db, &group).await?;
+    let image = find_image(db, &image).await?;
+    let machines = db.list_group_machines(group.id).await?;
+    if machines.is_empty() {
+        anyhow::bail!("Machine group '{}' has no machines", group.name);
+    }
+
+    let machine_ids: Vec<Uuid> = machines.iter().map(|machine| machine.id).collect();
+    let result = db
+        .create_deployments_bulk(&machine_ids, image.id, &[])
+        .await?;
+    let by_id: HashMap<Uuid, &Machine> = machines
+        .iter()
+        .map(|machine| (machine.id, machine))
+        .collect();
+    let describe = |id: Uuid| {
+        by_id.get(&id).map_or_else(
+            || (id.to_string(), "-".to_string()),
+            |machine| {
+                (
+                    machine.mac_address.to_string(),
+                    machine.hostname.clone().unwrap_or_else(|| "-".to_string()),
+                )
+            },
+        )
+    };
+
+    println!(
+        "\nDeploying '{}' to group '{}': {} created, {} skipped",
+        image.name,
+        group.name,
+        result.created.len(),
+        result.skipped.len()
+    );
+
+    if !result.created.is_empty() {
+        println!(
+            "\n{:<36} {:<17} {:<20}",
+            "Deployment ID", "MAC Address", "Hostname"
+        );
+        println!("{}", "-".repeat(75));
+        for deployment in &result.created {
+            let (mac, hostname) = describe(deployment.machine_id);
+            println!("{:<36} {:<17} {:<20}", deployment.id, mac, hostname);
+        }
+    }
+
+    if !result.skipped.is_empty() {
+        println!("\nSkipped (deployment already active):");
+        println!(
+            "{:<36} {:<17} {:<20}",
+            "Active Deployment", "MAC Address", "Hostname"
+        );
+        println!("{}", "-".repeat(75));
+        for skipped in &result.skipped {
+            let (mac, hostname) = describe(skipped.machine_id);
+            println!(
+                "{:<36} {:<17} {:<20}",
+                skipped.active_deployment_id, mac, hostname
+            );
+        }
+    }
+
+    println!("\nThe machines will receive the deployment on next boot.");
+    Ok(())
+}
+
 async fn status(db: &Database, id: String) -> Result<()> {
     let deployment_id = Uuid::parse_str(&id)?;
     let deployment = db
@@ -99,6 +186,9 @@ async fn status(db: &Database, id: String) -> Result<()> {
     println!("\nDeployment Status:");
     println!("  ID: {}", deployment.id);
     println!("  Status: {:?}", deployment.status);
+    if deployment.retry_count > 0 {
+        println!("  Retry: {}", deployment.retry_count);
+    }
     println!(
         "  Started: {}",
         deployment.started_at.format("%Y-%m-%d %H:%M:%S")
@@ -146,3 +236,85 @@ async fn cancel(db: &Database, id: String) -> Result<()> {
     println!("Deployment {} cancelled.", deployment_id);
     Ok(())
 }
+
+/// Write the artifacts a deployment would serve to `output`
+///
+/// Files: `boot.ipxe`, `drivers.json`, `deployment.json` and `trace.txt`,
+/// so the output of two template versions can be compared with `diff -r`.
+async fn dry_run(
+    db: &Database,
+    config: &ServerConfig,
+    machine_id: String,
+    image_id: String,
+    driver_pack_ids: Vec<String>,
+    output: &Path,
+) -> Result<()> {
+    let req = CreateDeploymentRequest {
+        machine_id: Uuid::parse_str(&machine_id)?,
+        image_id: Uuid::parse_str(&image_id)?,
+        driver_pack_ids: driver_pack_ids
+            .iter()
+            .map(|id| Uuid::parse_str(id))
+            .collect::<std::result::Result<_, _>>()?,
+    };
+
+    let bundle = snow_owl_http::dry_run(db, config, &req).await?;
+
+    tokio::fs::create_dir_all(output).await?;
+    tokio::fs::write(output.join("boot.ipxe"), &bundle.boot_script).await?;
+    tokio::fs::write(
+        output.join("drivers.json"),
+        serde_json::to_string_pretty(&bundle.driver_manifest)?,
+    )
+    .await?;
+    tokio::fs::write(
+        output.join("deployment.json"),
+        serde_json::to_string_pretty(&bundle.deployment)?,
+    )
+    .await?;
+    let mut trace = bundle.trace.join("\n");
+    trace.push('\n');
+    tokio::fs::write(output.join("trace.txt"), trace).await?;
+
+    println!("Dry-run artifacts written to {}", output.display());
+    for line in &bundle.trace {
+        println!("  {}", line);
+    }
+    println!("\nNo deployment was created.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cli, Commands, DeployCommands};
+    use clap::Parser;
+
+    fn parse_create(args: &[&str]) -> Option<(Option<String>, String, Option<String>)> {
+        let cli = Cli::try_parse_from(["snow-owl", "deploy", "create"].iter().chain(args)).ok()?;
+        match cli.command {
+            Commands::Deploy(DeployCommands::Create {
+                machine,
+                image,
+                group,
+            }) => Some((machine, image, group)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_create_takes_machine_or_group() {
+        assert_eq!(
+            parse_create(&["52:54:00:12:34:56", "win11"]),
+            Some((Some("52:54:00:12:34:56".into()), "win11".into(), None))
+        );
+        assert_eq!(
+            parse_create(&["--group", "lab", "win11"]),
+            Some((None, "win11".into(), Some("lab".into())))
+        );
+
+        // A machine and a group together, or neither, is refused
+        assert_eq!(parse_create(&["--group", "lab", "m1", "win11"]), None);
+        assert_eq!(parse_create(&["win11"]), None);
+    }
+}
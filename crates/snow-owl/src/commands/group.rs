@@ -0,0 +1,154 @@
+use anyhow::Result;
+use snow_owl_core::MachineGroup;
+use snow_owl_db::Database;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::commands::machine::find_machine;
+use crate::{GroupCommands, config};
+
+pub async fn handle(config_path: &Path, command: GroupCommands) -> Result<()> {
+    let config = config::load_config(config_path).await?;
+    let db = Database::new(&config.database_url).await?;
+
+    match command {
+        GroupCommands::List => list(&db).await?,
+        GroupCommands::Create { name, description } => create(&db, name, description).await?,
+        GroupCommands::Delete { name_or_id } => delete(&db, name_or_id).await?,
+        GroupCommands::Machines { name_or_id } => machines(&db, name_or_id).await?,
+        GroupCommands::Add {
+            name_or_id,
+            machines,
+        } => add(&db, name_or_id, machines).await?,
+        GroupCommands::Remove {
+            name_or_id,
+            machines,
+        } => remove(&db, name_or_id, machines).await?,
+    }
+
+    Ok(())
+}
+
+async fn list(db: &Database) -> Result<()> {
+    let groups = db.list_machine_groups().await?;
+
+    if groups.is_empty() {
+        println!("No machine groups defined.");
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<36} {:<24} {:<8} Description",
+        "ID", "Name", "Machines"
+    );
+    println!("{}", "-".repeat(90));
+
+    for group in groups {
+        let members = db.list_group_machines(group.id).await?.len();
+        println!(
+            "{:<36} {:<24} {:<8} {}",
+            group.id,
+            group.name,
+            members,
+            group.description.as_deref().unwrap_or("-")
+        );
+    }
+
+    println!();
+    Ok(())
+}
+
+async fn create(db: &Database, name: String, description: Option<String>) -> Result<()> {
+    MachineGroup::validate_name(&name).map_err(|e| anyhow::anyhow!(e))?;
+    if db.get_machine_group_by_name(&name).await?.is_some() {
+        anyhow::bail!("Machine group already exists: {}", name);
+    }
+
+    let group = MachineGroup::new(name, description);
+    db.create_machine_group(&group).await?;
+
+    println!("Machine group created successfully!");
+    println!("  ID: {}", group.id);
+    println!("  Name: {}", group.name);
+
+    Ok(())
+}
+
+async fn delete(db: &Database, name_or_id: String) -> Result<()> {
+    let group = find_group(db, &name_or_id).await?;
+    db.delete_machine_group(group.id).await?;
+
+    println!("Machine group '{}' deleted.", group.name);
+    Ok(())
+}
+
+async fn machines(db: &Database, name_or_id: String) -> Result<()> {
+    let group = find_group(db, &name_or_id).await?;
+    let machines = db.list_group_machines(group.id).await?;
+
+    if machines.is_empty() {
+        println!("Machine group '{}' has no machines.", group.name);
+        return Ok(());
+    }
+
+    println!("\nMachines in '{}':", group.name);
+    println!("{:<36} {:<17} {:<20}", "ID", "MAC Address", "Hostname");
+    println!("{}", "-".repeat(75));
+
+    for machine in machines {
+        println!(
+            "{:<36} {:<17} {:<20}",
+            machine.id,
+            machine.mac_address,
+            machine.hostname.as_deref().unwrap_or("-")
+        );
+    }
+
+    println!();
+    Ok(())
+}
+
+async fn add(db: &Database, name_or_id: String, machines: Vec<String>) -> Result<()> {
+    let group = find_group(db, &name_or_id).await?;
+
+    // Resolve every machine first so a typo adds nothing
+    let mut resolved = Vec::with_capacity(machines.len());
+    for mac_or_id in &machines {
+        resolved.push(find_machine(db, mac_or_id).await?);
+    }
+
+    for machine in resolved {
+        if db.add_machine_to_group(group.id, machine.id).await? {
+            println!("Added {} to '{}'.", machine.mac_address, group.name);
+        } else {
+            println!("{} is already in '{}'.", machine.mac_address, group.name);
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove(db: &Database, name_or_id: String, machines: Vec<String>) -> Result<()> {
+    let group = find_group(db, &name_or_id).await?;
+
+    for mac_or_id in &machines {
+        let machine = find_machine(db, mac_or_id).await?;
+        if db.remove_machine_from_group(group.id, machine.id).await? {
+            println!("Removed {} from '{}'.", machine.mac_address, group.name);
+        } else {
+            println!("{} is not in '{}'.", machine.mac_address, group.name);
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn find_group(db: &Database, name_or_id: &str) -> Result<MachineGroup> {
+    let group = if let Ok(id) = Uuid::parse_str(name_or_id) {
+        db.get_machine_group_by_id(id).await?
+    } else {
+        db.get_machine_group_by_name(name_or_id).await?
+    };
+
+    group.ok_or_else(|| anyhow::anyhow!("Machine group not found: {}", name_or_id))
+}
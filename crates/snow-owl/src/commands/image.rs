@@ -1,7 +1,11 @@
 use anyhow::Result;
-use snow_owl_core::{ImageType, WindowsImage};
+use sha2::{Digest, Sha256};
+use snow_owl_core::image_metadata::read_image_metadata_file;
+use snow_owl_core::{ImageDeletion, ImageMetadata, ImageType, WindowsImage};
 use snow_owl_db::Database;
+use std::io::{IsTerminal, Write};
 use std::path::Path;
+use tokio::io::AsyncReadExt;
 use uuid::Uuid;
 
 use crate::{ImageCommands, config};
@@ -11,21 +15,38 @@ pub async fn handle(config_path: &Path, command: ImageCommands) -> Result<()> {
     let db = Database::new(&config.database_url).await?;
 
     match command {
-        ImageCommands::List => list(&db).await?,
+        ImageCommands::List { deleted } => list(&db, deleted).await?,
         ImageCommands::Add {
             name,
             path,
             description,
-        } => add(&db, name, path, description).await?,
+            parent,
+        } => {
+            add(
+                &db,
+                name,
+                path,
+                description,
+                parent,
+                config.strict_image_validation,
+            )
+            .await?
+        }
         ImageCommands::Remove { name_or_id } => remove(&db, name_or_id).await?,
+        ImageCommands::Restore { id } => restore(&db, id).await?,
         ImageCommands::Info { name_or_id } => info(&db, name_or_id).await?,
+        ImageCommands::Verify { name_or_id, all } => verify(&db, name_or_id, all).await?,
     }
 
     Ok(())
 }
 
-async fn list(db: &Database) -> Result<()> {
-    let images = db.list_images().await?;
+async fn list(db: &Database, deleted: bool) -> Result<()> {
+    let images = if deleted {
+        db.list_images_including_deleted().await?
+    } else {
+        db.list_images().await?
+    };
 
     if images.is_empty() {
         println!("No images registered.");
@@ -38,8 +59,16 @@ async fn list(db: &Database) -> Result<()> {
     for image in images {
         let size_mb = image.size_bytes as f64 / 1_048_576.0;
         println!(
-            "{:<36} {:<30} {:<8} {:.2} MB",
-            image.id, image.name, image.image_type, size_mb
+            "{:<36} {:<30} {:<8} {:.2} MB{}",
+            image.id,
+            image.name,
+            image.image_type,
+            size_mb,
+            if image.deleted_at.is_some() {
+                " (deleted)"
+            } else {
+                ""
+            }
         );
     }
 
@@ -52,31 +81,32 @@ async fn add(
     name: String,
     path: std::path::PathBuf,
     description: Option<String>,
+    parent: Option<String>,
+    strict: bool,
 ) -> Result<()> {
-    // Determine image type from extension
-    let image_type = match path.extension().and_then(|e| e.to_str()) {
-        Some("wim") => ImageType::Wim,
-        Some("vhd") => ImageType::Vhd,
-        Some("vhdx") => ImageType::Vhdx,
-        _ => anyhow::bail!("Unsupported file extension. Use .wim, .vhd, or .vhdx"),
+    let parent = match parent {
+        Some(parent) => Some(find_image(db, &parent).await?),
+        None => None,
     };
+    let file = inspect_image_file(&path, true).await?;
 
-    // Check if file exists
-    if !path.exists() {
-        anyhow::bail!("File not found: {}", path.display());
-    }
-
-    let metadata = tokio::fs::metadata(&path).await?;
+    let header_path = path.clone();
+    let metadata =
+        tokio::task::spawn_blocking(move || read_image_metadata_file(&header_path)).await??;
+    metadata.check_declared_type(file.image_type, strict)?;
 
     let image = WindowsImage {
         id: Uuid::new_v4(),
         name: name.clone(),
         description,
-        image_type,
+        image_type: file.image_type,
         file_path: path.canonicalize()?,
-        size_bytes: metadata.len(),
+        size_bytes: file.size_bytes,
         created_at: chrono::Utc::now(),
-        checksum: None,
+        checksum: Some(file.checksum),
+        metadata: Some(metadata),
+        parent_image_id: parent.as_ref().map(|parent| parent.id),
+        deleted_at: None,
     };
 
     db.create_image(&image).await?;
@@ -85,6 +115,15 @@ async fn add(
     println!("ID: {}", image.id);
     println!("Type: {}", image.image_type);
     println!("Size: {:.2} MB", image.size_bytes as f64 / 1_048_576.0);
+    if let Some(parent) = &parent {
+        println!("Parent: {} ({})", parent.name, parent.id);
+    }
+    if let Some(checksum) = &image.checksum {
+        println!("SHA-256: {}", checksum);
+    }
+    if let Some(metadata) = &image.metadata {
+        print_metadata(metadata, "");
+    }
 
     Ok(())
 }
@@ -92,8 +131,23 @@ async fn add(
 async fn remove(db: &Database, name_or_id: String) -> Result<()> {
     let image = find_image(db, &name_or_id).await?;
 
-    db.delete_image(image.id).await?;
-    println!("Image '{}' removed successfully.", image.name);
+    match db.delete_image(image.id).await? {
+        ImageDeletion::Removed => println!("Image '{}' removed successfully.", image.name),
+        ImageDeletion::Archived => println!(
+            "Image '{}' deleted; its record is kept for deployment history (restore with \
+             `snow-owl image restore {}`).",
+            image.name, image.id
+        ),
+    }
+
+    Ok(())
+}
+
+async fn restore(db: &Database, id: String) -> Result<()> {
+    let image_id = Uuid::parse_str(&id)?;
+    db.restore_image(image_id).await?;
+    let image = find_image(db, &id).await?;
+    println!("Image '{}' restored.", image.name);
 
     Ok(())
 }
@@ -111,20 +165,67 @@ async fn info(db: &Database, name_or_id: String) -> Result<()> {
         "  Created: {}",
         image.created_at.format("%Y-%m-%d %H:%M:%S")
     );
+    if let Some(deleted_at) = image.deleted_at {
+        println!("  Deleted: {}", deleted_at.format("%Y-%m-%d %H:%M:%S"));
+    }
 
     if let Some(desc) = &image.description {
         println!("  Description: {}", desc);
     }
 
+    if let Some(parent_id) = image.parent_image_id {
+        match db.get_image_by_id(parent_id).await? {
+            Some(parent) => println!("  Parent: {} ({})", parent.name, parent.id),
+            None => println!("  Parent: {} (missing)", parent_id),
+        }
+    }
+
     if let Some(checksum) = &image.checksum {
         println!("  Checksum: {}", checksum);
     }
 
+    if let Some(metadata) = &image.metadata {
+        print_metadata(metadata, "  ");
+    }
+
     println!();
     Ok(())
 }
 
-async fn find_image(db: &Database, name_or_id: &str) -> Result<WindowsImage> {
+/// Print the header-derived details of an image
+fn print_metadata(metadata: &ImageMetadata, indent: &str) {
+    if let Some(virtual_size) = metadata.virtual_size_bytes {
+        println!(
+            "{}Virtual size: {:.2} GB",
+            indent,
+            virtual_size as f64 / 1_073_741_824.0
+        );
+    }
+    if let Some(block_size) = metadata.block_size_bytes {
+        println!("{}Block size: {} KB", indent, block_size / 1024);
+    }
+    if let Some(count) = metadata.image_count {
+        println!("{}Images: {}", indent, count);
+    }
+    for image in &metadata.images {
+        let mut details = Vec::new();
+        details.extend(image.edition.clone());
+        details.extend(image.architecture.clone());
+        details.extend(image.version.clone());
+        println!(
+            "{}  [{}] {} ({})",
+            indent,
+            image.index,
+            image.name.as_deref().unwrap_or("(unnamed)"),
+            details.join(", ")
+        );
+    }
+    if let Some(error) = &metadata.parse_error {
+        println!("{}Metadata: incomplete ({})", indent, error);
+    }
+}
+
+pub(crate) async fn find_image(db: &Database, name_or_id: &str) -> Result<WindowsImage> {
     // Try as UUID first
     if let Ok(id) = Uuid::parse_str(name_or_id)
         && let Some(image) = db.get_image_by_id(id).await?
@@ -139,3 +240,282 @@ async fn find_image(db: &Database, name_or_id: &str) -> Result<WindowsImage> {
 
     anyhow::bail!("Image not found: {}", name_or_id)
 }
+
+async fn verify(db: &Database, name_or_id: Option<String>, all: bool) -> Result<()> {
+    let images = match name_or_id {
+        Some(name_or_id) if !all => vec![find_image(db, &name_or_id).await?],
+        _ => db.list_images().await?,
+    };
+
+    if images.is_empty() {
+        println!("No images registered.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for image in &images {
+        let status = match verify_image(image, true).await {
+            Ok(status) => status,
+            Err(e) => {
+                failed += 1;
+                println!("{:<30} ERROR ({})", image.name, e);
+                continue;
+            }
+        };
+
+        match &status {
+            VerifyStatus::Ok => println!("{:<30} OK", image.name),
+            VerifyStatus::Mismatch { actual } => {
+                failed += 1;
+                println!(
+                    "{:<30} MISMATCH (expected {}, got {})",
+                    image.name,
+                    image.checksum.as_deref().unwrap_or_default(),
+                    actual
+                );
+            }
+            VerifyStatus::Missing => {
+                failed += 1;
+                println!("{:<30} MISSING ({})", image.name, image.file_path.display());
+            }
+            VerifyStatus::NoChecksum => {
+                println!("{:<30} SKIPPED (no checksum recorded)", image.name);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} image(s) failed verification",
+            failed,
+            images.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Read size used when streaming image files through SHA-256
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Type, size and digest of an image file on disk
+#[derive(Debug)]
+struct ImageFile {
+    image_type: ImageType,
+    size_bytes: u64,
+    checksum: String,
+}
+
+/// Outcome of re-hashing a registered image
+#[derive(Debug, PartialEq, Eq)]
+enum VerifyStatus {
+    Ok,
+    Mismatch { actual: String },
+    Missing,
+    NoChecksum,
+}
+
+/// Determine the type of an image file and compute its size and SHA-256
+async fn inspect_image_file(path: &Path, show_progress: bool) -> Result<ImageFile> {
+    // Determine image type from extension
+    let image_type = match path.extension().and_then(|e| e.to_str()) {
+        Some("wim") => ImageType::Wim,
+        Some("vhd") => ImageType::Vhd,
+        Some("vhdx") => ImageType::Vhdx,
+        _ => anyhow::bail!("Unsupported file extension. Use .wim, .vhd, or .vhdx"),
+    };
+
+    // Check if file exists
+    if !path.exists() {
+        anyhow::bail!("File not found: {}", path.display());
+    }
+
+    let metadata = tokio::fs::metadata(path).await?;
+    let checksum = sha256_file(path, metadata.len(), show_progress).await?;
+
+    Ok(ImageFile {
+        image_type,
+        size_bytes: metadata.len(),
+        checksum,
+    })
+}
+
+/// Re-hash an image file and compare it with the recorded checksum
+async fn verify_image(image: &WindowsImage, show_progress: bool) -> Result<VerifyStatus> {
+    let metadata = match tokio::fs::metadata(&image.file_path).await {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(VerifyStatus::Missing),
+        Err(e) => return Err(e.into()),
+    };
+
+    let Some(expected) = &image.checksum else {
+        return Ok(VerifyStatus::NoChecksum);
+    };
+
+    let actual = sha256_file(&image.file_path, metadata.len(), show_progress).await?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(VerifyStatus::Ok)
+    } else {
+        Ok(VerifyStatus::Mismatch { actual })
+    }
+}
+
+/// Stream a file through SHA-256 and return the lowercase hex digest
+///
+/// The file is read in chunks through tokio so hashing a multi-gigabyte
+/// WIM/VHDX never blocks the runtime or loads the image into memory.
+async fn sha256_file(path: &Path, total: u64, show_progress: bool) -> Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; HASH_CHUNK_SIZE];
+
+    let label = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut progress = Progress::new(label, total, show_progress);
+
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        progress.advance(n as u64);
+    }
+    progress.finish();
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Single-line progress bar drawn on stderr while hashing
+///
+/// Only drawn when stderr is a terminal, so piped output stays clean.
+struct Progress {
+    label: String,
+    total: u64,
+    done: u64,
+    last_percent: Option<u64>,
+    enabled: bool,
+}
+
+impl Progress {
+    const WIDTH: u64 = 30;
+
+    fn new(label: String, total: u64, show: bool) -> Self {
+        Self {
+            label,
+            total,
+            done: 0,
+            last_percent: None,
+            enabled: show && std::io::stderr().is_terminal(),
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        if !self.enabled {
+            return;
+        }
+
+        let done = self.done.min(self.total);
+        let percent = (done * 100).checked_div(self.total).unwrap_or(100);
+        if self.last_percent == Some(percent) {
+            return;
+        }
+        self.last_percent = Some(percent);
+
+        let filled = (percent * Self::WIDTH / 100) as usize;
+        eprint!(
+            "\rHashing {} [{}{}] {:>3}% ({:.0}/{:.0} MB)",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(Self::WIDTH as usize - filled),
+            percent,
+            done as f64 / 1_048_576.0,
+            self.total as f64 / 1_048_576.0
+        );
+        let _ = std::io::stderr().flush();
+    }
+
+    fn finish(&self) {
+        if self.enabled && self.last_percent.is_some() {
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// SHA-256 of the three bytes "abc" (FIPS 180-2 test vector)
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn fixture(name: &str, contents: &[u8]) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("snow_owl_image_test_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    async fn registered(path: &Path) -> WindowsImage {
+        let file = inspect_image_file(path, false).await.unwrap();
+        WindowsImage {
+            id: Uuid::new_v4(),
+            name: "fixture".to_string(),
+            description: None,
+            image_type: file.image_type,
+            file_path: path.to_path_buf(),
+            size_bytes: file.size_bytes,
+            created_at: chrono::Utc::now(),
+            checksum: Some(file.checksum),
+            metadata: None,
+            parent_image_id: None,
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_records_size_and_checksum() {
+        let path = fixture("install.wim", b"abc");
+
+        let file = inspect_image_file(&path, false).await.unwrap();
+
+        assert_eq!(file.image_type, ImageType::Wim);
+        assert_eq!(file.size_bytes, 3);
+        assert_eq!(file.checksum, ABC_SHA256);
+    }
+
+    #[tokio::test]
+    async fn test_verify_ok() {
+        let contents: Vec<u8> = (0..3 * HASH_CHUNK_SIZE as u32 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let path = fixture("disk.vhdx", &contents);
+        let image = registered(&path).await;
+
+        assert_eq!(verify_image(&image, false).await.unwrap(), VerifyStatus::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_verify_mismatch_and_missing() {
+        let path = fixture("install.wim", b"abc");
+        let image = registered(&path).await;
+
+        std::fs::write(&path, b"abd").unwrap();
+        assert!(matches!(
+            verify_image(&image, false).await.unwrap(),
+            VerifyStatus::Mismatch { .. }
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            verify_image(&image, false).await.unwrap(),
+            VerifyStatus::Missing
+        );
+    }
+}
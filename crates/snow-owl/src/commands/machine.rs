@@ -1,5 +1,5 @@
 use anyhow::Result;
-use snow_owl_core::MacAddress;
+use snow_owl_core::{MacAddress, Machine};
 use snow_owl_db::Database;
 use std::path::Path;
 use uuid::Uuid;
@@ -13,6 +13,12 @@ pub async fn handle(config_path: &Path, command: MachineCommands) -> Result<()>
     match command {
         MachineCommands::List => list(&db).await?,
         MachineCommands::Info { mac_or_id } => info(&db, mac_or_id).await?,
+        MachineCommands::SetKernelArgs { mac_or_id, args } => {
+            set_kernel_args(&db, mac_or_id, args).await?
+        }
+        MachineCommands::Group(command) => {
+            crate::commands::group::handle(config_path, command).await?
+        }
     }
 
     Ok(())
@@ -51,15 +57,7 @@ async fn list(db: &Database) -> Result<()> {
 }
 
 async fn info(db: &Database, mac_or_id: String) -> Result<()> {
-    let machine = if let Ok(id) = Uuid::parse_str(&mac_or_id) {
-        db.get_machine_by_id(id).await?
-    } else if let Ok(mac) = mac_or_id.parse::<MacAddress>() {
-        db.get_machine_by_mac(&mac).await?
-    } else {
-        anyhow::bail!("Invalid MAC address or UUID");
-    };
-
-    let machine = machine.ok_or_else(|| anyhow::anyhow!("Machine not found"))?;
+    let machine = find_machine(db, &mac_or_id).await?;
 
     println!("\nMachine Information:");
     println!("  ID: {}", machine.id);
@@ -73,6 +71,10 @@ async fn info(db: &Database, mac_or_id: String) -> Result<()> {
         println!("  IP Address: {}", ip);
     }
 
+    if let Some(kernel_args) = &machine.kernel_args {
+        println!("  Kernel Args: {}", kernel_args);
+    }
+
     println!(
         "  Created: {}",
         machine.created_at.format("%Y-%m-%d %H:%M:%S")
@@ -82,6 +84,12 @@ async fn info(db: &Database, mac_or_id: String) -> Result<()> {
         machine.last_seen.format("%Y-%m-%d %H:%M:%S")
     );
 
+    let groups = db.list_machine_group_memberships(machine.id).await?;
+    if !groups.is_empty() {
+        let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+        println!("  Groups: {}", names.join(", "));
+    }
+
     // Show active deployments
     if let Some(deployment) = db.get_active_deployment_for_machine(machine.id).await? {
         println!("\nActive Deployment:");
@@ -96,3 +104,29 @@ async fn info(db: &Database, mac_or_id: String) -> Result<()> {
     println!();
     Ok(())
 }
+
+async fn set_kernel_args(db: &Database, mac_or_id: String, args: Option<String>) -> Result<()> {
+    let mut machine = find_machine(db, &mac_or_id).await?;
+
+    machine.kernel_args = args.filter(|args| !args.trim().is_empty());
+    db.create_or_update_machine(&machine).await?;
+
+    match &machine.kernel_args {
+        Some(args) => println!("Kernel args for {} set to: {}", machine.mac_address, args),
+        None => println!("Kernel args for {} cleared.", machine.mac_address),
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn find_machine(db: &Database, mac_or_id: &str) -> Result<Machine> {
+    let machine = if let Ok(id) = Uuid::parse_str(mac_or_id) {
+        db.get_machine_by_id(id).await?
+    } else if let Ok(mac) = mac_or_id.parse::<MacAddress>() {
+        db.get_machine_by_mac(&mac).await?
+    } else {
+        anyhow::bail!("Invalid MAC address or UUID");
+    };
+
+    machine.ok_or_else(|| anyhow::anyhow!("Machine not found"))
+}
@@ -1,5 +1,8 @@
+pub mod audit;
 pub mod auth;
+pub mod config;
 pub mod deploy;
+pub mod group;
 pub mod image;
 pub mod machine;
 pub mod server;
@@ -1,14 +1,21 @@
 use anyhow::{Context, Result};
-use snow_owl_core::ServerConfig;
+use snow_owl_core::{AuditQueue, DEFAULT_AUDIT_QUEUE_CAPACITY, ServerConfig, ShutdownCoordinator};
 use snow_owl_db::Database;
 use snow_owl_http::HttpServer;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::info;
 
 use crate::config;
 
+/// How long in-flight HTTP requests may run after a shutdown signal
+///
+/// NIST SC-24: Fail in Known State
+const HTTP_DRAIN_TIMEOUT: Duration = Duration::from_secs(8);
+
 pub async fn init_config(config_path: &Path) -> Result<()> {
     let default_config = ServerConfig::default();
     config::save_config(config_path, &default_config).await?;
@@ -40,6 +47,7 @@ pub async fn run(config_path: &Path) -> Result<()> {
     tokio::fs::create_dir_all(&config.tftp_root).await?;
     tokio::fs::create_dir_all(&config.images_dir).await?;
     tokio::fs::create_dir_all(&config.winpe_dir).await?;
+    tokio::fs::create_dir_all(&config.drivers_dir).await?;
 
     // NIST IA-5: Initialize database with authenticated connection
     // NIST SC-28: Protection of Information at Rest (database)
@@ -62,22 +70,55 @@ pub async fn run(config_path: &Path) -> Result<()> {
         info!("TFTP server disabled");
     }
 
+    // NIST AU-9: Security events from the API go to the shared audit_log table
+    let (audit, audit_writer) = AuditQueue::spawn(db.clone(), DEFAULT_AUDIT_QUEUE_CAPACITY);
+
     // Start HTTP server
-    let http_server = HttpServer::new(db, config);
-    let http_handle = tokio::spawn(async move {
-        if let Err(e) = http_server.run().await {
+    let http_server = HttpServer::new(db.clone(), config).with_audit_queue(audit);
+    // Only the replica holding the database lock runs the background tasks
+    let background_tasks = http_server.spawn_background_tasks();
+    let http_stop = CancellationToken::new();
+    let server_stop = http_stop.clone();
+    let mut http_handle = tokio::spawn(async move {
+        if let Err(e) = http_server.run_with_shutdown(server_stop).await {
             tracing::error!("HTTP server error: {}", e);
         }
     });
 
-    info!("Snow-Owl server is running. Press Ctrl+C to stop.");
+    // NIST SC-24: Fail in Known State - stop accepting requests before
+    // closing the database pool the handlers depend on
+    let mut shutdown = ShutdownCoordinator::new("snow-owl").with_deadline(Duration::from_secs(30));
+    // In-flight requests get until shortly before the phase timeout to
+    // finish; whatever is still open then (event streams) is cut off
+    shutdown.register("http", Duration::from_secs(10), move || async move {
+        http_stop.cancel();
+        if tokio::time::timeout(HTTP_DRAIN_TIMEOUT, &mut http_handle)
+            .await
+            .is_err()
+        {
+            http_handle.abort();
+            let _ = http_handle.await;
+        }
+    });
+    shutdown.register(
+        "background-tasks",
+        Duration::from_secs(5),
+        move || async move {
+            background_tasks.stop().await;
+        },
+    );
+    // The writer exits once the HTTP handlers holding the queue are gone
+    shutdown.register("audit", Duration::from_secs(5), move || async move {
+        let _ = audit_writer.await;
+    });
+    shutdown.register("database", Duration::from_secs(10), move || async move {
+        db.close().await;
+    });
 
-    // Wait for Ctrl+C
-    tokio::signal::ctrl_c().await?;
-    info!("Shutting down...");
+    info!("Snow-Owl server is running. Press Ctrl+C to stop.");
 
-    // Note: In a production system, we would gracefully shut down the servers here
-    http_handle.abort();
+    // Wait for Ctrl+C, then run the shutdown phases in order
+    shutdown.run_on_ctrl_c().await;
 
     Ok(())
 }
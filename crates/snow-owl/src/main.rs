@@ -28,6 +28,10 @@ enum Commands {
         init_config: bool,
     },
 
+    /// Export or check the configuration file schema
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
     /// Manage Windows images
     #[command(subcommand)]
     Image(ImageCommands),
@@ -40,6 +44,10 @@ enum Commands {
     #[command(subcommand)]
     Machine(MachineCommands),
 
+    /// Manage machine groups
+    #[command(subcommand)]
+    Group(GroupCommands),
+
     /// Manage users
     #[command(subcommand)]
     User(UserCommands),
@@ -48,6 +56,10 @@ enum Commands {
     #[command(subcommand, name = "api-key")]
     ApiKey(ApiKeyCommands),
 
+    /// Maintain the audit trail
+    #[command(subcommand)]
+    Audit(AuditCommands),
+
     /// Initialize WinPE environment
     InitWinpe {
         /// Path to WinPE ISO or extracted directory
@@ -59,10 +71,31 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the JSON Schema for the configuration file
+    Schema,
+
+    /// Check a configuration file against the schema without starting anything
+    Validate {
+        /// Configuration file to check (defaults to --config)
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Check against this JSON Schema file instead of the built-in one
+        #[arg(long, num_args = 0..=1, value_name = "SCHEMA_FILE")]
+        schema: Option<Option<PathBuf>>,
+    },
+}
+
 #[derive(Subcommand)]
 enum ImageCommands {
     /// List all registered images
-    List,
+    List {
+        /// Include deleted images kept for deployment history
+        #[arg(long)]
+        deleted: bool,
+    },
 
     /// Add a new Windows image
     Add {
@@ -75,6 +108,10 @@ enum ImageCommands {
         /// Image description
         #[arg(short, long)]
         description: Option<String>,
+
+        /// Base image (name or ID) this image is a differential image on
+        #[arg(long)]
+        parent: Option<String>,
     },
 
     /// Remove an image
@@ -83,11 +120,28 @@ enum ImageCommands {
         name_or_id: String,
     },
 
+    /// Restore a deleted image that deployment history kept
+    Restore {
+        /// Image ID
+        id: String,
+    },
+
     /// Show image details
     Info {
         /// Image name or ID
         name_or_id: String,
     },
+
+    /// Re-hash image files and compare against the recorded checksum
+    Verify {
+        /// Image name or ID
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name_or_id: Option<String>,
+
+        /// Verify every registered image
+        #[arg(long)]
+        all: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -95,13 +149,19 @@ enum DeployCommands {
     /// List all deployments
     List,
 
-    /// Create a new deployment
+    /// Create a new deployment, or one per machine in a group
+    #[command(allow_missing_positional = true)]
     Create {
         /// Machine MAC address or ID
-        machine: String,
+        #[arg(required_unless_present = "group", conflicts_with = "group")]
+        machine: Option<String>,
 
         /// Image name or ID
         image: String,
+
+        /// Deploy to every machine in this group (name or ID) instead
+        #[arg(long)]
+        group: Option<String>,
     },
 
     /// Show deployment status
@@ -115,6 +175,23 @@ enum DeployCommands {
         /// Deployment ID
         id: String,
     },
+
+    /// Render what a deployment would serve, without creating it
+    DryRun {
+        /// Machine ID
+        machine: String,
+
+        /// Image ID
+        image: String,
+
+        /// Driver pack ID to inject (repeat for several, in order)
+        #[arg(long = "driver-pack")]
+        driver_packs: Vec<String>,
+
+        /// Directory to write the rendered artifacts to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -127,6 +204,77 @@ enum MachineCommands {
         /// Machine MAC address or ID
         mac_or_id: String,
     },
+
+    /// Set the kernel arguments exposed to iPXE templates
+    SetKernelArgs {
+        /// Machine MAC address or ID
+        mac_or_id: String,
+
+        /// Kernel command line (omit to clear)
+        args: Option<String>,
+    },
+
+    /// Manage machine groups (same as `snow-owl group`)
+    #[command(subcommand)]
+    Group(GroupCommands),
+}
+
+#[derive(Subcommand)]
+enum GroupCommands {
+    /// List all machine groups
+    List,
+
+    /// Create a machine group
+    Create {
+        /// Group name
+        name: String,
+
+        /// Group description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+
+    /// Delete a machine group (its machines are kept)
+    Delete {
+        /// Group name or ID
+        name_or_id: String,
+    },
+
+    /// List the machines in a group
+    Machines {
+        /// Group name or ID
+        name_or_id: String,
+    },
+
+    /// Add machines to a group
+    Add {
+        /// Group name or ID
+        name_or_id: String,
+
+        /// Machine MAC addresses or IDs
+        #[arg(required = true)]
+        machines: Vec<String>,
+    },
+
+    /// Remove machines from a group
+    Remove {
+        /// Group name or ID
+        name_or_id: String,
+
+        /// Machine MAC addresses or IDs
+        #[arg(required = true)]
+        machines: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// Delete audit records older than the retention period
+    Prune {
+        /// Retention period in days; older records are deleted
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        days: u32,
+    },
 }
 
 #[tokio::main]
@@ -150,6 +298,9 @@ async fn main() -> anyhow::Result<()> {
                 commands::server::run(&cli.config).await?;
             }
         }
+        Commands::Config(cmd) => {
+            commands::config::handle(&cli.config, cmd)?;
+        }
         Commands::Image(cmd) => {
             commands::image::handle(&cli.config, cmd).await?;
         }
@@ -159,12 +310,18 @@ async fn main() -> anyhow::Result<()> {
         Commands::Machine(cmd) => {
             commands::machine::handle(&cli.config, cmd).await?;
         }
+        Commands::Group(cmd) => {
+            commands::group::handle(&cli.config, cmd).await?;
+        }
         Commands::User(cmd) => {
             commands::auth::handle_user(&cli.config, cmd).await?;
         }
         Commands::ApiKey(cmd) => {
             commands::auth::handle_api_key(&cli.config, cmd).await?;
         }
+        Commands::Audit(cmd) => {
+            commands::audit::handle(&cli.config, cmd).await?;
+        }
         Commands::InitWinpe { source, dest } => {
             commands::winpe::init(&cli.config, source, dest).await?;
         }